@@ -1,14 +1,19 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 
 #[derive(Parser)]
 #[command(
     name = "gitp",
     about = "A fast git profile switcher with SSH and HTTPS support",
     version,
+    disable_version_flag = true,
     author,
     long_about = None
 )]
 pub struct Cli {
+    /// Print version; with --verbose, also the optional features compiled in
+    #[arg(short = 'V', long)]
+    pub version: bool,
+
     /// Turn on/off colored output
     #[arg(long, global = true, default_value = "true")]
     pub color: bool,
@@ -17,8 +22,30 @@ pub struct Cli {
     #[arg(short, long, global = true)]
     pub verbose: bool,
 
+    /// Don't show progress spinners. `gitp check` prints nothing at all and only sets its
+    /// exit status.
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+
+    /// Print plain-ASCII symbols instead of Unicode ones
+    #[arg(long, global = true)]
+    pub ascii: bool,
+
+    /// Show times in UTC instead of the local time zone
+    #[arg(long, global = true)]
+    pub utc: bool,
+
+    /// Print tokens, passwords and secret-named config values instead of `<redacted>`
+    #[arg(long, global = true)]
+    pub reveal: bool,
+
+    /// Report on stderr how long loading the config, git calls, the keyring and SSH config
+    /// writes took
+    #[arg(long, global = true, alias = "profile-perf")]
+    pub timings: bool,
+
     #[command(subcommand)]
-    pub command: Commands,
+    pub command: Option<Commands>,
 }
 
 #[derive(Subcommand)]
@@ -52,6 +79,14 @@ pub enum Commands {
         #[arg(long, requires = "ssh_key_path")]
         ssh_key_host: Option<String>,
 
+        /// core.autocrlf for this profile: true, false or input (for non-interactive mode)
+        #[arg(long)]
+        autocrlf: Option<String>,
+
+        /// core.eol for this profile: lf, crlf or native (for non-interactive mode)
+        #[arg(long)]
+        eol: Option<String>,
+
         // HTTPS Credentials (for non-interactive mode)
         /// Hostname for HTTPS (e.g., github.com).
         #[arg(long, group = "https_new_details")]
@@ -65,6 +100,11 @@ pub enum Commands {
         /// Store the provided --https-token in the system keychain (requires --https-host, --https-username, and --https-token).
         #[arg(long, requires_all = ["https_host", "https_username", "https_token"])]
         https_store_in_keychain: bool,
+
+        /// Never prompt: fill in missing name, email and signing key from the global git
+        /// identity, or placeholders when it is unset
+        #[arg(long)]
+        defaults: bool,
     },
 
     /// List all profiles
@@ -72,13 +112,18 @@ pub enum Commands {
         /// Show detailed information
         #[arg(short, long)]
         verbose: bool,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
     },
 
     /// Switch to a profile
     #[command(name = "use")]
     Use {
         /// Profile name
-        name: String,
+        #[arg(required_unless_present = "auto")]
+        name: Option<String>,
 
         /// Apply profile to current repository only
         #[arg(short, long, conflicts_with = "global")]
@@ -87,12 +132,35 @@ pub enum Commands {
         /// Apply profile globally (default behavior)
         #[arg(short, long)]
         global: bool,
+
+        /// Only switch the current shell session by printing exports for the `gitp` shell
+        /// function (see `gitp shell-init`)
+        #[arg(long, conflicts_with_all = ["local", "global"])]
+        session: bool,
+
+        /// Shell syntax for the --session exports
+        #[arg(long, value_enum, default_value_t = Shell::Bash, requires = "session")]
+        shell: Shell,
+
+        /// Pick the profile tied to the host of the repository's origin remote and apply it
+        /// locally, with its host_signing entry for that host
+        #[arg(long, conflicts_with_all = ["name", "global", "session"])]
+        auto: bool,
+
+        /// Skip the checks before switching: custom_config against the known git keys and, when
+        /// the profile signs every commit, the smartcard holding its GPG key
+        #[arg(long)]
+        no_validate: bool,
     },
 
     /// Show profile details
     Show {
-        /// Profile name
-        name: String,
+        /// Profile names or globs such as 'work-*' (default: the profile in use)
+        names: Vec<String>,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
     },
 
     /// Edit an existing profile
@@ -125,13 +193,19 @@ pub enum Commands {
         #[arg(long, requires = "ssh_key_path")]
         ssh_key_host: Option<String>,
 
+        /// New core.autocrlf: true, false or input. Provide an empty string to remove it.
+        #[arg(long)]
+        autocrlf: Option<String>,
+
+        /// New core.eol: lf, crlf or native. Provide an empty string to remove it.
+        #[arg(long)]
+        eol: Option<String>,
+
         // HTTPS Credentials (for non-interactive mode)
-        /// New hostname for HTTPS (e.g., github.com). Conflicts with --https-remove-credentials.
-        #[arg(
-            long,
-            group = "https_edit_details_group",
-            conflicts_with = "https_remove_credentials"
-        )]
+        /// Hostname for HTTPS (e.g., github.com). Credentials for a host the profile has none
+        /// for are added next to the existing ones. With --https-remove-credentials, only this
+        /// host's credentials are removed.
+        #[arg(long, group = "https_edit_details_group")]
         https_host: Option<String>,
         /// New username for HTTPS (requires --https-host). Conflicts with --https-remove-credentials.
         #[arg(
@@ -146,15 +220,53 @@ pub enum Commands {
         /// Store the provided --https-token in the system keychain (requires --https-host, --https-username, and --https-token). Conflicts with --https-remove-credentials.
         #[arg(long, requires_all = ["https_host", "https_username", "https_token"], conflicts_with = "https_remove_credentials")]
         https_store_in_keychain: bool,
-        /// Remove existing HTTPS credentials from the profile. Conflicts with providing new HTTPS details.
-        #[arg(long, conflicts_with_all = ["https_host", "https_username", "https_token", "https_store_in_keychain"])]
+        /// Remove existing HTTPS credentials from the profile: those for --https-host, or all of
+        /// them. Conflicts with providing new HTTPS details.
+        #[arg(long, conflicts_with_all = ["https_username", "https_token", "https_store_in_keychain"])]
         https_remove_credentials: bool,
+
+        /// Set a custom git config key, as KEY=VALUE. Repeat a key to give it several values.
+        #[arg(long, value_name = "KEY=VALUE")]
+        set_config: Vec<String>,
+
+        /// Remove a custom git config key
+        #[arg(long, value_name = "KEY")]
+        unset_config: Vec<String>,
+
+        /// Whether `gitp use` writes a Host entry for the SSH key in ~/.ssh/config (true or
+        /// false). Turn it off for keys ssh-agent or your own entries already handle.
+        #[arg(long)]
+        ssh_managed: Option<bool>,
+
+        /// Limit the SSH key to ssh runs below this directory, with a `Match host ... exec`
+        /// entry (repeatable; replaces the current list, an empty string removes it)
+        #[arg(long, value_name = "DIR")]
+        ssh_match_dir: Vec<String>,
+
+        /// Add or replace the SSH key for one more host, as [USER@]HOST=KEY_PATH
+        /// (repeatable), e.g. gitlab.com=~/.ssh/id_gitlab
+        #[arg(long, value_name = "[USER@]HOST=KEY_PATH")]
+        add_ssh_host: Vec<String>,
+
+        /// Remove the SSH key added for a host with --add-ssh-host (repeatable)
+        #[arg(long, value_name = "HOST")]
+        remove_ssh_host: Vec<String>,
+
+        /// Notes such as "requires corp VPN", shown when the profile's hosts can't be reached
+        /// (an empty string removes them)
+        #[arg(long)]
+        notes: Option<String>,
+
+        /// Save custom_config without checking it against the known git keys
+        #[arg(long)]
+        no_validate: bool,
     },
 
     /// Remove a profile
     Remove {
-        /// Profile name
-        name: String,
+        /// Profile names or globs such as 'client-*'
+        #[arg(required = true)]
+        names: Vec<String>,
 
         /// Skip confirmation prompt
         #[arg(short, long)]
@@ -175,8 +287,63 @@ pub enum Commands {
         #[command(subcommand)]
         command: SshKeyCommands,
     },
+    /// Configure SSH connection sharing per profile and close shared connections
+    Ssh {
+        #[command(subcommand)]
+        command: SshCommands,
+    },
     /// Display the current Git user name, email, and signing key
-    Current,
+    Current {
+        /// Also say which stored profile these values match, if any
+        #[arg(long = "match")]
+        match_profiles: bool,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Compare git's user.name, user.email and user.signingkey (global and local) and the SSH
+    /// config entries with the profiles gitp applied, showing what drifted
+    Status,
+    /// Show which provider account each HTTPS token of a profile belongs to, as last verified
+    Whoami {
+        /// Name of the profile (default: the profile in use)
+        profile_name: Option<String>,
+
+        /// Ask the providers again and record what they answer
+        #[arg(long)]
+        refresh: bool,
+    },
+    /// Show which profile, SSH key and credentials git would use for a remote URL
+    Resolve {
+        /// Remote URL (e.g. git@github.com:acme/app.git or https://github.com/acme/app), or the
+        /// name of a remote of the current repository
+        remote_url: String,
+    },
+    /// Manage HTTPS credentials associated with profiles
+    #[command(alias = "credential")]
+    Credentials {
+        #[command(subcommand)]
+        command: CredentialsCommands,
+    },
+    /// Git credential helper protocol (configured by `gitp use`, invoked by git)
+    #[command(hide = true)]
+    CredentialHelper {
+        /// Profile whose HTTPS credentials answer the request
+        #[arg(long)]
+        profile: String,
+
+        /// Operation requested by git (get, store or erase)
+        operation: String,
+    },
+    /// Exit 0 when run inside one of the profile's ssh_match_dirs (invoked by ssh through the
+    /// managed block's `Match ... exec` entries)
+    #[command(hide = true)]
+    SshMatch {
+        /// Profile whose directories to check
+        #[arg(long)]
+        profile: String,
+    },
     /// Export a profile to a TOML file or stdout
     Export {
         /// Name of the profile to export
@@ -186,6 +353,20 @@ pub enum Commands {
         /// If not provided, the profile will be printed to stdout.
         #[arg(short, long)]
         output_path: Option<String>,
+
+        /// Output format: gitp's TOML, a git config snippet, or an SSH config Host block
+        #[arg(long = "as", value_enum, default_value_t = ExportFormat::Toml)]
+        format: ExportFormat,
+
+        /// Encrypt the token and secret-named values with a passphrase, leaving the rest of
+        /// the TOML readable (passphrase from GITP_EXPORT_PASSPHRASE or a prompt)
+        #[arg(long)]
+        encrypt_secrets: bool,
+
+        /// Export the profile as it takes effect: system-store origin, settings and the current
+        /// repository's host_signing entry resolved, defaults written out
+        #[arg(long)]
+        effective: bool,
     },
 
     /// Import a profile from a TOML file or stdin
@@ -203,9 +384,305 @@ pub enum Commands {
         /// Overwrite existing profile if it has the same name
         #[arg(long)]
         force: bool,
+
+        /// Import HTTPS credentials from a git credential-store file instead
+        /// (defaults to ~/.git-credentials or ~/.config/git/credentials)
+        #[arg(long, value_name = "PATH", conflicts_with_all = ["profile_name", "force"])]
+        from_credential_store: Option<Option<String>>,
+    },
+
+    /// Manage repository remotes
+    Remotes {
+        #[command(subcommand)]
+        command: RemotesCommands,
+    },
+
+    /// Clone a repository the way a profile reaches its host, then apply the profile to it
+    Clone {
+        /// Repository URL
+        url: String,
+
+        /// Directory to clone into; by default the repository name, as git picks it
+        dir: Option<String>,
+
+        /// Profile to clone with and apply to the clone
+        #[arg(short, long)]
+        profile: String,
+
+        /// Further options for git clone, after `--` (e.g. `-- --depth 1`)
+        #[arg(last = true)]
+        git_args: Vec<String>,
+    },
+
+    /// Convert clone URLs to a profile's SSH host alias
+    Url {
+        #[command(subcommand)]
+        command: UrlCommands,
+    },
+
+    /// Switch identity by directory through git includeIf rules
+    Rules {
+        #[command(subcommand)]
+        command: RulesCommands,
+    },
+
+    /// Check SSH, ssh-agent and remote setup for common multi-account problems
+    Doctor {
+        /// Apply the profile of each `[includeIf "gitdir:..."]` rule that sets an identity to
+        /// the repositories below its directory, then offer to remove the rule
+        #[arg(long)]
+        convert_includes: bool,
+    },
+
+    /// Check GPG signing, including keys on smartcards
+    Gpg {
+        #[command(subcommand)]
+        command: GpgCommands,
+    },
+
+    /// Configure git format-patch/send-email per profile and check its SMTP server
+    Sendemail {
+        #[command(subcommand)]
+        command: SendemailCommands,
+    },
+
+    /// Summarize which identities authored a repository's commits and flag unknown emails
+    Report {
+        /// Repository (default: the current one)
+        repo: Option<String>,
+    },
+
+    /// Pass/fail checks for makefiles, hooks and CI: exit 0 when fine, 1 on a problem, 2 when
+    /// the check couldn't run. Combine with --quiet to print nothing.
+    Check {
+        #[command(subcommand)]
+        command: CheckCommands,
+    },
+
+    /// Install git hooks that stop mistakes gitp can detect, e.g. pushing with another
+    /// profile's account
+    Guard {
+        #[command(subcommand)]
+        command: GuardCommands,
+    },
+
+    /// Attribute a repository's history written under several profiles to one identity
+    Mailmap {
+        #[command(subcommand)]
+        command: MailmapCommands,
+    },
+
+    /// Show where gitp's files and the files it writes to are on this machine
+    Paths,
+
+    /// Show what gitp knows about a Git host: SSH endpoint, API, private email form
+    Provider {
+        /// Host name, e.g. github.com or git.mycompany.com
+        host: String,
+    },
+
+    /// Show who created, edited, renamed or removed profiles, and when
+    Changelog {
+        /// Only changes to this profile (under its current or a previous name)
+        profile: Option<String>,
+
+        /// Show at most this many entries
+        #[arg(short = 'n', long)]
+        limit: Option<usize>,
+    },
+
+    /// Write a sanitized diagnostics bundle (.tar.gz) to attach to bug reports
+    DebugBundle {
+        /// Where to write the bundle (default: ./gitp-debug-<timestamp>.tar.gz)
+        #[arg(short, long)]
+        output_path: Option<String>,
+    },
+
+    /// Split the global git identity into a personal and a work profile
+    Split,
+
+    /// Create a profile from the environment and apply it globally, for devcontainers and
+    /// Codespaces (never prompts, leaves the SSH config alone)
+    Bootstrap {
+        /// Read the identity from GIT_AUTHOR_NAME/GIT_AUTHOR_EMAIL (or GIT_COMMITTER_*) and
+        /// HTTPS credentials from GITHUB_TOKEN
+        #[arg(long, required = true)]
+        from_env: bool,
+
+        /// Name of the profile to create or replace
+        #[arg(long, default_value = "container")]
+        name: String,
+    },
+
+    /// Set up git on CI runners
+    Ci {
+        #[command(subcommand)]
+        command: CiCommands,
+    },
+
+    /// Print the shell function that makes `gitp use --session` work
+    /// (e.g. add `eval "$(gitp shell-init bash)"` to ~/.bashrc)
+    ShellInit {
+        /// Shell to print the function for
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+
+    /// Print an alias per profile that switches the current repository to it
+    /// (e.g. add `eval "$(gitp shell-aliases bash)"` to ~/.bashrc for `gw` -> `gitp use work --local`)
+    ShellAliases {
+        /// Shell to print the aliases for
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+
+    /// Print a compact profile indicator for tmux's status-right or a zellij plugin
+    /// (e.g. `#(gitp badge --path '#{pane_current_path}')`)
+    Badge {
+        /// Output format
+        #[arg(long, value_enum, default_value_t = BadgeFormat::Tmux)]
+        format: BadgeFormat,
+        /// Directory to check instead of the current one
+        #[arg(long)]
+        path: Option<String>,
+    },
+
+    /// Manage where gitp stores profiles and state
+    Storage {
+        #[command(subcommand)]
+        command: StorageCommands,
+    },
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Colored text for people
+    Text,
+    /// One JSON object, for scripts and editor plugins
+    Json,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// gitp profile TOML, importable with `gitp import`
+    Toml,
+    /// `[user]`/`[core]`/`[url]` snippet for ~/.gitconfig
+    Gitconfig,
+    /// `Host` block for ~/.ssh/config
+    Sshconfig,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BadgeFormat {
+    /// tmux format string with `#[fg=...]` colors
+    Tmux,
+    /// One JSON object, for status bar plugins
+    Json,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    /// bash (also any POSIX sh)
+    Bash,
+    Zsh,
+    Fish,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum RemotesCommands {
+    /// Rewrite SSH remotes that use a profile's raw host to its host alias
+    Migrate {
+        /// Directory to search for repositories (default: current directory)
+        #[arg(long)]
+        path: Option<String>,
+
+        /// Only list the remotes that would be rewritten
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Rewrite without asking for each repository
+        #[arg(short, long, conflicts_with = "dry_run")]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum RulesCommands {
+    /// Use a profile in every repository below a directory (e.g. ~/work)
+    Add {
+        /// Directory whose repositories use the profile; a trailing /** is allowed
+        dir: String,
+
+        /// Profile to use there
+        profile: String,
+    },
+    /// Stop switching by a directory
+    Remove {
+        /// Directory given to `rules add`
+        dir: String,
+    },
+    /// List the rules and whether the global git config is in sync with them
+    List,
+    /// Rewrite the rules' includeIf entries and gitconfig fragments from the profiles
+    Apply,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum UrlCommands {
+    /// Print the form of a clone URL that goes through a profile's host alias
+    /// (https://github.com/org/repo -> git@github.com-work:org/repo.git)
+    Rewrite {
+        /// URL to convert, HTTPS or SSH
+        url: String,
+
+        /// Profile whose alias to use (default: the one profile with an alias for the URL's host)
+        #[arg(short, long)]
+        profile: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum CiCommands {
+    /// Apply a profile manifest to the runner's global git config
+    Setup {
+        /// CI system the runner belongs to
+        #[arg(long, value_enum)]
+        provider: CiProvider,
+
+        /// Profile TOML, as written by `gitp export`
+        #[arg(long)]
+        manifest: String,
+
+        /// Environment variable holding the HTTPS token (pass it from a secret)
+        #[arg(long, default_value = "GITP_TOKEN")]
+        token_env: String,
+    },
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CiProvider {
+    /// GitHub Actions (masks the token with ::add-mask::)
+    GithubActions,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum StorageCommands {
+    /// Move profiles, settings and state to another storage backend
+    Migrate {
+        /// Backend to move to
+        #[arg(value_enum)]
+        to: StorageBackend,
     },
 }
 
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageBackend {
+    /// config.toml and state.toml (default)
+    Toml,
+    /// A single SQLite database (requires the `sqlite` feature)
+    Sqlite,
+}
+
 #[derive(Subcommand, Debug, Clone)]
 pub enum SshKeyCommands {
     /// Set or update the SSH key path for a profile
@@ -214,6 +691,26 @@ pub enum SshKeyCommands {
         profile_name: String,
         /// Path to the SSH private key (e.g., ~/.ssh/id_rsa_work)
         key_path: String,
+        /// Host the key is for (e.g., github.com)
+        #[arg(long)]
+        host: Option<String>,
+    },
+    /// Set the host a profile's SSH key is for (e.g., github.com or *.corp.example.com)
+    SetHost {
+        /// Name of the profile
+        profile_name: String,
+        /// Hostname the key authenticates to
+        host: String,
+    },
+    /// Show the host a profile's SSH key is for, and its host alias
+    ShowHost {
+        /// Name of the profile
+        profile_name: String,
+    },
+    /// Remove the SSH host association (and host alias) from a profile
+    RemoveHost {
+        /// Name of the profile
+        profile_name: String,
     },
     /// Remove the SSH key association from a profile
     Remove {
@@ -225,6 +722,354 @@ pub enum SshKeyCommands {
         /// Name of the profile
         profile_name: String,
     },
+    /// List the SSH keys in ~/.ssh with their type, including FIDO2 security keys
+    Scan {
+        /// Directory to look in (default: ~/.ssh)
+        #[arg(long)]
+        dir: Option<String>,
+    },
+    /// Generate a new SSH key with ssh-keygen and set it for a profile
+    Generate {
+        /// Name of the profile
+        profile_name: String,
+
+        /// Key algorithm (default: ed25519)
+        #[arg(long = "type", value_enum, conflicts_with_all = ["sk", "ecdsa"])]
+        algorithm: Option<KeyAlgorithm>,
+
+        /// Generate a FIDO2 key backed by a security key (ed25519-sk, or ecdsa-sk with --ecdsa)
+        #[arg(long)]
+        sk: bool,
+
+        /// Keep the key on the security key, so `ssh-keygen -K` can recover it on other machines
+        #[arg(long)]
+        resident: bool,
+
+        /// Require the security key's PIN on every use, not just a touch
+        #[arg(long)]
+        verify_required: bool,
+
+        /// Generate an ECDSA key instead of Ed25519 (for authenticators without Ed25519)
+        #[arg(long)]
+        ecdsa: bool,
+
+        /// Host the key is for (default: the profile's SSH key host); it also goes into the
+        /// key's comment, `gitp:<profile>@<host>`
+        #[arg(long)]
+        host: Option<String>,
+
+        /// Leave the key unencrypted instead of asking for a passphrase
+        #[arg(long)]
+        no_passphrase: bool,
+
+        /// Where to write the key (default: ~/.ssh/id_<type>_<profile>)
+        #[arg(long)]
+        path: Option<String>,
+    },
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyAlgorithm {
+    /// Ed25519, which every common host accepts
+    Ed25519,
+    /// 4096-bit RSA, for servers that don't take Ed25519 yet
+    Rsa4096,
+    /// Ed25519 on a FIDO2 security key
+    Ed25519Sk,
+    /// ECDSA on a FIDO2 security key, for authenticators without Ed25519
+    EcdsaSk,
+    /// ECDSA (P-256)
+    Ecdsa,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum SshCommands {
+    /// Set the ControlMaster, ControlPath and ControlPersist of a profile's SSH entry. Provide
+    /// an empty string to remove a value. `gitp use` writes them.
+    Control {
+        /// Name of the profile
+        profile_name: String,
+        /// ControlMaster: yes, no, ask, auto or autoask
+        #[arg(long)]
+        master: Option<String>,
+        /// ControlPath; `{profile}` stands for the profile name (default:
+        /// ~/.ssh/gitp-{profile}-%C)
+        #[arg(long)]
+        path: Option<String>,
+        /// ControlPersist: yes, no, or how long an idle connection stays open (e.g. 10m)
+        #[arg(long)]
+        persist: Option<String>,
+        /// Remove the profile's connection sharing settings
+        #[arg(long, conflicts_with_all = ["master", "path", "persist"])]
+        clear: bool,
+    },
+    /// Close the profile's shared SSH connection, or remove its control socket when the
+    /// connection is gone, so the next connection logs in with the profile's key
+    ResetControl {
+        /// Name of the profile
+        profile_name: String,
+    },
+    /// Show whether the profile's SSH key has a passphrase and whether ssh-agent holds it, so
+    /// fetches without a terminal work; set AddKeysToAgent for its SSH entries or load the key
+    Agent {
+        /// Name of the profile
+        profile_name: String,
+        /// AddKeysToAgent for the profile's SSH entries: yes, confirm, ask, no or a lifetime
+        /// (e.g. 1h). Provide an empty string to remove it. `gitp use` writes it.
+        #[arg(long)]
+        add_keys_to_agent: Option<String>,
+        /// On macOS, keep the key's passphrase in the keychain (true or false): `gitp use`
+        /// writes `UseKeychain yes` into the profile's SSH entries, with AddKeysToAgent yes
+        /// unless set otherwise
+        #[arg(long)]
+        use_keychain: Option<bool>,
+        /// Load the key into ssh-agent now; ssh-add asks for the passphrase
+        #[arg(long)]
+        load: bool,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum GpgCommands {
+    /// Find a profile's signing key, check its smartcard is inserted and make a test signature
+    Test {
+        /// Name of the profile (default: the profile in use)
+        profile_name: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum CheckCommands {
+    /// Fail when git would commit with another email than the profile gitp applied, or with
+    /// the profile meant for another host
+    Identity {
+        /// Directory to check instead of the current one
+        #[arg(long)]
+        path: Option<String>,
+    },
+    /// Fail when one of the profile's HTTPS tokens is rejected, belongs to another user, is
+    /// expired or lacks the repository scope
+    Token {
+        /// Name of the profile (default: the profile in use)
+        profile_name: Option<String>,
+        /// Use what the last `gitp credentials verify` recorded instead of asking the provider
+        #[arg(long)]
+        offline: bool,
+    },
+    /// Fail when git would push as another account (the one `ssh -T` greets, or the one the
+    /// HTTPS token belongs to) than the account of the profile that authored the commits
+    Push {
+        /// Remote name, as git passes it to a pre-push hook (default: origin)
+        remote: Option<String>,
+        /// URL pushed to (default: the remote's URL)
+        url: Option<String>,
+        /// Read the refs being pushed from standard input, as git writes them to a pre-push
+        /// hook, and check their commits' authors instead of the email git commits with
+        #[arg(long)]
+        stdin: bool,
+        /// Don't connect to the host: only check SSH host aliases and the username in the URL
+        #[arg(long)]
+        offline: bool,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum GuardCommands {
+    /// Install a hook in the current repository
+    Install {
+        /// Install a pre-push hook that runs `gitp check push` and aborts the push when git
+        /// would authenticate as another account than the commits' profile uses
+        #[arg(long, required = true)]
+        pre_push: bool,
+        /// Replace a pre-push hook gitp didn't write (it is kept as pre-push.bak)
+        #[arg(long)]
+        force: bool,
+    },
+    /// Remove a hook `gitp guard install` wrote, restoring the one it replaced
+    Uninstall {
+        /// Remove the pre-push hook
+        #[arg(long, required = true)]
+        pre_push: bool,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum MailmapCommands {
+    /// Print `.mailmap` entries mapping the profile emails found in the repository's history
+    /// to a canonical profile
+    Generate {
+        /// Profile whose name and email the others map to (default: the profile in use)
+        #[arg(long)]
+        canonical: Option<String>,
+        /// Repository (default: the current one)
+        #[arg(long)]
+        path: Option<String>,
+        /// Write the entries to the repository's .mailmap, in a gitp-managed block
+        #[arg(long)]
+        install: bool,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum SendemailCommands {
+    /// Set a profile's format.from and sendemail settings. Provide an empty string to remove
+    /// a value. `gitp use` applies them.
+    Set {
+        /// Name of the profile
+        profile_name: String,
+        /// format.from, the From: of generated patches (e.g. "John Doe <john@kernel.org>")
+        #[arg(long)]
+        from: Option<String>,
+        /// sendemail.confirm: always, never, cc, compose or auto
+        #[arg(long)]
+        confirm: Option<String>,
+        /// Put the SMTP settings in a `[sendemail "<identity>"]` block and select it
+        #[arg(long)]
+        identity: Option<String>,
+        /// sendemail.smtpServer (e.g. smtp.gmail.com)
+        #[arg(long)]
+        smtp_server: Option<String>,
+        /// sendemail.smtpServerPort
+        #[arg(long)]
+        smtp_port: Option<String>,
+        /// sendemail.smtpEncryption: tls (STARTTLS) or ssl
+        #[arg(long)]
+        smtp_encryption: Option<String>,
+        /// sendemail.smtpUser
+        #[arg(long)]
+        smtp_user: Option<String>,
+        /// SMTP password, kept in the configured secret store (needs --smtp-server and
+        /// --smtp-user, set now or before)
+        #[arg(long)]
+        smtp_pass: Option<String>,
+        /// Remove every sendemail setting from the profile
+        #[arg(long, conflicts_with_all = ["from", "confirm", "identity", "smtp_server", "smtp_port", "smtp_encryption", "smtp_user", "smtp_pass"])]
+        clear: bool,
+    },
+    /// Connect to the profile's SMTP server and log in with its stored password, without
+    /// sending mail
+    Test {
+        /// Name of the profile (default: the profile in use)
+        profile_name: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum CredentialsCommands {
+    /// Set or replace a profile's HTTPS token, stored in the configured secret store
+    Set {
+        /// Name of the profile
+        profile_name: String,
+        /// Host the token is for; a new host is added to the profile (default: the profile's
+        /// HTTPS host, when it has only one)
+        #[arg(long)]
+        host: Option<String>,
+        /// HTTPS username (default: the profile's current HTTPS username)
+        #[arg(long)]
+        username: Option<String>,
+        /// The token; prompted for when omitted, which keeps it out of shell history
+        #[arg(long)]
+        token: Option<String>,
+        /// Keep the token in config.toml instead of the secret store (not recommended)
+        #[arg(long)]
+        in_config: bool,
+    },
+    /// Show a profile's HTTPS hosts, usernames and where their tokens are stored
+    Show {
+        /// Name of the profile
+        profile_name: String,
+    },
+    /// Remove a profile's HTTPS credentials for a host and delete its stored token
+    Remove {
+        /// Name of the profile
+        profile_name: String,
+        /// Host whose credentials to remove (required when the profile has several)
+        #[arg(long)]
+        host: Option<String>,
+        /// Skip confirmation prompt
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Check a profile's HTTPS tokens with their providers and record their scopes and expiry
+    Verify {
+        /// Name of the profile
+        profile_name: String,
+        /// Only check the token for this host
+        #[arg(long)]
+        host: Option<String>,
+    },
+    /// Keep a profile's tokens in git's credential-cache daemon, so fetches don't read the
+    /// keyring every time
+    Cache {
+        /// Name of the profile
+        profile_name: String,
+        /// How long a token stays cached after it was read; 0 turns caching off
+        minutes: u32,
+        /// Only change the token for this host
+        #[arg(long)]
+        host: Option<String>,
+    },
+    /// Drop cached tokens: the profile's, or all of them
+    Flush {
+        /// Name of the profile (default: every profile)
+        profile_name: Option<String>,
+        /// Only drop the token for this host
+        #[arg(long)]
+        host: Option<String>,
+    },
+    /// Move HTTPS tokens into the configured secret store (`secret_store`), from config.toml
+    /// or from the other store
+    Migrate {
+        /// Profile names or globs such as 'client-*' (default: every profile)
+        profiles: Vec<String>,
+        /// Only list the tokens that would move
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Attach the tokens another credential helper already stores for the profile's hosts,
+    /// copying them into gitp's secret store
+    Import {
+        /// Name of the profile
+        profile_name: String,
+        /// Helper whose store to read
+        #[arg(long, value_enum)]
+        from: HelperStore,
+        /// Only import the token for this host; a new host is added to the profile
+        #[arg(long)]
+        host: Option<String>,
+        /// Username the token is stored for (default: the profile's HTTPS username for the
+        /// host, else whichever the helper has)
+        #[arg(long, requires = "host")]
+        username: Option<String>,
+    },
+    /// Store a profile's HTTPS tokens in another credential helper, for tools that only ask
+    /// that one
+    Export {
+        /// Name of the profile
+        profile_name: String,
+        /// Helper whose store to write
+        #[arg(long, value_enum)]
+        to: HelperStore,
+        /// Only export the token for this host
+        #[arg(long)]
+        host: Option<String>,
+    },
+    /// Git credential helper protocol for the active profile, for
+    /// `credential.helper = "!gitp credential"` (invoked by git)
+    #[command(hide = true)]
+    Get,
+    #[command(hide = true)]
+    Store,
+    #[command(hide = true)]
+    Erase,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HelperStore {
+    /// Git Credential Manager
+    Manager,
+    /// The macOS keychain, through git-credential-osxkeychain
+    Osxkeychain,
 }
 
 // For future implementation
@@ -0,0 +1,217 @@
+// src/commands/badge.rs
+// Compact profile indicator for terminal multiplexer status bars
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::cli::BadgeFormat;
+use crate::commands::use_profile::PROFILE_MARKER_KEY;
+use crate::config::Config;
+use crate::utils::output::is_ascii;
+
+/// How long the badge may wait for git. Status bars redraw on a timer and block on their
+/// commands, so a slow filesystem shows an unchecked badge instead of freezing the bar.
+const GIT_BUDGET: Duration = Duration::from_millis(100);
+
+/// What the badge shows for a directory.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Badge {
+    /// Profile in effect: the one gitp applied here, else the global one
+    pub profile: Option<String>,
+    /// user.email git uses in the directory
+    pub email: Option<String>,
+    /// The profile's email
+    pub expected_email: Option<String>,
+    /// git would commit with another email than the profile's
+    pub mismatch: bool,
+    /// `false` when git didn't answer within the budget and only gitp's state was used
+    pub checked: bool,
+}
+
+pub fn execute(format: BadgeFormat, path: Option<String>) -> Result<()> {
+    // The cached copy keeps TOML parsing off the status bar's timer
+    let config = Config::load_cached().context("Failed to load configuration.")?;
+    let dir = match path {
+        Some(path) => PathBuf::from(path),
+        None => std::env::current_dir().context("Failed to get current directory.")?,
+    };
+    let badge = badge_for(&config, read_identity(&dir));
+    println!("{}", render(&badge, format)?);
+    Ok(())
+}
+
+/// Builds the badge from the `(key, value)` pairs git reported, or from gitp's state alone
+/// when git didn't answer (`None`).
+pub fn badge_for(config: &Config, entries: Option<Vec<(String, String)>>) -> Badge {
+    let Some(entries) = entries else {
+        return Badge {
+            profile: config.state.current_profile.clone(),
+            ..Badge::default()
+        };
+    };
+    // Later values win, as with `git config --get`
+    let last = |key: &str| {
+        entries
+            .iter()
+            .rev()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v.clone())
+    };
+    let email = last("user.email");
+    let profile = last(PROFILE_MARKER_KEY)
+        .or_else(|| config.state.current_profile.clone())
+        .or_else(|| {
+            // Configured by hand: name the profile with the same email, if any
+            let email = email.as_deref()?;
+            config
+                .profiles
+                .values()
+                .find(|p| p.git_config.user_email.eq_ignore_ascii_case(email))
+                .map(|p| p.name.clone())
+        });
+    let expected_email = profile
+        .as_ref()
+        .and_then(|name| config.profiles.get(name))
+        .map(|p| p.git_config.user_email.clone());
+    let mismatch = profile.is_some()
+        && expected_email.as_deref().map(str::to_lowercase)
+            != email.as_deref().map(str::to_lowercase);
+    Badge {
+        profile,
+        email,
+        expected_email,
+        mismatch,
+        checked: true,
+    }
+}
+
+pub fn render(badge: &Badge, format: BadgeFormat) -> Result<String> {
+    match format {
+        BadgeFormat::Json => serde_json::to_string(badge).context("Failed to serialize badge."),
+        BadgeFormat::Tmux => Ok(render_tmux(badge)),
+    }
+}
+
+fn render_tmux(badge: &Badge) -> String {
+    let Some(profile) = &badge.profile else {
+        return "#[fg=colour244]no profile#[default]".to_string();
+    };
+    // `#` starts a format sequence in tmux
+    let name = profile.replace('#', "##");
+    let (style, marker) = if !badge.checked {
+        ("fg=yellow", " ?")
+    } else if badge.mismatch {
+        ("fg=red,bold", if is_ascii() { " !" } else { " ≠" })
+    } else {
+        ("fg=green", "")
+    };
+    format!("#[{}]{}{}#[default]", style, name, marker)
+}
+
+/// user.email and the gitp marker as git sees them in `dir`, or `None` when git fails or
+/// runs past [`GIT_BUDGET`].
+fn read_identity(dir: &Path) -> Option<Vec<(String, String)>> {
+    let pattern = format!(
+        "^(user\\.email|{})$",
+        PROFILE_MARKER_KEY.replace('.', "\\.")
+    );
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["config", "-z", "--get-regexp", &pattern])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+
+    let deadline = Instant::now() + GIT_BUDGET;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() < deadline => thread::sleep(Duration::from_millis(2)),
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+        }
+    };
+    // Exit code 1: neither key is set
+    if !status.success() {
+        return (status.code() == Some(1)).then(Vec::new);
+    }
+    let mut output = String::new();
+    child.stdout.take()?.read_to_string(&mut output).ok()?;
+    Some(
+        output
+            .split('\0')
+            .filter_map(|entry| entry.split_once('\n'))
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Profile;
+
+    fn entries(pairs: &[(&str, &str)]) -> Option<Vec<(String, String)>> {
+        Some(
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_badge_for() {
+        let mut config = Config::default();
+        for (name, email) in [("work", "john@company.com"), ("personal", "john@home.org")] {
+            let profile = Profile::new(name.to_string(), "John".to_string(), email.to_string());
+            config.profiles.insert(name.to_string(), profile);
+        }
+        config.state.current_profile = Some("personal".to_string());
+
+        let local = badge_for(
+            &config,
+            entries(&[
+                ("user.email", "john@home.org"),
+                ("gitp.profile", "personal"),
+                ("user.email", "John@Company.com"),
+                ("gitp.profile", "work"),
+            ]),
+        );
+        assert_eq!(local.profile.as_deref(), Some("work"));
+        assert!(!local.mismatch && local.checked);
+        assert_eq!(render_tmux(&local), "#[fg=green]work#[default]");
+
+        let edited = badge_for(&config, entries(&[("user.email", "john@company.com")]));
+        assert_eq!(edited.profile.as_deref(), Some("personal"));
+        assert!(edited.mismatch);
+        assert!(render_tmux(&edited).starts_with("#[fg=red,bold]personal "));
+
+        config.state.current_profile = None;
+        let by_email = badge_for(&config, entries(&[("user.email", "john@company.com")]));
+        assert_eq!(by_email.profile.as_deref(), Some("work"));
+        assert!(!by_email.mismatch);
+        assert_eq!(
+            render_tmux(&badge_for(&config, entries(&[]))),
+            "#[fg=colour244]no profile#[default]"
+        );
+
+        let late = badge_for(&config, None);
+        assert!(!late.checked);
+        assert_eq!(
+            render(&late, BadgeFormat::Json).unwrap(),
+            r#"{"profile":null,"email":null,"expected_email":null,"mismatch":false,"checked":false}"#
+        );
+    }
+}
@@ -0,0 +1,139 @@
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+
+use crate::commands::use_profile::apply_unattended;
+use crate::config::{normalize_host, Config, CredentialType, HttpsCredentials, Profile};
+use crate::credentials::{store_label, store_secure_token};
+use crate::utils::output::Paint;
+
+/// Username sent with a `GITHUB_TOKEN` when the environment names no user; GitHub accepts any
+/// username with a token, and this is the one its apps use.
+const DEFAULT_TOKEN_USERNAME: &str = "x-access-token";
+
+/// Creates (or replaces) profile `name` from the environment and applies it globally, for
+/// devcontainer and Codespaces setup scripts. Leaves the SSH config alone, and only prompts
+/// when the secret store asks.
+pub fn execute(name: String) -> Result<()> {
+    let mut config = Config::load().context("Failed to load configuration.")?;
+    let mut profile = profile_from_env(&name, |var| std::env::var(var).ok())?;
+    profile
+        .validate()
+        .context("The environment doesn't describe a valid profile")?;
+    // Into the secret store like `gitp add`; keyring_fallback decides without a keyring
+    for creds in &mut profile.https_credentials {
+        if let CredentialType::Token(token) = &creds.credential_type {
+            creds.credential_type =
+                store_secure_token(&config.settings, &creds.host, &creds.username, token)
+                    .with_context(|| format!("Failed to store the token for {}", creds.host))?;
+        }
+    }
+
+    let replaced = config.profiles.insert(name.clone(), profile.clone());
+    println!(
+        "{} profile {} from the environment.",
+        if replaced.is_some() {
+            "Updated"
+        } else {
+            "Created"
+        },
+        name.accent().bold()
+    );
+    for creds in &profile.https_credentials {
+        println!(
+            "  HTTPS credentials for {}@{} from GITHUB_TOKEN, in the {}",
+            creds.username.accent(),
+            creds.host.success(),
+            store_label(&creds.credential_type)
+        );
+    }
+
+    apply_unattended(&mut config, &name)?;
+    config
+        .save()
+        .context("Failed to save gitp configuration.")?;
+    println!(
+        "Applied '{}' to the global Git configuration.",
+        name.success()
+    );
+    Ok(())
+}
+
+/// Builds the profile from `GIT_AUTHOR_NAME`/`GIT_AUTHOR_EMAIL` (falling back to the
+/// `GIT_COMMITTER_*` pair) and, when `GITHUB_TOKEN` is set, HTTPS credentials for
+/// `GITHUB_SERVER_URL` (github.com by default) as `GITHUB_USER` or `GITHUB_ACTOR`.
+fn profile_from_env(name: &str, var: impl Fn(&str) -> Option<String>) -> Result<Profile> {
+    let var = |names: &[&str]| {
+        names
+            .iter()
+            .filter_map(|name| var(name))
+            .map(|value| value.trim().to_string())
+            .find(|value| !value.is_empty())
+    };
+    let (Some(user_name), Some(user_email)) = (
+        var(&["GIT_AUTHOR_NAME", "GIT_COMMITTER_NAME"]),
+        var(&["GIT_AUTHOR_EMAIL", "GIT_COMMITTER_EMAIL"]),
+    ) else {
+        bail!(
+            "Set GIT_AUTHOR_NAME and GIT_AUTHOR_EMAIL (or the GIT_COMMITTER_* pair) to bootstrap a profile."
+        );
+    };
+
+    let mut profile = Profile::new(name.to_string(), user_name, user_email);
+    if let Some(token) = var(&["GITHUB_TOKEN"]) {
+        let host = var(&["GITHUB_SERVER_URL"])
+            .map_or_else(|| "github.com".to_string(), |url| normalize_host(&url));
+        let username = var(&["GITHUB_USER", "GITHUB_ACTOR"])
+            .unwrap_or_else(|| DEFAULT_TOKEN_USERNAME.to_string());
+        // Moved to the secret store by `execute`
+        profile.https_credentials = vec![HttpsCredentials::new(
+            host,
+            username,
+            CredentialType::Token(token),
+        )];
+    }
+    Ok(profile)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn profile(vars: &[(&str, &str)]) -> Result<Profile> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        profile_from_env("container", |var| vars.get(var).cloned())
+    }
+
+    #[test]
+    fn test_profile_from_env() -> Result<()> {
+        assert!(profile(&[("GIT_AUTHOR_NAME", "John Doe")]).is_err());
+
+        let p = profile(&[
+            ("GIT_AUTHOR_NAME", " "),
+            ("GIT_COMMITTER_NAME", "John Doe"),
+            ("GIT_AUTHOR_EMAIL", "john@company.com"),
+        ])?;
+        assert_eq!(p.git_config.user_name, "John Doe");
+        assert_eq!(p.git_config.user_email, "john@company.com");
+        assert!(p.https_credentials.is_empty());
+
+        let p = profile(&[
+            ("GIT_AUTHOR_NAME", "John Doe"),
+            ("GIT_AUTHOR_EMAIL", "john@company.com"),
+            ("GITHUB_TOKEN", "ghu_secret"),
+            ("GITHUB_SERVER_URL", "https://GitHub.acme.com/"),
+            ("GITHUB_ACTOR", "jdoe"),
+        ])?;
+        let creds = &p.https_credentials[0];
+        assert_eq!(creds.host, "github.acme.com");
+        assert_eq!(creds.username, "jdoe");
+        assert_eq!(
+            creds.credential_type,
+            CredentialType::Token("ghu_secret".to_string())
+        );
+        Ok(())
+    }
+}
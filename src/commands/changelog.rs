@@ -0,0 +1,63 @@
+use anyhow::Result;
+use colored::Colorize;
+
+use crate::config::audit::{read_changelog, Action, ChangeEntry};
+use crate::utils::output::Paint;
+use crate::utils::time::format_time;
+
+/// Prints recorded profile changes, newest first, optionally only those of `profile`.
+pub fn execute(profile: Option<String>, limit: Option<usize>) -> Result<()> {
+    let entries: Vec<ChangeEntry> = read_changelog()?
+        .into_iter()
+        .rev()
+        .filter(|entry| profile.as_deref().is_none_or(|name| entry.concerns(name)))
+        .take(limit.unwrap_or(usize::MAX))
+        .collect();
+    if entries.is_empty() {
+        match &profile {
+            Some(name) => println!("No recorded changes to profile '{}'.", name),
+            None => println!("No recorded profile changes."),
+        }
+        return Ok(());
+    }
+
+    for (i, entry) in entries.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        print_entry(entry);
+    }
+    Ok(())
+}
+
+fn print_entry(entry: &ChangeEntry) {
+    let action = match entry.action {
+        Action::Created => entry.action.as_str().success(),
+        Action::Edited => entry.action.as_str().warning(),
+        Action::Renamed => entry.action.as_str().accent(),
+        Action::Removed => entry.action.as_str().error(),
+    };
+    let subject = match &entry.renamed_from {
+        Some(from) => format!("{} -> {}", from, entry.profile),
+        None => entry.profile.clone(),
+    };
+    println!(
+        "{} {} {} {}",
+        format_time(&entry.at).dimmed(),
+        action,
+        subject.bold(),
+        format!("by {}", entry.user).dimmed()
+    );
+    for change in &entry.changes {
+        let value = |value: &Option<String>| value.clone().unwrap_or_else(|| "(unset)".to_string());
+        match entry.action {
+            Action::Created => println!("    {}: {}", change.field, value(&change.new)),
+            _ => println!(
+                "    {}: {} -> {}",
+                change.field,
+                value(&change.old).dimmed(),
+                value(&change.new)
+            ),
+        }
+    }
+}
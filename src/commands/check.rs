@@ -0,0 +1,510 @@
+// src/commands/check.rs
+// Pass/fail checks for makefiles, hooks and CI, reported through the exit status
+
+use anyhow::{bail, Context, Result};
+use chrono::Local;
+use colored::Colorize;
+use std::io::Read;
+use std::path::PathBuf;
+
+use crate::cli::CheckCommands;
+use crate::commands::current::email_host_warning;
+use crate::commands::use_profile::PROFILE_MARKER_KEY;
+use crate::config::{profile_from_env, Config, HostMatch, HttpsCredentials, Profile};
+use crate::credentials::{resolve_token, verify};
+use crate::git::url::{parse_remote_url, RemoteUrl, Transport};
+use crate::git::{
+    author_emails, credential_fill, get_effective_git_config, get_primary_remote,
+    get_remote_effective_url, get_remotes, get_repo_root, in_repo, require_git,
+};
+use crate::ssh::auth::authenticated_login;
+use crate::utils::output::{Paint, Symbol};
+use crate::utils::progress::with_spinner;
+
+/// Exit status when the check ran and found a problem.
+const EXIT_PROBLEM: i32 = 1;
+/// Exit status when the check couldn't run (no configuration, git missing, network error...).
+const EXIT_ERROR: i32 = 2;
+
+/// Outcome of a check that ran.
+#[derive(Debug, Default)]
+struct Report {
+    /// One-line summary printed when nothing is wrong
+    summary: String,
+    problems: Vec<String>,
+}
+
+/// Runs the check and returns the process exit status. With `quiet`, nothing is printed,
+/// not even errors.
+pub fn execute(command: CheckCommands, quiet: bool) -> i32 {
+    let report = match command {
+        CheckCommands::Identity { path } => check_identity(path),
+        CheckCommands::Token {
+            profile_name,
+            offline,
+        } => check_token(profile_name, offline),
+        CheckCommands::Push {
+            remote,
+            url,
+            stdin,
+            offline,
+        } => check_push(remote, url, stdin, offline),
+    };
+    match report {
+        Ok(report) if report.problems.is_empty() => {
+            if !quiet {
+                println!("{} {}", Symbol::Ok.as_str().success(), report.summary);
+            }
+            0
+        }
+        Ok(report) => {
+            if !quiet {
+                for problem in &report.problems {
+                    println!("{} {}", Symbol::Fail.as_str().error(), problem);
+                }
+            }
+            EXIT_PROBLEM
+        }
+        Err(e) => {
+            if !quiet {
+                eprintln!("{} {:#}", "Error:".error().bold(), e);
+            }
+            EXIT_ERROR
+        }
+    }
+}
+
+/// Passes when git would commit with the email of the profile gitp applied, and that
+/// profile is the one for the repository's host.
+fn check_identity(path: Option<String>) -> Result<Report> {
+    require_git("Checking the identity")?;
+    let config = Config::load_cached().context("Failed to load configuration.")?;
+    let start = match path {
+        Some(path) => PathBuf::from(path),
+        None => std::env::current_dir().context("Failed to get current directory.")?,
+    };
+    in_repo(&start, || {
+        let repo = get_repo_root()?;
+        let repo = repo.as_ref().map(|r| r.to_string_lossy());
+        // Directory rules (`gitp rules`) set the identity and the marker through includeIf
+        let email = get_effective_git_config("user.email")?;
+        let profile = get_effective_git_config(PROFILE_MARKER_KEY)?.or_else(|| {
+            config
+                .state
+                .current_for(repo.as_deref())
+                .map(str::to_string)
+        });
+
+        let mut report = identity_report(&config, profile.as_deref(), email.as_deref());
+        if let Some(profile) = &profile {
+            for remote in get_remotes()? {
+                if let Err(e) = config.require_approved(&remote.effective_url, profile) {
+                    report.problems.push(e.to_string());
+                }
+            }
+        }
+        if let Some(email) = &email {
+            if let Some(warning) = email_host_warning(&config, email)? {
+                report
+                    .problems
+                    .push(format!("{} Fix: {}", warning.message, warning.fix));
+            }
+        }
+        Ok(report)
+    })
+}
+
+/// Compares the email git uses with the email of the profile in effect.
+fn identity_report(config: &Config, profile: Option<&str>, email: Option<&str>) -> Report {
+    let mut report = Report::default();
+    let Some(name) = profile else {
+        report
+            .problems
+            .push("No gitp profile is in use here. Run 'gitp use <profile>'.".to_string());
+        return report;
+    };
+    let Some(expected) = config.profiles.get(name).map(|p| &p.git_config.user_email) else {
+        report.problems.push(format!(
+            "Profile '{}' is in use but no longer exists.",
+            name
+        ));
+        return report;
+    };
+    match email {
+        Some(email) if email.eq_ignore_ascii_case(expected) => {
+            report.summary = format!("Committing as {} (profile '{}').", email, name);
+        }
+        Some(email) => report.problems.push(format!(
+            "git commits as {}, but profile '{}' uses {}. Run 'gitp use {}' again.",
+            email, name, expected, name
+        )),
+        None => report.problems.push(format!(
+            "user.email is not set, but profile '{}' uses {}. Run 'gitp use {}' again.",
+            name, expected, name
+        )),
+    }
+    report
+}
+
+/// Passes when each of the profile's HTTPS tokens is accepted by its provider, belongs to the
+/// profile's username, and is neither expired nor missing the repository scope.
+fn check_token(profile_name: Option<String>, offline: bool) -> Result<Report> {
+    let config = Config::load_cached().context("Failed to load configuration.")?;
+    let name = match profile_name {
+        Some(name) => name,
+        None => {
+            let repo = get_repo_root().ok().flatten();
+            let repo = repo.as_ref().map(|r| r.to_string_lossy());
+            profile_from_env()
+                .or_else(|| {
+                    config
+                        .state
+                        .current_for(repo.as_deref())
+                        .map(str::to_string)
+                })
+                .context("No profile is in use. Pass a profile name.")?
+        }
+    };
+    let Some(profile) = config.profiles.get(&name) else {
+        bail!("Profile '{}' not found.", name);
+    };
+    if profile.https_credentials.is_empty() {
+        bail!("Profile '{}' has no HTTPS credentials to check.", name);
+    }
+
+    let mut report = Report::default();
+    let mut summaries = Vec::new();
+    for creds in &profile.https_credentials {
+        let mut creds = creds.clone();
+        if !offline {
+            let token = resolve_token(&config.settings, &creds).with_context(|| {
+                format!(
+                    "Failed to read the token for {}@{}",
+                    creds.username, creds.host
+                )
+            })?;
+            let info = with_spinner(&format!("Contacting {}...", creds.host), || {
+                verify::inspect_token(&config.settings, &creds.host, &token)
+            })?;
+            if let Some(login) = info.login.filter(|login| login != &creds.username) {
+                report.problems.push(format!(
+                    "The token for {} belongs to '{}' but the profile's HTTPS username is '{}'.",
+                    creds.host, login, creds.username
+                ));
+            }
+            creds.token_scopes = info.scopes;
+            creds.token_expires_at = info.expires_at;
+        }
+        report.problems.extend(verify::token_warnings(
+            &config.settings,
+            &creds,
+            Local::now().date_naive(),
+        ));
+        summaries.push(format!(
+            "Token for {}@{} is {}.",
+            creds.username,
+            creds.host,
+            if offline {
+                "fine as of its last verification"
+            } else {
+                "valid"
+            }
+        ));
+    }
+    report.summary = summaries.join(" ");
+    Ok(report)
+}
+
+/// Passes when the account git pushes to the remote as is the account of the profile whose
+/// email authored the commits being pushed, as far as gitp can tell.
+fn check_push(
+    remote: Option<String>,
+    url: Option<String>,
+    stdin: bool,
+    offline: bool,
+) -> Result<Report> {
+    require_git("Checking a push")?;
+    let config = Config::load_cached().context("Failed to load configuration.")?;
+    let url = match (url, remote) {
+        (Some(url), _) => url,
+        (None, Some(remote)) => get_remote_effective_url(&remote)?.unwrap_or(remote),
+        (None, None) => {
+            get_primary_remote()?
+                .context("This repository has no remote to push to.")?
+                .effective_url
+        }
+    };
+    let Some(remote) = parse_remote_url(&url) else {
+        return Ok(Report {
+            summary: format!("{} is not on a Git host; nothing to check.", url),
+            problems: Vec::new(),
+        });
+    };
+
+    let emails = if stdin {
+        let mut input = String::new();
+        std::io::stdin()
+            .read_to_string(&mut input)
+            .context("Failed to read the refs being pushed.")?;
+        let revs = pushed_revs(&input);
+        if revs.is_empty() {
+            Vec::new()
+        } else {
+            author_emails(&revs)?
+        }
+    } else {
+        get_effective_git_config("user.email")?
+            .into_iter()
+            .collect()
+    };
+    if authoring_profiles(&config, &emails).is_empty() {
+        return Ok(push_report(&config, &remote.host, &emails, None));
+    }
+
+    let login = match (&remote.transport, offline) {
+        (Transport::Https, true) => remote.user.clone(),
+        (_, true) | (Transport::Other(_), _) => None,
+        (Transport::Https, false) => https_login(&config, &remote)?,
+        (Transport::Ssh, false) => {
+            let ssh_command = match std::env::var("GIT_SSH_COMMAND") {
+                Ok(command) if !command.is_empty() => Some(command),
+                _ => get_effective_git_config("core.sshCommand")?,
+            };
+            with_spinner(&format!("Connecting to {}...", remote.host), || {
+                authenticated_login(
+                    ssh_command.as_deref(),
+                    remote.user.as_deref(),
+                    &remote.host,
+                    remote.port,
+                )
+            })?
+        }
+    };
+    Ok(push_report(
+        &config,
+        &remote.host,
+        &emails,
+        login.as_deref(),
+    ))
+}
+
+/// The account git's credential helpers authenticate to an HTTPS remote as: the account
+/// recorded for the only profile credentials with the username the helper answered with,
+/// else the owner of the token where the provider can tell, else that username.
+fn https_login(config: &Config, remote: &RemoteUrl) -> Result<Option<String>> {
+    let Some((username, password)) =
+        credential_fill("https", &remote.host, remote.user.as_deref())?
+    else {
+        return Ok(None);
+    };
+    let recorded: Vec<&HttpsCredentials> = config
+        .profiles
+        .values()
+        .filter_map(|p| p.https_for(&remote.host))
+        .filter(|c| c.username == username)
+        .collect();
+    if let [creds] = recorded[..] {
+        if let Some(account) = &creds.account {
+            return Ok(Some(account.login.clone()));
+        }
+    }
+    let info = with_spinner(&format!("Contacting {}...", remote.host), || {
+        verify::inspect_token(&config.settings, &remote.host, &password)
+    });
+    Ok(Some(
+        info.ok().and_then(|info| info.login).unwrap_or(username),
+    ))
+}
+
+/// `git log` arguments for the commits a pre-push hook's input pushes that no remote has yet.
+/// Each line reads `<local ref> <local sha> <remote ref> <remote sha>`; deletions push nothing.
+fn pushed_revs(input: &str) -> Vec<String> {
+    let mut revs: Vec<String> = input
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .filter(|sha| !sha.chars().all(|c| c == '0'))
+        .map(str::to_string)
+        .collect();
+    if !revs.is_empty() {
+        revs.extend(["--not".to_string(), "--remotes".to_string()]);
+    }
+    revs
+}
+
+/// Profiles whose email is one of `emails`.
+fn authoring_profiles<'a>(config: &'a Config, emails: &[String]) -> Vec<&'a Profile> {
+    config
+        .profiles
+        .values()
+        .filter(|p| {
+            emails
+                .iter()
+                .any(|e| e.eq_ignore_ascii_case(&p.git_config.user_email))
+        })
+        .collect()
+}
+
+/// Compares the account a push to `host` authenticates as (`login`, when known) with the
+/// accounts the profiles authoring `emails` use on that host.
+fn push_report(config: &Config, host: &str, emails: &[String], login: Option<&str>) -> Report {
+    let mut report = Report::default();
+    let owners = authoring_profiles(config, emails);
+    if owners.is_empty() {
+        report.summary = format!("No profile authored the commits pushed to {}.", host);
+        return report;
+    }
+    let names = owners
+        .iter()
+        .map(|p| format!("'{}'", p.name))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let is_owner = |profile: &Profile| owners.iter().any(|o| o.name == profile.name);
+
+    if let Some(alias) = config
+        .find_profiles_for_host(host)
+        .into_iter()
+        .find(|m| m.via == HostMatch::SshAlias && !is_owner(m.profile))
+    {
+        report.problems.push(format!(
+            "{} is the SSH host alias of profile '{}', but the commits are by profile {}.",
+            host, alias.profile.name, names
+        ));
+        return report;
+    }
+    let real_host = config.real_host(host);
+    let Some(login) = login else {
+        report.summary = format!(
+            "Commits by profile {} go to {}; the account couldn't be told.",
+            names, real_host
+        );
+        return report;
+    };
+    let uses_login = |p: &Profile| {
+        p.https_for(real_host).is_some_and(|c| {
+            c.login().eq_ignore_ascii_case(login) || c.username.eq_ignore_ascii_case(login)
+        })
+    };
+
+    if let Some(owner) = owners.iter().find(|p| uses_login(p)) {
+        report.summary = format!(
+            "Pushing to {} as '{}', the account of profile '{}'.",
+            real_host, login, owner.name
+        );
+    } else if let Some(other) = config
+        .profiles
+        .values()
+        .find(|p| !is_owner(p) && uses_login(p))
+    {
+        report.problems.push(format!(
+            "Pushing to {} as '{}', the account of profile '{}', but the commits are by \
+             profile {}.",
+            real_host, login, other.name, names
+        ));
+    } else if let Some((owner, creds)) = owners
+        .iter()
+        .find_map(|p| p.https_for(real_host).map(|c| (p, c)))
+    {
+        report.problems.push(format!(
+            "Pushing to {} as '{}', but profile '{}' uses the account '{}' there.",
+            real_host,
+            login,
+            owner.name,
+            creds.login()
+        ));
+    } else {
+        report.summary = format!(
+            "Pushing to {} as '{}'; no profile records an account there.",
+            real_host, login
+        );
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CredentialType;
+
+    #[test]
+    fn test_identity_report() {
+        let mut config = Config::default();
+        let profile = Profile::new(
+            "work".to_string(),
+            "John".to_string(),
+            "john@company.com".to_string(),
+        );
+        config.profiles.insert("work".to_string(), profile);
+
+        let ok = identity_report(&config, Some("work"), Some("John@Company.com"));
+        assert!(ok.problems.is_empty());
+        assert!(ok.summary.contains("profile 'work'"));
+
+        for (profile, email) in [
+            (Some("work"), Some("john@home.org")),
+            (Some("work"), None),
+            (Some("gone"), Some("john@company.com")),
+            (None, Some("john@company.com")),
+        ] {
+            assert_eq!(
+                identity_report(&config, profile, email).problems.len(),
+                1,
+                "{:?} {:?}",
+                profile,
+                email
+            );
+        }
+    }
+
+    #[test]
+    fn test_push_report() {
+        let mut config = Config::default();
+        for (name, email, username) in [
+            ("work", "john@company.com", "john-acme"),
+            ("personal", "john@home.org", "johnny"),
+        ] {
+            let mut profile = Profile::new(name.to_string(), "John".to_string(), email.to_string());
+            profile.ssh_key_host = Some("github.com".to_string());
+            profile.ssh_host_alias = Some(format!("github.com-{}", name));
+            profile.https_credentials = vec![HttpsCredentials::new(
+                "github.com".to_string(),
+                username.to_string(),
+                CredentialType::Token("t".to_string()),
+            )];
+            config.profiles.insert(name.to_string(), profile);
+        }
+        let work = ["john@company.com".to_string()];
+
+        let ok = push_report(&config, "github.com-work", &work, Some("John-Acme"));
+        assert!(ok.problems.is_empty());
+        assert!(ok.summary.contains("profile 'work'"));
+        assert!(push_report(&config, "github.com", &work, None)
+            .problems
+            .is_empty());
+        assert!(push_report(
+            &config,
+            "github.com",
+            &["bot@ci".to_string()],
+            Some("johnny")
+        )
+        .problems
+        .is_empty());
+
+        for (host, login) in [
+            ("github.com-personal", None),
+            ("github.com", Some("johnny")),
+            ("github.com", Some("someone-else")),
+        ] {
+            assert_eq!(
+                push_report(&config, host, &work, login).problems.len(),
+                1,
+                "{} {:?}",
+                host,
+                login
+            );
+        }
+        assert_eq!(
+            pushed_revs("refs/heads/main 1111 refs/heads/main 2222\nrefs/heads/old 0000 refs/heads/old 3333\n"),
+            ["1111", "--not", "--remotes"]
+        );
+    }
+}
@@ -0,0 +1,175 @@
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use std::fs;
+
+use crate::cli::{CiCommands, CiProvider};
+use crate::commands::use_profile::apply_unattended;
+use crate::config::{Config, CredentialType, Profile};
+use crate::credentials::sealed::{decrypt_secrets, obtain_passphrase};
+use crate::utils::output::Paint;
+
+pub fn execute(command: CiCommands) -> Result<()> {
+    match command {
+        CiCommands::Setup {
+            provider,
+            manifest,
+            token_env,
+        } => setup(provider, &manifest, &token_env),
+    }
+}
+
+/// Applies the profile in `manifest` (a `gitp export` TOML file) to the runner's global git
+/// config, with the HTTPS token taken from the `token_env` variable.
+fn setup(provider: CiProvider, manifest: &str, token_env: &str) -> Result<()> {
+    provider.check_environment()?;
+    let content = fs::read_to_string(manifest)
+        .with_context(|| format!("Failed to read profile manifest '{}'", manifest))?;
+    let mut profile: Profile = toml::from_str(&content)
+        .with_context(|| format!("Failed to parse profile manifest '{}'", manifest))?;
+    let token = std::env::var(token_env)
+        .ok()
+        .filter(|token| !token.trim().is_empty());
+    // Before anything else is printed, so the token can't leak into the log
+    if let Some(token) = &token {
+        provider.mask(token);
+    }
+    prepare_profile(&mut profile, token, token_env)?;
+    // Values encrypted with `gitp export --encrypt-secrets` need GITP_EXPORT_PASSPHRASE
+    decrypt_secrets(&mut profile, || obtain_passphrase(false))
+        .with_context(|| format!("Failed to decrypt values in '{}'", manifest))?;
+    profile
+        .validate()
+        .with_context(|| format!("Profile manifest '{}' is invalid", manifest))?;
+
+    let mut config = Config::load().context("Failed to load configuration.")?;
+    let name = profile.name.clone();
+    config.profiles.insert(name.clone(), profile.clone());
+
+    provider.begin_group(&format!("gitp: applying profile '{}'", name));
+    let applied = apply_unattended(&mut config, &name);
+    provider.end_group();
+    applied?;
+    config
+        .save()
+        .context("Failed to save gitp configuration.")?;
+
+    println!("Applied profile {} to the runner:", name.success().bold());
+    println!("  user.name   {}", profile.git_config.user_name);
+    println!("  user.email  {}", profile.git_config.user_email);
+    if let Some(key) = &profile.git_config.user_signingkey {
+        println!("  signing key {}", key);
+    }
+    for creds in &profile.https_credentials {
+        println!(
+            "  HTTPS       {}@{} (token from {}, masked)",
+            creds.username, creds.host, token_env
+        );
+    }
+    Ok(())
+}
+
+/// Fits a manifest profile to a runner: the token comes from the environment, and SSH
+/// settings are dropped since runner keys come from the workflow, not from gitp.
+fn prepare_profile(profile: &mut Profile, token: Option<String>, token_env: &str) -> Result<()> {
+    if profile.name.trim().is_empty() {
+        bail!("The profile manifest has no name.");
+    }
+    profile.ssh_key = None;
+    profile.ssh_key_host = None;
+    profile.ssh_key_fingerprint = None;
+    profile.ssh_host_alias = None;
+
+    match (profile.https_credentials.as_mut_slice(), token) {
+        ([creds], Some(token)) => creds.credential_type = CredentialType::Token(token),
+        ([creds], None) => bail!(
+            "The profile manifest has HTTPS credentials for {}, but {} is not set. Pass the \
+             token from a secret, e.g. `env: {}: ${{{{ secrets.GIT_TOKEN }}}}`.",
+            creds.host,
+            token_env,
+            token_env
+        ),
+        ([], Some(_)) => println!(
+            "  {}: {} is set but the manifest has no HTTPS credentials; ignoring it.",
+            "Note".dimmed(),
+            token_env
+        ),
+        ([], None) => {}
+        (several, _) => bail!(
+            "The profile manifest has HTTPS credentials for {}, but a runner gets a single \
+             token from {}. Keep only the host the workflow pushes to.",
+            several
+                .iter()
+                .map(|c| c.host.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+            token_env
+        ),
+    }
+    Ok(())
+}
+
+impl CiProvider {
+    fn check_environment(self) -> Result<()> {
+        match self {
+            CiProvider::GithubActions => {
+                if std::env::var("GITHUB_ACTIONS").as_deref() != Ok("true") {
+                    bail!(
+                        "Not running in GitHub Actions (GITHUB_ACTIONS is not 'true'). \
+                         `gitp ci setup` changes the global git config; use `gitp import` \
+                         and `gitp use` on other machines."
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Keeps `secret` out of the job log from here on.
+    fn mask(self, secret: &str) {
+        match self {
+            CiProvider::GithubActions => println!("::add-mask::{}", secret),
+        }
+    }
+
+    fn begin_group(self, title: &str) {
+        match self {
+            CiProvider::GithubActions => println!("::group::{}", title),
+        }
+    }
+
+    fn end_group(self) {
+        match self {
+            CiProvider::GithubActions => println!("::endgroup::"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::HttpsCredentials;
+
+    #[test]
+    fn test_prepare_profile() -> Result<()> {
+        let mut profile = Profile::new(
+            "ci".to_string(),
+            "CI Bot".to_string(),
+            "ci@company.com".to_string(),
+        );
+        profile.ssh_key = Some("~/.ssh/id_work".into());
+        profile.https_credentials = vec![HttpsCredentials::new(
+            "github.com".to_string(),
+            "ci-bot".to_string(),
+            CredentialType::KeychainRef("ci-bot".to_string()),
+        )];
+        assert!(prepare_profile(&mut profile.clone(), None, "GITP_TOKEN").is_err());
+
+        prepare_profile(&mut profile, Some("ghp_secret".to_string()), "GITP_TOKEN")?;
+        assert!(profile.ssh_key.is_none());
+        assert_eq!(
+            profile.https_credentials[0].credential_type,
+            CredentialType::Token("ghp_secret".to_string())
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,190 @@
+// src/commands/clone.rs
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use std::path::PathBuf;
+
+use crate::commands::url::alias_clone_url;
+use crate::commands::use_profile::apply_git_identity;
+use crate::config::{Config, Profile};
+use crate::credentials::helper::{helper_command, helper_key};
+use crate::git::url::{parse_remote_url, Transport};
+use crate::git::{clone_repo, get_repo_root, in_repo, GitConfigScope};
+use crate::utils::output::Paint;
+
+/// How a repository is cloned for a profile.
+#[derive(Debug, PartialEq)]
+struct ClonePlan {
+    url: String,
+    /// Host whose credential requests gitp's helper answers during the clone
+    helper_host: Option<String>,
+}
+
+/// Clones `url` into `dir` (git's default directory when `None`) the way `profile` reaches
+/// the host, then applies the profile to the clone as `gitp use --local` would.
+pub fn execute(url: &str, dir: Option<String>, profile: &str, git_args: &[String]) -> Result<()> {
+    let mut config = Config::load().context("Failed to load configuration.")?;
+    let Some(profile) = config.profiles.get(profile).cloned() else {
+        bail!(
+            "Profile '{}' not found. Use '{}' to list available profiles.",
+            profile.warning(),
+            "gitp list".accent()
+        );
+    };
+    let dir = match dir {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(default_dir(url).with_context(|| {
+            format!(
+                "Can't tell a directory name from '{}'; pass one after the URL.",
+                url
+            )
+        })?),
+    };
+
+    config.require_approved(url, &profile.name)?;
+
+    let plan = clone_plan(url, &profile);
+    if plan.url != url {
+        println!("Cloning through {}", plan.url.accent());
+    }
+    let helper: Vec<String> = plan
+        .helper_host
+        .iter()
+        .map(|host| format!("{}={}", helper_key(host), helper_command(&profile.name)))
+        .collect();
+    clone_repo(&plan.url, &dir, &helper, git_args)?;
+
+    println!(
+        "Applying profile '{}' to {}...",
+        profile.name.accent(),
+        dir.display()
+    );
+    let repo = in_repo(&dir, || {
+        apply_git_identity(&mut config, &profile.name, GitConfigScope::Local)?;
+        get_repo_root()
+    })?;
+    config.state.record_switch(
+        &profile.name,
+        repo.map(|repo| repo.to_string_lossy().into_owned()),
+        Utc::now(),
+    );
+    config.save_switch()?;
+    println!(
+        "Cloned into {} with profile '{}'.",
+        dir.display(),
+        profile.name.success()
+    );
+    Ok(())
+}
+
+/// An HTTPS URL for a host the profile has credentials for keeps HTTPS, with the profile's
+/// username so its credentials are picked. Otherwise the URL goes through the profile's SSH
+/// host alias when it has one for the host, and is left alone when it doesn't.
+fn clone_plan(url: &str, profile: &Profile) -> ClonePlan {
+    let as_is = ClonePlan {
+        url: url.to_string(),
+        helper_host: None,
+    };
+    let Some(remote) = parse_remote_url(url) else {
+        return as_is;
+    };
+    if remote.transport == Transport::Https {
+        if let Some(creds) = profile.https_for(&remote.host) {
+            let url = match remote.user {
+                Some(_) => url.to_string(),
+                None => url.replacen(
+                    "://",
+                    &format!("://{}@", encode_userinfo(&creds.username)),
+                    1,
+                ),
+            };
+            return ClonePlan {
+                url,
+                helper_host: Some(remote.host),
+            };
+        }
+    }
+    match alias_clone_url(url, profile) {
+        Some(url) => ClonePlan {
+            url,
+            helper_host: None,
+        },
+        None => as_is,
+    }
+}
+
+/// `username` percent-encoded for the user part of a URL.
+fn encode_userinfo(username: &str) -> String {
+    let mut encoded = String::new();
+    for c in username.chars() {
+        match c {
+            '@' | ':' | '/' | '%' | '?' | '#' => encoded.push_str(&format!("%{:02X}", c as u8)),
+            c => encoded.push(c),
+        }
+    }
+    encoded
+}
+
+/// The directory git clones `url` into by default: the last path component without `.git`.
+fn default_dir(url: &str) -> Option<String> {
+    let url = url.trim().trim_end_matches('/');
+    let url = url
+        .strip_suffix(".git")
+        .unwrap_or(url)
+        .trim_end_matches('/');
+    url.rsplit(['/', ':'])
+        .next()
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{CredentialType, HttpsCredentials};
+
+    #[test]
+    fn test_clone_plan() {
+        let mut profile = Profile::new(
+            "work".to_string(),
+            "John Doe".to_string(),
+            "john@company.com".to_string(),
+        );
+        profile.ssh_key = Some("~/.ssh/id_work".into());
+        profile.ssh_key_host = Some("github.com".to_string());
+        profile.ssh_host_alias = Some("github.com-work".to_string());
+        assert_eq!(
+            clone_plan("https://github.com/acme/app", &profile).url,
+            "git@github.com-work:acme/app.git"
+        );
+        assert_eq!(
+            clone_plan("https://gitlab.com/acme/app", &profile).url,
+            "https://gitlab.com/acme/app"
+        );
+
+        profile.https_credentials = vec![HttpsCredentials::new(
+            "github.com".to_string(),
+            "john@company.com".to_string(),
+            CredentialType::Token("t".to_string()),
+        )];
+        assert_eq!(
+            clone_plan("https://github.com/acme/app", &profile),
+            ClonePlan {
+                url: "https://john%40company.com@github.com/acme/app".to_string(),
+                helper_host: Some("github.com".to_string()),
+            }
+        );
+        assert_eq!(
+            clone_plan("git@github.com:acme/app.git", &profile).url,
+            "git@github.com-work:acme/app.git"
+        );
+
+        for url in [
+            "https://github.com/acme/app.git",
+            "git@github.com:acme/app",
+            "https://github.com/acme/app/",
+            "/srv/git/app.git/",
+        ] {
+            assert_eq!(default_dir(url).as_deref(), Some("app"), "{}", url);
+        }
+    }
+}
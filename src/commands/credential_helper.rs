@@ -0,0 +1,259 @@
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+
+use anyhow::{Context, Result};
+
+use crate::commands::use_profile::PROFILE_MARKER_KEY;
+use crate::config::{is_host_pattern, profile_from_env, Config, RestrictedRemote, PROFILE_ENV_VAR};
+use crate::credentials::{cache, helper, resolve_token};
+use crate::git::{get_effective_git_config_regexp, regex_escape};
+use crate::utils::progress::set_quiet;
+
+/// Entry point for git's credential helper protocol. Only `get` is answered; gitp owns its
+/// tokens, so `store` (and unknown operations) are accepted and ignored, and `erase` only
+/// drops the token from the session cache. `GITP_PROFILE` takes precedence over the profile
+/// git passes in, which in turn takes precedence over the active profile.
+pub fn execute(profile_name: Option<String>, operation: String) -> Result<()> {
+    let mut input = String::new();
+    io::stdin()
+        .read_to_string(&mut input)
+        .context("Failed to read credential request from git.")?;
+
+    if operation != "get" && operation != "erase" {
+        return Ok(());
+    }
+    // git shares its terminal with the helper; keep it clean
+    set_quiet(true);
+
+    let config = Config::load_cached().context("Failed to load configuration.")?;
+    let forced = profile_from_env();
+    let Some(profile_name) = select_profile(&config, forced.as_deref(), profile_name, markers)
+    else {
+        return Ok(());
+    };
+    if let Some(forced) = forced.filter(|forced| *forced != profile_name) {
+        eprintln!(
+            "gitp: {} names unknown profile '{}'; using '{}'",
+            PROFILE_ENV_VAR, forced, profile_name
+        );
+    }
+    // Unknown profiles or hosts produce no answer so git falls through to its other helpers.
+    let Some(profile) = config.profiles.get(&profile_name) else {
+        return Ok(());
+    };
+    let request = helper::parse_request(&input);
+    let matching = |exact: bool| {
+        profile.https_credentials.iter().find(|creds| {
+            helper::request_matches(&request, creds) && (!exact || !is_host_pattern(&creds.host))
+        })
+    };
+    // A host's own credentials win over those for a pattern covering it
+    let Some(creds) = matching(true).or_else(|| matching(false)) else {
+        return Ok(());
+    };
+    let host = request
+        .get("host")
+        .map(String::as_str)
+        .unwrap_or(&creds.host);
+
+    // git erases credentials the server rejected; a cached token would be offered again
+    if operation == "erase" {
+        if creds.cache_minutes.is_some() {
+            let _ = cache::forget(creds);
+        }
+        return Ok(());
+    }
+    if let Some((rule, path_known)) = restriction(&config, &request, host, &profile_name) {
+        if path_known {
+            eprintln!(
+                "gitp: profile '{}' isn't approved for {} (restricted_remotes '{}')",
+                profile_name, host, rule.remote
+            );
+        } else {
+            eprintln!(
+                "gitp: git didn't say which repository on {} it needs credentials for, and \
+                 restricted_remotes '{}' may not approve profile '{}'; set \
+                 credential.https://{}/.useHttpPath to true (gitp use does)",
+                host, rule.remote, profile_name, host
+            );
+        }
+        // Stop git from asking other helpers, which could answer with another account
+        io::stdout()
+            .write_all(b"quit=1\n")
+            .context("Failed to write to git.")?;
+        return Ok(());
+    }
+    // Cache failures (no daemon support, no socket directory) fall back to the secret store
+    let cached = match creds.cache_minutes {
+        Some(_) => cache::get(host, &creds.username).ok().flatten(),
+        None => None,
+    };
+    let token = match cached {
+        Some(token) => token,
+        None => {
+            let token = resolve_token(&config.settings, creds).with_context(|| {
+                format!(
+                    "gitp: failed to read the token for {}@{}",
+                    creds.username, creds.host
+                )
+            })?;
+            if let Some(minutes) = creds.cache_minutes {
+                let _ = cache::store(host, &creds.username, &token, minutes);
+            }
+            token
+        }
+    };
+    io::stdout()
+        .write_all(helper::format_response(&creds.username, &token).as_bytes())
+        .context("Failed to write credentials to git.")?;
+
+    Ok(())
+}
+
+/// The profile that answers: `forced` (`GITP_PROFILE`) when it names a known profile, else the
+/// profile git passed in, else the one gitp last applied where git runs the helper. That is the
+/// last `gitp.profile` marker git sees (local, from a directory rule, or global) naming a known
+/// profile, or the recorded current profile.
+fn select_profile(
+    config: &Config,
+    forced: Option<&str>,
+    requested: Option<String>,
+    markers: impl FnOnce() -> Vec<String>,
+) -> Option<String> {
+    if let Some(forced) = forced.filter(|name| config.profiles.contains_key(*name)) {
+        return Some(forced.to_string());
+    }
+    if requested.is_some() {
+        return requested;
+    }
+    markers()
+        .into_iter()
+        .rev()
+        .chain(config.state.current_profile.clone())
+        .find(|name| config.profiles.contains_key(name))
+}
+
+/// Values of the `gitp.profile` markers git sees here, in config order.
+fn markers() -> Vec<String> {
+    get_effective_git_config_regexp(&format!("^{}$", regex_escape(PROFILE_MARKER_KEY)))
+        .map(|entries| entries.into_iter().map(|entry| entry.value).collect())
+        .unwrap_or_default()
+}
+
+/// The restricted_remotes rule that keeps `profile` from answering, and whether git sent the
+/// repository path. Git only sends it with credential.useHttpPath; without it, any rule on the
+/// host that doesn't approve the profile applies, since the repository could be covered.
+fn restriction<'a>(
+    config: &'a Config,
+    request: &BTreeMap<String, String>,
+    host: &str,
+    profile: &str,
+) -> Option<(&'a RestrictedRemote, bool)> {
+    match request.get("path") {
+        Some(path) => config
+            .restriction_for(&format!("https://{}/{}", host, path), profile)
+            .map(|rule| (rule, true)),
+        None => config
+            .restriction_on_host(host, profile)
+            .map(|rule| (rule, false)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Profile;
+
+    fn config() -> Config {
+        let mut config = Config::default();
+        for name in ["home", "work"] {
+            let profile = Profile::new(
+                name.to_string(),
+                "John Doe".to_string(),
+                "john@example.com".to_string(),
+            );
+            config.profiles.insert(name.to_string(), profile);
+        }
+        config
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_select_profile_from_markers() {
+        let mut config = config();
+        config.state.current_profile = Some("work".to_string());
+
+        // The last marker wins: local after global
+        let select = |markers: &[&str]| select_profile(&config, None, None, || names(markers));
+        assert_eq!(select(&["home", "work"]).as_deref(), Some("work"));
+        // A stale local marker doesn't hide the global one
+        assert_eq!(select(&["home", "ghost"]).as_deref(), Some("home"));
+        // Without a known marker, the recorded current profile
+        assert_eq!(select(&["ghost"]).as_deref(), Some("work"));
+        assert_eq!(select(&[]).as_deref(), Some("work"));
+
+        config.state.current_profile = Some("gone".to_string());
+        let select = |markers: &[&str]| select_profile(&config, None, None, || names(markers));
+        assert_eq!(select(&["ghost"]), None);
+    }
+
+    #[test]
+    fn test_select_profile_precedence() {
+        let config = config();
+        let markers = || names(&["home"]);
+
+        // GITP_PROFILE wins over the profile git passes in and the markers
+        assert_eq!(
+            select_profile(&config, Some("work"), Some("home".to_string()), markers).as_deref(),
+            Some("work")
+        );
+        assert_eq!(
+            select_profile(&config, Some("work"), None, markers).as_deref(),
+            Some("work")
+        );
+        // An unknown GITP_PROFILE is ignored
+        assert_eq!(
+            select_profile(&config, Some("ghost"), None, markers).as_deref(),
+            Some("home")
+        );
+        // The profile git passes in is used as is; unknown ones produce no answer later
+        assert_eq!(
+            select_profile(&config, None, Some("ghost".to_string()), markers).as_deref(),
+            Some("ghost")
+        );
+    }
+
+    #[test]
+    fn test_restriction_without_path() {
+        let mut config = config();
+        config.settings.restricted_remotes = vec![RestrictedRemote {
+            remote: "github.com/acme".to_string(),
+            profiles: names(&["work"]),
+        }];
+        let request = |path: Option<&str>| {
+            let mut request = BTreeMap::new();
+            request.insert("host".to_string(), "github.com".to_string());
+            if let Some(path) = path {
+                request.insert("path".to_string(), path.to_string());
+            }
+            request
+        };
+        let check = |path: Option<&str>, profile: &str| {
+            restriction(&config, &request(path), "github.com", profile).map(|(_, known)| known)
+        };
+
+        assert_eq!(check(Some("acme/app.git"), "home"), Some(true));
+        assert_eq!(check(Some("john/dotfiles.git"), "home"), None);
+        assert_eq!(check(Some("acme/app.git"), "work"), None);
+        // Without the path the repository could be covered
+        assert_eq!(check(None, "home"), Some(false));
+        assert_eq!(check(None, "work"), None);
+        assert_eq!(
+            restriction(&config, &request(None), "gitlab.com", "home").map(|(_, known)| known),
+            None
+        );
+    }
+}
@@ -0,0 +1,772 @@
+use anyhow::{bail, Context, Result};
+use chrono::{Local, Utc};
+use colored::Colorize;
+
+use crate::cli::{CredentialsCommands, HelperStore};
+use crate::commands::credential_helper;
+use crate::commands::list::print_https_credentials;
+use crate::config::{
+    is_host_pattern, same_host, Config, CredentialType, HttpsCredentials, Profile, SecretStoreKind,
+    Settings,
+};
+use crate::credentials::{
+    cache, delete_stored_token, resolve_token, store_label, store_secure_token, verify,
+};
+use crate::git::{helper_approve, helper_fill};
+use crate::utils::output::{theme, Paint};
+use crate::utils::progress::with_spinner;
+use crate::utils::prompt::{prompt_required, Confirm, Password};
+use crate::utils::terminal::{can_prompt, require_terminal};
+
+pub fn execute(command: CredentialsCommands) -> Result<()> {
+    match command {
+        CredentialsCommands::Set {
+            profile_name,
+            host,
+            username,
+            token,
+            in_config,
+        } => set_credentials(profile_name, host, username, token, in_config),
+        CredentialsCommands::Show { profile_name } => show_credentials(profile_name),
+        CredentialsCommands::Remove {
+            profile_name,
+            host,
+            force,
+        } => remove_credentials(profile_name, host, force),
+        CredentialsCommands::Verify { profile_name, host } => {
+            verify_credentials(profile_name, host)
+        }
+        CredentialsCommands::Cache {
+            profile_name,
+            minutes,
+            host,
+        } => set_cache(profile_name, minutes, host),
+        CredentialsCommands::Flush { profile_name, host } => flush(profile_name, host),
+        CredentialsCommands::Migrate { profiles, dry_run } => migrate(profiles, dry_run),
+        CredentialsCommands::Import {
+            profile_name,
+            from,
+            host,
+            username,
+        } => import(profile_name, from, host, username),
+        CredentialsCommands::Export {
+            profile_name,
+            to,
+            host,
+        } => export(profile_name, to, host),
+        CredentialsCommands::Get => credential_helper::execute(None, "get".to_string()),
+        CredentialsCommands::Store => credential_helper::execute(None, "store".to_string()),
+        CredentialsCommands::Erase => credential_helper::execute(None, "erase".to_string()),
+    }
+}
+
+/// Sets a profile's HTTPS token for a host, adding the host when the profile has no credentials
+/// for it yet. Host and username default to the current ones when the profile has credentials
+/// for a single host, so rotating a token only needs the new token.
+fn set_credentials(
+    profile_name: String,
+    host: Option<String>,
+    username: Option<String>,
+    token: Option<String>,
+    in_config: bool,
+) -> Result<()> {
+    let mut config = Config::load().context("Failed to load configuration.")?;
+    let settings = config.settings.clone();
+    let Some(profile) = config.profiles.get_mut(&profile_name) else {
+        bail!("Profile '{}' not found.", profile_name.warning());
+    };
+
+    let host = match host.as_deref().map(str::trim).filter(|h| !h.is_empty()) {
+        Some(host) => host.to_string(),
+        None => match profile.https_credentials.as_slice() {
+            [only] => only.host.clone(),
+            [] if can_prompt() => {
+                prompt_required("HTTPS host (e.g., github.com)", None, "HTTPS host")?
+            }
+            [] => bail!(
+                "Profile '{}' has no HTTPS credentials yet. Pass --host and --username.",
+                profile_name.warning()
+            ),
+            _ => bail!(
+                "Profile '{}' has HTTPS credentials for {}; pick one with --host.",
+                profile_name.warning(),
+                profile.https_hosts().join(", ")
+            ),
+        },
+    };
+    let old = profile
+        .https_credentials
+        .iter()
+        .find(|old| same_host(&old.host, &host))
+        .cloned();
+    let current_username = old.as_ref().map(|old| old.username.clone());
+    let username = match username.as_deref().map(str::trim).filter(|u| !u.is_empty()) {
+        Some(username) => username.to_string(),
+        None => match current_username {
+            Some(username) => username,
+            None if can_prompt() => prompt_required(
+                &format!("HTTPS username for host '{}'", host),
+                None,
+                "HTTPS username",
+            )?,
+            None => bail!("Pass --username for host '{}'.", host),
+        },
+    };
+    let token = match token {
+        Some(token) => token,
+        None => {
+            require_terminal("Entering an HTTPS token", "Pass it with --token.")?;
+            Password::with_theme(&theme())
+                .with_prompt(format!("HTTPS token for {}@{}", username, host))
+                .with_confirmation("Confirm HTTPS token", "Tokens do not match.")
+                .interact()
+                .context("Failed to get HTTPS token input.")?
+        }
+    };
+    let token = token.trim();
+    if token.is_empty() {
+        bail!(
+            "HTTPS token cannot be empty. Use 'gitp credentials remove' to remove the credentials."
+        );
+    }
+
+    let credential_type = store_https_token(&settings, &host, &username, token, !in_config)?;
+    let mut creds = HttpsCredentials::new(host, username, credential_type);
+    if let Some(old) = &old {
+        creds.cache_minutes = old.cache_minutes;
+        creds.required_scopes = old.required_scopes.clone();
+    }
+    replace_https_credentials(&settings, &mut profile.https_credentials, creds);
+    config.save().context("Failed to save configuration.")?;
+    // The old token must not be answered from the session cache
+    if let Some(old) = old.filter(|old| old.cache_minutes.is_some()) {
+        let _ = cache::forget(&old);
+    }
+    println!(
+        "Check it with '{}'.",
+        format!("gitp credentials verify {}", profile_name).accent()
+    );
+    Ok(())
+}
+
+fn show_credentials(profile_name: String) -> Result<()> {
+    let config = Config::load().context("Failed to load configuration.")?;
+    let Some(profile) = config.profiles.get(&profile_name) else {
+        bail!("Profile '{}' not found.", profile_name.warning());
+    };
+    if profile.https_credentials.is_empty() {
+        println!(
+            "Profile '{}' does not have HTTPS credentials.",
+            profile_name.accent()
+        );
+        return Ok(());
+    }
+    println!("HTTPS credentials of profile '{}':", profile_name.accent());
+    for creds in &profile.https_credentials {
+        print_https_credentials(&config.settings, creds);
+    }
+    Ok(())
+}
+
+/// Removes a profile's HTTPS credentials for one host, deleting the token from its store.
+/// `--host` picks the host, and is required when the profile has credentials for several.
+fn remove_credentials(profile_name: String, host: Option<String>, force: bool) -> Result<()> {
+    let mut config = Config::load().context("Failed to load configuration.")?;
+    let settings = config.settings.clone();
+    let Some(profile) = config.profiles.get_mut(&profile_name) else {
+        bail!("Profile '{}' not found.", profile_name.warning());
+    };
+    if profile.https_credentials.is_empty() {
+        println!(
+            "Profile '{}' does not have HTTPS credentials.",
+            profile_name.accent()
+        );
+        return Ok(());
+    }
+    let creds = profile.select_https(host.as_deref())?.clone();
+
+    if !force {
+        require_terminal(
+            "Confirming the removal",
+            "Pass --force to remove the credentials without asking.",
+        )?;
+        let confirmed = Confirm::with_theme(&theme())
+            .with_prompt(format!(
+                "Remove the HTTPS credentials for {}@{} from profile '{}'?",
+                creds.username,
+                creds.host,
+                profile_name.accent()
+            ))
+            .default(false)
+            .interact()
+            .context("Failed to get confirmation for removal.")?;
+        if !confirmed {
+            println!("Removal cancelled.");
+            return Ok(());
+        }
+    }
+
+    remove_https_credentials(&settings, &mut profile.https_credentials, &creds.host);
+    config.save().context("Failed to save configuration.")?;
+    if creds.cache_minutes.is_some() {
+        let _ = cache::forget(&creds);
+    }
+    println!(
+        "{} HTTPS credentials for host '{}' from profile '{}'.",
+        "Removed".warning(),
+        creds.host.success(),
+        profile_name.accent()
+    );
+    Ok(())
+}
+
+/// Moves the HTTPS tokens of the selected profiles (default: all) into the configured
+/// `secret_store`, from config.toml or from the other store.
+fn migrate(names: Vec<String>, dry_run: bool) -> Result<()> {
+    let mut config = Config::load().context("Failed to load configuration.")?;
+    let settings = config.settings.clone();
+    let selected = if names.is_empty() {
+        let mut all: Vec<String> = config.profiles.keys().cloned().collect();
+        all.sort();
+        all
+    } else {
+        config.select_profiles(&names)?
+    };
+    let target = store_name(settings.secret_store);
+
+    let mut moved = 0;
+    let pending: Vec<(String, HttpsCredentials)> = selected
+        .iter()
+        .flat_map(|name| {
+            config.profiles[name]
+                .https_credentials
+                .iter()
+                .map(move |creds| (name.clone(), creds.clone()))
+        })
+        .collect();
+    for (name, creds) in &pending {
+        if in_store(settings.secret_store, &creds.credential_type) {
+            continue;
+        }
+        let from = store_label(&creds.credential_type);
+        if dry_run {
+            println!(
+                "  {}: {}@{} would move from the {} to the {}.",
+                name.accent(),
+                creds.username,
+                creds.host,
+                from,
+                target
+            );
+            moved += 1;
+            continue;
+        }
+
+        let token = match resolve_token(&settings, creds) {
+            Ok(token) => token,
+            Err(e) => {
+                eprintln!(
+                    "  {}: {}: could not read the token for {}@{}: {:#}",
+                    "Warning".warning(),
+                    name,
+                    creds.username,
+                    creds.host,
+                    e
+                );
+                continue;
+            }
+        };
+        let stored = store_secure_token(&settings, &creds.host, &creds.username, &token)?;
+        if !in_store(settings.secret_store, &stored) {
+            // The keyring failed and keyring_fallback put the token somewhere else
+            if stored != creds.credential_type {
+                let _ = delete_stored_token(&settings, &creds.host, &stored);
+            }
+            eprintln!(
+                "  {}: {}: the token stays in the {}.",
+                "Warning".warning(),
+                name,
+                from
+            );
+            continue;
+        }
+        let mut migrated = creds.clone();
+        migrated.credential_type = stored;
+        let profile = config
+            .profiles
+            .get_mut(name.as_str())
+            .expect("selected profile exists");
+        replace_https_credentials(&settings, &mut profile.https_credentials, migrated);
+        println!(
+            "  {}: moved the token for {}@{} from the {} to the {}.",
+            name.accent(),
+            creds.username,
+            creds.host,
+            from,
+            target
+        );
+        moved += 1;
+    }
+
+    if moved == 0 {
+        println!("Every HTTPS token is already in the {}.", target);
+    } else if dry_run {
+        println!(
+            "{} token(s) would move. Run without --dry-run to move them.",
+            moved
+        );
+    } else {
+        config.save().context("Failed to save configuration.")?;
+        println!("Moved {} token(s) to the {}.", moved, target);
+    }
+    Ok(())
+}
+
+/// Whether `credential_type` already keeps its token in the `kind` store.
+fn in_store(kind: SecretStoreKind, credential_type: &CredentialType) -> bool {
+    matches!(
+        (kind, credential_type),
+        (SecretStoreKind::Keyring, CredentialType::KeychainRef(_))
+            | (SecretStoreKind::File, CredentialType::FileStoreRef(_))
+    )
+}
+
+fn store_name(kind: SecretStoreKind) -> &'static str {
+    match kind {
+        SecretStoreKind::Keyring => "keychain",
+        SecretStoreKind::File => "encrypted file store",
+    }
+}
+
+/// Keeps an HTTPS token in the configured secret store, or in config.toml when `secure` is
+/// false, and says where it went.
+pub(crate) fn store_https_token(
+    settings: &Settings,
+    host: &str,
+    username: &str,
+    token: &str,
+    secure: bool,
+) -> Result<CredentialType> {
+    if !secure {
+        println!(
+            "  Set HTTPS token for {}@{} (stored in config file).",
+            username.accent(),
+            host.success()
+        );
+        return Ok(CredentialType::Token(token.to_string()));
+    }
+    let stored = store_secure_token(settings, host, username, token)?;
+    println!(
+        "  Stored HTTPS token for {}@{} in {}.",
+        username.accent(),
+        host.success(),
+        store_label(&stored)
+    );
+    Ok(stored)
+}
+
+/// Puts `new` in place of a profile's HTTPS credentials for the same host, or adds it for a new
+/// host. The previous stored token is deleted unless the new one overwrote the same entry.
+pub(crate) fn replace_https_credentials(
+    settings: &Settings,
+    slot: &mut Vec<HttpsCredentials>,
+    new: HttpsCredentials,
+) {
+    match slot.iter_mut().find(|old| same_host(&old.host, &new.host)) {
+        Some(old) => {
+            if old.host != new.host || old.credential_type != new.credential_type {
+                delete_token_reporting(settings, old, "previous token");
+            }
+            *old = new;
+        }
+        None => slot.push(new),
+    }
+}
+
+/// Takes a profile's HTTPS credentials for `host`, deleting their stored token.
+pub(crate) fn remove_https_credentials(
+    settings: &Settings,
+    slot: &mut Vec<HttpsCredentials>,
+    host: &str,
+) -> Option<HttpsCredentials> {
+    let index = slot.iter().position(|c| same_host(&c.host, host))?;
+    let creds = slot.remove(index);
+    delete_token_reporting(settings, &creds, "token");
+    Some(creds)
+}
+
+/// Indexes of the profile's HTTPS credentials a command acts on: those for `host`, or all of
+/// them when no host is given.
+fn selected_https(profile: &Profile, host: Option<&str>, action: &str) -> Result<Vec<usize>> {
+    if profile.https_credentials.is_empty() {
+        bail!(
+            "Profile '{}' has no HTTPS credentials to {}.",
+            profile.name.warning(),
+            action
+        );
+    }
+    match host {
+        Some(host) => {
+            profile.select_https(Some(host))?;
+            Ok(profile
+                .https_credentials
+                .iter()
+                .position(|c| same_host(&c.host, host))
+                .into_iter()
+                .collect())
+        }
+        None => Ok((0..profile.https_credentials.len()).collect()),
+    }
+}
+
+/// Deletes the token `creds` keeps in the keychain or file store, if any. A failure is only
+/// reported: the credentials are dropped from the profile either way.
+fn delete_token_reporting(settings: &Settings, creds: &HttpsCredentials, what: &str) {
+    let Some(account) = creds.credential_type.stored_account() else {
+        return;
+    };
+    let store = store_label(&creds.credential_type);
+    match delete_stored_token(settings, &creds.host, &creds.credential_type) {
+        Ok(_) => println!(
+            "  Successfully deleted {} for {}@{} from {}.",
+            what,
+            account.accent(),
+            creds.host.success(),
+            store
+        ),
+        Err(e) => eprintln!(
+            "  {}: Failed to delete {} for {}@{} from {}: {}. Please remove it manually if needed.",
+            "Warning".warning(),
+            what,
+            account.accent(),
+            creds.host.success(),
+            store,
+            e
+        ),
+    }
+}
+
+/// Checks the profile's HTTPS tokens (only the one for `host` when given) with their providers
+/// and records their scopes and expiry.
+fn verify_credentials(profile_name: String, host: Option<String>) -> Result<()> {
+    let mut config = Config::load().context("Failed to load configuration.")?;
+    let settings = config.settings.clone();
+
+    let profile = config
+        .profiles
+        .get_mut(&profile_name)
+        .ok_or_else(|| anyhow::anyhow!("Profile '{}' not found.", profile_name.warning()))?;
+    for index in selected_https(profile, host.as_deref(), "verify")? {
+        verify_token(&settings, &mut profile.https_credentials[index])?;
+    }
+
+    config
+        .save()
+        .context("Failed to save configuration after verifying credentials.")?;
+    println!(
+        "Token details recorded for profile '{}'.",
+        profile_name.success()
+    );
+
+    Ok(())
+}
+
+fn verify_token(settings: &Settings, creds: &mut HttpsCredentials) -> Result<()> {
+    let token = resolve_token(settings, creds).with_context(|| {
+        format!(
+            "Failed to read the token for {}@{}",
+            creds.username, creds.host
+        )
+    })?;
+
+    println!(
+        "Verifying HTTPS token for {}@{}...",
+        creds.username.accent(),
+        creds.host.success()
+    );
+    let info = with_spinner(&format!("Contacting {}...", creds.host), || {
+        verify::inspect_token(settings, &creds.host, &token)
+    })?;
+
+    if let Some(login) = &info.login {
+        match &info.display_name {
+            Some(name) if !name.is_empty() => println!(
+                "  {} {} ({})",
+                "Authenticated as:".accent(),
+                login.success(),
+                name
+            ),
+            _ => println!("  {} {}", "Authenticated as:".accent(), login.success()),
+        }
+        if login != &creds.username {
+            println!(
+                "  {}: The token belongs to '{}' but the profile's HTTPS username is '{}'.",
+                "Warning".warning(),
+                login,
+                creds.username
+            );
+        }
+    }
+    match &info.scopes {
+        Some(scopes) if !scopes.is_empty() => {
+            println!("  {} {}", "Scopes:".accent(), scopes.join(", "))
+        }
+        Some(_) => println!("  {} {}", "Scopes:".accent(), "none".warning()),
+        None => println!(
+            "  {} {}",
+            "Scopes:".accent(),
+            "not reported (fine-grained token)".dimmed()
+        ),
+    }
+    match info.expires_at {
+        Some(expires_at) => println!("  {} {}", "Expires:".accent(), expires_at),
+        None => println!("  {} {}", "Expires:".accent(), "never".dimmed()),
+    }
+
+    info.record(creds, Utc::now());
+
+    for warning in verify::token_warnings(settings, creds, Local::now().date_naive()) {
+        println!("  {}: {}", "Warning".warning(), warning);
+    }
+    Ok(())
+}
+
+fn set_cache(profile_name: String, minutes: u32, host: Option<String>) -> Result<()> {
+    let mut config = Config::load().context("Failed to load configuration.")?;
+    let profile = config
+        .profiles
+        .get_mut(&profile_name)
+        .ok_or_else(|| anyhow::anyhow!("Profile '{}' not found.", profile_name.warning()))?;
+    let mut hosts = Vec::new();
+    for index in selected_https(profile, host.as_deref(), "cache")? {
+        let creds = &mut profile.https_credentials[index];
+        creds.cache_minutes = (minutes > 0).then_some(minutes);
+        // Tokens cached with the old setting would outlive it
+        let _ = cache::forget(creds);
+        hosts.push(creds.host.clone());
+    }
+    config.save().context("Failed to save configuration.")?;
+
+    let tokens = format!(
+        "The token{} of profile '{}' for {}",
+        if hosts.len() == 1 { "" } else { "s" },
+        profile_name.success(),
+        hosts.join(", ")
+    );
+    if minutes > 0 {
+        println!(
+            "{} stay{} in git's credential cache for {} minute{} after it is read.",
+            tokens,
+            if hosts.len() == 1 { "s" } else { "" },
+            minutes,
+            if minutes == 1 { "" } else { "s" }
+        );
+    } else {
+        println!(
+            "{} {} read from the store on every request.",
+            tokens,
+            if hosts.len() == 1 { "is" } else { "are" }
+        );
+    }
+    Ok(())
+}
+
+fn flush(profile_name: Option<String>, host: Option<String>) -> Result<()> {
+    let Some(profile_name) = profile_name else {
+        if host.is_some() {
+            bail!("--host needs a profile name.");
+        }
+        cache::flush_all()?;
+        println!("Dropped every cached token.");
+        return Ok(());
+    };
+    let config = Config::load().context("Failed to load configuration.")?;
+    let profile = config
+        .profiles
+        .get(&profile_name)
+        .ok_or_else(|| anyhow::anyhow!("Profile '{}' not found.", profile_name.warning()))?;
+    for index in selected_https(profile, host.as_deref(), "flush")? {
+        let creds = &profile.https_credentials[index];
+        cache::forget(creds)?;
+        println!(
+            "Dropped the cached token of {}@{}.",
+            creds.username, creds.host
+        );
+    }
+    Ok(())
+}
+
+/// The name `credential.helper` takes for `store`.
+fn helper_name(store: HelperStore) -> &'static str {
+    match store {
+        HelperStore::Manager => "manager",
+        HelperStore::Osxkeychain => "osxkeychain",
+    }
+}
+
+/// Copies the tokens `from` stores for the profile's HTTPS hosts (or for `host`) into the
+/// secret store and attaches them to the profile, so adopting gitp doesn't mean creating new
+/// tokens. Helpers store tokens per host, so host patterns are left out.
+fn import(
+    profile_name: String,
+    from: HelperStore,
+    host: Option<String>,
+    username: Option<String>,
+) -> Result<()> {
+    let mut config = Config::load().context("Failed to load configuration.")?;
+    let settings = config.settings.clone();
+    let Some(profile) = config.profiles.get_mut(&profile_name) else {
+        bail!("Profile '{}' not found.", profile_name.warning());
+    };
+    let helper = helper_name(from);
+
+    let host = host.as_deref().map(str::trim).filter(|h| !h.is_empty());
+    let wanted: Vec<(String, Option<String>)> = match host {
+        Some(host) if is_host_pattern(host) => bail!(
+            "{} stores tokens per host; pass a host instead of the pattern '{}'.",
+            helper,
+            host.warning()
+        ),
+        Some(host) => {
+            let recorded = profile
+                .https_credentials
+                .iter()
+                .find(|c| same_host(&c.host, host))
+                .map(|c| c.username.clone());
+            vec![(host.to_string(), username.or(recorded))]
+        }
+        None => {
+            let hosts: Vec<_> = profile
+                .https_credentials
+                .iter()
+                .filter(|c| !is_host_pattern(&c.host))
+                .map(|c| (c.host.clone(), Some(c.username.clone())))
+                .collect();
+            if hosts.is_empty() {
+                bail!(
+                    "Profile '{}' has no HTTPS hosts to import tokens for. Pick one with --host.",
+                    profile_name.warning()
+                );
+            }
+            hosts
+        }
+    };
+
+    let mut imported = 0;
+    for (host, username) in wanted {
+        let Some((username, token)) = helper_fill(helper, "https", &host, username.as_deref())?
+        else {
+            let account = match &username {
+                Some(username) => format!("{}@{}", username, host),
+                None => host.clone(),
+            };
+            println!("  {} has no token for {}.", helper, account.warning());
+            continue;
+        };
+        let old = profile
+            .https_credentials
+            .iter()
+            .find(|old| same_host(&old.host, &host))
+            .cloned();
+        if let Some(old) = old.as_ref().filter(|old| old.username == username) {
+            if resolve_token(&settings, old).is_ok_and(|current| current == token) {
+                println!(
+                    "  Profile '{}' already has the token for {}@{}.",
+                    profile_name,
+                    username.accent(),
+                    host.success()
+                );
+                continue;
+            }
+        }
+        let credential_type = store_https_token(&settings, &host, &username, &token, true)?;
+        let mut creds = HttpsCredentials::new(host, username, credential_type);
+        if let Some(old) = &old {
+            creds.cache_minutes = old.cache_minutes;
+            creds.required_scopes = old.required_scopes.clone();
+        }
+        replace_https_credentials(&settings, &mut profile.https_credentials, creds);
+        if let Some(old) = old.filter(|old| old.cache_minutes.is_some()) {
+            let _ = cache::forget(&old);
+        }
+        imported += 1;
+    }
+    if imported == 0 {
+        println!("No token imported from {}.", helper);
+        return Ok(());
+    }
+    config.save().context("Failed to save configuration.")?;
+    println!(
+        "Imported {} token{} from {} into profile '{}'. Check {} with '{}'.",
+        imported,
+        if imported == 1 { "" } else { "s" },
+        helper,
+        profile_name.success(),
+        if imported == 1 { "it" } else { "them" },
+        format!("gitp credentials verify {}", profile_name).accent()
+    );
+    println!(
+        "{}: {} keeps its copy; git asks whichever helper comes first.",
+        "Note".dimmed(),
+        helper
+    );
+    Ok(())
+}
+
+/// Stores the profile's HTTPS tokens (or the one for `host`) in `to` as well, for tools that
+/// only ask that helper.
+fn export(profile_name: String, to: HelperStore, host: Option<String>) -> Result<()> {
+    let config = Config::load().context("Failed to load configuration.")?;
+    let profile = config
+        .profiles
+        .get(&profile_name)
+        .ok_or_else(|| anyhow::anyhow!("Profile '{}' not found.", profile_name.warning()))?;
+    let helper = helper_name(to);
+    let mut exported = 0;
+    for index in selected_https(profile, host.as_deref(), "export")? {
+        let creds = &profile.https_credentials[index];
+        if is_host_pattern(&creds.host) {
+            println!(
+                "  Skipped {}: {} stores tokens per host.",
+                creds.host.warning(),
+                helper
+            );
+            continue;
+        }
+        let token = resolve_token(&config.settings, creds).with_context(|| {
+            format!(
+                "Failed to read the token for {}@{}.",
+                creds.username, creds.host
+            )
+        })?;
+        helper_approve(helper, "https", &creds.host, &creds.username, &token)?;
+        println!(
+            "  Stored the token for {}@{} in {}.",
+            creds.username.accent(),
+            creds.host.success(),
+            helper
+        );
+        exported += 1;
+    }
+    if exported == 0 {
+        println!("No token exported to {}.", helper);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_in_store() {
+        let keychain = CredentialType::KeychainRef("me".to_string());
+        let file = CredentialType::FileStoreRef("me".to_string());
+        let plain = CredentialType::Token("t".to_string());
+        assert!(in_store(SecretStoreKind::Keyring, &keychain));
+        assert!(!in_store(SecretStoreKind::Keyring, &file));
+        assert!(!in_store(SecretStoreKind::Keyring, &plain));
+        assert!(in_store(SecretStoreKind::File, &file));
+        assert!(!in_store(SecretStoreKind::File, &plain));
+    }
+}
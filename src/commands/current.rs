@@ -1,36 +1,380 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use colored::Colorize;
+use serde::Serialize;
 
-use crate::git::{get_git_config, GitConfigScope};
+use crate::cli::OutputFormat;
+use crate::commands::rules::fragment_profile;
+use crate::commands::use_profile::PROFILE_MARKER_KEY;
+use crate::config::{profile_from_env, Config, Profile, PROFILE_ENV_VAR};
+use crate::git::includes::{identity_include_rules, IncludeRule};
+use crate::git::url::parse_remote_url;
+use crate::git::{get_git_config, get_primary_remote, get_repo_root, GitConfigScope};
+use crate::utils::output::{print_json, Paint};
 
-fn print_config_value(label: &str, local_val: Option<String>, global_val: Option<String>) {
-    match (local_val, global_val) {
-        (Some(l), _) => println!("  {}: {} {}", label.dimmed(), l.green(), "(local)".cyan()),
-        (None, Some(g)) => println!("  {}: {} {}", label.dimmed(), g.green(), "(global)".blue()),
-        (None, None) => println!("  {}: {}", label.dimmed(), "Not set".yellow()),
+/// A value git uses here, and where it comes from.
+#[derive(Debug, Clone, Serialize)]
+struct SourcedValue {
+    value: String,
+    /// `local`, `directory_rule` or `global`
+    source: &'static str,
+}
+
+/// The value git uses: local, then a directory rule, then global.
+fn sourced(
+    local_val: Option<String>,
+    rule_val: Option<String>,
+    global_val: Option<String>,
+) -> Option<SourcedValue> {
+    [
+        (local_val, "local"),
+        (rule_val, "directory_rule"),
+        (global_val, "global"),
+    ]
+    .into_iter()
+    .find_map(|(value, source)| {
+        Some(SourcedValue {
+            value: value?,
+            source,
+        })
+    })
+}
+
+fn print_config_value(label: &str, value: Option<&SourcedValue>) {
+    match value {
+        Some(value) => println!(
+            "  {}: {} {}",
+            label.dimmed(),
+            value.value.success(),
+            format!("({})", value.source.replace('_', " ")).accent()
+        ),
+        None => println!("  {}: {}", label.dimmed(), "Not set".warning()),
     }
 }
 
-pub fn execute() -> Result<()> {
+/// The values git uses here, local overriding global.
+#[derive(Debug, Default)]
+struct EffectiveIdentity {
+    user_name: Option<String>,
+    user_email: Option<String>,
+    signing_key: Option<String>,
+    autocrlf: Option<String>,
+    eol: Option<String>,
+    /// Profile gitp last applied here (`gitp.profile`)
+    marker: Option<String>,
+}
+
+/// `gitp current --format json`.
+#[derive(Debug, Serialize)]
+struct CurrentJson {
+    user_name: Option<SourcedValue>,
+    user_email: Option<SourcedValue>,
+    signing_key: Option<SourcedValue>,
+    core_autocrlf: Option<SourcedValue>,
+    core_eol: Option<SourcedValue>,
+    /// Profile gitp last applied here
+    profile: Option<String>,
+    /// Profile of the directory rule that sets identity values here
+    directory_rule: Option<String>,
+    /// Profiles the values match exactly; only with `--match`
+    matching_profiles: Option<Vec<String>>,
+    /// Profile forced for the shell session by `GITP_PROFILE`
+    session_profile: Option<String>,
+    /// The email belongs to another profile than the remote's host
+    warning: Option<String>,
+}
+
+pub fn execute(match_profiles: bool, format: OutputFormat) -> Result<()> {
+    let cached = Config::load_cached()?;
+    let rule = directory_rule(&cached)?;
+    let value = |key: &str, from_rule: bool| -> Result<Option<SourcedValue>> {
+        let rule_val = rule
+            .as_ref()
+            .filter(|_| from_rule)
+            .and_then(|(_, rule)| rule.value(key))
+            .map(str::to_string);
+        Ok(sourced(
+            get_git_config(key, GitConfigScope::Local)?,
+            rule_val,
+            get_git_config(key, GitConfigScope::Global)?,
+        ))
+    };
+    let user_name = value("user.name", true)?;
+    let user_email = value("user.email", true)?;
+    let signing_key = value("user.signingkey", true)?;
+    let autocrlf = value("core.autocrlf", false)?;
+    let eol = value("core.eol", false)?;
+    let warning = match &user_email {
+        Some(email) => email_host_warning(&cached, &email.value)?,
+        None => None,
+    };
+    let rule_owner = rule.map(|(owner, _)| owner);
+
+    let matches = if match_profiles {
+        let effective = EffectiveIdentity {
+            user_name: user_name.as_ref().map(|v| v.value.clone()),
+            user_email: user_email.as_ref().map(|v| v.value.clone()),
+            signing_key: signing_key.as_ref().map(|v| v.value.clone()),
+            autocrlf: autocrlf.as_ref().map(|v| v.value.clone()),
+            eol: eol.as_ref().map(|v| v.value.clone()),
+            marker: applied_profile(rule_owner.as_ref())?,
+        };
+        let config = Config::load().context("Failed to load configuration.")?;
+        Some(rank_profiles(&config, &effective))
+    } else {
+        None
+    };
+
+    if format == OutputFormat::Json {
+        return print_json(&CurrentJson {
+            user_name,
+            user_email,
+            signing_key,
+            core_autocrlf: autocrlf,
+            core_eol: eol,
+            profile: applied_profile(rule_owner.as_ref())?,
+            directory_rule: rule_owner,
+            matching_profiles: matches.map(|ranked| {
+                ranked
+                    .into_iter()
+                    .filter(|(_, diff)| diff.is_empty())
+                    .map(|(name, _)| name)
+                    .collect()
+            }),
+            session_profile: profile_from_env(),
+            warning: warning.map(|w| w.message),
+        });
+    }
+
     println!("{}", "Current Git Configuration:".bold().underline());
+    print_config_value("User Name", user_name.as_ref());
+    print_config_value("User Email", user_email.as_ref());
+    print_config_value("Signing Key", signing_key.as_ref());
+    print_config_value("core.autocrlf", autocrlf.as_ref());
+    print_config_value("core.eol", eol.as_ref());
 
-    let user_name_local = get_git_config("user.name", GitConfigScope::Local)?;
-    let user_name_global = get_git_config("user.name", GitConfigScope::Global)?;
-    print_config_value("User Name", user_name_local, user_name_global);
+    if let Some(owner) = &rule_owner {
+        println!(
+            "  {}: {} {}",
+            "Directory rule".dimmed(),
+            format!("profile '{}'", owner).success(),
+            "(gitp rules list)".dimmed()
+        );
+    }
+
+    if let Some(warning) = warning {
+        println!("\n{} {}", "Warning:".warning().bold(), warning.message);
+        println!("  {}", warning.fix.accent());
+    }
 
-    let user_email_local = get_git_config("user.email", GitConfigScope::Local)?;
-    let user_email_global = get_git_config("user.email", GitConfigScope::Global)?;
-    print_config_value("User Email", user_email_local, user_email_global);
+    if let Some(ranked) = matches {
+        print_profile_match(&ranked);
+    }
 
-    let signing_key_local = get_git_config("user.signingkey", GitConfigScope::Local)?;
-    let signing_key_global = get_git_config("user.signingkey", GitConfigScope::Global)?;
-    print_config_value("Signing Key", signing_key_local, signing_key_global);
+    if let Some(forced) = profile_from_env() {
+        println!(
+            "\n{} {} is set: gitp answers credential requests with profile '{}' in this session.",
+            "Override:".bold(),
+            PROFILE_ENV_VAR.accent(),
+            forced.success()
+        );
+    }
 
     println!(
         "\n{}",
         "Note: Values are read directly from Git. Local settings override global settings."
             .dimmed()
     );
+    println!(
+        "{}",
+        "Run 'gitp status' to compare them with the selected profiles.".dimmed()
+    );
 
     Ok(())
 }
+
+/// The profile gitp applied here: the local marker, the directory rule's, or the global marker.
+fn applied_profile(rule_owner: Option<&String>) -> Result<Option<String>> {
+    Ok(
+        match get_git_config(PROFILE_MARKER_KEY, GitConfigScope::Local)? {
+            Some(marker) => Some(marker),
+            None => match rule_owner {
+                Some(owner) => Some(owner.clone()),
+                None => get_git_config(PROFILE_MARKER_KEY, GitConfigScope::Global)?,
+            },
+        },
+    )
+}
+
+/// The profile of the gitp directory rule (`gitp rules`) that sets identity values here, and
+/// its includeIf rule. Local values still win over them.
+fn directory_rule(config: &Config) -> Result<Option<(String, IncludeRule)>> {
+    if get_repo_root()?.is_none() {
+        return Ok(None);
+    }
+    Ok(identity_include_rules()?
+        .into_iter()
+        .rev()
+        .filter(|rule| !rule.effective.is_empty())
+        .find_map(|rule| {
+            let owner = fragment_profile(config, &rule.path)?;
+            Some((owner.name.clone(), rule))
+        }))
+}
+
+/// The effective email belongs to the wrong profile for the current repository's remote.
+pub(crate) struct HostWarning {
+    pub message: String,
+    /// Command that switches the repository to the profile for its host
+    pub fix: String,
+}
+
+/// Checks `email` against the host of the current repository's `origin` (or first) remote.
+/// `None` outside a repository, or when nothing points at another profile.
+pub(crate) fn email_host_warning(config: &Config, email: &str) -> Result<Option<HostWarning>> {
+    if get_repo_root()?.is_none() {
+        return Ok(None);
+    }
+    let Some(remote) = get_primary_remote()? else {
+        return Ok(None);
+    };
+    let Some(url) = parse_remote_url(&remote.effective_url) else {
+        return Ok(None);
+    };
+    let Some(mismatch) = config.email_host_mismatch(email, &url.host) else {
+        return Ok(None);
+    };
+    Ok(Some(HostWarning {
+        message: format!(
+            "{} is the email of profile '{}' ({}), but remote '{}' is on {}, which profile '{}' \
+             is for.",
+            email,
+            mismatch.owner.name,
+            mismatch.owner.hosts().join(", "),
+            remote.name,
+            url.host,
+            mismatch.suggested.name
+        ),
+        fix: format!("gitp use {} --local", mismatch.suggested.name),
+    }))
+}
+
+/// Every profile with the keys whose effective value differs from it, closest first.
+fn rank_profiles(
+    config: &Config,
+    effective: &EffectiveIdentity,
+) -> Vec<(String, Vec<&'static str>)> {
+    let mut candidates: Vec<(String, Vec<&'static str>)> = config
+        .profiles
+        .iter()
+        .map(|(name, profile)| (name.clone(), differences(profile, effective)))
+        .collect();
+    // Among equally close profiles, the one gitp applied here comes first
+    let applied = |name: &String| effective.marker.as_ref() != Some(name);
+    candidates.sort_by(|(a, a_diff), (b, b_diff)| {
+        (a_diff.len(), applied(a), a).cmp(&(b_diff.len(), applied(b), b))
+    });
+    candidates
+}
+
+/// Says which stored profile the effective values belong to, or which one comes closest.
+fn print_profile_match(candidates: &[(String, Vec<&'static str>)]) {
+    let exact: Vec<&String> = candidates
+        .iter()
+        .filter(|(_, diff)| diff.is_empty())
+        .map(|(name, _)| name)
+        .collect();
+    print!("\n{} ", "Profile:".bold());
+    match (exact.as_slice(), candidates.first()) {
+        ([name], _) => println!("matches profile '{}'", name.success()),
+        ([], None) => println!("{}", "no profiles to match against".warning()),
+        ([], Some((closest, diff))) => println!(
+            "matches no profile {}; closest is '{}', which differs in {}",
+            "(modified)".warning(),
+            closest.accent(),
+            diff.join(", ")
+        ),
+        ([first, others @ ..], _) => println!(
+            "matches profile '{}' (identical to {})",
+            first.success(),
+            others
+                .iter()
+                .map(|n| format!("'{}'", n))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// Git config keys whose effective value differs from what `profile` applies. Line-ending
+/// settings only count when the profile sets them, since switching doesn't clear them.
+fn differences(profile: &Profile, effective: &EffectiveIdentity) -> Vec<&'static str> {
+    let git_config = &profile.git_config;
+    let mut diff = Vec::new();
+    if effective.user_name.as_deref() != Some(git_config.user_name.as_str()) {
+        diff.push("user.name");
+    }
+    if !effective
+        .user_email
+        .as_deref()
+        .is_some_and(|email| email.eq_ignore_ascii_case(&git_config.user_email))
+    {
+        diff.push("user.email");
+    }
+    // A local switch may have applied a key from host_signing instead
+    if effective.signing_key != git_config.user_signingkey
+        && !profile
+            .host_signing
+            .iter()
+            .any(|s| effective.signing_key.as_deref() == Some(s.git_key().as_str()))
+    {
+        diff.push("user.signingkey");
+    }
+    if let Some(autocrlf) = git_config.core_autocrlf {
+        if effective.autocrlf.as_deref() != Some(autocrlf.as_str()) {
+            diff.push("core.autocrlf");
+        }
+    }
+    if let Some(eol) = git_config.core_eol {
+        if effective.eol.as_deref() != Some(eol.as_str()) {
+            diff.push("core.eol");
+        }
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AutoCrlf;
+
+    #[test]
+    fn test_differences() {
+        let mut profile = Profile::new(
+            "work".to_string(),
+            "John Doe".to_string(),
+            "john@company.com".to_string(),
+        );
+        let mut effective = EffectiveIdentity {
+            user_name: Some("John Doe".to_string()),
+            user_email: Some("John@Company.com".to_string()),
+            autocrlf: Some("input".to_string()),
+            ..Default::default()
+        };
+        assert!(differences(&profile, &effective).is_empty());
+
+        profile.git_config.core_autocrlf = Some(AutoCrlf::True);
+        effective.user_name = Some("J. Doe".to_string());
+        effective.signing_key = Some("ABCD".to_string());
+        assert_eq!(
+            differences(&profile, &effective),
+            ["user.name", "user.signingkey", "core.autocrlf"]
+        );
+
+        let value = sourced(None, Some("rule".to_string()), Some("global".to_string())).unwrap();
+        assert_eq!(
+            (value.value.as_str(), value.source),
+            ("rule", "directory_rule")
+        );
+        assert!(sourced(None, None, None).is_none());
+    }
+}
@@ -0,0 +1,1191 @@
+use anyhow::{bail, Context, Result};
+use chrono::Local;
+use colored::Colorize;
+use std::path::Path;
+
+use crate::commands::current::email_host_warning;
+use crate::commands::remotes::{aliased_url, SCAN_DEPTH};
+use crate::commands::rules::{fragment_profile, is_fragment};
+use crate::commands::use_profile::{apply_git_identity, ssh_entries, PROFILE_MARKER_KEY};
+use crate::config::{
+    is_host_pattern, normalize_host, Config, CredentialType, HttpsCredentials, Profile,
+    ValidationError,
+};
+use crate::credentials::helper::{helper_order, is_credential_manager, is_gitp_helper};
+use crate::credentials::{resolve_token, store_label, verify};
+use crate::git::includes::{identity_include_rules, remove_include_rule, IncludeRule};
+use crate::git::{
+    find_repos, get_git_config, get_remotes, get_repo_root, git_version, in_repo, GitConfigScope,
+    GIT_INSTALL_HINT,
+};
+use crate::gpg::{
+    card_status, display_serial, gpg_signing_key, signing_key, signing_required, CardStatus,
+    KeyLocation,
+};
+use crate::providers;
+use crate::ssh::agent::{agent_identities, agent_requirement, key_fingerprint, AgentRequirement};
+use crate::ssh::effective::effective_ssh_config;
+use crate::ssh::ssh_config::{
+    get_ssh_config_path, managed_block_format, read_ssh_config, shadowing_sections,
+};
+use crate::utils::net::{reachable, REACHABILITY_TIMEOUT};
+use crate::utils::output::{theme, Paint, Symbol};
+use crate::utils::prompt::Confirm;
+use crate::utils::terminal::can_prompt;
+use crate::utils::{contract_home, expand_home, FormatCheck};
+
+/// Counts findings while printing them.
+#[derive(Default)]
+struct Report {
+    warnings: usize,
+    failures: usize,
+}
+
+impl Report {
+    fn section(&self, title: &str) {
+        println!("\n{}", title.bold());
+    }
+
+    fn ok(&self, message: impl AsRef<str>) {
+        println!("  {} {}", Symbol::Ok.as_str().success(), message.as_ref());
+    }
+
+    fn warn(&mut self, message: impl AsRef<str>) {
+        self.warnings += 1;
+        println!(
+            "  {} {}",
+            Symbol::Warn.as_str().warning().bold(),
+            message.as_ref()
+        );
+    }
+
+    fn fail(&mut self, message: impl AsRef<str>) {
+        self.failures += 1;
+        println!(
+            "  {} {}",
+            Symbol::Fail.as_str().error().bold(),
+            message.as_ref()
+        );
+    }
+
+    fn hint(&self, message: impl AsRef<str>) {
+        println!("    {}", message.as_ref().dimmed());
+    }
+}
+
+/// Checks the machine for the usual multi-account pitfalls. Fails when problems that break
+/// authentication are found, so it can be used in scripts.
+pub fn execute(convert_includes: bool) -> Result<()> {
+    if convert_includes {
+        return convert_include_rules();
+    }
+    let mut report = Report::default();
+
+    println!("{}", "gitp doctor".bold().underline());
+    check_git(&mut report);
+    let Some(config) = check_config(&mut report) else {
+        return finish(&report);
+    };
+    let ssh_profiles = config.ssh_managed_profiles();
+    check_ssh_keys(&mut report, &config);
+    let identities_only = check_ssh_hosts(&mut report, &ssh_profiles)?;
+    report_unmanaged_ssh(&mut report, &config);
+    check_agent(&mut report, &ssh_profiles, identities_only)?;
+    check_repo_remotes(&mut report, &config);
+    check_current_repo(&mut report, &config)?;
+    check_include_rules(&mut report, &config)?;
+    check_gpg_signing(&mut report, &config);
+    check_tokens(&mut report, &config);
+    check_credential_helpers(&mut report, &config)?;
+    check_network_notes(&mut report, &config);
+    finish(&report)
+}
+
+/// Prints the totals; fails when a problem was found.
+fn finish(report: &Report) -> Result<()> {
+    println!();
+    match (report.failures, report.warnings) {
+        (0, 0) => println!("{}", "No problems found.".success()),
+        (0, warnings) => println!("{} warning(s).", warnings.to_string().warning()),
+        (failures, warnings) => bail!("{} problem(s) and {} warning(s) found.", failures, warnings),
+    }
+    Ok(())
+}
+
+/// Loads the configuration and validates its profiles. `None` when it can't be loaded, which
+/// leaves nothing else to check. Missing SSH keys are left to [`check_ssh_keys`].
+fn check_config(report: &mut Report) -> Option<Config> {
+    report.section("Configuration");
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            report.fail(format!("The configuration can't be loaded: {:#}", e));
+            report.hint("Fix the file it names; 'gitp paths' shows where gitp keeps it.");
+            return None;
+        }
+    };
+    let mut profiles: Vec<&Profile> = config.profiles.values().collect();
+    profiles.sort_by(|a, b| a.name.cmp(&b.name));
+    let mut invalid = 0;
+    for profile in &profiles {
+        match profile.validate() {
+            Ok(()) | Err(ValidationError::SshKeyNotFound(_)) => {}
+            Err(e) => {
+                invalid += 1;
+                report.fail(format!("{}: {}", profile.name, e.user_message()));
+                report.hint(format!("Fix it with 'gitp edit {}'.", profile.name));
+            }
+        }
+    }
+    match (profiles.len(), invalid) {
+        (0, _) => report.ok("No profiles yet; create one with 'gitp new <name>'."),
+        (count, 0) => report.ok(format!("{} profile(s), all valid", count)),
+        _ => {}
+    }
+    Some(config)
+}
+
+/// Checks that each profile's SSH key files exist and that only their owner can read them,
+/// since ssh ignores a private key others can read.
+fn check_ssh_keys(report: &mut Report, config: &Config) {
+    let mut keys: Vec<(&Profile, &Path)> = config
+        .profiles
+        .values()
+        .flat_map(|p| {
+            p.ssh_key
+                .iter()
+                .chain(p.ssh_hosts.iter().map(|entry| &entry.key))
+                .map(move |key| (p, key.as_path()))
+        })
+        .collect();
+    if keys.is_empty() {
+        return;
+    }
+    keys.sort_by(|(a, _), (b, _)| a.name.cmp(&b.name));
+    report.section("SSH keys");
+    for (profile, key) in keys {
+        let path = expand_home(&key.to_string_lossy());
+        let metadata = match std::fs::metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(_) => {
+                report.fail(format!(
+                    "{}: {} doesn't exist.",
+                    profile.name,
+                    key.display()
+                ));
+                report.hint(format!(
+                    "Point the profile at the key with 'gitp edit {} --ssh-key-path <path>', or \
+                     create one with 'gitp ssh-key generate {}'.",
+                    profile.name, profile.name
+                ));
+                continue;
+            }
+        };
+        if let Some(mode) = key_mode(&metadata).filter(|mode| mode & 0o077 != 0) {
+            report.fail(format!(
+                "{}: {} can be read by others (mode {:o}), so ssh refuses to use it.",
+                profile.name,
+                key.display(),
+                mode
+            ));
+            report.hint(format!("Run: chmod 600 {}", path.display()));
+            continue;
+        }
+        let recorded = profile
+            .ssh_key_fingerprint
+            .as_deref()
+            .filter(|_| profile.ssh_key.as_deref() == Some(key));
+        match recorded {
+            Some(recorded) => match key_fingerprint(&path) {
+                Ok(Some(fingerprint)) if fingerprint != recorded => {
+                    report.fail(format!(
+                        "{}: {} is another key ({}) than the one set for the profile ({}).",
+                        profile.name,
+                        key.display(),
+                        fingerprint,
+                        recorded
+                    ));
+                    report.hint(format!(
+                        "If the key was replaced on purpose, record it with 'gitp ssh-key set {} {}'.",
+                        profile.name,
+                        key.display()
+                    ));
+                }
+                _ => report.ok(format!(
+                    "{}: {} ({})",
+                    profile.name,
+                    key.display(),
+                    recorded
+                )),
+            },
+            None => report.ok(format!("{}: {}", profile.name, key.display())),
+        }
+        match agent_requirement(&path) {
+            Ok(AgentRequirement::NoAgent) => {
+                report.warn(format!(
+                    "{}: {} is passphrase-protected and no ssh-agent is running, so fetches \
+                     without a terminal (CI, IDE fetches) fail.",
+                    profile.name,
+                    key.display()
+                ));
+                report.hint("Start one with: eval \"$(ssh-agent -s)\"");
+            }
+            Ok(AgentRequirement::NotLoaded) => {
+                report.warn(format!(
+                    "{}: {} is passphrase-protected and ssh-agent doesn't hold it, so fetches \
+                     without a terminal (CI, IDE fetches) fail.",
+                    profile.name,
+                    key.display()
+                ));
+                report.hint(match profile.ssh_add_keys_to_agent {
+                    None if cfg!(target_os = "macos") => format!(
+                        "Load it with 'gitp ssh agent {} --load', or keep its passphrase in the \
+                         keychain with 'gitp ssh agent {} --use-keychain true'.",
+                        profile.name, profile.name
+                    ),
+                    Some(_) => format!(
+                        "Load it with 'gitp ssh agent {} --load', or connect once on a terminal \
+                         (the entry has AddKeysToAgent).",
+                        profile.name
+                    ),
+                    None => format!(
+                        "Load it with 'gitp ssh agent {} --load', or let ssh load it once \
+                         unlocked with 'gitp ssh agent {} --add-keys-to-agent yes'.",
+                        profile.name, profile.name
+                    ),
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Permission bits of a file; `None` where there are none to check (Windows).
+fn key_mode(metadata: &std::fs::Metadata) -> Option<u32> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        Some(metadata.permissions().mode() & 0o777)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        None
+    }
+}
+
+/// Checks that the tokens kept in the keychain or the encrypted file store can be read, and
+/// the tokens against what their last `gitp credentials verify` recorded: expiry, the scope
+/// needed to push and the required scopes. Asks no provider.
+fn check_tokens(report: &mut Report, config: &Config) {
+    let tokens: Vec<(&Profile, &HttpsCredentials)> = config
+        .profiles
+        .values()
+        .flat_map(|p| p.https_credentials.iter().map(move |creds| (p, creds)))
+        .collect();
+    if tokens.is_empty() {
+        return;
+    }
+    report.section("HTTPS tokens");
+    let today = Local::now().date_naive();
+    for (profile, creds) in tokens {
+        let who = format!("{}@{} ({})", creds.username, creds.host, profile.name);
+        if !matches!(creds.credential_type, CredentialType::Token(_)) {
+            if let Err(e) = resolve_token(&config.settings, creds) {
+                report.fail(format!(
+                    "{}: the token can't be read from the {}: {:#}",
+                    who,
+                    store_label(&creds.credential_type),
+                    e
+                ));
+                report.hint(format!(
+                    "Store it again with 'gitp credentials set {} --host {}'.",
+                    profile.name, creds.host
+                ));
+                continue;
+            }
+        }
+        let warnings = verify::token_warnings(&config.settings, creds, today);
+        if creds.token_scopes.is_none()
+            && !verify::required_scopes(&config.settings, creds).is_empty()
+        {
+            report.warn(format!(
+                "{}: scopes unknown, so the required ones can't be checked.",
+                who
+            ));
+            report.hint(format!(
+                "Run 'gitp credentials verify {} --host {}' to record them.",
+                profile.name, creds.host
+            ));
+        } else if warnings.is_empty() {
+            report.ok(format!("{}: fine as of its last verification", who));
+        }
+        for warning in warnings {
+            report.warn(warning);
+        }
+    }
+}
+
+/// Checks that git asks gitp's credential helper first for the HTTPS hosts of the selected
+/// profile. Git Credential Manager (set up system-wide by Git for Windows) asked before it
+/// answers with the account it signed in, or opens its own sign-in.
+fn check_credential_helpers(report: &mut Report, config: &Config) -> Result<()> {
+    let Some(profile) = config
+        .state
+        .current_profile
+        .as_ref()
+        .and_then(|name| config.profiles.get(name))
+    else {
+        return Ok(());
+    };
+    let hosts: Vec<&str> = profile
+        .https_hosts()
+        .into_iter()
+        .filter(|host| !is_host_pattern(host))
+        .collect();
+    if hosts.is_empty() {
+        return Ok(());
+    }
+    report.section("Credential helpers");
+    for host in hosts {
+        let order = helper_order(host)?;
+        let Some(gitp) = order.iter().position(|e| is_gitp_helper(&e.value)) else {
+            report.warn(format!(
+                "{}: git doesn't ask gitp's credential helper, so the profile's token isn't used.",
+                host
+            ));
+            report.hint(format!(
+                "Apply the profile again with 'gitp use {}'.",
+                profile.name
+            ));
+            continue;
+        };
+        match order[..gitp].first() {
+            Some(first) if is_credential_manager(&first.value) => {
+                report.warn(format!(
+                    "{}: Git Credential Manager ('{}', {}) is asked before gitp, so it answers \
+                     with the account it signed in or opens its own sign-in.",
+                    host, first.value, first.origin
+                ));
+                report.hint(format!(
+                    "Add \"{}\" = \"replace\" (or \"prepend\") under \
+                     [settings.credential_helper_hosts] in config.toml, then run 'gitp use {}'.",
+                    host, profile.name
+                ));
+            }
+            Some(first) => {
+                report.warn(format!(
+                    "{}: '{}' ({}) is asked before gitp and may answer with other credentials.",
+                    host, first.value, first.origin
+                ));
+                report.hint(format!(
+                    "Set credential_helper_coexistence to \"prepend\" or \"replace\", then run \
+                     'gitp use {}'.",
+                    profile.name
+                ));
+            }
+            None => report.ok(format!("{}: git asks gitp's credential helper first", host)),
+        }
+    }
+    Ok(())
+}
+
+/// Checks the hosts of profiles with notes (e.g. "requires corp VPN"), so a host that can't
+/// be reached shows its note instead of looking like a configuration error.
+fn check_network_notes(report: &mut Report, config: &Config) {
+    let mut profiles: Vec<&Profile> = config
+        .profiles
+        .values()
+        .filter(|p| p.notes.is_some())
+        .collect();
+    if profiles.is_empty() {
+        return;
+    }
+    profiles.sort_by(|a, b| a.name.cmp(&b.name));
+    report.section("Network");
+    for profile in profiles {
+        let unreachable = unreachable_hosts(profile);
+        if unreachable.is_empty() {
+            report.ok(format!(
+                "Profile '{}': its hosts can be reached.",
+                profile.name
+            ));
+            continue;
+        }
+        report.warn(format!(
+            "Profile '{}': {} can't be reached.",
+            profile.name,
+            unreachable.join(", ")
+        ));
+        if let Some(notes) = &profile.notes {
+            report.hint(format!("Note: {}", notes));
+        }
+    }
+}
+
+/// `host:port` of each of `profile`'s hosts that doesn't accept connections: the SSH hosts on
+/// the port ssh would use, and the HTTPS hosts. Host patterns are skipped.
+pub(crate) fn unreachable_hosts(profile: &Profile) -> Vec<String> {
+    let mut endpoints: Vec<(String, u16)> = Vec::new();
+    let ssh_hosts = profile
+        .ssh_host()
+        .into_iter()
+        .chain(profile.ssh_hosts.iter().map(|entry| entry.host.as_str()))
+        .filter(|host| !is_host_pattern(host));
+    for host in ssh_hosts {
+        match effective_ssh_config(host, None, None) {
+            Ok(ssh) => endpoints.push((ssh.hostname, ssh.port)),
+            Err(_) => endpoints.push(host_and_port(
+                host,
+                providers::lookup(host).map_or(22, |p| p.ssh_port),
+            )),
+        }
+    }
+    for creds in &profile.https_credentials {
+        if !is_host_pattern(&creds.host) {
+            endpoints.push(host_and_port(&creds.host, 443));
+        }
+    }
+    endpoints.dedup();
+    endpoints
+        .into_iter()
+        .filter(|(host, port)| !reachable(host, *port, REACHABILITY_TIMEOUT))
+        .map(|(host, port)| format!("{}:{}", host, port))
+        .collect()
+}
+
+/// `host` without scheme or user, and its port, or `default_port` when it names none.
+fn host_and_port(host: &str, default_port: u16) -> (String, u16) {
+    let host = normalize_host(host);
+    match host
+        .rsplit_once(':')
+        .and_then(|(name, port)| Some((name, port.parse().ok()?)))
+    {
+        Some((name, port)) => (name.to_string(), port),
+        None => (host, default_port),
+    }
+}
+
+/// Checks that git can be run; without it only the profile commands work.
+fn check_git(report: &mut Report) {
+    report.section("Git");
+    match git_version() {
+        Some(version) => report.ok(version),
+        None => {
+            report.fail(
+                "git wasn't found on PATH; clone, resolve, rules, report, mailmap, guard and \
+                 credentials import/export run it.",
+            );
+            report.hint(GIT_INSTALL_HINT);
+        }
+    }
+}
+
+/// Checks the managed SSH block and what `ssh -G` makes of each profile's host. Returns
+/// whether every profile's host has IdentitiesOnly in effect.
+fn check_ssh_hosts(report: &mut Report, profiles: &[&Profile]) -> Result<bool> {
+    report.section("SSH Host entries");
+    if profiles.is_empty() {
+        report.ok("No profile has an SSH key gitp manages; nothing to check.");
+        return Ok(true);
+    }
+
+    let config_path = get_ssh_config_path()?;
+    let content = read_ssh_config(&config_path)?;
+    match managed_block_format(&content) {
+        Some(FormatCheck::Current) => {
+            report.ok(format!("Managed block found in {:?}", config_path))
+        }
+        Some(FormatCheck::Outdated(version)) => {
+            report.warn(format!("Managed block uses the old format {}.", version));
+            report.hint("Run 'gitp use <profile>' to rewrite it.");
+        }
+        Some(FormatCheck::Newer(version)) => {
+            report.fail(format!(
+                "Managed block was written by a newer gitp (format {}).",
+                version
+            ));
+            report.hint("Upgrade gitp.");
+        }
+        Some(FormatCheck::Unrecognized(tag)) => report.fail(format!(
+            "Managed block has an unrecognized header tag '{}'.",
+            tag
+        )),
+        None => {
+            report.fail(format!("{:?} has no gitp-managed block.", config_path));
+            report.hint("Run 'gitp use <profile>' to write it.");
+        }
+    }
+    check_shadowing_sections(report, profiles, &content);
+
+    let mut all_identities_only = true;
+    for (profile, entry) in profiles
+        .iter()
+        .flat_map(|p| ssh_entries(p).into_iter().map(move |entry| (p, entry)))
+    {
+        let (key, host) = (&entry.identity_file, entry.host.as_str());
+        // For a `*.domain` pattern, ask ssh about a host the pattern covers
+        let probe = host.replacen('*', "gitp-probe", 1);
+        let ssh = match effective_ssh_config(&probe, None, None) {
+            Ok(ssh) => ssh,
+            Err(e) => {
+                report.warn(format!("Could not check host '{}': {:#}", host, e));
+                continue;
+            }
+        };
+        let key = expand_home(&key.to_string_lossy());
+        let offered_first = ssh
+            .identity_files
+            .first()
+            .is_some_and(|first| expand_home(first) == key);
+
+        if !ssh.identities_only {
+            all_identities_only = false;
+            report.warn(format!(
+                "{} ({}): IdentitiesOnly is off, so ssh-agent keys are offered before {:?}.",
+                host, profile.name, key
+            ));
+            report
+                .hint("A 'Host *' block earlier in ~/.ssh/config may be overriding gitp's entry.");
+        } else if !offered_first {
+            report.warn(format!(
+                "{} ({}): another IdentityFile is tried before {:?}.",
+                host, profile.name, key
+            ));
+        } else {
+            report.ok(format!(
+                "{} ({}): IdentitiesOnly, {:?} offered first",
+                host, profile.name, key
+            ));
+        }
+    }
+    Ok(all_identities_only)
+}
+
+/// Flags `Host *`-style sections above the managed block whose values ssh uses instead of
+/// gitp's.
+fn check_shadowing_sections(report: &mut Report, profiles: &[&Profile], content: &str) {
+    // A host each entry stands for; patterns are probed with a name they cover
+    let hosts: Vec<String> = profiles
+        .iter()
+        .flat_map(|p| ssh_entries(p))
+        .map(|entry| entry.host.replacen('*', "gitp-probe", 1))
+        .collect();
+    let hosts: Vec<&str> = hosts.iter().map(String::as_str).collect();
+    for section in shadowing_sections(content, &hosts) {
+        let location = if section.line == 0 {
+            "Options before the first Host line".to_string()
+        } else {
+            format!("'{}' (line {})", section.header, section.line)
+        };
+        report.warn(format!(
+            "{} set{} {} ahead of gitp's entries; ssh uses the first value it finds.",
+            location,
+            if section.line == 0 { "" } else { "s" },
+            section.options.join(", ")
+        ));
+        report.hint(
+            "Move it below the gitp block, or set ssh_block_position = \"top\" in [settings] \
+             and run 'gitp use <profile>'.",
+        );
+    }
+}
+
+/// Names the profiles with SSH keys left out of the managed block, so their missing entries
+/// aren't mistaken for a problem.
+fn report_unmanaged_ssh(report: &mut Report, config: &Config) {
+    let mut unmanaged: Vec<&str> = config
+        .profiles
+        .values()
+        .filter(|p| p.ssh_key.is_some() && !config.manages_ssh(p))
+        .map(|p| p.name.as_str())
+        .collect();
+    if unmanaged.is_empty() {
+        return;
+    }
+    unmanaged.sort();
+    report.ok(format!(
+        "Not managed by gitp, left to ssh-agent and your own entries: {}",
+        unmanaged.join(", ")
+    ));
+}
+
+/// Lists the keys ssh-agent offers and flags the setup where it can offer the wrong one.
+fn check_agent(report: &mut Report, profiles: &[&Profile], identities_only: bool) -> Result<()> {
+    report.section("ssh-agent");
+    let identities = match agent_identities()? {
+        Some(identities) => identities,
+        None => {
+            report.ok("No ssh-agent is reachable; ssh only uses IdentityFile entries.");
+            return Ok(());
+        }
+    };
+    if identities.is_empty() {
+        report.ok("ssh-agent is running with no keys loaded.");
+        return Ok(());
+    }
+
+    let mut profile_fingerprints = Vec::new();
+    for profile in profiles {
+        let keys = profile
+            .ssh_key
+            .iter()
+            .chain(profile.ssh_hosts.iter().map(|entry| &entry.key));
+        for key in keys {
+            if let Some(fingerprint) = key_fingerprint(&expand_home(&key.to_string_lossy()))? {
+                profile_fingerprints.push((fingerprint, profile.name.as_str()));
+            }
+        }
+    }
+
+    println!("  Keys offered by ssh-agent, in order:");
+    for (i, identity) in identities.iter().enumerate() {
+        let owner = profile_fingerprints
+            .iter()
+            .find(|(fingerprint, _)| *fingerprint == identity.fingerprint)
+            .map(|(_, name)| format!(" (profile {})", name.success()))
+            .unwrap_or_default();
+        println!(
+            "    {}. {} {} {}{}",
+            i + 1,
+            identity.fingerprint.dimmed(),
+            identity.comment,
+            identity.key_type.dimmed(),
+            owner
+        );
+    }
+
+    let loaded_profile_keys = identities
+        .iter()
+        .filter(|identity| {
+            profile_fingerprints
+                .iter()
+                .any(|(fingerprint, _)| *fingerprint == identity.fingerprint)
+        })
+        .count();
+    if identities.len() > 1 && !identities_only {
+        report.fail(
+            "ssh-agent holds several keys and not every gitp host sets IdentitiesOnly, so the \
+             first key may authenticate you as the wrong account.",
+        );
+        report.hint("Remove the overriding IdentitiesOnly setting, or load one key at a time.");
+    } else if loaded_profile_keys > 1 {
+        report
+            .ok("Several profile keys are loaded; IdentitiesOnly keeps each host on its own key.");
+    } else {
+        report.ok("No ambiguity between loaded keys.");
+    }
+    Ok(())
+}
+
+/// Flags remotes of repositories switched with `use --local` that reach a profile's host
+/// directly instead of through its alias, and so authenticate with the default key.
+fn check_repo_remotes(report: &mut Report, config: &Config) {
+    report.section("Repository remotes");
+    let mut checked = 0;
+    for (repo, profile_name) in &config.state.repos {
+        let Some(profile) = config.profiles.get(profile_name) else {
+            continue;
+        };
+        let (Some(alias), Some(host)) = (&profile.ssh_host_alias, &profile.ssh_key_host) else {
+            continue;
+        };
+        if !Path::new(repo).exists() {
+            continue;
+        }
+        let remotes = match in_repo(Path::new(repo), get_remotes) {
+            Ok(remotes) => remotes,
+            Err(e) => {
+                report.warn(format!("Could not read remotes of {}: {:#}", repo, e));
+                continue;
+            }
+        };
+        checked += 1;
+        for remote in remotes {
+            if let Some(new_url) = aliased_url(&remote.effective_url, profile) {
+                report.warn(format!(
+                    "{}: remote '{}' uses {}, not the alias {} of profile '{}'.",
+                    repo, remote.name, host, alias, profile.name
+                ));
+                if remote.url != remote.effective_url {
+                    report.hint(format!(
+                        "Its URL {} is rewritten to {} by an insteadOf rule.",
+                        remote.url, remote.effective_url
+                    ));
+                }
+                report.hint(format!(
+                    "git -C {} remote set-url {} {}",
+                    repo, remote.name, new_url
+                ));
+            }
+        }
+    }
+    if checked == 0 {
+        report.ok("No repositories use a profile with a host alias.");
+    }
+}
+
+/// Checks that the current repository commits with the email of a profile meant for its
+/// remote's host.
+fn check_current_repo(report: &mut Report, config: &Config) -> Result<()> {
+    if get_repo_root()?.is_none() {
+        return Ok(());
+    }
+    report.section("Current repository");
+    let email = match get_git_config("user.email", GitConfigScope::Local)? {
+        Some(email) => Some(email),
+        None => get_git_config("user.email", GitConfigScope::Global)?,
+    };
+    let Some(email) = email else {
+        report.warn("No user.email is set; git will refuse to commit.");
+        return Ok(());
+    };
+    match email_host_warning(config, &email)? {
+        Some(warning) => {
+            report.warn(warning.message);
+            report.hint(warning.fix);
+        }
+        None => report.ok(format!("Commits as {}", email)),
+    }
+    Ok(())
+}
+
+/// Flags `[includeIf]` rules of the global and system config that set user.name, user.email or
+/// user.signingkey. Where one is in effect it wins over a global switch, the usual reason
+/// "gitp use didn't work".
+fn check_include_rules(report: &mut Report, config: &Config) -> Result<()> {
+    let rules = identity_include_rules()?;
+    if rules.is_empty() {
+        return Ok(());
+    }
+    report.section("includeIf rules");
+    let applied = match get_git_config(PROFILE_MARKER_KEY, GitConfigScope::Local)? {
+        Some(name) => Some(name),
+        None => get_git_config(PROFILE_MARKER_KEY, GitConfigScope::Global)?,
+    }
+    .and_then(|name| config.profiles.get(&name));
+
+    for rule in &rules {
+        let location = format!(
+            "{} in {}",
+            rule.header(),
+            contract_home(&rule.file).display()
+        );
+        if is_fragment(&rule.path) {
+            match fragment_profile(config, &rule.path) {
+                Some(owner) => report.ok(format!(
+                    "{} is gitp's directory rule for profile '{}'.",
+                    location, owner.name
+                )),
+                None => {
+                    report.warn(format!(
+                        "{} includes a gitp fragment no profile's rule uses.",
+                        location
+                    ));
+                    report.hint("Remove it with 'gitp rules apply'.");
+                }
+            }
+            continue;
+        }
+        let overrides = applied
+            .map(|profile| include_overrides(rule, profile))
+            .unwrap_or_default();
+        match applied {
+            Some(profile) if !overrides.is_empty() => {
+                report.warn(format!(
+                    "{} overrides profile '{}' here: {}",
+                    location,
+                    profile.name,
+                    overrides.join(", ")
+                ));
+                report.hint(format!(
+                    "Apply the profile to this repository with 'gitp use {} --local', or let \
+                     gitp take over the rule with 'gitp doctor --convert-includes'.",
+                    profile.name
+                ));
+            }
+            _ => {
+                let keys: Vec<&str> = rule.identity.iter().map(|(k, _)| k.as_str()).collect();
+                report.ok(format!(
+                    "{} sets {} in the repositories it matches.",
+                    location,
+                    keys.join(", ")
+                ));
+                report.hint(
+                    "A global 'gitp use' doesn't change them there; 'gitp doctor \
+                     --convert-includes' hands them to gitp.",
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The identity values `rule` sets in the current directory in place of those `profile`
+/// applied, as `key = value (not applied)`.
+pub(crate) fn include_overrides(rule: &IncludeRule, profile: &Profile) -> Vec<String> {
+    let git_config = &profile.git_config;
+    rule.effective
+        .iter()
+        .filter_map(|(key, value)| {
+            let applied = match key.as_str() {
+                "user.name" => Some(git_config.user_name.as_str()),
+                "user.email" => Some(git_config.user_email.as_str()),
+                _ => git_config.user_signingkey.as_deref(),
+            };
+            // Emails and key IDs are compared without case, as elsewhere
+            match applied {
+                Some(applied) if applied == value => None,
+                Some(applied) if key != "user.name" && applied.eq_ignore_ascii_case(value) => None,
+                Some(applied) => Some(format!("{} = {} (not {})", key, value, applied)),
+                None => Some(format!("{} = {}", key, value)),
+            }
+        })
+        .collect()
+}
+
+/// Hands `gitdir:` includeIf rules over to gitp: the profile with the included email is
+/// applied locally to every repository below the rule's directory (recorded like `gitp use
+/// --local`), then the rule can be removed.
+fn convert_include_rules() -> Result<()> {
+    let mut config = Config::load().context("Failed to load configuration.")?;
+    let rules = identity_include_rules()?;
+    if rules.is_empty() {
+        println!("No includeIf rule in the global or system config sets an identity.");
+        return Ok(());
+    }
+
+    for rule in &rules {
+        let location = format!(
+            "{} in {}",
+            rule.header(),
+            contract_home(&rule.file).display()
+        );
+        if is_fragment(&rule.path) {
+            // Already gitp's own rule (`gitp rules`)
+            continue;
+        }
+        println!("\n{}", location.bold());
+        let Some(dir) = rule.gitdir() else {
+            println!(
+                "  {}: only 'gitdir:' conditions naming a directory (such as gitdir:~/work/) \
+                 can be converted.",
+                "Skipped".warning()
+            );
+            continue;
+        };
+        let Some(email) = rule.value("user.email") else {
+            println!(
+                "  {}: the included file sets no user.email to find the profile by.",
+                "Skipped".warning()
+            );
+            continue;
+        };
+        let Some(name) = config
+            .profiles
+            .values()
+            .find(|p| p.git_config.user_email.eq_ignore_ascii_case(email))
+            .map(|p| p.name.clone())
+        else {
+            println!(
+                "  {}: no profile uses {}. Create one with '{}' and run this again.",
+                "Skipped".warning(),
+                email,
+                "gitp new <name>".accent()
+            );
+            continue;
+        };
+
+        let repos = find_repos(&dir, SCAN_DEPTH);
+        println!(
+            "Applying profile '{}' to {} repositories under {}...",
+            name.accent(),
+            repos.len(),
+            contract_home(&dir).display()
+        );
+        for repo in &repos {
+            println!("{}", contract_home(repo).display());
+            in_repo(repo, || {
+                apply_git_identity(&mut config, &name, GitConfigScope::Local)
+            })?;
+            config.state.record_switch(
+                &name,
+                Some(repo.to_string_lossy().into_owned()),
+                chrono::Utc::now(),
+            );
+        }
+        config
+            .save()
+            .context("Failed to save gitp configuration.")?;
+
+        let remove = can_prompt()
+            && Confirm::with_theme(&theme())
+                .with_prompt(format!(
+                    "Remove {} from {}? Repositories cloned there later need 'gitp use {} \
+                     --local' (or 'gitp use --auto').",
+                    rule.header(),
+                    contract_home(&rule.file).display(),
+                    name
+                ))
+                .default(false)
+                .interact()
+                .context("Failed to get confirmation.")?;
+        if remove {
+            remove_include_rule(rule)?;
+            println!("  {} {}", "Removed".success(), rule.header());
+        } else {
+            println!(
+                "  Kept the rule. Remove it later with: {}",
+                format!(
+                    "git config --file {} --remove-section 'includeIf.{}'",
+                    contract_home(&rule.file).display(),
+                    rule.condition
+                )
+                .accent()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Checks that gpg has each profile's signing key, and that keys on a smartcard have their card
+/// inserted. A missing key is a failure for profiles that sign every commit.
+fn check_gpg_signing(report: &mut Report, config: &Config) {
+    report.section("GPG signing");
+    let mut profiles: Vec<&Profile> = config
+        .profiles
+        .values()
+        .filter(|p| gpg_signing_key(p).is_some())
+        .collect();
+    if profiles.is_empty() {
+        report.ok("No profile has a GPG signing key; nothing to check.");
+        return;
+    }
+    profiles.sort_by(|a, b| a.name.cmp(&b.name));
+
+    // Read once, and only when a key lives on a card
+    let mut card: Option<Option<CardStatus>> = None;
+    for profile in profiles {
+        let key_id = gpg_signing_key(profile).unwrap_or_default();
+        let required = signing_required(profile);
+        let problem = |report: &mut Report, message: String| {
+            if required {
+                report.fail(format!("{} The profile signs every commit.", message));
+            } else {
+                report.warn(message);
+            }
+        };
+        let key = match signing_key(key_id) {
+            Ok(Some(key)) => key,
+            Ok(None) => {
+                problem(
+                    report,
+                    format!("{}: gpg has no secret key {}.", profile.name, key_id),
+                );
+                report.hint("For a smartcard key, run 'gpg --card-status' with the card inserted.");
+                continue;
+            }
+            Err(e) => {
+                report.warn(format!("Could not run gpg: {:#}", e));
+                return;
+            }
+        };
+        let serial = match &key.location {
+            KeyLocation::Local => {
+                report.ok(format!("{}: {} in the local keyring", profile.name, key_id));
+                continue;
+            }
+            KeyLocation::Card(serial) => display_serial(serial),
+            KeyLocation::Stub => "unknown".to_string(),
+        };
+        let inserted = card.get_or_insert_with(|| card_status().ok().flatten());
+        match inserted {
+            Some(status) if status.holds(&key.fingerprint) => report.ok(format!(
+                "{}: {} on smartcard {}, inserted",
+                profile.name, key_id, serial
+            )),
+            Some(status) => problem(
+                report,
+                format!(
+                    "{}: {} is on smartcard {}, but card {} is inserted.",
+                    profile.name,
+                    key_id,
+                    serial,
+                    display_serial(&status.serial)
+                ),
+            ),
+            None => {
+                problem(
+                    report,
+                    format!(
+                        "{}: {} is on smartcard {}, which isn't inserted.",
+                        profile.name, key_id, serial
+                    ),
+                );
+                report
+                    .hint("If it is plugged in, 'gpgconf --kill scdaemon' resets a stuck reader.");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use std::fs;
+
+    fn profile(name: &str) -> Profile {
+        Profile::new(
+            name.to_string(),
+            "John Doe".to_string(),
+            format!("john@{}.example.com", name),
+        )
+    }
+
+    fn config(profiles: Vec<Profile>) -> Config {
+        let mut config = Config::default();
+        for profile in profiles {
+            config.profiles.insert(profile.name.clone(), profile);
+        }
+        config
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_key_mode() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir()?;
+        let key = dir.path().join("id_test");
+        fs::write(&key, "key")?;
+        fs::set_permissions(&key, fs::Permissions::from_mode(0o600))?;
+        assert_eq!(key_mode(&fs::metadata(&key)?), Some(0o600));
+        // The file type bits are left out
+        fs::set_permissions(&key, fs::Permissions::from_mode(0o644))?;
+        assert_eq!(key_mode(&fs::metadata(&key)?), Some(0o644));
+        Ok(())
+    }
+
+    #[test]
+    fn test_host_and_port() {
+        assert_eq!(
+            host_and_port("github.com", 22),
+            ("github.com".to_string(), 22)
+        );
+        assert_eq!(
+            host_and_port("ssh.github.com:443", 22),
+            ("ssh.github.com".to_string(), 443)
+        );
+        assert_eq!(
+            host_and_port("https://git@Git.Corp.example.com:8443/", 443),
+            ("git.corp.example.com".to_string(), 8443)
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_check_ssh_keys() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir()?;
+        let key = dir.path().join("id_work");
+        fs::write(&key, "not a real key")?;
+        fs::set_permissions(&key, fs::Permissions::from_mode(0o600))?;
+        let mut work = profile("work");
+        work.ssh_key = Some(key.clone());
+        let mut home = profile("home");
+        home.ssh_key = Some(dir.path().join("id_missing"));
+
+        // A missing key fails
+        let mut report = Report::default();
+        check_ssh_keys(&mut report, &config(vec![work.clone(), home]));
+        assert_eq!((report.failures, report.warnings), (1, 0));
+
+        // So does a key others can read
+        fs::set_permissions(&key, fs::Permissions::from_mode(0o644))?;
+        let mut report = Report::default();
+        check_ssh_keys(&mut report, &config(vec![work.clone()]));
+        assert_eq!(report.failures, 1);
+
+        fs::set_permissions(&key, fs::Permissions::from_mode(0o600))?;
+        let mut report = Report::default();
+        check_ssh_keys(&mut report, &config(vec![work]));
+        assert_eq!((report.failures, report.warnings), (0, 0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_tokens() {
+        let today = Local::now().date_naive();
+        let creds = |expires_at: Option<chrono::NaiveDate>| {
+            let mut creds = HttpsCredentials::new(
+                "github.com".to_string(),
+                "jdoe".to_string(),
+                CredentialType::Token("ghp_abc".to_string()),
+            );
+            creds.token_expires_at = expires_at;
+            creds
+        };
+        let check = |creds: HttpsCredentials| {
+            let mut work = profile("work");
+            work.https_credentials = vec![creds];
+            let mut report = Report::default();
+            check_tokens(&mut report, &config(vec![work]));
+            (report.failures, report.warnings)
+        };
+
+        assert_eq!(check(creds(None)), (0, 0));
+        assert_eq!(check(creds(Some(today + Duration::days(90)))), (0, 0));
+        // An expired token warns; doctor asks no provider, so it doesn't fail
+        assert_eq!(check(creds(Some(today - Duration::days(1)))), (0, 1));
+        // Required scopes can't be checked without the recorded ones
+        let mut scoped = creds(None);
+        scoped.required_scopes = vec!["repo".to_string()];
+        assert_eq!(check(scoped.clone()), (0, 1));
+        scoped.token_scopes = Some(vec!["repo".to_string()]);
+        assert_eq!(check(scoped), (0, 0));
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_check_repo_remotes() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let repo = git2::Repository::init(dir.path())?;
+        repo.remote("origin", "git@github.com:acme/app.git")?;
+        let mut work = profile("work");
+        work.ssh_key_host = Some("github.com".to_string());
+        work.ssh_host_alias = Some("github.com-work".to_string());
+        let mut config = config(vec![work]);
+        let path = dir.path().to_string_lossy().into_owned();
+        config.state.repos.insert(path, "work".to_string());
+
+        // The remote reaches the host directly, so ssh picks the default key
+        let mut report = Report::default();
+        check_repo_remotes(&mut report, &config);
+        assert_eq!((report.failures, report.warnings), (0, 1));
+
+        repo.remote_set_url("origin", "git@github.com-work:acme/app.git")?;
+        let mut report = Report::default();
+        check_repo_remotes(&mut report, &config);
+        assert_eq!((report.failures, report.warnings), (0, 0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_finish() {
+        assert!(finish(&Report::default()).is_ok());
+        // Warnings alone don't fail scripts
+        let warned = Report {
+            warnings: 2,
+            failures: 0,
+        };
+        assert!(finish(&warned).is_ok());
+        let failed = Report {
+            warnings: 1,
+            failures: 1,
+        };
+        let err = finish(&failed).unwrap_err().to_string();
+        assert_eq!(err, "1 problem(s) and 1 warning(s) found.");
+    }
+}
@@ -1,11 +1,25 @@
 use anyhow::{bail, Context, Result};
 use colored::Colorize;
-use dialoguer::{theme::ColorfulTheme, Confirm, Input, Password};
-use std::path::PathBuf;
-
-use crate::config::{Config, CredentialType, HttpsCredentials};
-use crate::credentials::keyring::{delete_token, store_token}; // Added keyring imports
 
+use crate::commands::credentials::{
+    remove_https_credentials, replace_https_credentials, store_https_token,
+};
+use crate::commands::new::identity_suggestions;
+use crate::commands::rules::refresh_rules;
+use crate::commands::use_profile::validate_custom_config;
+use crate::config::{
+    same_host, AutoCrlf, Config, CredentialType, CustomValue, Eol, HttpsCredentials, SshHost,
+};
+use crate::credentials::cache;
+use crate::utils::mask::mask_secret;
+use crate::utils::output::{theme, Paint};
+use crate::utils::prompt::{
+    prompt_optional, prompt_required, prompt_suggested, Confirm, Input, Password,
+};
+use crate::utils::terminal::require_terminal;
+use crate::utils::{non_empty, normalize_path};
+
+#[allow(clippy::too_many_arguments)]
 pub fn execute(
     name: String,
     cli_user_name: Option<String>,
@@ -20,13 +34,20 @@ pub fn execute(
     cli_https_store_in_keychain: bool,
     cli_https_remove_credentials: bool,
     cli_ssh_key_host: Option<String>,
+    cli_autocrlf: Option<String>,
+    cli_eol: Option<String>,
+    cli_set_config: Vec<String>,
+    cli_unset_config: Vec<String>,
+    cli_ssh_managed: Option<bool>,
+    cli_ssh_match_dirs: Vec<String>,
+    cli_add_ssh_hosts: Vec<String>,
+    cli_remove_ssh_hosts: Vec<String>,
+    cli_notes: Option<String>,
+    no_validate: bool,
 ) -> Result<()> {
     let mut config = Config::load().context("Failed to load configuration.")?;
-
-    let profile_to_edit = config
-        .profiles
-        .get_mut(&name)
-        .ok_or_else(|| anyhow::anyhow!("Profile '{}' not found.", name.cyan()))?;
+    config.require_user_profile(&name, "edit")?;
+    let settings = config.settings.clone();
 
     let is_non_interactive = cli_user_name.is_some()
         || cli_user_email.is_some()
@@ -39,12 +60,33 @@ pub fn execute(
         // || cli_https_keychain_ref.is_some() // Removed
         || cli_https_store_in_keychain // This is a bool, presence means non-interactive intent if other flags are set or if it's true
         || cli_https_remove_credentials // Same for this flag
-        || cli_ssh_key_host.is_some();
+        || cli_ssh_key_host.is_some()
+        || cli_autocrlf.is_some()
+        || cli_eol.is_some()
+        || !cli_set_config.is_empty()
+        || !cli_unset_config.is_empty()
+        || cli_ssh_managed.is_some()
+        || !cli_ssh_match_dirs.is_empty()
+        || !cli_add_ssh_hosts.is_empty()
+        || !cli_remove_ssh_hosts.is_empty()
+        || cli_notes.is_some();
+
+    let (names, emails) = if is_non_interactive {
+        Default::default()
+    } else {
+        identity_suggestions(&config, Some(&name))
+    };
+
+    let profile_to_edit = config
+        .profiles
+        .get_mut(&name)
+        .ok_or_else(|| anyhow::anyhow!("Profile '{}' not found.", name.accent()))?;
+    let old_https_credentials = profile_to_edit.https_credentials.clone();
 
     if is_non_interactive {
         println!(
             "Editing profile '{}' non-interactively.",
-            name.cyan().bold()
+            name.accent().bold()
         );
 
         if let Some(uname) = cli_user_name {
@@ -54,7 +96,7 @@ pub fn execute(
             profile_to_edit.git_config.user_name = uname.trim().to_string();
             println!(
                 "  Updated user name to: {}",
-                profile_to_edit.git_config.user_name.green()
+                profile_to_edit.git_config.user_name.success()
             );
         }
 
@@ -65,17 +107,17 @@ pub fn execute(
             profile_to_edit.git_config.user_email = email.trim().to_string();
             println!(
                 "  Updated user email to: {}",
-                profile_to_edit.git_config.user_email.green()
+                profile_to_edit.git_config.user_email.success()
             );
         }
 
         if let Some(key) = cli_signing_key {
             if key.trim().is_empty() {
                 profile_to_edit.git_config.user_signingkey = None;
-                println!("  {} Git signing key.", "Removed".yellow());
+                println!("  {} Git signing key.", "Removed".warning());
             } else {
                 profile_to_edit.git_config.user_signingkey = Some(key.trim().to_string());
-                println!("  Updated Git signing key to: {}", key.trim().green());
+                println!("  Updated Git signing key to: {}", key.trim().success());
             }
         }
 
@@ -83,19 +125,24 @@ pub fn execute(
             if path.trim().is_empty() {
                 profile_to_edit.ssh_key = None;
                 profile_to_edit.ssh_key_host = None; // Clear host if key path is cleared
-                println!("  {} SSH key path and host.", "Removed".yellow());
+                profile_to_edit.ssh_key_fingerprint = None;
+                println!("  {} SSH key path and host.", "Removed".warning());
             } else {
-                profile_to_edit.ssh_key = Some(PathBuf::from(path.trim()));
-                println!("  Updated SSH key path to: {}", path.trim().green());
+                let ssh_key = normalize_path(&path);
+                if profile_to_edit.ssh_key.as_ref() != Some(&ssh_key) {
+                    profile_to_edit.ssh_key_fingerprint = None;
+                }
+                profile_to_edit.ssh_key = Some(ssh_key);
+                println!("  Updated SSH key path to: {}", path.trim().success());
                 // Handle ssh_key_host only if ssh_key_path was provided
                 if let Some(host) = cli_ssh_key_host.as_deref() {
                     // Use as_deref to work with &str
                     if host.trim().is_empty() {
                         profile_to_edit.ssh_key_host = None;
-                        println!("  {} SSH key host.", "Removed".yellow());
+                        println!("  {} SSH key host.", "Removed".warning());
                     } else {
                         profile_to_edit.ssh_key_host = Some(host.trim().to_string());
-                        println!("  Updated SSH key host to: {}", host.trim().green());
+                        println!("  Updated SSH key host to: {}", host.trim().success());
                     }
                 } else if profile_to_edit.ssh_key.is_some()
                     && profile_to_edit.ssh_key_host.is_none()
@@ -110,44 +157,149 @@ pub fn execute(
         if let Some(id) = cli_gpg_key_id {
             if id.trim().is_empty() {
                 profile_to_edit.gpg_key = None;
-                println!("  {} GPG key ID.", "Removed".yellow());
+                println!("  {} GPG key ID.", "Removed".warning());
             } else {
                 profile_to_edit.gpg_key = Some(id.trim().to_string());
-                println!("  Updated GPG key ID to: {}", id.trim().green());
+                println!("  Updated GPG key ID to: {}", id.trim().success());
             }
         }
 
-        // Handle HTTPS credentials in non-interactive mode
-        if cli_https_remove_credentials {
-            if let Some(existing_creds) = profile_to_edit.https_credentials.take() {
-                // Use take to remove it
-                if let CredentialType::KeychainRef(keychain_username) =
-                    existing_creds.credential_type
-                {
-                    match delete_token(&existing_creds.host, &keychain_username) {
-                        Ok(_) => println!(
-                            "  Successfully deleted token for {}@{} from keychain.",
-                            keychain_username.cyan(),
-                            existing_creds.host.green()
-                        ),
-                        Err(e) => eprintln!(
-                            "  {}: Failed to delete token for {}@{} from keychain: {}. Please remove it manually if needed.",
-                            "Warning".yellow(),
-                            keychain_username.cyan(),
-                            existing_creds.host.green(),
-                            e
-                        ),
-                    }
-                }
+        if let Some(value) = cli_autocrlf {
+            if value.trim().is_empty() {
+                profile_to_edit.git_config.core_autocrlf = None;
+                println!("  {} core.autocrlf.", "Removed".warning());
+            } else {
+                let autocrlf: AutoCrlf = value.parse()?;
+                profile_to_edit.git_config.core_autocrlf = Some(autocrlf);
+                println!(
+                    "  Updated core.autocrlf to: {}",
+                    autocrlf.as_str().success()
+                );
+            }
+        }
+
+        if let Some(value) = cli_eol {
+            if value.trim().is_empty() {
+                profile_to_edit.git_config.core_eol = None;
+                println!("  {} core.eol.", "Removed".warning());
+            } else {
+                let eol: Eol = value.parse()?;
+                profile_to_edit.git_config.core_eol = Some(eol);
+                println!("  Updated core.eol to: {}", eol.as_str().success());
+            }
+        }
+
+        if let Some(managed) = cli_ssh_managed {
+            profile_to_edit.ssh_managed = managed;
+            println!(
+                "  {} the SSH Host entry in ~/.ssh/config. '{}' rewrites it.",
+                if managed {
+                    "gitp now manages".success()
+                } else {
+                    "gitp no longer manages".warning()
+                },
+                "gitp use".accent()
+            );
+        }
+
+        if !cli_ssh_match_dirs.is_empty() {
+            profile_to_edit.ssh_match_dirs = cli_ssh_match_dirs
+                .iter()
+                .filter(|dir| !dir.trim().is_empty())
+                .map(|dir| normalize_path(dir).to_string_lossy().into_owned())
+                .collect();
+            if profile_to_edit.ssh_match_dirs.is_empty() {
+                println!("  {} the SSH match directories.", "Removed".warning());
+            } else {
                 println!(
-                    "  {} HTTPS credentials for host '{}'.",
-                    "Removed".yellow(),
-                    existing_creds.host.green()
+                    "  SSH key limited to: {}",
+                    profile_to_edit.ssh_match_dirs.join(", ").success()
                 );
+            }
+        }
+
+        for host in &cli_remove_ssh_hosts {
+            let before = profile_to_edit.ssh_hosts.len();
+            profile_to_edit
+                .ssh_hosts
+                .retain(|entry| !same_host(&entry.host, host));
+            if profile_to_edit.ssh_hosts.len() < before {
+                println!("  {} the SSH key for {}.", "Removed".warning(), host.trim());
+            } else {
+                println!("  The profile has no SSH key added for {}.", host.trim());
+            }
+        }
+        for arg in &cli_add_ssh_hosts {
+            let entry = parse_ssh_host(arg)?;
+            println!(
+                "  SSH key for {}: {}",
+                entry.host.success(),
+                entry.key.display()
+            );
+            match profile_to_edit
+                .ssh_hosts
+                .iter_mut()
+                .find(|other| same_host(&other.host, &entry.host))
+            {
+                Some(existing) => *existing = entry,
+                None => profile_to_edit.ssh_hosts.push(entry),
+            }
+        }
+
+        if let Some(notes) = cli_notes {
+            profile_to_edit.notes = non_empty(&notes);
+            match &profile_to_edit.notes {
+                Some(notes) => println!("  Updated notes to: {}", notes.success()),
+                None => println!("  {} the notes.", "Removed".warning()),
+            }
+        }
+
+        for key in &cli_unset_config {
+            if profile_to_edit.custom_config.remove(key.trim()).is_some() {
+                println!("  {} {}.", "Removed".warning(), key.trim());
             } else {
+                println!("  {} is not set in this profile.", key.trim());
+            }
+        }
+
+        for (key, values) in parse_set_config(&cli_set_config)? {
+            println!("  Set {} to: {}", key, values.join(", ").success());
+            let value = match <[String; 1]>::try_from(values) {
+                Ok([value]) => CustomValue::One(value),
+                Err(values) => CustomValue::Many(values),
+            };
+            profile_to_edit.custom_config.insert(key, value);
+        }
+
+        // Handle HTTPS credentials in non-interactive mode
+        if cli_https_remove_credentials {
+            let hosts = match cli_https_host.as_deref().map(str::trim) {
+                Some(host) => vec![host.to_string()],
+                None => profile_to_edit
+                    .https_hosts()
+                    .into_iter()
+                    .map(str::to_string)
+                    .collect(),
+            };
+            let mut removed = 0;
+            for host in &hosts {
+                if let Some(existing_creds) = remove_https_credentials(
+                    &settings,
+                    &mut profile_to_edit.https_credentials,
+                    host,
+                ) {
+                    println!(
+                        "  {} HTTPS credentials for host '{}'.",
+                        "Removed".warning(),
+                        existing_creds.host.success()
+                    );
+                    removed += 1;
+                }
+            }
+            if removed == 0 {
                 println!(
                     "  No HTTPS credentials found for profile '{}' to remove.",
-                    name.cyan()
+                    name.accent()
                 );
             }
         } else if let Some(host_cli_val) = &cli_https_host {
@@ -164,7 +316,7 @@ pub fn execute(
                 // For robustness, treat as a warning and no-op for HTTPS credentials.
                 eprintln!(
                     "  {}: --https-host was provided as empty when not using --https-remove-credentials. No changes made to HTTPS credentials.",
-                    "Warning".yellow()
+                    "Warning".warning()
                 );
             } else {
                 // Host is not empty. Username must be present (clap: requires = "https_host" on https_username).
@@ -180,87 +332,26 @@ pub fn execute(
                 if let Some(new_token_val) = &cli_https_token {
                     let new_token = new_token_val.trim().to_string();
                     if new_token.is_empty() {
-                        bail!("HTTPS token cannot be set to empty in non-interactive mode. Use --https-remove-credentials to remove all HTTPS credentials, or provide a valid token.");
-                    }
-
-                    // Check if existing credentials need keychain cleanup
-                    let mut old_keychain_creds_to_delete: Option<(String, String)> = None;
-                    if let Some(ref existing_creds) = profile_to_edit.https_credentials {
-                        if let CredentialType::KeychainRef(ref old_keychain_username) =
-                            existing_creds.credential_type
-                        {
-                            // Conditions for deleting old keychain entry:
-                            // 1. Host is changing.
-                            // 2. Username (keychain service user) is changing for the same host.
-                            // 3. Host and username are the same, but user wants to switch from keychain to plain token.
-                            if existing_creds.host != new_host
-                                || (existing_creds.host == new_host
-                                    && old_keychain_username != &new_username)
-                                || (existing_creds.host == new_host
-                                    && old_keychain_username == &new_username
-                                    && !cli_https_store_in_keychain)
-                            {
-                                old_keychain_creds_to_delete = Some((
-                                    existing_creds.host.clone(),
-                                    old_keychain_username.clone(),
-                                ));
-                            }
-                        }
-                    }
-
-                    if let Some((old_h, old_u)) = old_keychain_creds_to_delete {
-                        match delete_token(&old_h, &old_u) {
-                            Ok(_) => println!(
-                                "  Successfully deleted previous token for {}@{} from keychain.",
-                                old_u.cyan(),
-                                old_h.green()
-                            ),
-                            Err(e) => eprintln!(
-                                "  {}: Failed to delete previous token for {}@{} from keychain: {}. Please check manually.",
-                                "Warning".yellow(),
-                                old_u.cyan(),
-                                old_h.green(),
-                                e
-                            ),
-                        }
+                        bail!("HTTPS token cannot be set to empty in non-interactive mode. Use --https-remove-credentials to remove HTTPS credentials, or provide a valid token.");
                     }
 
-                    let final_credential_type;
-                    if cli_https_store_in_keychain {
-                        match store_token(&new_host, &new_username, &new_token) {
-                            Ok(_) => {
-                                final_credential_type =
-                                    CredentialType::KeychainRef(new_username.clone());
-                                println!(
-                                    "  Successfully stored HTTPS token for {}@{} in keychain.",
-                                    new_username.cyan(),
-                                    new_host.green()
-                                );
-                            }
-                            Err(e) => {
-                                eprintln!(
-                                    "  {}: Failed to store token in keychain: {}. Falling back to plain text storage in config.",
-                                    "Warning".yellow(),
-                                    e
-                                );
-                                final_credential_type = CredentialType::Token(new_token.clone());
-                            }
-                        }
-                    } else {
-                        final_credential_type = CredentialType::Token(new_token.clone());
-                        println!(
-                            "  Set HTTPS token for {}@{} (stored in config file).",
-                            new_username.cyan(),
-                            new_host.green()
-                        );
-                    }
-
-                    profile_to_edit.https_credentials = Some(HttpsCredentials {
-                        host: new_host.clone(),
-                        username: new_username.clone(),
-                        credential_type: final_credential_type,
-                    });
-                    println!("  Updated HTTPS credentials for profile '{}'.", name.cyan());
+                    let final_credential_type = store_https_token(
+                        &settings,
+                        &new_host,
+                        &new_username,
+                        &new_token,
+                        cli_https_store_in_keychain,
+                    )?;
+                    println!(
+                        "  Updated HTTPS credentials for host '{}' of profile '{}'.",
+                        new_host.success(),
+                        name.accent()
+                    );
+                    replace_https_credentials(
+                        &settings,
+                        &mut profile_to_edit.https_credentials,
+                        HttpsCredentials::new(new_host, new_username, final_credential_type),
+                    );
                 } else {
                     // --https-host and --https-username provided, but --https-token is None.
                     // This means the user is trying to change host/username without providing a new token.
@@ -270,7 +361,7 @@ pub fn execute(
                     // A new entry is not created. User should use --https-remove-credentials and then add new ones, or provide all three.
                     println!(
                         "  {}: --https-host and --https-username provided without --https-token. ",
-                        "Info".blue()
+                        "Info".accent()
                     );
                     println!("  To set or update a token, please provide --https-host, --https-username, and --https-token together.");
                     println!("  No changes made to HTTPS credentials based on host/username alone without a token.");
@@ -282,81 +373,81 @@ pub fn execute(
             // This branch is needed to ensure the if/else if chain has a fallthrough for the Result type if other non-interactive flags were set.
         }
     } else {
-        println!("Editing profile: {}", name.cyan().bold());
+        require_terminal(
+            "Editing a profile interactively",
+            "Pass the fields to change as flags, e.g. --user-email.",
+        )?;
+        println!("Editing profile: {}", name.accent().bold());
         println!("{}", "(Press Enter to keep current value, if any)".dimmed());
         // HTTPS Credentials Interactive Editing
         println!();
         println!("{}", "HTTPS Credentials Configuration:".bold());
 
         let current_https_creds = profile_to_edit.https_credentials.clone();
-        if let Some(creds) = &current_https_creds {
-            println!("  Current host: {}", creds.host.yellow());
-            println!("  Current username: {}", creds.username.yellow());
+        for creds in &current_https_creds {
+            println!("  Current host: {}", creds.host.warning());
+            println!("  Current username: {}", creds.username.warning());
             match &creds.credential_type {
-                CredentialType::Token(_) => {
-                    println!("  Current type: {}", "Token (value is masked)".yellow())
-                }
+                CredentialType::Token(token) => println!(
+                    "  Current type: {}",
+                    format!("Token ({})", mask_secret(token)).warning()
+                ),
                 CredentialType::KeychainRef(r) => {
-                    println!("  Current type: Keychain Reference ({})", r.yellow())
+                    println!("  Current type: Keychain Reference ({})", r.warning())
+                }
+                CredentialType::FileStoreRef(r) => {
+                    println!(
+                        "  Current type: Encrypted File Store Reference ({})",
+                        r.warning()
+                    )
                 }
             }
-        } else {
+        }
+        if current_https_creds.is_empty() {
             println!("  {}", "No HTTPS credentials currently set.".dimmed());
         }
 
-        if Confirm::with_theme(&ColorfulTheme::default())
+        if Confirm::with_theme(&theme())
             .with_prompt("Do you want to configure or update HTTPS credentials?")
-            .default(current_https_creds.is_some()) // Default to yes if creds exist, no otherwise
+            .default(!current_https_creds.is_empty()) // Default to yes if creds exist, no otherwise
             .interact()?
         {
-            let https_host_input: String = Input::with_theme(&ColorfulTheme::default())
+            let https_host_input: String = Input::with_theme(&theme())
                 .with_prompt(
-                    "HTTPS Host (e.g., github.com, leave blank to remove if currently set)",
-                )
-                .default(
-                    current_https_creds
-                        .as_ref()
-                        .map_or_else(String::new, |c| c.host.clone()),
+                    "HTTPS Host to add or update (e.g., github.com, leave blank to remove all currently set)",
                 )
+                .default(match current_https_creds.as_slice() {
+                    [only] => only.host.clone(),
+                    _ => String::new(),
+                })
                 .allow_empty(true)
                 .interact_text()
                 .context("Failed to get HTTPS host input.")?;
 
             if https_host_input.trim().is_empty() {
-                if let Some(ref actual_current_creds) = current_https_creds {
-                    // Use the cloned current_https_creds
-                    if let CredentialType::KeychainRef(ref keychain_username_to_delete) =
-                        actual_current_creds.credential_type
-                    {
-                        match delete_token(&actual_current_creds.host, keychain_username_to_delete) {
-                            Ok(_) => println!(
-                                "  Successfully deleted token for {}@{} from keychain.",
-                                keychain_username_to_delete.cyan(),
-                                actual_current_creds.host.green()
-                            ),
-                            Err(e) => eprintln!(
-                                "  {}: Failed to delete token for {}@{} from keychain: {}. Please remove it manually if needed.",
-                                "Warning".yellow(),
-                                keychain_username_to_delete.cyan(),
-                                actual_current_creds.host.green(),
-                                e
-                            ),
-                        }
-                    }
-                    profile_to_edit.https_credentials = None;
-                    println!("  {}", "HTTPS credentials removed.".yellow());
+                let mut removed = false;
+                for creds in &current_https_creds {
+                    removed |= remove_https_credentials(
+                        &settings,
+                        &mut profile_to_edit.https_credentials,
+                        &creds.host,
+                    )
+                    .is_some();
+                }
+                if removed {
+                    println!("  {}", "HTTPS credentials removed.".warning());
                 } else {
                     // No current credentials to remove, so do nothing.
                     println!("  No HTTPS credentials were set to remove.");
                 }
             } else {
                 let new_host = https_host_input.trim().to_string();
-                let new_username: String = Input::with_theme(&ColorfulTheme::default())
+                let new_username: String = Input::with_theme(&theme())
                     .with_prompt("HTTPS Username")
                     .default(
                         current_https_creds
-                            .as_ref()
-                            .filter(|c| c.host == new_host)
+                            .iter()
+                            .find(|c| same_host(&c.host, &new_host))
                             .map_or_else(String::new, |c| c.username.clone()),
                     )
                     .interact_text()
@@ -367,12 +458,12 @@ pub fn execute(
                 }
                 let actual_new_username = new_username.trim().to_string();
 
-                let store_in_keychain = Confirm::with_theme(&ColorfulTheme::default())
+                let store_in_keychain = Confirm::with_theme(&theme())
                     .with_prompt("Store this HTTPS token securely in the system keychain?")
                     .default(true)
                     .interact()?;
 
-                let new_token: String = Password::with_theme(&ColorfulTheme::default())
+                let new_token: String = Password::with_theme(&theme())
                     .with_prompt("Enter Personal Access Token")
                     .interact()
                     .context("Failed to get token input.")?;
@@ -381,250 +472,241 @@ pub fn execute(
                 }
                 let actual_new_token = new_token.trim().to_string();
 
-                // Delete old keychain entry if necessary (before setting new credentials)
-                if let Some(ref old_creds) = current_https_creds {
-                    if let CredentialType::KeychainRef(ref old_keychain_username) =
-                        old_creds.credential_type
-                    {
-                        let changing_host = old_creds.host != new_host;
-                        let changing_username = old_keychain_username != &actual_new_username;
-                        let switching_to_plain_text = !store_in_keychain;
-
-                        if changing_host
-                            || (old_creds.host == new_host && changing_username)
-                            || (old_creds.host == new_host
-                                && old_keychain_username == &actual_new_username
-                                && switching_to_plain_text)
-                        {
-                            match delete_token(&old_creds.host, old_keychain_username) {
-                                Ok(_) => println!(
-                                    "  Successfully deleted previous token for {}@{} from keychain.",
-                                    old_keychain_username.cyan(),
-                                    old_creds.host.green()
-                                ),
-                                Err(e) => eprintln!(
-                                    "  {}: Failed to delete previous token for {}@{} from keychain: {}. Please check manually.",
-                                    "Warning".yellow(),
-                                    old_keychain_username.cyan(),
-                                    old_creds.host.green(),
-                                    e
-                                ),
-                            }
-                        }
-                    }
-                }
-
-                let final_credential_type;
-                if store_in_keychain {
-                    match store_token(&new_host, &actual_new_username, &actual_new_token) {
-                        Ok(_) => {
-                            final_credential_type =
-                                CredentialType::KeychainRef(actual_new_username.clone());
-                            println!(
-                                "  Successfully stored HTTPS token for {}@{} in keychain.",
-                                actual_new_username.cyan(),
-                                new_host.green()
-                            );
-                        }
-                        Err(e) => {
-                            eprintln!(
-                                "  {}: Failed to store token in keychain: {}. Falling back to plain text storage in config.",
-                                "Warning".yellow(),
-                                e
-                            );
-                            final_credential_type = CredentialType::Token(actual_new_token.clone());
-                        }
-                    }
-                } else {
-                    final_credential_type = CredentialType::Token(actual_new_token.clone());
-                    println!(
-                        "  Set HTTPS token for {}@{} (stored in config file).",
-                        actual_new_username.cyan(),
-                        new_host.green()
-                    );
-                }
-
-                profile_to_edit.https_credentials = Some(HttpsCredentials {
-                    host: new_host,
-                    username: actual_new_username,
-                    credential_type: final_credential_type,
-                });
+                let final_credential_type = store_https_token(
+                    &settings,
+                    &new_host,
+                    &actual_new_username,
+                    &actual_new_token,
+                    store_in_keychain,
+                )?;
+                replace_https_credentials(
+                    &settings,
+                    &mut profile_to_edit.https_credentials,
+                    HttpsCredentials::new(new_host, actual_new_username, final_credential_type),
+                );
                 println!("  HTTPS credentials updated.");
             }
-        } else if profile_to_edit.https_credentials.is_some() {
+        } else if !profile_to_edit.https_credentials.is_empty() {
             // User chose not to configure/update, but creds exist
-            if !Confirm::with_theme(&ColorfulTheme::default())
+            if !Confirm::with_theme(&theme())
                 .with_prompt("Keep existing HTTPS credentials?")
                 .default(true)
                 .interact()?
             {
                 // User chose to remove existing credentials
-                if let Some(ref actual_current_creds) = current_https_creds {
-                    // Use the cloned current_https_creds
-                    if let CredentialType::KeychainRef(ref keychain_username_to_delete) =
-                        actual_current_creds.credential_type
-                    {
-                        match delete_token(&actual_current_creds.host, keychain_username_to_delete) {
-                            Ok(_) => println!(
-                                "  Successfully deleted token for {}@{} from keychain.",
-                                keychain_username_to_delete.cyan(),
-                                actual_current_creds.host.green()
-                            ),
-                            Err(e) => eprintln!(
-                                "  {}: Failed to delete token for {}@{} from keychain: {}. Please remove it manually if needed.",
-                                "Warning".yellow(),
-                                keychain_username_to_delete.cyan(),
-                                actual_current_creds.host.green(),
-                                e
-                            ),
-                        }
-                    }
+                for creds in &current_https_creds {
+                    remove_https_credentials(
+                        &settings,
+                        &mut profile_to_edit.https_credentials,
+                        &creds.host,
+                    );
                 }
-                profile_to_edit.https_credentials = None;
                 println!(
                     "  {}",
-                    "Existing HTTPS credentials removed as per choice.".yellow()
+                    "Existing HTTPS credentials removed as per choice.".warning()
                 );
             }
         }
         println!(); // Add a blank line after HTTPS config section
 
-        // User Name
-        let new_user_name = Input::with_theme(&ColorfulTheme::default())
-            .with_prompt("User name")
-            .default(profile_to_edit.git_config.user_name.clone())
-            .interact_text()
-            .context("Failed to get user name input.")?;
-        if new_user_name.trim().is_empty() {
-            bail!("User name cannot be empty. Edit aborted.");
-        }
-        profile_to_edit.git_config.user_name = new_user_name.trim().to_string();
-
-        // User Email
-        let new_user_email = Input::with_theme(&ColorfulTheme::default())
-            .with_prompt("User email")
-            .default(profile_to_edit.git_config.user_email.clone())
-            .interact_text()
-            .context("Failed to get user email input.")?;
-        if new_user_email.trim().is_empty() {
-            bail!("User email cannot be empty. Edit aborted.");
+        let git_config = &mut profile_to_edit.git_config;
+        git_config.user_name = prompt_suggested(
+            "User name",
+            Some(&git_config.user_name),
+            "User name",
+            &names,
+        )?;
+        git_config.user_email = prompt_suggested(
+            "User email",
+            Some(&git_config.user_email),
+            "User email",
+            &emails,
+        )?;
+        git_config.user_signingkey = prompt_optional(
+            "Git User Signing Key (for commit signing, e.g., GPG key ID or SSH key path, leave blank for none)",
+            git_config.user_signingkey.as_deref(),
+        )?;
+
+        let current_ssh_key = profile_to_edit
+            .ssh_key
+            .as_ref()
+            .map(|p| p.to_string_lossy().into_owned());
+        match prompt_optional(
+            "Path to SSH private key (leave blank for none)",
+            current_ssh_key.as_deref(),
+        )? {
+            None => {
+                profile_to_edit.ssh_key = None;
+                profile_to_edit.ssh_key_host = None; // Clear host if key path is cleared
+                profile_to_edit.ssh_key_fingerprint = None;
+            }
+            Some(ssh_key) => {
+                let ssh_key = normalize_path(&ssh_key);
+                if profile_to_edit.ssh_key.as_ref() != Some(&ssh_key) {
+                    profile_to_edit.ssh_key_fingerprint = None;
+                }
+                profile_to_edit.ssh_key = Some(ssh_key);
+                // If a new SSH key path is set, prompt for the host
+                profile_to_edit.ssh_key_host = Some(prompt_required(
+                    "Enter SSH key host (e.g., github.com, required if SSH key is set)",
+                    profile_to_edit.ssh_key_host.as_deref(),
+                    "SSH key host",
+                )?);
+            }
         }
-        profile_to_edit.git_config.user_email = new_user_email.trim().to_string();
-
-        // Git User Signing Key
-        let new_signing_key_str = Input::with_theme(&ColorfulTheme::default())
-            .with_prompt("Git User Signing Key (for commit signing, e.g., GPG key ID or SSH key path, leave blank for none)")
-            .default(profile_to_edit.git_config.user_signingkey.clone().unwrap_or_default())
-            .allow_empty(true)
-            .interact_text()
-            .context("Failed to get signing key input.")?;
-        profile_to_edit.git_config.user_signingkey = if new_signing_key_str.trim().is_empty() {
-            None
-        } else {
-            Some(new_signing_key_str.trim().to_string())
-        };
 
-        // SSH Key Path
-        let new_ssh_key_str = Input::with_theme(&ColorfulTheme::default())
-            .with_prompt("Path to SSH private key (leave blank for none)")
-            .default(
-                profile_to_edit
-                    .ssh_key
-                    .as_ref()
-                    .map(|p| p.to_string_lossy().into_owned())
-                    .unwrap_or_default(),
-            )
-            .allow_empty(true)
-            .interact_text()
-            .context("Failed to get SSH key path input.")?;
-        if new_ssh_key_str.trim().is_empty() {
-            profile_to_edit.ssh_key = None;
-            profile_to_edit.ssh_key_host = None; // Clear host if key path is cleared
-        } else {
-            profile_to_edit.ssh_key = Some(PathBuf::from(new_ssh_key_str.trim()));
-            // If a new SSH key path is set, prompt for the host
-            let new_ssh_key_host_str = Input::with_theme(&ColorfulTheme::default())
-                .with_prompt("Enter SSH key host (e.g., github.com, required if SSH key is set)")
-                .default(profile_to_edit.ssh_key_host.clone().unwrap_or_default())
-                .allow_empty(false) // Host cannot be empty if key is provided
-                .interact_text()
-                .context("Failed to get SSH key host input.")?;
-            if new_ssh_key_host_str.trim().is_empty() {
-                // Should not happen due to allow_empty(false)
-                // This case implies an issue or a desire to clear, but validation will prevent empty if key is set.
-                // For safety, if somehow empty, treat as wanting to clear, though validation should catch this logic error.
-                profile_to_edit.ssh_key_host = None;
-            } else {
-                profile_to_edit.ssh_key_host = Some(new_ssh_key_host_str.trim().to_string());
-            }
-        };
+        profile_to_edit.gpg_key = prompt_optional(
+            "Associated GPG Key ID (optional, for other GPG uses, leave blank for none)",
+            profile_to_edit.gpg_key.as_deref(),
+        )?;
 
-        // Associated GPG Key ID
-        let new_gpg_key_str = Input::with_theme(&ColorfulTheme::default())
-            .with_prompt(
-                "Associated GPG Key ID (optional, for other GPG uses, leave blank for none)",
-            )
-            .default(profile_to_edit.gpg_key.clone().unwrap_or_default())
-            .allow_empty(true)
-            .interact_text()
-            .context("Failed to get GPG key ID input.")?;
-        profile_to_edit.gpg_key = if new_gpg_key_str.trim().is_empty() {
-            None
-        } else {
-            Some(new_gpg_key_str.trim().to_string())
-        };
+        crate::commands::new::prompt_line_endings(&mut profile_to_edit.git_config)?;
+
+        profile_to_edit.notes = prompt_optional(
+            "Notes, e.g. \"requires corp VPN\" (shown when its hosts can't be reached, leave blank for none)",
+            profile_to_edit.notes.as_deref(),
+        )?;
+    }
+
+    // New credentials keep the session cache setting and required scopes of the ones they
+    // replace
+    for new in &mut profile_to_edit.https_credentials {
+        if let Some(old) = old_https_credentials
+            .iter()
+            .find(|old| same_host(&old.host, &new.host))
+        {
+            new.cache_minutes = new.cache_minutes.or(old.cache_minutes);
+            if new.required_scopes.is_empty() {
+                new.required_scopes = old.required_scopes.clone();
+            }
+        }
     }
 
     // Validate the modified profile
     if let Err(validation_error) = profile_to_edit.validate() {
-        let error_message = match validation_error {
-            crate::config::ValidationError::EmptyName => {
-                "Profile name cannot be empty.".to_string()
-            }
-            crate::config::ValidationError::EmptyUserName => {
-                "User name cannot be empty.".to_string()
-            }
-            crate::config::ValidationError::EmptyEmail => "User email cannot be empty.".to_string(),
-            crate::config::ValidationError::InvalidEmail(email) => {
-                format!("Invalid email format: '{}'.", email)
-            }
-            crate::config::ValidationError::SshKeyNotFound(path) => {
-                format!("SSH key not found: '{}'.", path.display())
-            }
-            crate::config::ValidationError::InvalidGpgKeyFormat(key) => {
-                format!(
-                    "Invalid GPG key format for '{}'. Expected 8, 16, or 40 hex characters.",
-                    key
-                )
-            }
-            crate::config::ValidationError::EmptySshKeyHost => {
-                "SSH key host cannot be empty when an SSH key is provided.".to_string()
-            }
-            crate::config::ValidationError::EmptyHttpsHost => {
-                "HTTPS credentials host cannot be empty.".to_string()
-            }
-            crate::config::ValidationError::EmptyHttpsUsername => {
-                "HTTPS credentials username cannot be empty.".to_string()
-            }
-            crate::config::ValidationError::EmptyHttpsToken => {
-                "HTTPS credentials token cannot be empty when type is Token.".to_string()
-            }
-            crate::config::ValidationError::EmptyHttpsKeychainRef => {
-                "HTTPS credentials keychain reference cannot be empty when type is KeychainRef."
-                    .to_string()
-            }
-        };
         bail!(
             "Profile validation failed after edits: {}\nChanges not saved.",
-            error_message.red()
+            validation_error.user_message().error()
         );
     }
 
+    if !no_validate {
+        if let Err(e) = validate_custom_config(profile_to_edit) {
+            bail!("{}\nChanges not saved.", e);
+        }
+    }
+
+    let changed: Vec<HttpsCredentials> = old_https_credentials
+        .into_iter()
+        .filter(|old| {
+            old.cache_minutes.is_some() && !profile_to_edit.https_credentials.contains(old)
+        })
+        .collect();
     config
         .save()
         .context("Failed to save configuration after editing profile.")?;
+    // Old tokens must not be answered from the session cache
+    for old in &changed {
+        let _ = cache::forget(old);
+    }
 
-    println!("Profile '{}' updated successfully.", name.green());
+    if !config.profiles[&name].auto_dirs.is_empty() {
+        refresh_rules(&config);
+    }
+
+    println!("Profile '{}' updated successfully.", name.success());
 
     Ok(())
 }
+
+/// Parses an `--add-ssh-host [USER@]HOST=KEY_PATH` argument.
+fn parse_ssh_host(arg: &str) -> Result<SshHost> {
+    let Some((target, key)) = arg.split_once('=') else {
+        bail!(
+            "Expected [USER@]HOST=KEY_PATH for --add-ssh-host, got '{}'.",
+            arg
+        );
+    };
+    let (user, host) = match target.trim().rsplit_once('@') {
+        Some((user, host)) => (non_empty(user), host.trim()),
+        None => (None, target.trim()),
+    };
+    if host.is_empty() || key.trim().is_empty() {
+        bail!(
+            "Expected [USER@]HOST=KEY_PATH for --add-ssh-host, got '{}'.",
+            arg
+        );
+    }
+    Ok(SshHost {
+        host: host.to_string(),
+        key: normalize_path(key.trim()),
+        user,
+    })
+}
+
+/// Groups `--set-config KEY=VALUE` arguments by key, keeping the order of repeated keys' values.
+fn parse_set_config(args: &[String]) -> Result<Vec<(String, Vec<String>)>> {
+    let mut entries: Vec<(String, Vec<String>)> = Vec::new();
+    for arg in args {
+        let Some((key, value)) = arg.split_once('=') else {
+            bail!("--set-config expects KEY=VALUE, got '{}'.", arg);
+        };
+        let key = key.trim();
+        if key.is_empty() || !key.contains('.') {
+            bail!(
+                "'{}' is not a git config key; keys look like section.name.",
+                key
+            );
+        }
+        let value = value.trim().to_string();
+        match entries.iter_mut().find(|(k, _)| k == key) {
+            Some((_, values)) => values.push(value),
+            None => entries.push((key.to_string(), vec![value])),
+        }
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_parse_set_config() -> Result<()> {
+        let args = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let entries = parse_set_config(&args(&[
+            "pull.rebase=true",
+            "url.git@work:.insteadOf=https://github.com/work/",
+            "url.git@work:.insteadOf=https://gitlab.com/work/",
+        ]))?;
+        assert_eq!(
+            entries,
+            [
+                ("pull.rebase".to_string(), args(&["true"])),
+                (
+                    "url.git@work:.insteadOf".to_string(),
+                    args(&["https://github.com/work/", "https://gitlab.com/work/"])
+                ),
+            ]
+        );
+        assert!(parse_set_config(&args(&["pull.rebase"])).is_err());
+        assert!(parse_set_config(&args(&["rebase=true"])).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_ssh_host() -> Result<()> {
+        let entry = parse_ssh_host("gitlab.com=/keys/id_gitlab")?;
+        assert_eq!(entry.host, "gitlab.com");
+        assert_eq!(entry.key, PathBuf::from("/keys/id_gitlab"));
+        assert_eq!(entry.user, None);
+        let entry = parse_ssh_host(" gitolite@git.corp.example.com = /keys/id_corp")?;
+        assert_eq!(entry.host, "git.corp.example.com");
+        assert_eq!(entry.user.as_deref(), Some("gitolite"));
+        for bad in ["gitlab.com", "=/keys/id", "gitlab.com= "] {
+            assert!(parse_ssh_host(bad).is_err(), "{}", bad);
+        }
+        Ok(())
+    }
+}
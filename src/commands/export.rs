@@ -1,36 +1,114 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use colored::Colorize;
 use std::fs;
 use std::io::{self, Write};
+use std::path::Path;
 
-use crate::config::Config;
+use crate::cli::ExportFormat;
+use crate::commands::use_profile::{remote_signing, ssh_entry};
+use crate::config::storage::open_store;
+use crate::config::system::system_profiles;
+use crate::config::{is_host_pattern, Config, HostSigning, Profile};
+use crate::credentials::sealed::{encrypt_secrets, obtain_passphrase};
+use crate::git::get_repo_root;
+use crate::providers::DEFAULT_SSH_USER;
+use crate::ssh::ssh_command;
+use crate::ssh::ssh_config::render_entry;
+use crate::utils::mask::{redact_profile, revealed, REDACTED};
+use crate::utils::output::Paint;
+use crate::utils::quote::{gitconfig_subsection, gitconfig_value};
 
-pub fn execute(profile_name: String, output_path: Option<String>) -> Result<()> {
+pub fn execute(
+    profile_name: String,
+    output_path: Option<String>,
+    format: ExportFormat,
+    encrypt: bool,
+    effective: bool,
+) -> Result<()> {
     let config = Config::load().context("Failed to load configuration.")?;
 
     let profile = config
         .profiles
         .get(&profile_name)
-        .ok_or_else(|| anyhow::anyhow!("Profile '{}' not found.", profile_name.yellow()))?;
+        .ok_or_else(|| anyhow::anyhow!("Profile '{}' not found.", profile_name.warning()))?;
+    // Printed to a terminal the export is a preview; files, pipes and sealed exports keep the
+    // secrets, which `gitp import` needs
+    let mut masked = profile.clone();
+    redact_profile(&mut masked);
+    let preview = output_path.is_none()
+        && !encrypt
+        && !revealed()
+        && atty::is(atty::Stream::Stdout)
+        && masked != *profile;
+    let profile = if preview { &masked } else { profile };
 
-    let toml_string =
-        toml::to_string_pretty(profile).context("Failed to serialize profile to TOML.")?;
+    if encrypt && format != ExportFormat::Toml {
+        bail!("--encrypt-secrets only applies to TOML exports; the other formats never include secrets.");
+    }
+    if effective && (encrypt || format != ExportFormat::Toml) {
+        bail!(
+            "--effective writes gitp's TOML and can't be combined with --as or --encrypt-secrets."
+        );
+    }
+
+    let output = match format {
+        ExportFormat::Toml if effective => {
+            let origin = match config.system_profile_path(&profile_name) {
+                Some(path) => format!("{} (system store, read-only)", path.display()),
+                None => open_store()?.paths().first().map_or_else(
+                    || "your configuration".to_string(),
+                    |p| p.display().to_string(),
+                ),
+            };
+            let hidden = system_profiles()
+                .get(&profile_name)
+                .filter(|_| config.system_profile_path(&profile_name).is_none())
+                .map(|system| system.path.as_path());
+            // host_signing depends on the remote of the repository the export runs in
+            let signing = match get_repo_root().ok().flatten() {
+                Some(_) => remote_signing(&config, profile)?,
+                None => None,
+            };
+            render_effective(&config, profile, &origin, hidden, signing)?
+        }
+        ExportFormat::Toml if encrypt => {
+            let mut sealed = profile.clone();
+            let passphrase = obtain_passphrase(true)?;
+            let count = encrypt_secrets(&mut sealed, &passphrase)?;
+            if count == 0 {
+                eprintln!(
+                    "  {}: profile '{}' has no token or secret-named values to encrypt.",
+                    "Note".dimmed(),
+                    profile_name
+                );
+            }
+            toml::to_string_pretty(&sealed).context("Failed to serialize profile to TOML.")?
+        }
+        ExportFormat::Toml => {
+            toml::to_string_pretty(profile).context("Failed to serialize profile to TOML.")?
+        }
+        ExportFormat::Gitconfig => render_gitconfig(profile),
+        ExportFormat::Sshconfig => render_sshconfig(
+            profile,
+            config.settings.ssh_security_key_provider.as_deref(),
+        )?,
+    };
 
     match output_path {
         Some(path) => {
-            fs::write(&path, toml_string)
+            fs::write(&path, output)
                 .with_context(|| format!("Failed to write profile to file '{}'", path))?;
             println!(
                 "Profile '{}' exported successfully to '{}'.",
-                profile_name.cyan(),
-                path.green()
+                profile_name.accent(),
+                path.success()
             );
         }
         None => {
             let stdout = io::stdout();
             let mut handle = stdout.lock();
             handle
-                .write_all(toml_string.as_bytes())
+                .write_all(output.as_bytes())
                 .context("Failed to write profile to stdout.")?;
             // Add a newline if stdout is a tty, for better terminal output
             if atty::is(atty::Stream::Stdout) {
@@ -38,8 +116,286 @@ pub fn execute(profile_name: String, output_path: Option<String>) -> Result<()>
                     .write_all(b"\n")
                     .context("Failed to write newline to stdout.")?;
             }
+            if preview {
+                eprintln!(
+                    "{}: secrets are shown as {}. Pass {} or write to a file with {} to include them.",
+                    "Note".dimmed(),
+                    REDACTED,
+                    "--reveal".accent(),
+                    "--output-path".accent()
+                );
+            }
         }
     }
 
     Ok(())
 }
+
+/// Renders the profile as a git config snippet. Tokens are never included; for HTTPS only the
+/// username is emitted so git knows which account to ask its own helper for.
+pub(crate) fn render_gitconfig(profile: &Profile) -> String {
+    let mut sections: Vec<(String, Vec<(String, String)>)> = Vec::new();
+    let mut push = |section: String, key: &str, value: &str| match sections
+        .iter_mut()
+        .find(|(s, _)| *s == section)
+    {
+        Some((_, entries)) => entries.push((key.to_string(), value.to_string())),
+        None => sections.push((section, vec![(key.to_string(), value.to_string())])),
+    };
+
+    let git_config = &profile.git_config;
+    push("user".to_string(), "name", &git_config.user_name);
+    push("user".to_string(), "email", &git_config.user_email);
+    if let Some(key) = git_config
+        .user_signingkey
+        .as_ref()
+        .or(profile.gpg_key.as_ref())
+    {
+        push("user".to_string(), "signingkey", key);
+    }
+
+    if let Some(key_path) = &profile.ssh_key {
+        push("core".to_string(), "sshCommand", &ssh_command(key_path));
+        // Only an alias is the profile's own: rewriting to the bare host would send every
+        // HTTPS remote there over SSH. insteadOf can't rewrite to a host pattern either.
+        let host = profile
+            .ssh_key_host
+            .as_ref()
+            .filter(|h| !is_host_pattern(h));
+        if let (Some(host), Some(alias)) = (host, profile.ssh_host_alias.as_ref()) {
+            push(
+                format!("url {}", gitconfig_subsection(&format!("git@{}:", alias))),
+                "insteadOf",
+                &format!("https://{}/", host),
+            );
+        }
+    }
+
+    if let Some(autocrlf) = git_config.core_autocrlf {
+        push("core".to_string(), "autocrlf", autocrlf.as_str());
+    }
+    if let Some(eol) = git_config.core_eol {
+        push("core".to_string(), "eol", eol.as_str());
+    }
+
+    for creds in &profile.https_credentials {
+        push(
+            format!(
+                "credential {}",
+                gitconfig_subsection(&format!("https://{}", creds.host))
+            ),
+            "username",
+            &creds.username,
+        );
+    }
+
+    let sendemail = profile
+        .sendemail
+        .as_ref()
+        .map(|s| s.git_entries())
+        .unwrap_or_default();
+    let mut custom: Vec<_> = profile.custom_config.iter().collect();
+    custom.sort_by_key(|(key, _)| *key);
+    let entries = sendemail
+        .iter()
+        .map(|(key, value)| (key, std::slice::from_ref(value)))
+        .chain(custom.into_iter().map(|(key, value)| (key, value.values())));
+    for (key, values) in entries {
+        // section.key or section.subsection.key
+        let (section, name) = match key.split_once('.') {
+            Some((section, rest)) => match rest.rsplit_once('.') {
+                Some((subsection, name)) => (
+                    format!("{} {}", section, gitconfig_subsection(subsection)),
+                    name,
+                ),
+                None => (section.to_string(), rest),
+            },
+            None => continue,
+        };
+        for value in values {
+            push(section.clone(), name, value);
+        }
+    }
+
+    let mut out = format!("# git config for gitp profile '{}'\n", profile.name);
+    for (section, entries) in &sections {
+        out.push_str(&format!("[{}]\n", section));
+        for (key, value) in entries {
+            out.push_str(&format!("\t{} = {}\n", key, gitconfig_value(value)));
+        }
+    }
+    out
+}
+
+/// Renders the profile with every layer resolved: its origin and what it hides go in the
+/// header, the repository's host_signing entry replaces user.signingkey, and defaults that are
+/// normally left out (ssh_managed, ssh_user) are written with the value that applies.
+fn render_effective(
+    config: &Config,
+    profile: &Profile,
+    origin: &str,
+    hidden: Option<&Path>,
+    signing: Option<(String, HostSigning)>,
+) -> Result<String> {
+    let mut header = vec![
+        format!(
+            "# Effective configuration of gitp profile '{}'",
+            profile.name
+        ),
+        format!("# profile: {}", origin),
+    ];
+    if let Some(path) = hidden {
+        header.push(format!("# hides: the system profile in {}", path.display()));
+    }
+
+    let mut effective = profile.clone();
+    if let Some((host, signing)) = signing {
+        effective.git_config.user_signingkey = Some(signing.git_key());
+        header.push(format!(
+            "# user.signingkey: host_signing entry for {} (gpg.format = {}), from this repository's remote",
+            host,
+            signing.format.as_str()
+        ));
+    }
+    let managed = config.manages_ssh(profile);
+    if profile.ssh_managed && !managed {
+        header.push("# ssh_managed: off through settings.ssh_unmanaged_profiles".to_string());
+    }
+
+    let mut table =
+        toml::Table::try_from(&effective).context("Failed to serialize profile to TOML.")?;
+    table.insert("ssh_managed".to_string(), managed.into());
+    if effective.ssh_key.is_some() {
+        table
+            .entry("ssh_user")
+            .or_insert_with(|| DEFAULT_SSH_USER.into());
+    }
+    Ok(format!(
+        "{}\n\n{}",
+        header.join("\n"),
+        toml::to_string_pretty(&table).context("Failed to serialize profile to TOML.")?
+    ))
+}
+
+fn render_sshconfig(profile: &Profile, security_key_provider: Option<&str>) -> Result<String> {
+    match ssh_entry(profile) {
+        Some(entry) => Ok(format!(
+            "# SSH config for gitp profile '{}'\n{}",
+            profile.name,
+            render_entry(&entry, security_key_provider)
+        )),
+        None => anyhow::bail!(
+            "Profile '{}' has no SSH key and host to export. Set them with '{}'.",
+            profile.name.warning(),
+            format!(
+                "gitp edit {} --ssh-key-path <path> --ssh-key-host <host>",
+                profile.name
+            )
+            .accent()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{AutoCrlf, CredentialType, HttpsCredentials};
+
+    fn profile() -> Profile {
+        let mut profile = Profile::new(
+            "work".to_string(),
+            "John Doe".to_string(),
+            "john@company.com".to_string(),
+        );
+        profile.ssh_key = Some("~/.ssh/id_work".into());
+        profile.ssh_key_host = Some("github.com".to_string());
+        profile.git_config.core_autocrlf = Some(AutoCrlf::Input);
+        profile.https_credentials = vec![HttpsCredentials::new(
+            "github.com".to_string(),
+            "jdoe".to_string(),
+            CredentialType::Token("secret".to_string()),
+        )];
+        profile
+            .custom_config
+            .insert("commit.gpgsign".to_string(), "true".into());
+        profile.custom_config.insert(
+            "includeIf.gitdir:~/work/.path".to_string(),
+            "~/.gitconfig-work".into(),
+        );
+        profile
+    }
+
+    #[test]
+    fn test_render_gitconfig() {
+        let rendered = render_gitconfig(&profile());
+        assert_eq!(
+            rendered,
+            "# git config for gitp profile 'work'\n\
+             [user]\n\
+             \tname = John Doe\n\
+             \temail = john@company.com\n\
+             [core]\n\
+             \tsshCommand = ssh -i ~/.ssh/id_work -o IdentitiesOnly=yes\n\
+             \tautocrlf = input\n\
+             [credential \"https://github.com\"]\n\
+             \tusername = jdoe\n\
+             [commit]\n\
+             \tgpgsign = true\n\
+             [includeIf \"gitdir:~/work/\"]\n\
+             \tpath = ~/.gitconfig-work\n"
+        );
+        assert!(!rendered.contains("secret"));
+    }
+
+    #[test]
+    fn test_render_effective() {
+        let mut config = Config::default();
+        config.settings.ssh_unmanaged_profiles = vec!["w*".to_string()];
+        let signing = HostSigning {
+            host: "github.com".to_string(),
+            format: crate::config::SigningFormat::Ssh,
+            key: "key::ssh-ed25519 AAAA".to_string(),
+        };
+        let rendered = render_effective(
+            &config,
+            &profile(),
+            "config.toml",
+            Some(Path::new("/etc/gitp/profiles.d/work.toml")),
+            Some(("github.com".to_string(), signing)),
+        )
+        .unwrap();
+        assert!(rendered.starts_with(
+            "# Effective configuration of gitp profile 'work'\n# profile: config.toml\n\
+             # hides: the system profile in /etc/gitp/profiles.d/work.toml\n"
+        ));
+        assert!(rendered.contains("# ssh_managed: off through settings.ssh_unmanaged_profiles\n"));
+        assert!(rendered.contains("ssh_managed = false\n"));
+        assert!(rendered.contains("ssh_user = \"git\"\n"));
+        assert!(rendered.contains("signingkey = \"key::ssh-ed25519 AAAA\"\n"));
+    }
+
+    #[test]
+    fn test_render_sshconfig() {
+        let rendered = render_sshconfig(&profile(), None).unwrap();
+        assert!(rendered.contains("Host github.com\n"));
+        assert!(rendered.contains("IdentityFile ~/.ssh/id_work\n"));
+        // Without an alias, HTTPS remotes on the host are left alone
+        assert!(!render_gitconfig(&profile()).contains("insteadOf"));
+
+        let mut aliased = profile();
+        aliased.ssh_host_alias = Some("github.com-work".to_string());
+        let rendered = render_sshconfig(&aliased, None).unwrap();
+        assert!(rendered.contains("Host github.com-work\n    HostName github.com\n"));
+        assert!(render_gitconfig(&aliased).contains("[url \"git@github.com-work:\"]\n"));
+
+        let mut corp = profile();
+        corp.ssh_key_host = Some("*.Corp.example.com".to_string());
+        let rendered = render_sshconfig(&corp, None).unwrap();
+        assert!(rendered.contains("Host *.corp.example.com !*.*.corp.example.com\n    User git\n"));
+        assert!(!render_gitconfig(&corp).contains("insteadOf"));
+
+        let mut no_ssh = profile();
+        no_ssh.ssh_key = None;
+        assert!(render_sshconfig(&no_ssh, None).is_err());
+    }
+}
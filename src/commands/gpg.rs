@@ -0,0 +1,135 @@
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+
+use crate::cli::GpgCommands;
+use crate::config::{profile_from_env, Config};
+use crate::git::get_repo_root;
+use crate::gpg::{
+    card_status, display_serial, gpg_signing_key, signing_key, signing_required, test_signature,
+    CardStatus, KeyLocation, SigningKey,
+};
+use crate::utils::output::Paint;
+
+pub fn execute(command: GpgCommands) -> Result<()> {
+    match command {
+        GpgCommands::Test { profile_name } => test(profile_name),
+    }
+}
+
+/// Walks through what git needs to sign for the profile, stopping at the first problem.
+fn test(profile_name: Option<String>) -> Result<()> {
+    let config = Config::load().context("Failed to load configuration.")?;
+    let name = match profile_name {
+        Some(name) => name,
+        None => {
+            let repo = get_repo_root().ok().flatten();
+            let repo = repo.as_ref().map(|r| r.to_string_lossy());
+            profile_from_env()
+                .or_else(|| {
+                    config
+                        .state
+                        .current_for(repo.as_deref())
+                        .map(str::to_string)
+                })
+                .context("No profile is in use. Pass a profile name.")?
+        }
+    };
+    let Some(profile) = config.profiles.get(&name) else {
+        bail!("Profile '{}' not found.", name.warning());
+    };
+    let Some(key_id) = gpg_signing_key(profile) else {
+        println!(
+            "Profile '{}' has no GPG signing key (or signs with SSH/X.509).",
+            name.accent()
+        );
+        return Ok(());
+    };
+
+    println!("GPG signing for profile '{}':", name.accent().bold());
+    println!(
+        "  Signing required: {}",
+        if signing_required(profile) {
+            "yes (commit.gpgsign/tag.gpgsign)"
+        } else {
+            "no"
+        }
+    );
+    let Some(key) = signing_key(key_id)? else {
+        bail!(
+            "gpg has no secret key for {}. Import it, or for a smartcard key run \
+             'gpg --card-status' with the card inserted to create the key stubs.",
+            key_id
+        );
+    };
+    println!("  Key: {} ({})", key_id, key.fingerprint.dimmed());
+
+    match &key.location {
+        KeyLocation::Local => println!("  Location: local keyring"),
+        KeyLocation::Card(serial) => {
+            println!("  Location: smartcard {}", display_serial(serial).warning())
+        }
+        KeyLocation::Stub => println!("  Location: offline (only a stub is in the keyring)"),
+    }
+    if key.location != KeyLocation::Local {
+        println!("  Card: {}", inserted_card(key_id, &key, card_status()?)?);
+    }
+
+    println!("  Making a test signature (the card may ask for its PIN or a touch)...");
+    test_signature(key_id)?;
+    println!("{}", "Signing works.".success());
+    Ok(())
+}
+
+/// Describes the inserted card when it holds `key`, or says which card to insert.
+fn inserted_card(key_id: &str, key: &SigningKey, card: Option<CardStatus>) -> Result<String> {
+    match card {
+        Some(card) if card.holds(&key.fingerprint) => Ok(format!(
+            "{} {} inserted",
+            card.vendor.as_deref().unwrap_or("card"),
+            card.serial.success()
+        )),
+        Some(card) => bail!(
+            "The inserted card ({}) doesn't hold signing key {}. Insert the right card.",
+            card.serial,
+            key_id
+        ),
+        None => bail!(
+            "No smartcard found. Insert the card holding signing key {} (check 'gpg \
+             --card-status'; a stuck scdaemon is fixed by 'gpgconf --kill scdaemon').",
+            key_id
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inserted_card() -> Result<()> {
+        let key = SigningKey {
+            fingerprint: "BBBB5555666677778888BBBB5555666677778888".to_string(),
+            location: KeyLocation::Card("D2760001240103040006123456780000".to_string()),
+        };
+        let card = |fingerprint: &str| CardStatus {
+            serial: "12345678".to_string(),
+            vendor: Some("Yubico".to_string()),
+            fingerprints: vec![fingerprint.to_string()],
+        };
+
+        // Fingerprints match regardless of case
+        let inserted = inserted_card("0xABCD", &key, Some(card(&key.fingerprint.to_lowercase())))?;
+        assert!(inserted.starts_with("Yubico "), "{}", inserted);
+        assert!(inserted.contains("12345678"), "{}", inserted);
+        let err = inserted_card(
+            "0xABCD",
+            &key,
+            Some(card("AAAA1111222233334444AAAA1111222233334444")),
+        )
+        .unwrap_err();
+        assert!(err.to_string().starts_with("The inserted card (12345678)"));
+        let err = inserted_card("0xABCD", &key, None).unwrap_err();
+        assert!(err.to_string().starts_with("No smartcard found."));
+        Ok(())
+    }
+}
@@ -0,0 +1,248 @@
+// src/commands/guard.rs
+// Git hooks that run gitp's checks before git does something hard to take back
+
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::cli::GuardCommands;
+use crate::git::{get_repo_root, hook_path};
+use crate::utils::output::Paint;
+
+/// Line that marks a hook as written by gitp, so it is only ever replaced or removed by gitp.
+const HOOK_MARKER: &str = "# Installed by gitp guard.";
+
+/// The pre-push hook. Git passes the remote's name and URL as arguments and the refs being
+/// pushed on standard input; only a mismatch (status 1) stops the push, so a check that can't
+/// run (offline, unknown host) doesn't keep anyone from pushing. The hook `--force` replaced
+/// runs first, with the same arguments and input, and its failure stops the push.
+const PRE_PUSH_HOOK: &str = r#"#!/bin/sh
+# Installed by gitp guard.
+# Refuses the push when git would authenticate as another account than the one of the
+# profile that authored the commits. Skip once with 'git push --no-verify'; remove with
+# 'gitp guard uninstall --pre-push'.
+input=$(cat)
+refs() { [ -z "$input" ] || printf '%s\n' "$input"; }
+previous="$(dirname "$0")/pre-push.bak"
+if [ -x "$previous" ]; then
+    refs | "$previous" "$@" || exit $?
+fi
+refs | gitp check push --stdin "$1" "$2"
+[ $? -ne 1 ]
+"#;
+
+pub fn execute(command: GuardCommands) -> Result<()> {
+    match command {
+        GuardCommands::Install { force, .. } => install_pre_push(force),
+        GuardCommands::Uninstall { .. } => uninstall_pre_push(),
+    }
+}
+
+/// Writes the pre-push hook into the current repository. A hook gitp didn't write is only
+/// replaced with `force`, and kept next to it as `pre-push.bak`, which gitp's hook runs first.
+fn install_pre_push(force: bool) -> Result<()> {
+    let path = pre_push_path()?;
+    if let Some(existing) = read_hook(&path)? {
+        if !existing.contains(HOOK_MARKER) {
+            if !force {
+                bail!(
+                    "{} already exists and wasn't written by gitp. Pass {} to replace it (it is kept as pre-push.bak).",
+                    path.display(),
+                    "--force".accent()
+                );
+            }
+            let backup = path.with_extension("bak");
+            fs::rename(&path, &backup)
+                .with_context(|| format!("Failed to move {:?} to {:?}", path, backup))?;
+            println!(
+                "Kept the previous hook as {}; it still runs before gitp's check.",
+                backup.display()
+            );
+        }
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {:?}", dir))?;
+    }
+    fs::write(&path, PRE_PUSH_HOOK).with_context(|| format!("Failed to write {:?}", path))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))
+            .with_context(|| format!("Failed to make {:?} executable", path))?;
+    }
+    println!(
+        "Installed the pre-push guard at {}",
+        path.display().to_string().success()
+    );
+    println!(
+        "{}: pushes now stop when the account doesn't match the commits' profile. Check by hand with '{}'.",
+        "Note".dimmed(),
+        "gitp check push".accent()
+    );
+    Ok(())
+}
+
+/// Removes the pre-push hook gitp wrote, putting back the hook it replaced.
+fn uninstall_pre_push() -> Result<()> {
+    let path = pre_push_path()?;
+    match read_hook(&path)? {
+        None => {
+            println!("No pre-push hook is installed.");
+            return Ok(());
+        }
+        Some(existing) if !existing.contains(HOOK_MARKER) => bail!(
+            "{} wasn't written by gitp; leaving it alone.",
+            path.display()
+        ),
+        Some(_) => {}
+    }
+    fs::remove_file(&path).with_context(|| format!("Failed to remove {:?}", path))?;
+    let backup = path.with_extension("bak");
+    if backup.exists() {
+        fs::rename(&backup, &path).with_context(|| format!("Failed to restore {:?}", backup))?;
+        println!(
+            "Removed the pre-push guard and restored the previous hook at {}.",
+            path.display().to_string().success()
+        );
+    } else {
+        println!("Removed the pre-push guard.");
+    }
+    Ok(())
+}
+
+/// Where git looks for the current repository's pre-push hook.
+fn pre_push_path() -> Result<PathBuf> {
+    if get_repo_root()?.is_none() {
+        bail!("Not inside a Git repository.");
+    }
+    hook_path("pre-push")
+}
+
+/// The hook's content, or `None` when there is none.
+fn read_hook(path: &Path) -> Result<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {:?}", path)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::in_repo;
+
+    fn write_script(path: &Path, content: &str) -> Result<()> {
+        fs::write(path, content)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_install_keeps_previous_hook() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        git2::Repository::init(dir.path())?;
+        let hooks = dir.path().join(".git/hooks");
+        fs::create_dir_all(&hooks)?;
+        write_script(&hooks.join("pre-push"), "#!/bin/sh\nexit 0\n")?;
+
+        in_repo(dir.path(), || {
+            // A hook gitp didn't write is only replaced with --force
+            assert!(install_pre_push(false).is_err());
+            install_pre_push(true)?;
+            assert!(fs::read_to_string(hooks.join("pre-push"))?.contains(HOOK_MARKER));
+            assert_eq!(
+                fs::read_to_string(hooks.join("pre-push.bak"))?,
+                "#!/bin/sh\nexit 0\n"
+            );
+            // Reinstalling over gitp's own hook keeps the backup
+            install_pre_push(false)?;
+            assert!(hooks.join("pre-push.bak").exists());
+
+            uninstall_pre_push()?;
+            assert_eq!(
+                fs::read_to_string(hooks.join("pre-push"))?,
+                "#!/bin/sh\nexit 0\n"
+            );
+            assert!(!hooks.join("pre-push.bak").exists());
+            Ok(())
+        })
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hook_runs_previous_hook_first() -> Result<()> {
+        use std::io::Write;
+        use std::process::{Command, Stdio};
+
+        let dir = tempfile::tempdir()?;
+        let bin = dir.path().join("bin");
+        fs::create_dir(&bin)?;
+        let log = dir.path().join("log");
+        // Stands in for gitp: records what it got and reports a mismatch when asked to
+        write_script(
+            &bin.join("gitp"),
+            &format!(
+                "#!/bin/sh\necho \"gitp $*\" >> {log:?}\ncat >> {log:?}\nexit ${{GITP_STATUS:-0}}\n"
+            ),
+        )?;
+        let hook = dir.path().join("pre-push");
+        write_script(&hook, PRE_PUSH_HOOK)?;
+        let run = |previous_status: Option<i32>, gitp_status: i32| -> Result<Option<i32>> {
+            let _ = fs::remove_file(&log);
+            let previous = dir.path().join("pre-push.bak");
+            match previous_status {
+                Some(status) => write_script(
+                    &previous,
+                    &format!(
+                        "#!/bin/sh\necho \"previous $*\" >> {log:?}\ncat >> {log:?}\nexit {status}\n"
+                    ),
+                )?,
+                None => {
+                    let _ = fs::remove_file(&previous);
+                }
+            }
+            let path = format!(
+                "{}:{}",
+                bin.display(),
+                std::env::var("PATH").unwrap_or_default()
+            );
+            let mut child = Command::new(&hook)
+                .args(["origin", "git@github.com:acme/app.git"])
+                .env("PATH", path)
+                .env("GITP_STATUS", gitp_status.to_string())
+                .stdin(Stdio::piped())
+                .spawn()?;
+            child
+                .stdin
+                .take()
+                .expect("piped stdin")
+                .write_all(b"refs/heads/main abc refs/heads/main def\n")?;
+            Ok(child.wait()?.code())
+        };
+        let refs = "refs/heads/main abc refs/heads/main def\n";
+
+        // Both hooks get the arguments and the refs, the previous one first
+        assert_eq!(run(Some(0), 0)?, Some(0));
+        assert_eq!(
+            fs::read_to_string(&log)?,
+            format!(
+                "previous origin git@github.com:acme/app.git\n{refs}\
+                 gitp check push --stdin origin git@github.com:acme/app.git\n{refs}"
+            )
+        );
+        // The previous hook's failure stops the push with its status, before gitp runs
+        assert_eq!(run(Some(3), 0)?, Some(3));
+        assert!(!fs::read_to_string(&log)?.contains("gitp check"));
+        // Only a mismatch from gitp stops the push
+        assert_eq!(run(None, 1)?, Some(1));
+        assert_eq!(run(None, 2)?, Some(0));
+        Ok(())
+    }
+}
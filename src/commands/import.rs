@@ -1,9 +1,15 @@
 use anyhow::{bail, Context, Result};
-use colored::Colorize;
 use std::fs;
 use std::io::{self, Read};
+use std::path::PathBuf;
 
-use crate::config::{Config, Profile};
+use crate::commands::credentials::{replace_https_credentials, store_https_token};
+use crate::config::{Config, HttpsCredentials, Profile};
+use crate::credentials::credential_store::{self, StoredCredential};
+use crate::credentials::sealed::{decrypt_secrets, obtain_passphrase};
+use crate::utils::output::{theme, Paint};
+use crate::utils::prompt::{Confirm, Input, Select};
+use crate::utils::terminal::require_terminal;
 
 pub fn execute(
     input_path: String,
@@ -28,6 +34,12 @@ pub fn execute(
     let mut imported_profile: Profile =
         toml::from_str(&input_content).context("Failed to deserialize profile from TOML data.")?;
 
+    let decrypted = decrypt_secrets(&mut imported_profile, || obtain_passphrase(false))
+        .context("Failed to decrypt the imported profile's encrypted values.")?;
+    if decrypted > 0 {
+        println!("Decrypted {} encrypted value(s).", decrypted);
+    }
+
     let final_profile_name = match profile_name_override {
         Some(name_override) => {
             if name_override.trim().is_empty() {
@@ -50,7 +62,7 @@ pub fn execute(
     // Validate the imported profile (after name is finalized)
     imported_profile
         .validate()
-        .map_err(|e| anyhow::anyhow!(e)) // Convert ValidationError to anyhow::Error
+        .map_err(|e| anyhow::anyhow!(e.user_message()))
         .context("Imported profile data is invalid.")?;
 
     let mut config = Config::load().context("Failed to load current configuration.")?;
@@ -58,7 +70,7 @@ pub fn execute(
     if !force && config.profiles.contains_key(&final_profile_name) {
         bail!(
             "A profile named '{}' already exists. Use --force to overwrite.",
-            final_profile_name.yellow()
+            final_profile_name.warning()
         );
     }
 
@@ -71,8 +83,188 @@ pub fn execute(
 
     println!(
         "Profile '{}' imported successfully.",
-        final_profile_name.cyan()
+        final_profile_name.accent()
     );
 
     Ok(())
 }
+
+/// Imports HTTPS credentials from a `git credential-store` file into new or existing profiles,
+/// moving the tokens into the configured secret store.
+pub fn execute_from_credential_store(path: Option<String>) -> Result<()> {
+    require_terminal(
+        "Importing from a credential store",
+        "Run it from a terminal; each entry needs a decision.",
+    )?;
+
+    let path = match path {
+        Some(path) => PathBuf::from(path),
+        None => credential_store::default_paths()
+            .into_iter()
+            .find(|p| p.exists())
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "No git credential-store file found (looked for ~/.git-credentials and ~/.config/git/credentials)."
+                )
+            })?,
+    };
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read credential store '{}'", path.display()))?;
+    let stored = credential_store::parse(&content);
+    if stored.is_empty() {
+        println!("No HTTPS credentials found in {}.", path.display());
+        return Ok(());
+    }
+
+    let mut config = Config::load().context("Failed to load current configuration.")?;
+    println!(
+        "Found {} HTTPS credential(s) in {}.",
+        stored.len(),
+        path.display().to_string().accent()
+    );
+
+    let mut imported = Vec::new();
+    for credential in &stored {
+        println!(
+            "\n{}@{}",
+            credential.username.accent(),
+            credential.host.success()
+        );
+        if let Some(profile_name) = import_credential(&mut config, credential)? {
+            config
+                .save()
+                .context("Failed to save configuration after importing credentials.")?;
+            println!("  Imported into profile '{}'.", profile_name.success());
+            imported.push((credential.host.clone(), credential.username.clone()));
+        } else {
+            println!("  Skipped.");
+        }
+    }
+
+    if imported.is_empty() {
+        return Ok(());
+    }
+
+    let scrub = Confirm::with_theme(&theme())
+        .with_prompt(format!(
+            "Remove the {} imported entr{} from {}? gitp now stores these tokens.",
+            imported.len(),
+            if imported.len() == 1 { "y" } else { "ies" },
+            path.display()
+        ))
+        .default(false)
+        .interact()
+        .context("Failed to get confirmation.")?;
+    if scrub {
+        fs::write(&path, credential_store::scrub(&content, &imported))
+            .with_context(|| format!("Failed to update '{}'", path.display()))?;
+        println!(
+            "Removed imported entries from {}.",
+            path.display().to_string().accent()
+        );
+    }
+
+    Ok(())
+}
+
+/// Asks where `credential` should go and stores it. Returns the profile it was added to, or
+/// `None` when skipped.
+fn import_credential(config: &mut Config, credential: &StoredCredential) -> Result<Option<String>> {
+    let mut targets: Vec<(String, Option<String>)> = Vec::new();
+    let mut names: Vec<&String> = config.profiles.keys().collect();
+    names.sort();
+    for name in names {
+        let existing = config.profiles[name]
+            .https_credentials
+            .iter()
+            .find(|creds| creds.host.eq_ignore_ascii_case(&credential.host));
+        match existing {
+            Some(creds) if creds.username == credential.username => {
+                targets.push((
+                    format!("Update the token of profile '{}'", name),
+                    Some(name.clone()),
+                ));
+            }
+            Some(_) => {} // A profile holds one set of HTTPS credentials per host
+            None => targets.push((format!("Add to profile '{}'", name), Some(name.clone()))),
+        }
+    }
+    targets.push(("Create a new profile".to_string(), None));
+
+    let mut options: Vec<&str> = targets.iter().map(|(label, _)| label.as_str()).collect();
+    options.push("Skip");
+    let choice = Select::with_theme(&theme())
+        .with_prompt("Import into")
+        .items(&options)
+        .default(0)
+        .interact()
+        .context("Failed to get import choice.")?;
+
+    let profile_name = match targets.get(choice) {
+        None => return Ok(None),
+        Some((_, Some(name))) => name.clone(),
+        Some((_, None)) => {
+            let profile = prompt_new_profile(config, credential)?;
+            let name = profile.name.clone();
+            config.profiles.insert(name.clone(), profile);
+            name
+        }
+    };
+
+    let settings = config.settings.clone();
+    let stored = store_https_token(
+        &settings,
+        &credential.host,
+        &credential.username,
+        &credential.token,
+        true,
+    )?;
+
+    let profile = config
+        .profiles
+        .get_mut(&profile_name)
+        .expect("import target profile exists");
+    replace_https_credentials(
+        &settings,
+        &mut profile.https_credentials,
+        HttpsCredentials::new(credential.host.clone(), credential.username.clone(), stored),
+    );
+
+    Ok(Some(profile_name))
+}
+
+fn prompt_new_profile(config: &Config, credential: &StoredCredential) -> Result<Profile> {
+    let name: String = Input::with_theme(&theme())
+        .with_prompt("Profile name")
+        .validate_with(|input: &String| -> Result<(), String> {
+            if input.trim().is_empty() {
+                Err("Profile name cannot be empty.".to_string())
+            } else if config.profiles.contains_key(input.trim()) {
+                Err(format!("Profile '{}' already exists.", input.trim()))
+            } else {
+                Ok(())
+            }
+        })
+        .interact_text()
+        .context("Failed to get profile name input.")?;
+    let user_name: String = Input::with_theme(&theme())
+        .with_prompt("Git user name")
+        .with_initial_text(credential.username.clone())
+        .interact_text()
+        .context("Failed to get user name input.")?;
+    let user_email: String = Input::with_theme(&theme())
+        .with_prompt("Git user email")
+        .interact_text()
+        .context("Failed to get user email input.")?;
+
+    let profile = Profile::new(
+        name.trim().to_string(),
+        user_name.trim().to_string(),
+        user_email.trim().to_string(),
+    );
+    profile
+        .validate()
+        .map_err(|e| anyhow::anyhow!(e))
+        .context("The new profile is invalid.")?;
+    Ok(profile)
+}
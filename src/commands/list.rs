@@ -1,23 +1,56 @@
 use anyhow::Result;
+use chrono::{DateTime, Local, NaiveDate, Utc};
 use colored::Colorize;
+use serde::Serialize;
+use std::collections::BTreeMap;
 
-use crate::config::{Config, Profile};
+use crate::cli::OutputFormat;
+use crate::config::{
+    Config, CredentialType, CustomValue, HttpsCredentials, Profile, ProviderAccount, Settings,
+    SshHost,
+};
+use crate::credentials::verify;
+use crate::git::get_repo_root;
+use crate::gpg::{self, KeyLocation};
+use crate::ssh::keys::inspect_key;
+use crate::utils::expand_home;
+use crate::utils::mask::{mask_secret, mask_value};
+use crate::utils::output::{print_json, Paint, Symbol};
+use crate::utils::time::{format_date, format_time};
 
 /// Execute the list command to show all profiles
-pub fn execute(verbose: bool) -> Result<()> {
+pub fn execute(verbose: bool, format: OutputFormat) -> Result<()> {
     let config = Config::load()?;
 
+    // Inside a repository a profile applied with `use --local` takes precedence
+    let repo = get_repo_root().ok().flatten();
+    let repo = repo.as_ref().map(|r| r.to_string_lossy());
+    let current_profile = config.state.current_for(repo.as_deref());
+
+    if format == OutputFormat::Json {
+        let mut names: Vec<&String> = config.profiles.keys().collect();
+        names.sort();
+        return print_json(&ProfilesJson::new(&config, &names, current_profile));
+    }
+
     if config.profiles.is_empty() {
         println!("No profiles found. Create one with 'gitp new <name>'");
         return Ok(());
     }
 
-    let current_profile = config.current_profile.as_deref();
-
     if verbose {
         // Detailed view
         for (name, profile) in &config.profiles {
-            print_profile_detailed(name, profile, current_profile);
+            print_profile_detailed(
+                &config.settings,
+                name,
+                profile,
+                current_profile,
+                config.state.last_used.get(name),
+            );
+            if let Some(path) = config.system_profile_path(name) {
+                println!("  {} {}", "System Profile:".accent(), path.display());
+            }
             println!(); // Empty line between profiles
         }
     } else {
@@ -26,74 +59,379 @@ pub fn execute(verbose: bool) -> Result<()> {
         println!();
 
         for name in config.profiles.keys() {
+            let system = match config.system_profile_path(name) {
+                Some(_) => format!(" {}", ("(system)" as &str).dimmed()),
+                None => String::new(),
+            };
             if Some(name.as_str()) == current_profile {
-                println!("  {} {}", "*".green().bold(), name.green().bold());
+                println!(
+                    "  {} {}{}",
+                    "*".success().bold(),
+                    name.success().bold(),
+                    system
+                );
             } else {
-                println!("    {}", name);
+                println!("    {}{}", name, system);
             }
         }
 
         println!();
         println!("{}", ("* = current profile" as &str).dimmed());
+        if !config.system_profiles.is_empty() {
+            println!(
+                "{}",
+                ("(system) = provided by the administrator, read-only" as &str).dimmed()
+            );
+        }
     }
 
     Ok(())
 }
 
-pub fn print_profile_detailed(name: &str, profile: &Profile, current_profile: Option<&str>) {
+pub fn print_profile_detailed(
+    settings: &Settings,
+    name: &str,
+    profile: &Profile,
+    current_profile: Option<&str>,
+    last_used: Option<&DateTime<Utc>>,
+) {
     // Header
     if Some(name) == current_profile {
         println!(
             "{} {} {}",
-            "●".green().bold(),
-            name.green().bold(),
+            Symbol::Current.as_str().success().bold(),
+            name.success().bold(),
             ("(current)" as &str).dimmed()
         );
     } else {
-        println!("{} {}", "●".white(), name.bold());
+        println!("{} {}", Symbol::Profile.as_str(), name.bold());
     }
 
     // Git config
-    println!("  {} {}", "Name:".cyan(), profile.git_config.user_name);
-    println!("  {} {}", "Email:".cyan(), profile.git_config.user_email);
+    println!("  {} {}", "Name:".accent(), profile.git_config.user_name);
+    println!("  {} {}", "Email:".accent(), profile.git_config.user_email);
 
     // Optional fields
     if let Some(ref signing_key) = profile.git_config.user_signingkey {
-        println!("  {} {}", "Signing Key:".cyan(), signing_key);
+        println!("  {} {}", "Signing Key:".accent(), signing_key);
     }
 
     if let Some(ref ssh_key) = profile.ssh_key {
-        println!("  {} {}", "SSH Key:".cyan(), ssh_key.display());
+        println!("  {} {}", "SSH Key:".accent(), ssh_key.display());
+        // Security keys behave differently enough (touch, residency) to call out
+        if let Ok(info) = inspect_key(&expand_home(&ssh_key.to_string_lossy())) {
+            if info.security_key.is_some() {
+                println!("    {} {}", "Type:".accent(), info.describe());
+            }
+        }
+        if !profile.ssh_managed {
+            println!("    {} not managed by gitp", "Host entry:".accent());
+        }
     }
 
-    if let Some(ref gpg_key) = profile.gpg_key {
-        println!("  {} {}", "GPG Key:".cyan(), gpg_key);
+    if let (Some(alias), Some(host)) = (&profile.ssh_host_alias, &profile.ssh_key_host) {
+        println!("  {} {} -> {}", "SSH Host Alias:".accent(), alias, host);
     }
 
-    // HTTPS Credentials
-    if let Some(https_creds) = &profile.https_credentials {
+    for entry in &profile.ssh_hosts {
+        let user = entry
+            .user
+            .as_ref()
+            .map(|user| format!("{}@", user))
+            .unwrap_or_default();
         println!(
-            "  {} {} ({})",
-            "HTTPS:".cyan(),
-            https_creds.host.yellow(),
-            https_creds.username
+            "  {} {} ({}{})",
+            "SSH Key:".accent(),
+            entry.key.display(),
+            user,
+            entry.host
         );
-        match &https_creds.credential_type {
-            crate::config::CredentialType::Token(_) => {
-                println!("    {} {}", "Type:".cyan(), "Token (<masked>)".dimmed());
-            }
-            crate::config::CredentialType::KeychainRef(_) => { // Reference string (username) is already part of the host/user line
-                println!("    {} {}", "Type:".cyan(), "Stored in System Keychain".yellow());
+    }
+
+    if let Some(ref gpg_key) = profile.gpg_key {
+        println!("  {} {}", "GPG Key:".accent(), gpg_key);
+    }
+
+    // Smartcard keys fail differently from file-based ones, so say where the key lives
+    if let Some(Ok(Some(key))) = gpg::gpg_signing_key(profile).map(gpg::signing_key) {
+        match key.location {
+            KeyLocation::Card(serial) => println!(
+                "    {} smartcard {}",
+                "Signing Key On:".accent(),
+                gpg::display_serial(&serial)
+            ),
+            KeyLocation::Stub => println!(
+                "    {} offline key or smartcard (stub only)",
+                "Signing Key On:".accent()
+            ),
+            KeyLocation::Local => {}
+        }
+    }
+
+    for (applier, settings) in &profile.extras {
+        let settings: Vec<String> = settings
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, mask_value(key, value)))
+            .collect();
+        println!(
+            "  {} {}",
+            format!("Extras ({}):", applier).accent(),
+            if settings.is_empty() {
+                "defaults".to_string()
+            } else {
+                settings.join(", ")
             }
+        );
+    }
+
+    let git_config = &profile.git_config;
+    let line_endings: Vec<String> = [
+        git_config
+            .core_autocrlf
+            .map(|v| format!("autocrlf={}", v.as_str())),
+        git_config.core_eol.map(|v| format!("eol={}", v.as_str())),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    if !line_endings.is_empty() {
+        println!("  {} {}", "Line Endings:".accent(), line_endings.join(", "));
+    }
+
+    // HTTPS Credentials
+    for https_creds in &profile.https_credentials {
+        print_https_credentials(settings, https_creds);
+    }
+
+    if let Some(sendemail) = &profile.sendemail {
+        println!("  {}", "Send Email:".accent());
+        for (key, value) in sendemail.git_entries() {
+            println!("    {} = {}", key, value);
+        }
+        if let Some(stored) = &sendemail.smtp_pass {
+            println!(
+                "    {} {}",
+                "SMTP Password:".accent(),
+                crate::credentials::store_label(stored)
+            );
         }
     }
 
     if !profile.custom_config.is_empty() {
-        println!("  {}:", "Custom Config:".cyan());
-        for (key, value) in &profile.custom_config {
-            println!("    {} = {}", key, value);
+        println!("  {}:", "Custom Config:".accent());
+        let mut custom: Vec<_> = profile.custom_config.iter().collect();
+        custom.sort_by_key(|(key, _)| *key);
+        for (key, value) in custom {
+            let op = if value.is_additive() { "+=" } else { "=" };
+            for value in value.values() {
+                println!("    {} {} {}", key, op, mask_value(key, value));
+            }
+        }
+    }
+
+    if let Some(notes) = &profile.notes {
+        println!("  {} {}", "Notes:".accent(), notes);
+    }
+
+    if let Some(last_used) = last_used {
+        println!("  {} {}", "Last Used:".accent(), format_time(last_used));
+    }
+}
+
+/// Profiles as `--format json` shows them, with the one in use here.
+#[derive(Debug, Serialize)]
+pub(crate) struct ProfilesJson<'a> {
+    pub current: Option<&'a str>,
+    pub profiles: Vec<ProfileJson<'a>>,
+}
+
+impl<'a> ProfilesJson<'a> {
+    /// The profiles called `names`, in that order.
+    pub fn new(config: &'a Config, names: &[&'a String], current: Option<&'a str>) -> Self {
+        Self {
+            current,
+            profiles: names
+                .iter()
+                .filter_map(|name| {
+                    let profile = config.profiles.get(name.as_str())?;
+                    let mut json = ProfileJson::new(
+                        profile,
+                        current == Some(name.as_str()),
+                        config.state.last_used.get(name.as_str()),
+                    );
+                    json.system = config.system_profiles.contains_key(name.as_str());
+                    Some(json)
+                })
+                .collect(),
+        }
+    }
+}
+
+/// A profile in JSON output. Tokens are never included; the `store` of each `https` entry says
+/// where its token is kept.
+#[derive(Debug, Serialize)]
+pub(crate) struct ProfileJson<'a> {
+    pub name: &'a str,
+    pub current: bool,
+    /// Provided by the system store rather than the user's configuration
+    pub system: bool,
+    pub user_name: &'a str,
+    pub user_email: &'a str,
+    pub signing_key: Option<&'a str>,
+    pub gpg_key: Option<&'a str>,
+    pub ssh_key: Option<String>,
+    pub ssh_key_host: Option<&'a str>,
+    pub ssh_host_alias: Option<&'a str>,
+    pub ssh_user: Option<&'a str>,
+    pub ssh_hosts: &'a [SshHost],
+    pub https: Vec<HttpsJson<'a>>,
+    pub core_autocrlf: Option<&'static str>,
+    pub core_eol: Option<&'static str>,
+    /// Secret-named values are masked unless `--reveal` was passed
+    pub custom_config: BTreeMap<&'a str, CustomValue>,
+    pub auto_dirs: &'a [String],
+    pub notes: Option<&'a str>,
+    pub last_used: Option<&'a DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct HttpsJson<'a> {
+    pub host: &'a str,
+    pub username: &'a str,
+    /// `config`, `keychain` or `file`
+    pub store: &'static str,
+    pub token_scopes: Option<&'a [String]>,
+    pub token_expires_at: Option<NaiveDate>,
+    pub account: Option<&'a ProviderAccount>,
+}
+
+impl<'a> ProfileJson<'a> {
+    pub fn new(profile: &'a Profile, current: bool, last_used: Option<&'a DateTime<Utc>>) -> Self {
+        let git_config = &profile.git_config;
+        Self {
+            name: &profile.name,
+            current,
+            system: false,
+            user_name: &git_config.user_name,
+            user_email: &git_config.user_email,
+            signing_key: git_config.user_signingkey.as_deref(),
+            gpg_key: profile.gpg_key.as_deref(),
+            ssh_key: profile
+                .ssh_key
+                .as_ref()
+                .map(|key| key.to_string_lossy().into_owned()),
+            ssh_key_host: profile.ssh_key_host.as_deref(),
+            ssh_host_alias: profile.ssh_host_alias.as_deref(),
+            ssh_user: profile.ssh_user.as_deref(),
+            ssh_hosts: &profile.ssh_hosts,
+            https: profile
+                .https_credentials
+                .iter()
+                .map(|creds| HttpsJson {
+                    host: &creds.host,
+                    username: &creds.username,
+                    store: match creds.credential_type {
+                        CredentialType::Token(_) => "config",
+                        CredentialType::KeychainRef(_) => "keychain",
+                        CredentialType::FileStoreRef(_) => "file",
+                    },
+                    token_scopes: creds.token_scopes.as_deref(),
+                    token_expires_at: creds.token_expires_at,
+                    account: creds.account.as_ref(),
+                })
+                .collect(),
+            core_autocrlf: git_config.core_autocrlf.map(|v| v.as_str()),
+            core_eol: git_config.core_eol.map(|v| v.as_str()),
+            custom_config: profile
+                .custom_config
+                .iter()
+                .map(|(key, value)| {
+                    let mut value = value.clone();
+                    for v in value.values_mut() {
+                        *v = mask_value(key, v);
+                    }
+                    (key.as_str(), value)
+                })
+                .collect(),
+            auto_dirs: &profile.auto_dirs,
+            notes: profile.notes.as_deref(),
+            last_used,
+        }
+    }
+}
+
+/// `login (Display Name, #id)`, with what the provider reported.
+pub(crate) fn describe_account(account: &ProviderAccount) -> String {
+    let details: Vec<String> = account
+        .name
+        .clone()
+        .into_iter()
+        .chain(account.id.map(|id| format!("#{}", id)))
+        .collect();
+    if details.is_empty() {
+        account.login.clone()
+    } else {
+        format!("{} ({})", account.login, details.join(", "))
+    }
+}
+
+/// Prints a profile's HTTPS host and username, where its token is kept, and what the last
+/// verification recorded about it. The token itself is never shown.
+pub(crate) fn print_https_credentials(settings: &Settings, https_creds: &HttpsCredentials) {
+    println!(
+        "  {} {} ({})",
+        "HTTPS:".accent(),
+        https_creds.host.warning(),
+        https_creds.username
+    );
+    match &https_creds.credential_type {
+        crate::config::CredentialType::Token(token) => {
+            println!(
+                "    {} {}",
+                "Type:".accent(),
+                format!("Token ({})", mask_secret(token)).dimmed()
+            );
+        }
+        crate::config::CredentialType::KeychainRef(_) => {
+            // Reference string (username) is already part of the host/user line
+            println!(
+                "    {} {}",
+                "Type:".accent(),
+                "Stored in System Keychain".warning()
+            );
+        }
+        crate::config::CredentialType::FileStoreRef(_) => {
+            println!(
+                "    {} {}",
+                "Type:".accent(),
+                "Stored in Encrypted File Store".warning()
+            );
         }
     }
+    if let Some(account) = &https_creds.account {
+        println!(
+            "    {} {}, verified {}",
+            "Account:".accent(),
+            describe_account(account),
+            format_time(&account.verified_at)
+        );
+    }
+    if let Some(expires_at) = https_creds.token_expires_at {
+        println!("    {} {}", "Expires:".accent(), format_date(expires_at));
+    }
+    if let Some(scopes) = &https_creds.token_scopes {
+        println!("    {} {}", "Scopes:".accent(), scopes.join(", "));
+    }
+    if let Some(minutes) = https_creds.cache_minutes {
+        println!(
+            "    {} {} min in git's credential cache",
+            "Cached:".accent(),
+            minutes
+        );
+    }
+    for warning in verify::token_warnings(settings, https_creds, Local::now().date_naive()) {
+        println!("    {} {}", "Warning:".warning(), warning);
+    }
 }
 
 #[cfg(test)]
@@ -129,4 +467,28 @@ mod tests {
         // let output_str = String::from_utf8(output).unwrap();
         // assert!(output_str.contains("Test User"));
     }
+
+    #[test]
+    fn test_profile_json() {
+        use super::ProfileJson;
+        use crate::config::{CredentialType, HttpsCredentials};
+
+        let mut profile = Profile::new(
+            "work".to_string(),
+            "John Doe".to_string(),
+            "john@company.com".to_string(),
+        );
+        profile.https_credentials = vec![HttpsCredentials::new(
+            "github.com".to_string(),
+            "johndoe".to_string(),
+            CredentialType::Token("ghp_secret".to_string()),
+        )];
+        let json = serde_json::to_value(ProfileJson::new(&profile, true, None)).unwrap();
+        assert_eq!(json["name"], "work");
+        assert_eq!(json["current"], true);
+        assert_eq!(json["user_email"], "john@company.com");
+        assert_eq!(json["https"][0]["store"], "config");
+        assert!(json["signing_key"].is_null());
+        assert!(!json.to_string().contains("ghp_secret"));
+    }
 }
@@ -0,0 +1,176 @@
+use anyhow::{bail, Context, Result};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::cli::MailmapCommands;
+use crate::config::{Config, Profile};
+use crate::git::{commit_identities, get_repo_root, in_repo, CommitIdentity};
+use crate::utils::managed_block::write_block;
+use crate::utils::output::Paint;
+
+pub fn execute(command: MailmapCommands) -> Result<()> {
+    match command {
+        MailmapCommands::Generate {
+            canonical,
+            path,
+            install,
+        } => generate(canonical, path, install),
+    }
+}
+
+fn generate(canonical: Option<String>, path: Option<String>, install: bool) -> Result<()> {
+    let config = Config::load().context("Failed to load configuration.")?;
+    let start = match path {
+        Some(path) => PathBuf::from(path),
+        None => std::env::current_dir().context("Failed to get current directory.")?,
+    };
+    let (repo, identities) = in_repo(&start, || {
+        let repo = get_repo_root()?
+            .with_context(|| format!("{} is not in a git repository.", start.display()))?;
+        Ok((repo, commit_identities()?))
+    })?;
+
+    let repo_key = repo.to_string_lossy();
+    let canonical = match canonical {
+        Some(name) => name,
+        None => config
+            .state
+            .current_for(Some(&repo_key))
+            .map(str::to_string)
+            .context("No profile is in use here. Pass --canonical <profile>.")?,
+    };
+    let Some(canonical) = config.profiles.get(&canonical) else {
+        bail!("Profile '{}' not found.", canonical.warning());
+    };
+
+    let entries = mailmap_entries(canonical, &config, &identities);
+    if install {
+        return install_entries(&repo, &entries);
+    }
+    if entries.is_empty() {
+        eprintln!(
+            "History has no commits under other profiles' emails; no entries needed for '{}'.",
+            canonical.name
+        );
+    }
+    for entry in &entries {
+        println!("{}", entry);
+    }
+    Ok(())
+}
+
+/// `.mailmap` lines that attribute every commit made with a profile's email to `canonical`.
+/// Only emails found in `identities` are listed, so the file doesn't reveal addresses the
+/// repository never used.
+fn mailmap_entries(
+    canonical: &Profile,
+    config: &Config,
+    identities: &[CommitIdentity],
+) -> Vec<String> {
+    let name = &canonical.git_config.user_name;
+    let email = &canonical.git_config.user_email;
+    let is_profile_email = |candidate: &str| {
+        config
+            .profiles
+            .values()
+            .any(|p| p.git_config.user_email.eq_ignore_ascii_case(candidate))
+    };
+
+    // Keyed by lowercase email, since .mailmap matches emails case-insensitively
+    let mut entries: BTreeMap<String, String> = BTreeMap::new();
+    let pairs = identities.iter().flat_map(|id| {
+        [
+            (&id.author_name, &id.author_email),
+            (&id.committer_name, &id.committer_email),
+        ]
+    });
+    for (commit_name, commit_email) in pairs {
+        if !is_profile_email(commit_email) {
+            continue;
+        }
+        if commit_email.eq_ignore_ascii_case(email) {
+            // Only the name needs fixing
+            if commit_name != name {
+                entries.insert(email.to_lowercase(), format!("{} <{}>", name, email));
+            }
+        } else {
+            entries.insert(
+                commit_email.to_lowercase(),
+                format!("{} <{}> <{}>", name, email, commit_email),
+            );
+        }
+    }
+    entries.into_values().collect()
+}
+
+fn install_entries(repo: &Path, entries: &[String]) -> Result<()> {
+    let path = repo.join(".mailmap");
+    let body = entries
+        .iter()
+        .map(|entry| format!("{}\n", entry))
+        .collect::<String>();
+    if !write_block(&path, &body)? {
+        println!("{} is already up to date.", path.display());
+    } else if entries.is_empty() {
+        println!("Removed gitp's entries from {}.", path.display());
+    } else {
+        println!(
+            "Wrote {} entr{} to {}. Commit it so everyone sees the same attribution.",
+            entries.len(),
+            if entries.len() == 1 { "y" } else { "ies" },
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit(author: (&str, &str), committer: (&str, &str)) -> CommitIdentity {
+        CommitIdentity {
+            author_name: author.0.to_string(),
+            author_email: author.1.to_string(),
+            committer_name: committer.0.to_string(),
+            committer_email: committer.1.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_mailmap_entries() {
+        let mut config = Config::default();
+        for (name, user, email) in [
+            ("personal", "John Doe", "john@home.org"),
+            ("work", "J. Doe", "john@company.com"),
+            ("oss", "John Doe", "jdoe@users.noreply.github.com"),
+        ] {
+            let profile = Profile::new(name.to_string(), user.to_string(), email.to_string());
+            config.profiles.insert(name.to_string(), profile);
+        }
+        let identities = [
+            commit(("John Doe", "john@home.org"), ("John Doe", "john@home.org")),
+            commit(
+                ("J. Doe", "John@Company.com"),
+                ("GitHub", "noreply@github.com"),
+            ),
+            commit(
+                ("Johnny", "john@home.org"),
+                ("Jane Roe", "jane@example.com"),
+            ),
+        ];
+
+        assert_eq!(
+            mailmap_entries(&config.profiles["personal"], &config, &identities),
+            [
+                "John Doe <john@home.org> <John@Company.com>",
+                "John Doe <john@home.org>",
+            ]
+        );
+        // The unused oss email and other people's commits are left out
+        assert_eq!(
+            mailmap_entries(&config.profiles["work"], &config, &identities),
+            ["J. Doe <john@company.com> <john@home.org>"]
+        );
+    }
+}
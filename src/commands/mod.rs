@@ -1,11 +1,41 @@
+pub mod badge;
+pub mod bootstrap;
+pub mod changelog;
+pub mod check;
+pub mod ci;
+pub mod credential_helper;
+pub mod credentials;
 pub mod current;
+pub mod debug_bundle;
+pub mod doctor;
 pub mod edit;
+pub mod gpg;
 pub mod list;
+pub mod mailmap;
 pub mod new;
+pub mod paths;
+pub mod provider;
+pub mod remotes;
 pub mod remove;
 pub mod rename;
+pub mod report;
+pub mod resolve;
+pub mod rules;
+pub mod sendemail;
+pub mod session;
+pub mod shell_aliases;
 pub mod show;
+pub mod split;
+pub mod ssh;
+pub mod ssh_match;
 pub mod ssh_key;
+pub mod storage;
+pub mod clone;
+pub mod url;
 pub mod use_profile;
+pub mod version;
 pub mod export;
 pub mod import;
+pub mod guard;
+pub mod whoami;
+pub mod status;
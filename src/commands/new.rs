@@ -1,9 +1,27 @@
 use anyhow::{bail, Context, Result};
 use colored::Colorize;
-use dialoguer::{theme::ColorfulTheme, Confirm, Input, Password};
 
-use crate::config::{Config, CredentialType, HttpsCredentials, Profile, ValidationError};
+use crate::commands::credentials::store_https_token;
+use crate::commands::ssh_key::{default_key_path, generate_key, key_comment};
+use crate::commands::url::clone_example;
+use crate::config::{
+    same_host, AutoCrlf, Config, Eol, GitConfig, HttpsCredentials, Profile, Settings,
+};
+use crate::git::{get_git_config, recent_authors, GitConfigScope};
+use crate::providers::{self, DEFAULT_SSH_USER};
+use crate::utils::output::{theme, Paint};
+use crate::utils::prompt::{
+    prompt_optional, prompt_required, prompt_suggested, Confirm, Password, Select, Suggestions,
+};
+use crate::utils::terminal::require_terminal;
+use crate::utils::{contract_home, expand_home, non_empty, normalize_path};
 
+const PLACEHOLDER_USER_NAME: &str = "Your Name";
+const PLACEHOLDER_USER_EMAIL: &str = "you@example.com";
+/// Commits whose authors are suggested for a new profile's name and email
+const RECENT_AUTHORS: usize = 200;
+
+#[allow(clippy::too_many_arguments)]
 pub fn execute(
     profile_name: String,
     cli_user_name: Option<String>,
@@ -16,17 +34,26 @@ pub fn execute(
     cli_https_token: Option<String>,
     cli_https_store_in_keychain: bool, // Updated argument
     cli_ssh_key_host: Option<String>,
+    cli_autocrlf: Option<String>,
+    cli_eol: Option<String>,
+    defaults: bool,
 ) -> Result<()> {
     let mut config = Config::load().context("Failed to load configuration. Ensure ~/.config/gitp/config.toml is accessible or run init if applicable.")?;
 
     if config.profiles.contains_key(&profile_name) {
         bail!(
             "Profile '{}' already exists. Choose a different name or edit the existing one.",
-            profile_name.yellow()
+            profile_name.warning()
         );
     }
 
-    println!("Creating new profile: {}", profile_name.cyan().bold());
+    println!("Creating new profile: {}", profile_name.accent().bold());
+
+    let (cli_user_name, cli_user_email, cli_signing_key) = if defaults {
+        fill_from_global_identity(cli_user_name, cli_user_email, cli_signing_key)?
+    } else {
+        (cli_user_name, cli_user_email, cli_signing_key)
+    };
 
     let mut new_profile: Profile;
 
@@ -45,25 +72,21 @@ pub fn execute(
             cli_user_email.as_ref().unwrap().trim().to_string(),
         );
 
-        if let Some(key) = &cli_signing_key {
-            if !key.trim().is_empty() {
-                new_profile.git_config.user_signingkey = Some(key.trim().to_string());
-            }
+        new_profile.git_config.user_signingkey = cli_signing_key.as_deref().and_then(non_empty);
+        if let Some(path) = cli_ssh_key_path.as_deref().and_then(non_empty) {
+            new_profile.ssh_key = Some(normalize_path(&path));
+            // If SSH key path is provided, check for SSH key host
+            new_profile.ssh_key_host = cli_ssh_key_host.as_deref().and_then(non_empty);
         }
-        if let Some(path) = &cli_ssh_key_path {
-            if !path.trim().is_empty() {
-                new_profile.ssh_key = Some(path.trim().into());
-                // If SSH key path is provided, check for SSH key host
-                if let Some(host) = &cli_ssh_key_host {
-                    if !host.trim().is_empty() {
-                        new_profile.ssh_key_host = Some(host.trim().to_string());
-                    }
-                }
+        new_profile.gpg_key = cli_gpg_key_id.as_deref().and_then(non_empty);
+        if let Some(value) = &cli_autocrlf {
+            if !value.trim().is_empty() {
+                new_profile.git_config.core_autocrlf = Some(value.parse()?);
             }
         }
-        if let Some(id) = &cli_gpg_key_id {
-            if !id.trim().is_empty() {
-                new_profile.gpg_key = Some(id.trim().to_string());
+        if let Some(value) = &cli_eol {
+            if !value.trim().is_empty() {
+                new_profile.git_config.core_eol = Some(value.parse()?);
             }
         }
 
@@ -79,207 +102,60 @@ pub fn execute(
                 let username = username_str.trim().to_string();
                 let token = token_str.trim().to_string();
 
-                let credential_type = if cli_https_store_in_keychain {
-                    match crate::credentials::keyring::store_token(&host, &username, &token) {
-                        Ok(_) => {
-                            println!(
-                                "  Stored HTTPS token for {}@{} in keychain.",
-                                username.cyan(),
-                                host.green()
-                            );
-                            CredentialType::KeychainRef(username.clone())
-                        }
-                        Err(e) => {
-                            eprintln!(
-                                "  {}: Failed to store HTTPS token in keychain for {}@{}: {}. Storing as plain text instead.",
-                                "Warning".yellow(),
-                                username.cyan(),
-                                host.green(),
-                                e
-                            );
-                            CredentialType::Token(token)
-                        }
-                    }
-                } else {
-                    CredentialType::Token(token)
-                };
-
-                new_profile.https_credentials = Some(HttpsCredentials {
-                    host,
-                    username,
-                    credential_type,
-                });
+                let credential_type = store_https_token(
+                    &config.settings,
+                    &host,
+                    &username,
+                    &token,
+                    cli_https_store_in_keychain,
+                )?;
+
+                new_profile.https_credentials =
+                    vec![HttpsCredentials::new(host, username, credential_type)];
                 println!(
                     "  Configured HTTPS credentials for host: {}",
-                    host_str.trim().green()
+                    host_str.trim().success()
                 );
             }
         }
     } else {
+        require_terminal(
+            "Creating a profile interactively",
+            "Pass --user-name and --user-email to create it non-interactively.",
+        )?;
         println!("Running in interactive mode.");
-        let user_name_input: String = Input::with_theme(&ColorfulTheme::default())
-            .with_prompt("Enter Git user name (e.g., John Doe)")
-            .interact_text()
-            .context("Failed to get user name input.")?;
-
-        let user_email_input: String = Input::with_theme(&ColorfulTheme::default())
-            .with_prompt("Enter Git user email (e.g., john.doe@example.com)")
-            .interact_text()
-            .context("Failed to get user email input.")?;
-
-        if user_name_input.trim().is_empty() {
-            bail!("User name cannot be empty. Profile creation aborted.");
-        }
-        if user_email_input.trim().is_empty() {
-            bail!("User email cannot be empty. Profile creation aborted.");
-        }
+        let (names, emails) = identity_suggestions(&config, None);
+        let user_name = prompt_suggested(
+            "Enter Git user name (e.g., John Doe)",
+            None,
+            "User name",
+            &names,
+        )?;
+        let user_email = prompt_suggested(
+            "Enter Git user email (e.g., john.doe@example.com)",
+            None,
+            "User email",
+            &emails,
+        )?;
+        new_profile = Profile::new(profile_name.clone(), user_name, user_email);
 
-        new_profile = Profile::new(
-            profile_name.clone(),
-            user_name_input.trim().to_string(),
-            user_email_input.trim().to_string(),
-        );
-
-        let signing_key_input: String = Input::with_theme(&ColorfulTheme::default())
-            .with_prompt("Enter Git signing key (optional, press Enter to skip)")
-            .allow_empty(true)
-            .interact_text()
-            .context("Failed to get signing key input.")?;
-        if !signing_key_input.trim().is_empty() {
-            new_profile.git_config.user_signingkey = Some(signing_key_input.trim().to_string());
-        }
-
-        let ssh_key_path_input: String = Input::with_theme(&ColorfulTheme::default())
-            .with_prompt("Enter path to SSH key (optional, press Enter to skip)")
-            .allow_empty(true)
-            .interact_text()
-            .context("Failed to get SSH key path input.")?;
-        if !ssh_key_path_input.trim().is_empty() {
-            new_profile.ssh_key = Some(ssh_key_path_input.trim().into());
-
-            let ssh_key_host_input: String = Input::with_theme(&ColorfulTheme::default())
-                .with_prompt("Enter SSH key host (e.g., github.com, gitlab.mycompany.com)")
-                .allow_empty(false) // Host cannot be empty if key is provided
-                .interact_text()
-                .context("Failed to get SSH key host input.")?;
-            if !ssh_key_host_input.trim().is_empty() {
-                // Redundant check due to allow_empty(false), but good practice
-                new_profile.ssh_key_host = Some(ssh_key_host_input.trim().to_string());
-            }
-        }
-
-        let gpg_key_id_input: String = Input::with_theme(&ColorfulTheme::default())
-            .with_prompt("Enter GPG key ID for signing (optional, press Enter to skip)")
-            .allow_empty(true)
-            .interact_text()
-            .context("Failed to get GPG key ID input.")?;
-        if !gpg_key_id_input.trim().is_empty() {
-            new_profile.gpg_key = Some(gpg_key_id_input.trim().to_string());
-        }
-
-        // HTTPS Credentials Interactive Prompts
-        println!("\n{}", "HTTPS Credentials (optional):".cyan());
-        let https_host_input: String = Input::with_theme(&ColorfulTheme::default())
-            .with_prompt("Enter HTTPS host (e.g., github.com, leave blank to skip)")
-            .allow_empty(true)
-            .interact_text()
-            .context("Failed to get HTTPS host input.")?;
-
-        if !https_host_input.trim().is_empty() {
-            let https_username_input: String = Input::with_theme(&ColorfulTheme::default())
-                .with_prompt(format!(
-                    "Enter HTTPS username for host '{}'",
-                    https_host_input.trim()
-                ))
-                .interact_text()
-                .context("Failed to get HTTPS username input.")?;
-
-            if https_username_input.trim().is_empty() {
-                bail!("HTTPS username cannot be empty if host is provided. HTTPS credentials setup aborted.");
-            }
-
-            let token_input: String = Password::with_theme(&ColorfulTheme::default())
-                .with_prompt("Enter HTTPS Token")
-                .with_confirmation("Confirm HTTPS Token", "Tokens do not match.")
-                .interact()
-                .context("Failed to get HTTPS token input.")?;
-            if token_input.trim().is_empty() {
-                bail!("Token cannot be empty. HTTPS credentials setup aborted.");
-            }
-
-            let credential_type_value = if Confirm::with_theme(&ColorfulTheme::default())
-                .with_prompt("Store this HTTPS token securely in the system keychain?")
-                .default(true)
-                .interact()?
-            {
-                match crate::credentials::keyring::store_token(
-                    https_host_input.trim(),
-                    https_username_input.trim(),
-                    token_input.trim(),
-                ) {
-                    Ok(_) => {
-                        println!(
-                            "  Stored HTTPS token for {}@{} in keychain.",
-                            https_username_input.trim().cyan(),
-                            https_host_input.trim().green()
-                        );
-                        CredentialType::KeychainRef(https_username_input.trim().to_string())
-                    }
-                    Err(e) => {
-                        eprintln!(
-                            "  {}: Failed to store HTTPS token in keychain: {}. Storing as plain text instead.",
-                            "Warning".yellow(),
-                            e
-                        );
-                        CredentialType::Token(token_input.trim().to_string())
-                    }
-                }
-            } else {
-                CredentialType::Token(token_input.trim().to_string())
-            };
-
-            new_profile.https_credentials = Some(HttpsCredentials {
-                host: https_host_input.trim().to_string(),
-                username: https_username_input.trim().to_string(),
-                credential_type: credential_type_value,
-            });
+        if Confirm::with_theme(&theme())
+            .with_prompt("Configure advanced options (signing, SSH, HTTPS, line endings)?")
+            .default(config.settings.new_advanced_options)
+            .interact()?
+        {
+            prompt_advanced(&config.settings, &mut new_profile)?;
+        } else {
+            println!(
+                "Skipped advanced options; add them later with {}",
+                format!("gitp edit {}", profile_name).accent()
+            );
         }
     }
 
     // Validate the newly created profile
     if let Err(validation_error) = new_profile.validate() {
-        let error_message = match validation_error {
-            ValidationError::EmptyName => "Profile name cannot be empty.".to_string(),
-            ValidationError::EmptyUserName => "User name cannot be empty.".to_string(),
-            ValidationError::EmptyEmail => "User email cannot be empty.".to_string(),
-            ValidationError::InvalidEmail(email) => format!("Invalid email format: '{}'.", email),
-            ValidationError::SshKeyNotFound(path) => {
-                format!("SSH key not found: '{}'.", path.display())
-            }
-            ValidationError::InvalidGpgKeyFormat(key) => {
-                format!(
-                    "Invalid GPG key format for '{}'. Expected 8, 16, or 40 hex characters.",
-                    key
-                )
-            }
-            ValidationError::EmptySshKeyHost => {
-                "SSH key host cannot be empty when an SSH key is provided.".to_string()
-            }
-            ValidationError::EmptyHttpsHost => {
-                "HTTPS credentials host cannot be empty.".to_string()
-            }
-            ValidationError::EmptyHttpsUsername => {
-                "HTTPS credentials username cannot be empty.".to_string()
-            }
-            ValidationError::EmptyHttpsToken => {
-                "HTTPS credentials token cannot be empty when type is Token.".to_string()
-            }
-            ValidationError::EmptyHttpsKeychainRef => {
-                "HTTPS credentials keychain reference cannot be empty when type is KeychainRef."
-                    .to_string()
-            }
-        };
-        bail!(error_message);
+        bail!(validation_error.user_message());
     }
 
     config.profiles.insert(profile_name.clone(), new_profile);
@@ -287,34 +163,329 @@ pub fn execute(
         "Failed to save configuration. Check permissions for ~/.config/gitp/config.toml.",
     )?;
 
-    println!("\nProfile '{}' created successfully!", profile_name.green());
+    println!(
+        "\nProfile '{}' created successfully!",
+        profile_name.success()
+    );
 
     if !is_non_interactive {
-        if Confirm::with_theme(&ColorfulTheme::default())
+        if Confirm::with_theme(&theme())
             .with_prompt(format!(
                 "Do you want to use (activate) profile '{}' now?",
-                profile_name.cyan()
+                profile_name.accent()
             ))
             .default(true)
             .interact()?
         {
             // Directly call the use_profile execute function
             // Defaulting to global activation (local=false, global=true)
-            match crate::commands::use_profile::execute(profile_name.clone(), false, true) {
-                Ok(_) => println!("Profile '{}' activated globally.", profile_name.green()),
+            match crate::commands::use_profile::execute(
+                Some(profile_name.clone()),
+                false,
+                true,
+                false,
+            ) {
+                Ok(_) => println!("Profile '{}' activated globally.", profile_name.success()),
                 Err(e) => eprintln!(
                     "Failed to activate profile '{}': {}",
-                    profile_name.yellow(),
-                    e.to_string().red()
+                    profile_name.warning(),
+                    e.to_string().error()
                 ),
             }
         } else {
             println!(
                 "You can activate it later using: {}",
-                format!("gitp use {}", profile_name).yellow()
+                format!("gitp use {}", profile_name).warning()
+            );
+            print_alias_hint(&config, &profile_name);
+        }
+    } else {
+        print_alias_hint(&config, &profile_name);
+    }
+
+    Ok(())
+}
+
+/// Names and emails to suggest for a profile's identity: authors of recent commits in the
+/// current repository, then the other profiles' identities.
+pub(crate) fn identity_suggestions(
+    config: &Config,
+    except: Option<&str>,
+) -> (Suggestions, Suggestions) {
+    let mut identities = recent_authors(RECENT_AUTHORS).unwrap_or_default();
+    identities.extend(
+        config
+            .profiles
+            .values()
+            .filter(|p| Some(p.name.as_str()) != except)
+            .map(|p| {
+                (
+                    p.git_config.user_name.clone(),
+                    p.git_config.user_email.clone(),
+                )
+            }),
+    );
+    let (names, emails): (Vec<_>, Vec<_>) = identities.into_iter().unzip();
+    (Suggestions::new(names), Suggestions::new(emails))
+}
+
+/// Signing, SSH, line ending and HTTPS prompts, which most first profiles can skip. Hosts
+/// default to `new_default_host` from the settings.
+fn prompt_advanced(settings: &Settings, profile: &mut Profile) -> Result<()> {
+    println!("\n{}", "Signing (optional):".accent());
+    profile.git_config.user_signingkey = prompt_optional(
+        "Enter Git signing key (optional, press Enter to skip)",
+        None,
+    )?;
+    profile.gpg_key = prompt_optional(
+        "Enter GPG key ID for signing (optional, press Enter to skip)",
+        None,
+    )?;
+
+    println!("\n{}", "SSH (optional):".accent());
+    if Confirm::with_theme(&theme())
+        .with_prompt("Use an SSH key?")
+        .default(true)
+        .interact()?
+    {
+        prompt_ssh(settings, profile)?;
+    }
+
+    println!("\n{}", "Line endings (optional):".accent());
+    prompt_line_endings(&mut profile.git_config)?;
+
+    println!("\n{}", "HTTPS Credentials (optional):".accent());
+    while Confirm::with_theme(&theme())
+        .with_prompt(if profile.https_credentials.is_empty() {
+            "Add an HTTPS token?"
+        } else {
+            "Add an HTTPS token for another host?"
+        })
+        .default(false)
+        .interact()?
+    {
+        let default_host = match profile.https_credentials.is_empty() {
+            true => profile
+                .ssh_key_host
+                .as_deref()
+                .or(Some(&settings.new_default_host)),
+            false => None,
+        };
+        let https_host = prompt_required("Enter HTTPS host", default_host, "HTTPS host")?;
+        if profile
+            .https_credentials
+            .iter()
+            .any(|c| same_host(&c.host, &https_host))
+        {
+            println!("  The profile already has HTTPS credentials for that host.");
+            continue;
+        }
+        let https_username = prompt_required(
+            &format!("Enter HTTPS username for host '{}'", https_host),
+            None,
+            "HTTPS username",
+        )?;
+
+        let token_input: String = Password::with_theme(&theme())
+            .with_prompt("Enter HTTPS Token")
+            .with_confirmation("Confirm HTTPS Token", "Tokens do not match.")
+            .interact()
+            .context("Failed to get HTTPS token input.")?;
+        if token_input.trim().is_empty() {
+            bail!("Token cannot be empty. HTTPS credentials setup aborted.");
+        }
+
+        let store_in_keychain = Confirm::with_theme(&theme())
+            .with_prompt("Store this HTTPS token securely in the system keychain?")
+            .default(true)
+            .interact()?;
+        let credential_type_value = store_https_token(
+            settings,
+            &https_host,
+            &https_username,
+            token_input.trim(),
+            store_in_keychain,
+        )?;
+
+        profile.https_credentials.push(HttpsCredentials::new(
+            https_host,
+            https_username,
+            credential_type_value,
+        ));
+    }
+
+    prompt_private_email(profile)?;
+    Ok(())
+}
+
+/// Asks for the SSH host first, then prefills the login user and key path from what gitp
+/// knows about the host.
+fn prompt_ssh(settings: &Settings, profile: &mut Profile) -> Result<()> {
+    let host = prompt_required(
+        "Enter SSH key host (e.g., github.com, gitlab.mycompany.com)",
+        Some(&settings.new_default_host),
+        "SSH key host",
+    )?;
+    let provider = providers::lookup(&host);
+    if let Some(provider) = &provider {
+        println!(
+            "  {} defaults below; change any of them.",
+            provider.name.accent()
+        );
+    }
+
+    let ssh_user = prompt_required(
+        "SSH user",
+        Some(provider.as_ref().map_or(DEFAULT_SSH_USER, |p| &p.ssh_user)),
+        "SSH user",
+    )?;
+    profile.ssh_user = (ssh_user != DEFAULT_SSH_USER).then_some(ssh_user);
+
+    let suggested = contract_home(&default_key_path(&profile.name, "ed25519")?);
+    let key_path = prompt_required(
+        "Path to SSH key",
+        Some(&suggested.to_string_lossy()),
+        "SSH key path",
+    )?;
+    let key_path = normalize_path(&key_path);
+    let expanded = expand_home(&key_path.to_string_lossy());
+    if !expanded.exists() {
+        if !Confirm::with_theme(&theme())
+            .with_prompt(format!(
+                "{} doesn't exist. Generate it now?",
+                key_path.display()
+            ))
+            .default(true)
+            .interact()?
+        {
+            profile.ssh_user = None;
+            println!(
+                "Skipped the SSH key; add one later with '{}'.",
+                format!("gitp ssh-key generate {}", profile.name).accent()
             );
+            return Ok(());
+        }
+        profile.ssh_key_fingerprint =
+            generate_key(&expanded, &key_comment(&profile.name, Some(&host)))?;
+    }
+    profile.ssh_key = Some(key_path);
+    profile.ssh_key_host = Some(host);
+    Ok(())
+}
+
+/// For hosts that give accounts a private commit email, shows its form and lets the email be
+/// changed, when the profile's email isn't one already.
+fn prompt_private_email(profile: &mut Profile) -> Result<()> {
+    let hosts = profile
+        .ssh_key_host
+        .as_deref()
+        .into_iter()
+        .chain(profile.https_hosts());
+    let Some((provider, noreply)) = hosts
+        .filter_map(providers::lookup)
+        .find_map(|p| Some((p.name, p.noreply_email?)))
+    else {
+        return Ok(());
+    };
+    let domain = noreply.rsplit('@').next().unwrap_or(&noreply);
+    let email = &profile.git_config.user_email;
+    if email.to_lowercase().ends_with(&format!("@{}", domain)) {
+        return Ok(());
+    }
+    println!(
+        "\n{} keeps your address out of commits with a private email of the form {} (see \
+         your account's email settings).",
+        provider,
+        noreply.accent()
+    );
+    profile.git_config.user_email = prompt_required("Git user email", Some(email), "User email")?;
+    Ok(())
+}
+
+/// Tells how to clone through the new profile's host alias, which the SSH config only gets
+/// once a profile is used.
+fn print_alias_hint(config: &Config, name: &str) {
+    if let Some(example) = config.profiles.get(name).and_then(clone_example) {
+        println!(
+            "After '{}', clone its repositories through the host alias: {}",
+            format!("gitp use {}", name).accent(),
+            format!("git clone {}", example).accent()
+        );
+    }
+}
+
+/// Fills in name, email and signing key that weren't given on the command line from the
+/// global git identity, falling back to placeholders for name and email.
+fn fill_from_global_identity(
+    user_name: Option<String>,
+    user_email: Option<String>,
+    signing_key: Option<String>,
+) -> Result<(Option<String>, Option<String>, Option<String>)> {
+    let fill = |given: Option<String>, key: &str, placeholder: Option<&str>| -> Result<_> {
+        if given.as_ref().is_some_and(|v| !v.trim().is_empty()) {
+            return Ok(given);
         }
+        match get_git_config(key, GitConfigScope::Global)?.filter(|v| !v.trim().is_empty()) {
+            Some(value) => {
+                println!("  Using global {}: {}", key, value.success());
+                Ok(Some(value))
+            }
+            None => {
+                if let Some(placeholder) = placeholder {
+                    println!(
+                        "  Global {} is not set; using placeholder {}",
+                        key,
+                        placeholder.warning()
+                    );
+                }
+                Ok(placeholder.map(str::to_string))
+            }
+        }
+    };
+    Ok((
+        fill(user_name, "user.name", Some(PLACEHOLDER_USER_NAME))?,
+        fill(user_email, "user.email", Some(PLACEHOLDER_USER_EMAIL))?,
+        fill(signing_key, "user.signingkey", None)?,
+    ))
+}
+
+/// Asks for core.autocrlf and core.eol, defaulting to the values already in `git_config`.
+/// core.eol is only offered when autocrlf leaves it in effect.
+pub(crate) fn prompt_line_endings(git_config: &mut GitConfig) -> Result<()> {
+    let mut autocrlf_items = vec!["Leave unset".to_string()];
+    autocrlf_items.extend(AutoCrlf::ALL.iter().map(|v| v.as_str().to_string()));
+    let default = git_config
+        .core_autocrlf
+        .and_then(|current| AutoCrlf::ALL.iter().position(|v| *v == current))
+        .map_or(0, |i| i + 1);
+    let selection = Select::with_theme(&theme())
+        .with_prompt("core.autocrlf for this profile")
+        .items(&autocrlf_items)
+        .default(default)
+        .interact()
+        .context("Failed to get core.autocrlf selection.")?;
+    git_config.core_autocrlf = selection.checked_sub(1).map(|i| AutoCrlf::ALL[i]);
+
+    if matches!(
+        git_config.core_autocrlf,
+        Some(AutoCrlf::True | AutoCrlf::Input)
+    ) {
+        git_config.core_eol = None;
+        return Ok(());
     }
 
+    let mut eol_items = vec!["Leave unset".to_string()];
+    eol_items.extend(Eol::ALL.iter().map(|v| v.as_str().to_string()));
+    let default = git_config
+        .core_eol
+        .and_then(|current| Eol::ALL.iter().position(|v| *v == current))
+        .map_or(0, |i| i + 1);
+    let selection = Select::with_theme(&theme())
+        .with_prompt("core.eol for this profile")
+        .items(&eol_items)
+        .default(default)
+        .interact()
+        .context("Failed to get core.eol selection.")?;
+    git_config.core_eol = selection.checked_sub(1).map(|i| Eol::ALL[i]);
     Ok(())
 }
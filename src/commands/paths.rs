@@ -0,0 +1,82 @@
+use anyhow::Result;
+use colored::Colorize;
+use std::fs;
+use std::path::Path;
+
+use crate::commands::rules::fragment_dir;
+use crate::config::audit::changelog_path;
+use crate::config::cache::cache_path;
+use crate::config::storage::open_store;
+use crate::config::system::system_profiles_dir;
+use crate::credentials::cache;
+use crate::credentials::filestore::{get_secrets_path, key_cache_path};
+use crate::extras::{builtin_appliers, plugins};
+use crate::git::global_config_path;
+use crate::providers::overrides_path;
+use crate::ssh::ssh_config::get_ssh_config_path;
+use crate::utils::managed_block::BLOCK_BEGIN;
+
+/// Prints where gitp keeps its own files and which files of other tools it writes to.
+pub fn execute() -> Result<()> {
+    let store = open_store()?;
+    println!("{} ({} store)", "Configuration".bold(), store.name());
+    for path in store.paths() {
+        print_path(&path, false);
+    }
+    if let Some(path) = cache_path() {
+        print_path(&path, false);
+    }
+    print_path(&changelog_path()?, false);
+    print_path(&fragment_dir()?, false);
+    print_path(&overrides_path()?, false);
+    if let Some(dir) = system_profiles_dir() {
+        print_path(&dir, false);
+    }
+
+    println!("\n{}", "Secrets".bold());
+    print_path(&get_secrets_path()?, false);
+    if let Some(path) = key_cache_path() {
+        print_path(&path, false);
+    }
+    if let Some(path) = cache::socket_path() {
+        print_path(&path, false);
+    }
+
+    println!("\n{}", "Shared files (gitp-managed blocks)".bold());
+    print_path(&get_ssh_config_path()?, true);
+    for applier in builtin_appliers() {
+        print_path(&applier.config_path()?, true);
+    }
+
+    println!("\n{}", "Global git config".bold());
+    match global_config_path() {
+        Some(path) => print_path(&path, false),
+        None => println!("  {}", "(no home directory)".dimmed()),
+    }
+
+    let plugins = plugins::discover();
+    if !plugins.is_empty() {
+        println!("\n{}", "Plugins".bold());
+        for plugin in plugins {
+            print_path(&plugin.path, false);
+        }
+    }
+    Ok(())
+}
+
+/// Prints `path` with a note when it doesn't exist or, for files gitp shares with the user,
+/// when it holds no gitp block.
+fn print_path(path: &Path, managed: bool) {
+    let note = if !path.exists() {
+        " (not created yet)"
+    } else if managed
+        && !fs::read_to_string(path)
+            .map(|content| content.contains(BLOCK_BEGIN))
+            .unwrap_or(false)
+    {
+        " (no gitp block)"
+    } else {
+        ""
+    };
+    println!("  {}{}", path.display(), note.dimmed());
+}
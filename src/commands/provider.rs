@@ -0,0 +1,49 @@
+// src/commands/provider.rs
+use anyhow::{bail, Result};
+use colored::Colorize;
+
+use crate::providers::{overrides_path, Providers};
+use crate::utils::output::Paint;
+
+/// Prints what gitp knows about `host`, with the overrides of providers.toml applied.
+pub fn execute(host: &str) -> Result<()> {
+    let overrides = Providers::load()?;
+    let Some(provider) = overrides.find(host) else {
+        bail!(
+            "gitp doesn't know what '{}' runs. Describe it in {} (e.g. kind = \"gitlab\").",
+            host.warning(),
+            overrides_path()?.display()
+        );
+    };
+    let or_none = |value: Option<String>| value.unwrap_or_else(|| "(none)".dimmed().to_string());
+
+    println!("{} ({})", provider.host.bold(), provider.name);
+    println!(
+        "  {} {}@{}:{}",
+        "SSH:".accent(),
+        provider.ssh_user,
+        provider.ssh_host,
+        provider.ssh_port
+    );
+    println!(
+        "  {} {}",
+        "API:".accent(),
+        or_none(provider.api_base.clone())
+    );
+    println!(
+        "  {} {}",
+        "Token header:".accent(),
+        provider.auth_header("<token>").0
+    );
+    println!(
+        "  {} {}",
+        "Key upload:".accent(),
+        or_none(provider.key_upload_url("<user>"))
+    );
+    println!(
+        "  {} {}",
+        "Private email:".accent(),
+        or_none(provider.noreply_email.clone())
+    );
+    Ok(())
+}
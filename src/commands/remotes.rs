@@ -0,0 +1,238 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::path::{Path, PathBuf};
+
+use crate::cli::RemotesCommands;
+use crate::commands::use_profile::PROFILE_MARKER_KEY;
+use crate::config::{same_host, Config, Profile};
+use crate::git::url::{parse_remote_url, Transport};
+use crate::git::{
+    find_repos, get_git_config, get_remotes, in_repo, set_git_config, GitConfigScope, Remote,
+};
+use crate::utils::expand_home;
+use crate::utils::output::{theme, Paint};
+use crate::utils::prompt::Confirm;
+use crate::utils::terminal::require_terminal;
+
+/// How many directories below `--path` are searched for repositories.
+pub(crate) const SCAN_DEPTH: usize = 4;
+
+pub fn execute(command: RemotesCommands) -> Result<()> {
+    match command {
+        RemotesCommands::Migrate { path, dry_run, yes } => migrate(path, dry_run, yes),
+    }
+}
+
+/// `url` rewritten to go through `profile`'s host alias, when it is an SSH remote for the
+/// profile's raw host.
+pub(crate) fn aliased_url(url: &str, profile: &Profile) -> Option<String> {
+    let (alias, host) = (
+        profile.ssh_host_alias.as_ref()?,
+        profile.ssh_key_host.as_ref()?,
+    );
+    let remote = parse_remote_url(url)?;
+    (remote.transport == Transport::Ssh && same_host(&remote.host, host))
+        .then(|| url.replacen(&remote.host, alias, 1))
+}
+
+fn migrate(path: Option<String>, dry_run: bool, yes: bool) -> Result<()> {
+    let config = Config::load().context("Failed to load configuration.")?;
+    if !config.profiles.values().any(|p| p.ssh_host_alias.is_some()) {
+        println!("No profile has an SSH host alias; nothing to migrate.");
+        return Ok(());
+    }
+    if !dry_run && !yes {
+        require_terminal(
+            "Confirming remote rewrites",
+            "Pass --yes to rewrite without asking, or --dry-run to only list the changes.",
+        )?;
+    }
+
+    let root = match path {
+        Some(path) => expand_home(&path),
+        None => std::env::current_dir().context("Failed to get current directory.")?,
+    };
+    let repos = find_repos(&root, SCAN_DEPTH);
+    println!(
+        "Scanning {} repositories under {}...",
+        repos.len(),
+        root.display()
+    );
+
+    let (mut migrated, mut skipped) = (0, 0);
+    for repo in &repos {
+        let remotes = match in_repo(repo, get_remotes) {
+            Ok(remotes) => remotes,
+            Err(e) => {
+                println!("  {}: {}: {:#}", "Warning".warning(), repo.display(), e);
+                continue;
+            }
+        };
+        let profile = match repo_profile(&config, repo)? {
+            Some(profile) => profile,
+            None => {
+                // Only worth mentioning when some alias could apply
+                let covered = remotes.iter().any(|remote| {
+                    config
+                        .profiles
+                        .values()
+                        .any(|p| aliased_url(&remote.effective_url, p).is_some())
+                });
+                if covered {
+                    skipped += 1;
+                    println!(
+                        "\n{} {}",
+                        repo.display(),
+                        "(skipped: no profile known for this repository)".dimmed()
+                    );
+                    println!(
+                        "  Run '{}' in it first.",
+                        "gitp use <profile> --local".accent()
+                    );
+                }
+                continue;
+            }
+        };
+
+        // Judged by the URL git connects to: a stored URL may only reach the raw host through
+        // an insteadOf rule, or already reach the alias through one
+        let changes: Vec<(Remote, String)> = remotes
+            .into_iter()
+            .filter_map(|remote| {
+                aliased_url(&remote.effective_url, profile).map(|new| (remote, new))
+            })
+            .collect();
+        if changes.is_empty() {
+            continue;
+        }
+
+        println!(
+            "\n{} (profile {})",
+            repo.display().to_string().bold(),
+            profile.name.success()
+        );
+        for (remote, new) in &changes {
+            println!(
+                "  {}: {} -> {}",
+                remote.name,
+                remote.url.dimmed(),
+                new.success()
+            );
+            if remote.url != remote.effective_url {
+                println!(
+                    "    {}",
+                    format!("(insteadOf rewrites it to {})", remote.effective_url).dimmed()
+                );
+            }
+        }
+        if dry_run {
+            continue;
+        }
+        if !yes
+            && !Confirm::with_theme(&theme())
+                .with_prompt("Rewrite these remotes?")
+                .default(true)
+                .interact()?
+        {
+            skipped += 1;
+            continue;
+        }
+        in_repo(repo, || {
+            for (remote, new) in &changes {
+                set_git_config(
+                    &format!("remote.{}.url", remote.name),
+                    new,
+                    GitConfigScope::Local,
+                )?;
+            }
+            Ok(())
+        })
+        .with_context(|| format!("Failed to rewrite remotes of {}", repo.display()))?;
+        migrated += 1;
+    }
+
+    println!();
+    if dry_run {
+        println!("Dry run: nothing was changed.");
+    } else {
+        println!(
+            "Rewrote remotes in {} repositories; {} skipped.",
+            migrated.to_string().success(),
+            skipped
+        );
+    }
+    Ok(())
+}
+
+/// The profile a repository belongs to: the one registered by `use --local`, else the one its
+/// local marker names, else the one whose email it commits with.
+fn repo_profile<'a>(config: &'a Config, repo: &Path) -> Result<Option<&'a Profile>> {
+    let key = canonical(repo).to_string_lossy().into_owned();
+    if let Some(profile) = config
+        .state
+        .repos
+        .get(&key)
+        .and_then(|n| config.profiles.get(n))
+    {
+        return Ok(Some(profile));
+    }
+    let (marker, email) = in_repo(repo, || {
+        Ok((
+            get_git_config(PROFILE_MARKER_KEY, GitConfigScope::Local)?,
+            get_git_config("user.email", GitConfigScope::Local)?,
+        ))
+    })?;
+    if let Some(profile) = marker.and_then(|m| config.profiles.get(&m)) {
+        return Ok(Some(profile));
+    }
+    Ok(email.and_then(|email| {
+        let mut matching = config
+            .profiles
+            .values()
+            .filter(|p| p.git_config.user_email.eq_ignore_ascii_case(&email));
+        // An email shared by several profiles doesn't identify one
+        match (matching.next(), matching.next()) {
+            (Some(profile), None) => Some(profile),
+            _ => None,
+        }
+    }))
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aliased_url() {
+        let mut profile = Profile::new(
+            "work".to_string(),
+            "John Doe".to_string(),
+            "john@company.com".to_string(),
+        );
+        profile.ssh_key_host = Some("github.com".to_string());
+        assert_eq!(aliased_url("git@github.com:acme/app.git", &profile), None);
+
+        profile.ssh_host_alias = Some("github.com-work".to_string());
+        assert_eq!(
+            aliased_url("git@github.com:acme/app.git", &profile).as_deref(),
+            Some("git@github.com-work:acme/app.git")
+        );
+        assert_eq!(
+            aliased_url("ssh://git@GitHub.com/acme/app.git", &profile).as_deref(),
+            Some("ssh://git@github.com-work/acme/app.git")
+        );
+        assert_eq!(
+            aliased_url("https://github.com/acme/app.git", &profile),
+            None
+        );
+        assert_eq!(
+            aliased_url("git@github.com-work:acme/app.git", &profile),
+            None
+        );
+        assert_eq!(aliased_url("git@gitlab.com:acme/app.git", &profile), None);
+    }
+}
@@ -1,81 +1,416 @@
-use anyhow::{bail, Context, Result};
+use anyhow::{anyhow, Context, Result};
 use colored::Colorize;
-use dialoguer::{theme::ColorfulTheme, Confirm};
+use std::path::Path;
 
-use crate::config::{Config, CredentialType};
-use crate::credentials::keyring::delete_token;
+use crate::commands::rules::refresh_rules;
+use crate::commands::use_profile::{apply_git_identity, clear_git_identity, sync_ssh_config};
+use crate::config::{Config, Profile};
+use crate::credentials::{delete_stored_token, store_label};
+use crate::git::{in_repo, GitConfigScope};
+use crate::utils::output::{theme, Paint};
+use crate::utils::prompt::{Confirm, Select};
+use crate::utils::terminal::require_terminal;
 
-pub fn execute(name: String, force: bool) -> Result<()> {
+/// Removes the profiles selected by `names` (profile names or globs such as `client-*`) after
+/// a single confirmation listing everything that goes with them.
+pub fn execute(names: Vec<String>, force: bool) -> Result<()> {
     let mut config = Config::load().context("Failed to load configuration.")?;
-
-    if !config.profiles.contains_key(&name) {
-        bail!("Profile '{}' not found. Cannot remove it.", name.yellow());
+    let selected = config.select_profiles(&names)?;
+    for name in &selected {
+        config.require_user_profile(name, "remove")?;
     }
 
     if !force {
-        let confirmation = Confirm::with_theme(&ColorfulTheme::default())
-            .with_prompt(format!(
+        require_terminal(
+            "Confirming the removal",
+            "Pass --force to remove the profiles without asking.",
+        )?;
+        print_removal_summary(&config, &selected);
+        let prompt = match selected.as_slice() {
+            [name] => format!(
                 "Are you sure you want to remove profile '{}'?",
-                name.yellow()
-            ))
+                name.warning()
+            ),
+            _ => format!("Remove these {} profiles?", selected.len()),
+        };
+        let confirmation = Confirm::with_theme(&theme())
+            .with_prompt(prompt)
             .default(false) // Default to No
             .interact()
             .context("Failed to get confirmation for removal.")?;
 
         if !confirmation {
-            println!("Removal of profile '{}' cancelled.", name.cyan());
+            println!("Removal cancelled.");
             return Ok(());
         }
     }
 
-    // Get the profile before removing it to check for keychain credentials
-    let profile_to_remove = config.profiles.get(&name).cloned(); // Cloned to avoid borrow issues
-
-    // Remove the profile from the HashMap
-    if config.profiles.remove(&name).is_some() {
-        if let Some(profile) = profile_to_remove {
-            if let Some(https_creds) = profile.https_credentials {
-                if let CredentialType::KeychainRef(keychain_username) = https_creds.credential_type
-                {
-                    match delete_token(&https_creds.host, &keychain_username) {
-                        Ok(_) => println!(
-                            "  Successfully deleted token for {}@{} from keychain.",
-                            keychain_username.cyan(),
-                            https_creds.host.green()
-                        ),
-                        Err(e) => eprintln!(
-                            "  {}: Failed to delete token for {}@{} from keychain: {}. Please remove it manually if needed.",
-                            "Warning".yellow(),
-                            keychain_username.cyan(),
-                            https_creds.host.green(),
-                            e
-                        ),
-                    }
-                }
+    let had_ssh_entries = selected
+        .iter()
+        .any(|name| !ssh_hosts(&config, &config.profiles[name]).is_empty());
+    let had_rules = selected
+        .iter()
+        .any(|name| !config.profiles[name].auto_dirs.is_empty());
+    for name in &selected {
+        clean_repo_applications(&mut config, name, &selected, force)?;
+        remove_profile(&mut config, name)?;
+    }
+
+    config
+        .save()
+        .context("Failed to save configuration after removing profile.")?;
+
+    // Drop the removed profiles' Host entries from the managed SSH block
+    if had_ssh_entries {
+        sync_ssh_config(&config)?;
+    }
+    if had_rules {
+        refresh_rules(&config);
+    }
+
+    for name in &selected {
+        println!("Profile '{}' removed successfully.", name.success());
+    }
+
+    Ok(())
+}
+
+/// Lists each profile about to be removed with the stored token, SSH host entry and local
+/// applications that go with it.
+fn print_removal_summary(config: &Config, selected: &[String]) {
+    println!("The following will be removed:");
+    for name in selected {
+        let profile = &config.profiles[name];
+        let current = if config.state.current_profile.as_deref() == Some(name.as_str()) {
+            " (current)"
+        } else {
+            ""
+        };
+        println!("  {}{}", name.warning().bold(), current.dimmed());
+        for https_creds in &profile.https_credentials {
+            if let Some(account) = https_creds.credential_type.stored_account() {
+                println!(
+                    "    token for {}@{} in {}",
+                    account.accent(),
+                    https_creds.host.success(),
+                    store_label(&https_creds.credential_type)
+                );
             }
         }
-    } else {
-        // This case should ideally not be reached if the initial check (line 9) passes
-        bail!(
+        for host in ssh_hosts(config, profile) {
+            println!("    SSH host entry '{}' in ~/.ssh/config", host.accent());
+        }
+        let repos = config.state.repos.values().filter(|p| *p == name).count();
+        if repos > 0 {
+            println!(
+                "    local application in {} repositor{} (you'll be asked what to do)",
+                repos,
+                if repos == 1 { "y" } else { "ies" }
+            );
+        }
+    }
+}
+
+/// The Hosts the profile has entries for in the managed SSH block.
+fn ssh_hosts<'a>(config: &Config, profile: &'a Profile) -> Vec<&'a str> {
+    if !config.manages_ssh(profile) {
+        return Vec::new();
+    }
+    let primary = profile
+        .ssh_key
+        .as_ref()
+        .and(profile.ssh_key_host.as_ref())
+        .and(profile.ssh_host());
+    primary
+        .into_iter()
+        .chain(profile.ssh_hosts.iter().map(|entry| entry.host.as_str()))
+        .collect()
+}
+
+/// Removes `name` from `config` along with its stored token and its runtime state.
+fn remove_profile(config: &mut Config, name: &str) -> Result<()> {
+    let profile = config.profiles.remove(name).ok_or_else(|| {
+        anyhow!(
             "Profile '{}' was expected but not found during removal operation.",
-            name.yellow()
-        );
+            name.warning()
+        )
+    })?;
+
+    for https_creds in profile.https_credentials {
+        if let Some(account) = https_creds.credential_type.stored_account() {
+            let store = store_label(&https_creds.credential_type);
+            match delete_stored_token(
+                &config.settings,
+                &https_creds.host,
+                &https_creds.credential_type,
+            ) {
+                Ok(_) => println!(
+                    "  Successfully deleted token for {}@{} from {}.",
+                    account.accent(),
+                    https_creds.host.success(),
+                    store
+                ),
+                Err(e) => eprintln!(
+                    "  {}: Failed to delete token for {}@{} from {}: {}. Please remove it manually if needed.",
+                    "Warning".warning(),
+                    account.accent(),
+                    https_creds.host.success(),
+                    store,
+                    e
+                ),
+            }
+        }
     }
 
     // If the removed profile was the current one, unset it
-    if config.current_profile.as_deref() == Some(name.as_str()) {
-        config.current_profile = None;
+    let was_current = config.state.current_profile.as_deref() == Some(name);
+    config.state.forget_profile(name);
+    if was_current {
         println!(
             "Profile '{}' was the current profile and has been unset.",
-            name.yellow()
+            name.warning()
         );
     }
+    Ok(())
+}
 
-    config
-        .save()
-        .context("Failed to save configuration after removing profile.")?;
+/// What happens to the repositories a removed profile was applied in.
+#[derive(Debug, Clone, PartialEq)]
+enum RepoAction {
+    /// Unset the profile's local Git config
+    Unset,
+    /// Apply another profile
+    Reassign(String),
+    /// Leave the local Git config as it is
+    Leave,
+}
+
+/// Handles repositories where the profile was applied with `use --local`: unset its identity
+/// there, reassign them to another profile not in `removing`, or leave them as they are. With
+/// `force` nothing is asked and the repositories are left untouched.
+fn clean_repo_applications(
+    config: &mut Config,
+    name: &str,
+    removing: &[String],
+    force: bool,
+) -> Result<()> {
+    let repos = take_repo_applications(config, name);
+    if repos.is_empty() {
+        return Ok(());
+    }
 
-    println!("Profile '{}' removed successfully.", name.green());
+    println!(
+        "Profile '{}' is applied locally in {} repositor{}:",
+        name.warning(),
+        repos.len(),
+        if repos.len() == 1 { "y" } else { "ies" }
+    );
+    for repo in &repos {
+        println!("  {}", repo.accent());
+    }
 
+    if force {
+        println!(
+            "  Leaving their local Git config untouched. Run '{}' in them to switch.",
+            "gitp use <profile> --local".accent()
+        );
+        return Ok(());
+    }
+
+    let action = ask_repo_action(config, removing)?;
+    apply_repo_action(config, &repos, &action);
     Ok(())
 }
+
+/// Removes the registry entries of the repositories `name` was applied in, and returns those
+/// that still exist.
+fn take_repo_applications(config: &mut Config, name: &str) -> Vec<String> {
+    let (repos, missing): (Vec<String>, Vec<String>) = config
+        .state
+        .repos
+        .iter()
+        .filter(|(_, profile)| *profile == name)
+        .map(|(repo, _)| repo.clone())
+        .partition(|repo| Path::new(repo).is_dir());
+    for repo in missing.iter().chain(&repos) {
+        config.state.repos.remove(repo);
+    }
+    repos
+}
+
+/// Asks what to do with the repositories; profiles in `removing` can't be reassigned to.
+fn ask_repo_action(config: &Config, removing: &[String]) -> Result<RepoAction> {
+    let mut others: Vec<String> = config
+        .profiles
+        .keys()
+        .filter(|p| !removing.contains(p))
+        .cloned()
+        .collect();
+    others.sort();
+
+    let mut options = vec!["Unset the profile's local Git config in these repositories"];
+    if !others.is_empty() {
+        options.push("Reassign them to another profile");
+    }
+    options.push("Leave them as they are");
+    let choice = Select::with_theme(&theme())
+        .with_prompt("What should happen to these repositories?")
+        .items(&options)
+        .default(0)
+        .interact()
+        .context("Failed to get choice for repositories.")?;
+
+    if choice == 0 {
+        Ok(RepoAction::Unset)
+    } else if choice == 1 && !others.is_empty() {
+        let target = Select::with_theme(&theme())
+            .with_prompt("Reassign to which profile?")
+            .items(&others)
+            .default(0)
+            .interact()
+            .context("Failed to get profile choice.")?;
+        Ok(RepoAction::Reassign(others[target].clone()))
+    } else {
+        Ok(RepoAction::Leave)
+    }
+}
+
+/// Carries out `action` in each of `repos`. Failures are reported and don't stop the others;
+/// reassigned repositories are registered under their new profile.
+fn apply_repo_action(config: &mut Config, repos: &[String], action: &RepoAction) {
+    match action {
+        RepoAction::Unset => {
+            for repo in repos {
+                match in_repo(Path::new(repo), || {
+                    clear_git_identity(GitConfigScope::Local)
+                }) {
+                    Ok(_) => println!("  Unset local Git identity in {}", repo.accent()),
+                    Err(e) => eprintln!(
+                        "  {}: Failed to unset local Git config in {}: {:#}",
+                        "Warning".warning(),
+                        repo,
+                        e
+                    ),
+                }
+            }
+        }
+        RepoAction::Reassign(target) => {
+            for repo in repos {
+                println!("Applying profile '{}' in {}...", target.accent(), repo);
+                match in_repo(Path::new(repo), || {
+                    apply_git_identity(config, target, GitConfigScope::Local)
+                }) {
+                    Ok(_) => {
+                        config.state.repos.insert(repo.clone(), target.clone());
+                    }
+                    Err(e) => eprintln!(
+                        "  {}: Failed to apply '{}' in {}: {:#}",
+                        "Warning".warning(),
+                        target,
+                        repo,
+                        e
+                    ),
+                }
+            }
+        }
+        RepoAction::Leave => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::use_profile::PROFILE_MARKER_KEY;
+    use crate::git::get_git_config;
+
+    fn config() -> Config {
+        let mut config = Config::default();
+        for name in ["work", "home"] {
+            let profile = Profile::new(
+                name.to_string(),
+                "John Doe".to_string(),
+                format!("john@{}.com", name),
+            );
+            config.profiles.insert(name.to_string(), profile);
+        }
+        config
+    }
+
+    fn init_repo(dir: &Path) -> Result<String> {
+        git2::Repository::init(dir)?;
+        Ok(dir.to_string_lossy().into_owned())
+    }
+
+    fn local_config(repo: &str, key: &str) -> Result<Option<String>> {
+        in_repo(Path::new(repo), || {
+            get_git_config(key, GitConfigScope::Local)
+        })
+    }
+
+    #[test]
+    fn test_take_repo_applications() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let existing = init_repo(&dir.path().join("app"))?;
+        let other = init_repo(&dir.path().join("blog"))?;
+        let missing = dir.path().join("gone").to_string_lossy().into_owned();
+        let mut config = config();
+        for (repo, profile) in [(&existing, "work"), (&missing, "work"), (&other, "home")] {
+            config.state.repos.insert(repo.clone(), profile.to_string());
+        }
+
+        // Missing repositories are pruned; the others are left to the caller
+        assert_eq!(take_repo_applications(&mut config, "work"), [existing]);
+        assert_eq!(
+            config.state.repos.into_iter().collect::<Vec<_>>(),
+            [(other, "home".to_string())]
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_apply_repo_action() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut config = config();
+        let mut repos = Vec::new();
+        for name in ["a-unset", "b-reassign", "c-leave"] {
+            let repo = init_repo(&dir.path().join(name))?;
+            in_repo(Path::new(&repo), || {
+                apply_git_identity(&mut config, "work", GitConfigScope::Local)
+            })?;
+            config.state.repos.insert(repo.clone(), "work".to_string());
+            repos.push(repo);
+        }
+        assert_eq!(take_repo_applications(&mut config, "work"), repos);
+
+        apply_repo_action(&mut config, &repos[..1], &RepoAction::Unset);
+        assert_eq!(local_config(&repos[0], "user.email")?, None);
+        assert_eq!(local_config(&repos[0], PROFILE_MARKER_KEY)?, None);
+
+        apply_repo_action(
+            &mut config,
+            &repos[1..2],
+            &RepoAction::Reassign("home".to_string()),
+        );
+        assert_eq!(
+            local_config(&repos[1], "user.email")?.as_deref(),
+            Some("john@home.com")
+        );
+        assert_eq!(
+            local_config(&repos[1], PROFILE_MARKER_KEY)?.as_deref(),
+            Some("home")
+        );
+        assert_eq!(
+            config.state.repos.get(&repos[1]).map(String::as_str),
+            Some("home")
+        );
+
+        apply_repo_action(&mut config, &repos[2..], &RepoAction::Leave);
+        assert_eq!(
+            local_config(&repos[2], "user.email")?.as_deref(),
+            Some("john@work.com")
+        );
+        assert_eq!(config.state.repos.len(), 1);
+        Ok(())
+    }
+}
@@ -1,7 +1,12 @@
 use anyhow::{bail, Context, Result};
-use colored::Colorize;
 
+use crate::commands::rules::refresh_rules;
+use crate::commands::use_profile::PROFILE_MARKER_KEY;
 use crate::config::Config;
+use crate::credentials::helper;
+use crate::git::{get_git_config, in_repo, set_git_config, GitConfigScope};
+use crate::utils::output::Paint;
+use std::path::Path;
 
 pub fn execute(old_name: String, new_name: String) -> Result<()> {
     let mut config = Config::load().context("Failed to load configuration.")?;
@@ -13,10 +18,12 @@ pub fn execute(old_name: String, new_name: String) -> Result<()> {
     if !config.profiles.contains_key(&old_name) {
         bail!(
             "Profile '{}' not found. Cannot rename it.",
-            old_name.yellow()
+            old_name.warning()
         );
     }
 
+    config.require_user_profile(&old_name, "rename")?;
+
     if old_name == new_name {
         println!("The new name is the same as the old name. No changes made.");
         return Ok(());
@@ -25,7 +32,7 @@ pub fn execute(old_name: String, new_name: String) -> Result<()> {
     if config.profiles.contains_key(&new_name) {
         bail!(
             "A profile named '{}' already exists. Please choose a different name.",
-            new_name.yellow()
+            new_name.warning()
         );
     }
 
@@ -35,29 +42,141 @@ pub fn execute(old_name: String, new_name: String) -> Result<()> {
         config.profiles.insert(new_name.clone(), profile_to_rename);
 
         // If the renamed profile was the current one, update current_profile
-        if config.current_profile.as_deref() == Some(old_name.as_str()) {
-            config.current_profile = Some(new_name.clone());
+        if config.state.current_profile.as_deref() == Some(old_name.as_str()) {
             println!(
                 "Current profile '{}' has been updated to '{}'.",
-                old_name.yellow(),
-                new_name.green()
+                old_name.warning(),
+                new_name.success()
             );
         }
 
+        update_git_references(&mut config, &old_name, &new_name);
+
         config
             .save()
             .context("Failed to save configuration after renaming profile.")?;
+        // The rules include a fragment named after the profile
+        if !config.profiles[&new_name].auto_dirs.is_empty() {
+            refresh_rules(&config);
+        }
 
         println!(
             "Profile '{}' successfully renamed to '{}'.",
-            old_name.yellow(),
-            new_name.green()
+            old_name.warning(),
+            new_name.success()
         );
     } else {
         // This case should ideally be caught by the contains_key check earlier,
         // but it's good practice for robustness if remove somehow fails after a successful check.
-        bail!("Failed to retrieve profile '{}' for renaming, though it was initially found. This should not happen.", old_name.red());
+        bail!("Failed to retrieve profile '{}' for renaming, though it was initially found. This should not happen.", old_name.error());
     }
 
     Ok(())
 }
+
+/// Updates references to the profile outside the profile table: gitp's state (current profile,
+/// repo registry, usage history), and the
+/// `gitp.profile` marker and credential helper entries in the global config and in every
+/// registered repository that still exists. Failures are reported as warnings since the rename
+/// itself has already succeeded.
+fn update_git_references(config: &mut Config, old_name: &str, new_name: &str) {
+    let repos: Vec<String> = config
+        .state
+        .repos
+        .iter()
+        .filter(|(_, profile)| *profile == old_name)
+        .map(|(repo, _)| repo.clone())
+        .collect();
+    config.state.rename_profile(old_name, new_name);
+
+    if let Err(e) = update_scope(old_name, new_name, GitConfigScope::Global) {
+        eprintln!(
+            "  {}: Failed to update global Git config: {:#}",
+            "Warning".warning(),
+            e
+        );
+    }
+    for repo in repos.iter().filter(|r| Path::new(r).is_dir()) {
+        match in_repo(Path::new(repo), || {
+            update_scope(old_name, new_name, GitConfigScope::Local)
+        }) {
+            Ok(_) => println!("  Updated local Git config in {}", repo.accent()),
+            Err(e) => eprintln!(
+                "  {}: Failed to update local Git config in {}: {:#}",
+                "Warning".warning(),
+                repo,
+                e
+            ),
+        }
+    }
+    if !repos.is_empty() {
+        println!(
+            "Updated {} registered repositor{}.",
+            repos.len(),
+            if repos.len() == 1 { "y" } else { "ies" }
+        );
+    }
+}
+
+fn update_scope(old_name: &str, new_name: &str, scope: GitConfigScope) -> Result<()> {
+    if get_git_config(PROFILE_MARKER_KEY, scope)?.as_deref() == Some(old_name) {
+        set_git_config(PROFILE_MARKER_KEY, new_name, scope)?;
+    }
+    helper::rename_helper_profile(old_name, new_name, scope)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::{add_git_config, get_git_config_regexp};
+
+    const OLD: &str = "gitp-test-old";
+    const NEW: &str = "gitp-test-new";
+
+    #[test]
+    #[serial_test::serial]
+    fn test_rename_updates_marker_and_helpers() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        git2::Repository::init(dir.path())?;
+        let repo = dir.path().to_string_lossy().into_owned();
+        let local = GitConfigScope::Local;
+        in_repo(dir.path(), || {
+            set_git_config(PROFILE_MARKER_KEY, OLD, local)?;
+            let key = helper::helper_key("github.com");
+            add_git_config(&key, "", local)?;
+            add_git_config(&key, &helper::helper_command(OLD), local)?;
+            add_git_config(&key, "osxkeychain", local)?;
+            add_git_config(
+                &helper::helper_key("gitlab.com"),
+                &helper::helper_command("other"),
+                local,
+            )
+        })?;
+        let mut config = Config::default();
+        config.state.repos.insert(repo.clone(), OLD.to_string());
+
+        update_git_references(&mut config, OLD, NEW);
+
+        assert_eq!(config.state.repos.get(&repo).map(String::as_str), Some(NEW));
+        let (marker, helpers) = in_repo(dir.path(), || {
+            Ok((
+                get_git_config(PROFILE_MARKER_KEY, local)?,
+                get_git_config_regexp(r"^credential\..+\.helper$", local)?,
+            ))
+        })?;
+        assert_eq!(marker.as_deref(), Some(NEW));
+        let helpers: Vec<&str> = helpers.iter().map(|(_, value)| value.as_str()).collect();
+        // Only the renamed profile's entry changes, in place
+        assert_eq!(
+            helpers,
+            [
+                "",
+                helper::helper_command(NEW).as_str(),
+                "osxkeychain",
+                helper::helper_command("other").as_str(),
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,167 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+use crate::config::Config;
+use crate::git::{commit_identities, get_repo_root, in_repo, CommitIdentity};
+use crate::utils::output::Paint;
+
+/// Commits authored with one email.
+#[derive(Debug, Clone, PartialEq)]
+struct AuthorRow {
+    email: String,
+    /// Profile the email belongs to, if any
+    profile: Option<String>,
+    /// Author names used with the email
+    names: BTreeSet<String>,
+    commits: usize,
+}
+
+/// Summarizes who authored the repository's commits, by email, against the stored profiles.
+pub fn execute(path: Option<String>) -> Result<()> {
+    let config = Config::load_cached().context("Failed to load configuration.")?;
+    let start = match path {
+        Some(path) => PathBuf::from(path),
+        None => std::env::current_dir().context("Failed to get current directory.")?,
+    };
+    let (repo, identities) = in_repo(&start, || {
+        let repo = get_repo_root()?
+            .with_context(|| format!("{} is not in a git repository.", start.display()))?;
+        Ok((repo, commit_identities()?))
+    })?;
+
+    println!(
+        "{} {} ({} commits)",
+        "Commit authors in".bold(),
+        repo.display().to_string().bold(),
+        identities.len()
+    );
+    if identities.is_empty() {
+        println!("  No commits yet.");
+        return Ok(());
+    }
+
+    let rows = author_rows(&config, &identities);
+    let email_width = rows.iter().map(|r| r.email.len()).max().unwrap_or(0);
+    let profile_width = rows
+        .iter()
+        .filter_map(|r| r.profile.as_ref().map(String::len))
+        .max()
+        .unwrap_or(0)
+        .max("unknown".len());
+    println!();
+    for row in &rows {
+        let profile = match &row.profile {
+            Some(profile) => format!("{:<w$}", profile, w = profile_width).success(),
+            None => format!("{:<w$}", "unknown", w = profile_width)
+                .error()
+                .bold(),
+        };
+        println!(
+            "  {}  {:<w$}  {:>6}  {}",
+            profile,
+            row.email,
+            row.commits,
+            row.names
+                .iter()
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(", ")
+                .dimmed(),
+            w = email_width
+        );
+    }
+
+    let unknown: Vec<&AuthorRow> = rows.iter().filter(|r| r.profile.is_none()).collect();
+    if !unknown.is_empty() {
+        println!(
+            "\n{} {} email(s) belong to no profile. Check they are meant to be public before \
+             publishing the history.",
+            "Warning:".warning().bold(),
+            unknown.len()
+        );
+    }
+    let profiles: BTreeSet<&str> = rows.iter().filter_map(|r| r.profile.as_deref()).collect();
+    if profiles.len() > 1 {
+        println!(
+            "{} commits were made under {} profiles ({}). '{}' can attribute them to one.",
+            "Note:".accent().bold(),
+            profiles.len(),
+            profiles.into_iter().collect::<Vec<_>>().join(", "),
+            "gitp mailmap generate".accent()
+        );
+    }
+    Ok(())
+}
+
+/// One row per author email (compared case-insensitively), most commits first.
+fn author_rows(config: &Config, identities: &[CommitIdentity]) -> Vec<AuthorRow> {
+    let mut rows: BTreeMap<String, AuthorRow> = BTreeMap::new();
+    for id in identities {
+        let row = rows
+            .entry(id.author_email.to_lowercase())
+            .or_insert_with(|| AuthorRow {
+                email: id.author_email.clone(),
+                profile: profile_for_email(config, &id.author_email),
+                names: BTreeSet::new(),
+                commits: 0,
+            });
+        row.names.insert(id.author_name.clone());
+        row.commits += 1;
+    }
+    let mut rows: Vec<AuthorRow> = rows.into_values().collect();
+    rows.sort_by(|a, b| b.commits.cmp(&a.commits).then(a.email.cmp(&b.email)));
+    rows
+}
+
+/// The first profile, by name, committing with `email`.
+fn profile_for_email(config: &Config, email: &str) -> Option<String> {
+    let mut names: Vec<&String> = config
+        .profiles
+        .iter()
+        .filter(|(_, p)| p.git_config.user_email.eq_ignore_ascii_case(email))
+        .map(|(name, _)| name)
+        .collect();
+    names.sort();
+    names.first().map(|name| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Profile;
+
+    #[test]
+    fn test_author_rows() {
+        let mut config = Config::default();
+        let profile = Profile::new(
+            "work".to_string(),
+            "J. Doe".to_string(),
+            "john@company.com".to_string(),
+        );
+        config.profiles.insert("work".to_string(), profile);
+        let commit = |name: &str, email: &str| CommitIdentity {
+            author_name: name.to_string(),
+            author_email: email.to_string(),
+            committer_name: "GitHub".to_string(),
+            committer_email: "noreply@github.com".to_string(),
+        };
+        let identities = [
+            commit("J. Doe", "john@company.com"),
+            commit("bot", "ci@example.com"),
+            commit("John Doe", "John@Company.com"),
+        ];
+
+        let rows = author_rows(&config, &identities);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].profile.as_deref(), Some("work"));
+        assert_eq!(rows[0].commits, 2);
+        assert_eq!(
+            rows[0].names.iter().collect::<Vec<_>>(),
+            ["J. Doe", "John Doe"]
+        );
+        assert_eq!(rows[1].email, "ci@example.com");
+        assert_eq!(rows[1].profile, None);
+    }
+}
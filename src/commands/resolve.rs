@@ -0,0 +1,375 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+
+use crate::commands::use_profile::PROFILE_MARKER_KEY;
+use crate::config::{Config, HostMatch, Profile};
+use crate::credentials::helper;
+use crate::git::url::{parse_remote_url, rewrite_url, RemoteUrl, Transport, UrlRewrite};
+use crate::git::{get_effective_git_config_regexp, get_git_config, get_repo_root, GitConfigScope};
+use crate::ssh::effective::effective_ssh_config;
+use crate::utils::expand_home;
+use crate::utils::output::Paint;
+
+pub fn execute(remote: String) -> Result<()> {
+    let config = Config::load().context("Failed to load configuration.")?;
+    let remote_url = match repo_remote_url(&remote)? {
+        Some(url) => {
+            println!("Remote '{}' of the current repository", remote);
+            url
+        }
+        None => remote,
+    };
+    println!("Resolving {}", remote_url.accent());
+
+    let (fetch_rules, push_rules) = read_rewrite_rules()?;
+    let fetch_url = match rewrite_url(&remote_url, &fetch_rules) {
+        Some((rewritten, rule)) => {
+            println!(
+                "  Rewritten to {} by url.\"{}\".insteadOf = {}",
+                rewritten.success(),
+                rule.base,
+                rule.prefix
+            );
+            rewritten
+        }
+        None => remote_url.clone(),
+    };
+    let push_url = rewrite_url(&remote_url, &push_rules);
+
+    let transport_profile = describe_url(&config, &fetch_url)?;
+    if let Some((push_url, rule)) = push_url.filter(|(url, _)| *url != fetch_url) {
+        println!(
+            "\nPushes go to {} (url.\"{}\".pushInsteadOf = {})",
+            push_url.success(),
+            rule.base,
+            rule.prefix
+        );
+        describe_url(&config, &push_url)?;
+    }
+
+    describe_commit_identity(transport_profile.as_deref())?;
+    Ok(())
+}
+
+/// The stored URL of the current repository's remote called `name`, when `name` isn't a URL
+/// itself. insteadOf rules are applied by the caller, so the rewrite is shown.
+fn repo_remote_url(name: &str) -> Result<Option<String>> {
+    if parse_remote_url(name).is_some() || get_repo_root()?.is_none() {
+        return Ok(None);
+    }
+    get_git_config(&format!("remote.{}.url", name), GitConfigScope::Local)
+}
+
+fn read_rewrite_rules() -> Result<(Vec<UrlRewrite>, Vec<UrlRewrite>)> {
+    let mut fetch_rules = Vec::new();
+    let mut push_rules = Vec::new();
+    for entry in get_effective_git_config_regexp(r"^url\..*\.(push)?insteadof$")? {
+        if let Some(rule) = UrlRewrite::from_config_entry(&entry.key, &entry.value) {
+            if entry.key.to_lowercase().ends_with(".pushinsteadof") {
+                push_rules.push(rule);
+            } else {
+                fetch_rules.push(rule);
+            }
+        }
+    }
+    Ok((fetch_rules, push_rules))
+}
+
+/// Prints how git authenticates to `url`. Returns the name of the profile that matches it.
+fn describe_url(config: &Config, url: &str) -> Result<Option<String>> {
+    let remote = parse_remote_url(url)
+        .ok_or_else(|| anyhow::anyhow!("Unrecognized remote URL '{}'.", url))?;
+
+    match &remote.transport {
+        Transport::Ssh => describe_ssh(config, &remote),
+        Transport::Https => describe_https(config, &remote),
+        Transport::Other(scheme) => {
+            println!(
+                "  Transport: {} (gitp does not manage credentials for it)",
+                scheme.warning()
+            );
+            Ok(None)
+        }
+    }
+}
+
+fn describe_ssh(config: &Config, remote: &RemoteUrl) -> Result<Option<String>> {
+    println!("  Transport: {}", "SSH".accent());
+
+    let ssh = match effective_ssh_config(&remote.host, remote.user.as_deref(), remote.port) {
+        Ok(ssh) => Some(ssh),
+        Err(e) => {
+            println!(
+                "  {}: Could not read the effective SSH config: {:#}",
+                "Warning".warning(),
+                e
+            );
+            None
+        }
+    };
+
+    let hostname = ssh
+        .as_ref()
+        .map(|s| s.hostname.clone())
+        .unwrap_or_else(|| remote.host.clone());
+    if let Some(ssh) = &ssh {
+        if !ssh.hostname.eq_ignore_ascii_case(&remote.host) {
+            println!(
+                "  Host alias: {} -> {}",
+                remote.host.warning(),
+                ssh.hostname.success()
+            );
+        } else {
+            println!("  Host: {}", ssh.hostname.success());
+        }
+        println!("  SSH user: {}, port {}", ssh.user, ssh.port);
+
+        let existing: Vec<&String> = ssh
+            .identity_files
+            .iter()
+            .filter(|f| expand_home(f).exists())
+            .collect();
+        if existing.is_empty() {
+            println!("  SSH keys: none of the configured files exist (ssh-agent keys only)");
+        } else {
+            for key in existing {
+                println!("  SSH key: {}", key.success());
+            }
+        }
+        if !ssh.identities_only {
+            println!(
+                "  {}: IdentitiesOnly is off, so ssh-agent may offer other keys first.",
+                "Note".dimmed()
+            );
+        }
+    }
+
+    // The alias (or the host) remotes use, then the real host ssh connects to, then the key
+    let hosts = config.host_index();
+    let matching = hosts
+        .lookup(&remote.host)
+        .into_iter()
+        .chain(hosts.lookup(&hostname))
+        .find(|m| m.via.is_ssh())
+        .map(|m| m.profile)
+        .or_else(|| {
+            let ssh = ssh.as_ref()?;
+            sorted_profiles(config).into_iter().find(|profile| {
+                profile.ssh_key.as_ref().is_some_and(|key| {
+                    ssh.identity_files
+                        .iter()
+                        .any(|f| expand_home(f) == expand_home(&key.to_string_lossy()))
+                })
+            })
+        });
+    Ok(print_profile_match(matching, "SSH key or host"))
+}
+
+fn describe_https(config: &Config, remote: &RemoteUrl) -> Result<Option<String>> {
+    println!("  Transport: {}", "HTTPS".accent());
+    let host = match remote.port {
+        Some(port) => format!("{}:{}", remote.host, port),
+        None => remote.host.clone(),
+    };
+    println!("  Host: {}", host.success());
+
+    let matching = config
+        .find_profiles_for_host(&host)
+        .into_iter()
+        .filter(|m| m.via == HostMatch::Https)
+        .map(|m| m.profile)
+        .find(|profile| {
+            profile
+                .https_for(&host)
+                .is_some_and(|c| remote.user.as_ref().is_none_or(|u| *u == c.username))
+        });
+    if let Some(creds) = matching.and_then(|p| p.https_for(&host)) {
+        println!("  HTTPS user: {}", creds.username.success());
+    }
+    let matched = print_profile_match(matching, "HTTPS credentials");
+
+    let helpers = helper::helper_order(&host)?;
+    if helpers.is_empty() {
+        println!("  Credential helpers: none (git will prompt)");
+    } else {
+        println!("  Credential helpers, in the order git asks them:");
+        for (i, entry) in helpers.iter().enumerate() {
+            println!(
+                "    {}. {} ({})",
+                i + 1,
+                entry.value.warning(),
+                entry.origin.dimmed()
+            );
+        }
+    }
+    Ok(matched)
+}
+
+fn print_profile_match(profile: Option<&Profile>, what: &str) -> Option<String> {
+    match profile {
+        Some(profile) => {
+            println!("  Profile: {} (by {})", profile.name.success().bold(), what);
+            Some(profile.name.clone())
+        }
+        None => {
+            println!("  Profile: {}", "no gitp profile matches".warning());
+            None
+        }
+    }
+}
+
+/// Prints the identity commits get in the current directory and warns when it belongs to a
+/// different profile than the one authenticating to the remote.
+fn describe_commit_identity(transport_profile: Option<&str>) -> Result<()> {
+    let mut user_name = None;
+    let mut user_email = None;
+    let mut marker = None;
+    for entry in get_effective_git_config_regexp(r"^(user\.name|user\.email|gitp\.profile)$")? {
+        match entry.key.as_str() {
+            "user.name" => user_name = Some(entry),
+            "user.email" => user_email = Some(entry),
+            key if key == PROFILE_MARKER_KEY => marker = Some(entry),
+            _ => {}
+        }
+    }
+
+    println!(
+        "\nCommits made here use: {} <{}>",
+        user_name
+            .as_ref()
+            .map_or("(unset)", |e| e.value.as_str())
+            .success(),
+        user_email
+            .as_ref()
+            .map_or("(unset)", |e| e.value.as_str())
+            .success()
+    );
+    // The marker only describes the identity when it was written alongside it
+    let marker = marker
+        .filter(|m| user_name.as_ref().is_none_or(|n| n.origin == m.origin))
+        .map(|m| m.value);
+    if let Some(marker) = &marker {
+        println!("  Applied profile: {}", marker.success());
+    }
+    if let (Some(marker), Some(transport)) = (&marker, transport_profile) {
+        if marker != transport {
+            println!(
+                "  {}: commits use profile '{}' but the remote authenticates with profile '{}'.",
+                "Warning".warning(),
+                marker,
+                transport
+            );
+        }
+    }
+    Ok(())
+}
+
+fn sorted_profiles(config: &Config) -> Vec<&Profile> {
+    let mut profiles: Vec<&Profile> = config.profiles.values().collect();
+    profiles.sort_by(|a, b| a.name.cmp(&b.name));
+    profiles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{CredentialType, HttpsCredentials};
+    use crate::git::in_repo;
+
+    fn profile(name: &str, https_user: &str) -> Profile {
+        let mut profile = Profile::new(
+            name.to_string(),
+            "John Doe".to_string(),
+            format!("john@{}.example.com", name),
+        );
+        profile.https_credentials.push(HttpsCredentials::new(
+            "github.com".to_string(),
+            https_user.to_string(),
+            CredentialType::Token("t".to_string()),
+        ));
+        profile
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_repo_remote_url() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let repo = git2::Repository::init(dir.path())?;
+        repo.remote("origin", "git@github.com:acme/app.git")?;
+
+        in_repo(dir.path(), || {
+            assert_eq!(
+                repo_remote_url("origin")?.as_deref(),
+                Some("git@github.com:acme/app.git")
+            );
+            assert_eq!(repo_remote_url("upstream")?, None);
+            // URLs are resolved as given
+            assert_eq!(repo_remote_url("https://github.com/acme/app.git")?, None);
+            Ok(())
+        })
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_read_rewrite_rules() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let repo = git2::Repository::init(dir.path())?;
+        let mut local = repo.config()?.open_level(git2::ConfigLevel::Local)?;
+        local.set_str("url.git@github.com-work:.insteadOf", "https://github.com/")?;
+        local.set_str("url.git@github.com:.pushInsteadOf", "https://github.com/")?;
+
+        let (fetch, push) = in_repo(dir.path(), read_rewrite_rules)?;
+        let rule = |rules: &[UrlRewrite], base: &str| {
+            rules
+                .iter()
+                .any(|r| r.base == base && r.prefix == "https://github.com/")
+        };
+        assert!(rule(&fetch, "git@github.com-work:"));
+        assert!(!rule(&fetch, "git@github.com:"));
+        assert!(rule(&push, "git@github.com:"));
+        assert!(!rule(&push, "git@github.com-work:"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_describe_https_matches_user() -> Result<()> {
+        let mut config = Config::default();
+        for profile in [profile("work", "octo-work"), profile("home", "octocat")] {
+            config.profiles.insert(profile.name.clone(), profile);
+        }
+
+        let matched = describe_url(&config, "https://octocat@github.com/acme/app.git")?;
+        assert_eq!(matched.as_deref(), Some("home"));
+        let matched = describe_url(&config, "https://octo-work@github.com/acme/app.git")?;
+        assert_eq!(matched.as_deref(), Some("work"));
+        // A user no profile has credentials for
+        let matched = describe_url(&config, "https://someone@github.com/acme/app.git")?;
+        assert_eq!(matched, None);
+        let matched = describe_url(&config, "https://gitlab.com/acme/app.git")?;
+        assert_eq!(matched, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_describe_url_other_transports() -> Result<()> {
+        let config = Config::default();
+        assert_eq!(describe_url(&config, "git://example.com/app.git")?, None);
+        assert!(describe_url(&config, "not a url").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_sorted_profiles() {
+        let mut config = Config::default();
+        for name in ["work", "client", "home"] {
+            config
+                .profiles
+                .insert(name.to_string(), profile(name, "octocat"));
+        }
+        let names: Vec<&str> = sorted_profiles(&config)
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect();
+        assert_eq!(names, ["client", "home", "work"]);
+    }
+}
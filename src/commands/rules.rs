@@ -0,0 +1,393 @@
+// src/commands/rules.rs
+// Directory rules: `includeIf "gitdir:"` entries in the global git config, each including a
+// gitconfig fragment gitp writes for the profile
+
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::cli::RulesCommands;
+use crate::commands::export::render_gitconfig;
+use crate::commands::use_profile::PROFILE_MARKER_KEY;
+use crate::config::storage::get_config_dir;
+use crate::config::{Config, Profile};
+use crate::git::includes::{add_global_include, global_include_paths, remove_global_include};
+use crate::utils::output::{Paint, Symbol};
+use crate::utils::quote::gitconfig_value;
+use crate::utils::{contract_home, expand_home, is_glob, normalize_path};
+
+/// Directory under the gitp config directory holding the fragments the rules include
+const FRAGMENT_DIR: &str = "gitconfig";
+
+/// A directory whose repositories use a profile.
+#[derive(Debug)]
+pub(crate) struct DirRule<'a> {
+    /// As stored, e.g. `~/work`
+    pub dir: String,
+    pub profile: &'a Profile,
+}
+
+impl DirRule<'_> {
+    /// The includeIf condition, matching every repository below the directory.
+    pub fn condition(&self) -> String {
+        format!("gitdir:{}/", self.dir.trim_end_matches('/'))
+    }
+}
+
+pub fn execute(command: RulesCommands) -> Result<()> {
+    match command {
+        RulesCommands::Add { dir, profile } => add(&dir, &profile),
+        RulesCommands::Remove { dir } => remove(&dir),
+        RulesCommands::List => list(),
+        RulesCommands::Apply => apply(),
+    }
+}
+
+fn add(dir: &str, name: &str) -> Result<()> {
+    let mut config = Config::load().context("Failed to load configuration.")?;
+    if !config.profiles.contains_key(name) {
+        bail!(
+            "Profile '{}' not found. Use 'gitp list' to list available profiles.",
+            name.warning()
+        );
+    }
+    let dir = rule_dir(dir)?;
+
+    for profile in config.profiles.values_mut() {
+        if profile.name == name {
+            continue;
+        }
+        let before = profile.auto_dirs.len();
+        profile.auto_dirs.retain(|d| !same_dir(d, &dir));
+        if profile.auto_dirs.len() != before {
+            println!("  Moved {} from profile '{}'.", dir, profile.name);
+        }
+    }
+    let profile = config.profiles.get_mut(name).expect("checked above");
+    if profile.auto_dirs.iter().any(|d| same_dir(d, &dir)) {
+        println!("Repositories under {} already use profile '{}'.", dir, name);
+    } else {
+        profile.auto_dirs.push(dir.clone());
+        config.save().context("Failed to save configuration.")?;
+        println!(
+            "{} Repositories under {} now use profile '{}'.",
+            Symbol::Ok.as_str().success(),
+            dir.accent(),
+            name.success()
+        );
+    }
+    sync_rules(&config)?;
+
+    if !expand_home(&dir).is_dir() {
+        println!(
+            "  {}: {} doesn't exist yet; the rule applies once it does.",
+            "Note".dimmed(),
+            dir
+        );
+    }
+    Ok(())
+}
+
+fn remove(dir: &str) -> Result<()> {
+    let mut config = Config::load().context("Failed to load configuration.")?;
+    let dir = rule_dir(dir)?;
+    let Some(profile) = config
+        .profiles
+        .values_mut()
+        .find(|p| p.auto_dirs.iter().any(|d| same_dir(d, &dir)))
+    else {
+        bail!(
+            "No rule for {}. '{}' shows the rules.",
+            dir,
+            "gitp rules list".accent()
+        );
+    };
+    profile.auto_dirs.retain(|d| !same_dir(d, &dir));
+    let name = profile.name.clone();
+    config.save().context("Failed to save configuration.")?;
+    sync_rules(&config)?;
+    println!(
+        "{} Repositories under {} no longer switch to profile '{}'.",
+        Symbol::Ok.as_str().success(),
+        dir.accent(),
+        name
+    );
+    Ok(())
+}
+
+fn list() -> Result<()> {
+    let config = Config::load().context("Failed to load configuration.")?;
+    let rules = dir_rules(&config);
+    if rules.is_empty() {
+        println!(
+            "No directory rules. Add one with '{}'.",
+            "gitp rules add ~/work work".accent()
+        );
+        return Ok(());
+    }
+
+    let fragments = fragment_dir()?;
+    let included = global_include_paths()?;
+    let mut in_sync = true;
+    for rule in &rules {
+        let fragment = fragment_path(&fragments, &rule.profile.name);
+        let problem = if !included.iter().any(|(condition, path)| {
+            *condition == rule.condition() && expand_home(path) == fragment
+        }) {
+            Some("not in the global git config")
+        } else if fs::read_to_string(&fragment).ok() != Some(render_fragment(rule.profile)) {
+            Some("fragment out of date")
+        } else {
+            None
+        };
+        match problem {
+            None => println!(
+                "  {} {} {} {}",
+                Symbol::Ok.as_str().success(),
+                rule.dir,
+                "->".dimmed(),
+                rule.profile.name.success()
+            ),
+            Some(problem) => {
+                in_sync = false;
+                println!(
+                    "  {} {} {} {} ({})",
+                    Symbol::Warn.as_str().warning(),
+                    rule.dir,
+                    "->".dimmed(),
+                    rule.profile.name.success(),
+                    problem.warning()
+                );
+            }
+        }
+    }
+    let stray = included
+        .iter()
+        .filter(|(condition, path)| {
+            expand_home(path).starts_with(&fragments)
+                && !rules.iter().any(|rule| {
+                    rule.condition() == *condition
+                        && fragment_path(&fragments, &rule.profile.name) == expand_home(path)
+                })
+        })
+        .count();
+    if stray > 0 {
+        in_sync = false;
+        println!(
+            "  {} {} includeIf entries in the global git config belong to no rule.",
+            Symbol::Warn.as_str().warning(),
+            stray
+        );
+    }
+    if !in_sync {
+        println!(
+            "\nUpdate the global git config with '{}'.",
+            "gitp rules apply".accent()
+        );
+    }
+    Ok(())
+}
+
+fn apply() -> Result<()> {
+    let config = Config::load().context("Failed to load configuration.")?;
+    sync_rules(&config)?;
+    let rules = dir_rules(&config);
+    println!(
+        "{} Wrote {} directory rules to the global git config.",
+        Symbol::Ok.as_str().success(),
+        rules.len()
+    );
+    Ok(())
+}
+
+/// The rules of all profiles, shallower directories first: git reads the includes in order, so
+/// a rule for `~/work/oss` must come after one for `~/work` to win below it.
+pub(crate) fn dir_rules(config: &Config) -> Vec<DirRule<'_>> {
+    let mut rules: Vec<DirRule> = config
+        .profiles
+        .values()
+        .flat_map(|profile| {
+            profile.auto_dirs.iter().map(move |dir| DirRule {
+                dir: dir.trim_end_matches('/').to_string(),
+                profile,
+            })
+        })
+        .collect();
+    rules.sort_by(|a, b| {
+        let depth = |dir: &str| Path::new(dir).components().count();
+        depth(&a.dir)
+            .cmp(&depth(&b.dir))
+            .then_with(|| a.dir.cmp(&b.dir))
+    });
+    rules
+}
+
+/// Writes a fragment for each profile with rules and rewrites the rules' includeIf entries at
+/// the end of the global config, after the `[user]` section they override. Fragments and
+/// entries of rules that are gone are removed.
+pub(crate) fn sync_rules(config: &Config) -> Result<()> {
+    let fragments = fragment_dir()?;
+    let rules = dir_rules(config);
+    let managed: Vec<(String, String)> = global_include_paths()?
+        .into_iter()
+        .filter(|(_, path)| expand_home(path).starts_with(&fragments))
+        .collect();
+    if rules.is_empty() && managed.is_empty() && !fragments.exists() {
+        return Ok(());
+    }
+
+    for (condition, path) in &managed {
+        remove_global_include(condition, path)?;
+    }
+
+    let mut written: Vec<PathBuf> = Vec::new();
+    for rule in &rules {
+        let path = fragment_path(&fragments, &rule.profile.name);
+        if written.contains(&path) {
+            continue;
+        }
+        fs::create_dir_all(&fragments)
+            .with_context(|| format!("Failed to create {}", fragments.display()))?;
+        fs::write(&path, render_fragment(rule.profile))
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        written.push(path);
+    }
+    if let Ok(entries) = fs::read_dir(&fragments) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == "gitconfig") && !written.contains(&path) {
+                fs::remove_file(&path)
+                    .with_context(|| format!("Failed to remove {}", path.display()))?;
+            }
+        }
+    }
+
+    for rule in &rules {
+        let path = fragment_path(&fragments, &rule.profile.name);
+        add_global_include(&rule.condition(), &contract_home(&path).to_string_lossy())?;
+    }
+    Ok(())
+}
+
+/// [`sync_rules`] after a change to the profiles, for commands whose change is already saved:
+/// a failure is a warning, and `gitp rules apply` retries.
+pub(crate) fn refresh_rules(config: &Config) {
+    if let Err(e) = sync_rules(config) {
+        eprintln!(
+            "{}: failed to update the directory rules: {:#}\n  Retry with '{}'.",
+            "Warning".warning(),
+            e,
+            "gitp rules apply".accent()
+        );
+    }
+}
+
+/// The profile whose rules include `path`, when it is one of gitp's fragments.
+pub(crate) fn fragment_profile<'a>(config: &'a Config, path: &Path) -> Option<&'a Profile> {
+    let fragments = fragment_dir().ok()?;
+    config
+        .profiles
+        .values()
+        .find(|p| fragment_path(&fragments, &p.name) == path)
+}
+
+/// Whether `path` is in the directory of gitp's fragments.
+pub(crate) fn is_fragment(path: &Path) -> bool {
+    fragment_dir().is_ok_and(|fragments| path.starts_with(fragments))
+}
+
+/// Where the fragments the rules include are written.
+pub(crate) fn fragment_dir() -> Result<PathBuf> {
+    Ok(get_config_dir()?.join(FRAGMENT_DIR))
+}
+
+/// `<profile>.gitconfig`, with characters that don't belong in a file name replaced.
+fn fragment_path(fragments: &Path, profile: &str) -> PathBuf {
+    let name: String = profile
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || "-_.".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    fragments.join(format!("{}.gitconfig", name))
+}
+
+/// The profile's git config as `gitp export --as gitconfig` renders it, plus the profile marker
+/// so `gitp current --match` names it.
+fn render_fragment(profile: &Profile) -> String {
+    let (section, key) = PROFILE_MARKER_KEY
+        .split_once('.')
+        .expect("marker key has a section");
+    format!(
+        "# Written by 'gitp rules'; edit the profile instead, this file is overwritten.\n{}[{}]\n\t{} = {}\n",
+        render_gitconfig(profile),
+        section,
+        key,
+        gitconfig_value(&profile.name)
+    )
+}
+
+/// The directory as stored in `auto_dirs`: absolute, under `~` when in the home directory, and
+/// without a trailing `/` or `/**`.
+fn rule_dir(dir: &str) -> Result<String> {
+    let dir = dir.trim();
+    let dir = dir.strip_suffix("/**").unwrap_or(dir).trim_end_matches('/');
+    if dir.is_empty() {
+        bail!("The directory cannot be empty.");
+    }
+    if is_glob(dir) {
+        bail!(
+            "'{}' is a pattern; rules take a directory, optionally ending in /**.",
+            dir
+        );
+    }
+    Ok(normalize_path(dir).to_string_lossy().into_owned())
+}
+
+fn same_dir(a: &str, b: &str) -> bool {
+    a.trim_end_matches('/') == b.trim_end_matches('/')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rule_dirs() {
+        assert_eq!(rule_dir("/src/work/**").unwrap(), "/src/work");
+        assert_eq!(rule_dir("/src/work/").unwrap(), "/src/work");
+        assert!(rule_dir("/src/*/work").is_err());
+        assert!(rule_dir("/**").is_err());
+
+        let mut config = Config::default();
+        for (name, dirs) in [("work", vec!["/src/work"]), ("oss", vec!["/src/work/oss/"])] {
+            let mut profile = Profile::new(
+                name.to_string(),
+                "John Doe".to_string(),
+                format!("john@{}.dev", name),
+            );
+            profile.auto_dirs = dirs.into_iter().map(String::from).collect();
+            config.profiles.insert(name.to_string(), profile);
+        }
+        let rules: Vec<(String, &str)> = dir_rules(&config)
+            .iter()
+            .map(|rule| (rule.condition(), rule.profile.name.as_str()))
+            .collect();
+        assert_eq!(
+            rules,
+            [
+                ("gitdir:/src/work/".to_string(), "work"),
+                ("gitdir:/src/work/oss/".to_string(), "oss")
+            ]
+        );
+        assert_eq!(
+            fragment_path(Path::new("/cfg"), "client a/b"),
+            PathBuf::from("/cfg/client_a_b.gitconfig")
+        );
+    }
+}
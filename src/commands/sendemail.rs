@@ -0,0 +1,330 @@
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use crate::cli::SendemailCommands;
+use crate::config::{profile_from_env, Config, SendEmail, SmtpEncryption};
+use crate::credentials::{delete_stored_token, resolve_secret, store_label, store_secure_token};
+use crate::git::get_repo_root;
+use crate::utils::output::{Paint, Symbol};
+
+pub fn execute(command: SendemailCommands) -> Result<()> {
+    match command {
+        SendemailCommands::Set {
+            profile_name,
+            from,
+            confirm,
+            identity,
+            smtp_server,
+            smtp_port,
+            smtp_encryption,
+            smtp_user,
+            smtp_pass,
+            clear,
+        } => {
+            let changes = Changes {
+                from,
+                confirm,
+                identity,
+                smtp_server,
+                smtp_port,
+                smtp_encryption,
+                smtp_user,
+                smtp_pass,
+            };
+            set(profile_name, changes, clear)
+        }
+        SendemailCommands::Test { profile_name } => test(profile_name),
+    }
+}
+
+/// Values given to `sendemail set`; `None` keeps the current one, an empty string removes it.
+struct Changes {
+    from: Option<String>,
+    confirm: Option<String>,
+    identity: Option<String>,
+    smtp_server: Option<String>,
+    smtp_port: Option<String>,
+    smtp_encryption: Option<String>,
+    smtp_user: Option<String>,
+    smtp_pass: Option<String>,
+}
+
+fn set(name: String, changes: Changes, clear: bool) -> Result<()> {
+    let mut config = Config::load().context("Failed to load configuration.")?;
+    let settings = config.settings.clone();
+    let Some(profile) = config.profiles.get_mut(&name) else {
+        bail!("Profile '{}' not found.", name.warning());
+    };
+    let old = profile.sendemail.clone().unwrap_or_default();
+
+    let mut sendemail = if clear {
+        SendEmail::default()
+    } else {
+        let mut sendemail = old.clone();
+        update(&mut sendemail.from, changes.from);
+        update(&mut sendemail.identity, changes.identity);
+        update(&mut sendemail.smtp_server, changes.smtp_server);
+        update(&mut sendemail.smtp_user, changes.smtp_user);
+        if let Some(confirm) = changes.confirm {
+            sendemail.confirm = parse_optional(&confirm)?;
+        }
+        if let Some(encryption) = changes.smtp_encryption {
+            sendemail.smtp_encryption = parse_optional(&encryption)?;
+        }
+        if let Some(port) = changes.smtp_port {
+            sendemail.smtp_server_port =
+                parse_optional(&port).with_context(|| format!("Invalid SMTP port '{}'.", port))?;
+        }
+        sendemail
+    };
+
+    // The password is stored under the server and user, so it can't silently follow them
+    let moved = old.smtp_server != sendemail.smtp_server || old.smtp_user != sendemail.smtp_user;
+    let new_pass = match changes.smtp_pass {
+        Some(pass) => Some(pass),
+        None if clear => Some(String::new()),
+        None if moved && old.smtp_pass.is_some() => {
+            bail!(
+                "The SMTP password is stored for the previous server and user. Pass \
+                 --smtp-pass again, or an empty --smtp-pass to remove it."
+            )
+        }
+        None => None,
+    };
+    if let Some(pass) = new_pass {
+        if let (Some(stored), Some(server)) = (&old.smtp_pass, &old.smtp_server) {
+            delete_stored_token(&settings, &SendEmail::credential_host(server), stored)
+                .context("Failed to remove the previous SMTP password.")?;
+        }
+        sendemail.smtp_pass = if pass.is_empty() {
+            None
+        } else {
+            let (Some(server), Some(user)) = (&sendemail.smtp_server, &sendemail.smtp_user) else {
+                bail!("An SMTP password needs --smtp-server and --smtp-user.");
+            };
+            let stored =
+                store_secure_token(&settings, &SendEmail::credential_host(server), user, &pass)?;
+            println!("SMTP password stored in the {}.", store_label(&stored));
+            Some(stored)
+        };
+    }
+
+    profile.sendemail = (sendemail != SendEmail::default()).then_some(sendemail);
+    profile
+        .validate()
+        .map_err(|e| anyhow::anyhow!("{}. Changes not saved.", e))?;
+    config.save().context("Failed to save configuration.")?;
+    println!(
+        "Updated the sendemail settings of profile '{}'. Run '{}' to apply them.",
+        name.success(),
+        format!("gitp use {}", name).accent()
+    );
+    Ok(())
+}
+
+fn update(field: &mut Option<String>, value: Option<String>) {
+    if let Some(value) = value {
+        *field = (!value.trim().is_empty()).then(|| value.trim().to_string());
+    }
+}
+
+/// `None` for an empty string, else the parsed value.
+fn parse_optional<T>(value: &str) -> Result<Option<T>>
+where
+    T: std::str::FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    if value.trim().is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(value.trim().parse()?))
+}
+
+/// Connects to the profile's SMTP server the way git send-email would and logs in, without
+/// sending mail.
+fn test(profile_name: Option<String>) -> Result<()> {
+    let config = Config::load().context("Failed to load configuration.")?;
+    let name = match profile_name {
+        Some(name) => name,
+        None => {
+            let repo = get_repo_root().ok().flatten();
+            let repo = repo.as_ref().map(|r| r.to_string_lossy());
+            profile_from_env()
+                .or_else(|| {
+                    config
+                        .state
+                        .current_for(repo.as_deref())
+                        .map(str::to_string)
+                })
+                .context("No profile is in use. Pass a profile name.")?
+        }
+    };
+    let Some(profile) = config.profiles.get(&name) else {
+        bail!("Profile '{}' not found.", name.warning());
+    };
+    let Some((sendemail, server)) = profile
+        .sendemail
+        .as_ref()
+        .and_then(|s| Some((s, s.smtp_server.as_deref()?)))
+    else {
+        bail!(
+            "Profile '{}' has no SMTP server. Set one with '{}'.",
+            name,
+            format!("gitp sendemail set {} --smtp-server <host>", name).accent()
+        );
+    };
+
+    let port = sendemail.effective_port();
+    println!("SMTP for profile '{}':", name.accent().bold());
+    println!(
+        "  Server: {}:{} ({})",
+        server,
+        port,
+        match sendemail.smtp_encryption {
+            Some(SmtpEncryption::Tls) => "STARTTLS",
+            Some(SmtpEncryption::Ssl) => "TLS",
+            None => "unencrypted",
+        }
+    );
+    let login = match (&sendemail.smtp_user, &sendemail.smtp_pass) {
+        (Some(user), Some(stored)) => {
+            let pass = resolve_secret(
+                &config.settings,
+                &SendEmail::credential_host(server),
+                stored,
+            )
+            .context("Failed to read the stored SMTP password.")?;
+            println!(
+                "  Login: {} (password from the {})",
+                user,
+                store_label(stored)
+            );
+            Some((user.as_str(), pass))
+        }
+        (Some(user), None) => {
+            println!(
+                "  Login: {} {}",
+                user,
+                "(no stored password; only the connection is checked)".warning()
+            );
+            None
+        }
+        (None, _) => {
+            println!("  Login: none");
+            None
+        }
+    };
+
+    let logs_in = login.is_some();
+    probe_smtp(server, port, sendemail.smtp_encryption, login)?;
+    println!(
+        "{} Connected{}.",
+        Symbol::Ok.as_str().success(),
+        if logs_in { " and logged in" } else { "" }
+    );
+    Ok(())
+}
+
+/// Opens an SMTP session with curl, logs in when `login` is given, and ends it with NOOP.
+fn probe_smtp(
+    server: &str,
+    port: u16,
+    encryption: Option<SmtpEncryption>,
+    login: Option<(&str, String)>,
+) -> Result<()> {
+    let scheme = match encryption {
+        Some(SmtpEncryption::Ssl) => "smtps",
+        _ => "smtp",
+    };
+    let mut command = Command::new("curl");
+    command
+        .args(["--silent", "--show-error", "--connect-timeout", "10"])
+        .args(["--max-time", "30", "--request", "NOOP"])
+        .arg("--url")
+        .arg(format!("{}://{}:{}", scheme, server, port));
+    if encryption == Some(SmtpEncryption::Tls) {
+        command.arg("--ssl-reqd");
+    }
+    // The password goes through stdin, not the command line other users can see
+    command.args(["--config", "-"]);
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to execute 'curl', which gitp uses to talk to SMTP servers.")?;
+    if let (Some(mut stdin), Some((user, pass))) = (child.stdin.take(), login) {
+        let escape = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+        writeln!(stdin, "user = \"{}:{}\"", escape(user), escape(&pass))
+            .context("Failed to pass the SMTP login to curl.")?;
+    }
+    let output = child
+        .wait_with_output()
+        .context("Failed to wait for curl.")?;
+    if output.status.success() {
+        return Ok(());
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    let hint = match output.status.code() {
+        Some(6) => "The server name doesn't resolve.",
+        Some(7) => "Nothing accepts connections on that port.",
+        Some(28) => "The server didn't answer in time; a firewall may block the port.",
+        Some(35) | Some(60) => "The TLS handshake failed; check --smtp-encryption and the port.",
+        Some(64) => "The server doesn't offer STARTTLS; try --smtp-encryption ssl.",
+        Some(67) => "The server refused the login; check the user and password.",
+        _ => "",
+    };
+    bail!("SMTP check failed: {} {}", stderr, hint)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SendEmailConfirm;
+
+    #[test]
+    fn test_update() {
+        let mut field = Some("smtp.example.com".to_string());
+        update(&mut field, None);
+        assert_eq!(field.as_deref(), Some("smtp.example.com"));
+        update(&mut field, Some(" mail.example.com ".to_string()));
+        assert_eq!(field.as_deref(), Some("mail.example.com"));
+        // An empty value removes the setting
+        update(&mut field, Some("  ".to_string()));
+        assert_eq!(field, None);
+    }
+
+    #[test]
+    fn test_parse_optional() -> Result<()> {
+        assert_eq!(parse_optional::<u16>("587")?, Some(587));
+        assert_eq!(parse_optional::<u16>(" ")?, None);
+        assert!(parse_optional::<u16>("smtp").is_err());
+        assert_eq!(
+            parse_optional::<SmtpEncryption>("SSL")?,
+            Some(SmtpEncryption::Ssl)
+        );
+        assert!(parse_optional::<SmtpEncryption>("starttls").is_err());
+        assert_eq!(
+            parse_optional::<SendEmailConfirm>("never")?,
+            Some(SendEmailConfirm::Never)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_probe_smtp_reports_closed_port() -> Result<()> {
+        // Bind and drop a listener to get a port nothing listens on
+        let port = std::net::TcpListener::bind("127.0.0.1:0")?
+            .local_addr()?
+            .port();
+        let err = probe_smtp("127.0.0.1", port, None, None)
+            .unwrap_err()
+            .to_string();
+        assert!(err.starts_with("SMTP check failed:"), "{}", err);
+        assert!(err.contains("Nothing accepts connections"), "{}", err);
+        Ok(())
+    }
+}
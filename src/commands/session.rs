@@ -0,0 +1,159 @@
+use anyhow::{bail, Context, Result};
+
+use crate::cli::Shell;
+use crate::config::{Config, Profile, PROFILE_ENV_VAR};
+use crate::ssh::ssh_command;
+use crate::utils::output::Paint;
+use crate::utils::quote::{fish_quote, posix_quote};
+
+/// Prints the exports that switch the calling shell to profile `name`. The `gitp` function
+/// from `gitp shell-init` evals them; messages go to stderr to keep stdout evaluable.
+pub fn execute_use(name: String, shell: Shell) -> Result<()> {
+    let config = Config::load_cached().context("Failed to load configuration.")?;
+    let profile = match config.profiles.get(&name) {
+        Some(profile) => profile,
+        None => bail!(
+            "Profile '{}' not found. Use '{}' to list available profiles.",
+            name.warning(),
+            "gitp list".accent()
+        ),
+    };
+
+    print!("{}", render_exports(&session_env(profile), shell));
+    eprintln!(
+        "Using profile '{}' in this shell session only.",
+        name.success()
+    );
+    Ok(())
+}
+
+/// Prints the `gitp` shell function that makes `gitp use --session` take effect.
+pub fn execute_init(shell: Shell) -> Result<()> {
+    print!("{}", wrapper(shell));
+    Ok(())
+}
+
+/// Environment for `profile`: author/committer identity, the SSH key, the remaining profile
+/// settings through `GIT_CONFIG_*`, and `GITP_PROFILE` for gitp's own commands. Unset
+/// (`None`) variables are cleared so nothing leaks from the previously used profile.
+fn session_env(profile: &Profile) -> Vec<(String, Option<String>)> {
+    let git_config = &profile.git_config;
+    let mut config_entries: Vec<(&str, &str)> = Vec::new();
+    if let Some(key) = git_config
+        .user_signingkey
+        .as_deref()
+        .or(profile.gpg_key.as_deref())
+    {
+        config_entries.push(("user.signingkey", key));
+    }
+    if let Some(autocrlf) = git_config.core_autocrlf {
+        config_entries.push(("core.autocrlf", autocrlf.as_str()));
+    }
+    if let Some(eol) = git_config.core_eol {
+        config_entries.push(("core.eol", eol.as_str()));
+    }
+
+    let ssh_command = profile.ssh_key.as_deref().map(ssh_command);
+
+    let mut env = vec![
+        ("GIT_AUTHOR_NAME", Some(git_config.user_name.clone())),
+        ("GIT_AUTHOR_EMAIL", Some(git_config.user_email.clone())),
+        ("GIT_COMMITTER_NAME", Some(git_config.user_name.clone())),
+        ("GIT_COMMITTER_EMAIL", Some(git_config.user_email.clone())),
+        ("GIT_SSH_COMMAND", ssh_command),
+        (
+            "GIT_CONFIG_COUNT",
+            (!config_entries.is_empty()).then(|| config_entries.len().to_string()),
+        ),
+        (PROFILE_ENV_VAR, Some(profile.name.clone())),
+    ]
+    .into_iter()
+    .map(|(name, value)| (name.to_string(), value))
+    .collect::<Vec<_>>();
+    for (i, (key, value)) in config_entries.into_iter().enumerate() {
+        env.push((format!("GIT_CONFIG_KEY_{}", i), Some(key.to_string())));
+        env.push((format!("GIT_CONFIG_VALUE_{}", i), Some(value.to_string())));
+    }
+    env
+}
+
+fn render_exports(env: &[(String, Option<String>)], shell: Shell) -> String {
+    let mut out = String::new();
+    for (name, value) in env {
+        let line = match (shell, value) {
+            (Shell::Fish, Some(value)) => format!("set -gx {} {}\n", name, fish_quote(value)),
+            (Shell::Fish, None) => format!("set -e {}\n", name),
+            (_, Some(value)) => format!("export {}={}\n", name, posix_quote(value)),
+            (_, None) => format!("unset {}\n", name),
+        };
+        out.push_str(&line);
+    }
+    out
+}
+
+fn wrapper(shell: Shell) -> &'static str {
+    match shell {
+        Shell::Bash | Shell::Zsh => {
+            r#"# gitp: lets `gitp use --session` switch the current shell
+gitp() {
+    if [ "$1" = "use" ]; then
+        case " $* " in
+            *" --session "*)
+                local exports
+                exports="$(command gitp "$@" --shell bash)" || return
+                eval "$exports"
+                return
+                ;;
+        esac
+    fi
+    command gitp "$@"
+}
+"#
+        }
+        Shell::Fish => {
+            r#"# gitp: lets `gitp use --session` switch the current shell
+function gitp
+    if test "$argv[1]" = use; and contains -- --session $argv
+        set -l exports (command gitp $argv --shell fish); or return
+        string join \n $exports | source
+    else
+        command gitp $argv
+    end
+end
+"#
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AutoCrlf;
+
+    #[test]
+    fn test_session_exports() {
+        let mut profile = Profile::new(
+            "work".to_string(),
+            "John O'Doe".to_string(),
+            "john@company.com".to_string(),
+        );
+        profile.git_config.core_autocrlf = Some(AutoCrlf::Input);
+        let env = session_env(&profile);
+
+        assert_eq!(
+            render_exports(&env, Shell::Bash),
+            "export GIT_AUTHOR_NAME='John O'\\''Doe'\n\
+             export GIT_AUTHOR_EMAIL='john@company.com'\n\
+             export GIT_COMMITTER_NAME='John O'\\''Doe'\n\
+             export GIT_COMMITTER_EMAIL='john@company.com'\n\
+             unset GIT_SSH_COMMAND\n\
+             export GIT_CONFIG_COUNT='1'\n\
+             export GITP_PROFILE='work'\n\
+             export GIT_CONFIG_KEY_0='core.autocrlf'\n\
+             export GIT_CONFIG_VALUE_0='input'\n"
+        );
+        let fish = render_exports(&env, Shell::Fish);
+        assert!(fish.starts_with("set -gx GIT_AUTHOR_NAME 'John O\\'Doe'\n"));
+        assert!(fish.contains("set -e GIT_SSH_COMMAND\n"));
+    }
+}
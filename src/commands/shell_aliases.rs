@@ -0,0 +1,142 @@
+// src/commands/shell_aliases.rs
+// One-word shell aliases that switch to each profile
+
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use std::collections::BTreeMap;
+
+use crate::cli::Shell;
+use crate::config::{Config, Settings};
+use crate::utils::output::Paint;
+use crate::utils::quote::{fish_quote, posix_quote, shell_word};
+
+pub fn execute(shell: Shell) -> Result<()> {
+    let config = Config::load().context("Failed to load configuration.")?;
+    let names: Vec<&str> = config.profiles.keys().map(String::as_str).collect();
+    let aliases = profile_aliases(&config.settings, &names, shell)?;
+    if aliases.is_empty() {
+        eprintln!("No profiles yet; create one with 'gitp new <name>'.");
+        return Ok(());
+    }
+    println!("# gitp: one alias per profile. Regenerate after adding or renaming profiles.");
+    for (name, command) in &aliases {
+        match shell {
+            Shell::Fish => println!("alias {} {}", name, fish_quote(command)),
+            Shell::Bash | Shell::Zsh => println!("alias {}={}", name, posix_quote(command)),
+        }
+    }
+    Ok(())
+}
+
+/// `(alias, command)` for each profile, from the `shell_alias_name` and `shell_alias_command`
+/// templates. Profiles whose alias wouldn't be a valid shell word are skipped with a warning.
+fn profile_aliases(
+    settings: &Settings,
+    names: &[&str],
+    shell: Shell,
+) -> Result<Vec<(String, String)>> {
+    // Alias -> profile, to report two profiles getting the same alias
+    let mut aliases: BTreeMap<String, (&str, String)> = BTreeMap::new();
+    for &name in names {
+        let alias = settings
+            .shell_alias_name
+            .replace("{prefix}", unique_prefix(name, names))
+            .replace("{profile}", name);
+        if !is_alias_name(&alias) {
+            eprintln!(
+                "{} Skipping profile '{}': '{}' isn't a valid alias name.",
+                "Warning:".warning().bold(),
+                name,
+                alias
+            );
+            continue;
+        }
+        if let Some((other, _)) = aliases.get(&alias) {
+            bail!(
+                "shell_alias_name = '{}' gives profiles '{}' and '{}' the same alias '{}'. Use \
+                 {{prefix}} or {{profile}} in it.",
+                settings.shell_alias_name,
+                other,
+                name,
+                alias
+            );
+        }
+        let command = settings
+            .shell_alias_command
+            .replace("{profile}", &profile_word(name, shell));
+        aliases.insert(alias, (name, command));
+    }
+    Ok(aliases
+        .into_iter()
+        .map(|(alias, (_, command))| (alias, command))
+        .collect())
+}
+
+/// The shortest start of `name` that isn't the start of another name, or the whole name when
+/// it begins another one (`work` next to `work-old`).
+fn unique_prefix<'a>(name: &'a str, names: &[&str]) -> &'a str {
+    name.char_indices()
+        .map(|(i, c)| &name[..i + c.len_utf8()])
+        .find(|prefix| {
+            names
+                .iter()
+                .all(|other| *other == name || !other.starts_with(prefix))
+        })
+        .unwrap_or(name)
+}
+
+fn is_alias_name(alias: &str) -> bool {
+    !alias.is_empty()
+        && !alias.starts_with('-')
+        && alias
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-.".contains(c))
+}
+
+/// `name` as one word for `shell`, quoted only when it needs to be.
+fn profile_word(name: &str, shell: Shell) -> String {
+    match shell {
+        Shell::Fish if shell_word(name) != name => fish_quote(name),
+        _ => shell_word(name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_aliases() {
+        let settings = Settings::default();
+        let names = ["personal", "web", "work", "work-old", "client a"];
+        let aliases = profile_aliases(&settings, &names, Shell::Bash).unwrap();
+        assert_eq!(
+            aliases,
+            [
+                ("gc".to_string(), "gitp use 'client a' --local".to_string()),
+                ("gp".to_string(), "gitp use personal --local".to_string()),
+                ("gwe".to_string(), "gitp use web --local".to_string()),
+                ("gwork".to_string(), "gitp use work --local".to_string()),
+                (
+                    "gwork-".to_string(),
+                    "gitp use work-old --local".to_string()
+                ),
+            ]
+        );
+
+        let settings = Settings {
+            shell_alias_name: "use-{profile}".to_string(),
+            ..Settings::default()
+        };
+        // "use-client a" has a space
+        let aliases = profile_aliases(&settings, &names, Shell::Fish).unwrap();
+        assert_eq!(aliases.len(), 4);
+        assert_eq!(aliases[0].0, "use-personal");
+
+        let settings = Settings {
+            shell_alias_name: "gg".to_string(),
+            ..Settings::default()
+        };
+        assert!(profile_aliases(&settings, &names, Shell::Zsh).is_err());
+    }
+}
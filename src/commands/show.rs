@@ -1,22 +1,59 @@
 use anyhow::{bail, Context, Result};
 use colored::Colorize;
 
-use crate::commands::list::print_profile_detailed;
-use crate::config::Config; // Import the shared function
+use crate::cli::OutputFormat;
+use crate::commands::list::{print_profile_detailed, ProfilesJson};
+use crate::config::{profile_from_env, Config}; // Import the shared function
+use crate::git::get_repo_root;
+use crate::utils::output::{print_json, Paint};
 
-pub fn execute(name: String) -> Result<()> {
+/// Shows the profiles selected by `names` (profile names or globs such as `work-*`), or the
+/// profile in use when none are given.
+pub fn execute(names: Vec<String>, format: OutputFormat) -> Result<()> {
     let config = Config::load().context("Failed to load configuration.")?;
 
-    if let Some(profile_details) = config.profiles.get(&name) {
-        println!("Details for profile: {}", name.cyan().bold());
-        // Pass config.current_profile.as_deref() to correctly show if it's the current one
-        print_profile_detailed(&name, profile_details, config.current_profile.as_deref());
+    // Pass the profile in effect here to correctly show if it's the current one
+    let repo = get_repo_root().ok().flatten();
+    let repo = repo.as_ref().map(|r| r.to_string_lossy());
+    let current = config.state.current_for(repo.as_deref());
+
+    let selected = if names.is_empty() {
+        match profile_from_env().or_else(|| current.map(str::to_string)) {
+            Some(name) if config.profiles.contains_key(&name) => vec![name],
+            Some(name) => bail!(
+                "The profile in use, '{}', no longer exists. Use '{}' to list available profiles.",
+                name.warning(),
+                "gitp list".accent()
+            ),
+            None => bail!(
+                "No profile is in use. Pass a profile name or run '{}'.",
+                "gitp use <name>".accent()
+            ),
+        }
     } else {
-        bail!(
-            "Profile '{}' not found. Use '{}' to list available profiles.",
-            name.yellow(),
-            "gitp list".cyan()
+        config.select_profiles(&names)?
+    };
+
+    if format == OutputFormat::Json {
+        let selected: Vec<&String> = selected.iter().collect();
+        return print_json(&ProfilesJson::new(&config, &selected, current));
+    }
+
+    for (i, name) in selected.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!("Details for profile: {}", name.accent().bold());
+        print_profile_detailed(
+            &config.settings,
+            name,
+            &config.profiles[name],
+            current,
+            config.state.last_used.get(name),
         );
+        if let Some(path) = config.system_profile_path(name) {
+            println!("  {} {}", "System Profile:".accent(), path.display());
+        }
     }
 
     Ok(())
@@ -0,0 +1,215 @@
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use colored::Colorize;
+
+use crate::commands::new::identity_suggestions;
+use crate::commands::use_profile::{apply_git_identity, sync_ssh_config};
+use crate::config::{Config, Profile};
+use crate::git::{get_git_config, GitConfigScope};
+use crate::utils::normalize_path;
+use crate::utils::output::{theme, Paint};
+use crate::utils::prompt::{prompt_suggested, Input, Select};
+use crate::utils::terminal::require_terminal;
+
+const DEFAULT_SSH_HOST: &str = "github.com";
+
+/// Turns the global git identity into a "personal" profile, asks for a second (work) identity,
+/// gives the work SSH key a host alias when both keys are for the same host, and applies the
+/// profile chosen as the default globally.
+pub fn execute() -> Result<()> {
+    require_terminal(
+        "Splitting the global identity",
+        "Create the profiles with 'gitp new' instead.",
+    )?;
+    let mut config = Config::load().context("Failed to load configuration.")?;
+
+    let global_name = get_git_config("user.name", GitConfigScope::Global)?;
+    let global_email = get_git_config("user.email", GitConfigScope::Global)?;
+    let (global_name, global_email) = match (global_name, global_email) {
+        (Some(name), Some(email)) if !name.trim().is_empty() && !email.trim().is_empty() => {
+            (name, email)
+        }
+        _ => bail!(
+            "The global git identity is incomplete (user.name and user.email must both be set). Use '{}' to create profiles instead.",
+            "gitp new".accent()
+        ),
+    };
+    println!(
+        "Current global identity: {} <{}>",
+        global_name.success(),
+        global_email.success()
+    );
+
+    println!("\n{}", "Personal profile (from the global identity)".bold());
+    let personal_name = prompt_profile_name(&config, "personal", None)?;
+    let mut personal = Profile::new(personal_name.clone(), global_name.clone(), global_email);
+    personal.git_config.user_signingkey =
+        get_git_config("user.signingkey", GitConfigScope::Global)?.filter(|k| !k.trim().is_empty());
+    prompt_ssh_key(&mut personal)?;
+
+    println!("\n{}", "Work profile".bold());
+    let work_name = prompt_profile_name(&config, "work", Some(&personal_name))?;
+    let (names, emails) = identity_suggestions(&config, None);
+    let work_user_name =
+        prompt_suggested("Git user name", Some(&global_name), "User name", &names)?;
+    let work_email = prompt_suggested("Git user email", None, "User email", &emails)?;
+    let mut work = Profile::new(work_name.clone(), work_user_name, work_email);
+    let work_signing_key: String = Input::with_theme(&theme())
+        .with_prompt("Git signing key (optional, press Enter to skip)")
+        .allow_empty(true)
+        .interact_text()
+        .context("Failed to get signing key input.")?;
+    if !work_signing_key.trim().is_empty() {
+        work.git_config.user_signingkey = Some(work_signing_key.trim().to_string());
+    }
+    prompt_ssh_key(&mut work)?;
+
+    if assign_host_alias(&personal, &mut work) {
+        let alias = work.ssh_host_alias.as_deref().unwrap_or_default();
+        println!(
+            "\nBoth SSH keys are for {}, so the work key gets the host alias {}.",
+            work.ssh_key_host.as_deref().unwrap_or_default().accent(),
+            alias.success()
+        );
+        println!(
+            "Clone work repositories as {} to use it.",
+            format!("git@{}:org/repo.git", alias).warning()
+        );
+    }
+
+    for profile in [&personal, &work] {
+        profile.validate().map_err(|e| {
+            anyhow::anyhow!("Profile '{}' is invalid: {}", profile.name.warning(), e)
+        })?;
+    }
+
+    let choices = [personal_name.as_str(), work_name.as_str()];
+    let selection = Select::with_theme(&theme())
+        .with_prompt("Which profile should be the global default?")
+        .items(&choices)
+        .default(0)
+        .interact()
+        .context("Failed to get default profile selection.")?;
+    let default_profile = choices[selection].to_string();
+
+    config.profiles.insert(personal_name.clone(), personal);
+    config.profiles.insert(work_name.clone(), work);
+
+    println!(
+        "\nApplying profile '{}' to global Git configuration...",
+        default_profile.accent()
+    );
+    apply_git_identity(&mut config, &default_profile, GitConfigScope::Global)?;
+    config
+        .state
+        .record_switch(&default_profile, None, Utc::now());
+    sync_ssh_config(&config)?;
+    config
+        .save()
+        .context("Failed to save gitp configuration.")?;
+
+    println!(
+        "\nCreated profiles '{}' and '{}'; '{}' is the global default.",
+        personal_name.success(),
+        work_name.success(),
+        default_profile.success().bold()
+    );
+    println!(
+        "Use {} inside a repository to switch it to the other profile.",
+        "gitp use <profile> --local".warning()
+    );
+    Ok(())
+}
+
+fn prompt_profile_name(config: &Config, default: &str, taken: Option<&str>) -> Result<String> {
+    let name: String = Input::with_theme(&theme())
+        .with_prompt("Profile name")
+        .default(default.to_string())
+        .validate_with(|input: &String| -> Result<(), String> {
+            let input = input.trim();
+            if input.is_empty() {
+                Err("Profile name cannot be empty".to_string())
+            } else if config.profiles.contains_key(input) || taken == Some(input) {
+                Err(format!("Profile '{}' already exists", input))
+            } else {
+                Ok(())
+            }
+        })
+        .interact_text()
+        .context("Failed to get profile name input.")?;
+    Ok(name.trim().to_string())
+}
+
+fn prompt_ssh_key(profile: &mut Profile) -> Result<()> {
+    let key_path: String = Input::with_theme(&theme())
+        .with_prompt("Path to SSH key (optional, press Enter to skip)")
+        .allow_empty(true)
+        .interact_text()
+        .context("Failed to get SSH key path input.")?;
+    if key_path.trim().is_empty() {
+        return Ok(());
+    }
+    let host: String = Input::with_theme(&theme())
+        .with_prompt("SSH key host")
+        .default(DEFAULT_SSH_HOST.to_string())
+        .interact_text()
+        .context("Failed to get SSH key host input.")?;
+    profile.ssh_key = Some(normalize_path(&key_path));
+    profile.ssh_key_host = Some(host.trim().to_string());
+    Ok(())
+}
+
+/// Gives `work`'s SSH key a `<host>-<profile>` alias when both profiles have keys for the same
+/// host, since SSH would otherwise always offer the first one. Returns whether it did.
+fn assign_host_alias(personal: &Profile, work: &mut Profile) -> bool {
+    let same_host = match (
+        &personal.ssh_key,
+        &personal.ssh_key_host,
+        &work.ssh_key,
+        &work.ssh_key_host,
+    ) {
+        (Some(_), Some(a), Some(_), Some(b)) => a.eq_ignore_ascii_case(b),
+        _ => false,
+    };
+    if same_host {
+        work.ssh_host_alias = work
+            .ssh_key_host
+            .as_ref()
+            .map(|host| format!("{}-{}", host, work.name));
+    }
+    same_host
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(name: &str, host: Option<&str>) -> Profile {
+        let mut profile = Profile::new(
+            name.to_string(),
+            "John Doe".to_string(),
+            format!("john@{}.com", name),
+        );
+        if let Some(host) = host {
+            profile.ssh_key = Some(format!("~/.ssh/id_{}", name).into());
+            profile.ssh_key_host = Some(host.to_string());
+        }
+        profile
+    }
+
+    #[test]
+    fn test_assign_host_alias() {
+        let personal = profile("personal", Some("github.com"));
+        let mut work = profile("work", Some("GitHub.com"));
+        assert!(assign_host_alias(&personal, &mut work));
+        assert_eq!(work.ssh_host(), Some("GitHub.com-work"));
+
+        let mut work = profile("work", Some("gitlab.acme.com"));
+        assert!(!assign_host_alias(&personal, &mut work));
+        assert_eq!(work.ssh_host(), Some("gitlab.acme.com"));
+
+        let mut work = profile("work", Some("github.com"));
+        assert!(!assign_host_alias(&profile("personal", None), &mut work));
+        assert!(work.ssh_host_alias.is_none());
+    }
+}
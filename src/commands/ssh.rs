@@ -0,0 +1,333 @@
+// src/commands/ssh.rs
+// SSH connection sharing (ControlMaster) per profile
+
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::cli::SshCommands;
+use crate::commands::use_profile::uses_keychain;
+use crate::config::{is_host_pattern, Config, ControlMaster, SshControl};
+use crate::ssh::agent::{add_to_agent, agent_requirement, AgentRequirement};
+use crate::ssh::effective::{effective_ssh_config, effective_ssh_config_in};
+use crate::ssh::keys::inspect_key;
+use crate::utils::output::{Paint, Symbol};
+use crate::utils::{expand_home, non_empty};
+
+pub fn execute(command: SshCommands) -> Result<()> {
+    match command {
+        SshCommands::Control {
+            profile_name,
+            master,
+            path,
+            persist,
+            clear,
+        } => set_control(profile_name, master, path, persist, clear),
+        SshCommands::ResetControl { profile_name } => reset_control(profile_name),
+        SshCommands::Agent {
+            profile_name,
+            add_keys_to_agent,
+            use_keychain,
+            load,
+        } => agent(profile_name, add_keys_to_agent, use_keychain, load),
+    }
+}
+
+fn set_control(
+    name: String,
+    master: Option<String>,
+    path: Option<String>,
+    persist: Option<String>,
+    clear: bool,
+) -> Result<()> {
+    let mut config = Config::load().context("Failed to load configuration.")?;
+    let Some(profile) = config.profiles.get_mut(&name) else {
+        bail!("Profile '{}' not found.", name.warning());
+    };
+    profile.ssh_control = if clear {
+        None
+    } else {
+        updated_control(profile.ssh_control.clone(), master, path, persist)?
+    };
+    profile
+        .validate()
+        .map_err(|e| anyhow::anyhow!("{}. Changes not saved.", e))?;
+    let managed = config.manages_ssh(&config.profiles[&name]);
+    config.save().context("Failed to save configuration.")?;
+    println!(
+        "Updated the SSH connection sharing of profile '{}'. Run '{}' to write it to ~/.ssh/config.",
+        name.success(),
+        format!("gitp use {}", name).accent()
+    );
+    if !managed {
+        println!(
+            "  {}: gitp doesn't manage the profile's SSH entry, so it won't write these settings.",
+            "Warning".warning()
+        );
+    }
+    Ok(())
+}
+
+/// `control` with the given values applied; `None` keeps a value, an empty string removes it.
+/// `None` when nothing is left.
+fn updated_control(
+    control: Option<SshControl>,
+    master: Option<String>,
+    path: Option<String>,
+    persist: Option<String>,
+) -> Result<Option<SshControl>> {
+    let mut control = control.unwrap_or_default();
+    if let Some(master) = master {
+        control.master = if master.trim().is_empty() {
+            None
+        } else {
+            Some(master.parse::<ControlMaster>()?)
+        };
+    }
+    for (field, value) in [(&mut control.path, path), (&mut control.persist, persist)] {
+        if let Some(value) = value {
+            *field = (!value.trim().is_empty()).then(|| value.trim().to_string());
+        }
+    }
+    Ok((control != SshControl::default()).then_some(control))
+}
+
+/// Sets the profile's AddKeysToAgent and UseKeychain and loads its key as asked, then says
+/// what the key needs from ssh-agent.
+fn agent(
+    name: String,
+    add_keys_to_agent: Option<String>,
+    use_keychain: Option<bool>,
+    load: bool,
+) -> Result<()> {
+    let mut config = Config::load().context("Failed to load configuration.")?;
+    let Some(profile) = config.profiles.get_mut(&name) else {
+        bail!("Profile '{}' not found.", name.warning());
+    };
+    if add_keys_to_agent.is_none() && use_keychain.is_none() {
+        return show_agent(&config, &name, load);
+    }
+    if let Some(value) = add_keys_to_agent {
+        let value = value.trim();
+        if !value.is_empty() && !is_add_keys_to_agent(value) {
+            bail!(
+                "'{}' isn't an AddKeysToAgent value: use yes, confirm, ask, no or a lifetime such as 1h.",
+                value
+            );
+        }
+        profile.ssh_add_keys_to_agent = non_empty(value);
+    }
+    if let Some(use_keychain) = use_keychain {
+        profile.ssh_use_keychain = use_keychain;
+        // The keychain only hands the passphrase to ssh; the agent keeps the unlocked key
+        if use_keychain && profile.ssh_add_keys_to_agent.is_none() {
+            profile.ssh_add_keys_to_agent = Some("yes".to_string());
+        }
+    }
+    let managed = config.manages_ssh(&config.profiles[&name]);
+    config.save().context("Failed to save configuration.")?;
+    println!(
+        "Updated the ssh-agent settings of profile '{}'. Run '{}' to write them to ~/.ssh/config.",
+        name.success(),
+        format!("gitp use {}", name).accent()
+    );
+    if !managed {
+        println!(
+            "  {}: gitp doesn't manage the profile's SSH entry, so it won't write these settings.",
+            "Warning".warning()
+        );
+    }
+    if use_keychain == Some(true) && !cfg!(target_os = "macos") {
+        println!(
+            "  {}: UseKeychain is only written on macOS; ssh elsewhere doesn't know it.",
+            "Note".dimmed()
+        );
+    }
+    show_agent(&config, &name, load)
+}
+
+/// Loads the profile's key when asked, then prints its passphrase and agent state.
+fn show_agent(config: &Config, name: &str, load: bool) -> Result<()> {
+    let profile = &config.profiles[name];
+    let Some(key) = &profile.ssh_key else {
+        bail!("Profile '{}' has no SSH key.", name.warning());
+    };
+    let path = expand_home(&key.to_string_lossy());
+    if load {
+        add_to_agent(&path, uses_keychain(profile))?;
+    }
+    let passphrase = match inspect_key(&path).ok().and_then(|info| info.encrypted) {
+        Some(true) => "passphrase-protected".warning(),
+        Some(false) => "no passphrase".success(),
+        None => "can't tell whether it has a passphrase".dimmed(),
+    };
+    println!("Key: {} ({})", key.display(), passphrase);
+    let agent = match agent_requirement(&path)? {
+        AgentRequirement::Ready => "ready; git can use the key without a terminal".success(),
+        AgentRequirement::NotLoaded => format!(
+            "running without the key; fetches without a terminal fail until 'gitp ssh agent {} --load'",
+            name
+        )
+        .warning(),
+        AgentRequirement::NoAgent => {
+            "not running; fetches without a terminal fail (start one with 'eval \"$(ssh-agent -s)\"')"
+                .warning()
+        }
+    };
+    println!("ssh-agent: {}", agent);
+    println!(
+        "AddKeysToAgent: {}",
+        profile
+            .ssh_add_keys_to_agent
+            .as_deref()
+            .unwrap_or("not set")
+            .accent()
+    );
+    if cfg!(target_os = "macos") {
+        println!(
+            "UseKeychain: {}",
+            if profile.ssh_use_keychain {
+                "yes"
+            } else {
+                "not set"
+            }
+            .accent()
+        );
+    }
+    Ok(())
+}
+
+/// Whether `value` is one ssh accepts for AddKeysToAgent: yes, confirm, ask, no, or how long
+/// the agent keeps the key (`1h`, `30m`, `1h30m`, seconds without a unit).
+fn is_add_keys_to_agent(value: &str) -> bool {
+    if matches!(value, "yes" | "confirm" | "ask" | "no") {
+        return true;
+    }
+    let mut digits = false;
+    for c in value.chars() {
+        match c {
+            '0'..='9' => digits = true,
+            's' | 'S' | 'm' | 'M' | 'h' | 'H' | 'd' | 'D' | 'w' | 'W' if digits => digits = false,
+            _ => return false,
+        }
+    }
+    !value.is_empty() && value.starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// Closes the master connection ssh would share for the profile's host, or removes its socket
+/// when the master is gone.
+fn reset_control(name: String) -> Result<()> {
+    let config = Config::load().context("Failed to load configuration.")?;
+    let Some(profile) = config.profiles.get(&name) else {
+        bail!("Profile '{}' not found.", name.warning());
+    };
+    let Some(host) = profile.ssh_host() else {
+        bail!("Profile '{}' has no SSH key host.", name.warning());
+    };
+    if is_host_pattern(host) {
+        bail!(
+            "Profile '{}' covers {}, with a connection per host. Close one with '{}'.",
+            name,
+            host,
+            "ssh -O exit <host>".accent()
+        );
+    }
+
+    // The profile's `Match ... exec` entry only applies below its directories
+    let match_dir = profile
+        .ssh_match_dirs
+        .iter()
+        .map(|dir| expand_home(dir.trim()))
+        .find(|dir| dir.is_dir());
+    let ssh = match &match_dir {
+        Some(dir) => effective_ssh_config_in(host, dir)?,
+        None => effective_ssh_config(host, None, None)?,
+    };
+    let Some(control_path) = ssh.control_path else {
+        println!(
+            "ssh doesn't share connections to {} (no ControlPath).",
+            host
+        );
+        return Ok(());
+    };
+    if !Path::new(&control_path).exists() {
+        println!(
+            "{} No shared connection to {} is open for profile '{}'.",
+            Symbol::Ok.as_str().success(),
+            host,
+            name
+        );
+        return Ok(());
+    }
+
+    let exited = Command::new("ssh")
+        .args(["-O", "exit", "-S", &control_path, host])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .context("Failed to execute 'ssh -O exit'. Is OpenSSH installed?")?
+        .success();
+    if exited {
+        println!(
+            "{} Closed the shared connection to {} ({}).",
+            Symbol::Ok.as_str().success(),
+            host,
+            control_path
+        );
+    } else {
+        // No master answers on the socket: a leftover from a connection that died
+        std::fs::remove_file(&control_path)
+            .with_context(|| format!("Failed to remove the control socket {}", control_path))?;
+        println!(
+            "{} Removed the stale control socket {}.",
+            Symbol::Ok.as_str().success(),
+            control_path
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_updated_control() {
+        let control = updated_control(
+            None,
+            Some("auto".to_string()),
+            None,
+            Some("10m".to_string()),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(control.master, Some(ControlMaster::Auto));
+        assert_eq!(
+            control.for_profile("client a").path.as_deref(),
+            Some("~/.ssh/gitp-client_a-%C")
+        );
+
+        let kept = updated_control(Some(control.clone()), None, Some(String::new()), None).unwrap();
+        assert_eq!(kept, Some(control.clone()));
+        let removed = updated_control(
+            Some(control),
+            Some(String::new()),
+            None,
+            Some(" ".to_string()),
+        );
+        assert_eq!(removed.unwrap(), None);
+        assert!(updated_control(None, Some("always".to_string()), None, None).is_err());
+    }
+
+    #[test]
+    fn test_is_add_keys_to_agent() {
+        for value in ["yes", "confirm", "3600", "1h", "1h30m"] {
+            assert!(is_add_keys_to_agent(value), "{}", value);
+        }
+        for value in ["", "always", "h", "1hh", "1.5h"] {
+            assert!(!is_add_keys_to_agent(value), "{}", value);
+        }
+    }
+}
@@ -1,49 +1,95 @@
 use anyhow::{bail, Context, Result};
 use colored::Colorize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 
-use crate::cli::SshKeyCommands;
-use crate::config::Config;
+use crate::cli::{KeyAlgorithm, SshKeyCommands};
+use crate::commands::use_profile::{ssh_entries, ssh_entry};
+use crate::config::{Config, Profile};
+use crate::ssh::agent::key_fingerprint;
+use crate::ssh::keys::{inspect_key, public_key_path, scan_keys};
+use crate::ssh::ssh_config::{
+    entry_status, get_ssh_config_path, read_ssh_config, render_entry, EntryStatus, SshEntry,
+};
+use crate::utils::output::Paint;
+use crate::utils::{contract_home, expand_home, non_empty, normalize_path};
 
 pub fn execute(command: SshKeyCommands) -> Result<()> {
     match command {
-        SshKeyCommands::Set { profile_name, key_path } => {
-            set_ssh_key(profile_name, key_path)
-        }
-        SshKeyCommands::Remove { profile_name } => {
-            remove_ssh_key(profile_name)
-        }
-        SshKeyCommands::Show { profile_name } => {
-            show_ssh_key(profile_name)
-        }
+        SshKeyCommands::Set {
+            profile_name,
+            key_path,
+            host,
+        } => set_ssh_key(profile_name, key_path, host),
+        SshKeyCommands::Remove { profile_name } => remove_ssh_key(profile_name),
+        SshKeyCommands::Show { profile_name } => show_ssh_key(profile_name),
+        SshKeyCommands::SetHost { profile_name, host } => set_host(profile_name, host),
+        SshKeyCommands::ShowHost { profile_name } => show_host(profile_name),
+        SshKeyCommands::RemoveHost { profile_name } => remove_host(profile_name),
+        SshKeyCommands::Scan { dir } => scan(dir),
+        SshKeyCommands::Generate {
+            profile_name,
+            algorithm,
+            sk,
+            resident,
+            verify_required,
+            ecdsa,
+            host,
+            no_passphrase,
+            path,
+        } => generate(
+            profile_name,
+            GenerateOptions {
+                algorithm,
+                sk,
+                resident,
+                verify_required,
+                ecdsa,
+                host,
+                no_passphrase,
+                path,
+            },
+        ),
     }
 }
 
-fn set_ssh_key(profile_name: String, key_path: String) -> Result<()> {
+fn set_ssh_key(profile_name: String, key_path: String, host: Option<String>) -> Result<()> {
     let mut config = Config::load().context("Failed to load configuration.")?;
 
     if !config.profiles.contains_key(&profile_name) {
-        bail!("Profile '{}' not found.", profile_name.yellow());
+        bail!("Profile '{}' not found.", profile_name.warning());
     }
 
-    // Validate key_path looks like a path (basic check)
-    // More thorough validation (e.g. file existence) could be added here or in Profile::validate
-    let path = std::path::PathBuf::from(key_path.clone());
-    if !path.exists() {
-        // Optionally, prompt if user wants to add a non-existent path, or just error
-        bail!("SSH key path '{}' does not exist.", key_path.red());
+    if !expand_home(key_path.trim()).exists() {
+        bail!("SSH key path '{}' does not exist.", key_path.error());
     }
-    // It's good practice to check if it's an absolute path or resolve it.
-    // For simplicity, we'll store it as given, but real-world might need canonicalization.
+    let path = normalize_path(&key_path);
 
+    let fingerprint = key_fingerprint(&expand_home(key_path.trim()))?;
     let profile = config.profiles.get_mut(&profile_name).unwrap(); // Should exist due to check above
     profile.ssh_key = Some(path);
+    profile.ssh_key_fingerprint = fingerprint;
+    if let Some(host) = host.as_deref().and_then(non_empty) {
+        profile.ssh_key_host = Some(host);
+        profile
+            .validate()
+            .map_err(|e| anyhow::anyhow!("{} Changes not saved.", e.user_message()))?;
+    }
+    let has_host = profile.ssh_key_host.is_some();
 
     config.save().context("Failed to save configuration.")?;
     println!(
         "SSH key path for profile '{}' set to '{}'.",
-        profile_name.cyan(),
-        key_path.green()
+        profile_name.accent(),
+        key_path.success()
     );
+    if !has_host {
+        println!(
+            "  Set the host it is for with '{}'.",
+            format!("gitp ssh-key set-host {} <host>", profile_name).accent()
+        );
+    }
     Ok(())
 }
 
@@ -51,23 +97,24 @@ fn remove_ssh_key(profile_name: String) -> Result<()> {
     let mut config = Config::load().context("Failed to load configuration.")?;
 
     if !config.profiles.contains_key(&profile_name) {
-        bail!("Profile '{}' not found.", profile_name.yellow());
+        bail!("Profile '{}' not found.", profile_name.warning());
     }
 
     let profile = config.profiles.get_mut(&profile_name).unwrap();
     if profile.ssh_key.is_none() {
         println!(
             "Profile '{}' does not have an SSH key associated.",
-            profile_name.cyan()
+            profile_name.accent()
         );
         return Ok(());
     }
 
     profile.ssh_key = None;
+    profile.ssh_key_fingerprint = None;
     config.save().context("Failed to save configuration.")?;
     println!(
         "SSH key association removed from profile '{}'.",
-        profile_name.cyan()
+        profile_name.accent()
     );
     Ok(())
 }
@@ -77,22 +124,456 @@ fn show_ssh_key(profile_name: String) -> Result<()> {
 
     match config.profiles.get(&profile_name) {
         Some(profile) => {
-            if let Some(ssh_key_path) = &profile.ssh_key {
-                println!(
-                    "SSH key for profile '{}': {}",
-                    profile_name.cyan(),
-                    ssh_key_path.display().to_string().green()
+            if profile.ssh_key.is_some() || !profile.ssh_hosts.is_empty() {
+                let keys = profile.ssh_key.iter().map(|key| (key, None)).chain(
+                    profile
+                        .ssh_hosts
+                        .iter()
+                        .map(|entry| (&entry.key, Some(entry.host.as_str()))),
                 );
+                for (ssh_key_path, host) in keys {
+                    let host = host.map(|h| format!(" (for {})", h)).unwrap_or_default();
+                    println!(
+                        "SSH key for profile '{}'{}: {}",
+                        profile_name.accent(),
+                        host,
+                        ssh_key_path.display().to_string().success()
+                    );
+                    match inspect_key(&expand_home(&ssh_key_path.to_string_lossy())) {
+                        Ok(info) => println!("  Type: {}", info.describe()),
+                        Err(e) => println!("  {}: {}", "Warning".warning(), e),
+                    }
+                }
+                print_ssh_entries(&config, profile)?;
             } else {
                 println!(
                     "Profile '{}' does not have an SSH key associated.",
-                    profile_name.cyan()
+                    profile_name.accent()
                 );
             }
         }
         None => {
-            bail!("Profile '{}' not found.", profile_name.yellow());
+            bail!("Profile '{}' not found.", profile_name.warning());
+        }
+    }
+    Ok(())
+}
+
+/// Prints the Host entries `gitp use` writes for `profile` and whether ~/.ssh/config has them.
+fn print_ssh_entries(config: &Config, profile: &Profile) -> Result<()> {
+    if !config.manages_ssh(profile) {
+        println!(
+            "  {}: gitp doesn't manage this profile's SSH entry.",
+            "Note".dimmed()
+        );
+        return Ok(());
+    }
+    if profile.ssh_key.is_some() && ssh_entry(profile).is_none() {
+        println!(
+            "  {}: no SSH config entry without a host. Set one with '{}'.",
+            "Note".dimmed(),
+            format!("gitp ssh-key set-host {} <host>", profile.name).accent()
+        );
+    }
+    let ssh_config = get_ssh_config_path()?;
+    let content = read_ssh_config(&ssh_config)?;
+    for entry in ssh_entries(profile) {
+        print_ssh_entry(config, profile, &entry, &ssh_config, &content);
+    }
+    Ok(())
+}
+
+/// Prints one Host entry of `profile` and whether `content` (~/.ssh/config) has it.
+fn print_ssh_entry(
+    config: &Config,
+    profile: &Profile,
+    entry: &SshEntry,
+    ssh_config: &Path,
+    content: &str,
+) {
+    let rendered = render_entry(entry, config.settings.ssh_security_key_provider.as_deref());
+    let status = match entry_status(content, &rendered) {
+        EntryStatus::InSync => format!("in sync with {}", ssh_config.display()).success(),
+        EntryStatus::Outdated => format!(
+            "out of date in {}; run 'gitp use {}' to update it",
+            ssh_config.display(),
+            profile.name
+        )
+        .warning(),
+        EntryStatus::Missing => format!(
+            "not in {}; run 'gitp use {}' to write it",
+            ssh_config.display(),
+            profile.name
+        )
+        .warning(),
+    };
+    println!("  SSH config entry ({}):", status);
+    for line in rendered.lines() {
+        println!("    {}", line);
+    }
+}
+
+fn set_host(profile_name: String, host: String) -> Result<()> {
+    let mut config = Config::load().context("Failed to load configuration.")?;
+    let Some(profile) = config.profiles.get_mut(&profile_name) else {
+        bail!("Profile '{}' not found.", profile_name.warning());
+    };
+    let Some(host) = non_empty(&host) else {
+        bail!(
+            "The host cannot be empty. Use '{}' to remove it.",
+            format!("gitp ssh-key remove-host {}", profile_name).accent()
+        );
+    };
+    profile.ssh_key_host = Some(host.clone());
+    profile
+        .validate()
+        .map_err(|e| anyhow::anyhow!("{} Changes not saved.", e.user_message()))?;
+    config.save().context("Failed to save configuration.")?;
+    println!(
+        "SSH key host for profile '{}' set to '{}'. Run '{}' to write it to ~/.ssh/config.",
+        profile_name.accent(),
+        host.success(),
+        format!("gitp use {}", profile_name).accent()
+    );
+    Ok(())
+}
+
+fn show_host(profile_name: String) -> Result<()> {
+    let config = Config::load().context("Failed to load configuration.")?;
+    let Some(profile) = config.profiles.get(&profile_name) else {
+        bail!("Profile '{}' not found.", profile_name.warning());
+    };
+    let Some(host) = &profile.ssh_key_host else {
+        println!(
+            "Profile '{}' does not have an SSH host associated.",
+            profile_name.accent()
+        );
+        return Ok(());
+    };
+    println!(
+        "SSH host for profile '{}': {}",
+        profile_name.accent(),
+        host.success()
+    );
+    if let Some(alias) = &profile.ssh_host_alias {
+        println!("  Host alias: {}", alias.success());
+    }
+    if profile.ssh_key.is_none() {
+        println!(
+            "  {}: the profile has no SSH key, so no Host entry is written for it.",
+            "Note".dimmed()
+        );
+    }
+    Ok(())
+}
+
+fn remove_host(profile_name: String) -> Result<()> {
+    let mut config = Config::load().context("Failed to load configuration.")?;
+    let Some(profile) = config.profiles.get_mut(&profile_name) else {
+        bail!("Profile '{}' not found.", profile_name.warning());
+    };
+    if profile.ssh_key_host.is_none() {
+        println!(
+            "Profile '{}' does not have an SSH host associated.",
+            profile_name.accent()
+        );
+        return Ok(());
+    }
+    if profile.ssh_key.is_some() {
+        bail!(
+            "Profile '{}' has an SSH key, which needs a host. Change the host with '{}', or \
+             remove the key first with '{}'.",
+            profile_name.warning(),
+            format!("gitp ssh-key set-host {} <host>", profile_name).accent(),
+            format!("gitp ssh-key remove {}", profile_name).accent()
+        );
+    }
+
+    profile.ssh_key_host = None;
+    let alias = profile.ssh_host_alias.take();
+    config.save().context("Failed to save configuration.")?;
+    println!(
+        "SSH host association removed from profile '{}'.",
+        profile_name.accent()
+    );
+    if let Some(alias) = alias {
+        println!("  Also removed its host alias '{}'.", alias);
+    }
+    Ok(())
+}
+
+/// Lists the keys in `dir` (default ~/.ssh) and the profiles using them.
+fn scan(dir: Option<String>) -> Result<()> {
+    let dir = match dir {
+        Some(dir) => expand_home(&dir),
+        None => dirs::home_dir()
+            .context("Failed to get home directory.")?
+            .join(".ssh"),
+    };
+    let keys = scan_keys(&dir)?;
+    if keys.is_empty() {
+        println!("No SSH keys found in {}.", dir.display());
+        return Ok(());
+    }
+    let config = Config::load().context("Failed to load configuration.")?;
+
+    println!("SSH keys in {}:", dir.display());
+    for key in &keys {
+        let mut users: Vec<&str> = config
+            .profiles
+            .values()
+            .filter(|p| {
+                p.ssh_key
+                    .as_ref()
+                    .is_some_and(|k| expand_home(&k.to_string_lossy()) == key.path)
+            })
+            .map(|p| p.name.as_str())
+            .collect();
+        users.sort();
+        let name = key.path.file_name().unwrap_or_default().to_string_lossy();
+        let description = if key.security_key.is_some() {
+            key.describe().warning().to_string()
+        } else {
+            key.describe()
+        };
+        print!("  {} {}", name.bold(), description);
+        if let Some(comment) = &key.comment {
+            print!(" {}", format!("({})", comment).dimmed());
+        }
+        if !users.is_empty() {
+            print!(" {} {}", "used by".dimmed(), users.join(", ").accent());
+        }
+        println!();
+    }
+    Ok(())
+}
+
+/// Where `ssh-key generate` puts a new key by default: `~/.ssh/id_<type>_<profile>`.
+pub(crate) fn default_key_path(profile_name: &str, key_type: &str) -> Result<PathBuf> {
+    Ok(dirs::home_dir()
+        .context("Failed to get home directory.")?
+        .join(".ssh")
+        .join(format!(
+            "id_{}_{}",
+            key_type.replace('-', "_"),
+            profile_name
+        )))
+}
+
+/// ssh-keygen's `-t` (and `-b`) for an algorithm.
+fn keygen_type(algorithm: KeyAlgorithm) -> (&'static str, Option<u32>) {
+    match algorithm {
+        KeyAlgorithm::Ed25519 => ("ed25519", None),
+        KeyAlgorithm::Rsa4096 => ("rsa", Some(4096)),
+        KeyAlgorithm::Ed25519Sk => ("ed25519-sk", None),
+        KeyAlgorithm::EcdsaSk => ("ecdsa-sk", None),
+        KeyAlgorithm::Ecdsa => ("ecdsa", None),
+    }
+}
+
+/// Comment for a key gitp generates, `gitp:<profile>@<host>`, so the key lists of hosts and
+/// agents show which profile a key belongs to.
+pub(crate) fn key_comment(profile_name: &str, host: Option<&str>) -> String {
+    match host {
+        Some(host) => format!("gitp:{}@{}", profile_name, host),
+        None => format!("gitp:{}", profile_name),
+    }
+}
+
+/// ssh-keygen writing an `algorithm` key to `path`. ssh-keygen asks for the passphrase on the
+/// terminal itself, so it never appears on a command line; `no_passphrase` passes an empty one.
+fn keygen_command(
+    algorithm: KeyAlgorithm,
+    path: &Path,
+    comment: &str,
+    no_passphrase: bool,
+) -> Result<Command> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create directory {}", dir.display()))?;
+    }
+    let (key_type, bits) = keygen_type(algorithm);
+    let mut command = Command::new("ssh-keygen");
+    command.arg("-t").arg(key_type);
+    if let Some(bits) = bits {
+        command.arg("-b").arg(bits.to_string());
+    }
+    command.arg("-f").arg(path).arg("-C").arg(comment);
+    if no_passphrase {
+        command.arg("-N").arg("");
+        println!(
+            "{}: the key is stored unencrypted; anyone who can read the file can use it.",
+            "Note".dimmed()
+        );
+    }
+    Ok(command)
+}
+
+/// Runs `command` (from `keygen_command`) and returns the new key's fingerprint.
+fn run_keygen(mut command: Command, path: &Path) -> Result<Option<String>> {
+    let status = command
+        .status()
+        .context("Failed to execute 'ssh-keygen'. Is OpenSSH installed?")?;
+    if !status.success() {
+        bail!("ssh-keygen failed ({}).", status);
+    }
+    key_fingerprint(path)
+}
+
+/// Runs ssh-keygen for an ed25519 key at `path`, for a profile that isn't saved yet, prints
+/// its public key and returns its fingerprint.
+pub(crate) fn generate_key(path: &Path, comment: &str) -> Result<Option<String>> {
+    let command = keygen_command(KeyAlgorithm::Ed25519, path, comment, false)?;
+    let fingerprint = run_keygen(command, path)?;
+    if let Ok(public) = fs::read_to_string(public_key_path(path)) {
+        println!("Add this public key to your account on the host:");
+        println!("  {}", public.trim());
+    }
+    Ok(fingerprint)
+}
+
+/// Options of `gitp ssh-key generate` beyond the profile.
+pub struct GenerateOptions {
+    pub algorithm: Option<KeyAlgorithm>,
+    pub sk: bool,
+    pub resident: bool,
+    pub verify_required: bool,
+    pub ecdsa: bool,
+    pub host: Option<String>,
+    pub no_passphrase: bool,
+    pub path: Option<String>,
+}
+
+/// Runs ssh-keygen for a new key (on a FIDO2 security key for the `-sk` algorithms) and sets
+/// it for the profile, recording its fingerprint. ssh-keygen asks for a touch or PIN of the
+/// security key.
+fn generate(profile_name: String, options: GenerateOptions) -> Result<()> {
+    let mut config = Config::load().context("Failed to load configuration.")?;
+    let Some(profile) = config.profiles.get(&profile_name) else {
+        bail!("Profile '{}' not found.", profile_name.warning());
+    };
+
+    let algorithm = options
+        .algorithm
+        .unwrap_or(match (options.ecdsa, options.sk) {
+            (false, false) => KeyAlgorithm::Ed25519,
+            (false, true) => KeyAlgorithm::Ed25519Sk,
+            (true, false) => KeyAlgorithm::Ecdsa,
+            (true, true) => KeyAlgorithm::EcdsaSk,
+        });
+    let (key_type, _) = keygen_type(algorithm);
+    let sk = key_type.ends_with("-sk");
+    if (options.resident || options.verify_required) && !sk {
+        bail!(
+            "--resident and --verify-required are for security keys; pass --sk or --type ed25519-sk."
+        );
+    }
+    let host = options
+        .host
+        .as_deref()
+        .and_then(non_empty)
+        .or_else(|| profile.ssh_key_host.clone());
+    let path: PathBuf = match options.path {
+        Some(path) => expand_home(&path),
+        None => default_key_path(&profile_name, key_type)?,
+    };
+    if path.exists() || public_key_path(&path).exists() {
+        bail!(
+            "'{}' already exists. Pass --path to write the key elsewhere, or set the existing key \
+             with 'gitp ssh-key set'.",
+            path.display()
+        );
+    }
+
+    let comment = key_comment(&profile_name, host.as_deref());
+    let mut command = keygen_command(algorithm, &path, &comment, options.no_passphrase)?;
+    if options.resident {
+        command.args(["-O", "resident"]);
+        // A separate application per profile, so keys for several profiles can share one
+        // security key without replacing each other
+        command
+            .arg("-O")
+            .arg(format!("application=ssh:gitp-{}", profile_name));
+    }
+    if options.verify_required {
+        command.args(["-O", "verify-required"]);
+    }
+    if sk {
+        if let Some(provider) = &config.settings.ssh_security_key_provider {
+            command.arg("-w").arg(provider);
         }
+        println!(
+            "Generating a {} key; touch your security key when it blinks.",
+            key_type.accent()
+        );
+    }
+    let fingerprint = run_keygen(command, &path)
+        .with_context(|| format!("No key was set for profile '{}'.", profile_name))?;
+
+    let profile = config
+        .profiles
+        .get_mut(&profile_name)
+        .expect("checked above");
+    profile.ssh_key = Some(contract_home(&path));
+    profile.ssh_key_fingerprint = fingerprint.clone();
+    if host.is_some() {
+        profile.ssh_key_host = host;
+    }
+    config.save().context("Failed to save configuration.")?;
+
+    println!(
+        "SSH key for profile '{}' set to '{}'.",
+        profile_name.accent(),
+        path.display().to_string().success()
+    );
+    if let Ok(info) = inspect_key(&path) {
+        println!("  Type: {}", info.describe());
+    }
+    if let Some(fingerprint) = &fingerprint {
+        println!("  Fingerprint: {}", fingerprint);
+    }
+    println!("  Comment: {}", comment);
+    if let Ok(public) = fs::read_to_string(public_key_path(&path)) {
+        println!("Add this public key to your account on the host:");
+        println!("  {}", public.trim());
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_comment() {
+        assert_eq!(
+            key_comment("work", Some("github.com")),
+            "gitp:work@github.com"
+        );
+        assert_eq!(key_comment("work", None), "gitp:work");
+
+        let command = keygen_command(
+            KeyAlgorithm::Rsa4096,
+            Path::new("/tmp/gitp-test/id_rsa_work"),
+            "gitp:work@github.com",
+            true,
+        )
+        .unwrap();
+        let args: Vec<_> = command.get_args().map(|a| a.to_string_lossy()).collect();
+        assert_eq!(
+            args,
+            [
+                "-t",
+                "rsa",
+                "-b",
+                "4096",
+                "-f",
+                "/tmp/gitp-test/id_rsa_work",
+                "-C",
+                "gitp:work@github.com",
+                "-N",
+                ""
+            ]
+        );
+    }
+}
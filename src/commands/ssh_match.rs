@@ -0,0 +1,50 @@
+// src/commands/ssh_match.rs
+// Directory test behind the managed SSH block's `Match host ... exec` entries
+
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::utils::expand_home;
+
+/// Whether ssh is running inside one of `profile`'s `ssh_match_dirs`. ssh treats a failing
+/// exec as "no match" and shows its stderr, so problems (no such profile, unreadable
+/// configuration) are a quiet `false`.
+pub fn execute(profile: String) -> bool {
+    let Ok(config) = Config::load_cached() else {
+        return false;
+    };
+    let (Some(profile), Ok(cwd)) = (config.profiles.get(&profile), std::env::current_dir()) else {
+        return false;
+    };
+    in_match_dirs(&profile.ssh_match_dirs, &cwd)
+}
+
+/// Whether `dir` is one of `match_dirs` or below it. Symlinks are resolved on both sides, so
+/// `~/work` matches whichever way the directory was reached.
+fn in_match_dirs(match_dirs: &[String], dir: &Path) -> bool {
+    let resolve = |path: PathBuf| path.canonicalize().unwrap_or(path);
+    let dir = resolve(dir.to_path_buf());
+    match_dirs
+        .iter()
+        .any(|match_dir| dir.starts_with(resolve(expand_home(match_dir.trim()))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_in_match_dirs() {
+        let root = std::env::temp_dir().join(format!("gitp-ssh-match-{}", std::process::id()));
+        let repo = root.join("work/acme/app");
+        std::fs::create_dir_all(&repo).unwrap();
+        let dirs = |name: &str| vec![root.join(name).to_string_lossy().to_string()];
+
+        assert!(in_match_dirs(&dirs("work"), &repo));
+        assert!(in_match_dirs(&dirs("work"), &root.join("work")));
+        // Path components, not string prefixes
+        assert!(!in_match_dirs(&dirs("wor"), &repo));
+        assert!(!in_match_dirs(&[], &repo));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
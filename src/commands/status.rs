@@ -0,0 +1,348 @@
+// src/commands/status.rs
+// Whether git's configuration still holds what gitp applied for the selected profiles
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+
+use crate::commands::use_profile::{remote_signing, ssh_entries, PROFILE_MARKER_KEY};
+use crate::config::{Config, Profile};
+use crate::git::{get_git_config, get_repo_root, GitConfigScope};
+use crate::ssh::ssh_config::{
+    entry_status, get_ssh_config_path, read_ssh_config, render_entry, EntryStatus,
+};
+use crate::utils::output::Paint;
+
+/// Identity keys `gitp use` writes at each scope.
+const IDENTITY_KEYS: [&str; 3] = ["user.name", "user.email", "user.signingkey"];
+
+/// An identity key whose value in git differs from what the profile applies.
+#[derive(Debug, PartialEq)]
+struct Drift {
+    key: &'static str,
+    expected: Option<String>,
+    actual: Option<String>,
+}
+
+pub fn execute() -> Result<()> {
+    let config = Config::load().context("Failed to load configuration.")?;
+    let repo = get_repo_root()?.map(|repo| repo.to_string_lossy().into_owned());
+    let selected = config.state.current_profile.as_deref();
+    let mut fixes: Vec<String> = Vec::new();
+    let mut drifted = 0;
+
+    let global = match selected {
+        Some(name) => match config.profiles.get(name) {
+            Some(profile) => {
+                println!(
+                    "{} '{}' (selected globally)",
+                    "Profile:".bold(),
+                    name.accent()
+                );
+                Some(profile)
+            }
+            None => {
+                println!(
+                    "{} '{}' is selected but no longer exists.",
+                    "Profile:".bold(),
+                    name.warning()
+                );
+                None
+            }
+        },
+        None => {
+            println!(
+                "{} none selected; choose one with '{}'.",
+                "Profile:".bold(),
+                "gitp use <profile>".accent()
+            );
+            None
+        }
+    };
+
+    if let Some(profile) = global {
+        println!("\n{}", "Global git config:".bold());
+        let expected = expected_identity(&config, profile, GitConfigScope::Global)?;
+        let drift = scope_drift(&expected, GitConfigScope::Global)?;
+        print_identity(&expected, &drift, &profile.name);
+        let marker = get_git_config(PROFILE_MARKER_KEY, GitConfigScope::Global)?;
+        if marker
+            .as_deref()
+            .is_some_and(|marker| marker != profile.name)
+        {
+            println!(
+                "  {}: {} names profile '{}'.",
+                "Note".dimmed(),
+                PROFILE_MARKER_KEY,
+                marker.unwrap_or_default()
+            );
+        }
+        if !drift.is_empty() {
+            drifted += drift.len();
+            fixes.push(format!("gitp use {}", profile.name));
+        }
+    }
+
+    let mut local = None;
+    if let Some(repo) = &repo {
+        let local_name = get_git_config(PROFILE_MARKER_KEY, GitConfigScope::Local)?
+            .or_else(|| config.state.repos.get(repo).cloned());
+        match local_name
+            .as_deref()
+            .map(|name| (name, config.profiles.get(name)))
+        {
+            Some((_, Some(profile))) => {
+                local = Some(profile);
+                println!(
+                    "\n{} (profile '{}'):",
+                    "Local git config".bold(),
+                    profile.name.accent()
+                );
+                let expected = expected_identity(&config, profile, GitConfigScope::Local)?;
+                let drift = scope_drift(&expected, GitConfigScope::Local)?;
+                print_identity(&expected, &drift, &profile.name);
+                if !drift.is_empty() {
+                    drifted += drift.len();
+                    fixes.push(format!("gitp use {} --local", profile.name));
+                }
+            }
+            Some((name, None)) => println!(
+                "\n{} names profile '{}', which no longer exists.",
+                "Local git config".bold(),
+                name.warning()
+            ),
+            // Without a profile of its own, anything the repository sets overrides the
+            // selected one
+            None => {
+                let overrides = match global {
+                    Some(profile) => {
+                        let expected = expected_identity(&config, profile, GitConfigScope::Global)?;
+                        scope_drift(&expected, GitConfigScope::Local)?
+                            .into_iter()
+                            .filter(|drift| drift.actual.is_some())
+                            .collect()
+                    }
+                    None => Vec::new(),
+                };
+                if !overrides.is_empty() {
+                    println!(
+                        "\n{} overrides the selected profile:",
+                        "Local git config".bold()
+                    );
+                    for drift in &overrides {
+                        print_drift(drift, selected.unwrap_or_default());
+                    }
+                    drifted += overrides.len();
+                    fixes.push(format!("gitp use {} --local", selected.unwrap_or_default()));
+                }
+            }
+        }
+    }
+
+    let mut ssh_profiles: Vec<&Profile> = global.into_iter().chain(local).collect();
+    ssh_profiles.dedup_by(|a, b| a.name == b.name);
+    ssh_profiles.retain(|profile| config.manages_ssh(profile));
+    let ssh_drift = check_ssh_entries(&config, &ssh_profiles)?;
+    if ssh_drift > 0 {
+        drifted += ssh_drift;
+        // `gitp use` rewrites the whole managed block, whichever profile it applies
+        if let Some(profile) = ssh_profiles.first() {
+            let fix = format!("gitp use {}", profile.name);
+            if !fixes.contains(&fix) {
+                fixes.push(fix);
+            }
+        }
+    }
+
+    println!();
+    if drifted == 0 {
+        println!(
+            "{}",
+            "Git's configuration matches the selected profiles.".success()
+        );
+    } else {
+        println!(
+            "{} {} setting{} drifted from the selected profiles. Apply them again with: {}",
+            "Drift:".warning().bold(),
+            drifted,
+            if drifted == 1 { "" } else { "s" },
+            fixes
+                .iter()
+                .map(|fix| format!("'{}'", fix.accent()))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// What `gitp use` writes for `profile` at `scope`: in a repository, a `host_signing` entry
+/// for the remote replaces the profile's signing key.
+fn expected_identity(
+    config: &Config,
+    profile: &Profile,
+    scope: GitConfigScope,
+) -> Result<Vec<(&'static str, Option<String>)>> {
+    let signing_key = match scope {
+        GitConfigScope::Local => remote_signing(config, profile)?.map(|(_, s)| s.git_key()),
+        GitConfigScope::Global => None,
+    }
+    .or_else(|| profile.git_config.user_signingkey.clone());
+    Ok(vec![
+        ("user.name", Some(profile.git_config.user_name.clone())),
+        ("user.email", Some(profile.git_config.user_email.clone())),
+        ("user.signingkey", signing_key),
+    ])
+}
+
+/// Reads the identity keys at `scope` and compares them with `expected`.
+fn scope_drift(
+    expected: &[(&'static str, Option<String>)],
+    scope: GitConfigScope,
+) -> Result<Vec<Drift>> {
+    let mut actual = Vec::new();
+    for key in IDENTITY_KEYS {
+        actual.push((key, get_git_config(key, scope)?));
+    }
+    Ok(differing(expected, &actual))
+}
+
+/// The keys whose `actual` value differs from the `expected` one. Emails compare without
+/// regard to case, as git hosts match them.
+fn differing(
+    expected: &[(&'static str, Option<String>)],
+    actual: &[(&'static str, Option<String>)],
+) -> Vec<Drift> {
+    expected
+        .iter()
+        .filter_map(|(key, expected)| {
+            let actual = actual
+                .iter()
+                .find(|(k, _)| k == key)
+                .and_then(|(_, value)| value.clone());
+            let same = match (expected, &actual) {
+                (Some(e), Some(a)) if *key == "user.email" => e.eq_ignore_ascii_case(a),
+                (e, a) => e == a,
+            };
+            (!same).then(|| Drift {
+                key,
+                expected: expected.clone(),
+                actual,
+            })
+        })
+        .collect()
+}
+
+/// Prints each identity key with its value, marking the ones that drifted.
+fn print_identity(expected: &[(&'static str, Option<String>)], drift: &[Drift], profile: &str) {
+    for (key, value) in expected {
+        match drift.iter().find(|d| d.key == *key) {
+            Some(drift) => print_drift(drift, profile),
+            None => println!(
+                "  {:<16} {}",
+                key.dimmed(),
+                value.as_deref().unwrap_or("not set").success()
+            ),
+        }
+    }
+}
+
+fn print_drift(drift: &Drift, profile: &str) {
+    let expected = match &drift.expected {
+        Some(value) => format!("profile '{}' sets {}", profile, value),
+        None => format!("profile '{}' sets none", profile),
+    };
+    println!(
+        "  {:<16} {} {}",
+        drift.key.dimmed(),
+        drift.actual.as_deref().unwrap_or("not set").error(),
+        format!("({})", expected).warning()
+    );
+}
+
+/// Prints whether ~/.ssh/config holds the entries of `profiles` as `gitp use` writes them,
+/// returning how many don't.
+fn check_ssh_entries(config: &Config, profiles: &[&Profile]) -> Result<usize> {
+    let entries: Vec<(&Profile, String)> = profiles
+        .iter()
+        .flat_map(|profile| {
+            ssh_entries(profile).into_iter().map(move |entry| {
+                let rendered =
+                    render_entry(&entry, config.settings.ssh_security_key_provider.as_deref());
+                (*profile, rendered)
+            })
+        })
+        .collect();
+    if entries.is_empty() {
+        return Ok(0);
+    }
+    let ssh_config = get_ssh_config_path()?;
+    let content = read_ssh_config(&ssh_config)?;
+    println!(
+        "\n{} ({}):",
+        "SSH config entries".bold(),
+        ssh_config.display()
+    );
+    let mut drifted = 0;
+    for (profile, rendered) in entries {
+        let header = rendered.lines().next().unwrap_or_default().trim();
+        let status = match entry_status(&content, &rendered) {
+            EntryStatus::InSync => "in sync".success(),
+            EntryStatus::Outdated => {
+                drifted += 1;
+                "out of date".error()
+            }
+            EntryStatus::Missing => {
+                drifted += 1;
+                "missing".error()
+            }
+        };
+        println!(
+            "  {} {} {}",
+            header,
+            format!("({})", profile.name).dimmed(),
+            status
+        );
+    }
+    Ok(drifted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_differing() {
+        let expected = [
+            ("user.name", Some("John Doe".to_string())),
+            ("user.email", Some("john@company.com".to_string())),
+            ("user.signingkey", None),
+        ];
+        let actual = [
+            ("user.name", Some("John Doe".to_string())),
+            ("user.email", Some("John@Company.com".to_string())),
+            ("user.signingkey", None),
+        ];
+        assert!(differing(&expected, &actual).is_empty());
+
+        let actual = [
+            ("user.name", Some("John Doe".to_string())),
+            ("user.email", Some("john@personal.com".to_string())),
+            ("user.signingkey", Some("ABCD".to_string())),
+        ];
+        assert_eq!(
+            differing(&expected, &actual),
+            [
+                Drift {
+                    key: "user.email",
+                    expected: Some("john@company.com".to_string()),
+                    actual: Some("john@personal.com".to_string()),
+                },
+                Drift {
+                    key: "user.signingkey",
+                    expected: None,
+                    actual: Some("ABCD".to_string()),
+                },
+            ]
+        );
+    }
+}
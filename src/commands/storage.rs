@@ -0,0 +1,184 @@
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::cli::{StorageBackend, StorageCommands};
+use crate::config::storage::{open_store, ConfigStore, TomlStore};
+use crate::config::Config;
+use crate::utils::output::Paint;
+
+pub fn execute(command: StorageCommands) -> Result<()> {
+    match command {
+        StorageCommands::Migrate { to } => migrate(to),
+    }
+}
+
+/// Copies everything to the `to` backend, checks the copy reads back identically, then moves
+/// the old files aside with a `.migrated` suffix so the new backend is picked up.
+fn migrate(to: StorageBackend) -> Result<()> {
+    let source = open_store()?;
+    let target = target_store(to)?;
+    if source.name() == target.name() {
+        println!(
+            "Configuration is already stored as {}.",
+            source.name().accent()
+        );
+        return Ok(());
+    }
+    copy_store(source.as_ref(), target.as_ref())
+}
+
+/// The part of [`migrate`] after the backends are picked: copy, check and move the old files.
+fn copy_store(source: &dyn ConfigStore, target: &dyn ConfigStore) -> Result<()> {
+    let config = Config::load_from(source)
+        .with_context(|| format!("Failed to load configuration from {}.", source.name()))?;
+    config
+        .save_to(target)
+        .with_context(|| format!("Failed to write configuration to {}.", target.name()))?;
+    let copied = Config::load_from(target)?;
+    if copied != config {
+        bail!(
+            "The {} copy does not match the original; {} files were left in place.",
+            target.name(),
+            source.name()
+        );
+    }
+    println!(
+        "Copied {} profile(s) and state from {} to {}.",
+        config.profiles.len(),
+        source.name().warning(),
+        target.name().success()
+    );
+
+    for path in source.paths().into_iter().filter(|p| p.exists()) {
+        let mut backup = path.clone().into_os_string();
+        backup.push(".migrated");
+        let backup = PathBuf::from(backup);
+        fs::rename(&path, &backup)
+            .with_context(|| format!("Failed to move {:?} to {:?}", path, backup))?;
+        println!("  Moved {} to {}", path.display(), backup.display());
+    }
+    for path in target.paths() {
+        println!(
+            "Configuration is now stored in {}",
+            path.display().to_string().success()
+        );
+    }
+    Ok(())
+}
+
+fn target_store(to: StorageBackend) -> Result<Box<dyn ConfigStore>> {
+    match to {
+        StorageBackend::Toml => Ok(Box::new(TomlStore::open()?)),
+        #[cfg(feature = "sqlite")]
+        StorageBackend::Sqlite => Ok(Box::new(crate::config::sqlite::SqliteStore::new(
+            crate::config::storage::get_database_path()?,
+        ))),
+        #[cfg(not(feature = "sqlite"))]
+        StorageBackend::Sqlite => bail!(
+            "This gitp was built without SQLite support. Reinstall with `cargo install gitp --features sqlite`."
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::state::State;
+    use crate::config::storage::ConfigStorage;
+    use crate::config::Profile;
+    use std::path::Path;
+
+    fn toml_store(dir: &Path) -> TomlStore {
+        TomlStore::new(dir.join("config.toml"), dir.join("state.toml"))
+    }
+
+    fn seed(store: &dyn ConfigStore) -> Result<()> {
+        let mut config = ConfigStorage::default();
+        config.profiles.insert(
+            "work".to_string(),
+            Profile::new(
+                "work".to_string(),
+                "John Doe".to_string(),
+                "john@company.com".to_string(),
+            ),
+        );
+        store.save_config(&config)?;
+        store.save_state(&State {
+            current_profile: Some("work".to_string()),
+            ..Default::default()
+        })
+    }
+
+    /// A backend that loses the profiles it is given.
+    struct LossyStore(TomlStore);
+
+    impl ConfigStore for LossyStore {
+        fn name(&self) -> &'static str {
+            "lossy"
+        }
+        fn paths(&self) -> Vec<PathBuf> {
+            self.0.paths()
+        }
+        fn load_config(&self) -> Result<ConfigStorage> {
+            self.0.load_config()
+        }
+        fn save_config(&self, config: &ConfigStorage) -> Result<()> {
+            self.0.save_config(&ConfigStorage {
+                settings: config.settings.clone(),
+                ..Default::default()
+            })
+        }
+        fn load_state(&self) -> Result<Option<State>> {
+            self.0.load_state()
+        }
+        fn save_state(&self, state: &State) -> Result<()> {
+            self.0.save_state(state)
+        }
+        fn read_only_path(&self) -> Option<PathBuf> {
+            None
+        }
+    }
+
+    #[test]
+    fn test_copy_store() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let source = toml_store(&dir.path().join("old"));
+        let target = toml_store(&dir.path().join("new"));
+        seed(&source)?;
+        let original = Config::load_from(&source)?;
+
+        copy_store(&source, &target)?;
+        assert_eq!(Config::load_from(&target)?, original);
+        // The old files are moved aside, not deleted
+        for path in source.paths() {
+            assert!(!path.exists());
+            let mut backup = path.into_os_string();
+            backup.push(".migrated");
+            assert!(PathBuf::from(backup).exists());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_store_keeps_source_on_mismatch() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let source = toml_store(&dir.path().join("old"));
+        let target = LossyStore(toml_store(&dir.path().join("new")));
+        seed(&source)?;
+
+        let err = copy_store(&source, &target).unwrap_err().to_string();
+        assert!(err.contains("does not match the original"), "{}", err);
+        for path in source.paths() {
+            assert!(path.exists());
+        }
+        Ok(())
+    }
+
+    #[cfg(not(feature = "sqlite"))]
+    #[test]
+    fn test_target_store_without_sqlite() {
+        let err = target_store(StorageBackend::Sqlite).err().unwrap();
+        assert!(err.to_string().contains("without SQLite support"));
+    }
+}
@@ -0,0 +1,173 @@
+// src/commands/url.rs
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+
+use crate::cli::UrlCommands;
+use crate::commands::remotes::aliased_url;
+use crate::config::{same_host, Config, Profile};
+use crate::git::url::{parse_remote_url, Transport};
+use crate::utils::output::Paint;
+
+pub fn execute(command: UrlCommands) -> Result<()> {
+    match command {
+        UrlCommands::Rewrite { url, profile } => rewrite(&url, profile.as_deref()),
+    }
+}
+
+/// Prints `url` in the form that goes through a profile's host alias. Only the URL goes to
+/// stdout, so `git clone "$(gitp url rewrite <url>)"` works.
+fn rewrite(url: &str, profile: Option<&str>) -> Result<()> {
+    let config = Config::load().context("Failed to load configuration.")?;
+    let remote =
+        parse_remote_url(url).with_context(|| format!("'{}' is not an SSH or HTTPS URL.", url))?;
+
+    if let Some(owner) = config
+        .profiles
+        .values()
+        .find(|p| p.ssh_host_alias.as_deref() == Some(remote.host.as_str()))
+    {
+        eprintln!(
+            "{}: already uses the host alias of profile '{}'.",
+            "Note".dimmed(),
+            owner.name
+        );
+        println!("{}", url);
+        return Ok(());
+    }
+
+    let profile = match profile {
+        Some(name) => config
+            .profiles
+            .get(name)
+            .with_context(|| format!("Profile '{}' not found.", name.warning()))?,
+        None => {
+            let candidates: Vec<&Profile> = config
+                .profiles
+                .values()
+                .filter(|p| {
+                    p.ssh_host_alias.is_some()
+                        && p.ssh_key_host
+                            .as_deref()
+                            .is_some_and(|host| same_host(host, &remote.host))
+                })
+                .collect();
+            match candidates.as_slice() {
+                [profile] => *profile,
+                [] => bail!(
+                    "No profile has a host alias for {}. 'gitp split' sets one up, or set \
+                     ssh_host_alias in a profile in config.toml.",
+                    remote.host
+                ),
+                several => bail!(
+                    "Profiles {} all have a host alias for {}. Pick one with --profile.",
+                    several
+                        .iter()
+                        .map(|p| format!("'{}'", p.name))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    remote.host
+                ),
+            }
+        }
+    };
+
+    match alias_clone_url(url, profile) {
+        Some(rewritten) => {
+            println!("{}", rewritten);
+            Ok(())
+        }
+        None => bail!(
+            "Profile '{}' has no host alias for {}.",
+            profile.name,
+            remote.host
+        ),
+    }
+}
+
+/// `url` as an SSH URL through `profile`'s host alias. HTTPS URLs become scp-like
+/// `git@<alias>:<path>.git`, the form GitHub, GitLab and Bitbucket all accept.
+pub(crate) fn alias_clone_url(url: &str, profile: &Profile) -> Option<String> {
+    let remote = parse_remote_url(url)?;
+    match remote.transport {
+        Transport::Ssh => aliased_url(url, profile),
+        Transport::Https => {
+            let (alias, host) = (
+                profile.ssh_host_alias.as_ref()?,
+                profile.ssh_key_host.as_ref()?,
+            );
+            if !same_host(&remote.host, host) {
+                return None;
+            }
+            let path = remote.path.trim_matches('/');
+            if path.is_empty() {
+                return None;
+            }
+            let suffix = if path.ends_with(".git") { "" } else { ".git" };
+            Some(format!("git@{}:{}{}", alias, path, suffix))
+        }
+        Transport::Other(_) => None,
+    }
+}
+
+/// An example clone URL for `profile`'s host alias, when it has one the SSH config sets up.
+pub(crate) fn clone_example(profile: &Profile) -> Option<String> {
+    profile.ssh_key.as_ref()?;
+    profile.ssh_key_host.as_ref()?;
+    let alias = profile.ssh_host_alias.as_ref()?;
+    Some(format!("git@{}:<org>/<repo>.git", alias))
+}
+
+/// Tells how to clone through `profile`'s host alias, which plain `git@<host>:` URLs bypass.
+pub(crate) fn print_clone_hint(profile: &Profile) {
+    if let Some(example) = clone_example(profile) {
+        println!(
+            "Clone repositories for '{}' through its host alias: {}",
+            profile.name,
+            format!("git clone {}", example).accent()
+        );
+        println!(
+            "  Convert a copied URL with: {}",
+            format!("gitp url rewrite <url> --profile {}", profile.name).accent()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alias_clone_url() {
+        let mut profile = Profile::new(
+            "work".to_string(),
+            "John Doe".to_string(),
+            "john@company.com".to_string(),
+        );
+        profile.ssh_key = Some("~/.ssh/id_work".into());
+        profile.ssh_key_host = Some("github.com".to_string());
+        profile.ssh_host_alias = Some("github.com-work".to_string());
+
+        for url in [
+            "https://github.com/acme/app",
+            "https://github.com/acme/app.git",
+            "https://user@github.com/acme/app/",
+            "git@github.com:acme/app.git",
+        ] {
+            assert_eq!(
+                alias_clone_url(url, &profile).as_deref(),
+                Some("git@github.com-work:acme/app.git"),
+                "{}",
+                url
+            );
+        }
+        assert_eq!(
+            alias_clone_url("https://gitlab.com/acme/app", &profile),
+            None
+        );
+        assert_eq!(alias_clone_url("https://github.com/", &profile), None);
+        assert_eq!(
+            clone_example(&profile).as_deref(),
+            Some("git@github.com-work:<org>/<repo>.git")
+        );
+    }
+}
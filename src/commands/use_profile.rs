@@ -1,23 +1,65 @@
 // src/commands/use_profile.rs
 use anyhow::{bail, Context, Result};
+use chrono::{Local, Utc};
 use colored::Colorize;
 
-use crate::config::Config;
-use crate::git::{set_git_config, unset_git_config, GitConfigScope};
-use crate::ssh::ssh_config;
+use crate::commands::doctor::{include_overrides, unreachable_hosts};
+use crate::commands::rules::{dir_rules, fragment_profile, refresh_rules};
+use crate::commands::url::print_clone_hint;
+use crate::config::git_keys::check_custom_config;
+use crate::config::{
+    is_host_pattern, Config, CredentialHelperSetup, HelperCoexistence, HostMatch, HostSigning,
+    Profile,
+};
+use crate::credentials::{helper, verify};
+use crate::extras::{self, plugins};
+use crate::git::includes::identity_include_rules;
+use crate::git::url::{parse_remote_url, Transport};
+use crate::git::{
+    apply_git_config, get_git_config, get_git_config_regexp, get_primary_remote, get_remotes,
+    get_repo_root, regex_escape, GitConfigChange, GitConfigEntry, GitConfigScope,
+};
+use crate::gpg;
+use crate::ssh::agent::{add_to_agent, agent_requirement, AgentRequirement};
+use crate::ssh::ssh_config::{self, SshEntry};
+use crate::utils::mask::mask_value;
+use crate::utils::output::{theme, Paint, Symbol};
+use crate::utils::prompt::Select;
+use crate::utils::terminal::can_prompt;
+use crate::utils::{contract_home, expand_home};
 use std::path::PathBuf;
 
-pub fn execute(name: String, local: bool, global: bool) -> Result<()> {
+/// Git config key recording which gitp profile was applied at a scope.
+pub const PROFILE_MARKER_KEY: &str = "gitp.profile";
+
+/// Git config key recording the `custom_config` entries gitp set at a scope, so they are
+/// removed on the next switch even when the profile that set them was edited or removed since.
+/// Each value is a key gitp replaced, or `<key>\t<value>` for a value it added.
+pub const APPLIED_CONFIG_KEY: &str = "gitp.customConfig";
+
+/// Switches to profile `name`, or with `None` (`use --auto`) to the one tied to the current
+/// repository's remote.
+pub fn execute(name: Option<String>, local: bool, global: bool, no_validate: bool) -> Result<()> {
     let mut config = Config::load().context("Failed to load configuration.")?;
+    let name = match name {
+        Some(name) => name,
+        None => detect_profile(&config)?,
+    };
 
-    let profile_to_apply = config.profiles.get(&name).ok_or_else(|| {
-        anyhow::anyhow!(
+    if !config.profiles.contains_key(&name) {
+        bail!(
             "Profile '{}' not found. Use '{}' to list available profiles or '{}' to create a new one.",
-            name.yellow(),
-            "gitp list".cyan(),
-            format!("gitp new {}", name).cyan()
-        )
-    })?;
+            name.warning(),
+            "gitp list".accent(),
+            format!("gitp new {}", name).accent()
+        );
+    }
+
+    if !no_validate {
+        validate_custom_config(&config.profiles[&name])?;
+        check_signing_card(&config.profiles[&name])?;
+    }
+    check_ssh_host_conflicts(&config)?;
 
     // Determine scope
     let scope = match (local, global) {
@@ -32,89 +74,1236 @@ pub fn execute(name: String, local: bool, global: bool) -> Result<()> {
 
     let scope_str = format!("{:?}", scope).to_lowercase();
 
+    if scope == GitConfigScope::Local {
+        for remote in get_remotes()? {
+            config.require_approved(&remote.effective_url, &name)?;
+        }
+    }
+
     println!(
         "Applying profile '{}' to {} Git configuration...",
-        name.cyan(),
+        name.accent(),
         scope_str
     );
 
-    // Apply Git configurations
-    set_git_config("user.name", &profile_to_apply.git_config.user_name, scope).with_context(
-        || {
-            format!(
-                "Failed to set user.name for profile '{}' ({})",
-                name, scope_str
-            )
-        },
-    )?;
+    let repo = match scope {
+        GitConfigScope::Local => get_repo_root()?.map(|repo| repo.to_string_lossy().into_owned()),
+        GitConfigScope::Global => None,
+    };
+    let previous = match &repo {
+        Some(repo) => config.state.repos.get(repo).cloned(),
+        None => config.state.current_profile.clone(),
+    };
+    // Plugins run first so a failing one can stop the switch under `plugin_failure = "abort"`
+    plugins::run_switch(&config, previous.as_deref(), &name, scope)?;
+
+    apply_git_identity(&mut config, &name, scope)?;
+
+    config.state.record_switch(&name, repo, Utc::now());
+
+    // Tools other than git only have user-wide configuration
+    if let Some(profile) = config.profiles.get(&name) {
+        match scope {
+            GitConfigScope::Global => extras::apply_extras(profile),
+            GitConfigScope::Local if !profile.extras.is_empty() => println!(
+                "  {}: extras ({}) are only applied by a global switch.",
+                "Note".dimmed(),
+                profile
+                    .extras
+                    .keys()
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            GitConfigScope::Local => {}
+        }
+    }
+
+    // TODO: Add logic for ssh_key and gpg_key if they influence git config directly (e.g. core.sshCommand, gpg.program)
+    // For now, they are informational or for other tools.
+
+    // Update SSH configuration for all profiles
+    println!("Updating SSH configuration based on all gitp profiles...");
+    sync_ssh_config(&config)?;
+    println!("SSH configuration updated successfully.");
+
+    // A global switch can start a [user] section after the rules' includes, which would then
+    // lose to it
+    if scope == GitConfigScope::Global && !dir_rules(&config).is_empty() {
+        refresh_rules(&config);
+    }
+
+    let recorded = config.save_switch()?;
+
     println!(
-        "  Set user.name to: {}",
-        profile_to_apply.git_config.user_name.green()
+        "Successfully set '{}' as the active Git profile for {} scope.",
+        name.success(),
+        scope_str
     );
+    if scope == GitConfigScope::Global && recorded {
+        println!(
+            "gitp internal current profile also updated to '{}'.",
+            name.success()
+        );
+    }
+    if let Some(profile) = config.profiles.get(&name) {
+        print_include_overrides(&config, profile)?;
+        print_clone_hint(profile);
+        print_network_notes(profile);
+    }
+    if !no_validate {
+        check_key_agent(&mut config, &name)?;
+    }
 
-    set_git_config("user.email", &profile_to_apply.git_config.user_email, scope).with_context(
-        || {
-            format!(
-                "Failed to set user.email for profile '{}' ({})",
-                name, scope_str
-            )
-        },
-    )?;
+    Ok(())
+}
+
+/// Picks the profile tied to the host of the current repository's `origin` remote. An SSH
+/// alias names one profile; otherwise profiles for the remote's transport (and, over HTTPS,
+/// its user) are preferred, and the choice must come down to one.
+fn detect_profile(config: &Config) -> Result<String> {
+    if get_repo_root()?.is_none() {
+        bail!("--auto picks the profile by the repository's remote; run it inside a repository.");
+    }
+    let remote = get_primary_remote()?
+        .context("The repository has no remote to pick a profile by. Name the profile instead.")?;
+    let url = parse_remote_url(&remote.effective_url).with_context(|| {
+        format!(
+            "Remote '{}' has an unrecognized URL '{}'.",
+            remote.name, remote.effective_url
+        )
+    })?;
+
+    let matches = config.find_profiles_for_host(&url.host);
+    let ssh = url.transport == Transport::Ssh;
+    let mut candidates: Vec<&Profile> = Vec::new();
+    let preferred: Vec<_> = match matches.iter().find(|m| m.via == HostMatch::SshAlias) {
+        Some(alias) => vec![*alias],
+        None => matches
+            .iter()
+            .filter(|m| m.via.is_ssh() == ssh)
+            .copied()
+            .collect(),
+    };
+    let pool = if preferred.is_empty() {
+        &matches
+    } else {
+        &preferred
+    };
+    for found in pool {
+        if !candidates.iter().any(|p| p.name == found.profile.name) {
+            candidates.push(found.profile);
+        }
+    }
+    if let (false, Some(user), true) = (ssh, &url.user, candidates.len() > 1) {
+        candidates.retain(|p| {
+            p.https_for(&url.host)
+                .is_some_and(|c| c.username.eq_ignore_ascii_case(user))
+        });
+    }
+
+    match candidates.as_slice() {
+        [profile] => {
+            println!(
+                "Remote '{}' is on {}: using profile '{}'.",
+                remote.name,
+                url.host,
+                profile.name.accent()
+            );
+            Ok(profile.name.clone())
+        }
+        [] => bail!(
+            "No profile is tied to {}, the host of remote '{}'. Name one: {}",
+            url.host,
+            remote.name,
+            "gitp use <name> --local".accent()
+        ),
+        several => bail!(
+            "Profiles {} are all tied to {}. Name one: {}",
+            several
+                .iter()
+                .map(|p| format!("'{}'", p.name))
+                .collect::<Vec<_>>()
+                .join(", "),
+            url.host,
+            "gitp use <name> --local".accent()
+        ),
+    }
+}
+
+/// Warns about `[includeIf]` rules of the global or system config whose identity wins over
+/// the one just applied in the current repository.
+fn print_include_overrides(config: &Config, profile: &Profile) -> Result<()> {
+    if get_repo_root()?.is_none() {
+        return Ok(());
+    }
+    for rule in identity_include_rules()? {
+        let overrides = include_overrides(&rule, profile);
+        if overrides.is_empty() {
+            continue;
+        }
+        if let Some(owner) = fragment_profile(config, &rule.path) {
+            println!(
+                "{}: this repository is under a directory rule for profile '{}', which wins \
+                 over the global profile here. '{}' overrides it for this repository.",
+                "Note".dimmed(),
+                owner.name,
+                format!("gitp use {} --local", profile.name).accent()
+            );
+            continue;
+        }
+        println!(
+            "{}: {} in {} overrides this profile in the current repository: {}",
+            "Warning".warning(),
+            rule.header(),
+            contract_home(&rule.file).display(),
+            overrides.join(", ")
+        );
+        println!(
+            "  Apply the profile with '{}', or let gitp take over the rule with '{}'.",
+            format!("gitp use {} --local", profile.name).accent(),
+            "gitp doctor --convert-includes".accent()
+        );
+    }
+    Ok(())
+}
+
+/// Shows the profile's notes (e.g. "requires corp VPN") when one of its hosts can't be
+/// reached, so a failing fetch isn't mistaken for a configuration error.
+fn print_network_notes(profile: &Profile) {
+    let Some(notes) = &profile.notes else {
+        return;
+    };
+    let unreachable = unreachable_hosts(profile);
+    if unreachable.is_empty() {
+        return;
+    }
     println!(
-        "  Set user.email to: {}",
-        profile_to_apply.git_config.user_email.green()
+        "{} {} can't be reached. Note for profile '{}': {}",
+        Symbol::Warn.as_str().warning().bold(),
+        unreachable.join(", ").bold(),
+        profile.name,
+        notes.warning().bold()
     );
+}
+
+/// Applies profile `name` globally without prompting, for unattended setup (`bootstrap`,
+/// `ci setup`): an `ask` credential helper coexistence is treated as `leave`. The SSH config
+/// and extras are left alone.
+pub(crate) fn apply_unattended(config: &mut Config, name: &str) -> Result<()> {
+    let coexistence = config.settings.credential_helper_coexistence;
+    if coexistence == HelperCoexistence::Ask {
+        config.settings.credential_helper_coexistence = HelperCoexistence::Leave;
+    }
+    let applied = apply_git_identity(config, name, GitConfigScope::Global);
+    config.settings.credential_helper_coexistence = coexistence;
+    applied?;
+    config.state.record_switch(name, None, Utc::now());
+    Ok(())
+}
+
+/// Checks the profile's `custom_config` against the catalog of known git keys: unknown keys
+/// are reported, values a key can't take are an error. Skipped with `--no-validate`.
+pub(crate) fn validate_custom_config(profile: &Profile) -> Result<()> {
+    let findings = check_custom_config(&profile.custom_config);
+    for finding in findings.iter().filter(|f| !f.is_invalid()) {
+        eprintln!("  {}: {}", "Warning".warning(), finding);
+    }
+    let invalid: Vec<String> = findings
+        .iter()
+        .filter(|f| f.is_invalid())
+        .map(|f| format!("  {}", f))
+        .collect();
+    if !invalid.is_empty() {
+        bail!(
+            "Profile '{}' has invalid custom_config values:\n{}\nPass --no-validate to use them anyway.",
+            profile.name,
+            invalid.join("\n")
+        );
+    }
+    Ok(())
+}
+
+/// For a profile that signs every commit, checks that a smartcard holding its GPG key is
+/// inserted: without it every commit fails, in ways that don't point at the card.
+fn check_signing_card(profile: &Profile) -> Result<()> {
+    if !gpg::signing_required(profile) {
+        return Ok(());
+    }
+    match gpg::card_problem(profile) {
+        Ok(None) => Ok(()),
+        Ok(Some(problem)) => bail!(
+            "{}\nProfile '{}' signs every commit, so commits would fail. Fix that, or pass \
+             --no-validate to switch anyway.",
+            problem,
+            profile.name
+        ),
+        Err(e) => {
+            eprintln!(
+                "  {}: could not check the signing key: {:#}",
+                "Warning".warning(),
+                e
+            );
+            Ok(())
+        }
+    }
+}
+
+/// Ways `gitp use` offers to unlock a passphrase-protected key ssh-agent doesn't hold.
+enum Unlock {
+    Load,
+    Keychain,
+    AddKeysToAgent,
+    Leave,
+}
 
-    if let Some(signing_key) = &profile_to_apply.git_config.user_signingkey {
-        set_git_config("user.signingkey", signing_key, scope).with_context(|| {
+/// Warns when the profile's SSH key has a passphrase no ssh-agent holds: ssh then asks for it
+/// on every connection, which fails where nobody can type it (CI, an IDE's background fetch).
+/// On a terminal, offers to load the key now or to write AddKeysToAgent (and on macOS
+/// UseKeychain) into its SSH entry.
+fn check_key_agent(config: &mut Config, name: &str) -> Result<()> {
+    let Some(key) = config.profiles[name].ssh_key.clone() else {
+        return Ok(());
+    };
+    let path = expand_home(&key.to_string_lossy());
+    let requirement = match agent_requirement(&path) {
+        Ok(requirement) => requirement,
+        Err(e) => {
+            eprintln!(
+                "  {}: could not check whether ssh-agent holds the SSH key: {:#}",
+                "Warning".warning(),
+                e
+            );
+            return Ok(());
+        }
+    };
+    match requirement {
+        AgentRequirement::Ready => return Ok(()),
+        AgentRequirement::NoAgent => {
+            println!(
+                "  {}: {} is passphrase-protected and no ssh-agent is running, so git asks for \
+                 the passphrase on every fetch and push, and fails without a terminal (CI, IDE \
+                 fetches). Start one with '{}'.",
+                "Warning".warning(),
+                key.display(),
+                "eval \"$(ssh-agent -s)\"".accent()
+            );
+            return Ok(());
+        }
+        AgentRequirement::NotLoaded => println!(
+            "  {}: {} is passphrase-protected and ssh-agent doesn't hold it, so fetches and \
+             pushes without a terminal (CI, IDE fetches) fail.",
+            "Warning".warning(),
+            key.display()
+        ),
+    }
+
+    let profile = &config.profiles[name];
+    let has_entry = config.manages_ssh(profile) && ssh_entry(profile).is_some();
+    let can_configure = profile.ssh_add_keys_to_agent.is_none() && has_entry;
+    // On macOS the keychain can hold the passphrase, as ssh-add and ssh do with UseKeychain
+    let can_use_keychain = cfg!(target_os = "macos") && !profile.ssh_use_keychain && has_entry;
+    if !can_prompt() {
+        let configure = if can_use_keychain {
+            format!(
+                ", or keep its passphrase in the keychain with 'gitp ssh agent {} --use-keychain true'",
+                name
+            )
+        } else if can_configure {
             format!(
-                "Failed to set user.signingkey for profile '{}' ({})",
-                name, scope_str
+                ", or have ssh load it once unlocked with 'gitp ssh agent {} --add-keys-to-agent yes'",
+                name
             )
-        })?;
-        println!("  Set user.signingkey to: {}", signing_key.green());
+        } else {
+            String::new()
+        };
+        println!(
+            "  Load it with '{}'{}.",
+            format!("gitp ssh agent {} --load", name).accent(),
+            configure
+        );
+        return Ok(());
+    }
+    let mut choices = vec![(
+        Unlock::Load,
+        "Add it to ssh-agent now (ssh-add asks for the passphrase)",
+    )];
+    if can_use_keychain {
+        choices.push((
+            Unlock::Keychain,
+            "Keep the passphrase in the macOS keychain (UseKeychain, AddKeysToAgent yes)",
+        ));
+    }
+    if can_configure {
+        choices.push((
+            Unlock::AddKeysToAgent,
+            "Write 'AddKeysToAgent yes' into the profile's SSH entry",
+        ));
+    }
+    choices.push((Unlock::Leave, "Leave it"));
+    let items: Vec<&str> = choices.iter().map(|(_, label)| *label).collect();
+    let choice = Select::with_theme(&theme())
+        .with_prompt("How should the key be unlocked?")
+        .items(&items)
+        .default(0)
+        .interact()
+        .context("Failed to get the choice.")?;
+    let use_keychain = match choices[choice].0 {
+        Unlock::Load => return add_to_agent(&path, uses_keychain(&config.profiles[name])),
+        Unlock::Keychain => true,
+        Unlock::AddKeysToAgent => false,
+        Unlock::Leave => return Ok(()),
+    };
+    if let Some(profile) = config.profiles.get_mut(name) {
+        profile.ssh_use_keychain |= use_keychain;
+        profile
+            .ssh_add_keys_to_agent
+            .get_or_insert_with(|| "yes".to_string());
+    }
+    config.save().context("Failed to save configuration.")?;
+    sync_ssh_config(config)?;
+    if use_keychain {
+        println!(
+            "  ssh now takes the passphrase from the keychain once it is typed, and adds the key to the agent."
+        );
+    } else {
+        println!("  ssh now adds the key to the agent the first time its passphrase is typed.");
+    }
+    Ok(())
+}
+
+/// Rewrites the gitp-managed SSH block from the SSH keys of the profiles it manages.
+pub(crate) fn sync_ssh_config(config: &Config) -> Result<()> {
+    check_ssh_host_conflicts(config)?;
+    let mut ssh_entries_for_config_update: Vec<SshEntry> = config
+        .ssh_managed_profiles()
+        .into_iter()
+        .flat_map(ssh_entries)
+        .collect();
+    // Keep the block stable across runs. ssh takes each option from the first matching
+    // entry, so directory-limited entries go first, then single hosts, then the `*.domain`
+    // patterns that could also match them.
+    let precedence = |e: &SshEntry| (e.match_profile.is_none(), is_host_pattern(&e.host));
+    ssh_entries_for_config_update.sort_by(|a, b| (precedence(a), a).cmp(&(precedence(b), b)));
+
+    ssh_config::update_ssh_config(
+        &ssh_entries_for_config_update,
+        config.settings.ssh_security_key_provider.as_deref(),
+        config.settings.ssh_block_position,
+    )
+    .context("Failed to update SSH configuration.")
+}
+
+/// Fails when two profiles would write the same Host entry outside their match directories.
+/// ssh takes each option from the first entry, so the other profile's key would never be
+/// offered. `ssh_hosts` entries have no alias, which makes this easy to run into.
+pub(crate) fn check_ssh_host_conflicts(config: &Config) -> Result<()> {
+    let mut seen: Vec<(String, &str)> = Vec::new();
+    for profile in config.ssh_managed_profiles() {
+        for entry in ssh_entries(profile) {
+            if entry.match_profile.is_some() {
+                continue;
+            }
+            let host = entry.host.to_lowercase();
+            if let Some((_, other)) = seen
+                .iter()
+                .find(|(seen_host, name)| *seen_host == host && *name != profile.name)
+            {
+                bail!(
+                    "Profiles '{}' and '{}' both have an SSH key for '{}', and ssh would only \
+                     use the first. Give one of them ssh_match_dirs, or an ssh_key with an \
+                     ssh_host_alias instead of the ssh_hosts entry.",
+                    other,
+                    profile.name,
+                    entry.host
+                );
+            }
+            seen.push((host, &profile.name));
+        }
+    }
+    Ok(())
+}
+
+/// The entry `profile` gets in the managed SSH block, when it has an SSH key and host.
+pub(crate) fn ssh_entry(profile: &Profile) -> Option<SshEntry> {
+    let (key_path, host, alias) = (
+        profile.ssh_key.as_ref()?,
+        profile.ssh_key_host.as_ref()?,
+        profile.ssh_host()?,
+    );
+    Some(SshEntry {
+        host: alias.to_string(),
+        hostname: host.clone(),
+        identity_file: PathBuf::from(key_path),
+        user: profile.ssh_user.clone(),
+        match_profile: (!profile.ssh_match_dirs.is_empty()).then(|| profile.name.clone()),
+        control: profile
+            .ssh_control
+            .as_ref()
+            .map(|control| control.for_profile(&profile.name)),
+        add_keys_to_agent: profile.ssh_add_keys_to_agent.clone(),
+        use_keychain: uses_keychain(profile),
+    })
+}
+
+/// Whether the profile's SSH entries get `UseKeychain yes`: only on macOS, whose ssh is the
+/// only one that knows the option.
+pub(crate) fn uses_keychain(profile: &Profile) -> bool {
+    profile.ssh_use_keychain && cfg!(target_os = "macos")
+}
+
+/// Every entry `profile` gets in the managed SSH block: the one for `ssh_key`, then one per
+/// `ssh_hosts` entry. The profile's match directories and connection sharing apply to all.
+pub(crate) fn ssh_entries(profile: &Profile) -> Vec<SshEntry> {
+    let match_profile = (!profile.ssh_match_dirs.is_empty()).then(|| profile.name.clone());
+    let control = profile
+        .ssh_control
+        .as_ref()
+        .map(|control| control.for_profile(&profile.name));
+    let extra = profile.ssh_hosts.iter().map(|entry| SshEntry {
+        host: entry.host.clone(),
+        hostname: entry.host.clone(),
+        identity_file: entry.key.clone(),
+        user: entry.user.clone(),
+        match_profile: match_profile.clone(),
+        control: control.clone(),
+        add_keys_to_agent: profile.ssh_add_keys_to_agent.clone(),
+        use_keychain: uses_keychain(profile),
+    });
+    ssh_entry(profile).into_iter().chain(extra).collect()
+}
+
+/// Sets user.name, user.email, user.signingkey, the line-ending settings and the credential
+/// helper for profile `name` at `scope`.
+pub fn apply_git_identity(config: &mut Config, name: &str, scope: GitConfigScope) -> Result<()> {
+    let profile_to_apply = config
+        .profiles
+        .get(name)
+        .cloned()
+        .ok_or_else(|| anyhow::anyhow!("Profile '{}' not found.", name.warning()))?;
+    let scope_str = format!("{:?}", scope).to_lowercase();
+
+    // In a repository, a host_signing entry for its remote replaces the profile's own key
+    let host_signing = match scope {
+        GitConfigScope::Local => remote_signing(config, &profile_to_apply)?,
+        GitConfigScope::Global => None,
+    };
+    let git_config = &profile_to_apply.git_config;
+    let mut changes = vec![
+        GitConfigChange::Set("user.name".to_string(), git_config.user_name.clone()),
+        GitConfigChange::Set("user.email".to_string(), git_config.user_email.clone()),
+    ];
+    let mut applied = vec![
+        format!("Set user.name to: {}", git_config.user_name.success()),
+        format!("Set user.email to: {}", git_config.user_email.success()),
+    ];
+    if let Some((host, signing)) = &host_signing {
+        let key = signing.git_key();
+        applied.push(format!(
+            "Set user.signingkey to: {} (host_signing for {})",
+            key.success(),
+            host
+        ));
+        changes.push(GitConfigChange::Set("user.signingkey".to_string(), key));
+    } else if let Some(signing_key) = &git_config.user_signingkey {
+        changes.push(GitConfigChange::Set(
+            "user.signingkey".to_string(),
+            signing_key.clone(),
+        ));
+        applied.push(format!("Set user.signingkey to: {}", signing_key.success()));
     } else {
         // If the profile doesn't have a signing key, unset any existing one at this scope
-        unset_git_config("user.signingkey", scope)
-            .with_context(|| format!("Failed to unset user.signingkey ({})", scope_str))?;
-        println!("  Unset user.signingkey (profile has no signing key specified).");
+        changes.push(GitConfigChange::Unset("user.signingkey".to_string()));
+        applied.push("Unset user.signingkey (profile has no signing key specified).".to_string());
     }
 
-    // TODO: Add logic for ssh_key and gpg_key if they influence git config directly (e.g. core.sshCommand, gpg.program)
-    // For now, they are informational or for other tools.
+    // Line-ending settings are only unset when the profile gitp applied before set them, so a
+    // hand-configured core.autocrlf survives switching to a profile without one.
+    let previous = get_git_config(PROFILE_MARKER_KEY, scope)?
+        .and_then(|previous| config.profiles.get(&previous))
+        .cloned();
+    for (key, value, previously_set) in [
+        (
+            "core.autocrlf",
+            git_config.core_autocrlf.map(|v| v.as_str()),
+            previous
+                .as_ref()
+                .is_some_and(|p| p.git_config.core_autocrlf.is_some()),
+        ),
+        (
+            "core.eol",
+            git_config.core_eol.map(|v| v.as_str()),
+            previous
+                .as_ref()
+                .is_some_and(|p| p.git_config.core_eol.is_some()),
+        ),
+    ] {
+        match value {
+            Some(value) => {
+                changes.push(GitConfigChange::Set(key.to_string(), value.to_string()));
+                applied.push(format!("Set {} to: {}", key, value.success()));
+            }
+            None if previously_set => {
+                changes.push(GitConfigChange::Unset(key.to_string()));
+                applied.push(format!("Unset {} (profile does not set it).", key));
+            }
+            None => {}
+        }
+    }
 
-    // Update SSH configuration for all profiles
-    println!("Updating SSH configuration based on all gitp profiles...");
-    let mut ssh_entries_for_config_update: Vec<(String, PathBuf, Option<String>)> = Vec::new();
-    for profile in config.profiles.values() {
-        if let (Some(key_path_str), Some(host_str)) = (&profile.ssh_key, &profile.ssh_key_host) {
-            ssh_entries_for_config_update.push((
-                host_str.clone(),
-                PathBuf::from(key_path_str),
-                None, // Use default SSH user (git)
+    let mut batch = ConfigBatch { changes, applied };
+    sendemail_changes(previous.as_ref(), &profile_to_apply, &mut batch);
+    custom_config_changes(previous.as_ref(), &profile_to_apply, scope, &mut batch)
+        .with_context(|| format!("Failed to read the applied custom_config ({})", scope_str))?;
+    signing_format_changes(
+        previous.as_ref(),
+        &profile_to_apply,
+        host_signing.as_ref().map(|(_, signing)| signing),
+        scope,
+        &mut batch,
+    )?;
+    batch.changes.push(GitConfigChange::Set(
+        PROFILE_MARKER_KEY.to_string(),
+        name.to_string(),
+    ));
+
+    // The profile lands as a whole: a failure leaves the previous one in place
+    batch.apply(scope).with_context(|| {
+        format!(
+            "Failed to apply the identity of profile '{}' ({})",
+            name, scope_str
+        )
+    })?;
+
+    configure_credential_helper(config, name, scope)
+        .with_context(|| format!("Failed to configure the credential helper ({})", scope_str))?;
+
+    for creds in &profile_to_apply.https_credentials {
+        for warning in verify::token_warnings(&config.settings, creds, Local::now().date_naive()) {
+            println!("  {}: {} ({})", "Warning".warning(), warning, creds.host);
+        }
+    }
+
+    Ok(())
+}
+
+/// The profile's `host_signing` entry for the current repository's remote, with the real host
+/// it matched (an SSH alias resolved to its host).
+pub(crate) fn remote_signing(
+    config: &Config,
+    profile: &Profile,
+) -> Result<Option<(String, HostSigning)>> {
+    if profile.host_signing.is_empty() {
+        return Ok(None);
+    }
+    let Some(url) = get_primary_remote()?.and_then(|r| parse_remote_url(&r.effective_url)) else {
+        return Ok(None);
+    };
+    let host = config.real_host(&url.host);
+    Ok(profile
+        .signing_for_host(host)
+        .map(|signing| (host.to_string(), signing.clone())))
+}
+
+/// Git config changes made at once by [`apply_git_config`], and the lines saying what they do,
+/// printed once they are made.
+#[derive(Debug, Default)]
+struct ConfigBatch {
+    changes: Vec<GitConfigChange>,
+    applied: Vec<String>,
+}
+
+impl ConfigBatch {
+    fn push(&mut self, change: GitConfigChange) {
+        self.changes.push(change);
+    }
+
+    fn report(&mut self, line: String) {
+        self.applied.push(line);
+    }
+
+    fn apply(self, scope: GitConfigScope) -> Result<()> {
+        apply_git_config(&self.changes, scope)?;
+        for line in &self.applied {
+            println!("  {}", line);
+        }
+        Ok(())
+    }
+}
+
+/// Sets gpg.format for a `host_signing` entry. Without one, the gpg.format a previous
+/// profile's entry may have left is unset, unless this profile's custom_config sets it.
+fn signing_format_changes(
+    previous: Option<&Profile>,
+    profile: &Profile,
+    signing: Option<&HostSigning>,
+    scope: GitConfigScope,
+    batch: &mut ConfigBatch,
+) -> Result<()> {
+    match signing {
+        Some(signing) => {
+            let format = signing.format.as_str();
+            batch.push(GitConfigChange::Set(
+                "gpg.format".to_string(),
+                format.to_string(),
             ));
+            batch.report(format!("Set gpg.format to: {}", format.success()));
+        }
+        None if previous.is_some_and(|p| !p.host_signing.is_empty())
+            && !profile
+                .custom_config
+                .keys()
+                .any(|key| key.eq_ignore_ascii_case("gpg.format"))
+            && get_git_config("gpg.format", scope)?.is_some() =>
+        {
+            batch.push(GitConfigChange::Unset("gpg.format".to_string()));
+            batch.report(
+                "Unset gpg.format (no host_signing entry for this repository).".to_string(),
+            );
         }
+        None => {}
     }
+    Ok(())
+}
 
-    ssh_config::update_ssh_config(&ssh_entries_for_config_update)
-        .context("Failed to update SSH configuration.")?;
-    println!("SSH configuration updated successfully.");
+/// Sets format.from and the sendemail settings of the profile. Keys the previously applied
+/// profile set and this one doesn't (including its identity block) are unset.
+fn sendemail_changes(previous: Option<&Profile>, profile: &Profile, batch: &mut ConfigBatch) {
+    let entries = profile
+        .sendemail
+        .as_ref()
+        .map(|s| s.git_entries())
+        .unwrap_or_default();
+    if let Some(previous) = previous.and_then(|p| p.sendemail.as_ref()) {
+        for (key, _) in previous.git_entries() {
+            if !entries.iter().any(|(k, _)| k.eq_ignore_ascii_case(&key)) {
+                batch.report(format!("Unset {} (profile does not set it).", key));
+                batch.push(GitConfigChange::Unset(key));
+            }
+        }
+    }
+    for (key, value) in entries {
+        batch.report(format!("Set {} to: {}", key, value.success()));
+        batch.push(GitConfigChange::Set(key, value));
+    }
+}
 
-    // Update current profile in gitp config
-    config.current_profile = Some(name.clone());
-    config
-        .save()
-        .context("Failed to save gitp configuration.")?;
+/// Applies the profile's `custom_config` in key order, and records what it set. What gitp set
+/// at the scope before for a key this profile sets differently or not at all is removed
+/// first: every value of a replacing key, only gitp's values of an additive one.
+fn custom_config_changes(
+    previous: Option<&Profile>,
+    profile: &Profile,
+    scope: GitConfigScope,
+    batch: &mut ConfigBatch,
+) -> Result<()> {
+    removed_config_changes(previous, Some(profile), scope, batch)?;
 
-    println!(
-        "Successfully set '{}' as the active Git profile for {} scope.",
-        name.green(),
-        scope_str
-    );
-    println!(
-        "gitp internal current profile also updated to '{}'.",
-        name.green()
-    );
+    let mut custom: Vec<_> = profile.custom_config.iter().collect();
+    custom.sort_by_key(|(key, _)| *key);
+    let mut applied = Vec::new();
+    for (key, value) in custom {
+        let shown = value
+            .values()
+            .iter()
+            .map(|value| mask_value(key, value))
+            .collect::<Vec<_>>()
+            .join(", ");
+        if value.is_additive() {
+            // Re-adding moves a value already there behind the scope's own values
+            for value in value.values() {
+                batch.push(GitConfigChange::UnsetValue(key.clone(), value.clone()));
+                batch.push(GitConfigChange::Add(key.clone(), value.clone()));
+                applied.push(AppliedConfig::Added(key.clone(), value.clone()));
+            }
+            batch.report(format!("Added to {}: {}", key, shown.success()));
+        } else {
+            batch.push(GitConfigChange::UnsetAll(key.clone()));
+            for value in value.values() {
+                batch.push(GitConfigChange::Add(key.clone(), value.clone()));
+            }
+            applied.push(AppliedConfig::Replaced(key.clone()));
+            batch.report(format!("Set {} to: {}", key, shown.success()));
+        }
+    }
 
+    batch.push(GitConfigChange::UnsetAll(APPLIED_CONFIG_KEY.to_string()));
+    for entry in &applied {
+        batch.push(GitConfigChange::Add(
+            APPLIED_CONFIG_KEY.to_string(),
+            entry.to_value(),
+        ));
+    }
     Ok(())
 }
+
+/// A `custom_config` entry gitp set at a scope, as recorded under [`APPLIED_CONFIG_KEY`].
+#[derive(Debug, Clone, PartialEq)]
+enum AppliedConfig {
+    /// A key whose values gitp replaced
+    Replaced(String),
+    /// A value gitp added to a key
+    Added(String, String),
+}
+
+impl AppliedConfig {
+    fn parse(value: &str) -> Self {
+        match value.split_once('\t') {
+            Some((key, value)) => AppliedConfig::Added(key.to_string(), value.to_string()),
+            None => AppliedConfig::Replaced(value.to_string()),
+        }
+    }
+
+    fn to_value(&self) -> String {
+        match self {
+            AppliedConfig::Replaced(key) => key.clone(),
+            AppliedConfig::Added(key, value) => format!("{}\t{}", key, value),
+        }
+    }
+
+    fn key(&self) -> &str {
+        match self {
+            AppliedConfig::Replaced(key) | AppliedConfig::Added(key, _) => key,
+        }
+    }
+}
+
+/// The `custom_config` entries gitp set at `scope`. Scopes applied before gitp recorded them
+/// fall back to the custom_config of `previous`, the profile applied there.
+fn applied_config(previous: Option<&Profile>, scope: GitConfigScope) -> Result<Vec<AppliedConfig>> {
+    // git lists keys with the section and variable names in lowercase
+    let recorded = get_git_config_regexp(
+        &format!("^{}$", regex_escape(&APPLIED_CONFIG_KEY.to_lowercase())),
+        scope,
+    )?;
+    if !recorded.is_empty() || get_git_config(PROFILE_MARKER_KEY, scope)?.is_none() {
+        return Ok(recorded
+            .iter()
+            .map(|(_, value)| AppliedConfig::parse(value))
+            .collect());
+    }
+    let Some(previous) = previous else {
+        return Ok(Vec::new());
+    };
+    let mut custom: Vec<_> = previous.custom_config.iter().collect();
+    custom.sort_by_key(|(key, _)| *key);
+    Ok(custom
+        .into_iter()
+        .flat_map(|(key, value)| {
+            if value.is_additive() {
+                value
+                    .values()
+                    .iter()
+                    .map(|v| AppliedConfig::Added(key.clone(), v.clone()))
+                    .collect()
+            } else {
+                vec![AppliedConfig::Replaced(key.clone())]
+            }
+        })
+        .collect())
+}
+
+/// Removes the `custom_config` entries gitp set at `scope` that `next` (the profile being
+/// applied, if any) doesn't set the same way, and forgets them.
+fn removed_config_changes(
+    previous: Option<&Profile>,
+    next: Option<&Profile>,
+    scope: GitConfigScope,
+    batch: &mut ConfigBatch,
+) -> Result<()> {
+    let applied = applied_config(previous, scope)?;
+    let next_value = |key: &str| next.and_then(|p| p.custom_config.get(key));
+    let mut reported: Vec<&str> = Vec::new();
+    for entry in &applied {
+        let kept = next_value(entry.key());
+        match entry {
+            // Re-applied keys replace their values anyway
+            AppliedConfig::Replaced(_) if kept.is_some_and(|v| !v.is_additive()) => {}
+            AppliedConfig::Replaced(key) => {
+                batch.push(GitConfigChange::UnsetAll(key.clone()));
+                if kept.is_none() && !reported.contains(&key.as_str()) {
+                    batch.report(format!("Unset {} (profile does not set it).", key));
+                    reported.push(key);
+                }
+            }
+            AppliedConfig::Added(key, value) => {
+                if kept.is_some_and(|v| v.values().contains(value)) {
+                    continue;
+                }
+                batch.push(GitConfigChange::UnsetValue(key.clone(), value.clone()));
+                if kept.is_none() && !reported.contains(&key.as_str()) {
+                    batch.report(format!(
+                        "Removed the previous profile's values from {}.",
+                        key
+                    ));
+                    reported.push(key);
+                }
+            }
+        }
+    }
+    if !applied.is_empty() {
+        batch.push(GitConfigChange::UnsetAll(APPLIED_CONFIG_KEY.to_string()));
+    }
+    Ok(())
+}
+
+/// Removes the identity gitp applies (user.name, user.email, user.signingkey, line-ending
+/// settings, custom_config, the profile marker and gitp's credential helpers) from `scope`.
+pub fn clear_git_identity(scope: GitConfigScope) -> Result<()> {
+    let mut batch = ConfigBatch::default();
+    removed_config_changes(None, None, scope, &mut batch)?;
+    for key in [
+        "user.name",
+        "user.email",
+        "user.signingkey",
+        "core.autocrlf",
+        "core.eol",
+        PROFILE_MARKER_KEY,
+    ] {
+        batch.push(GitConfigChange::Unset(key.to_string()));
+    }
+    batch.apply(scope)?;
+    helper::sync_helper_config("", &[], scope)?;
+    Ok(())
+}
+
+/// Points git's credential helper for each of the profile's HTTPS hosts at gitp, asking how to
+/// combine it with already configured helpers when `credential_helper_coexistence` is `ask`.
+/// With `credential_helper = "blanket"`, sets the blanket helper instead.
+fn configure_credential_helper(
+    config: &mut Config,
+    name: &str,
+    scope: GitConfigScope,
+) -> Result<()> {
+    let hosts: Vec<String> = config
+        .profiles
+        .get(name)
+        .map(|p| p.https_hosts().into_iter().map(str::to_string).collect())
+        .unwrap_or_default();
+    // Without the path, restricted_remotes can't tell which repository git asks for
+    for host in &hosts {
+        let restricted = config
+            .settings
+            .restricted_remotes
+            .iter()
+            .any(|rule| rule.covers_host(host));
+        if restricted {
+            if let Some(key) = helper::enable_http_path(host, scope)? {
+                println!("  Set {} for restricted_remotes", key.success());
+            }
+        }
+    }
+    if config.settings.credential_helper == CredentialHelperSetup::Blanket {
+        return configure_blanket_helper(&hosts, scope);
+    }
+    // Helpers not written by gitp, per host
+    let mut foreign = Vec::new();
+    for host in hosts {
+        let helpers = helper::foreign_helpers(&host)?;
+        foreign.push((host, helpers));
+    }
+
+    // Each host takes its `credential_helper_hosts` entry, else the machine-wide setting
+    let mut hosts = Vec::new();
+    for (host, helpers) in foreign {
+        let mode = match config.settings.host_helper_coexistence(&host) {
+            Some(mode) => mode,
+            None => ask_helper_coexistence(config, &host, &helpers)?,
+        };
+        hosts.push((host, helpers, mode));
+    }
+
+    let sync = helper::sync_helper_config(name, &hosts, scope)?;
+    for key in &sync.removed {
+        println!("  Removed gitp credential helper from: {}", key.warning());
+    }
+    for (key, (host, helpers, _)) in sync.configured.iter().zip(&hosts) {
+        println!("  Set {} to gitp's credential helper", key.success());
+        if !helpers.is_empty() {
+            print_helper_order(name, host, &helper::helper_order(host)?);
+        }
+    }
+
+    Ok(())
+}
+
+/// Adds `!gitp credential` to `credential.helper` at `scope` and removes the per-host entries of
+/// previously used profiles. Other helpers are left where they are, so the coexistence settings
+/// don't apply; the profile's hosts where one of them is asked first are reported.
+fn configure_blanket_helper(hosts: &[String], scope: GitConfigScope) -> Result<()> {
+    let sync = helper::sync_helper_config("", &[], scope)?;
+    for key in &sync.removed {
+        println!("  Removed gitp credential helper from: {}", key.warning());
+    }
+    if helper::add_blanket_helper(scope)? {
+        println!(
+            "  Set {} to gitp's credential helper",
+            "credential.helper".success()
+        );
+    }
+    for host in hosts {
+        let order = helper::helper_order(host)?;
+        if let Some(first) = order.first().filter(|e| !helper::is_gitp_helper(&e.value)) {
+            println!(
+                "  {}: '{}' ({}) is asked before gitp for {} and may answer with other \
+                 credentials. Set {} to \"per-host\" to let gitp manage the order.",
+                "Warning".warning(),
+                first.value,
+                first.origin.dimmed(),
+                host,
+                "credential_helper".accent()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// `credential_helper_coexistence`, asking when it is `ask` and other helpers serve `host`.
+/// The answer is saved for every host, or for `host` alone when it is about Git Credential
+/// Manager, which people usually keep for some hosts. Without a terminal, `ask` is `leave`.
+fn ask_helper_coexistence(
+    config: &mut Config,
+    host: &str,
+    helpers: &[GitConfigEntry],
+) -> Result<HelperCoexistence> {
+    let mode = config.settings.credential_helper_coexistence;
+    if mode != HelperCoexistence::Ask || helpers.is_empty() {
+        return Ok(mode);
+    }
+    if !can_prompt() {
+        return Ok(HelperCoexistence::Leave);
+    }
+    let mode = helper::ask_coexistence(host, helpers)?;
+    let saved = if helpers
+        .iter()
+        .any(|e| helper::is_credential_manager(&e.value))
+    {
+        config
+            .settings
+            .credential_helper_hosts
+            .insert(host.to_string(), mode);
+        format!(
+            "\"{}\" = \"{}\" under [settings.credential_helper_hosts]",
+            host,
+            coexistence_name(mode)
+        )
+    } else {
+        config.settings.credential_helper_coexistence = mode;
+        format!(
+            "credential_helper_coexistence = \"{}\"",
+            coexistence_name(mode)
+        )
+    };
+    println!("  Saved choice as {} in config.toml.", saved.accent());
+    Ok(mode)
+}
+
+fn print_helper_order(name: &str, host: &str, order: &[GitConfigEntry]) {
+    println!("  Git will ask these credential helpers in order:");
+    for (i, entry) in order.iter().enumerate() {
+        if helper::is_gitp_helper(&entry.value) {
+            println!("    {}. gitp (profile '{}')", i + 1, name.success());
+        } else {
+            println!(
+                "    {}. {} ({})",
+                i + 1,
+                entry.value.warning(),
+                entry.origin.dimmed()
+            );
+        }
+    }
+    match order.first().filter(|e| !helper::is_gitp_helper(&e.value)) {
+        Some(first) if helper::is_credential_manager(&first.value) => println!(
+            "  {}: Git Credential Manager ('{}') is asked before gitp, so it answers for {} with \
+             the account it signed in, or opens its own sign-in. Keep it away from this host \
+             with {} under [settings.credential_helper_hosts] in config.toml.",
+            "Warning".warning(),
+            first.value,
+            host,
+            format!("\"{}\" = \"replace\"", host).accent()
+        ),
+        Some(first) => println!(
+            "  {}: '{}' is asked before gitp and may answer with other credentials. Set {} to \"prepend\" or \"replace\" to change this.",
+            "Warning".warning(),
+            first.value,
+            "credential_helper_coexistence".accent()
+        ),
+        None => {}
+    }
+}
+
+fn coexistence_name(mode: HelperCoexistence) -> &'static str {
+    match mode {
+        HelperCoexistence::Ask => "ask",
+        HelperCoexistence::Prepend => "prepend",
+        HelperCoexistence::Replace => "replace",
+        HelperCoexistence::Leave => "leave",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{CustomValue, SshHost};
+    use crate::git::{add_git_config, set_git_config, unset_all_git_config, unset_git_config};
+
+    const REPLACED: &str = "gitp.test.replaced";
+    const DROPPED: &str = "gitp.test.dropped";
+    const ADDED: &str = "gitp.test.added";
+
+    fn profile(name: &str, custom: &[(&str, CustomValue)]) -> Profile {
+        let mut profile = Profile::new(
+            name.to_string(),
+            "John Doe".to_string(),
+            "john@example.com".to_string(),
+        );
+        for (key, value) in custom {
+            profile.custom_config.insert(key.to_string(), value.clone());
+        }
+        profile
+    }
+
+    #[test]
+    fn test_ssh_host_conflicts() {
+        let mut config = Config::default();
+        for name in ["home", "work"] {
+            let mut extra = profile(name, &[]);
+            extra.ssh_hosts.push(SshHost {
+                host: "GitLab.com".to_string(),
+                key: PathBuf::from(format!("~/.ssh/id_{}", name)),
+                user: None,
+            });
+            config.profiles.insert(name.to_string(), extra);
+        }
+        let err = check_ssh_host_conflicts(&config).unwrap_err().to_string();
+        assert!(err.contains("'home' and 'work'"), "{}", err);
+
+        // A directory-limited entry is a Match section of its own
+        let work = config.profiles.get_mut("work").unwrap();
+        work.ssh_match_dirs.push("~/work/".to_string());
+        assert!(check_ssh_host_conflicts(&config).is_ok());
+
+        // A main key under an alias doesn't clash with another profile's extra entry
+        let work = config.profiles.get_mut("work").unwrap();
+        work.ssh_match_dirs.clear();
+        work.ssh_hosts.clear();
+        work.ssh_key = Some(PathBuf::from("~/.ssh/id_work"));
+        work.ssh_key_host = Some("gitlab.com".to_string());
+        work.ssh_host_alias = Some("gitlab.com-work".to_string());
+        assert!(check_ssh_host_conflicts(&config).is_ok());
+        config.profiles.get_mut("work").unwrap().ssh_host_alias = None;
+        assert!(check_ssh_host_conflicts(&config).is_err());
+    }
+
+    fn add(values: &[&str]) -> CustomValue {
+        CustomValue::Add {
+            add: values.iter().map(|v| v.to_string()).collect(),
+        }
+    }
+
+    fn local_values(key: &str) -> Result<Vec<String>> {
+        Ok(get_git_config_regexp(
+            &format!("^{}$", regex_escape(&key.to_lowercase())),
+            GitConfigScope::Local,
+        )?
+        .into_iter()
+        .map(|(_, value)| value)
+        .collect())
+    }
+
+    fn cleanup() {
+        for key in [REPLACED, DROPPED, ADDED, APPLIED_CONFIG_KEY] {
+            let _ = unset_all_git_config(key, GitConfigScope::Local);
+        }
+    }
+
+    #[test]
+    fn test_applied_config_encoding() {
+        for entry in [
+            AppliedConfig::Replaced("url.git@github.com:.insteadOf".to_string()),
+            AppliedConfig::Added(
+                "credential.helper".to_string(),
+                "cache --timeout=3600".to_string(),
+            ),
+        ] {
+            assert_eq!(AppliedConfig::parse(&entry.to_value()), entry);
+        }
+        assert_eq!(
+            AppliedConfig::Added("a.b".to_string(), "c d".to_string()).to_value(),
+            "a.b\tc d"
+        );
+    }
+
+    fn apply_custom_config(previous: Option<&Profile>, profile: &Profile) -> Result<()> {
+        let mut batch = ConfigBatch::default();
+        custom_config_changes(previous, profile, GitConfigScope::Local, &mut batch)?;
+        batch.apply(GitConfigScope::Local)
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_switching_removes_previous_custom_config() -> Result<()> {
+        let local = GitConfigScope::Local;
+        cleanup();
+        let marker = get_git_config(PROFILE_MARKER_KEY, local)?;
+        // A value of the user's own, which gitp must keep
+        add_git_config(ADDED, "mine", local)?;
+
+        let a = profile(
+            "a",
+            &[
+                (REPLACED, CustomValue::One("a".to_string())),
+                (DROPPED, CustomValue::One("x".to_string())),
+                (ADDED, add(&["a1", "a2"])),
+            ],
+        );
+        let b = profile(
+            "b",
+            &[
+                (REPLACED, CustomValue::One("b".to_string())),
+                (ADDED, add(&["a2", "b1"])),
+            ],
+        );
+
+        apply_custom_config(None, &a)?;
+        assert_eq!(
+            local_values(APPLIED_CONFIG_KEY)?,
+            [
+                "gitp.test.added\ta1",
+                "gitp.test.added\ta2",
+                "gitp.test.dropped",
+                "gitp.test.replaced"
+            ]
+        );
+
+        // Keys b drops are unset, values it doesn't add are removed, the user's are kept
+        apply_custom_config(Some(&a), &b)?;
+        assert_eq!(local_values(REPLACED)?, ["b"]);
+        assert!(local_values(DROPPED)?.is_empty());
+        assert_eq!(local_values(ADDED)?, ["mine", "a2", "b1"]);
+
+        // Without a record, as left by older versions, the previous profile's custom_config
+        // says what to remove
+        unset_all_git_config(APPLIED_CONFIG_KEY, local)?;
+        set_git_config(PROFILE_MARKER_KEY, "b", local)?;
+        apply_custom_config(Some(&b), &a)?;
+        assert_eq!(local_values(REPLACED)?, ["a"]);
+        assert_eq!(local_values(DROPPED)?, ["x"]);
+        assert_eq!(local_values(ADDED)?, ["mine", "a1", "a2"]);
+
+        let mut batch = ConfigBatch::default();
+        removed_config_changes(Some(&a), None, local, &mut batch)?;
+        batch.apply(local)?;
+        assert!(local_values(REPLACED)?.is_empty());
+        assert!(local_values(DROPPED)?.is_empty());
+        assert_eq!(local_values(ADDED)?, ["mine"]);
+        assert!(local_values(APPLIED_CONFIG_KEY)?.is_empty());
+
+        cleanup();
+        match marker {
+            Some(marker) => set_git_config(PROFILE_MARKER_KEY, &marker, local)?,
+            None => unset_git_config(PROFILE_MARKER_KEY, local)?,
+        }
+        Ok(())
+    }
+}
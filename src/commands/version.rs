@@ -0,0 +1,40 @@
+use colored::Colorize;
+
+use crate::utils::output::Paint;
+
+/// Cargo features that change what a build can do, whether this build has them, and what they
+/// provide.
+const FEATURES: &[(&str, bool, &str)] = &[
+    (
+        "interactive",
+        cfg!(feature = "interactive"),
+        "prompts and progress spinners",
+    ),
+    (
+        "http",
+        cfg!(feature = "http"),
+        "token verification (gitp credentials verify)",
+    ),
+    (
+        "keyring",
+        cfg!(feature = "keyring"),
+        "platform keyring as a secret store",
+    ),
+    ("sqlite", cfg!(feature = "sqlite"), "SQLite storage backend"),
+];
+
+/// Prints the version; with `--verbose`, also which optional features were compiled in.
+pub fn execute(verbose: bool) {
+    println!("gitp {}", env!("CARGO_PKG_VERSION"));
+    if !verbose {
+        return;
+    }
+    println!("\n{}", "Features".bold());
+    for (name, enabled, description) in FEATURES {
+        if *enabled {
+            println!("  {} {:<12} {}", "+".success(), name, description);
+        } else {
+            println!("  {} {:<12} {}", "-".dimmed(), name, description.dimmed());
+        }
+    }
+}
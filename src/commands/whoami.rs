@@ -0,0 +1,160 @@
+// src/commands/whoami.rs
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use colored::Colorize;
+
+use crate::commands::list::describe_account;
+use crate::config::{is_host_pattern, profile_from_env, Config, HttpsCredentials, Profile};
+use crate::credentials::{resolve_token, verify};
+use crate::git::get_repo_root;
+use crate::utils::output::Paint;
+use crate::utils::progress::with_spinner;
+use crate::utils::time::format_time;
+
+/// Prints the provider account behind each of the profile's HTTPS tokens, as recorded by the
+/// last verification. With `refresh`, the providers are asked again and the answers recorded.
+pub fn execute(profile_name: Option<String>, refresh: bool) -> Result<()> {
+    let mut config = Config::load().context("Failed to load configuration.")?;
+    let name = match profile_name {
+        Some(name) => name,
+        None => {
+            let repo = get_repo_root().ok().flatten();
+            let repo = repo.as_ref().map(|r| r.to_string_lossy());
+            profile_from_env()
+                .or_else(|| {
+                    config
+                        .state
+                        .current_for(repo.as_deref())
+                        .map(str::to_string)
+                })
+                .context("No profile is in use. Pass a profile name.")?
+        }
+    };
+    let settings = config.settings.clone();
+    let Some(profile) = config.profiles.get_mut(&name) else {
+        bail!("Profile '{}' not found.", name.warning());
+    };
+
+    println!(
+        "Profile '{}' ({})",
+        name.accent(),
+        profile.git_config.user_email
+    );
+    if profile.https_credentials.is_empty() {
+        println!("  No HTTPS credentials; the account is recorded when a token is verified.");
+        return Ok(());
+    }
+    let now = Utc::now();
+    for creds in &mut profile.https_credentials {
+        if refresh && !is_host_pattern(&creds.host) {
+            let info = resolve_token(&settings, creds).and_then(|token| {
+                with_spinner(&format!("Contacting {}...", creds.host), || {
+                    verify::inspect_token(&settings, &creds.host, &token)
+                })
+            });
+            match info {
+                Ok(info) => info.record(creds, now),
+                Err(e) => eprintln!(
+                    "  {}: couldn't refresh {}: {:#}",
+                    "Warning".warning(),
+                    creds.host,
+                    e
+                ),
+            }
+        }
+        println!("  {}", account_line(creds));
+    }
+
+    if refresh {
+        config.save().context("Failed to save configuration.")?;
+    } else if has_unverified(profile) {
+        println!(
+            "Run '{}' to ask the providers.",
+            format!("gitp whoami {} --refresh", name).accent()
+        );
+    }
+    Ok(())
+}
+
+/// The host and the account its token belongs to, or the configured user until it's verified.
+fn account_line(creds: &HttpsCredentials) -> String {
+    let host = format!("{}:", creds.host);
+    match &creds.account {
+        Some(account) => format!(
+            "{} {}, verified {}",
+            host.accent(),
+            describe_account(account).success(),
+            format_time(&account.verified_at)
+        ),
+        None => format!(
+            "{} {} {}",
+            host.accent(),
+            creds.username,
+            "(not verified yet)".dimmed()
+        ),
+    }
+}
+
+/// Whether a token of the profile was never verified. Wildcard hosts have no provider to ask.
+fn has_unverified(profile: &Profile) -> bool {
+    profile
+        .https_credentials
+        .iter()
+        .any(|creds| creds.account.is_none() && !is_host_pattern(&creds.host))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{CredentialType, ProviderAccount};
+
+    fn creds(host: &str) -> HttpsCredentials {
+        HttpsCredentials::new(
+            host.to_string(),
+            "octocat".to_string(),
+            CredentialType::KeychainRef("octocat".to_string()),
+        )
+    }
+
+    fn account() -> ProviderAccount {
+        ProviderAccount {
+            login: "octo-work".to_string(),
+            id: Some(42),
+            name: Some("Octo Cat".to_string()),
+            avatar_url: None,
+            verified_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_account_line() {
+        let mut creds = creds("github.com");
+        let line = account_line(&creds);
+        assert!(line.contains("github.com:"), "{}", line);
+        assert!(line.contains("octocat"), "{}", line);
+        assert!(line.contains("(not verified yet)"), "{}", line);
+
+        // The verified account is shown, not the configured username
+        creds.account = Some(account());
+        let line = account_line(&creds);
+        assert!(line.contains("octo-work (Octo Cat, #42)"), "{}", line);
+        assert!(line.contains(", verified "), "{}", line);
+        assert!(!line.contains("octocat"), "{}", line);
+    }
+
+    #[test]
+    fn test_has_unverified() {
+        let mut profile = Profile::new(
+            "work".to_string(),
+            "John Doe".to_string(),
+            "john@company.com".to_string(),
+        );
+        assert!(!has_unverified(&profile));
+        profile.https_credentials.push(creds("*.corp.example.com"));
+        assert!(!has_unverified(&profile));
+        profile.https_credentials.push(creds("github.com"));
+        assert!(has_unverified(&profile));
+        profile.https_credentials[1].account = Some(account());
+        assert!(!has_unverified(&profile));
+    }
+}
@@ -0,0 +1,357 @@
+// src/config/audit.rs
+// Append-only log of profile changes, shown by `gitp changelog`
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+use super::storage::get_config_dir;
+use super::Profile;
+use crate::utils::mask::is_secret_key;
+
+const CHANGELOG_FILE_NAME: &str = "changelog.jsonl";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Created,
+    Edited,
+    Renamed,
+    Removed,
+}
+
+impl Action {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Action::Created => "created",
+            Action::Edited => "edited",
+            Action::Renamed => "renamed",
+            Action::Removed => "removed",
+        }
+    }
+}
+
+/// One field of a profile that changed; `None` when the field was unset.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub field: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub old: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChangeEntry {
+    pub at: DateTime<Utc>,
+    /// Who ran gitp; `alice (as root)` under sudo
+    pub user: String,
+    pub action: Action,
+    pub profile: String,
+    /// Previous name of a renamed profile
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub renamed_from: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub changes: Vec<FieldChange>,
+}
+
+impl ChangeEntry {
+    /// Whether the entry concerns `name`, under its current or previous name.
+    pub fn concerns(&self, name: &str) -> bool {
+        self.profile == name || self.renamed_from.as_deref() == Some(name)
+    }
+}
+
+pub fn changelog_path() -> Result<PathBuf> {
+    Ok(get_config_dir()?.join(CHANGELOG_FILE_NAME))
+}
+
+/// Appends an entry for every profile that differs between `before` and `after`.
+pub fn record_changes(
+    before: &HashMap<String, Profile>,
+    after: &HashMap<String, Profile>,
+) -> Result<()> {
+    let entries = diff_profiles(before, after, Utc::now(), &invoking_user());
+    if entries.is_empty() {
+        return Ok(());
+    }
+    let path = changelog_path()?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut lines = String::new();
+    for entry in &entries {
+        lines.push_str(&serde_json::to_string(entry)?);
+        lines.push('\n');
+    }
+    // A single write, so concurrent gitp processes don't interleave their lines
+    file.write_all(lines.as_bytes())
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Reads the log, oldest entry first. Lines that don't parse are skipped.
+pub fn read_changelog() -> Result<Vec<ChangeEntry>> {
+    let path = changelog_path()?;
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+fn invoking_user() -> String {
+    let var = |name| std::env::var(name).ok().filter(|v: &String| !v.is_empty());
+    let user = var("USER")
+        .or_else(|| var("LOGNAME"))
+        .or_else(|| var("USERNAME"))
+        .unwrap_or_else(|| "unknown".to_string());
+    match var("SUDO_USER") {
+        Some(sudo_user) if sudo_user != user => format!("{} (as {})", sudo_user, user),
+        _ => user,
+    }
+}
+
+fn diff_profiles(
+    before: &HashMap<String, Profile>,
+    after: &HashMap<String, Profile>,
+    at: DateTime<Utc>,
+    user: &str,
+) -> Vec<ChangeEntry> {
+    let entry = |action, profile: &str, renamed_from: Option<&str>, changes| ChangeEntry {
+        at,
+        user: user.to_string(),
+        action,
+        profile: profile.to_string(),
+        renamed_from: renamed_from.map(str::to_string),
+        changes,
+    };
+    let mut removed: Vec<&String> = before.keys().filter(|n| !after.contains_key(*n)).collect();
+    let mut created: Vec<&String> = after.keys().filter(|n| !before.contains_key(*n)).collect();
+    removed.sort();
+    created.sort();
+
+    let mut entries = Vec::new();
+    // A profile that disappears while an identical one appears under another name was renamed
+    created.retain(|new_name| {
+        let renamed = removed.iter().position(|old_name| {
+            let mut old = before[*old_name].clone();
+            old.name = after[*new_name].name.clone();
+            old == after[*new_name]
+        });
+        match renamed {
+            Some(i) => {
+                let old_name = removed.remove(i);
+                entries.push(entry(Action::Renamed, new_name, Some(old_name), Vec::new()));
+                false
+            }
+            None => true,
+        }
+    });
+
+    for name in created {
+        let changes = field_changes(None, Some(&after[name]));
+        entries.push(entry(Action::Created, name, None, changes));
+    }
+    let mut kept: Vec<&String> = after.keys().filter(|n| before.contains_key(*n)).collect();
+    kept.sort();
+    for name in kept {
+        let changes = field_changes(Some(&before[name]), Some(&after[name]));
+        if !changes.is_empty() {
+            entries.push(entry(Action::Edited, name, None, changes));
+        }
+    }
+    for name in removed {
+        entries.push(entry(Action::Removed, name, None, Vec::new()));
+    }
+    entries
+}
+
+fn field_changes(before: Option<&Profile>, after: Option<&Profile>) -> Vec<FieldChange> {
+    let (old, new) = (flatten(before), flatten(after));
+    let mut fields: Vec<&String> = old.keys().chain(new.keys()).collect();
+    fields.sort();
+    fields.dedup();
+    fields
+        .into_iter()
+        .filter(|field| old.get(*field) != new.get(*field))
+        .map(|field| FieldChange {
+            field: field.clone(),
+            old: old.get(field).cloned(),
+            new: new.get(field).cloned(),
+        })
+        .collect()
+}
+
+/// The profile as dotted field paths (`git_config.email`) to display values, without the
+/// name and with secrets hashed.
+fn flatten(profile: Option<&Profile>) -> BTreeMap<String, String> {
+    let mut fields = BTreeMap::new();
+    if let Some(mut value) = profile.and_then(|p| serde_json::to_value(p).ok()) {
+        // HTTPS credentials are told apart by host: `https_credentials.github.com.username`
+        if let Some(creds) = value.get_mut("https_credentials") {
+            let by_host = creds
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|c| Some((c.get("host")?.as_str()?.to_string(), c.clone())))
+                .collect();
+            *creds = Value::Object(by_host);
+        }
+        flatten_into(&value, String::new(), &mut fields);
+    }
+    // Entries carry the name already, and a rename is its own action
+    fields.remove("name");
+    // A token's value is the secret; the account name of a keyring reference is not
+    let tokens: Vec<String> = fields
+        .iter()
+        .filter(|(field, value)| {
+            field.starts_with("https_credentials.")
+                && field.ends_with(".credential_type.type")
+                && *value == "Token"
+        })
+        .map(|(field, _)| field.replace(".credential_type.type", ".credential_type.value"))
+        .collect();
+    for field in tokens {
+        if let Some(value) = fields.get_mut(&field) {
+            *value = hash_secret(value);
+        }
+    }
+    fields
+}
+
+fn flatten_into(value: &Value, path: String, fields: &mut BTreeMap<String, String>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                flatten_into(value, path, fields);
+            }
+        }
+        Value::Null => {}
+        Value::String(s) => {
+            let value = if is_secret_field(&path) {
+                hash_secret(s)
+            } else {
+                s.clone()
+            };
+            fields.insert(path, value);
+        }
+        // Multi-valued custom_config keys
+        Value::Array(_) if is_secret_field(&path) => {
+            fields.insert(path, hash_secret(&value.to_string()));
+        }
+        other => {
+            fields.insert(path, other.to_string());
+        }
+    }
+}
+
+/// Whether `path` is a free-form key (custom git config or an extra tool's setting) named like
+/// a secret. The token of the HTTPS credentials is handled in [`flatten`].
+pub(crate) fn is_secret_field(path: &str) -> bool {
+    let Some(key) = path
+        .strip_prefix("custom_config.")
+        .or_else(|| path.strip_prefix("extras."))
+    else {
+        return false;
+    };
+    is_secret_key(key)
+}
+
+/// Enough of a SHA-256 to tell whether a secret changed, without recording it.
+fn hash_secret(secret: &str) -> String {
+    let digest = Sha256::digest(secret.as_bytes());
+    let hex: String = digest[..6].iter().map(|b| format!("{:02x}", b)).collect();
+    format!("sha256:{}", hex)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{CredentialType, HttpsCredentials};
+
+    fn profile(name: &str, email: &str) -> Profile {
+        Profile::new(name.to_string(), "John Doe".to_string(), email.to_string())
+    }
+
+    fn profiles(list: &[Profile]) -> HashMap<String, Profile> {
+        list.iter().map(|p| (p.name.clone(), p.clone())).collect()
+    }
+
+    #[test]
+    fn test_diff_profiles() {
+        let work = profile("work", "john@company.com");
+        let home = profile("home", "john@home.com");
+        let before = profiles(&[work.clone(), home.clone()]);
+
+        let mut edited = work.clone();
+        edited.git_config.user_signingkey = Some("ABCD1234".to_string());
+        edited.https_credentials = vec![HttpsCredentials::new(
+            "github.com".to_string(),
+            "john".to_string(),
+            CredentialType::Token("ghp_secret".to_string()),
+        )];
+        let mut renamed = home.clone();
+        renamed.name = "personal".to_string();
+        let client = profile("client", "jd@client.com");
+        let after = profiles(&[edited, renamed, client]);
+
+        let entries = diff_profiles(&before, &after, Utc::now(), "alice");
+        let summary: Vec<(Action, &str, Option<&str>)> = entries
+            .iter()
+            .map(|e| (e.action, e.profile.as_str(), e.renamed_from.as_deref()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (Action::Renamed, "personal", Some("home")),
+                (Action::Created, "client", None),
+                (Action::Edited, "work", None),
+            ]
+        );
+
+        let changes = &entries[2].changes;
+        assert!(changes.contains(&FieldChange {
+            field: "git_config.user_signingkey".to_string(),
+            old: None,
+            new: Some("ABCD1234".to_string()),
+        }));
+        let token = changes
+            .iter()
+            .find(|c| c.field == "https_credentials.github.com.credential_type.value")
+            .unwrap();
+        assert_eq!(token.new, Some(hash_secret("ghp_secret")));
+        assert!(!serde_json::to_string(&entries)
+            .unwrap()
+            .contains("ghp_secret"));
+
+        let entries = diff_profiles(&after, &profiles(&[]), Utc::now(), "alice");
+        assert!(entries.iter().all(|e| e.action == Action::Removed));
+        assert_eq!(entries.len(), 3);
+    }
+
+    #[test]
+    fn test_is_secret_field() {
+        assert!(is_secret_field("extras.npm._authToken"));
+        assert!(is_secret_field("custom_config.http.password"));
+        assert!(is_secret_field("custom_config.http.extraHeaderToken.add"));
+        assert!(!is_secret_field("git_config.email"));
+        assert!(!is_secret_field("https_credentials.token_expires_at"));
+    }
+}
@@ -0,0 +1,227 @@
+// src/config/cache.rs
+// Pre-parsed copy of the configuration for commands that run many times a minute
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use super::storage::{self, ConfigStore};
+use super::system::{self, SystemProfile};
+use super::{Config, CredentialType, Profile, Settings, State};
+use crate::utils::{timing, write_private_file};
+
+const CACHE_FILE_NAME: &str = "config-cache.json";
+
+/// Size and modification time of a file the cache was built from; `None` when it didn't exist.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct SourceStamp {
+    path: PathBuf,
+    stamp: Option<(u128, u64)>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedConfig {
+    /// gitp version that wrote the cache; any other version rebuilds it
+    version: String,
+    sources: Vec<SourceStamp>,
+    profiles: HashMap<String, Profile>,
+    settings: Settings,
+    state: State,
+    system_profiles: HashMap<String, SystemProfile>,
+}
+
+impl Config {
+    /// Loads the configuration for read-only hot paths (the credential helper, session
+    /// exports, startup). A JSON copy is reused while the store's files keep their size and
+    /// modification time, so TOML is only parsed after a change. Don't save what this returns:
+    /// use [`Config::load`] for commands that modify the configuration.
+    pub fn load_cached() -> Result<Self> {
+        timing::measure("config load", || {
+            let store = storage::open_store()?;
+            let Some(cache_path) = cache_path() else {
+                return Self::load_from(store.as_ref());
+            };
+            // Stamped before loading: a write in between leaves an old stamp on new data, which
+            // only costs a rebuild next time
+            let sources = stamp_sources(store.as_ref());
+            if let Some(config) = read_cache(&cache_path, &sources) {
+                return Ok(config);
+            }
+
+            let config = Self::load_from(store.as_ref())?;
+            // Best-effort: without a cache every call simply parses the store
+            let persistent = dirs::runtime_dir().is_none();
+            let _ = write_cache(&cache_path, sources, &config, persistent);
+            Ok(config)
+        })
+    }
+}
+
+/// The per-user runtime directory (tmpfs, cleared on logout) when there is one, since the
+/// cache can hold plaintext tokens; the cache directory otherwise, where it is only written
+/// while the configuration holds none.
+pub fn cache_path() -> Option<PathBuf> {
+    dirs::runtime_dir()
+        .or_else(dirs::cache_dir)
+        .map(|dir| dir.join("gitp").join(CACHE_FILE_NAME))
+}
+
+/// Stamps the store's files and the system store: its directory changes when a profile is
+/// added or removed, its files when one is edited.
+fn stamp_sources(store: &dyn ConfigStore) -> Vec<SourceStamp> {
+    let system = system::system_profiles_dir()
+        .map(|dir| {
+            let mut paths = system::profile_files(&dir);
+            paths.insert(0, dir);
+            paths
+        })
+        .unwrap_or_default();
+    store
+        .paths()
+        .into_iter()
+        .chain(system)
+        .map(|path| {
+            let stamp = fs::metadata(&path).ok().and_then(|metadata| {
+                let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+                Some((modified.as_nanos(), metadata.len()))
+            });
+            SourceStamp { path, stamp }
+        })
+        .collect()
+}
+
+fn read_cache(path: &Path, sources: &[SourceStamp]) -> Option<Config> {
+    let content = fs::read(path).ok()?;
+    let cached: CachedConfig = serde_json::from_slice(&content).ok()?;
+    if cached.version != env!("CARGO_PKG_VERSION") || cached.sources != sources {
+        return None;
+    }
+    Some(Config {
+        profiles: cached.profiles,
+        settings: cached.settings,
+        state: cached.state,
+        system_profiles: cached.system_profiles,
+    })
+}
+
+/// Whether a profile keeps a token or SMTP password as plain text in the configuration.
+fn holds_plaintext_secrets(config: &Config) -> bool {
+    config.profiles.values().any(|profile| {
+        let token = profile
+            .https_credentials
+            .iter()
+            .any(|creds| matches!(creds.credential_type, CredentialType::Token(_)));
+        let smtp_pass = profile
+            .sendemail
+            .as_ref()
+            .is_some_and(|s| matches!(s.smtp_pass, Some(CredentialType::Token(_))));
+        token || smtp_pass
+    })
+}
+
+/// Writes the cache. A `persistent` cache (outside the runtime directory) isn't written, and an
+/// old one is removed, while the configuration holds plaintext secrets: they'd outlive the
+/// session on disk.
+fn write_cache(
+    path: &Path,
+    sources: Vec<SourceStamp>,
+    config: &Config,
+    persistent: bool,
+) -> Result<()> {
+    if persistent && holds_plaintext_secrets(config) {
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        return Ok(());
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let cached = CachedConfig {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        sources,
+        profiles: config.profiles.clone(),
+        settings: config.settings.clone(),
+        state: config.state.clone(),
+        system_profiles: config.system_profiles.clone(),
+    };
+    write_private_file(path, &serde_json::to_vec(&cached)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::storage::TomlStore;
+    use crate::config::HttpsCredentials;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_cache_follows_source_changes() -> Result<()> {
+        let dir = tempdir()?;
+        let store = TomlStore::new(
+            dir.path().join("config.toml"),
+            dir.path().join("state.toml"),
+        );
+        let cache = dir.path().join(CACHE_FILE_NAME);
+
+        let mut config = Config::default();
+        config.profiles.insert(
+            "work".to_string(),
+            Profile::new(
+                "work".to_string(),
+                "John Doe".to_string(),
+                "john@company.com".to_string(),
+            ),
+        );
+        config.save_to(&store)?;
+        let sources = stamp_sources(&store);
+        write_cache(&cache, sources.clone(), &config, false)?;
+        assert_eq!(read_cache(&cache, &sources), Some(config.clone()));
+
+        // Saving another profile changes the file's size, so the cache no longer applies
+        config.profiles.insert(
+            "home".to_string(),
+            Profile::new(
+                "home".to_string(),
+                "John Doe".to_string(),
+                "john@home.com".to_string(),
+            ),
+        );
+        config.save_to(&store)?;
+        assert_eq!(read_cache(&cache, &stamp_sources(&store)), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_persistent_cache_skips_plaintext_secrets() -> Result<()> {
+        let dir = tempdir()?;
+        let cache = dir.path().join(CACHE_FILE_NAME);
+        let mut profile = Profile::new(
+            "work".to_string(),
+            "John Doe".to_string(),
+            "john@company.com".to_string(),
+        );
+        let mut config = Config::default();
+        config.profiles.insert("work".to_string(), profile.clone());
+
+        write_cache(&cache, Vec::new(), &config, true)?;
+        assert!(cache.exists());
+
+        profile.https_credentials.push(HttpsCredentials::new(
+            "github.com".to_string(),
+            "octocat".to_string(),
+            CredentialType::Token("ghp_secret".to_string()),
+        ));
+        config.profiles.insert("work".to_string(), profile);
+        write_cache(&cache, Vec::new(), &config, true)?;
+        assert!(!cache.exists());
+
+        // In the runtime directory the tokens may be copied
+        write_cache(&cache, Vec::new(), &config, false)?;
+        assert_eq!(read_cache(&cache, &[]), Some(config));
+        Ok(())
+    }
+}
@@ -0,0 +1,250 @@
+// src/config/git_keys.rs
+// Catalog of git config keys for checking `custom_config` entries
+
+use std::collections::HashMap;
+
+use super::CustomValue;
+use crate::utils::glob_match;
+
+/// What a git config key accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyType {
+    /// `true`/`false`, `yes`/`no`, `on`/`off` or `1`/`0`
+    Bool,
+    /// An integer, optionally with a `k`, `m` or `g` suffix
+    Int,
+    /// A file or directory; `~/` is expanded by git
+    Path,
+    /// Free text (commands, URLs, names)
+    Text,
+    /// One of a fixed set of words
+    Enum(&'static [&'static str]),
+    /// A boolean or one of the given words (e.g. `pull.rebase = merges`)
+    BoolOr(&'static [&'static str]),
+}
+
+/// Known keys, lowercase, with `*` standing for a subsection (`url.<base>.insteadof`).
+const CATALOG: &[(&str, KeyType)] = &[
+    ("color.ui", KeyType::BoolOr(&["auto", "always", "never"])),
+    ("commit.gpgsign", KeyType::Bool),
+    ("commit.template", KeyType::Path),
+    ("core.autocrlf", KeyType::BoolOr(&["input"])),
+    ("core.compression", KeyType::Int),
+    ("core.editor", KeyType::Text),
+    ("core.eol", KeyType::Enum(&["lf", "crlf", "native"])),
+    ("core.excludesfile", KeyType::Path),
+    ("core.filemode", KeyType::Bool),
+    ("core.fsmonitor", KeyType::BoolOr(&[])),
+    ("core.hookspath", KeyType::Path),
+    ("core.ignorecase", KeyType::Bool),
+    ("core.pager", KeyType::Text),
+    ("core.sshcommand", KeyType::Text),
+    ("credential.*.helper", KeyType::Text),
+    ("credential.*.username", KeyType::Text),
+    ("credential.helper", KeyType::Text),
+    ("credential.usehttppath", KeyType::Bool),
+    (
+        "diff.algorithm",
+        KeyType::Enum(&["myers", "minimal", "patience", "histogram"]),
+    ),
+    ("fetch.prune", KeyType::Bool),
+    ("format.from", KeyType::Text),
+    ("format.signoff", KeyType::Bool),
+    ("gc.auto", KeyType::Int),
+    ("gpg.format", KeyType::Enum(&["openpgp", "x509", "ssh"])),
+    ("gpg.program", KeyType::Path),
+    ("gpg.ssh.allowedsignersfile", KeyType::Path),
+    ("gpg.ssh.program", KeyType::Path),
+    ("help.autocorrect", KeyType::Text),
+    ("http.*.extraheader", KeyType::Text),
+    ("http.postbuffer", KeyType::Int),
+    ("http.proxy", KeyType::Text),
+    ("http.sslverify", KeyType::Bool),
+    ("includeif.*.path", KeyType::Path),
+    ("include.path", KeyType::Path),
+    ("init.defaultbranch", KeyType::Text),
+    (
+        "merge.conflictstyle",
+        KeyType::Enum(&["merge", "diff3", "zdiff3"]),
+    ),
+    ("merge.ff", KeyType::BoolOr(&["only"])),
+    ("pull.ff", KeyType::BoolOr(&["only"])),
+    (
+        "pull.rebase",
+        KeyType::BoolOr(&["merges", "interactive", "m", "i"]),
+    ),
+    (
+        "push.default",
+        KeyType::Enum(&[
+            "nothing", "current", "upstream", "tracking", "simple", "matching",
+        ]),
+    ),
+    ("push.autosetupremote", KeyType::Bool),
+    ("push.gpgsign", KeyType::BoolOr(&["if-asked"])),
+    ("rebase.autosquash", KeyType::Bool),
+    ("rebase.autostash", KeyType::Bool),
+    ("rebase.updaterefs", KeyType::Bool),
+    ("rerere.enabled", KeyType::Bool),
+    (
+        "sendemail.confirm",
+        KeyType::Enum(&["always", "never", "cc", "compose", "auto"]),
+    ),
+    ("sendemail.identity", KeyType::Text),
+    ("sendemail.smtpencryption", KeyType::Enum(&["tls", "ssl"])),
+    ("sendemail.smtpserver", KeyType::Text),
+    ("sendemail.smtpserverport", KeyType::Int),
+    ("sendemail.smtpuser", KeyType::Text),
+    ("sendemail.*.smtpencryption", KeyType::Enum(&["tls", "ssl"])),
+    ("sendemail.*.smtpserver", KeyType::Text),
+    ("sendemail.*.smtpserverport", KeyType::Int),
+    ("sendemail.*.smtpuser", KeyType::Text),
+    ("sendemail.suppresscc", KeyType::Text),
+    ("sendemail.to", KeyType::Text),
+    ("tag.gpgsign", KeyType::Bool),
+    ("url.*.insteadof", KeyType::Text),
+    ("url.*.pushinsteadof", KeyType::Text),
+    ("user.signingkey", KeyType::Text),
+];
+
+/// A problem with one `custom_config` entry.
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyFinding {
+    /// Not in the catalog: maybe a typo, maybe a key gitp doesn't know
+    Unknown { key: String },
+    /// A value the key can't take
+    Invalid {
+        key: String,
+        value: String,
+        expected: String,
+    },
+}
+
+impl KeyFinding {
+    pub fn is_invalid(&self) -> bool {
+        matches!(self, KeyFinding::Invalid { .. })
+    }
+}
+
+impl std::fmt::Display for KeyFinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyFinding::Unknown { key } => write!(f, "'{}' is not a git key gitp knows", key),
+            KeyFinding::Invalid {
+                key,
+                value,
+                expected,
+            } => write!(f, "'{}' can't be '{}' (expected {})", key, value, expected),
+        }
+    }
+}
+
+/// The type of `key`, if it is in the catalog. Section and key names are case-insensitive in
+/// git, so the comparison is too.
+pub fn key_type(key: &str) -> Option<KeyType> {
+    let key = key.to_lowercase();
+    CATALOG
+        .iter()
+        .find(|(pattern, _)| glob_match(pattern, &key))
+        .map(|(_, key_type)| *key_type)
+}
+
+/// Checks every entry of a profile's `custom_config`, sorted by key.
+pub fn check_custom_config(custom_config: &HashMap<String, CustomValue>) -> Vec<KeyFinding> {
+    let mut keys: Vec<&String> = custom_config.keys().collect();
+    keys.sort();
+    let mut findings = Vec::new();
+    for key in keys {
+        findings.extend(check_entry(key, custom_config[key].values()));
+    }
+    findings
+}
+
+/// Checks the values of one key.
+pub fn check_entry(key: &str, values: &[String]) -> Vec<KeyFinding> {
+    let Some(key_type) = key_type(key) else {
+        return vec![KeyFinding::Unknown {
+            key: key.to_string(),
+        }];
+    };
+    values
+        .iter()
+        .filter_map(|value| {
+            check_value(key_type, value)
+                .err()
+                .map(|expected| KeyFinding::Invalid {
+                    key: key.to_string(),
+                    value: value.clone(),
+                    expected,
+                })
+        })
+        .collect()
+}
+
+/// `Err` with a description of what was expected when `value` doesn't fit `key_type`.
+fn check_value(key_type: KeyType, value: &str) -> Result<(), String> {
+    let lower = value.trim().to_lowercase();
+    let ok = match key_type {
+        KeyType::Bool => is_bool(&lower),
+        KeyType::Int => is_int(&lower),
+        KeyType::Path => !lower.is_empty(),
+        KeyType::Text => true,
+        KeyType::Enum(words) => words.contains(&lower.as_str()),
+        KeyType::BoolOr(words) => is_bool(&lower) || words.contains(&lower.as_str()),
+    };
+    if ok {
+        return Ok(());
+    }
+    Err(match key_type {
+        KeyType::Bool => "true or false".to_string(),
+        KeyType::Int => "an integer".to_string(),
+        KeyType::Path => "a path".to_string(),
+        KeyType::Text => unreachable!("any text is accepted"),
+        KeyType::Enum(words) => words.join(", "),
+        KeyType::BoolOr(words) => {
+            let mut accepted = vec!["true", "false"];
+            accepted.extend(words.iter());
+            accepted.join(", ")
+        }
+    })
+}
+
+fn is_bool(value: &str) -> bool {
+    matches!(
+        value,
+        "true" | "false" | "yes" | "no" | "on" | "off" | "1" | "0"
+    )
+}
+
+fn is_int(value: &str) -> bool {
+    let digits = value.strip_prefix('-').unwrap_or(value);
+    let digits = digits.strip_suffix(['k', 'm', 'g']).unwrap_or(digits);
+    !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_entry() {
+        let check = |key: &str, value: &str| check_entry(key, &[value.to_string()]);
+        assert!(check("pull.rebase", "merges").is_empty());
+        assert!(check("Pull.Rebase", "Yes").is_empty());
+        assert!(check("pull.rebase", "yess")[0].is_invalid());
+        assert!(check("commit.gpgsign", "1").is_empty());
+        assert!(check("http.postBuffer", "512m").is_empty());
+        assert!(check("http.postBuffer", "lots")[0].is_invalid());
+        assert!(check("core.compression", "-1").is_empty());
+        assert!(check("url.git@github.com-work:.insteadOf", "https://github.com/").is_empty());
+        assert!(check("includeIf.gitdir:~/work/.path", "")
+            .first()
+            .unwrap()
+            .is_invalid());
+        assert_eq!(
+            check("commit.gpgsing", "true"),
+            [KeyFinding::Unknown {
+                key: "commit.gpgsing".to_string()
+            }]
+        );
+    }
+}
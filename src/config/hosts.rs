@@ -0,0 +1,475 @@
+// src/config/hosts.rs
+// Looking profiles up by the host a remote or credential request names
+
+use std::collections::HashMap;
+
+use anyhow::{bail, Context, Result};
+
+use super::{Config, HostSigning, HttpsCredentials, Profile};
+
+/// How a profile is tied to a host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HostMatch {
+    /// The profile's SSH host alias (e.g. `github.com-work`)
+    SshAlias,
+    /// The real host of one of the profile's SSH keys (e.g. `github.com`)
+    SshHost,
+    /// The host of the profile's HTTPS credentials
+    Https,
+}
+
+impl HostMatch {
+    pub fn is_ssh(&self) -> bool {
+        matches!(self, HostMatch::SshAlias | HostMatch::SshHost)
+    }
+}
+
+/// A profile found for a host, and why.
+#[derive(Debug, Clone, Copy)]
+pub struct ProfileMatch<'a> {
+    pub profile: &'a Profile,
+    pub via: HostMatch,
+}
+
+/// A commit email that belongs to a profile for other hosts than the repository's remote, while
+/// another profile is tied to the remote's host (a work email in an open-source repository).
+#[derive(Debug, Clone, Copy)]
+pub struct EmailHostMismatch<'a> {
+    /// Profile the email belongs to
+    pub owner: &'a Profile,
+    /// Profile tied to the remote's host, the one probably wanted
+    pub suggested: &'a Profile,
+}
+
+/// Canonical form of a host for comparisons: trimmed, lowercase, without a scheme, user,
+/// path or trailing dot (`GitHub.com.` and `https://git@github.com/` are `github.com`). A port
+/// is kept, since `host:8443` is a different server.
+pub fn normalize_host(host: &str) -> String {
+    let host = host.trim();
+    let host = host.split_once("://").map_or(host, |(_, rest)| rest);
+    let host = host.split('/').next().unwrap_or_default();
+    let host = host.rsplit('@').next().unwrap_or_default();
+    let (name, port) = match host.rsplit_once(':') {
+        Some((name, port)) if !port.is_empty() && port.bytes().all(|b| b.is_ascii_digit()) => {
+            (name, Some(port))
+        }
+        _ => (host, None),
+    };
+    let name = name.trim_end_matches('.').to_lowercase();
+    match port {
+        Some(port) => format!("{}:{}", name, port),
+        None => name,
+    }
+}
+
+/// Whether two hosts name the same server after normalization.
+pub fn same_host(a: &str, b: &str) -> bool {
+    normalize_host(a) == normalize_host(b)
+}
+
+/// Whether `host` is a wildcard pattern such as `*.corp.example.com` rather than one server.
+pub fn is_host_pattern(host: &str) -> bool {
+    normalize_host(host).starts_with("*.")
+}
+
+/// Whether a profile host covers `host`. A `*.corp.example.com` pattern matches the hosts
+/// one label below the domain (`git.corp.example.com`, but neither `corp.example.com` nor
+/// `a.b.corp.example.com`), as git's URL matching does; a port must be the same on both.
+/// Anything else must be the same host.
+pub fn host_matches(pattern: &str, host: &str) -> bool {
+    let (pattern, host) = (normalize_host(pattern), normalize_host(host));
+    match pattern.strip_prefix("*.") {
+        Some(domain) => host.split_once('.').is_some_and(|(label, rest)| {
+            !label.is_empty() && !label.contains('*') && rest == domain
+        }),
+        None => pattern == host,
+    }
+}
+
+/// Whether `pattern` is a wildcard gitp accepts: `*.` followed by a domain of at least two
+/// labels, with no other wildcard.
+pub fn valid_host_pattern(pattern: &str) -> bool {
+    let pattern = normalize_host(pattern);
+    pattern.strip_prefix("*.").is_some_and(|domain| {
+        let name = domain.split(':').next().unwrap_or_default();
+        name.contains('.') && !name.contains('*') && name.split('.').all(|label| !label.is_empty())
+    })
+}
+
+/// Profiles keyed by normalized host, built once for commands that look up many hosts.
+pub struct HostIndex<'a> {
+    hosts: HashMap<String, Vec<ProfileMatch<'a>>>,
+    /// Profiles tied to a `*.domain` pattern, with the normalized pattern
+    patterns: Vec<(String, ProfileMatch<'a>)>,
+}
+
+impl<'a> HostIndex<'a> {
+    pub fn new(config: &'a Config) -> Self {
+        let mut hosts: HashMap<String, Vec<ProfileMatch<'a>>> = HashMap::new();
+        let mut patterns = Vec::new();
+        for profile in config.profiles.values() {
+            let mut add = |host: &str, via| {
+                let found = ProfileMatch { profile, via };
+                if is_host_pattern(host) {
+                    patterns.push((normalize_host(host), found));
+                } else {
+                    hosts.entry(normalize_host(host)).or_default().push(found);
+                }
+            };
+            if let Some(alias) = &profile.ssh_host_alias {
+                add(alias, HostMatch::SshAlias);
+            }
+            if let Some(host) = &profile.ssh_key_host {
+                add(host, HostMatch::SshHost);
+            }
+            for entry in &profile.ssh_hosts {
+                add(&entry.host, HostMatch::SshHost);
+            }
+            for creds in &profile.https_credentials {
+                add(&creds.host, HostMatch::Https);
+            }
+        }
+        // Aliases first since they pick a single profile, then by name for stable output
+        for matches in hosts.values_mut() {
+            matches.sort_by(|a, b| (a.via, &a.profile.name).cmp(&(b.via, &b.profile.name)));
+        }
+        patterns.sort_by(|(_, a), (_, b)| (a.via, &a.profile.name).cmp(&(b.via, &b.profile.name)));
+        Self { hosts, patterns }
+    }
+
+    /// Profiles tied to `host`. Profiles naming the host itself come before those matching it
+    /// through a `*.domain` pattern; within each, aliases first, then by name. Several
+    /// profiles can share a real host (two GitHub accounts); callers narrow them down by
+    /// transport or user.
+    pub fn lookup(&self, host: &str) -> Vec<ProfileMatch<'a>> {
+        let mut found = self
+            .hosts
+            .get(&normalize_host(host))
+            .cloned()
+            .unwrap_or_default();
+        found.extend(
+            self.patterns
+                .iter()
+                .filter(|(pattern, _)| host_matches(pattern, host))
+                .map(|(_, m)| *m),
+        );
+        found
+    }
+}
+
+impl Config {
+    /// Index of the profiles by host; see [`HostIndex::lookup`].
+    pub fn host_index(&self) -> HostIndex<'_> {
+        HostIndex::new(self)
+    }
+
+    /// Profiles tied to `host` through an SSH alias, SSH host or HTTPS credentials.
+    pub fn find_profiles_for_host(&self, host: &str) -> Vec<ProfileMatch<'_>> {
+        self.host_index().lookup(host)
+    }
+
+    /// The real host behind `host` when it is a profile's SSH alias (`github.com-work` is
+    /// `github.com`), otherwise `host` itself.
+    pub fn real_host<'h>(&'h self, host: &'h str) -> &'h str {
+        self.profiles
+            .values()
+            .find(|p| {
+                p.ssh_host_alias
+                    .as_deref()
+                    .is_some_and(|a| same_host(a, host))
+            })
+            .and_then(|p| p.ssh_key_host.as_deref())
+            .unwrap_or(host)
+    }
+
+    /// Checks whether committing as `email` to a remote on `remote_host` uses the wrong
+    /// profile. `None` unless the email's profile is tied to hosts, none of them `remote_host`,
+    /// and some profile with another email is tied to `remote_host`.
+    pub fn email_host_mismatch(
+        &self,
+        email: &str,
+        remote_host: &str,
+    ) -> Option<EmailHostMismatch<'_>> {
+        let index = self.host_index();
+        let for_host = index.lookup(remote_host);
+        let mut owners: Vec<&Profile> = self
+            .profiles
+            .values()
+            .filter(|p| p.git_config.user_email.eq_ignore_ascii_case(email))
+            .collect();
+        owners.sort_by(|a, b| a.name.cmp(&b.name));
+        // Two accounts can share an email; any of them being right for the host is enough
+        if owners
+            .iter()
+            .any(|owner| for_host.iter().any(|m| m.profile.name == owner.name))
+        {
+            return None;
+        }
+        let owner = *owners.iter().find(|p| !p.hosts().is_empty())?;
+        let suggested = for_host
+            .iter()
+            .find(|m| !m.profile.git_config.user_email.eq_ignore_ascii_case(email))?
+            .profile;
+        Some(EmailHostMismatch { owner, suggested })
+    }
+}
+
+impl Profile {
+    /// Hosts the profile is tied to: its SSH alias, SSH hosts and HTTPS hosts.
+    pub fn hosts(&self) -> Vec<&str> {
+        let mut hosts = Vec::new();
+        hosts.extend(self.ssh_host_alias.as_deref());
+        hosts.extend(self.ssh_key_host.as_deref());
+        hosts.extend(self.ssh_hosts.iter().map(|entry| entry.host.as_str()));
+        hosts.extend(self.https_credentials.iter().map(|c| c.host.as_str()));
+        hosts
+    }
+
+    /// The HTTPS credentials for `host`. An entry naming the host wins over `*.domain`
+    /// patterns, as for [`Profile::signing_for_host`].
+    pub fn https_for(&self, host: &str) -> Option<&HttpsCredentials> {
+        let creds = &self.https_credentials;
+        creds
+            .iter()
+            .find(|c| !is_host_pattern(&c.host) && same_host(&c.host, host))
+            .or_else(|| {
+                creds
+                    .iter()
+                    .find(|c| is_host_pattern(&c.host) && host_matches(&c.host, host))
+            })
+    }
+
+    /// The profile's HTTPS credentials for the host named by `host`, or its only ones when
+    /// `host` is `None`. Fails when that is ambiguous or there are none.
+    pub fn select_https(&self, host: Option<&str>) -> Result<&HttpsCredentials> {
+        match (host, self.https_credentials.as_slice()) {
+            (_, []) => bail!("Profile '{}' has no HTTPS credentials.", self.name),
+            (None, [creds]) => Ok(creds),
+            (None, _) => bail!(
+                "Profile '{}' has HTTPS credentials for {}; pick one with --host.",
+                self.name,
+                self.https_hosts().join(", ")
+            ),
+            (Some(host), _) => self
+                .https_credentials
+                .iter()
+                .find(|c| same_host(&c.host, host))
+                .with_context(|| {
+                    format!(
+                        "Profile '{}' has no HTTPS credentials for {} (it has {}).",
+                        self.name,
+                        host,
+                        self.https_hosts().join(", ")
+                    )
+                }),
+        }
+    }
+
+    /// Hosts of the profile's HTTPS credentials, in config order.
+    pub fn https_hosts(&self) -> Vec<&str> {
+        self.https_credentials
+            .iter()
+            .map(|c| c.host.as_str())
+            .collect()
+    }
+
+    /// The `host_signing` entry for a remote on `host`. An entry naming the host wins over
+    /// `*.domain` patterns, as in [`HostIndex::lookup`]; otherwise the first in config order.
+    pub fn signing_for_host(&self, host: &str) -> Option<&HostSigning> {
+        self.host_signing
+            .iter()
+            .find(|s| !is_host_pattern(&s.host) && same_host(&s.host, host))
+            .or_else(|| {
+                self.host_signing
+                    .iter()
+                    .find(|s| is_host_pattern(&s.host) && host_matches(&s.host, host))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{CredentialType, SigningFormat};
+
+    fn profile(name: &str) -> Profile {
+        Profile::new(
+            name.to_string(),
+            "John Doe".to_string(),
+            format!("john@{}.com", name),
+        )
+    }
+
+    fn https(host: &str, username: &str) -> Vec<HttpsCredentials> {
+        vec![HttpsCredentials::new(
+            host.to_string(),
+            username.to_string(),
+            CredentialType::Token(String::new()),
+        )]
+    }
+
+    #[test]
+    fn test_normalize_host() {
+        assert_eq!(normalize_host(" GitHub.com. "), "github.com");
+        assert_eq!(normalize_host("https://git@github.com/acme"), "github.com");
+        assert_eq!(normalize_host("git.acme.com:8443"), "git.acme.com:8443");
+        assert_eq!(normalize_host("Git.Acme.com.:8443"), "git.acme.com:8443");
+        assert!(same_host("github.com", "GITHUB.COM."));
+        assert!(!same_host("git.acme.com", "git.acme.com:8443"));
+    }
+
+    #[test]
+    fn test_find_profiles_for_host() {
+        let mut config = Config::default();
+        let mut work = profile("work");
+        work.ssh_key_host = Some("github.com".to_string());
+        work.ssh_host_alias = Some("github.com-work".to_string());
+        work.https_credentials = https("GitHub.com", "john-work");
+        let mut personal = profile("personal");
+        personal.ssh_key_host = Some("github.com".to_string());
+        personal.https_credentials = https("github.com.", "john");
+        let mut client = profile("client");
+        client.https_credentials = https("git.client.com:8443", "jd");
+        for p in [work, personal, client] {
+            config.profiles.insert(p.name.clone(), p);
+        }
+
+        let found = |host: &str| -> Vec<(String, HostMatch)> {
+            config
+                .find_profiles_for_host(host)
+                .iter()
+                .map(|m| (m.profile.name.clone(), m.via))
+                .collect()
+        };
+        // The alias is unambiguous
+        assert_eq!(
+            found("GitHub.com-Work"),
+            [("work".to_string(), HostMatch::SshAlias)]
+        );
+        // The real host is shared: both accounts, SSH before HTTPS, by name
+        assert_eq!(
+            found("github.com"),
+            [
+                ("personal".to_string(), HostMatch::SshHost),
+                ("work".to_string(), HostMatch::SshHost),
+                ("personal".to_string(), HostMatch::Https),
+                ("work".to_string(), HostMatch::Https),
+            ]
+        );
+        assert_eq!(
+            found("git.client.com:8443"),
+            [("client".to_string(), HostMatch::Https)]
+        );
+        assert!(found("git.client.com").is_empty());
+        assert!(found("gitlab.com").is_empty());
+    }
+
+    #[test]
+    fn test_host_patterns() {
+        assert!(host_matches("*.corp.example.com", "Git.Corp.Example.com."));
+        assert!(!host_matches("*.corp.example.com", "corp.example.com"));
+        assert!(!host_matches("*.corp.example.com", "a.b.corp.example.com"));
+        assert!(host_matches("*.corp.com:8443", "git.corp.com:8443"));
+        assert!(!host_matches("*.corp.com:8443", "git.corp.com"));
+        assert!(host_matches("github.com", "GitHub.com"));
+        assert!(valid_host_pattern("*.corp.example.com"));
+        for invalid in ["*.com", "*corp.com", "git.*.corp.com", "*.*.corp.com", "*"] {
+            assert!(!valid_host_pattern(invalid), "{}", invalid);
+        }
+
+        let mut config = Config::default();
+        let mut corp = profile("corp");
+        corp.ssh_key_host = Some("*.corp.example.com".to_string());
+        corp.https_credentials = https("*.corp.example.com", "jdoe");
+        let mut infra = profile("infra");
+        infra.https_credentials = https("git.corp.example.com", "jdoe-infra");
+        for p in [corp, infra] {
+            config.profiles.insert(p.name.clone(), p);
+        }
+        let found = |host: &str| -> Vec<(String, HostMatch)> {
+            config
+                .find_profiles_for_host(host)
+                .iter()
+                .map(|m| (m.profile.name.clone(), m.via))
+                .collect()
+        };
+        // The profile naming the host wins over the pattern, whatever the transport
+        assert_eq!(
+            found("git.corp.example.com"),
+            [
+                ("infra".to_string(), HostMatch::Https),
+                ("corp".to_string(), HostMatch::SshHost),
+                ("corp".to_string(), HostMatch::Https),
+            ]
+        );
+        assert_eq!(found("ci.corp.example.com").len(), 2);
+        assert!(found("corp.example.com").is_empty());
+    }
+
+    #[test]
+    fn test_email_host_mismatch() {
+        let mut config = Config::default();
+        let mut work = profile("work");
+        work.ssh_key_host = Some("github.acme.com".to_string());
+        let mut personal = profile("personal");
+        personal.ssh_key_host = Some("github.com".to_string());
+        let unbound = profile("unbound");
+        for p in [work, personal, unbound] {
+            config.profiles.insert(p.name.clone(), p);
+        }
+
+        let mismatch = config
+            .email_host_mismatch("John@Work.com", "github.com")
+            .unwrap();
+        assert_eq!(mismatch.owner.name, "work");
+        assert_eq!(mismatch.suggested.name, "personal");
+        assert!(config
+            .email_host_mismatch("john@work.com", "github.acme.com")
+            .is_none());
+        assert!(config
+            .email_host_mismatch("john@personal.com", "github.com")
+            .is_none());
+        // No hosts to contradict, or no profile for the remote's host
+        assert!(config
+            .email_host_mismatch("john@unbound.com", "github.com")
+            .is_none());
+        assert!(config
+            .email_host_mismatch("john@work.com", "gitlab.com")
+            .is_none());
+    }
+
+    #[test]
+    fn test_signing_for_host() {
+        let signing = |host: &str, format, key: &str| HostSigning {
+            host: host.to_string(),
+            format,
+            key: key.to_string(),
+        };
+        let mut config = Config::default();
+        let mut work = profile("work");
+        work.ssh_key_host = Some("github.com".to_string());
+        work.ssh_host_alias = Some("github.com-work".to_string());
+        work.host_signing = vec![
+            signing("*.corp.example.com", SigningFormat::Openpgp, "ABCD1234"),
+            signing(
+                "review.corp.example.com",
+                SigningFormat::Openpgp,
+                "EF015678",
+            ),
+            signing("GitHub.com", SigningFormat::Ssh, "~/.ssh/id_work.pub"),
+        ];
+        config.profiles.insert(work.name.clone(), work);
+        let work = &config.profiles["work"];
+
+        let key = |host: &str| {
+            work.signing_for_host(config.real_host(host))
+                .map(|s| s.key.as_str())
+        };
+        assert_eq!(key("github.com-work"), Some("~/.ssh/id_work.pub"));
+        assert_eq!(key("github.com"), Some("~/.ssh/id_work.pub"));
+        assert_eq!(key("review.corp.example.com"), Some("EF015678"));
+        assert_eq!(key("git.corp.example.com"), Some("ABCD1234"));
+        assert_eq!(key("gitlab.com"), None);
+        assert_eq!(config.real_host("gitlab.com"), "gitlab.com");
+    }
+}
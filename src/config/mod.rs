@@ -1,39 +1,260 @@
+pub mod audit;
+pub mod cache;
+pub mod git_keys;
+pub mod hosts;
 pub mod profile;
+pub mod restricted;
+pub mod settings;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod state;
 pub mod storage; // Added storage module
+pub mod system;
+pub use hosts::*;
 pub use profile::*;
+pub use restricted::*;
+pub use settings::*;
+pub use state::*;
 
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
 use serde::{Deserialize, Serialize}; // Added Serialize, Deserialize
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use crate::utils::{glob_match, is_glob, timing};
+
+/// Environment variable that forces a profile for the current shell session, without touching
+/// git config or gitp state.
+pub const PROFILE_ENV_VAR: &str = "GITP_PROFILE";
+
+/// Profile named by `GITP_PROFILE`, if it is set and not empty.
+pub fn profile_from_env() -> Option<String> {
+    non_empty_profile(std::env::var(PROFILE_ENV_VAR).ok())
+}
+
+fn non_empty_profile(value: Option<String>) -> Option<String> {
+    value
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
 
 // The main Config struct that the rest of the application will use.
 // It mirrors storage::ConfigStorage but is the canonical one for the app.
 #[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
 pub struct Config {
     pub profiles: HashMap<String, Profile>,
-    pub current_profile: Option<String>,
+    #[serde(default, skip_serializing_if = "Settings::is_default")]
+    pub settings: Settings,
+    /// Runtime state, stored in state.toml rather than config.toml
+    #[serde(skip)]
+    pub state: State,
+    /// Profiles merged in from the system store, as read; they are never saved
+    #[serde(skip)]
+    pub system_profiles: HashMap<String, system::SystemProfile>,
 }
 
 impl Config {
-    /// Loads the configuration from the storage backend.
+    /// Loads the configuration and runtime state from the storage backend.
     pub fn load() -> Result<Self> {
-        let storage_config = storage::load_config_from_storage()?;
+        let config = timing::measure("config load", || {
+            Self::load_from(storage::open_store()?.as_ref())
+        })?;
+        config.warn_shadowed_system_profiles();
+        Ok(config)
+    }
+
+    /// Saves the current configuration to the storage backend.
+    pub fn save(&self) -> Result<()> {
+        let store = storage::open_store()?;
+        let before = store.load_config().map(|c| c.profiles).unwrap_or_default();
+        timing::measure("config save", || self.save_to(store.as_ref()))?;
+        // The change is saved either way; only its changelog entry is lost
+        if let Err(e) = audit::record_changes(&before, &self.user_profiles()?) {
+            eprintln!(
+                "Warning: failed to record the change in the changelog: {:#}",
+                e
+            );
+        }
+        Ok(())
+    }
+
+    /// Saves after a switch that is already applied to git config. With a read-only
+    /// configuration the switch is only not recorded, which is said, and `false` is returned.
+    pub fn save_switch(&self) -> Result<bool> {
+        if let Some(path) = storage::read_only_path()? {
+            println!(
+                "{}: {} is read-only, so gitp doesn't record this profile as the current one.",
+                "Note".dimmed(),
+                path.display()
+            );
+            return Ok(false);
+        }
+        self.save().context("Failed to save gitp configuration.")?;
+        Ok(true)
+    }
+
+    /// Loads the configuration and runtime state from `store`.
+    pub fn load_from(store: &dyn storage::ConfigStore) -> Result<Self> {
+        let storage_config = store.load_config()?;
+        // Until state.toml exists, seed it from the fields older versions kept in config.toml
+        let state = match store.load_state()? {
+            Some(state) => state,
+            None => State {
+                current_profile: storage_config.current_profile,
+                repos: storage_config.repos,
+                ..Default::default()
+            },
+        };
         // Convert from storage::ConfigStorage to config::Config
         // This is a direct mapping if structs are identical, otherwise map fields.
-        Ok(Self {
+        let mut config = Self {
             profiles: storage_config.profiles,
-            current_profile: storage_config.current_profile,
-        })
+            settings: storage_config.settings,
+            state,
+            ..Default::default()
+        };
+        config.merge_system_profiles(system::system_profiles());
+        Ok(config)
     }
 
-    /// Saves the current configuration to the storage backend.
-    pub fn save(&self) -> Result<()> {
+    /// Adds system profiles beneath the user's own: a user profile with the same name wins.
+    pub fn merge_system_profiles(&mut self, system: &BTreeMap<String, system::SystemProfile>) {
+        for (name, system) in system {
+            if !self.profiles.contains_key(name) {
+                self.profiles.insert(name.clone(), system.profile.clone());
+                self.system_profiles.insert(name.clone(), system.clone());
+            }
+        }
+    }
+
+    /// Warns about system profiles hidden by a user profile of the same name. Only loads for
+    /// commands do this; the hot paths of [`Config::load_cached`] stay quiet.
+    fn warn_shadowed_system_profiles(&self) {
+        for (name, system) in system::system_profiles() {
+            if !self.system_profiles.contains_key(name) {
+                eprintln!(
+                    "Warning: your profile '{}' hides the system profile of that name in {}.",
+                    name,
+                    system.path.display()
+                );
+            }
+        }
+    }
+
+    /// The system file `name` comes from, when it is a system profile.
+    pub fn system_profile_path(&self, name: &str) -> Option<&Path> {
+        self.system_profiles.get(name).map(|s| s.path.as_path())
+    }
+
+    /// Fails when `name` is a system profile, which `action` can't change.
+    pub fn require_user_profile(&self, name: &str, action: &str) -> Result<()> {
+        if let Some(path) = self.system_profile_path(name) {
+            bail!(
+                "Can't {} '{}': it is a system profile provided by {}, which gitp doesn't change.",
+                action,
+                name,
+                path.display()
+            );
+        }
+        Ok(())
+    }
+
+    /// The profiles to save: the user's own, without the unchanged system profiles. Fails when
+    /// a system profile was changed, renamed or removed.
+    fn user_profiles(&self) -> Result<HashMap<String, Profile>> {
+        for (name, system) in &self.system_profiles {
+            if self.profiles.get(name) != Some(&system.profile) {
+                self.require_user_profile(name, "change")?;
+            }
+        }
+        Ok(self
+            .profiles
+            .iter()
+            .filter(|(name, _)| !self.system_profiles.contains_key(*name))
+            .map(|(name, profile)| (name.clone(), profile.clone()))
+            .collect())
+    }
+
+    /// Names of the profiles selected by `patterns`, each a profile name or a glob such as
+    /// `work-*`, in the order given (globs expand alphabetically) and without duplicates. Fails
+    /// on a name that doesn't exist or a glob that matches nothing.
+    pub fn select_profiles(&self, patterns: &[String]) -> Result<Vec<String>> {
+        let mut selected: Vec<String> = Vec::new();
+        for pattern in patterns {
+            let names = if is_glob(pattern) {
+                let mut names: Vec<String> = self
+                    .profiles
+                    .keys()
+                    .filter(|name| glob_match(pattern, name))
+                    .cloned()
+                    .collect();
+                if names.is_empty() {
+                    bail!("No profile matches '{}'.", pattern);
+                }
+                names.sort();
+                names
+            } else if self.profiles.contains_key(pattern) {
+                vec![pattern.clone()]
+            } else {
+                bail!(
+                    "Profile '{}' not found. Use 'gitp list' to list available profiles.",
+                    pattern
+                );
+            };
+            for name in names {
+                if !selected.contains(&name) {
+                    selected.push(name);
+                }
+            }
+        }
+        Ok(selected)
+    }
+
+    /// Profiles with an SSH key and host whose Host entries gitp manages in ~/.ssh/config, by
+    /// name: all of them except those with `ssh_managed = false` and those matching
+    /// `settings.ssh_unmanaged_profiles`.
+    pub fn ssh_managed_profiles(&self) -> Vec<&Profile> {
+        let mut profiles: Vec<&Profile> = self
+            .profiles
+            .values()
+            .filter(|p| {
+                (p.ssh_key.is_some() && p.ssh_key_host.is_some()) || !p.ssh_hosts.is_empty()
+            })
+            .filter(|p| self.manages_ssh(p))
+            .collect();
+        profiles.sort_by(|a, b| a.name.cmp(&b.name));
+        profiles
+    }
+
+    /// Whether `profile` may have a Host entry in the managed SSH block.
+    pub fn manages_ssh(&self, profile: &Profile) -> bool {
+        profile.ssh_managed
+            && !self
+                .settings
+                .ssh_unmanaged_profiles
+                .iter()
+                .any(|pattern| glob_match(pattern, &profile.name))
+    }
+
+    /// Saves the configuration and runtime state to `store`.
+    pub fn save_to(&self, store: &dyn storage::ConfigStore) -> Result<()> {
         // Convert from config::Config to storage::ConfigStorage for saving
-        let storage_config = storage::ConfigStorage {
-            profiles: self.profiles.clone(), // Clone data for the storage struct
-            current_profile: self.current_profile.clone(),
+        let mut storage_config = storage::ConfigStorage {
+            profiles: self.user_profiles()?,
+            settings: self.settings.clone(),
+            ..Default::default()
         };
-        storage::save_config_to_storage(&storage_config)
+        // Paths under the home directory are stored as `~/...`, so a synced config works for
+        // other users and machines
+        for profile in storage_config.profiles.values_mut() {
+            profile.contract_paths();
+        }
+        storage_config.settings.contract_paths();
+        // Write state first: once it exists, the legacy fields dropped from config.toml are
+        // no longer needed
+        store.save_state(&self.state)?;
+        store.save_config(&storage_config)
     }
 }
 
@@ -41,6 +262,113 @@ impl Config {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_non_empty_profile() {
+        assert_eq!(
+            non_empty_profile(Some(" acme ".to_string())).as_deref(),
+            Some("acme")
+        );
+        assert_eq!(non_empty_profile(Some("  ".to_string())), None);
+        assert_eq!(non_empty_profile(None), None);
+    }
+
+    #[test]
+    fn test_select_profiles() {
+        let mut config = Config::default();
+        for name in ["work-acme", "work-globex", "personal"] {
+            let profile = Profile::new(
+                name.to_string(),
+                "John Doe".to_string(),
+                "john@example.com".to_string(),
+            );
+            config.profiles.insert(name.to_string(), profile);
+        }
+        let patterns = |p: &[&str]| p.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            config
+                .select_profiles(&patterns(&["personal", "work-*", "work-acme"]))
+                .unwrap(),
+            ["personal", "work-acme", "work-globex"]
+        );
+        assert!(config.select_profiles(&patterns(&["client-*"])).is_err());
+        assert!(config.select_profiles(&patterns(&["work"])).is_err());
+    }
+
+    #[test]
+    fn test_ssh_managed_profiles() {
+        let mut config = Config::default();
+        for name in ["work", "oss-rust", "oss-go", "agent", "no-key"] {
+            let mut profile = Profile::new(
+                name.to_string(),
+                "John Doe".to_string(),
+                "john@example.com".to_string(),
+            );
+            if name != "no-key" {
+                profile.ssh_key = Some(format!("~/.ssh/id_{}", name).into());
+                profile.ssh_key_host = Some("github.com".to_string());
+            }
+            config.profiles.insert(name.to_string(), profile);
+        }
+        config.profiles.get_mut("agent").unwrap().ssh_managed = false;
+        config.settings.ssh_unmanaged_profiles = vec!["oss-*".to_string()];
+
+        let names: Vec<&str> = config
+            .ssh_managed_profiles()
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect();
+        assert_eq!(names, ["work"]);
+
+        // Only the exception is written out
+        let toml = toml::to_string(&config.profiles["agent"]).unwrap();
+        assert!(toml.contains("ssh_managed = false"));
+        assert!(!toml::to_string(&config.profiles["work"])
+            .unwrap()
+            .contains("ssh_managed"));
+    }
+
+    #[test]
+    fn test_system_profiles() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let system_dir = dir.path().join("profiles.d");
+        std::fs::create_dir(&system_dir)?;
+        for (name, email) in [("acme", "bot@acme.com"), ("work", "old@company.com")] {
+            let profile = Profile::new(name.to_string(), "Acme".to_string(), email.to_string());
+            std::fs::write(
+                system_dir.join(format!("{}.toml", name)),
+                toml::to_string(&profile)?,
+            )?;
+        }
+        let store = storage::TomlStore::new(
+            dir.path().join("config.toml"),
+            dir.path().join("state.toml"),
+        );
+
+        let mut config = Config::default();
+        let work = Profile::new(
+            "work".to_string(),
+            "John Doe".to_string(),
+            "john@company.com".to_string(),
+        );
+        config.profiles.insert("work".to_string(), work.clone());
+        config.merge_system_profiles(&system::load_system_profiles(&system_dir));
+        // The user's own profile wins over the system one
+        assert_eq!(config.profiles["work"], work);
+        assert!(config.system_profile_path("acme").is_some());
+        assert!(config.system_profile_path("work").is_none());
+
+        // System profiles aren't saved with the user's, and can't be changed
+        config.save_to(&store)?;
+        assert!(!storage::ConfigStore::load_config(&store)?
+            .profiles
+            .contains_key("acme"));
+        config.profiles.remove("acme");
+        assert!(config.save_to(&store).is_err());
+        assert!(config.require_user_profile("work", "edit").is_ok());
+        Ok(())
+    }
+
     #[test]
     fn test_config_load_save_cycle() {
         // This test requires a way to mock or control the storage backend.
@@ -51,13 +379,13 @@ mod tests {
         // Example: Create a default config, save it, load it, and check equality.
         // This would implicitly test the storage functions if they weren't mocked.
         let original_config = Config::default();
-        
+
         // To test properly, we'd need to ensure `storage::save_config_to_storage` and
         // `storage::load_config_from_storage` operate on a temporary, controlled environment.
         // The tests in `storage.rs` are better suited for direct file interaction testing.
-        
+
         // For now, let's just assert that a default config can be created.
         assert_eq!(original_config.profiles.len(), 0);
-        assert!(original_config.current_profile.is_none());
+        assert!(original_config.state.current_profile.is_none());
     }
 }
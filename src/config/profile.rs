@@ -1,7 +1,11 @@
+use chrono::{DateTime, NaiveDate, Utc};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use super::hosts::{is_host_pattern, same_host, valid_host_pattern};
+use crate::utils::{contract_home, expand_home};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Profile {
@@ -19,17 +23,127 @@ pub struct Profile {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub ssh_key_host: Option<String>,
 
+    /// SHA256 fingerprint of `ssh_key` when gitp generated or set it, so `gitp doctor` can
+    /// tell when the file at the path was replaced by another key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh_key_fingerprint: Option<String>,
+
+    /// SSH host alias for the key (e.g., github.com-work), for when several profiles use keys
+    /// on the same host. Remotes use the alias in place of the host name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh_host_alias: Option<String>,
+
+    /// Login user in the SSH entry; `git` when unset, which every common provider uses
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh_user: Option<String>,
+
+    /// Whether gitp writes a Host entry for the SSH key in ~/.ssh/config. Off for profiles that
+    /// rely on ssh-agent or the default keys, which a generated entry would override.
+    #[serde(
+        default = "default_ssh_managed",
+        skip_serializing_if = "is_ssh_managed"
+    )]
+    pub ssh_managed: bool,
+
+    /// Directories (e.g. `~/work`) the SSH key is limited to. When set, the managed block gets
+    /// a `Match host ... exec` entry that applies only when ssh runs below one of them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ssh_match_dirs: Vec<String>,
+
+    /// Connection sharing (ControlMaster, ControlPath, ControlPersist) for the SSH entry
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh_control: Option<SshControl>,
+
+    /// AddKeysToAgent for the SSH entries (`yes`, `confirm`, `ask` or a lifetime such as
+    /// `1h`), so a passphrase-protected key is typed once and then served by ssh-agent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh_add_keys_to_agent: Option<String>,
+
+    /// UseKeychain for the SSH entries on macOS: ssh reads the key's passphrase from the
+    /// keychain and stores it there once typed. Left out of the entries elsewhere, where ssh
+    /// rejects the option.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub ssh_use_keychain: bool,
+
+    /// Keys for further hosts the identity reaches over SSH (gitlab.com, an internal server),
+    /// each written as its own Host entry next to the one for `ssh_key`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ssh_hosts: Vec<SshHost>,
+
     /// GPG signing key
     #[serde(skip_serializing_if = "Option::is_none")]
     pub gpg_key: Option<String>,
 
-    /// HTTPS credentials (future implementation)
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub https_credentials: Option<HttpsCredentials>,
+    /// Signing settings for repositories whose remote is on a given host (SSH signing for
+    /// GitHub, GPG for an internal Gerrit). A local `use` applies the first that matches the
+    /// remote in place of user.signingkey.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub host_signing: Vec<HostSigning>,
+
+    /// HTTPS credentials, one entry per host (e.g. github.com and a self-hosted GitLab).
+    /// Older configurations have a single table here, which is read as one entry.
+    #[serde(
+        default,
+        skip_serializing_if = "Vec::is_empty",
+        deserialize_with = "one_or_many"
+    )]
+    pub https_credentials: Vec<HttpsCredentials>,
+
+    /// Custom git configuration options, applied on `use` in key order
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "super::storage::serialize_sorted"
+    )]
+    pub custom_config: HashMap<String, CustomValue>,
+
+    /// Settings for non-git tools, keyed by applier (e.g. `npm`, `hg`), applied on `use`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extras: BTreeMap<String, BTreeMap<String, String>>,
+
+    /// `git format-patch`/`git send-email` settings, applied on `use`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sendemail: Option<SendEmail>,
 
-    /// Custom git configuration options
-    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
-    pub custom_config: HashMap<String, String>,
+    /// Operational notes such as "requires corp VPN", shown by `use` and `doctor` when the
+    /// profile's hosts can't be reached
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+
+    /// Directories (e.g. `~/work`) whose repositories use this profile without a switch, through
+    /// `includeIf "gitdir:"` rules gitp writes to the global git config (`gitp rules`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub auto_dirs: Vec<String>,
+}
+
+/// Reads a list of HTTPS credentials, or the single table configurations had before profiles
+/// could hold several.
+fn one_or_many<'de, D>(deserializer: D) -> Result<Vec<HttpsCredentials>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(Box<HttpsCredentials>),
+        Many(Vec<HttpsCredentials>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(creds) => vec![*creds],
+        OneOrMany::Many(creds) => creds,
+    })
+}
+
+fn default_ssh_managed() -> bool {
+    true
+}
+
+fn is_ssh_managed(managed: &bool) -> bool {
+    *managed
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -45,6 +159,433 @@ pub struct GitConfig {
     /// Git user.signingkey
     #[serde(skip_serializing_if = "Option::is_none")]
     pub user_signingkey: Option<String>,
+
+    /// Git core.autocrlf
+    #[serde(rename = "autocrlf", default, skip_serializing_if = "Option::is_none")]
+    pub core_autocrlf: Option<AutoCrlf>,
+
+    /// Git core.eol (ignored by git when core.autocrlf is true or input)
+    #[serde(rename = "eol", default, skip_serializing_if = "Option::is_none")]
+    pub core_eol: Option<Eol>,
+}
+
+/// Values of core.autocrlf
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AutoCrlf {
+    True,
+    False,
+    Input,
+}
+
+/// Values of core.eol
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Eol {
+    Lf,
+    Crlf,
+    Native,
+}
+
+impl AutoCrlf {
+    pub const ALL: [AutoCrlf; 3] = [AutoCrlf::True, AutoCrlf::Input, AutoCrlf::False];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AutoCrlf::True => "true",
+            AutoCrlf::False => "false",
+            AutoCrlf::Input => "input",
+        }
+    }
+}
+
+impl std::str::FromStr for AutoCrlf {
+    type Err = ValidationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "true" => Ok(AutoCrlf::True),
+            "false" => Ok(AutoCrlf::False),
+            "input" => Ok(AutoCrlf::Input),
+            _ => Err(ValidationError::InvalidAutoCrlf(s.to_string())),
+        }
+    }
+}
+
+impl Eol {
+    pub const ALL: [Eol; 3] = [Eol::Lf, Eol::Crlf, Eol::Native];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Eol::Lf => "lf",
+            Eol::Crlf => "crlf",
+            Eol::Native => "native",
+        }
+    }
+}
+
+impl std::str::FromStr for Eol {
+    type Err = ValidationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "lf" => Ok(Eol::Lf),
+            "crlf" => Ok(Eol::Crlf),
+            "native" => Ok(Eol::Native),
+            _ => Err(ValidationError::InvalidEol(s.to_string())),
+        }
+    }
+}
+
+/// Value of a `custom_config` key. Keys such as `url.<base>.insteadOf` or `credential.helper`
+/// take several values, which git applies in order.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum CustomValue {
+    /// `key = "value"`: replaces every value of the key at the applied scope
+    One(String),
+    /// `key = ["a", "b"]`: replaces every value of the key with these, in order
+    Many(Vec<String>),
+    /// `key = { add = ["a"] }`: appended after the values already set at the scope, which
+    /// are kept
+    Add { add: Vec<String> },
+}
+
+impl CustomValue {
+    pub fn values(&self) -> &[String] {
+        match self {
+            CustomValue::One(value) => std::slice::from_ref(value),
+            CustomValue::Many(values) | CustomValue::Add { add: values } => values,
+        }
+    }
+
+    pub fn values_mut(&mut self) -> &mut [String] {
+        match self {
+            CustomValue::One(value) => std::slice::from_mut(value),
+            CustomValue::Many(values) | CustomValue::Add { add: values } => values,
+        }
+    }
+
+    /// Whether the values are added to the key rather than replacing it.
+    pub fn is_additive(&self) -> bool {
+        matches!(self, CustomValue::Add { .. })
+    }
+}
+
+impl From<&str> for CustomValue {
+    fn from(value: &str) -> Self {
+        CustomValue::One(value.to_string())
+    }
+}
+
+/// ssh connection sharing for a profile's entry in the managed block.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SshControl {
+    /// ControlMaster
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub master: Option<ControlMaster>,
+
+    /// ControlPath, where `{profile}` stands for the profile name (default
+    /// [`SshControl::DEFAULT_PATH`])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+
+    /// ControlPersist: yes, no, or how long an idle master connection stays open (e.g. `10m`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub persist: Option<String>,
+}
+
+impl SshControl {
+    /// One socket per profile. `%C` alone hashes the user and host, which are the same
+    /// (`git@github.com`) for every account on a host, so accounts would share a connection.
+    pub const DEFAULT_PATH: &'static str = "~/.ssh/gitp-{profile}-%C";
+
+    /// These settings with the ControlPath resolved for `profile`, as written in its entry.
+    pub fn for_profile(&self, profile: &str) -> SshControl {
+        SshControl {
+            path: Some(self.control_path(profile)),
+            ..self.clone()
+        }
+    }
+
+    /// The ControlPath written for `profile`.
+    pub fn control_path(&self, profile: &str) -> String {
+        let profile: String = profile
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || "-_.".contains(c) {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        self.path
+            .as_deref()
+            .unwrap_or(Self::DEFAULT_PATH)
+            .replace("{profile}", &profile)
+    }
+}
+
+/// Values of ssh's ControlMaster
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum ControlMaster {
+    Yes,
+    No,
+    Ask,
+    Auto,
+    Autoask,
+}
+
+impl ControlMaster {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ControlMaster::Yes => "yes",
+            ControlMaster::No => "no",
+            ControlMaster::Ask => "ask",
+            ControlMaster::Auto => "auto",
+            ControlMaster::Autoask => "autoask",
+        }
+    }
+}
+
+impl std::str::FromStr for ControlMaster {
+    type Err = ValidationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "yes" => Ok(ControlMaster::Yes),
+            "no" => Ok(ControlMaster::No),
+            "ask" => Ok(ControlMaster::Ask),
+            "auto" => Ok(ControlMaster::Auto),
+            "autoask" => Ok(ControlMaster::Autoask),
+            _ => Err(ValidationError::InvalidControlMaster(s.to_string())),
+        }
+    }
+}
+
+/// Whether `value` is a ControlPersist ssh accepts: yes, no, or a time such as `600`, `10m`
+/// or `1h30m`.
+fn valid_control_persist(value: &str) -> bool {
+    let time = Regex::new(r"^([0-9]+[sSmMhHdDwW]?)+$").unwrap();
+    value == "yes" || value == "no" || time.is_match(value)
+}
+
+/// An SSH key for one more host of a profile.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SshHost {
+    /// Host or `*.domain` pattern the key is for
+    pub host: String,
+
+    /// Path to the private key
+    pub key: PathBuf,
+
+    /// Login user in the SSH entry; `git` when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+}
+
+/// How commits are signed in repositories whose remote is on `host`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HostSigning {
+    /// Host or `*.domain` pattern of the remote; an SSH alias counts as its real host
+    pub host: String,
+
+    /// gpg.format
+    pub format: SigningFormat,
+
+    /// user.signingkey: a GPG key ID for `openpgp`, a public key file (or `key::...`) for `ssh`
+    pub key: String,
+}
+
+impl HostSigning {
+    /// The user.signingkey value git gets. Git doesn't expand `~` in SSH key paths.
+    pub fn git_key(&self) -> String {
+        match self.format {
+            SigningFormat::Ssh if self.key.starts_with("~/") => {
+                expand_home(&self.key).to_string_lossy().into_owned()
+            }
+            _ => self.key.clone(),
+        }
+    }
+}
+
+/// Values of gpg.format
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SigningFormat {
+    Openpgp,
+    Ssh,
+    X509,
+}
+
+impl SigningFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SigningFormat::Openpgp => "openpgp",
+            SigningFormat::Ssh => "ssh",
+            SigningFormat::X509 => "x509",
+        }
+    }
+}
+
+/// Settings for patch-based workflows.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SendEmail {
+    /// format.from, the From: of generated patches (e.g. `John Doe <john@kernel.org>`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+
+    /// sendemail.confirm
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confirm: Option<SendEmailConfirm>,
+
+    /// Name of the `[sendemail "<identity>"]` block holding the SMTP settings, also set as
+    /// sendemail.identity. Without one they go in `[sendemail]` itself.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identity: Option<String>,
+
+    /// sendemail.smtpServer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub smtp_server: Option<String>,
+
+    /// sendemail.smtpServerPort
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub smtp_server_port: Option<u16>,
+
+    /// sendemail.smtpEncryption
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub smtp_encryption: Option<SmtpEncryption>,
+
+    /// sendemail.smtpUser
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub smtp_user: Option<String>,
+
+    /// SMTP password, kept like an HTTPS token and never written to git config
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub smtp_pass: Option<CredentialType>,
+}
+
+/// Values of sendemail.confirm
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SendEmailConfirm {
+    Always,
+    Never,
+    Cc,
+    Compose,
+    Auto,
+}
+
+impl SendEmailConfirm {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SendEmailConfirm::Always => "always",
+            SendEmailConfirm::Never => "never",
+            SendEmailConfirm::Cc => "cc",
+            SendEmailConfirm::Compose => "compose",
+            SendEmailConfirm::Auto => "auto",
+        }
+    }
+}
+
+impl std::str::FromStr for SendEmailConfirm {
+    type Err = ValidationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "always" => Ok(SendEmailConfirm::Always),
+            "never" => Ok(SendEmailConfirm::Never),
+            "cc" => Ok(SendEmailConfirm::Cc),
+            "compose" => Ok(SendEmailConfirm::Compose),
+            "auto" => Ok(SendEmailConfirm::Auto),
+            _ => Err(ValidationError::InvalidSendEmailConfirm(s.to_string())),
+        }
+    }
+}
+
+/// Values of sendemail.smtpEncryption
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpEncryption {
+    /// STARTTLS on a plain connection (usually port 587)
+    Tls,
+    /// TLS from the start (usually port 465)
+    Ssl,
+}
+
+impl SmtpEncryption {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SmtpEncryption::Tls => "tls",
+            SmtpEncryption::Ssl => "ssl",
+        }
+    }
+}
+
+impl std::str::FromStr for SmtpEncryption {
+    type Err = ValidationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "tls" => Ok(SmtpEncryption::Tls),
+            "ssl" => Ok(SmtpEncryption::Ssl),
+            _ => Err(ValidationError::InvalidSmtpEncryption(s.to_string())),
+        }
+    }
+}
+
+impl SendEmail {
+    /// The git config entries these settings make, in a stable order. SMTP settings live in
+    /// the identity's block when there is one.
+    pub fn git_entries(&self) -> Vec<(String, String)> {
+        let smtp_key = |name: &str| match &self.identity {
+            Some(identity) => format!("sendemail.{}.{}", identity, name),
+            None => format!("sendemail.{}", name),
+        };
+        let mut entries = Vec::new();
+        if let Some(from) = &self.from {
+            entries.push(("format.from".to_string(), from.clone()));
+        }
+        if let Some(confirm) = self.confirm {
+            entries.push((
+                "sendemail.confirm".to_string(),
+                confirm.as_str().to_string(),
+            ));
+        }
+        if let Some(identity) = &self.identity {
+            entries.push(("sendemail.identity".to_string(), identity.clone()));
+        }
+        if let Some(server) = &self.smtp_server {
+            entries.push((smtp_key("smtpServer"), server.clone()));
+        }
+        if let Some(port) = self.smtp_server_port {
+            entries.push((smtp_key("smtpServerPort"), port.to_string()));
+        }
+        if let Some(encryption) = self.smtp_encryption {
+            entries.push((smtp_key("smtpEncryption"), encryption.as_str().to_string()));
+        }
+        if let Some(user) = &self.smtp_user {
+            entries.push((smtp_key("smtpUser"), user.clone()));
+        }
+        entries
+    }
+
+    /// Port git send-email connects to: the configured one, else its default for the
+    /// encryption.
+    pub fn effective_port(&self) -> u16 {
+        self.smtp_server_port.unwrap_or(match self.smtp_encryption {
+            Some(SmtpEncryption::Ssl) => 465,
+            Some(SmtpEncryption::Tls) => 587,
+            None => 25,
+        })
+    }
+
+    /// Host under which the SMTP password is stored in the keychain or file store.
+    pub fn credential_host(server: &str) -> String {
+        format!("smtp://{}", server)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -57,6 +598,72 @@ pub struct HttpsCredentials {
 
     /// Credential type
     pub credential_type: CredentialType,
+
+    /// Scopes granted to the token, as reported by the provider at last verification
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_scopes: Option<Vec<String>>,
+
+    /// Token expiry date, as reported by the provider at last verification
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_expires_at: Option<NaiveDate>,
+
+    /// Minutes the credential helper keeps the resolved token in git's credential-cache
+    /// daemon, instead of reading the secret store on every request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_minutes: Option<u32>,
+
+    /// Account the token authenticated as at last verification
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account: Option<ProviderAccount>,
+
+    /// Scopes the token must have, on top of those `[[settings.required_token_scopes]]`
+    /// requires for the host
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub required_scopes: Vec<String>,
+}
+
+impl HttpsCredentials {
+    pub fn new(host: String, username: String, credential_type: CredentialType) -> Self {
+        Self {
+            host,
+            username,
+            credential_type,
+            token_scopes: None,
+            token_expires_at: None,
+            cache_minutes: None,
+            required_scopes: Vec::new(),
+            account: None,
+        }
+    }
+
+    /// The provider login the token belongs to, as last verified, or else the username.
+    pub fn login(&self) -> &str {
+        self.account
+            .as_ref()
+            .map_or(self.username.as_str(), |account| account.login.as_str())
+    }
+}
+
+/// The provider account a token authenticates as, recorded by `gitp credentials verify` so
+/// it can be shown and compared without asking the provider again.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProviderAccount {
+    /// Login (GitHub) or username (GitLab)
+    pub login: String,
+
+    /// Numeric account ID, which stays the same when the account is renamed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<u64>,
+
+    /// Display name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avatar_url: Option<String>,
+
+    /// When the provider last confirmed the account
+    pub verified_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -67,6 +674,21 @@ pub enum CredentialType {
 
     /// Reference to system keychain
     KeychainRef(String),
+
+    /// Reference to gitp's passphrase-encrypted secrets file
+    FileStoreRef(String),
+}
+
+impl CredentialType {
+    /// Returns the account name when the token itself lives outside config.toml.
+    pub fn stored_account(&self) -> Option<&str> {
+        match self {
+            CredentialType::Token(_) => None,
+            CredentialType::KeychainRef(account) | CredentialType::FileStoreRef(account) => {
+                Some(account)
+            }
+        }
+    }
 }
 
 impl Profile {
@@ -78,13 +700,52 @@ impl Profile {
                 user_name,
                 user_email,
                 user_signingkey: None,
+                core_autocrlf: None,
+                core_eol: None,
             },
             ssh_key: None,
             ssh_key_host: None,
+            ssh_key_fingerprint: None,
+            ssh_host_alias: None,
+            ssh_user: None,
+            ssh_managed: true,
+            ssh_match_dirs: Vec::new(),
+            ssh_control: None,
+            ssh_add_keys_to_agent: None,
+            ssh_use_keychain: false,
+            ssh_hosts: Vec::new(),
             gpg_key: None,
-            https_credentials: None,
+            host_signing: Vec::new(),
+            https_credentials: Vec::new(),
             custom_config: HashMap::new(),
+            extras: BTreeMap::new(),
+            sendemail: None,
+            notes: None,
+            auto_dirs: Vec::new(),
+        }
+    }
+
+    /// Replaces the home directory in the profile's paths with `~`; they are expanded again
+    /// where they are used.
+    pub fn contract_paths(&mut self) {
+        if let Some(key) = &self.ssh_key {
+            self.ssh_key = Some(contract_home(key));
+        }
+        for entry in &mut self.ssh_hosts {
+            entry.key = contract_home(&entry.key);
         }
+        for dir in self.ssh_match_dirs.iter_mut().chain(&mut self.auto_dirs) {
+            *dir = contract_home(Path::new(dir.as_str()))
+                .to_string_lossy()
+                .into_owned();
+        }
+    }
+
+    /// Host name remotes use to reach this profile's SSH key: the alias if set, else the host.
+    pub fn ssh_host(&self) -> Option<&str> {
+        self.ssh_host_alias
+            .as_deref()
+            .or(self.ssh_key_host.as_deref())
     }
 
     /// Validate profile configuration
@@ -112,9 +773,18 @@ impl Profile {
             ));
         }
 
+        // core.eol only takes effect when core.autocrlf is unset or false
+        if let (Some(AutoCrlf::True | AutoCrlf::Input), Some(eol)) =
+            (self.git_config.core_autocrlf, self.git_config.core_eol)
+        {
+            return Err(ValidationError::EolIgnoredByAutoCrlf(
+                eol.as_str().to_string(),
+            ));
+        }
+
         // Validate SSH key path and associated host if provided
         if let Some(ref ssh_key) = self.ssh_key {
-            if !ssh_key.exists() {
+            if !expand_home(&ssh_key.to_string_lossy()).exists() {
                 return Err(ValidationError::SshKeyNotFound(ssh_key.clone()));
             }
             // If ssh_key is present, ssh_key_host must also be present and non-empty
@@ -129,6 +799,68 @@ impl Profile {
             // This state might be prevented by CLI logic or cleaned up.
         }
 
+        for (i, entry) in self.ssh_hosts.iter().enumerate() {
+            if entry.host.trim().is_empty() {
+                return Err(ValidationError::EmptySshKeyHost);
+            }
+            let taken = self.ssh_key.is_some()
+                && self
+                    .ssh_key_host
+                    .as_deref()
+                    .is_some_and(|host| same_host(host, &entry.host));
+            if taken
+                || self.ssh_hosts[..i]
+                    .iter()
+                    .any(|other| same_host(&other.host, &entry.host))
+            {
+                return Err(ValidationError::DuplicateSshHost(entry.host.clone()));
+            }
+            if !expand_home(&entry.key.to_string_lossy()).exists() {
+                return Err(ValidationError::SshKeyNotFound(entry.key.clone()));
+            }
+        }
+
+        // Wildcards are only meaningful for real hosts; an alias names one Host entry
+        for host in self.hosts() {
+            if host.contains('*') && !valid_host_pattern(host) {
+                return Err(ValidationError::InvalidHostPattern(host.to_string()));
+            }
+        }
+        if !self.ssh_match_dirs.is_empty() {
+            if self.ssh_key.is_none() || self.ssh_key_host.is_none() {
+                return Err(ValidationError::SshMatchDirsWithoutKey);
+            }
+            if self.ssh_host_alias.is_some() {
+                return Err(ValidationError::SshMatchDirsWithAlias);
+            }
+        }
+        if let Some(control) = &self.ssh_control {
+            if self.ssh_key.is_none() || self.ssh_key_host.is_none() {
+                return Err(ValidationError::SshControlWithoutKey);
+            }
+            if let Some(persist) = control
+                .persist
+                .as_ref()
+                .filter(|p| !valid_control_persist(p))
+            {
+                return Err(ValidationError::InvalidControlPersist(persist.clone()));
+            }
+        }
+        for signing in &self.host_signing {
+            if signing.host.trim().is_empty() || signing.key.trim().is_empty() {
+                return Err(ValidationError::IncompleteHostSigning(signing.host.clone()));
+            }
+            if signing.host.contains('*') && !valid_host_pattern(&signing.host) {
+                return Err(ValidationError::InvalidHostPattern(signing.host.clone()));
+            }
+        }
+        if self.ssh_host_alias.is_some() {
+            let ssh_hosts = [&self.ssh_host_alias, &self.ssh_key_host];
+            if let Some(pattern) = ssh_hosts.into_iter().flatten().find(|h| is_host_pattern(h)) {
+                return Err(ValidationError::AliasForHostPattern(pattern.clone()));
+            }
+        }
+
         // Validate GPG key format if provided
         if let Some(ref gpg_key_id) = self.gpg_key {
             if gpg_key_id.is_empty() {
@@ -145,8 +877,20 @@ impl Profile {
             }
         }
 
+        if let Some(identity) = self.sendemail.as_ref().and_then(|s| s.identity.as_ref()) {
+            if identity.is_empty() || identity.contains(|c: char| c.is_whitespace() || c == '.') {
+                return Err(ValidationError::InvalidSendEmailIdentity(identity.clone()));
+            }
+        }
+
         // Validate HTTPS credentials if provided
-        if let Some(creds) = &self.https_credentials {
+        for (i, creds) in self.https_credentials.iter().enumerate() {
+            if self.https_credentials[..i]
+                .iter()
+                .any(|other| same_host(&other.host, &creds.host))
+            {
+                return Err(ValidationError::DuplicateHttpsHost(creds.host.clone()));
+            }
             if creds.host.trim().is_empty() {
                 return Err(ValidationError::EmptyHttpsHost);
             }
@@ -164,6 +908,11 @@ impl Profile {
                         return Err(ValidationError::EmptyHttpsKeychainRef);
                     }
                 }
+                CredentialType::FileStoreRef(file_store_ref) => {
+                    if file_store_ref.trim().is_empty() {
+                        return Err(ValidationError::EmptyHttpsFileStoreRef);
+                    }
+                }
             }
         }
 
@@ -194,9 +943,15 @@ pub enum ValidationError {
     #[error("SSH key host cannot be empty when an SSH key is provided")]
     EmptySshKeyHost,
 
+    #[error("More than one SSH key for host {0}")]
+    DuplicateSshHost(String),
+
     #[error("HTTPS credentials host cannot be empty")]
     EmptyHttpsHost,
 
+    #[error("More than one set of HTTPS credentials for host {0}")]
+    DuplicateHttpsHost(String),
+
     #[error("HTTPS credentials username cannot be empty")]
     EmptyHttpsUsername,
 
@@ -205,6 +960,94 @@ pub enum ValidationError {
 
     #[error("HTTPS credentials keychain reference cannot be empty when type is KeychainRef")]
     EmptyHttpsKeychainRef,
+
+    #[error("HTTPS credentials file store reference cannot be empty when type is FileStoreRef")]
+    EmptyHttpsFileStoreRef,
+
+    #[error("Invalid core.autocrlf value: {0}. Expected true, false or input.")]
+    InvalidAutoCrlf(String),
+
+    #[error("Invalid core.eol value: {0}. Expected lf, crlf or native.")]
+    InvalidEol(String),
+
+    #[error("core.eol = {0} has no effect while core.autocrlf is true or input")]
+    EolIgnoredByAutoCrlf(String),
+
+    #[error("Invalid sendemail.confirm value: {0}. Expected always, never, cc, compose or auto.")]
+    InvalidSendEmailConfirm(String),
+
+    #[error("Invalid sendemail.smtpEncryption value: {0}. Expected tls or ssl.")]
+    InvalidSmtpEncryption(String),
+
+    #[error("sendemail identity '{0}' cannot contain whitespace or dots")]
+    InvalidSendEmailIdentity(String),
+
+    #[error(
+        "Invalid host pattern: {0}. Expected '*.' followed by a domain, such as *.corp.example.com"
+    )]
+    InvalidHostPattern(String),
+
+    #[error(
+        "SSH host pattern {0} cannot be combined with a host alias, which names a single host"
+    )]
+    AliasForHostPattern(String),
+
+    #[error("SSH match directories need an SSH key and host")]
+    SshMatchDirsWithoutKey,
+
+    #[error("SSH match directories replace the host alias; remove one of them")]
+    SshMatchDirsWithAlias,
+
+    #[error("Invalid ControlMaster value: {0}. Expected yes, no, ask, auto or autoask")]
+    InvalidControlMaster(String),
+
+    #[error("Invalid ControlPersist value: {0}. Expected yes, no or a time such as 10m")]
+    InvalidControlPersist(String),
+
+    #[error("SSH connection sharing needs an SSH key and host")]
+    SshControlWithoutKey,
+
+    #[error("host_signing entry '{0}' needs both a host and a key")]
+    IncompleteHostSigning(String),
+}
+
+impl ValidationError {
+    /// The error as a full sentence for the terminal, quoting the offending value and saying how
+    /// to fix it where the plain message doesn't.
+    pub fn user_message(&self) -> String {
+        match self {
+            ValidationError::EmptyEmail => "User email cannot be empty.".to_string(),
+            ValidationError::InvalidEmail(email) => format!("Invalid email format: '{}'.", email),
+            ValidationError::SshKeyNotFound(path) => {
+                format!("SSH key not found: '{}'.", path.display())
+            }
+            ValidationError::InvalidGpgKeyFormat(key) => format!(
+                "Invalid GPG key format for '{}'. Expected 8, 16, or 40 hex characters.",
+                key
+            ),
+            ValidationError::InvalidAutoCrlf(value) => format!(
+                "Invalid core.autocrlf value '{}'. Expected true, false or input.",
+                value
+            ),
+            ValidationError::InvalidEol(value) => format!(
+                "Invalid core.eol value '{}'. Expected lf, crlf or native.",
+                value
+            ),
+            ValidationError::EolIgnoredByAutoCrlf(eol) => format!(
+                "core.eol = {} has no effect while core.autocrlf is true or input. Set autocrlf \
+                 to false or remove eol.",
+                eol
+            ),
+            _ => {
+                let message = self.to_string();
+                if message.ends_with('.') {
+                    message
+                } else {
+                    message + "."
+                }
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -242,6 +1085,58 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_line_ending_validation() {
+        let mut profile = Profile::new(
+            "test".to_string(),
+            "Test User".to_string(),
+            "test@example.com".to_string(),
+        );
+        profile.git_config.core_eol = Some(Eol::Lf);
+        assert!(profile.validate().is_ok());
+
+        profile.git_config.core_autocrlf = Some(AutoCrlf::False);
+        assert!(profile.validate().is_ok());
+
+        profile.git_config.core_autocrlf = Some(AutoCrlf::Input);
+        assert!(matches!(
+            profile.validate(),
+            Err(ValidationError::EolIgnoredByAutoCrlf(_))
+        ));
+
+        assert_eq!("Input".parse::<AutoCrlf>().unwrap(), AutoCrlf::Input);
+        assert_eq!("crlf".parse::<Eol>().unwrap(), Eol::Crlf);
+        assert!(matches!(
+            "yes".parse::<AutoCrlf>(),
+            Err(ValidationError::InvalidAutoCrlf(_))
+        ));
+        assert!("cr".parse::<Eol>().is_err());
+    }
+
+    #[test]
+    fn test_custom_value_forms() {
+        let config: HashMap<String, CustomValue> = toml::from_str(
+            r#"
+            "commit.gpgsign" = "true"
+            "url.git@github.com-work:.insteadOf" = ["https://github.com/", "git@github.com:"]
+            "credential.helper" = { add = ["cache"] }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config["commit.gpgsign"], CustomValue::from("true"));
+        assert_eq!(
+            config["url.git@github.com-work:.insteadOf"].values(),
+            ["https://github.com/", "git@github.com:"]
+        );
+        let helper = &config["credential.helper"];
+        assert!(helper.is_additive());
+        assert_eq!(helper.values(), ["cache"]);
+        assert_eq!(
+            toml::to_string(&config["credential.helper"]).unwrap(),
+            "add = [\"cache\"]\n"
+        );
+    }
+
     #[test]
     fn test_https_credentials_validation() {
         let base_profile = |host: &str, username: &str, cred_type: CredentialType| {
@@ -250,11 +1145,11 @@ mod tests {
                 "Test User".to_string(),
                 "test@example.com".to_string(),
             );
-            p.https_credentials = Some(HttpsCredentials {
-                host: host.to_string(),
-                username: username.to_string(),
-                credential_type: cred_type,
-            });
+            p.https_credentials = vec![HttpsCredentials::new(
+                host.to_string(),
+                username.to_string(),
+                cred_type,
+            )];
             p
         };
 
@@ -315,6 +1210,17 @@ mod tests {
             Err(ValidationError::EmptyHttpsKeychainRef)
         ));
 
+        // Invalid: Empty FileStoreRef
+        let profile_empty_file_store_ref = base_profile(
+            "source.example.com",
+            "user6",
+            CredentialType::FileStoreRef("".to_string()),
+        );
+        assert!(matches!(
+            profile_empty_file_store_ref.validate(),
+            Err(ValidationError::EmptyHttpsFileStoreRef)
+        ));
+
         // Valid: No HTTPS credentials
         let profile_no_https = Profile::new(
             "no_https".to_string(),
@@ -322,5 +1228,91 @@ mod tests {
             "test@example.com".to_string(),
         );
         assert!(profile_no_https.validate().is_ok());
+
+        // Valid: one set per host; invalid: two for the same host
+        let mut profile_two_hosts = base_profile(
+            "github.com",
+            "user7",
+            CredentialType::Token("token".to_string()),
+        );
+        profile_two_hosts
+            .https_credentials
+            .push(HttpsCredentials::new(
+                "gitlab.example.com".to_string(),
+                "user7".to_string(),
+                CredentialType::Token("token".to_string()),
+            ));
+        assert!(profile_two_hosts.validate().is_ok());
+        profile_two_hosts.https_credentials[1].host = "GitHub.com".to_string();
+        assert!(matches!(
+            profile_two_hosts.validate(),
+            Err(ValidationError::DuplicateHttpsHost(_))
+        ));
+
+        // A single table, as written before profiles could hold several, still loads
+        let legacy: Profile = toml::from_str(
+            r#"
+            name = "legacy"
+            [git_config]
+            name = "Test User"
+            email = "test@example.com"
+            [https_credentials]
+            host = "github.com"
+            username = "user8"
+            credential_type = { type = "Token", value = "token" }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(legacy.https_hosts(), ["github.com"]);
+    }
+
+    #[test]
+    fn test_sendemail_git_entries() {
+        let mut sendemail = SendEmail {
+            from: Some("John Doe <john@kernel.org>".to_string()),
+            confirm: Some("Always".parse().unwrap()),
+            smtp_server: Some("smtp.kernel.org".to_string()),
+            smtp_encryption: Some(SmtpEncryption::Ssl),
+            ..Default::default()
+        };
+        assert_eq!(
+            sendemail.git_entries(),
+            [
+                ("format.from", "John Doe <john@kernel.org>"),
+                ("sendemail.confirm", "always"),
+                ("sendemail.smtpServer", "smtp.kernel.org"),
+                ("sendemail.smtpEncryption", "ssl"),
+            ]
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+        );
+        assert_eq!(sendemail.effective_port(), 465);
+
+        sendemail.identity = Some("kernel".to_string());
+        let keys: Vec<String> = sendemail
+            .git_entries()
+            .into_iter()
+            .map(|(k, _)| k)
+            .collect();
+        assert_eq!(
+            keys[2..],
+            [
+                "sendemail.identity",
+                "sendemail.kernel.smtpServer",
+                "sendemail.kernel.smtpEncryption"
+            ]
+        );
+
+        let mut profile = Profile::new(
+            "kernel".to_string(),
+            "John Doe".to_string(),
+            "john@kernel.org".to_string(),
+        );
+        sendemail.identity = Some("linux.dev".to_string());
+        profile.sendemail = Some(sendemail);
+        assert!(matches!(
+            profile.validate(),
+            Err(ValidationError::InvalidSendEmailIdentity(_))
+        ));
+        assert!("later".parse::<SendEmailConfirm>().is_err());
     }
 }
@@ -0,0 +1,145 @@
+// src/config/restricted.rs
+// Remotes only approved profiles may commit to, push to or fetch from
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+use super::Config;
+use crate::git::url::parse_remote_url;
+use crate::utils::glob_match;
+
+/// A `[[settings.restricted_remotes]]` entry.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RestrictedRemote {
+    /// `host/owner` or `host/owner/repo`, with globs (`github.com/acme-*`)
+    pub remote: String,
+
+    /// Profiles, by name or glob (`work-*`), approved for matching remotes
+    #[serde(default)]
+    pub profiles: Vec<String>,
+}
+
+impl RestrictedRemote {
+    /// Whether the rule covers the repository at `path` (`owner/repo`, as in the URL) on
+    /// `host`: the pattern matches the owner, a group (GitLab subgroups) or the repository.
+    /// Hosts and paths are compared case-insensitively, as providers treat them.
+    pub fn covers(&self, host: &str, path: &str) -> bool {
+        let pattern = self.remote.trim().trim_end_matches('/').to_lowercase();
+        let path = path.trim_matches('/');
+        let path = path.strip_suffix(".git").unwrap_or(path).to_lowercase();
+        let mut prefix = host.to_lowercase();
+        path.split('/').filter(|part| !part.is_empty()).any(|part| {
+            prefix = format!("{}/{}", prefix, part);
+            glob_match(&pattern, &prefix)
+        })
+    }
+
+    /// Whether the rule can cover repositories on `host`.
+    pub fn covers_host(&self, host: &str) -> bool {
+        let pattern = self.remote.trim().to_lowercase();
+        let pattern = pattern.split('/').next().unwrap_or_default();
+        glob_match(pattern, &host.to_lowercase())
+    }
+
+    pub fn allows(&self, profile: &str) -> bool {
+        self.profiles
+            .iter()
+            .any(|pattern| glob_match(pattern, profile))
+    }
+}
+
+impl Config {
+    /// The restricted_remotes rule `profile` isn't approved by for `url`, if any. SSH host
+    /// aliases count as their real host.
+    pub fn restriction_for(&self, url: &str, profile: &str) -> Option<&RestrictedRemote> {
+        let url = parse_remote_url(url)?;
+        let host = self.real_host(&url.host);
+        self.settings
+            .restricted_remotes
+            .iter()
+            .find(|rule| rule.covers(host, &url.path) && !rule.allows(profile))
+    }
+
+    /// The first restricted_remotes rule on `host` that doesn't approve `profile`, for when the
+    /// repository isn't known.
+    pub fn restriction_on_host(&self, host: &str, profile: &str) -> Option<&RestrictedRemote> {
+        let host = self.real_host(host);
+        self.settings
+            .restricted_remotes
+            .iter()
+            .find(|rule| rule.covers_host(host) && !rule.allows(profile))
+    }
+
+    /// Fails when `profile` isn't approved for `url` by restricted_remotes.
+    pub fn require_approved(&self, url: &str, profile: &str) -> Result<()> {
+        if let Some(rule) = self.restriction_for(url, profile) {
+            bail!(
+                "Profile '{}' isn't approved for {} (restricted_remotes '{}' allows {}).",
+                profile,
+                url,
+                rule.remote,
+                match rule.profiles.as_slice() {
+                    [] => "no profile".to_string(),
+                    profiles => profiles.join(", "),
+                }
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Profile;
+
+    #[test]
+    fn test_restriction_for() {
+        let mut config = Config::default();
+        let mut work = Profile::new(
+            "work".to_string(),
+            "John Doe".to_string(),
+            "john@acme.com".to_string(),
+        );
+        work.ssh_key_host = Some("github.com".to_string());
+        work.ssh_host_alias = Some("github.com-work".to_string());
+        config.profiles.insert("work".to_string(), work);
+        config.settings.restricted_remotes = vec![RestrictedRemote {
+            remote: "github.com/acme-*".to_string(),
+            profiles: vec!["work".to_string(), "acme-*".to_string()],
+        }];
+
+        let url = "git@github.com:Acme-Corp/app.git";
+        assert!(config.restriction_for(url, "personal").is_some());
+        assert!(config.restriction_for(url, "work").is_none());
+        assert!(config.restriction_for(url, "acme-ci").is_none());
+        assert!(config
+            .restriction_for("git@github.com-work:acme-corp/app", "personal")
+            .is_some());
+        assert!(config
+            .restriction_for("https://github.com/acme/app", "personal")
+            .is_none());
+        assert!(config.require_approved(url, "personal").is_err());
+        assert!(config
+            .restriction_on_host("github.com-work", "personal")
+            .is_some());
+        assert!(config.restriction_on_host("GitHub.com", "work").is_none());
+        assert!(config
+            .restriction_on_host("gitlab.com", "personal")
+            .is_none());
+
+        let repo = RestrictedRemote {
+            remote: "gitlab.com/acme/secret".to_string(),
+            profiles: Vec::new(),
+        };
+        assert!(repo.covers("gitlab.com", "/acme/secret.git"));
+        assert!(!repo.covers("gitlab.com", "/acme/public.git"));
+        let group = RestrictedRemote {
+            remote: "gitlab.com/acme".to_string(),
+            profiles: Vec::new(),
+        };
+        assert!(group.covers("gitlab.com", "acme/team/app.git"));
+        assert!(!group.covers("gitlab.com", "acmeish/app.git"));
+    }
+}
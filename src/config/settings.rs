@@ -0,0 +1,395 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use super::{host_matches, is_host_pattern, RestrictedRemote};
+use crate::utils::contract_home;
+
+/// Machine-wide gitp behaviour, stored in the `[settings]` table of config.toml.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Settings {
+    /// Backend used when a token is stored "securely" (i.e. not as plain text)
+    #[serde(default)]
+    pub secret_store: SecretStoreKind,
+
+    /// What to do when the platform keyring cannot store a token
+    #[serde(default)]
+    pub keyring_fallback: KeyringFallback,
+
+    /// Seconds to wait for the platform keyring before giving up (reads are retried once).
+    /// 0, the default, waits as long as it takes, since the keyring may be showing an unlock
+    /// prompt.
+    #[serde(default = "default_keyring_timeout_secs")]
+    pub keyring_timeout_secs: u64,
+
+    /// Seconds the derived encrypted-file-store key stays cached after unlocking
+    #[serde(default = "default_filestore_cache_ttl_secs")]
+    pub filestore_cache_ttl_secs: u64,
+
+    /// Whether `gitp use` points git's credential helper at gitp per host or for every host
+    #[serde(default)]
+    pub credential_helper: CredentialHelperSetup,
+
+    /// How gitp's credential helper is combined with helpers already configured for a host
+    #[serde(default)]
+    pub credential_helper_coexistence: HelperCoexistence,
+
+    /// `credential_helper_coexistence` for particular hosts (or `*.domain` patterns), e.g. to
+    /// keep Git Credential Manager away from the hosts gitp has tokens for
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub credential_helper_hosts: BTreeMap<String, HelperCoexistence>,
+
+    /// Seconds a `gitp-apply-*` plugin may run before it is killed
+    #[serde(default = "default_plugin_timeout_secs")]
+    pub plugin_timeout_secs: u64,
+
+    /// What a failing `gitp-apply-*` plugin does to the switch
+    #[serde(default)]
+    pub plugin_failure: PluginFailure,
+
+    /// Unicode or plain-ASCII symbols in output
+    #[serde(default)]
+    pub output: OutputStyle,
+
+    /// Palette for colored output and prompts
+    #[serde(default)]
+    pub theme: ColorTheme,
+
+    /// `SecurityKeyProvider` written for FIDO2 (`-sk`) keys in the managed SSH config block:
+    /// a FIDO middleware library or `internal`. OpenSSH's default when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh_security_key_provider: Option<String>,
+
+    /// Where the managed block goes in ~/.ssh/config
+    #[serde(default)]
+    pub ssh_block_position: SshBlockPosition,
+
+    /// Profiles, by name or glob (`oss-*`), left out of the managed ~/.ssh/config block
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ssh_unmanaged_profiles: Vec<String>,
+
+    /// Name of each alias printed by `gitp shell-aliases`. `{prefix}` is the shortest start of
+    /// the profile name no other profile shares, `{profile}` the whole name.
+    #[serde(default = "default_shell_alias_name")]
+    pub shell_alias_name: String,
+
+    /// Command each alias runs; `{profile}` is the profile name, quoted for the shell
+    #[serde(default = "default_shell_alias_command")]
+    pub shell_alias_command: String,
+
+    /// Default answer to "Configure advanced options?" in interactive `gitp new`
+    #[serde(default)]
+    pub new_advanced_options: bool,
+
+    /// Host suggested for the SSH key and HTTPS token in interactive `gitp new`
+    #[serde(default = "default_new_default_host")]
+    pub new_default_host: String,
+
+    /// Proxy for requests to provider APIs (e.g. `http://proxy.corp:3128`). Unset: git's
+    /// global http.proxy, then the HTTPS_PROXY and ALL_PROXY environment variables.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_proxy: Option<String>,
+
+    /// PEM file of CA certificates trusted for provider APIs on top of the built-in ones, for
+    /// private CAs and TLS-inspecting proxies. Unset: git's global http.sslCAInfo.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_ca_file: Option<String>,
+
+    /// Times a provider request is retried after a network error, a 5xx answer or a short
+    /// rate-limit wait
+    #[serde(default = "default_http_retries")]
+    pub http_retries: u32,
+
+    /// Seconds a provider request may take, connection included
+    #[serde(default = "default_http_timeout_secs")]
+    pub http_timeout_secs: u64,
+
+    /// Remotes (e.g. an employer's organization) only the listed profiles may be used with
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub restricted_remotes: Vec<RestrictedRemote>,
+
+    /// Scopes HTTPS tokens for a host must have, e.g. to upload SSH keys
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub required_token_scopes: Vec<RequiredScopes>,
+}
+
+/// A `[[settings.required_token_scopes]]` entry.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RequiredScopes {
+    /// Host or `*.domain` pattern the tokens are for
+    pub host: String,
+
+    /// Scopes each token must have, as the provider names them (`repo`, `write:public_key`)
+    pub scopes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum SecretStoreKind {
+    /// Platform keyring (macOS Keychain, Windows Credential Manager, Secret Service)
+    #[default]
+    Keyring,
+
+    /// Passphrase-encrypted secrets file in the gitp config directory
+    File,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeyringFallback {
+    /// Abort the operation
+    Fail,
+
+    /// Ask interactively (fails when no terminal is attached)
+    Prompt,
+
+    /// Keep the token as plain text in config.toml and print a warning
+    #[default]
+    PlaintextWithWarning,
+
+    /// Put the token in the encrypted file store instead
+    EncryptedFile,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum CredentialHelperSetup {
+    /// `credential.https://<host>/.helper` for each HTTPS host of the profile
+    #[default]
+    PerHost,
+
+    /// `credential.helper = "!gitp credential"`, answering for the active profile on any host
+    Blanket,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum HelperCoexistence {
+    /// Ask on `use` when other helpers are found and remember the answer
+    #[default]
+    Ask,
+
+    /// Run gitp first for its hosts, then the existing helpers
+    Prepend,
+
+    /// Use only gitp for its hosts; other hosts keep their helpers
+    Replace,
+
+    /// Add gitp after the existing helpers, which may answer first
+    Leave,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum PluginFailure {
+    /// Print a warning and finish the switch
+    #[default]
+    Warn,
+
+    /// Stop before the git identity is changed
+    Abort,
+
+    /// Finish the switch silently
+    Ignore,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputStyle {
+    /// ASCII unless the locale uses UTF-8
+    #[default]
+    Auto,
+
+    /// Unicode bullets, check marks and prompt arrows
+    Unicode,
+
+    /// Plain ASCII, for terminals and log collectors that mangle Unicode
+    Ascii,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColorTheme {
+    /// Green for success, cyan for names and commands, yellow for warnings, red for errors
+    #[default]
+    Default,
+
+    /// Bright, bold colors for low-contrast terminals and low vision
+    HighContrast,
+
+    /// Blue for success and magenta for errors, which stay apart with red-green color
+    /// blindness
+    ColorblindSafe,
+
+    /// No colors; warnings and errors are bold
+    Mono,
+}
+
+/// ssh takes each option from the first section that matches a host, so the block's position
+/// decides whether gitp's entries or the user's own `Host *` settings win.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum SshBlockPosition {
+    /// Leave the block where it is; a new block goes at the end
+    #[default]
+    Keep,
+
+    /// First in the file, ahead of any `Host *` section
+    Top,
+
+    /// Last in the file, after the user's own sections
+    Bottom,
+}
+
+fn default_keyring_timeout_secs() -> u64 {
+    0
+}
+
+fn default_filestore_cache_ttl_secs() -> u64 {
+    300
+}
+
+fn default_plugin_timeout_secs() -> u64 {
+    10
+}
+
+fn default_shell_alias_name() -> String {
+    "g{prefix}".to_string()
+}
+
+fn default_shell_alias_command() -> String {
+    "gitp use {profile} --local".to_string()
+}
+
+fn default_new_default_host() -> String {
+    "github.com".to_string()
+}
+
+fn default_http_retries() -> u32 {
+    2
+}
+
+fn default_http_timeout_secs() -> u64 {
+    30
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            secret_store: SecretStoreKind::default(),
+            keyring_fallback: KeyringFallback::default(),
+            keyring_timeout_secs: default_keyring_timeout_secs(),
+            filestore_cache_ttl_secs: default_filestore_cache_ttl_secs(),
+            credential_helper: CredentialHelperSetup::default(),
+            credential_helper_coexistence: HelperCoexistence::default(),
+            credential_helper_hosts: BTreeMap::new(),
+            plugin_timeout_secs: default_plugin_timeout_secs(),
+            plugin_failure: PluginFailure::default(),
+            output: OutputStyle::default(),
+            theme: ColorTheme::default(),
+            ssh_security_key_provider: None,
+            ssh_block_position: SshBlockPosition::default(),
+            ssh_unmanaged_profiles: Vec::new(),
+            shell_alias_name: default_shell_alias_name(),
+            shell_alias_command: default_shell_alias_command(),
+            new_advanced_options: false,
+            new_default_host: default_new_default_host(),
+            http_proxy: None,
+            http_ca_file: None,
+            http_retries: default_http_retries(),
+            http_timeout_secs: default_http_timeout_secs(),
+            restricted_remotes: Vec::new(),
+            required_token_scopes: Vec::new(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The `credential_helper_hosts` entry for `host`; a host's own entry wins over a pattern.
+    pub fn host_helper_coexistence(&self, host: &str) -> Option<HelperCoexistence> {
+        let matching = |exact: bool| {
+            self.credential_helper_hosts
+                .iter()
+                .find(|(pattern, _)| {
+                    host_matches(pattern, host) && exact != is_host_pattern(pattern)
+                })
+                .map(|(_, mode)| *mode)
+        };
+        matching(true).or_else(|| matching(false))
+    }
+
+    /// Replaces the home directory in file settings with `~`, like
+    /// [`Profile::contract_paths`](super::Profile::contract_paths).
+    pub fn contract_paths(&mut self) {
+        for path in [&mut self.http_ca_file, &mut self.ssh_security_key_provider]
+            .into_iter()
+            .flatten()
+        {
+            *path = contract_home(Path::new(path.as_str()))
+                .to_string_lossy()
+                .into_owned();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_settings_use_defaults() {
+        let settings: Settings = toml::from_str("").unwrap();
+        assert_eq!(settings.secret_store, SecretStoreKind::Keyring);
+        assert_eq!(settings.filestore_cache_ttl_secs, 300);
+        assert_eq!(settings.keyring_timeout_secs, 0);
+        assert_eq!(
+            settings.credential_helper_coexistence,
+            HelperCoexistence::Ask
+        );
+        assert_eq!(
+            settings.keyring_fallback,
+            KeyringFallback::PlaintextWithWarning
+        );
+        assert_eq!(settings.plugin_timeout_secs, 10);
+        assert_eq!(settings.plugin_failure, PluginFailure::Warn);
+        assert_eq!(settings.output, OutputStyle::Auto);
+        assert!(settings.credential_helper_hosts.is_empty());
+    }
+
+    #[test]
+    fn test_host_helper_coexistence() {
+        let settings: Settings = toml::from_str(
+            "[credential_helper_hosts]\n\"*.corp.com\" = \"prepend\"\n\"git.corp.com\" = \"replace\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            settings.host_helper_coexistence("GIT.corp.com"),
+            Some(HelperCoexistence::Replace)
+        );
+        assert_eq!(
+            settings.host_helper_coexistence("ci.corp.com"),
+            Some(HelperCoexistence::Prepend)
+        );
+        assert_eq!(settings.host_helper_coexistence("github.com"), None);
+    }
+
+    #[test]
+    fn test_enums_are_kebab_case() {
+        let settings: Settings = toml::from_str("secret_store = \"file\"").unwrap();
+        assert_eq!(settings.secret_store, SecretStoreKind::File);
+
+        let settings: Settings =
+            toml::from_str("keyring_fallback = \"plaintext-with-warning\"").unwrap();
+        assert_eq!(
+            settings.keyring_fallback,
+            KeyringFallback::PlaintextWithWarning
+        );
+        let settings: Settings = toml::from_str("keyring_fallback = \"encrypted-file\"").unwrap();
+        assert_eq!(settings.keyring_fallback, KeyringFallback::EncryptedFile);
+    }
+}
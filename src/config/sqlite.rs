@@ -0,0 +1,256 @@
+// src/config/sqlite.rs
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use super::storage::{is_writable, ConfigStorage, ConfigStore};
+use super::{State, SwitchRecord};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS profiles (name TEXT PRIMARY KEY, data TEXT NOT NULL);
+CREATE TABLE IF NOT EXISTS meta (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+CREATE TABLE IF NOT EXISTS repos (path TEXT PRIMARY KEY, profile TEXT NOT NULL);
+CREATE TABLE IF NOT EXISTS last_used (profile TEXT PRIMARY KEY, at TEXT NOT NULL);
+CREATE TABLE IF NOT EXISTS history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    at TEXT NOT NULL,
+    profile TEXT NOT NULL,
+    repo TEXT
+);
+";
+
+/// Profiles, settings and state in one SQLite database. Profiles are stored one row each as
+/// JSON and only rows that changed are written, so large installations don't rewrite
+/// everything on each save.
+pub struct SqliteStore {
+    path: PathBuf,
+}
+
+impl SqliteStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    fn connect(&self) -> Result<Connection> {
+        let connection = Connection::open(&self.path)
+            .with_context(|| format!("Failed to open database {:?}", self.path))?;
+        connection
+            .execute_batch(SCHEMA)
+            .with_context(|| format!("Failed to initialize database {:?}", self.path))?;
+        Ok(connection)
+    }
+}
+
+fn get_meta(connection: &Connection, key: &str) -> Result<Option<String>> {
+    Ok(connection
+        .query_row("SELECT value FROM meta WHERE key = ?1", [key], |row| {
+            row.get(0)
+        })
+        .optional()?)
+}
+
+fn set_meta(connection: &Connection, key: &str, value: Option<&str>) -> Result<()> {
+    match value {
+        Some(value) => connection.execute(
+            "INSERT INTO meta (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![key, value],
+        )?,
+        None => connection.execute("DELETE FROM meta WHERE key = ?1", [key])?,
+    };
+    Ok(())
+}
+
+fn parse_time(value: &str) -> Result<DateTime<Utc>> {
+    Ok(DateTime::parse_from_rfc3339(value)
+        .with_context(|| format!("Invalid timestamp '{}' in database", value))?
+        .with_timezone(&Utc))
+}
+
+impl ConfigStore for SqliteStore {
+    fn name(&self) -> &'static str {
+        "sqlite"
+    }
+
+    fn paths(&self) -> Vec<PathBuf> {
+        vec![self.path.clone()]
+    }
+
+    fn read_only_path(&self) -> Option<PathBuf> {
+        (!is_writable(&self.path)).then(|| self.path.clone())
+    }
+
+    fn load_config(&self) -> Result<ConfigStorage> {
+        let connection = self.connect()?;
+        let mut config = ConfigStorage::default();
+
+        let mut statement = connection.prepare("SELECT name, data FROM profiles")?;
+        let rows = statement.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        for row in rows {
+            let (name, data) = row?;
+            let profile = serde_json::from_str(&data)
+                .with_context(|| format!("Failed to parse profile '{}' from database", name))?;
+            config.profiles.insert(name, profile);
+        }
+
+        if let Some(settings) = get_meta(&connection, "settings")? {
+            config.settings = serde_json::from_str(&settings)
+                .context("Failed to parse settings from database")?;
+        }
+        Ok(config)
+    }
+
+    fn save_config(&self, config: &ConfigStorage) -> Result<()> {
+        let mut connection = self.connect()?;
+        let transaction = connection.transaction()?;
+
+        for (name, profile) in &config.profiles {
+            let data = serde_json::to_string(profile)
+                .with_context(|| format!("Failed to serialize profile '{}'", name))?;
+            transaction.execute(
+                "INSERT INTO profiles (name, data) VALUES (?1, ?2)
+                 ON CONFLICT(name) DO UPDATE SET data = excluded.data
+                 WHERE data != excluded.data",
+                params![name, data],
+            )?;
+        }
+        let stored: Vec<String> = transaction
+            .prepare("SELECT name FROM profiles")?
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        let current: HashSet<&String> = config.profiles.keys().collect();
+        for name in stored.iter().filter(|name| !current.contains(name)) {
+            transaction.execute("DELETE FROM profiles WHERE name = ?1", [name])?;
+        }
+
+        let settings =
+            serde_json::to_string(&config.settings).context("Failed to serialize settings")?;
+        set_meta(&transaction, "settings", Some(&settings))?;
+
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn load_state(&self) -> Result<Option<State>> {
+        let connection = self.connect()?;
+        let mut state = State {
+            current_profile: get_meta(&connection, "current_profile")?,
+            ..Default::default()
+        };
+
+        let mut statement = connection.prepare("SELECT path, profile FROM repos")?;
+        for row in statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))? {
+            let (path, profile) = row?;
+            state.repos.insert(path, profile);
+        }
+
+        let mut statement = connection.prepare("SELECT profile, at FROM last_used")?;
+        for row in statement.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })? {
+            let (profile, at) = row?;
+            state.last_used.insert(profile, parse_time(&at)?);
+        }
+
+        let mut statement =
+            connection.prepare("SELECT at, profile, repo FROM history ORDER BY id")?;
+        for row in statement.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<String>>(2)?,
+            ))
+        })? {
+            let (at, profile, repo) = row?;
+            state.history.push(SwitchRecord {
+                at: parse_time(&at)?,
+                profile,
+                repo,
+            });
+        }
+        Ok(Some(state))
+    }
+
+    fn save_state(&self, state: &State) -> Result<()> {
+        let mut connection = self.connect()?;
+        let transaction = connection.transaction()?;
+
+        set_meta(
+            &transaction,
+            "current_profile",
+            state.current_profile.as_deref(),
+        )?;
+        transaction.execute("DELETE FROM repos", [])?;
+        for (path, profile) in &state.repos {
+            transaction.execute(
+                "INSERT INTO repos (path, profile) VALUES (?1, ?2)",
+                params![path, profile],
+            )?;
+        }
+        transaction.execute("DELETE FROM last_used", [])?;
+        for (profile, at) in &state.last_used {
+            transaction.execute(
+                "INSERT INTO last_used (profile, at) VALUES (?1, ?2)",
+                params![profile, at.to_rfc3339()],
+            )?;
+        }
+        transaction.execute("DELETE FROM history", [])?;
+        for record in &state.history {
+            transaction.execute(
+                "INSERT INTO history (at, profile, repo) VALUES (?1, ?2, ?3)",
+                params![record.at.to_rfc3339(), record.profile, record.repo],
+            )?;
+        }
+
+        transaction.commit()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Profile, SecretStoreKind};
+    use chrono::TimeZone;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_sqlite_store_round_trip() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let store = SqliteStore::new(temp_dir.path().join("gitp.db"));
+        assert!(store.load_config()?.profiles.is_empty());
+
+        let mut config = ConfigStorage::default();
+        for name in ["work", "oss"] {
+            config.profiles.insert(
+                name.to_string(),
+                Profile::new(
+                    name.to_string(),
+                    "John Doe".to_string(),
+                    format!("john@{}.com", name),
+                ),
+            );
+        }
+        config.settings.secret_store = SecretStoreKind::File;
+        store.save_config(&config)?;
+
+        config.profiles.remove("oss");
+        store.save_config(&config)?;
+        let loaded = store.load_config()?;
+        assert_eq!(loaded.profiles, config.profiles);
+        assert_eq!(loaded.settings, config.settings);
+
+        let mut state = State::default();
+        let at = Utc.with_ymd_and_hms(2026, 1, 2, 3, 4, 5).unwrap();
+        state.record_switch("work", None, at);
+        state.record_switch("work", Some("/src/app".to_string()), at);
+        store.save_state(&state)?;
+        assert_eq!(store.load_state()?, Some(state));
+        Ok(())
+    }
+}
@@ -0,0 +1,149 @@
+// src/config/state.rs
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Number of switches kept in `history`.
+pub const HISTORY_LIMIT: usize = 100;
+
+/// Runtime state gitp keeps outside config.toml, so profile definitions stay stable enough to
+/// keep in a dotfiles repository.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+pub struct State {
+    /// Profile last applied globally
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current_profile: Option<String>,
+    /// Work trees a profile was applied to with `use --local`, mapped to the profile name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub repos: BTreeMap<String, String>,
+    /// When each profile was last applied, at any scope
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub last_used: BTreeMap<String, DateTime<Utc>>,
+    /// Most recent switches, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<SwitchRecord>,
+}
+
+/// One `gitp use`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SwitchRecord {
+    pub at: DateTime<Utc>,
+    pub profile: String,
+    /// Work tree for a local switch; `None` for a global one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repo: Option<String>,
+}
+
+impl State {
+    /// Records that `profile` was applied to `repo`, or globally when `repo` is `None`.
+    pub fn record_switch(&mut self, profile: &str, repo: Option<String>, at: DateTime<Utc>) {
+        match &repo {
+            Some(repo) => {
+                self.repos.insert(repo.clone(), profile.to_string());
+            }
+            None => self.current_profile = Some(profile.to_string()),
+        }
+        self.last_used.insert(profile.to_string(), at);
+        self.history.push(SwitchRecord {
+            at,
+            profile: profile.to_string(),
+            repo,
+        });
+        if self.history.len() > HISTORY_LIMIT {
+            self.history.drain(..self.history.len() - HISTORY_LIMIT);
+        }
+    }
+
+    /// The profile in effect for `repo`: its registered profile, else the global one.
+    pub fn current_for(&self, repo: Option<&str>) -> Option<&str> {
+        repo.and_then(|repo| self.repos.get(repo))
+            .or(self.current_profile.as_ref())
+            .map(String::as_str)
+    }
+
+    /// Points every reference to `old` at `new`.
+    pub fn rename_profile(&mut self, old: &str, new: &str) {
+        if self.current_profile.as_deref() == Some(old) {
+            self.current_profile = Some(new.to_string());
+        }
+        for profile in self.repos.values_mut() {
+            if profile == old {
+                *profile = new.to_string();
+            }
+        }
+        if let Some(at) = self.last_used.remove(old) {
+            self.last_used.insert(new.to_string(), at);
+        }
+        for record in self.history.iter_mut().filter(|r| r.profile == old) {
+            record.profile = new.to_string();
+        }
+    }
+
+    /// Drops the global selection and last-used time of a removed profile. The history is kept
+    /// as a log; repo registry entries are handled by `remove` itself.
+    pub fn forget_profile(&mut self, name: &str) {
+        if self.current_profile.as_deref() == Some(name) {
+            self.current_profile = None;
+        }
+        self.last_used.remove(name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_record_switch() {
+        let mut state = State::default();
+        let at = Utc.with_ymd_and_hms(2026, 1, 2, 3, 4, 5).unwrap();
+        state.record_switch("work", None, at);
+        state.record_switch("oss", Some("/src/oss".to_string()), at);
+
+        assert_eq!(state.current_profile.as_deref(), Some("work"));
+        assert_eq!(state.current_for(Some("/src/oss")), Some("oss"));
+        assert_eq!(state.current_for(Some("/elsewhere")), Some("work"));
+        assert_eq!(state.last_used.get("oss"), Some(&at));
+        assert_eq!(state.history.len(), 2);
+
+        for _ in 0..HISTORY_LIMIT {
+            state.record_switch("work", None, at);
+        }
+        assert_eq!(state.history.len(), HISTORY_LIMIT);
+        assert!(state.history.iter().all(|r| r.profile == "work"));
+    }
+
+    #[test]
+    fn test_rename_and_forget() {
+        let mut state = State::default();
+        let at = Utc.with_ymd_and_hms(2026, 1, 2, 3, 4, 5).unwrap();
+        state.record_switch("work", None, at);
+        state.record_switch("work", Some("/src/app".to_string()), at);
+
+        state.rename_profile("work", "acme");
+        assert_eq!(state.current_profile.as_deref(), Some("acme"));
+        assert_eq!(
+            state.repos.get("/src/app").map(String::as_str),
+            Some("acme")
+        );
+        assert!(state.last_used.contains_key("acme") && !state.last_used.contains_key("work"));
+        assert!(state.history.iter().all(|r| r.profile == "acme"));
+
+        state.forget_profile("acme");
+        assert!(state.current_profile.is_none());
+        assert!(state.last_used.is_empty());
+        assert_eq!(state.history.len(), 2);
+    }
+
+    #[test]
+    fn test_state_toml_round_trip() {
+        let mut state = State::default();
+        let at = Utc.with_ymd_and_hms(2026, 1, 2, 3, 4, 5).unwrap();
+        state.record_switch("work", Some("/src/app".to_string()), at);
+        let serialized = toml::to_string_pretty(&state).unwrap();
+        assert_eq!(toml::from_str::<State>(&serialized).unwrap(), state);
+        assert_eq!(toml::from_str::<State>("").unwrap(), State::default());
+    }
+}
@@ -1,24 +1,47 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use super::Profile; // Assuming Profile is in super (config/mod.rs or config/profile.rs)
+use super::{Profile, Settings, State}; // Assuming Profile is in super (config/mod.rs or config/profile.rs)
 
 const CONFIG_DIR_NAME: &str = "gitp";
 const CONFIG_FILE_NAME: &str = "config.toml";
+const STATE_FILE_NAME: &str = "state.toml";
+const DATABASE_FILE_NAME: &str = "gitp.db";
 
 // Re-define Config struct here or ensure it's accessible
 // For now, let's assume Config is defined in config/mod.rs and we'll pass it around
 // If Config were defined here, it would look like:
 #[derive(Debug, Serialize, Deserialize, Default, Clone)]
 pub struct ConfigStorage {
+    #[serde(serialize_with = "serialize_sorted")]
     pub profiles: HashMap<String, Profile>,
+    /// Legacy location of `State::current_profile`; read once to seed state.toml, never written
+    #[serde(default, skip_serializing)]
     pub current_profile: Option<String>,
+    #[serde(default, skip_serializing_if = "Settings::is_default")]
+    pub settings: Settings,
+    /// Legacy location of `State::repos`; read once to seed state.toml, never written
+    #[serde(default, skip_serializing)]
+    pub repos: BTreeMap<String, String>,
 }
 
-fn get_config_path() -> Result<PathBuf> {
+/// Serializes a map with its keys sorted so config.toml doesn't reorder itself on every save.
+pub(super) fn serialize_sorted<S, V>(
+    map: &HashMap<String, V>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+    V: Serialize,
+{
+    map.iter().collect::<BTreeMap<_, _>>().serialize(serializer)
+}
+
+/// Returns the gitp config directory (e.g. ~/.config/gitp), creating it if needed.
+pub fn get_config_dir() -> Result<PathBuf> {
     let config_dir = dirs::config_dir()
         .ok_or_else(|| anyhow::anyhow!("Could not find user's config directory"))?
         .join(CONFIG_DIR_NAME);
@@ -28,41 +51,212 @@ fn get_config_path() -> Result<PathBuf> {
             .with_context(|| format!("Failed to create config directory at {:?}", config_dir))?;
     }
 
-    Ok(config_dir.join(CONFIG_FILE_NAME))
+    Ok(config_dir)
+}
+
+fn get_config_path() -> Result<PathBuf> {
+    Ok(get_config_dir()?.join(CONFIG_FILE_NAME))
+}
+
+/// Returns the path of state.toml: under the XDG state directory (~/.local/state/gitp) where
+/// there is one, otherwise the local data directory, so it stays out of the config directory.
+pub fn get_state_path() -> Result<PathBuf> {
+    let state_dir = dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .ok_or_else(|| anyhow::anyhow!("Could not find user's state directory"))?
+        .join(CONFIG_DIR_NAME);
+    Ok(state_dir.join(STATE_FILE_NAME))
+}
+
+/// Returns the path of the SQLite database used instead of the TOML files once
+/// `gitp storage migrate sqlite` has run.
+pub fn get_database_path() -> Result<PathBuf> {
+    Ok(get_config_dir()?.join(DATABASE_FILE_NAME))
 }
 
-pub fn load_config_from_storage() -> Result<ConfigStorage> {
-    let config_path = get_config_path()?;
+/// Where profiles, settings and runtime state are persisted.
+pub trait ConfigStore {
+    /// Short name shown to the user, e.g. `toml`
+    fn name(&self) -> &'static str;
+    /// Files holding the data
+    fn paths(&self) -> Vec<PathBuf>;
+    fn load_config(&self) -> Result<ConfigStorage>;
+    fn save_config(&self, config: &ConfigStorage) -> Result<()>;
+    /// Loads the runtime state, or `None` when it has never been saved.
+    fn load_state(&self) -> Result<Option<State>>;
+    fn save_state(&self, state: &State) -> Result<()>;
+    /// The file holding profiles and settings, when it can't be written
+    fn read_only_path(&self) -> Option<PathBuf>;
+}
 
-    if !config_path.exists() {
-        // If the config file doesn't exist, return a default configuration
-        return Ok(ConfigStorage::default());
+/// Whether `path` can be written: opened for writing when it exists, otherwise created in its
+/// directory. Both are tried for real, since permission bits don't say who owns the file.
+pub(crate) fn is_writable(path: &Path) -> bool {
+    if path.exists() {
+        return fs::OpenOptions::new().append(true).open(path).is_ok();
     }
+    let Some(dir) = path.parent() else {
+        return false;
+    };
+    let probe = dir.join(format!(".gitp-write-test-{}", std::process::id()));
+    match fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&probe)
+    {
+        Ok(_) => {
+            let _ = fs::remove_file(&probe);
+            true
+        }
+        Err(_) => false,
+    }
+}
 
-    let config_content = fs::read_to_string(&config_path)
-        .with_context(|| format!("Failed to read config file from {:?}", config_path))?;
+/// The configuration file that can't be written, when gitp runs read-only (e.g. a config
+/// managed by a corporate image).
+pub fn read_only_path() -> Result<Option<PathBuf>> {
+    Ok(open_store()?.read_only_path())
+}
 
-    if config_content.trim().is_empty() {
-        // If the file is empty, treat it as a default configuration
-        return Ok(ConfigStorage::default());
+/// Fails with the read-only file for commands that change the configuration, before they do
+/// anything. Commands that only read it carry on.
+pub fn require_writable(action: &str) -> Result<()> {
+    if let Some(path) = read_only_path()? {
+        anyhow::bail!(
+            "{} changes the gitp configuration, but {} is read-only. Commands that only read \
+             it (list, show, current, ...) still work.",
+            action,
+            path.display()
+        );
     }
+    Ok(())
+}
 
-    let config: ConfigStorage = toml::from_str(&config_content)
-        .with_context(|| format!("Failed to parse TOML from {:?}", config_path))?;
+/// Opens the store in use: the SQLite database if one exists, otherwise the TOML files.
+pub fn open_store() -> Result<Box<dyn ConfigStore>> {
+    let database_path = get_database_path()?;
+    if database_path.exists() {
+        #[cfg(feature = "sqlite")]
+        return Ok(Box::new(super::sqlite::SqliteStore::new(database_path)));
+        #[cfg(not(feature = "sqlite"))]
+        anyhow::bail!(
+            "Configuration is stored in {:?}, but this gitp was built without the 'sqlite' feature.",
+            database_path
+        );
+    }
+    Ok(Box::new(TomlStore::open()?))
+}
 
-    Ok(config)
+/// config.toml for profiles and settings, state.toml for runtime state.
+pub struct TomlStore {
+    config_path: PathBuf,
+    state_path: PathBuf,
 }
 
-pub fn save_config_to_storage(config: &ConfigStorage) -> Result<()> {
-    let config_path = get_config_path()?;
+impl TomlStore {
+    pub fn new(config_path: PathBuf, state_path: PathBuf) -> Self {
+        Self {
+            config_path,
+            state_path,
+        }
+    }
 
-    let toml_string =
-        toml::to_string_pretty(config).context("Failed to serialize config to TOML string")?;
+    /// The store at the default locations.
+    pub fn open() -> Result<Self> {
+        Ok(Self::new(get_config_path()?, get_state_path()?))
+    }
+}
 
-    fs::write(&config_path, toml_string)
-        .with_context(|| format!("Failed to write config to {:?}", config_path))?;
+impl ConfigStore for TomlStore {
+    fn name(&self) -> &'static str {
+        "toml"
+    }
 
-    Ok(())
+    fn paths(&self) -> Vec<PathBuf> {
+        vec![self.config_path.clone(), self.state_path.clone()]
+    }
+
+    fn read_only_path(&self) -> Option<PathBuf> {
+        (!is_writable(&self.config_path)).then(|| self.config_path.clone())
+    }
+
+    fn load_config(&self) -> Result<ConfigStorage> {
+        let config_path = &self.config_path;
+
+        if !config_path.exists() {
+            // If the config file doesn't exist, return a default configuration
+            return Ok(ConfigStorage::default());
+        }
+
+        let config_content = fs::read_to_string(config_path)
+            .with_context(|| format!("Failed to read config file from {:?}", config_path))?;
+
+        if config_content.trim().is_empty() {
+            // If the file is empty, treat it as a default configuration
+            return Ok(ConfigStorage::default());
+        }
+
+        let config: ConfigStorage = toml::from_str(&config_content)
+            .with_context(|| format!("Failed to parse TOML from {:?}", config_path))?;
+
+        Ok(config)
+    }
+
+    fn save_config(&self, config: &ConfigStorage) -> Result<()> {
+        let config_path = &self.config_path;
+
+        let toml_string =
+            toml::to_string_pretty(config).context("Failed to serialize config to TOML string")?;
+
+        // Leave the file alone when nothing changed, e.g. after a plain `gitp use`
+        if fs::read_to_string(config_path).is_ok_and(|existing| existing == toml_string) {
+            return Ok(());
+        }
+
+        if let Some(config_dir) = config_path.parent() {
+            fs::create_dir_all(config_dir).with_context(|| {
+                format!("Failed to create config directory at {:?}", config_dir)
+            })?;
+        }
+
+        fs::write(config_path, toml_string)
+            .with_context(|| format!("Failed to write config to {:?}", config_path))?;
+
+        Ok(())
+    }
+
+    fn load_state(&self) -> Result<Option<State>> {
+        let state_path = &self.state_path;
+
+        if !state_path.exists() {
+            return Ok(None);
+        }
+
+        let state_content = fs::read_to_string(state_path)
+            .with_context(|| format!("Failed to read state file from {:?}", state_path))?;
+
+        let state: State = toml::from_str(&state_content)
+            .with_context(|| format!("Failed to parse TOML from {:?}", state_path))?;
+
+        Ok(Some(state))
+    }
+
+    fn save_state(&self, state: &State) -> Result<()> {
+        let state_path = &self.state_path;
+
+        if let Some(state_dir) = state_path.parent() {
+            fs::create_dir_all(state_dir)
+                .with_context(|| format!("Failed to create state directory at {:?}", state_dir))?;
+        }
+
+        let toml_string =
+            toml::to_string_pretty(state).context("Failed to serialize state to TOML string")?;
+
+        fs::write(state_path, toml_string)
+            .with_context(|| format!("Failed to write state to {:?}", state_path))?;
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -71,6 +265,71 @@ mod tests {
     use crate::config::profile::GitConfig; // Adjust path as necessary
     use tempfile::tempdir;
 
+    #[test]
+    fn test_toml_store_round_trip() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let store = TomlStore::new(
+            temp_dir.path().join("gitp").join(CONFIG_FILE_NAME),
+            temp_dir.path().join("state").join(STATE_FILE_NAME),
+        );
+        assert!(store.load_config()?.profiles.is_empty());
+        assert!(store.load_state()?.is_none());
+
+        let mut config = ConfigStorage::default();
+        config.profiles.insert(
+            "work".to_string(),
+            Profile::new(
+                "work".to_string(),
+                "John Doe".to_string(),
+                "john@company.com".to_string(),
+            ),
+        );
+        let state = State {
+            current_profile: Some("work".to_string()),
+            ..Default::default()
+        };
+        store.save_config(&config)?;
+        store.save_state(&state)?;
+
+        assert_eq!(store.load_config()?.profiles, config.profiles);
+        assert_eq!(store.load_state()?, Some(state));
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_only_path() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let config_path = temp_dir.path().join(CONFIG_FILE_NAME);
+        let store = TomlStore::new(config_path.clone(), temp_dir.path().join(STATE_FILE_NAME));
+        // A missing file in a writable directory can be created, and the probe is cleaned up
+        assert_eq!(store.read_only_path(), None);
+        assert_eq!(fs::read_dir(temp_dir.path())?.count(), 0);
+
+        store.save_config(&ConfigStorage::default())?;
+        assert_eq!(store.read_only_path(), None);
+
+        let missing_dir = TomlStore::new(
+            temp_dir.path().join("missing").join(CONFIG_FILE_NAME),
+            temp_dir.path().join(STATE_FILE_NAME),
+        );
+        assert!(missing_dir.read_only_path().is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_state_not_serialized_to_config() -> Result<()> {
+        let legacy =
+            "current_profile = \"work\"\n\n[profiles]\n\n[repos]\n\"/src/app\" = \"work\"\n";
+        let config: ConfigStorage = toml::from_str(legacy)?;
+        assert_eq!(config.current_profile.as_deref(), Some("work"));
+        assert_eq!(config.repos.len(), 1);
+
+        let serialized = toml::to_string_pretty(&config)?;
+        assert!(!serialized.contains("current_profile"));
+        assert!(!serialized.contains("/src/app"));
+        Ok(())
+    }
+
     // Helper to set up a temporary config directory for tests
     fn _setup_temp_config_env(temp_dir: &std::path::Path) -> Result<()> {
         let mock_config_path = temp_dir.join(CONFIG_DIR_NAME);
@@ -154,12 +413,28 @@ mod tests {
                 user_name: "Test User".to_string(),
                 user_email: "test@example.com".to_string(),
                 user_signingkey: None,
+                core_autocrlf: None,
+                core_eol: None,
             },
             ssh_key: None,
             ssh_key_host: None, // Added missing field
+            ssh_key_fingerprint: None,
+            ssh_host_alias: None,
+            ssh_user: None,
+            ssh_managed: true,
+            ssh_match_dirs: Vec::new(),
+            ssh_control: None,
+            ssh_add_keys_to_agent: None,
+            ssh_use_keychain: false,
+            ssh_hosts: Vec::new(),
             gpg_key: None,
-            https_credentials: None,
+            host_signing: Vec::new(),
+            https_credentials: Vec::new(),
             custom_config: HashMap::new(),
+            extras: Default::default(),
+            sendemail: None,
+            notes: None,
+            auto_dirs: Vec::new(),
         };
         original_config
             .profiles
@@ -0,0 +1,116 @@
+// src/config/system.rs
+// Profiles an administrator provisions for every user of a machine, merged read-only beneath
+// the user's own
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use super::Profile;
+
+/// Environment variable naming another directory of system profiles
+pub const SYSTEM_PROFILES_ENV_VAR: &str = "GITP_SYSTEM_PROFILES";
+
+/// A profile from the system store, as read from its file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SystemProfile {
+    pub profile: Profile,
+    pub path: PathBuf,
+}
+
+/// The directory of system profiles: `GITP_SYSTEM_PROFILES` when set, otherwise
+/// `/etc/gitp/profiles.d` (`%ProgramData%\gitp\profiles.d` on Windows).
+pub fn system_profiles_dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os(SYSTEM_PROFILES_ENV_VAR).filter(|d| !d.is_empty()) {
+        return Some(PathBuf::from(dir));
+    }
+    if cfg!(windows) {
+        std::env::var_os("ProgramData")
+            .map(|dir| PathBuf::from(dir).join("gitp").join("profiles.d"))
+    } else {
+        Some(PathBuf::from("/etc/gitp/profiles.d"))
+    }
+}
+
+/// The profiles of the system store, read once per process so problems are reported once.
+pub fn system_profiles() -> &'static BTreeMap<String, SystemProfile> {
+    static PROFILES: OnceLock<BTreeMap<String, SystemProfile>> = OnceLock::new();
+    PROFILES.get_or_init(|| {
+        system_profiles_dir()
+            .map(|dir| load_system_profiles(&dir))
+            .unwrap_or_default()
+    })
+}
+
+/// The `*.toml` files of `dir`, sorted; empty when it doesn't exist.
+pub fn profile_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml") && path.is_file())
+        .collect();
+    files.sort();
+    files
+}
+
+/// The profiles of `dir`, each file holding one profile as `gitp export` writes it. Files
+/// that can't be read are reported and skipped, and of two files with the same profile name
+/// the first wins, so one bad file doesn't take the others down.
+pub fn load_system_profiles(dir: &Path) -> BTreeMap<String, SystemProfile> {
+    let mut profiles: BTreeMap<String, SystemProfile> = BTreeMap::new();
+    for path in profile_files(dir) {
+        let profile = fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|content| Ok(toml::from_str::<Profile>(&content)?));
+        match profile {
+            Ok(profile) if profiles.contains_key(&profile.name) => eprintln!(
+                "Warning: ignoring {}: profile '{}' is already defined in {}.",
+                path.display(),
+                profile.name,
+                profiles[&profile.name].path.display()
+            ),
+            Ok(profile) => {
+                profiles.insert(profile.name.clone(), SystemProfile { profile, path });
+            }
+            Err(e) => eprintln!(
+                "Warning: ignoring system profile {}: {:#}",
+                path.display(),
+                e
+            ),
+        }
+    }
+    profiles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_load_system_profiles() {
+        let dir = tempdir().unwrap();
+        let profile = |name: &str| {
+            toml::to_string(&Profile::new(
+                name.to_string(),
+                "Acme Bot".to_string(),
+                "bot@acme.com".to_string(),
+            ))
+            .unwrap()
+        };
+        fs::write(dir.path().join("acme.toml"), profile("acme")).unwrap();
+        fs::write(dir.path().join("b-acme.toml"), profile("acme")).unwrap();
+        fs::write(dir.path().join("broken.toml"), "name = ").unwrap();
+        fs::write(dir.path().join("notes.txt"), profile("notes")).unwrap();
+
+        let profiles = load_system_profiles(dir.path());
+        assert_eq!(profiles.keys().collect::<Vec<_>>(), ["acme"]);
+        assert_eq!(profiles["acme"].path, dir.path().join("acme.toml"));
+        assert!(load_system_profiles(&dir.path().join("missing")).is_empty());
+    }
+}
@@ -0,0 +1,109 @@
+// src/credentials/cache.rs
+// Keeps resolved tokens in git's credential-cache daemon for a while, so fetches don't reach
+// the keyring (and its unlock prompt) every time
+
+use anyhow::{bail, Context, Result};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use crate::config::{is_host_pattern, HttpsCredentials};
+
+/// Socket of gitp's own credential-cache daemon. Keeping it apart from git's default one means
+/// flushing gitp's tokens leaves the user's cached passwords alone.
+pub fn socket_path() -> Option<PathBuf> {
+    dirs::runtime_dir()
+        .or_else(dirs::cache_dir)
+        .map(|dir| dir.join("gitp").join("credential-cache").join("socket"))
+}
+
+/// Runs `git credential-cache <args>` with `input` on stdin and returns its output.
+fn credential_cache(args: &[&str], input: &str) -> Result<String> {
+    let socket = socket_path().context("No cache or runtime directory for the socket.")?;
+    let mut child = Command::new("git")
+        .arg("credential-cache")
+        .args(args)
+        .arg("--socket")
+        .arg(&socket)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .context("Failed to execute 'git credential-cache'.")?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(input.as_bytes())
+            .context("Failed to write to 'git credential-cache'.")?;
+    }
+    let output = child
+        .wait_with_output()
+        .context("Failed to wait for 'git credential-cache'.")?;
+    if !output.status.success() {
+        bail!("'git credential-cache {}' failed.", args.join(" "));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// A credential description in git's helper protocol; fields left out match anything.
+fn description(host: Option<&str>, username: &str, password: Option<&str>) -> String {
+    let mut text = String::from("protocol=https\n");
+    if let Some(host) = host {
+        text.push_str(&format!("host={}\n", host));
+    }
+    text.push_str(&format!("username={}\n", username));
+    if let Some(password) = password {
+        text.push_str(&format!("password={}\n", password));
+    }
+    text.push('\n');
+    text
+}
+
+/// The cached token for `username` at `host`, if the daemon is running and still has it.
+pub fn get(host: &str, username: &str) -> Result<Option<String>> {
+    let output = credential_cache(&["get"], &description(Some(host), username, None))?;
+    Ok(output
+        .lines()
+        .find_map(|line| line.strip_prefix("password="))
+        .map(str::to_string))
+}
+
+/// Caches `token` for `minutes`, starting the daemon when needed.
+pub fn store(host: &str, username: &str, token: &str, minutes: u32) -> Result<()> {
+    let timeout = (u64::from(minutes) * 60).to_string();
+    credential_cache(
+        &["--timeout", &timeout, "store"],
+        &description(Some(host), username, Some(token)),
+    )?;
+    Ok(())
+}
+
+/// Drops the cached tokens of `creds`. For a host pattern that is every host cached for the
+/// username, since the daemon has no notion of patterns.
+pub fn forget(creds: &HttpsCredentials) -> Result<()> {
+    let host = (!is_host_pattern(&creds.host)).then_some(creds.host.as_str());
+    credential_cache(&["erase"], &description(host, &creds.username, None))?;
+    Ok(())
+}
+
+/// Stops the daemon, dropping every token it holds.
+pub fn flush_all() -> Result<()> {
+    credential_cache(&["exit"], "")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_description() {
+        assert_eq!(
+            description(Some("github.com"), "octocat", Some("t")),
+            "protocol=https\nhost=github.com\nusername=octocat\npassword=t\n\n"
+        );
+        assert_eq!(
+            description(None, "octocat", None),
+            "protocol=https\nusername=octocat\n\n"
+        );
+    }
+}
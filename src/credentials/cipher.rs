@@ -0,0 +1,148 @@
+// src/credentials/cipher.rs
+// Passphrase-based encryption shared by the file store and the sealed values of exports
+
+use anyhow::{anyhow, bail, Context, Result};
+use argon2::Argon2;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+
+use crate::utils::output::theme;
+use crate::utils::prompt::Password;
+use crate::utils::terminal::can_prompt;
+
+pub const SALT_LEN: usize = 16;
+pub const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 24;
+
+/// A key derived from a passphrase with [`derive_key`].
+pub type Key = [u8; KEY_LEN];
+
+/// How [`obtain_passphrase`] asks for the passphrase of one kind of secret.
+pub struct PassphrasePrompt {
+    /// Environment variable consulted before prompting
+    pub env_var: &'static str,
+    /// What the passphrase unlocks, for the error without a terminal
+    pub unlocks: &'static str,
+    /// Prompt for a new passphrase, which is asked twice
+    pub new: &'static str,
+    /// Prompt for an existing passphrase
+    pub existing: &'static str,
+}
+
+/// Reads the passphrase from `prompt.env_var` or prompts for it on a terminal; a `new` one is
+/// asked twice.
+pub fn obtain_passphrase(prompt: &PassphrasePrompt, new: bool) -> Result<String> {
+    if let Ok(passphrase) = std::env::var(prompt.env_var) {
+        if !passphrase.is_empty() {
+            return Ok(passphrase);
+        }
+    }
+    if !can_prompt() {
+        bail!(
+            "No terminal is available to prompt for the passphrase of {}. Set {} to provide it \
+             non-interactively.",
+            prompt.unlocks,
+            prompt.env_var
+        );
+    }
+
+    let theme = theme();
+    let passphrase = if new {
+        Password::with_theme(&theme)
+            .with_prompt(prompt.new)
+            .with_confirmation("Confirm passphrase", "Passphrases do not match.")
+            .interact()
+    } else {
+        Password::with_theme(&theme)
+            .with_prompt(prompt.existing)
+            .interact()
+    }
+    .context("Failed to read passphrase.")?;
+    if passphrase.is_empty() {
+        bail!("Passphrase cannot be empty.");
+    }
+    Ok(passphrase)
+}
+
+pub fn random_salt() -> [u8; SALT_LEN] {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    salt
+}
+
+/// Derives a key from `passphrase` with Argon2id. Slow on purpose; callers derive once per
+/// salt.
+pub fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Key> {
+    let mut key = [0u8; KEY_LEN];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow!("Failed to derive the key: {}", e))?;
+    Ok(key)
+}
+
+/// Encrypts `plaintext` with XChaCha20-Poly1305 under a random nonce. `aad` isn't stored but
+/// has to be given again to decrypt. Returns the nonce and the ciphertext.
+pub fn encrypt(key: &Key, plaintext: &[u8], aad: &[u8]) -> Result<(Vec<u8>, Vec<u8>)> {
+    let cipher = XChaCha20Poly1305::new(key.into());
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
+        .map_err(|_| anyhow!("encryption failed"))?;
+    Ok((nonce.to_vec(), ciphertext))
+}
+
+/// Reverses [`encrypt`]. Fails for a wrong key, a changed ciphertext or a different `aad`.
+pub fn decrypt(key: &Key, nonce: &[u8], ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+    if nonce.len() != NONCE_LEN {
+        bail!("invalid nonce length");
+    }
+    let cipher = XChaCha20Poly1305::new(key.into());
+    cipher
+        .decrypt(
+            XNonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad,
+            },
+        )
+        .map_err(|_| anyhow!("decryption failed"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_decrypt() -> Result<()> {
+        let salt = random_salt();
+        let key = derive_key("correct horse", &salt)?;
+        let (nonce, ciphertext) = encrypt(&key, b"ghp_secret", b"github.com")?;
+        assert_eq!(
+            decrypt(&key, &nonce, &ciphertext, b"github.com")?,
+            b"ghp_secret"
+        );
+
+        // The associated data, the key and the nonce all have to match
+        assert!(decrypt(&key, &nonce, &ciphertext, b"gitlab.com").is_err());
+        let wrong = derive_key("battery staple", &salt)?;
+        assert!(decrypt(&wrong, &nonce, &ciphertext, b"github.com").is_err());
+        assert!(decrypt(&key, &nonce[1..], &ciphertext, b"github.com").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_derive_key_depends_on_salt() -> Result<()> {
+        let a = derive_key("passphrase", b"0123456789abcdef")?;
+        let b = derive_key("passphrase", b"fedcba9876543210")?;
+        assert_ne!(a, b);
+        assert_eq!(a, derive_key("passphrase", b"0123456789abcdef")?);
+        Ok(())
+    }
+}
@@ -0,0 +1,316 @@
+// src/credentials/filestore.rs
+
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::cipher::{self, derive_key, random_salt, Key, PassphrasePrompt};
+use crate::config::storage::get_config_dir;
+use crate::config::Settings;
+use crate::utils::progress::with_spinner;
+use crate::utils::write_private_file;
+
+const SECRETS_FILE_NAME: &str = "secrets.enc.toml";
+const FORMAT_VERSION: u32 = 1;
+const KDF_NAME: &str = "argon2id";
+const KEY_CACHE_FILE_NAME: &str = "filestore.key";
+
+/// Environment variable consulted for the passphrase before prompting.
+pub const PASSPHRASE_ENV_VAR: &str = "GITP_FILESTORE_PASSPHRASE";
+
+const PASSPHRASE_PROMPT: PassphrasePrompt = PassphrasePrompt {
+    env_var: PASSPHRASE_ENV_VAR,
+    unlocks: "the encrypted file store",
+    new: "Choose a passphrase for gitp's encrypted secrets file",
+    existing: "Passphrase for gitp's encrypted secrets file",
+};
+
+/// On-disk envelope. Only the ciphertext is secret; salt and nonce are stored alongside it.
+#[derive(Debug, Serialize, Deserialize)]
+struct EncryptedFile {
+    version: u32,
+    kdf: String,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// Cached master key, written to the per-user runtime directory.
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedKey {
+    salt: String,
+    key: String,
+    expires_at: u64,
+}
+
+/// Decrypted secrets, keyed by "<host>/<account>".
+type SecretMap = BTreeMap<String, String>;
+
+/// An unlocked secrets file: the derived key and salt are kept so it can be re-sealed.
+struct UnlockedStore {
+    path: PathBuf,
+    salt: Vec<u8>,
+    key: Key,
+    secrets: SecretMap,
+}
+
+/// Returns the path of the encrypted secrets file (e.g. ~/.config/gitp/secrets.enc.toml).
+pub fn get_secrets_path() -> Result<PathBuf> {
+    Ok(get_config_dir()?.join(SECRETS_FILE_NAME))
+}
+
+/// Whether an encrypted secrets file has already been created on this machine.
+pub fn is_initialized() -> bool {
+    get_secrets_path().map(|p| p.exists()).unwrap_or(false)
+}
+
+/// Stores an HTTPS token in the encrypted secrets file, creating the file if needed.
+pub fn store_token(
+    settings: &Settings,
+    target_host: &str,
+    account: &str,
+    token: &str,
+) -> Result<()> {
+    let mut store = unlock(settings)?;
+    store
+        .secrets
+        .insert(entry_key(target_host, account), token.to_string());
+    store.save().with_context(|| {
+        format!(
+            "Failed to store token for host '{}', account '{}' in encrypted file store",
+            target_host, account
+        )
+    })
+}
+
+/// Retrieves an HTTPS token from the encrypted secrets file.
+pub fn retrieve_token(settings: &Settings, target_host: &str, account: &str) -> Result<String> {
+    if !is_initialized() {
+        bail!("Encrypted file store has not been created yet.");
+    }
+    let store = unlock(settings)?;
+    store
+        .secrets
+        .get(&entry_key(target_host, account))
+        .cloned()
+        .ok_or_else(|| {
+            anyhow!(
+                "No token found for host '{}', account '{}' in encrypted file store",
+                target_host,
+                account
+            )
+        })
+}
+
+/// Deletes an HTTPS token from the encrypted secrets file.
+pub fn delete_token(settings: &Settings, target_host: &str, account: &str) -> Result<()> {
+    if !is_initialized() {
+        bail!("Encrypted file store has not been created yet.");
+    }
+    let mut store = unlock(settings)?;
+    if store
+        .secrets
+        .remove(&entry_key(target_host, account))
+        .is_none()
+    {
+        bail!(
+            "No token found for host '{}', account '{}' in encrypted file store",
+            target_host,
+            account
+        );
+    }
+    store.save()
+}
+
+fn entry_key(target_host: &str, account: &str) -> String {
+    format!("{}/{}", target_host, account)
+}
+
+impl UnlockedStore {
+    fn save(&self) -> Result<()> {
+        let envelope = seal(&self.key, &self.salt, &self.secrets)?;
+        let content = toml::to_string_pretty(&envelope)
+            .context("Failed to serialize encrypted secrets file")?;
+        write_private_file(&self.path, content.as_bytes())
+    }
+}
+
+/// Opens the secrets file (or prepares a new one), obtaining the key from the cache or the user.
+fn unlock(settings: &Settings) -> Result<UnlockedStore> {
+    let path = get_secrets_path()?;
+
+    if !path.exists() {
+        let salt = random_salt().to_vec();
+        let passphrase = obtain_passphrase(true)?;
+        let key = with_spinner("Deriving the file store key...", || {
+            derive_key(&passphrase, &salt)
+        })?;
+        cache_key(&salt, &key, settings.filestore_cache_ttl_secs);
+        return Ok(UnlockedStore {
+            path,
+            salt,
+            key,
+            secrets: SecretMap::new(),
+        });
+    }
+
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read encrypted secrets file from {:?}", path))?;
+    let envelope: EncryptedFile = toml::from_str(&content)
+        .with_context(|| format!("Failed to parse encrypted secrets file {:?}", path))?;
+    if envelope.version != FORMAT_VERSION || envelope.kdf != KDF_NAME {
+        bail!(
+            "Unsupported encrypted secrets file format (version {}, kdf '{}') at {:?}",
+            envelope.version,
+            envelope.kdf,
+            path
+        );
+    }
+    let salt = BASE64
+        .decode(&envelope.salt)
+        .context("Encrypted secrets file has an invalid salt")?;
+
+    if let Some(key) = cached_key(&salt) {
+        if let Ok(secrets) = open(&key, &envelope) {
+            return Ok(UnlockedStore {
+                path,
+                salt,
+                key,
+                secrets,
+            });
+        }
+        clear_cached_key();
+    }
+
+    let passphrase = obtain_passphrase(false)?;
+    let key = with_spinner("Unlocking the file store...", || {
+        derive_key(&passphrase, &salt)
+    })?;
+    let secrets = open(&key, &envelope)
+        .map_err(|_| anyhow!("Incorrect passphrase for encrypted secrets file {:?}", path))?;
+    cache_key(&salt, &key, settings.filestore_cache_ttl_secs);
+
+    Ok(UnlockedStore {
+        path,
+        salt,
+        key,
+        secrets,
+    })
+}
+
+/// Reads the passphrase from the environment or prompts for it on a terminal.
+fn obtain_passphrase(creating: bool) -> Result<String> {
+    cipher::obtain_passphrase(&PASSPHRASE_PROMPT, creating)
+}
+
+fn seal(key: &Key, salt: &[u8], secrets: &SecretMap) -> Result<EncryptedFile> {
+    let plaintext = toml::to_string(secrets).context("Failed to serialize secrets")?;
+    let (nonce, ciphertext) =
+        cipher::encrypt(key, plaintext.as_bytes(), b"").context("Failed to encrypt secrets")?;
+
+    Ok(EncryptedFile {
+        version: FORMAT_VERSION,
+        kdf: KDF_NAME.to_string(),
+        salt: BASE64.encode(salt),
+        nonce: BASE64.encode(nonce),
+        ciphertext: BASE64.encode(ciphertext),
+    })
+}
+
+fn open(key: &Key, envelope: &EncryptedFile) -> Result<SecretMap> {
+    let nonce = BASE64
+        .decode(&envelope.nonce)
+        .context("Encrypted secrets file has an invalid nonce")?;
+    let ciphertext = BASE64
+        .decode(&envelope.ciphertext)
+        .context("Encrypted secrets file has invalid ciphertext")?;
+
+    let plaintext =
+        cipher::decrypt(key, &nonce, &ciphertext, b"").context("Failed to decrypt secrets")?;
+    let plaintext = String::from_utf8(plaintext).context("Decrypted secrets are not UTF-8")?;
+    toml::from_str(&plaintext).context("Failed to parse decrypted secrets")
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Where the derived key is cached between commands, when there is a runtime directory.
+pub fn key_cache_path() -> Option<PathBuf> {
+    dirs::runtime_dir().map(|dir| dir.join("gitp").join(KEY_CACHE_FILE_NAME))
+}
+
+/// Caches the derived key in the per-user runtime directory (tmpfs, cleared on logout) so
+/// repeated operations don't re-prompt. Without one (macOS, Windows) nothing is cached: the key
+/// unlocks every secret, so it isn't left in a directory that persists, nor in the keyring that
+/// may be why the file store is used. Caching is best-effort and failures are ignored.
+fn cache_key(salt: &[u8], key: &Key, ttl_secs: u64) {
+    let Some(cache_path) = key_cache_path().filter(|_| ttl_secs > 0) else {
+        return;
+    };
+    let cached = CachedKey {
+        salt: BASE64.encode(salt),
+        key: BASE64.encode(key),
+        expires_at: now_secs() + ttl_secs,
+    };
+    if let Some(parent) = cache_path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    if let Ok(content) = toml::to_string(&cached) {
+        let _ = write_private_file(&cache_path, content.as_bytes());
+    }
+}
+
+fn cached_key(salt: &[u8]) -> Option<Key> {
+    let content = fs::read_to_string(key_cache_path()?).ok()?;
+    let cached = toml::from_str::<CachedKey>(&content).ok()?;
+    if cached.salt != BASE64.encode(salt) || cached.expires_at <= now_secs() {
+        return None;
+    }
+    BASE64.decode(&cached.key).ok()?.try_into().ok()
+}
+
+fn clear_cached_key() {
+    if let Some(cache_path) = key_cache_path() {
+        let _ = fs::remove_file(cache_path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::credentials::cipher::SALT_LEN;
+
+    const TEST_SALT: &[u8; SALT_LEN] = b"0123456789abcdef";
+
+    #[test]
+    fn test_seal_open_roundtrip() -> Result<()> {
+        let key = derive_key("correct horse", TEST_SALT)?;
+        let mut secrets = SecretMap::new();
+        secrets.insert(entry_key("github.com", "octocat"), "ghp_secret".to_string());
+
+        let envelope = seal(&key, TEST_SALT, &secrets)?;
+        assert!(!envelope.ciphertext.contains("ghp_secret"));
+        assert_eq!(envelope.salt, BASE64.encode(TEST_SALT));
+
+        let opened = open(&key, &envelope)?;
+        assert_eq!(opened, secrets);
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_with_wrong_passphrase_fails() -> Result<()> {
+        let key = derive_key("correct horse", TEST_SALT)?;
+        let wrong_key = derive_key("battery staple", TEST_SALT)?;
+        let envelope = seal(&key, TEST_SALT, &SecretMap::new())?;
+        assert!(open(&wrong_key, &envelope).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,506 @@
+// src/credentials/helper.rs
+
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+
+use crate::config::{host_matches, HelperCoexistence, HttpsCredentials};
+use crate::git::{
+    add_git_config, get_effective_git_config_regexp, get_git_config, get_git_config_regexp,
+    replace_git_config_value, set_git_config, unset_all_git_config, unset_git_config_value,
+    GitConfigEntry, GitConfigScope,
+};
+use crate::utils::output::{theme, Paint};
+use crate::utils::prompt::Select;
+use crate::utils::quote::shell_word;
+
+/// Start of every `credential.<url>.helper` value written by gitp; used to recognise our own entries.
+const HELPER_COMMAND_PREFIX: &str = "!gitp credential-helper";
+
+/// `credential.helper` value that answers for the active profile on every host.
+pub const BLANKET_HELPER_COMMAND: &str = "!gitp credential";
+
+/// Helper command git should run for `profile_name`.
+pub fn helper_command(profile_name: &str) -> String {
+    format!(
+        "{} --profile {}",
+        HELPER_COMMAND_PREFIX,
+        // Git runs `!` helpers through the shell
+        shell_word(profile_name)
+    )
+}
+
+/// Config key gitp owns for `host`. `https://<host>/` matches the same requests as
+/// `https://<host>`, but gives gitp a subsection of its own that it can rewrite freely.
+pub fn helper_key(host: &str) -> String {
+    format!("credential.https://{}/.helper", host.trim().to_lowercase())
+}
+
+/// Sets `credential.https://<host>/.useHttpPath` at `scope`, so git tells helpers which
+/// repository it needs credentials for. Returns the key when it wasn't set yet.
+pub fn enable_http_path(host: &str, scope: GitConfigScope) -> Result<Option<String>> {
+    let key = format!(
+        "credential.https://{}/.useHttpPath",
+        host.trim().to_lowercase()
+    );
+    if get_git_config(&key, scope)?.as_deref() == Some("true") {
+        return Ok(None);
+    }
+    set_git_config(&key, "true", scope)?;
+    Ok(Some(key))
+}
+
+/// Whether a helper value was written by gitp for a profile's hosts.
+fn is_profile_helper(value: &str) -> bool {
+    value.starts_with(HELPER_COMMAND_PREFIX)
+}
+
+/// Whether a helper value runs gitp: a profile's own helper (`!gitp credential-helper
+/// --profile <name>`) or the blanket one (`!gitp credential`), by name or by path.
+pub fn is_gitp_helper(value: &str) -> bool {
+    let mut words = value.split_whitespace();
+    let program = words
+        .next()
+        .and_then(|w| w.strip_prefix('!'))
+        .and_then(|w| w.rsplit('/').next());
+    program == Some("gitp")
+        && matches!(
+            words.next(),
+            Some("credential-helper" | "credential" | "credentials")
+        )
+}
+
+/// Whether a helper value runs Git Credential Manager (`manager`, `manager-core`, or its
+/// executable by path), which Git for Windows sets up system-wide. GCM answers a request with
+/// the account it signed in, or opens its own sign-in, instead of letting later helpers answer.
+pub fn is_credential_manager(value: &str) -> bool {
+    let program = value.rsplit(['/', '\\']).next().unwrap_or_default();
+    let program = program.split_whitespace().next().unwrap_or_default();
+    let program = program.strip_suffix(".exe").unwrap_or(program);
+    let program = program.strip_prefix("git-credential-").unwrap_or(program);
+    matches!(program, "manager" | "manager-core")
+}
+
+/// Whether the helper config `key` (`credential.helper` or `credential.<url>.helper`)
+/// applies to HTTPS requests for `host`.
+fn applies_to(key: &str, host: &str) -> bool {
+    if key == "credential.helper" {
+        return true;
+    }
+    let url = match key
+        .strip_prefix("credential.")
+        .and_then(|k| k.strip_suffix(".helper"))
+    {
+        Some(url) => url,
+        None => return false,
+    };
+    let rest = match url.split_once("://") {
+        Some((scheme, rest)) if scheme.eq_ignore_ascii_case("https") => rest,
+        Some(_) => return false,
+        None => url,
+    };
+    let authority = rest.split('/').next().unwrap_or_default();
+    host_matches(authority, host)
+}
+
+/// Helpers git will run for `host`, in order, after applying resets (empty values).
+pub fn effective_helpers(entries: &[GitConfigEntry], host: &str) -> Vec<GitConfigEntry> {
+    let mut helpers = Vec::new();
+    for entry in entries.iter().filter(|e| applies_to(&e.key, host)) {
+        if entry.value.is_empty() {
+            helpers.clear();
+        } else {
+            helpers.push(entry.clone());
+        }
+    }
+    helpers
+}
+
+fn read_helper_entries() -> Result<Vec<GitConfigEntry>> {
+    get_effective_git_config_regexp(r"^credential\.(.+\.)?helper$")
+        .context("Failed to read configured credential helpers.")
+}
+
+/// Helpers not written by gitp that git would run for `host`, in order.
+pub fn foreign_helpers(host: &str) -> Result<Vec<GitConfigEntry>> {
+    let own_key = helper_key(host);
+    let entries: Vec<GitConfigEntry> = read_helper_entries()?
+        .into_iter()
+        .filter(|e| !e.key.eq_ignore_ascii_case(&own_key) && !is_gitp_helper(&e.value))
+        .collect();
+    Ok(effective_helpers(&entries, host))
+}
+
+/// Helpers git will run for `host` with the current configuration, gitp's included.
+pub fn helper_order(host: &str) -> Result<Vec<GitConfigEntry>> {
+    Ok(effective_helpers(&read_helper_entries()?, host))
+}
+
+/// Values gitp writes under its own key so that git runs its helper as `mode` asks.
+fn desired_values(
+    mode: HelperCoexistence,
+    profile_name: &str,
+    foreign: &[GitConfigEntry],
+) -> Vec<String> {
+    let gitp = helper_command(profile_name);
+    if foreign.is_empty() {
+        return vec![gitp];
+    }
+    match mode {
+        HelperCoexistence::Ask | HelperCoexistence::Leave => vec![gitp],
+        HelperCoexistence::Replace => vec![String::new(), gitp],
+        // The reset drops the inherited helpers; re-adding them after gitp keeps them as fallback.
+        HelperCoexistence::Prepend => [String::new(), gitp]
+            .into_iter()
+            .chain(foreign.iter().map(|e| e.value.clone()))
+            .collect(),
+    }
+}
+
+/// What `sync_helper_config` changed.
+#[derive(Debug, Default, PartialEq)]
+pub struct HelperSync {
+    /// Keys that now hold gitp's helper, one per HTTPS host of the profile, in order
+    pub configured: Vec<String>,
+    /// Keys gitp's helper was removed from because they belong to another profile or host
+    pub removed: Vec<String>,
+}
+
+/// Writes gitp's helper for each of the profile's HTTPS hosts under `helper_key`, combined
+/// with the `foreign` helpers found for that host according to the host's mode, and removes
+/// gitp entries left behind by previously used profiles or hosts. Helpers not written by gitp,
+/// including a blanket `credential.helper`, are never modified.
+pub fn sync_helper_config(
+    profile_name: &str,
+    hosts: &[(String, Vec<GitConfigEntry>, HelperCoexistence)],
+    scope: GitConfigScope,
+) -> Result<HelperSync> {
+    let wanted: Vec<(String, Vec<String>)> = hosts
+        .iter()
+        .map(|(host, foreign, mode)| {
+            (
+                helper_key(host),
+                desired_values(*mode, profile_name, foreign),
+            )
+        })
+        .collect();
+
+    // Values per key in this scope, in file order
+    let mut by_key: Vec<(String, Vec<String>)> = Vec::new();
+    for (key, value) in get_git_config_regexp(r"^credential\..+\.helper$", scope)
+        .context("Failed to read existing credential helpers.")?
+    {
+        match by_key.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, values)) => values.push(value),
+            None => by_key.push((key, vec![value])),
+        }
+    }
+
+    let mut sync = HelperSync::default();
+    let mut already_configured: Vec<String> = Vec::new();
+    for (key, values) in by_key {
+        if !values.iter().any(|v| is_profile_helper(v)) {
+            continue;
+        }
+        let wanted_here = wanted.iter().find(|(k, _)| k.eq_ignore_ascii_case(&key));
+        if wanted_here.is_some_and(|(_, wanted_values)| *wanted_values == values) {
+            already_configured.push(key);
+            continue;
+        }
+        if key.ends_with("/.helper") {
+            // gitp's own subsection: everything in it was written by gitp
+            unset_all_git_config(&key, scope)?;
+        } else {
+            for value in values.iter().filter(|v| is_profile_helper(v)) {
+                unset_git_config_value(&key, value, scope)?;
+            }
+        }
+        if wanted_here.is_none() {
+            sync.removed.push(key);
+        }
+    }
+
+    for (key, values) in wanted {
+        if !already_configured
+            .iter()
+            .any(|k| k.eq_ignore_ascii_case(&key))
+        {
+            for value in &values {
+                add_git_config(&key, value, scope)?;
+            }
+        }
+        sync.configured.push(key);
+    }
+
+    Ok(sync)
+}
+
+/// Adds [`BLANKET_HELPER_COMMAND`] to `credential.helper` at `scope` unless a blanket gitp
+/// helper is already there. Returns whether it was added.
+pub fn add_blanket_helper(scope: GitConfigScope) -> Result<bool> {
+    let present = get_git_config_regexp(r"^credential\.helper$", scope)
+        .context("Failed to read existing credential helpers.")?
+        .iter()
+        .any(|(_, value)| is_gitp_helper(value) && !is_profile_helper(value));
+    if !present {
+        add_git_config("credential.helper", BLANKET_HELPER_COMMAND, scope)?;
+    }
+    Ok(!present)
+}
+
+/// Points gitp helper entries for profile `old_name` at `new_name`. Returns the number of
+/// keys updated.
+pub fn rename_helper_profile(
+    old_name: &str,
+    new_name: &str,
+    scope: GitConfigScope,
+) -> Result<usize> {
+    let old_value = helper_command(old_name);
+    let new_value = helper_command(new_name);
+    let mut keys: Vec<String> = get_git_config_regexp(r"^credential\..+\.helper$", scope)
+        .context("Failed to read existing credential helpers.")?
+        .into_iter()
+        .filter(|(_, value)| *value == old_value)
+        .map(|(key, _)| key)
+        .collect();
+    keys.sort();
+    keys.dedup();
+    for key in &keys {
+        replace_git_config_value(key, &old_value, &new_value, scope)?;
+    }
+    Ok(keys.len())
+}
+
+/// Explains which helpers already serve `host` and asks how gitp's helper should be combined
+/// with them.
+pub fn ask_coexistence(host: &str, foreign: &[GitConfigEntry]) -> Result<HelperCoexistence> {
+    println!(
+        "  Git already has credential helpers for {}:",
+        format!("https://{}", host).accent()
+    );
+    for entry in foreign {
+        println!(
+            "    - {} ({})",
+            entry.value.warning(),
+            entry.origin.dimmed()
+        );
+    }
+    println!("  Git asks helpers in order and uses the first answer, so these may shadow gitp's.");
+    let credential_manager = foreign.iter().any(|e| is_credential_manager(&e.value));
+    if credential_manager {
+        println!(
+            "  Git Credential Manager answers with the account it signed in, or opens its own \
+             sign-in, so gitp is only asked if it comes first. The choice is kept for {} only.",
+            host
+        );
+    }
+
+    let options = [
+        "Prepend: ask gitp first, keep the others as fallback",
+        "Replace: use only gitp for this host",
+        "Leave untouched: add gitp after the others",
+    ];
+    let choice = Select::with_theme(&theme())
+        .with_prompt("How should gitp's credential helper be added?")
+        .items(&options)
+        .default(if credential_manager { 1 } else { 0 })
+        .interact()
+        .context("Failed to get credential helper choice.")?;
+
+    Ok(match choice {
+        0 => HelperCoexistence::Prepend,
+        1 => HelperCoexistence::Replace,
+        _ => HelperCoexistence::Leave,
+    })
+}
+
+/// Parses the `key=value` lines git sends to a credential helper, up to the first blank line.
+pub fn parse_request(input: &str) -> BTreeMap<String, String> {
+    input
+        .lines()
+        .take_while(|line| !line.is_empty())
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+/// Whether a `get` request from git is for the host and user of `creds`.
+pub fn request_matches(request: &BTreeMap<String, String>, creds: &HttpsCredentials) -> bool {
+    if request.get("protocol").is_some_and(|p| p != "https") {
+        return false;
+    }
+    let host_matches = request
+        .get("host")
+        .is_some_and(|h| host_matches(&creds.host, h));
+    let user_matches = request.get("username").is_none_or(|u| u == &creds.username);
+    host_matches && user_matches
+}
+
+/// Answer to a `get` request.
+pub fn format_response(username: &str, password: &str) -> String {
+    format!("username={}\npassword={}\n", username, password)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CredentialType;
+
+    fn creds() -> HttpsCredentials {
+        HttpsCredentials::new(
+            "github.com".to_string(),
+            "octocat".to_string(),
+            CredentialType::Token("t".to_string()),
+        )
+    }
+
+    #[test]
+    fn test_helper_command_and_key() {
+        assert_eq!(
+            helper_command("work"),
+            "!gitp credential-helper --profile work"
+        );
+        assert_eq!(
+            helper_command("client a"),
+            "!gitp credential-helper --profile 'client a'"
+        );
+        assert_eq!(
+            helper_command("it's"),
+            r"!gitp credential-helper --profile 'it'\''s'"
+        );
+        assert_eq!(
+            helper_key("GitHub.com"),
+            "credential.https://github.com/.helper"
+        );
+        assert!(is_gitp_helper(&helper_command("work")));
+        assert!(is_gitp_helper(BLANKET_HELPER_COMMAND));
+        assert!(is_gitp_helper("!/usr/local/bin/gitp credentials"));
+        assert!(!is_gitp_helper("osxkeychain"));
+        assert!(!is_gitp_helper("!gitp-other credential"));
+        assert!(!is_profile_helper(BLANKET_HELPER_COMMAND));
+
+        for value in [
+            "manager",
+            "manager-core",
+            r"C:/Program\ Files/Git/mingw64/bin/git-credential-manager.exe",
+            "/usr/local/share/gcm-core/git-credential-manager",
+        ] {
+            assert!(is_credential_manager(value), "{}", value);
+        }
+        assert!(!is_credential_manager("osxkeychain"));
+        assert!(!is_credential_manager(&helper_command("manager")));
+    }
+
+    fn entry(key: &str, value: &str) -> GitConfigEntry {
+        GitConfigEntry {
+            origin: "file:/home/u/.gitconfig".to_string(),
+            key: key.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_applies_to() {
+        assert!(applies_to("credential.helper", "github.com"));
+        assert!(applies_to(
+            "credential.https://github.com.helper",
+            "GitHub.com"
+        ));
+        assert!(applies_to(
+            "credential.https://github.com/.helper",
+            "github.com"
+        ));
+        assert!(applies_to(
+            "credential.https://me@github.com/org.helper",
+            "github.com"
+        ));
+        assert!(applies_to("credential.github.com.helper", "github.com"));
+        assert!(applies_to(
+            "credential.https://*.corp.com.helper",
+            "git.corp.com"
+        ));
+        assert!(!applies_to(
+            "credential.http://github.com.helper",
+            "github.com"
+        ));
+        assert!(!applies_to(
+            "credential.https://gitlab.com.helper",
+            "github.com"
+        ));
+        assert!(!applies_to("credential.usehttppath", "github.com"));
+    }
+
+    #[test]
+    fn test_effective_helpers_apply_resets() {
+        let entries = vec![
+            entry("credential.helper", "manager-core"),
+            entry("credential.https://gitlab.com.helper", "store"),
+            entry("credential.https://github.com.helper", ""),
+            entry("credential.https://github.com.helper", "osxkeychain"),
+            entry("credential.helper", "cache"),
+        ];
+        let values: Vec<String> = effective_helpers(&entries, "github.com")
+            .into_iter()
+            .map(|e| e.value)
+            .collect();
+        assert_eq!(values, vec!["osxkeychain", "cache"]);
+        assert_eq!(effective_helpers(&entries, "gitlab.com").len(), 3);
+    }
+
+    #[test]
+    fn test_desired_values() {
+        let gitp = helper_command("work");
+        let foreign = vec![entry("credential.helper", "osxkeychain")];
+
+        assert_eq!(
+            desired_values(HelperCoexistence::Prepend, "work", &[]),
+            vec![gitp.clone()]
+        );
+        assert_eq!(
+            desired_values(HelperCoexistence::Leave, "work", &foreign),
+            vec![gitp.clone()]
+        );
+        assert_eq!(
+            desired_values(HelperCoexistence::Replace, "work", &foreign),
+            vec![String::new(), gitp.clone()]
+        );
+        assert_eq!(
+            desired_values(HelperCoexistence::Prepend, "work", &foreign),
+            vec![String::new(), gitp, "osxkeychain".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_parse_request() {
+        let request = parse_request("protocol=https\nhost=github.com\n\nignored=1\n");
+        assert_eq!(request.len(), 2);
+        assert_eq!(request["protocol"], "https");
+        assert_eq!(request["host"], "github.com");
+    }
+
+    #[test]
+    fn test_request_matches() {
+        let creds = creds();
+        assert!(request_matches(
+            &parse_request("protocol=https\nhost=GitHub.com\n"),
+            &creds
+        ));
+        assert!(request_matches(
+            &parse_request("protocol=https\nhost=github.com\nusername=octocat\n"),
+            &creds
+        ));
+        assert!(!request_matches(
+            &parse_request("protocol=https\nhost=github.com\nusername=other\n"),
+            &creds
+        ));
+        assert!(!request_matches(
+            &parse_request("protocol=http\nhost=github.com\n"),
+            &creds
+        ));
+        assert!(!request_matches(
+            &parse_request("protocol=https\nhost=gitlab.com\n"),
+            &creds
+        ));
+    }
+}
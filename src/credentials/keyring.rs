@@ -1,17 +1,35 @@
 // src/credentials/keyring.rs
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use keyring::Entry;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use crate::config::Settings;
+use crate::utils::progress::with_spinner;
+use crate::utils::timing;
 
 const KEYRING_SERVICE_PREFIX: &str = "gitp_https_token_for_";
 
 /// Stores an HTTPS token in the system keychain.
 /// `target_host` is used to construct the service name (e.g., "github.com").
 /// `username_or_profile` is used as the account name for the entry.
-pub fn store_token(target_host: &str, username_or_profile: &str, token: &str) -> Result<()> {
-    let service_name = format!("{}{}", KEYRING_SERVICE_PREFIX, target_host);
-    let entry = Entry::new(&service_name, username_or_profile)?;
-    entry.set_password(token).with_context(|| {
+pub fn store_token(
+    settings: &Settings,
+    target_host: &str,
+    username_or_profile: &str,
+    token: &str,
+) -> Result<()> {
+    let token = token.to_string();
+    run_entry_op(
+        settings,
+        target_host,
+        username_or_profile,
+        true,
+        move |entry| entry.set_password(&token),
+    )
+    .with_context(|| {
         format!(
             "Failed to store token for host '{}', user/profile '{}' in keychain",
             target_host, username_or_profile
@@ -22,11 +40,15 @@ pub fn store_token(target_host: &str, username_or_profile: &str, token: &str) ->
 /// Retrieves an HTTPS token from the system keychain.
 /// `target_host` is used to construct the service name.
 /// `username_or_profile` is the account name for the entry.
-#[allow(dead_code)]
-pub fn retrieve_token(target_host: &str, username_or_profile: &str) -> Result<String> {
-    let service_name = format!("{}{}", KEYRING_SERVICE_PREFIX, target_host);
-    let entry = Entry::new(&service_name, username_or_profile)?;
-    entry.get_password().with_context(|| {
+pub fn retrieve_token(
+    settings: &Settings,
+    target_host: &str,
+    username_or_profile: &str,
+) -> Result<String> {
+    run_entry_op(settings, target_host, username_or_profile, false, |entry| {
+        entry.get_password()
+    })
+    .with_context(|| {
         format!(
             "Failed to retrieve token for host '{}', user/profile '{}' from keychain",
             target_host, username_or_profile
@@ -37,10 +59,15 @@ pub fn retrieve_token(target_host: &str, username_or_profile: &str) -> Result<St
 /// Deletes an HTTPS token from the system keychain.
 /// `target_host` is used to construct the service name.
 /// `username_or_profile` is the account name for the entry.
-pub fn delete_token(target_host: &str, username_or_profile: &str) -> Result<()> {
-    let service_name = format!("{}{}", KEYRING_SERVICE_PREFIX, target_host);
-    let entry = Entry::new(&service_name, username_or_profile)?;
-    entry.delete_password().with_context(|| {
+pub fn delete_token(
+    settings: &Settings,
+    target_host: &str,
+    username_or_profile: &str,
+) -> Result<()> {
+    run_entry_op(settings, target_host, username_or_profile, true, |entry| {
+        entry.delete_password()
+    })
+    .with_context(|| {
         format!(
             "Failed to delete token for host '{}', user/profile '{}' from keychain",
             target_host, username_or_profile
@@ -48,6 +75,146 @@ pub fn delete_token(target_host: &str, username_or_profile: &str) -> Result<()>
     })
 }
 
+/// Outcome of a single keyring attempt.
+enum Attempt<T> {
+    Done(keyring::Result<T>),
+    TimedOut,
+}
+
+/// Error of a write or delete that didn't answer within `keyring_timeout_secs`. The call may
+/// still finish, so it is neither retried nor replaced by a `keyring_fallback`.
+#[derive(Debug)]
+pub struct WriteTimedOut(u64);
+
+impl std::fmt::Display for WriteTimedOut {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "The system keyring did not respond within {}s; the change may still be applied. {}",
+            self.0, LOCKED_KEYRING_HINT
+        )
+    }
+}
+
+impl std::error::Error for WriteTimedOut {}
+
+/// Whether `error` comes from a keyring write that timed out, see [`WriteTimedOut`].
+pub fn is_timed_out_write(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<WriteTimedOut>().is_some()
+}
+
+/// Runs a keyring operation, on a worker thread bounded by `settings.keyring_timeout_secs`
+/// when it is set. Unset (0), the operation is waited for, since the keyring may be showing an
+/// unlock prompt. Reads are retried once after a timeout or a platform/storage-access failure
+/// other than a dismissed prompt; writes and deletes (`write`) are never retried, so they can't
+/// run twice or ask twice. `NoEntry` and other definitive answers are returned immediately.
+/// Errors carry an actionable hint when the platform failure is recognised (locked keyring,
+/// dismissed prompt, missing service).
+fn run_entry_op<T, F>(
+    settings: &Settings,
+    target_host: &str,
+    username_or_profile: &str,
+    write: bool,
+    op: F,
+) -> Result<T>
+where
+    T: Send + 'static,
+    F: Fn(&Entry) -> keyring::Result<T> + Send + Sync + Clone + 'static,
+{
+    let service_name = format!("{}{}", KEYRING_SERVICE_PREFIX, target_host);
+    let account = username_or_profile.to_string();
+    let timeout = (settings.keyring_timeout_secs > 0)
+        .then(|| Duration::from_secs(settings.keyring_timeout_secs));
+    let attempts = if write { 1 } else { 2 };
+
+    let last_attempt = timing::measure("keyring", || {
+        with_spinner("Waiting for the system keyring...", || {
+            let mut last_attempt = Attempt::TimedOut;
+            for _ in 0..attempts {
+                let (service_name, account, op) =
+                    (service_name.clone(), account.clone(), op.clone());
+                let attempt = move || {
+                    let entry = Entry::new(&service_name, &account)?;
+                    op(&entry)
+                };
+                last_attempt = match timeout {
+                    Some(timeout) => run_with_timeout(timeout, attempt),
+                    None => Attempt::Done(attempt()),
+                };
+                match &last_attempt {
+                    Attempt::Done(Err(
+                        keyring::Error::PlatformFailure(e) | keyring::Error::NoStorageAccess(e),
+                    )) if !is_dismissed(&e.to_string()) => continue,
+                    Attempt::TimedOut => continue,
+                    Attempt::Done(_) => break,
+                }
+            }
+            last_attempt
+        })
+    });
+
+    match last_attempt {
+        Attempt::Done(Ok(value)) => Ok(value),
+        Attempt::Done(Err(e)) => {
+            let hint = keyring_error_hint(&e.to_string());
+            let err = anyhow::Error::new(e);
+            Err(match hint {
+                Some(hint) => err.context(hint),
+                None => err,
+            })
+        }
+        Attempt::TimedOut if write => Err(WriteTimedOut(settings.keyring_timeout_secs).into()),
+        Attempt::TimedOut => Err(anyhow!(
+            "The system keyring did not respond within {}s (tried twice). {}",
+            settings.keyring_timeout_secs,
+            LOCKED_KEYRING_HINT
+        )),
+    }
+}
+
+fn run_with_timeout<T, F>(timeout: Duration, f: F) -> Attempt<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> keyring::Result<T> + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel();
+    // The worker is detached: if the platform call hangs we stop waiting, not the call itself.
+    thread::spawn(move || {
+        let _ = tx.send(f());
+    });
+    match rx.recv_timeout(timeout) {
+        Ok(result) => Attempt::Done(result),
+        Err(_) => Attempt::TimedOut,
+    }
+}
+
+const LOCKED_KEYRING_HINT: &str = "Your login keyring may be locked: unlock it (e.g. with Seahorse or `gnome-keyring-daemon --unlock`) and try again, or set `secret_store = \"file\"` in gitp's settings.";
+
+/// Whether a platform error says the user dismissed the unlock prompt.
+fn is_dismissed(message: &str) -> bool {
+    message.to_lowercase().contains("dismissed")
+}
+
+/// Maps known platform error texts (mostly Secret Service over D-Bus) to actionable advice.
+fn keyring_error_hint(message: &str) -> Option<&'static str> {
+    let message = message.to_lowercase();
+    if is_dismissed(&message) {
+        Some("The keyring unlock prompt was dismissed. Run the command again and unlock your login keyring when asked.")
+    } else if message.contains("no secret service provider")
+        || message.contains("org.freedesktop.secrets")
+        || message.contains("serviceunknown")
+        || message.contains("not provided by any .service files")
+        || message.contains("connection refused")
+        || message.contains("no such file or directory")
+    {
+        Some("No Secret Service provider is running. Install and start gnome-keyring or KWallet, or set `secret_store = \"file\"` in gitp's settings.")
+    } else if message.contains("locked") {
+        Some(LOCKED_KEYRING_HINT)
+    } else {
+        None
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -58,7 +225,7 @@ mod tests {
 
     // Helper to clean up entry if it exists
     fn cleanup_test_entry() {
-        let _ = delete_token(TEST_HOST, TEST_USER); // Ignore error if not found
+        let _ = delete_token(&Settings::default(), TEST_HOST, TEST_USER); // Ignore error if not found
     }
 
     #[test]
@@ -66,21 +233,23 @@ mod tests {
         cleanup_test_entry(); // Ensure clean state
 
         // Test store
-        store_token(TEST_HOST, TEST_USER, TEST_TOKEN).context("Test: Failed to store token")?;
+        store_token(&Settings::default(), TEST_HOST, TEST_USER, TEST_TOKEN)
+            .context("Test: Failed to store token")?;
 
         // Test retrieve
-        let retrieved_token =
-            retrieve_token(TEST_HOST, TEST_USER).context("Test: Failed to retrieve token")?;
+        let retrieved_token = retrieve_token(&Settings::default(), TEST_HOST, TEST_USER)
+            .context("Test: Failed to retrieve token")?;
         assert_eq!(
             retrieved_token, TEST_TOKEN,
             "Retrieved token does not match stored token"
         );
 
         // Test delete
-        delete_token(TEST_HOST, TEST_USER).context("Test: Failed to delete token")?;
+        delete_token(&Settings::default(), TEST_HOST, TEST_USER)
+            .context("Test: Failed to delete token")?;
 
         // Verify deletion by trying to retrieve again (should fail)
-        match retrieve_token(TEST_HOST, TEST_USER) {
+        match retrieve_token(&Settings::default(), TEST_HOST, TEST_USER) {
             Ok(_) => panic!("Token was retrieved after it should have been deleted."),
             Err(e) => {
                 eprintln!("Debug: Error after delete: {:?}", e);
@@ -112,4 +281,51 @@ mod tests {
         }
         Ok(())
     }
+
+    #[test]
+    fn test_keyring_error_hints() {
+        assert!(
+            keyring_error_hint("Platform secure storage failure: Prompt dismissed")
+                .unwrap()
+                .contains("dismissed")
+        );
+        assert!(keyring_error_hint(
+            "org.freedesktop.DBus.Error.ServiceUnknown: The name org.freedesktop.secrets was not provided by any .service files"
+        )
+        .unwrap()
+        .contains("No Secret Service provider"));
+        assert!(keyring_error_hint(
+            "Couldn't access platform secure storage: collection is locked"
+        )
+        .unwrap()
+        .contains("unlock"));
+        assert!(keyring_error_hint(
+            "Platform secure storage failure: no secret service provider or dbus session found"
+        )
+        .unwrap()
+        .contains("No Secret Service provider"));
+        assert!(keyring_error_hint("No matching entry found in secure storage").is_none());
+        assert!(is_dismissed(
+            "Platform secure storage failure: Prompt Dismissed"
+        ));
+    }
+
+    #[test]
+    fn test_is_timed_out_write() {
+        let error = anyhow::Error::new(WriteTimedOut(5)).context("Failed to store token");
+        assert!(is_timed_out_write(&error));
+        assert!(!is_timed_out_write(&anyhow!("did not respond within 5s")));
+    }
+
+    #[test]
+    fn test_run_with_timeout() {
+        let done = run_with_timeout(Duration::from_secs(5), || Ok(42));
+        assert!(matches!(done, Attempt::Done(Ok(42))));
+
+        let timed_out = run_with_timeout(Duration::from_millis(10), || {
+            thread::sleep(Duration::from_millis(500));
+            Ok(())
+        });
+        assert!(matches!(timed_out, Attempt::TimedOut));
+    }
 }
@@ -1,3 +1,308 @@
 // src/credentials/mod.rs
 
+pub mod cache;
+pub mod cipher;
+pub mod credential_store;
+pub mod filestore;
+pub mod helper;
+#[cfg(feature = "keyring")]
 pub mod keyring;
+pub mod sealed;
+pub mod verify;
+
+/// Stand-in for builds without the `keyring` feature: every operation fails, so storing a
+/// token goes through the `keyring_fallback` policy.
+#[cfg(not(feature = "keyring"))]
+pub mod keyring {
+    use anyhow::{bail, Result};
+
+    use crate::config::Settings;
+
+    fn unavailable<T>() -> Result<T> {
+        bail!(
+            "This gitp build has no keyring support (built without the 'keyring' feature). \
+             Set `secret_store = \"file\"` in gitp's settings."
+        )
+    }
+
+    pub fn store_token(
+        _: &Settings,
+        _target_host: &str,
+        _account: &str,
+        _token: &str,
+    ) -> Result<()> {
+        unavailable()
+    }
+
+    pub fn retrieve_token(_: &Settings, _target_host: &str, _account: &str) -> Result<String> {
+        unavailable()
+    }
+
+    pub fn delete_token(_: &Settings, _target_host: &str, _account: &str) -> Result<()> {
+        unavailable()
+    }
+
+    pub fn is_timed_out_write(_error: &anyhow::Error) -> bool {
+        false
+    }
+}
+
+use anyhow::{bail, Context, Result};
+
+use crate::config::{CredentialType, HttpsCredentials, KeyringFallback, SecretStoreKind, Settings};
+use crate::utils::output::{theme, Paint};
+use crate::utils::prompt::Select;
+use crate::utils::terminal::can_prompt;
+
+/// Stores a token in the configured secure backend and returns the reference to keep on the profile.
+/// When the platform keyring fails, `settings.keyring_fallback` decides what happens next.
+pub fn store_secure_token(
+    settings: &Settings,
+    target_host: &str,
+    username: &str,
+    token: &str,
+) -> Result<CredentialType> {
+    match settings.secret_store {
+        SecretStoreKind::File => store_in_file(settings, target_host, username, token),
+        SecretStoreKind::Keyring => {
+            match keyring::store_token(settings, target_host, username, token) {
+                Ok(_) => Ok(CredentialType::KeychainRef(username.to_string())),
+                // The keyring may still store it; a fallback would leave a second copy
+                Err(e) if keyring::is_timed_out_write(&e) => Err(e),
+                Err(e) => {
+                    apply_keyring_fallback(settings, target_host, username, token, e, can_prompt())
+                }
+            }
+        }
+    }
+}
+
+fn store_in_file(
+    settings: &Settings,
+    target_host: &str,
+    username: &str,
+    token: &str,
+) -> Result<CredentialType> {
+    filestore::store_token(settings, target_host, username, token)?;
+    Ok(CredentialType::FileStoreRef(username.to_string()))
+}
+
+/// Stores the token the way `keyring_fallback` says after the keyring failed with
+/// `keyring_error`. `interactive` tells whether the `prompt` policy can ask.
+fn apply_keyring_fallback(
+    settings: &Settings,
+    target_host: &str,
+    username: &str,
+    token: &str,
+    keyring_error: anyhow::Error,
+    interactive: bool,
+) -> Result<CredentialType> {
+    let policy = match settings.keyring_fallback {
+        KeyringFallback::Prompt => match prompt_for_fallback(&keyring_error, interactive)? {
+            Some(policy) => policy,
+            None => {
+                return Err(keyring_error.context(
+                    "Keychain storage failed and storing the token another way was cancelled. \
+                     The token was not saved.",
+                ))
+            }
+        },
+        other => other,
+    };
+
+    match policy {
+        KeyringFallback::Fail | KeyringFallback::Prompt => Err(keyring_error.context(
+            "Keychain storage failed and keyring_fallback is 'fail'. The token was not saved.",
+        )),
+        KeyringFallback::PlaintextWithWarning => {
+            eprintln!(
+                "  {}: {:#}. Storing the token as plain text in config.toml instead.",
+                "Warning".warning(),
+                keyring_error
+            );
+            Ok(CredentialType::Token(token.to_string()))
+        }
+        KeyringFallback::EncryptedFile => {
+            eprintln!(
+                "  {}: {:#}. Using the encrypted file store instead.",
+                "Warning".warning(),
+                keyring_error
+            );
+            store_in_file(settings, target_host, username, token)
+        }
+    }
+}
+
+/// Asks which fallback to use. Returns `None` when the user aborts.
+fn prompt_for_fallback(
+    keyring_error: &anyhow::Error,
+    interactive: bool,
+) -> Result<Option<KeyringFallback>> {
+    if !interactive {
+        bail!(
+            "{:#}. keyring_fallback is 'prompt' but no terminal is available to ask how to proceed.",
+            keyring_error
+        );
+    }
+
+    eprintln!(
+        "  {}: {:#}",
+        "Keychain storage failed".warning(),
+        keyring_error
+    );
+    let options = [
+        "Store in the encrypted file store",
+        "Store as plain text in config.toml",
+        "Abort",
+    ];
+    let choice = Select::with_theme(&theme())
+        .with_prompt("How should the token be stored?")
+        .items(&options)
+        .default(0)
+        .interact()
+        .context("Failed to get fallback choice.")?;
+
+    Ok(match choice {
+        0 => Some(KeyringFallback::EncryptedFile),
+        1 => Some(KeyringFallback::PlaintextWithWarning),
+        _ => None,
+    })
+}
+
+/// Returns the token value for `creds`, reading it from the keychain or file store when needed.
+pub fn resolve_token(settings: &Settings, creds: &HttpsCredentials) -> Result<String> {
+    resolve_secret(settings, &creds.host, &creds.credential_type)
+}
+
+/// Returns the secret `credential_type` holds or refers to, stored for `target_host`.
+pub fn resolve_secret(
+    settings: &Settings,
+    target_host: &str,
+    credential_type: &CredentialType,
+) -> Result<String> {
+    match credential_type {
+        CredentialType::Token(token) => Ok(token.clone()),
+        CredentialType::KeychainRef(account) => {
+            keyring::retrieve_token(settings, target_host, account)
+        }
+        CredentialType::FileStoreRef(account) => {
+            filestore::retrieve_token(settings, target_host, account)
+        }
+    }
+}
+
+/// Deletes the externally stored token referenced by `credential_type`, if any.
+pub fn delete_stored_token(
+    settings: &Settings,
+    target_host: &str,
+    credential_type: &CredentialType,
+) -> Result<()> {
+    match credential_type {
+        CredentialType::Token(_) => Ok(()),
+        CredentialType::KeychainRef(account) => {
+            keyring::delete_token(settings, target_host, account)
+        }
+        CredentialType::FileStoreRef(account) => {
+            filestore::delete_token(settings, target_host, account)
+        }
+    }
+}
+
+/// Human-readable name of the backend holding a token.
+pub fn store_label(credential_type: &CredentialType) -> &'static str {
+    match credential_type {
+        CredentialType::Token(_) => "config file",
+        CredentialType::KeychainRef(_) => "keychain",
+        CredentialType::FileStoreRef(_) => "encrypted file store",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    fn settings(keyring_fallback: KeyringFallback) -> Settings {
+        Settings {
+            keyring_fallback,
+            ..Settings::default()
+        }
+    }
+
+    fn keyring_error() -> anyhow::Error {
+        anyhow!("Platform secure storage failure: collection is locked")
+    }
+
+    fn fallback(policy: KeyringFallback, interactive: bool) -> Result<CredentialType> {
+        apply_keyring_fallback(
+            &settings(policy),
+            "github.com",
+            "octocat",
+            "ghp_secret",
+            keyring_error(),
+            interactive,
+        )
+    }
+
+    #[test]
+    fn test_keyring_fallback_fail() {
+        let error = fallback(KeyringFallback::Fail, true).unwrap_err();
+        assert!(error.to_string().contains("keyring_fallback is 'fail'"));
+        assert!(format!("{:#}", error).contains("collection is locked"));
+    }
+
+    #[test]
+    fn test_keyring_fallback_plaintext() {
+        assert_eq!(
+            fallback(KeyringFallback::PlaintextWithWarning, false).unwrap(),
+            CredentialType::Token("ghp_secret".to_string())
+        );
+    }
+
+    #[test]
+    fn test_keyring_fallback_prompt_without_terminal() {
+        let error = fallback(KeyringFallback::Prompt, false).unwrap_err();
+        let message = error.to_string();
+        assert!(message.contains("no terminal is available"));
+        assert!(message.contains("collection is locked"));
+        assert!(!message.contains("keyring_fallback is 'fail'"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    #[serial_test::serial]
+    fn test_keyring_fallback_encrypted_file() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let vars = [
+            "XDG_CONFIG_HOME",
+            "XDG_RUNTIME_DIR",
+            filestore::PASSPHRASE_ENV_VAR,
+        ];
+        let saved: Vec<_> = vars.iter().map(std::env::var_os).collect();
+        std::env::set_var("XDG_CONFIG_HOME", dir.path());
+        std::env::set_var("XDG_RUNTIME_DIR", dir.path());
+        std::env::set_var(filestore::PASSPHRASE_ENV_VAR, "correct horse");
+
+        let mut settings = settings(KeyringFallback::EncryptedFile);
+        settings.filestore_cache_ttl_secs = 0;
+        let stored = apply_keyring_fallback(
+            &settings,
+            "github.com",
+            "octocat",
+            "ghp_secret",
+            keyring_error(),
+            false,
+        );
+        let retrieved = filestore::retrieve_token(&settings, "github.com", "octocat");
+
+        for (var, value) in vars.iter().zip(saved) {
+            match value {
+                Some(value) => std::env::set_var(var, value),
+                None => std::env::remove_var(var),
+            }
+        }
+        assert_eq!(stored?, CredentialType::FileStoreRef("octocat".to_string()));
+        assert_eq!(retrieved?, "ghp_secret");
+        Ok(())
+    }
+}
@@ -0,0 +1,296 @@
+// src/credentials/sealed.rs
+// Field-level encryption of secrets in exported profiles
+
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use std::collections::HashMap;
+
+use super::cipher::{self, derive_key, random_salt, Key, PassphrasePrompt};
+use crate::config::audit::is_secret_field;
+use crate::config::{CredentialType, Profile};
+
+/// Environment variable consulted for the export passphrase before prompting.
+pub const PASSPHRASE_ENV_VAR: &str = "GITP_EXPORT_PASSPHRASE";
+
+const PASSPHRASE_PROMPT: PassphrasePrompt = PassphrasePrompt {
+    env_var: PASSPHRASE_ENV_VAR,
+    unlocks: "the encrypted values",
+    new: "Passphrase to encrypt the exported secrets",
+    existing: "Passphrase for the encrypted values",
+};
+
+const PREFIX: &str = "ENC[xchacha20poly1305,argon2id,";
+
+/// Replaces the token and the secret-named `custom_config`/`extras` values of `profile` with
+/// `ENC[...]` strings, leaving the rest of the profile readable. Returns how many values were
+/// encrypted.
+pub fn encrypt_secrets(profile: &mut Profile, passphrase: &str) -> Result<usize> {
+    let salt = random_salt();
+    let mut key = None;
+    let mut count = 0;
+    for (field, value) in fields_mut(profile) {
+        if !field.secret || is_sealed(value) {
+            continue;
+        }
+        let key = match &key {
+            Some(key) => key,
+            None => key.insert(derive_key(passphrase, &salt)?),
+        };
+        *value = seal(key, &salt, &field.path, value)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Decrypts every `ENC[...]` value of `profile`. `passphrase` is only asked for when there is
+/// one. Returns how many values were decrypted.
+pub fn decrypt_secrets(
+    profile: &mut Profile,
+    passphrase: impl FnOnce() -> Result<String>,
+) -> Result<usize> {
+    let mut passphrase = Some(passphrase);
+    let mut phrase = String::new();
+    // Values exported together share a salt, so this usually derives a single key
+    let mut keys: HashMap<Vec<u8>, Key> = HashMap::new();
+    let mut count = 0;
+    for (field, value) in fields_mut(profile) {
+        if !is_sealed(value) {
+            continue;
+        }
+        let sealed = SealedValue::parse(value)
+            .with_context(|| format!("Malformed encrypted value for {}", field.path))?;
+        if let Some(obtain) = passphrase.take() {
+            phrase = obtain()?;
+        }
+        let key = match keys.get(&sealed.salt) {
+            Some(key) => *key,
+            None => {
+                let key = derive_key(&phrase, &sealed.salt)?;
+                keys.insert(sealed.salt.clone(), key);
+                key
+            }
+        };
+        let opened = sealed
+            .open(&key, &field.path)
+            .or_else(|e| match field.legacy_path {
+                Some(path) => sealed.open(&key, path),
+                None => Err(e),
+            });
+        *value = opened.map_err(|_| {
+            anyhow!(
+                "Failed to decrypt {}: wrong passphrase, or the value was moved from another field",
+                field.path
+            )
+        })?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Reads the export passphrase from `GITP_EXPORT_PASSPHRASE` or prompts for it; a new one
+/// (`confirm`) is asked twice.
+pub fn obtain_passphrase(confirm: bool) -> Result<String> {
+    cipher::obtain_passphrase(&PASSPHRASE_PROMPT, confirm)
+}
+
+fn is_sealed(value: &str) -> bool {
+    value.starts_with(PREFIX) && value.ends_with(']')
+}
+
+/// A string field of a profile that can hold a secret.
+struct Field {
+    /// Dotted path, bound into the ciphertext so a value can't be moved to another field
+    path: String,
+    /// Whether export encrypts it
+    secret: bool,
+    /// Path the value was bound to by older versions, also accepted on decryption
+    legacy_path: Option<&'static str>,
+}
+
+impl Field {
+    fn new(path: String, secret: bool) -> Self {
+        Self {
+            path,
+            secret,
+            legacy_path: None,
+        }
+    }
+}
+
+fn fields_mut(profile: &mut Profile) -> Vec<(Field, &mut String)> {
+    let mut fields = Vec::new();
+    // Profiles had a single set of HTTPS credentials before
+    let single = profile.https_credentials.len() == 1;
+    for creds in &mut profile.https_credentials {
+        if let CredentialType::Token(token) = &mut creds.credential_type {
+            let mut field = Field::new(
+                format!("https_credentials.{}.credential_type.value", creds.host),
+                true,
+            );
+            if single {
+                field.legacy_path = Some("https_credentials.credential_type.value");
+            }
+            fields.push((field, token));
+        }
+    }
+    if let Some(CredentialType::Token(pass)) = profile
+        .sendemail
+        .as_mut()
+        .and_then(|s| s.smtp_pass.as_mut())
+    {
+        fields.push((
+            Field::new("sendemail.smtp_pass.value".to_string(), true),
+            pass,
+        ));
+    }
+    for (key, value) in &mut profile.custom_config {
+        let path = format!("custom_config.{}", key);
+        let secret = is_secret_field(&path);
+        for (i, value) in value.values_mut().iter_mut().enumerate() {
+            fields.push((Field::new(format!("{}[{}]", path, i), secret), value));
+        }
+    }
+    for (tool, settings) in &mut profile.extras {
+        for (key, value) in settings {
+            let path = format!("extras.{}.{}", tool, key);
+            let secret = is_secret_field(&path);
+            fields.push((Field::new(path, secret), value));
+        }
+    }
+    fields
+}
+
+fn seal(key: &Key, salt: &[u8], path: &str, plaintext: &str) -> Result<String> {
+    let (nonce, data) = cipher::encrypt(key, plaintext.as_bytes(), path.as_bytes())
+        .with_context(|| format!("Failed to encrypt {}", path))?;
+    Ok(format!(
+        "{}salt:{},nonce:{},data:{}]",
+        PREFIX,
+        BASE64.encode(salt),
+        BASE64.encode(nonce),
+        BASE64.encode(data)
+    ))
+}
+
+/// The parts of an `ENC[xchacha20poly1305,argon2id,salt:..,nonce:..,data:..]` value.
+struct SealedValue {
+    salt: Vec<u8>,
+    nonce: Vec<u8>,
+    data: Vec<u8>,
+}
+
+impl SealedValue {
+    fn parse(value: &str) -> Result<Self> {
+        let body = value
+            .strip_prefix(PREFIX)
+            .and_then(|rest| rest.strip_suffix(']'))
+            .ok_or_else(|| anyhow!("not an ENC[...] value"))?;
+        let mut parts: HashMap<&str, Vec<u8>> = HashMap::new();
+        for part in body.split(',') {
+            let (name, encoded) = part
+                .split_once(':')
+                .ok_or_else(|| anyhow!("'{}' is not name:value", part))?;
+            let decoded = BASE64
+                .decode(encoded)
+                .with_context(|| format!("{} is not base64", name))?;
+            parts.insert(name, decoded);
+        }
+        let mut take = |name| parts.remove(name).ok_or_else(|| anyhow!("no {}", name));
+        let sealed = SealedValue {
+            salt: take("salt")?,
+            nonce: take("nonce")?,
+            data: take("data")?,
+        };
+        if sealed.nonce.len() != 24 {
+            bail!("invalid nonce length");
+        }
+        Ok(sealed)
+    }
+
+    fn open(&self, key: &Key, path: &str) -> Result<String> {
+        let plaintext = cipher::decrypt(key, &self.nonce, &self.data, path.as_bytes())?;
+        String::from_utf8(plaintext).context("decrypted value is not UTF-8")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::HttpsCredentials;
+
+    #[test]
+    fn test_encrypt_decrypt_secrets() -> Result<()> {
+        let mut profile = Profile::new(
+            "work".to_string(),
+            "John Doe".to_string(),
+            "john@company.com".to_string(),
+        );
+        profile.https_credentials = vec![HttpsCredentials::new(
+            "github.com".to_string(),
+            "jdoe".to_string(),
+            CredentialType::Token("ghp_secret".to_string()),
+        )];
+        profile
+            .custom_config
+            .insert("pull.rebase".to_string(), "true".into());
+        profile.extras.insert(
+            "npm".to_string(),
+            [("_authToken".to_string(), "npm_secret".to_string())].into(),
+        );
+        let original = profile.clone();
+
+        assert_eq!(encrypt_secrets(&mut profile, "correct horse")?, 2);
+        let exported = toml::to_string_pretty(&profile)?;
+        assert!(!exported.contains("ghp_secret") && !exported.contains("npm_secret"));
+        assert!(exported.contains("john@company.com") && exported.contains("pull.rebase"));
+
+        let mut imported: Profile = toml::from_str(&exported)?;
+        let wrong = decrypt_secrets(&mut imported.clone(), || Ok("battery staple".to_string()));
+        assert!(wrong.is_err());
+        assert_eq!(
+            decrypt_secrets(&mut imported, || Ok("correct horse".to_string()))?,
+            2
+        );
+        assert_eq!(imported, original);
+
+        // Nothing sealed: the passphrase isn't asked for
+        decrypt_secrets(&mut imported, || bail!("asked for a passphrase"))?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_sealed_value_is_bound_to_its_field() -> Result<()> {
+        let salt = [7u8; cipher::SALT_LEN];
+        let key = derive_key("correct horse", &salt)?;
+        let sealed = seal(&key, &salt, "extras.npm._authToken", "npm_secret")?;
+        let parsed = SealedValue::parse(&sealed)?;
+        assert_eq!(parsed.open(&key, "extras.npm._authToken")?, "npm_secret");
+        assert!(parsed
+            .open(&key, "https_credentials.github.com.credential_type.value")
+            .is_err());
+
+        // Tokens exported when profiles had a single set of HTTPS credentials still open
+        let legacy = seal(
+            &key,
+            &salt,
+            "https_credentials.credential_type.value",
+            "ghp_secret",
+        )?;
+        let mut profile = Profile::new(
+            "work".to_string(),
+            "John Doe".to_string(),
+            "john@company.com".to_string(),
+        );
+        profile.https_credentials = vec![HttpsCredentials::new(
+            "github.com".to_string(),
+            "jdoe".to_string(),
+            CredentialType::Token(legacy),
+        )];
+        decrypt_secrets(&mut profile, || Ok("correct horse".to_string()))?;
+        assert_eq!(
+            profile.https_credentials[0].credential_type,
+            CredentialType::Token("ghp_secret".to_string())
+        );
+        Ok(())
+    }
+}
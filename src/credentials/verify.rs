@@ -0,0 +1,377 @@
+// src/credentials/verify.rs
+
+#[cfg(feature = "http")]
+use anyhow::Context;
+use anyhow::{bail, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+
+use crate::config::{
+    host_matches, is_host_pattern, same_host, HttpsCredentials, ProviderAccount, Settings,
+};
+#[cfg(feature = "http")]
+use crate::http::Client;
+use crate::providers::{self, Provider, ProviderKind};
+
+/// Days before expiry at which a token starts being reported as "expiring soon".
+const EXPIRY_WARNING_DAYS: i64 = 7;
+
+/// What a provider reported about a token.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenInfo {
+    /// Account the token authenticates as
+    pub login: Option<String>,
+    /// Numeric ID of the account
+    pub account_id: Option<u64>,
+    /// Display name of the account
+    pub display_name: Option<String>,
+    pub avatar_url: Option<String>,
+    /// Granted scopes; `None` when the provider does not report them (e.g. GitHub fine-grained tokens)
+    pub scopes: Option<Vec<String>>,
+    /// Expiry date; `None` for tokens without an expiry
+    pub expires_at: Option<NaiveDate>,
+}
+
+impl TokenInfo {
+    /// The account to record on the credentials, when the provider named one.
+    pub fn account(&self, verified_at: DateTime<Utc>) -> Option<ProviderAccount> {
+        Some(ProviderAccount {
+            login: self.login.clone()?,
+            id: self.account_id,
+            name: self.display_name.clone().filter(|name| !name.is_empty()),
+            avatar_url: self.avatar_url.clone(),
+            verified_at,
+        })
+    }
+
+    /// Records what the provider reported on `creds`.
+    pub fn record(self, creds: &mut HttpsCredentials, verified_at: DateTime<Utc>) {
+        creds.account = self.account(verified_at);
+        creds.token_scopes = self.scopes;
+        creds.token_expires_at = self.expires_at;
+    }
+}
+
+/// Asks the provider for `host` who the token belongs to, which scopes it has and when it expires.
+pub fn inspect_token(settings: &Settings, host: &str, token: &str) -> Result<TokenInfo> {
+    if is_host_pattern(host) {
+        bail!(
+            "'{}' is a host pattern; tokens can only be verified against a single host.",
+            host
+        );
+    }
+    match providers::lookup(host) {
+        Some(provider) if matches!(provider.kind, ProviderKind::GitHub | ProviderKind::GitLab) => {
+            inspect_with(settings, provider, token)
+        }
+        _ => bail!(
+            "Token verification is not supported for host '{}' (supported: GitHub and GitLab).",
+            host
+        ),
+    }
+}
+
+#[cfg(feature = "http")]
+fn inspect_with(settings: &Settings, provider: Provider, token: &str) -> Result<TokenInfo> {
+    let client = Client::new(settings)?;
+    match provider.kind {
+        ProviderKind::GitHub => inspect_github_token(&client, &provider, token),
+        _ => inspect_gitlab_token(&client, &provider, token),
+    }
+}
+
+#[cfg(not(feature = "http"))]
+fn inspect_with(_settings: &Settings, _provider: Provider, _token: &str) -> Result<TokenInfo> {
+    bail!("This gitp build cannot reach the provider (built without the 'http' feature).")
+}
+
+#[cfg(feature = "http")]
+fn inspect_github_token(client: &Client, provider: &Provider, token: &str) -> Result<TokenInfo> {
+    let url = format!("{}/user", api_base(provider)?);
+    let (auth, value) = provider.auth_header(token);
+    let response = client.get(
+        &url,
+        &[(auth, &value), ("Accept", "application/vnd.github+json")],
+    )?;
+
+    let scopes = response.header("x-oauth-scopes").map(parse_scopes_header);
+    let expires_at = response
+        .header("github-authentication-token-expiration")
+        .and_then(parse_github_expiration);
+    let body: serde_json::Value = response
+        .into_json()
+        .context("Failed to parse GitHub user response")?;
+
+    Ok(TokenInfo {
+        login: body["login"].as_str().map(str::to_string),
+        account_id: body["id"].as_u64(),
+        display_name: body["name"].as_str().map(str::to_string),
+        avatar_url: body["avatar_url"].as_str().map(str::to_string),
+        scopes,
+        expires_at,
+    })
+}
+
+#[cfg(feature = "http")]
+fn inspect_gitlab_token(client: &Client, provider: &Provider, token: &str) -> Result<TokenInfo> {
+    let api_base = api_base(provider)?;
+    let (auth, value) = provider.auth_header(token);
+    let headers = [(auth, value.as_str())];
+    let token_url = format!("{}/personal_access_tokens/self", api_base);
+    let token_body: serde_json::Value = client
+        .get(&token_url, &headers)?
+        .into_json()
+        .context("Failed to parse GitLab token information")?;
+
+    let user_url = format!("{}/user", api_base);
+    let user_body: serde_json::Value = client
+        .get(&user_url, &headers)?
+        .into_json()
+        .context("Failed to parse GitLab user response")?;
+
+    Ok(TokenInfo {
+        login: user_body["username"].as_str().map(str::to_string),
+        account_id: user_body["id"].as_u64(),
+        display_name: user_body["name"].as_str().map(str::to_string),
+        avatar_url: user_body["avatar_url"].as_str().map(str::to_string),
+        scopes: token_body["scopes"].as_array().map(|scopes| {
+            scopes
+                .iter()
+                .filter_map(|s| s.as_str().map(str::to_string))
+                .collect()
+        }),
+        expires_at: token_body["expires_at"]
+            .as_str()
+            .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok()),
+    })
+}
+
+/// Parses GitHub's `X-OAuth-Scopes` header ("repo, read:org").
+#[cfg_attr(not(feature = "http"), allow(dead_code))]
+fn parse_scopes_header(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Parses GitHub's `GitHub-Authentication-Token-Expiration` header ("2024-06-30 12:00:00 UTC").
+#[cfg_attr(not(feature = "http"), allow(dead_code))]
+fn parse_github_expiration(value: &str) -> Option<NaiveDate> {
+    value
+        .trim()
+        .get(..10)
+        .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+}
+
+#[cfg(feature = "http")]
+fn api_base(provider: &Provider) -> Result<&str> {
+    provider
+        .api_base
+        .as_deref()
+        .with_context(|| format!("No API URL is known for {}.", provider.host))
+}
+
+/// Scopes the token of `creds` must have: the profile's own `required_scopes` and those of
+/// the `required_token_scopes` entries for its host, without duplicates.
+pub fn required_scopes(settings: &Settings, creds: &HttpsCredentials) -> Vec<String> {
+    let mut scopes: Vec<String> = Vec::new();
+    let policy = settings
+        .required_token_scopes
+        .iter()
+        .filter(|rule| same_host(&rule.host, &creds.host) || host_matches(&rule.host, &creds.host))
+        .flat_map(|rule| &rule.scopes);
+    for scope in creds.required_scopes.iter().chain(policy) {
+        if !scopes.contains(scope) {
+            scopes.push(scope.clone());
+        }
+    }
+    scopes
+}
+
+/// Warnings about an expired/expiring token, a missing repository scope or a scope
+/// [`required_scopes`] asks for, based on the information recorded by the last verification.
+/// Performs no network access.
+pub fn token_warnings(
+    settings: &Settings,
+    creds: &HttpsCredentials,
+    today: NaiveDate,
+) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let who = format!("{}@{}", creds.username, creds.host);
+
+    if let Some(expires_at) = creds.token_expires_at {
+        let days_left = (expires_at - today).num_days();
+        if days_left < 0 {
+            warnings.push(format!("Token for {} expired on {}.", who, expires_at));
+        } else if days_left <= EXPIRY_WARNING_DAYS {
+            warnings.push(format!(
+                "Token for {} expires on {} (in {} day{}).",
+                who,
+                expires_at,
+                days_left,
+                if days_left == 1 { "" } else { "s" }
+            ));
+        }
+    }
+
+    let provider = providers::lookup(&creds.host);
+    if let (Some(scopes), Some(provider), Some(needed)) = (
+        &creds.token_scopes,
+        &provider,
+        provider.as_ref().and_then(|p| p.repo_scopes()),
+    ) {
+        if !needed
+            .iter()
+            .any(|n| scopes.iter().any(|s| provider.kind.scope_covers(s, n)))
+        {
+            warnings.push(format!(
+                "Token for {} lacks the '{}' scope (has: {}).",
+                who,
+                needed.join("' or '"),
+                if scopes.is_empty() {
+                    "none".to_string()
+                } else {
+                    scopes.join(", ")
+                }
+            ));
+        }
+    }
+
+    if let Some(scopes) = &creds.token_scopes {
+        let required = required_scopes(settings, creds);
+        let missing: Vec<&str> = match &provider {
+            Some(provider) => provider.kind.missing_scopes(scopes, &required),
+            None => required
+                .iter()
+                .filter(|n| !scopes.contains(n))
+                .map(String::as_str)
+                .collect(),
+        };
+        if !missing.is_empty() {
+            warnings.push(format!(
+                "Token for {} lacks the required scope{} '{}'.",
+                who,
+                if missing.len() == 1 { "" } else { "s" },
+                missing.join("', '")
+            ));
+        }
+    }
+
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{CredentialType, RequiredScopes};
+
+    fn creds(host: &str) -> HttpsCredentials {
+        HttpsCredentials::new(
+            host.to_string(),
+            "octocat".to_string(),
+            CredentialType::Token("t".to_string()),
+        )
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_unsupported_provider() {
+        let settings = Settings::default();
+        for host in ["bitbucket.org", "git.example.com", "*.example.com"] {
+            assert!(inspect_token(&settings, host, "t").is_err(), "{}", host);
+        }
+    }
+
+    #[test]
+    fn test_parse_github_headers() {
+        assert_eq!(
+            parse_scopes_header("repo, read:org,  workflow"),
+            vec!["repo", "read:org", "workflow"]
+        );
+        assert!(parse_scopes_header("").is_empty());
+        assert_eq!(
+            parse_github_expiration("2024-06-30 12:00:00 UTC"),
+            Some(date("2024-06-30"))
+        );
+        assert_eq!(parse_github_expiration("soon"), None);
+    }
+
+    #[test]
+    fn test_token_warnings() {
+        let today = date("2024-06-01");
+        let mut settings = Settings::default();
+
+        let mut c = creds("github.com");
+        assert!(token_warnings(&settings, &c, today).is_empty());
+
+        c.token_expires_at = Some(date("2024-05-31"));
+        c.token_scopes = Some(vec!["repo".to_string()]);
+        let warnings = token_warnings(&settings, &c, today);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("expired on 2024-05-31"));
+
+        c.token_expires_at = Some(date("2024-06-02"));
+        assert!(token_warnings(&settings, &c, today)[0].contains("in 1 day)"));
+
+        c.token_expires_at = Some(date("2025-01-01"));
+        c.token_scopes = Some(vec!["read:org".to_string()]);
+        let warnings = token_warnings(&settings, &c, today);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("lacks the 'repo' scope"));
+
+        let mut gitlab = creds("gitlab.com");
+        gitlab.token_scopes = Some(vec!["api".to_string()]);
+        assert!(token_warnings(&settings, &gitlab, today).is_empty());
+
+        let mut unknown = creds("git.example.com");
+        unknown.token_scopes = Some(vec![]);
+        assert!(token_warnings(&settings, &unknown, today).is_empty());
+
+        settings.required_token_scopes.push(RequiredScopes {
+            host: "github.com".to_string(),
+            scopes: vec!["repo".to_string(), "write:public_key".to_string()],
+        });
+        c.token_scopes = Some(vec!["repo".to_string(), "admin:public_key".to_string()]);
+        assert!(token_warnings(&settings, &c, today).is_empty());
+        c.token_scopes = Some(vec!["repo".to_string()]);
+        c.required_scopes = vec!["workflow".to_string()];
+        assert_eq!(
+            token_warnings(&settings, &c, today),
+            ["Token for octocat@github.com lacks the required scopes 'workflow', 'write:public_key'."]
+        );
+    }
+
+    #[test]
+    fn test_record_account() {
+        let verified_at = Utc::now();
+        let mut c = creds("gitlab.com");
+        assert_eq!(c.login(), "octocat");
+
+        let info = TokenInfo {
+            login: Some("octo".to_string()),
+            account_id: Some(42),
+            display_name: Some(String::new()),
+            avatar_url: None,
+            scopes: Some(vec!["api".to_string()]),
+            expires_at: None,
+        };
+        info.record(&mut c, verified_at);
+        assert_eq!(
+            c.account,
+            Some(ProviderAccount {
+                login: "octo".to_string(),
+                id: Some(42),
+                name: None,
+                avatar_url: None,
+                verified_at,
+            })
+        );
+        assert_eq!(c.login(), "octo");
+        assert_eq!(c.token_scopes.as_deref(), Some(&["api".to_string()][..]));
+    }
+}
@@ -0,0 +1,98 @@
+// src/extras/hg.rs
+
+use anyhow::{bail, Context, Result};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use super::{check_single_line, ExtraApplier, ExtraSettings};
+use crate::config::Profile;
+use crate::utils::managed_block::write_block;
+
+/// Writes `extras.hg` entries, keyed `section.name`, to ~/.hgrc inside a gitp-managed block.
+/// `ui.username` defaults to the profile's git identity, so an empty `[extras.hg]` table is
+/// enough to keep Mercurial commits in line.
+pub struct HgApplier;
+
+impl ExtraApplier for HgApplier {
+    fn name(&self) -> &'static str {
+        "hg"
+    }
+
+    fn config_path(&self) -> Result<PathBuf> {
+        Ok(dirs::home_dir()
+            .context("Failed to get home directory.")?
+            .join(".hgrc"))
+    }
+
+    fn apply(&self, profile: &Profile, settings: Option<&ExtraSettings>) -> Result<Option<String>> {
+        let body = match settings {
+            Some(settings) => render(profile, settings)?,
+            None => String::new(),
+        };
+        let path = self.config_path()?;
+        Ok(write_block(&path, &body)?.then(|| format!("updated {}", path.display())))
+    }
+}
+
+fn render(profile: &Profile, settings: &ExtraSettings) -> Result<String> {
+    let mut sections: BTreeMap<&str, Vec<(&str, &str)>> = BTreeMap::new();
+    for (key, value) in settings {
+        check_single_line("hg", key, value)?;
+        match key.split_once('.') {
+            Some((section, name)) if !section.is_empty() && !name.is_empty() => {
+                sections.entry(section).or_default().push((name, value));
+            }
+            _ => bail!(
+                "hg setting '{}' must be written as section.name (e.g. ui.username)",
+                key
+            ),
+        }
+    }
+
+    let username = format!(
+        "{} <{}>",
+        profile.git_config.user_name, profile.git_config.user_email
+    );
+    let ui = sections.entry("ui").or_default();
+    if !ui.iter().any(|(name, _)| *name == "username") {
+        ui.insert(0, ("username", &username));
+    }
+
+    let mut body = String::new();
+    for (section, entries) in sections {
+        body.push_str(&format!("[{}]\n", section));
+        for (name, value) in entries {
+            body.push_str(&format!("{} = {}\n", name, value));
+        }
+    }
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_hgrc() {
+        let profile = Profile::new(
+            "work".to_string(),
+            "John Doe".to_string(),
+            "john@company.com".to_string(),
+        );
+        let mut settings = ExtraSettings::new();
+        assert_eq!(
+            render(&profile, &settings).unwrap(),
+            "[ui]\nusername = John Doe <john@company.com>\n"
+        );
+
+        settings.insert("ui.username".to_string(), "jdoe".to_string());
+        settings.insert("auth.acme.prefix".to_string(), "hg.acme.com".to_string());
+        assert_eq!(
+            render(&profile, &settings).unwrap(),
+            "[auth]\nacme.prefix = hg.acme.com\n[ui]\nusername = jdoe\n"
+        );
+
+        settings.insert("username".to_string(), "x".to_string());
+        assert!(render(&profile, &settings).is_err());
+    }
+}
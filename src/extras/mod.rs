@@ -0,0 +1,75 @@
+// src/extras/mod.rs
+// Identity settings for tools other than git, applied alongside a profile
+
+pub mod hg;
+pub mod npm;
+pub mod plugins;
+
+use anyhow::{bail, Result};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::config::Profile;
+use crate::utils::output::Paint;
+
+/// One applier's table in a profile's `extras`.
+pub type ExtraSettings = BTreeMap<String, String>;
+
+/// Applies the `extras.<name>` table of a profile to some tool's configuration.
+pub trait ExtraApplier {
+    /// Key of the profile's `extras` table this applier reads
+    fn name(&self) -> &'static str;
+
+    /// File whose gitp-managed block this applier writes
+    fn config_path(&self) -> Result<PathBuf>;
+
+    /// Applies `settings` for `profile`. `settings` is `None` when the profile has no table for
+    /// this applier, in which case whatever an earlier profile set is removed. Returns a short
+    /// description of the change, or `None` if nothing changed.
+    fn apply(&self, profile: &Profile, settings: Option<&ExtraSettings>) -> Result<Option<String>>;
+}
+
+pub fn builtin_appliers() -> Vec<Box<dyn ExtraApplier>> {
+    vec![Box::new(npm::NpmApplier), Box::new(hg::HgApplier)]
+}
+
+/// Runs every applier for `profile`. Failures are reported but don't stop the others, since
+/// the git identity has already been switched by then.
+pub fn apply_extras(profile: &Profile) {
+    let appliers = builtin_appliers();
+    for name in profile.extras.keys() {
+        if !appliers.iter().any(|a| a.name() == name) {
+            println!(
+                "  {}: unknown extras table '{}' in profile '{}' (known: {})",
+                "Warning".warning(),
+                name,
+                profile.name,
+                appliers
+                    .iter()
+                    .map(|a| a.name())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+    }
+    for applier in &appliers {
+        match applier.apply(profile, profile.extras.get(applier.name())) {
+            Ok(Some(change)) => println!("  {}: {}", applier.name(), change.success()),
+            Ok(None) => {}
+            Err(e) => println!(
+                "  {}: {} extras not applied: {:#}",
+                "Warning".warning(),
+                applier.name(),
+                e
+            ),
+        }
+    }
+}
+
+/// Rejects settings that would break out of a line-based config file.
+pub(crate) fn check_single_line(applier: &str, key: &str, value: &str) -> Result<()> {
+    if key.is_empty() || key.contains(['=', '\n', '\r']) || value.contains(['\n', '\r']) {
+        bail!("Invalid {} setting '{}'", applier, key.escape_default());
+    }
+    Ok(())
+}
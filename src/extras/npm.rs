@@ -0,0 +1,72 @@
+// src/extras/npm.rs
+
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+
+use super::{check_single_line, ExtraApplier, ExtraSettings};
+use crate::config::Profile;
+use crate::utils::managed_block::write_block;
+
+/// Writes `extras.npm` entries (e.g. `"@acme:registry" = "https://npm.acme.com/"`) to the user
+/// `.npmrc`, inside a gitp-managed block.
+pub struct NpmApplier;
+
+impl ExtraApplier for NpmApplier {
+    fn name(&self) -> &'static str {
+        "npm"
+    }
+
+    /// npm reads the user config from `NPM_CONFIG_USERCONFIG`, defaulting to ~/.npmrc.
+    fn config_path(&self) -> Result<PathBuf> {
+        match std::env::var_os("NPM_CONFIG_USERCONFIG") {
+            Some(path) if !path.is_empty() => Ok(PathBuf::from(path)),
+            _ => Ok(dirs::home_dir()
+                .context("Failed to get home directory.")?
+                .join(".npmrc")),
+        }
+    }
+
+    fn apply(
+        &self,
+        _profile: &Profile,
+        settings: Option<&ExtraSettings>,
+    ) -> Result<Option<String>> {
+        let body = match settings {
+            Some(settings) => render(settings)?,
+            None => String::new(),
+        };
+        let path = self.config_path()?;
+        Ok(write_block(&path, &body)?.then(|| format!("updated {}", path.display())))
+    }
+}
+
+fn render(settings: &ExtraSettings) -> Result<String> {
+    let mut body = String::new();
+    for (key, value) in settings {
+        check_single_line("npm", key, value)?;
+        body.push_str(&format!("{}={}\n", key, value));
+    }
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_npmrc() {
+        let mut settings = ExtraSettings::new();
+        settings.insert(
+            "@acme:registry".to_string(),
+            "https://npm.acme.com/".to_string(),
+        );
+        settings.insert("email".to_string(), "john@acme.com".to_string());
+        assert_eq!(
+            render(&settings).unwrap(),
+            "@acme:registry=https://npm.acme.com/\nemail=john@acme.com\n"
+        );
+
+        settings.insert("bad".to_string(), "a\nb".to_string());
+        assert!(render(&settings).is_err());
+    }
+}
@@ -0,0 +1,258 @@
+// src/extras/plugins.rs
+// External appliers: executables named gitp-apply-<name> on PATH
+
+use anyhow::{bail, Context, Result};
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::{Config, CredentialType, PluginFailure, Profile};
+use crate::git::GitConfigScope;
+use crate::utils::output::Paint;
+
+pub const PLUGIN_PREFIX: &str = "gitp-apply-";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Plugin {
+    /// Name after the `gitp-apply-` prefix
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// Why a plugin is run: its profile is being switched to, or away from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PluginEvent {
+    Use,
+    Unuse,
+}
+
+impl PluginEvent {
+    fn as_str(&self) -> &'static str {
+        match self {
+            PluginEvent::Use => "use",
+            PluginEvent::Unuse => "unuse",
+        }
+    }
+}
+
+/// Plugins on PATH, sorted by name. Like a shell, the first directory providing a name wins.
+pub fn discover() -> Vec<Plugin> {
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    discover_in(std::env::split_paths(&path))
+}
+
+fn discover_in(dirs: impl IntoIterator<Item = PathBuf>) -> Vec<Plugin> {
+    let mut plugins: BTreeMap<String, PathBuf> = BTreeMap::new();
+    for dir in dirs {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries.flatten() {
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            let name = match file_name.strip_prefix(PLUGIN_PREFIX) {
+                Some(name) => name.strip_suffix(".exe").unwrap_or(name),
+                None => continue,
+            };
+            if !name.is_empty() && is_executable(&entry.path()) {
+                plugins
+                    .entry(name.to_string())
+                    .or_insert_with(|| entry.path());
+            }
+        }
+    }
+    plugins
+        .into_iter()
+        .map(|(name, path)| Plugin { name, path })
+        .collect()
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// Runs every plugin for a switch from `previous` to `name`: `unuse` for the profile being
+/// left, then `use` for the new one. Failures are handled per `settings.plugin_failure`;
+/// with `abort` an error is returned and the caller must not switch.
+pub fn run_switch(
+    config: &Config,
+    previous: Option<&str>,
+    name: &str,
+    scope: GitConfigScope,
+) -> Result<()> {
+    let plugins = discover();
+    if plugins.is_empty() {
+        return Ok(());
+    }
+    let timeout = Duration::from_secs(config.settings.plugin_timeout_secs.max(1));
+
+    let mut steps = Vec::new();
+    if let Some(previous) = previous.filter(|p| *p != name) {
+        if let Some(profile) = config.profiles.get(previous) {
+            steps.push((PluginEvent::Unuse, profile));
+        }
+    }
+    if let Some(profile) = config.profiles.get(name) {
+        steps.push((PluginEvent::Use, profile));
+    }
+
+    let mut failures = Vec::new();
+    for (event, profile) in steps {
+        for plugin in &plugins {
+            if let Err(e) = run(plugin, event, profile, scope, timeout) {
+                failures.push(format!("{:#}", e));
+            }
+        }
+    }
+    if failures.is_empty() {
+        return Ok(());
+    }
+    match config.settings.plugin_failure {
+        PluginFailure::Ignore => Ok(()),
+        PluginFailure::Warn => {
+            for failure in failures {
+                println!("  {}: plugin {}", "Warning".warning(), failure);
+            }
+            Ok(())
+        }
+        PluginFailure::Abort => bail!(
+            "Profile '{}' was not applied because plugins failed:\n  {}",
+            name,
+            failures.join("\n  ")
+        ),
+    }
+}
+
+/// Runs `plugin <event> <scope>` with the profile as JSON on stdin. Plaintext tokens are left
+/// out of the JSON.
+fn run(
+    plugin: &Plugin,
+    event: PluginEvent,
+    profile: &Profile,
+    scope: GitConfigScope,
+    timeout: Duration,
+) -> Result<()> {
+    let mut profile = profile.clone();
+    for creds in &mut profile.https_credentials {
+        if let CredentialType::Token(token) = &mut creds.credential_type {
+            token.clear();
+        }
+    }
+    if let Some(CredentialType::Token(pass)) = profile
+        .sendemail
+        .as_mut()
+        .and_then(|s| s.smtp_pass.as_mut())
+    {
+        pass.clear();
+    }
+    let input = serde_json::to_vec(&profile).context("Failed to serialize profile")?;
+    let scope: OsString = format!("{:?}", scope).to_lowercase().into();
+
+    let mut child = Command::new(&plugin.path)
+        .arg(event.as_str())
+        .arg(scope)
+        .stdin(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run {:?}", plugin.path))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A plugin that doesn't read its input closes the pipe early; that's not an error
+        let _ = stdin.write_all(&input);
+    }
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            if status.success() {
+                return Ok(());
+            }
+            bail!("{} {} exited with {}", plugin.name, event.as_str(), status);
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            bail!(
+                "{} {} timed out after {}s",
+                plugin.name,
+                event.as_str(),
+                timeout.as_secs()
+            );
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::tempdir;
+
+    fn write_script(dir: &Path, name: &str, body: &str, mode: u32) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_discover_and_run() -> Result<()> {
+        let first = tempdir()?;
+        let second = tempdir()?;
+        let out = first.path().join("out");
+        let docker = write_script(
+            first.path(),
+            "gitp-apply-docker",
+            &format!("echo \"$1 $2\" > {:?}; cat >> {:?}", out, out),
+            0o755,
+        );
+        write_script(second.path(), "gitp-apply-docker", "exit 1", 0o755);
+        write_script(second.path(), "gitp-apply-kube", "sleep 5", 0o755);
+        write_script(second.path(), "gitp-apply-off", "exit 0", 0o644);
+        write_script(second.path(), "other", "exit 0", 0o755);
+
+        let plugins = discover_in([first.path().to_path_buf(), second.path().to_path_buf()]);
+        let names: Vec<&str> = plugins.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["docker", "kube"]);
+        assert_eq!(plugins[0].path, docker);
+
+        let profile = Profile::new(
+            "work".to_string(),
+            "John Doe".to_string(),
+            "john@company.com".to_string(),
+        );
+        let timeout = Duration::from_secs(1);
+        run(
+            &plugins[0],
+            PluginEvent::Use,
+            &profile,
+            GitConfigScope::Global,
+            timeout,
+        )?;
+        let written = fs::read_to_string(&out)?;
+        assert!(written.starts_with("use global\n{\"name\":\"work\""));
+
+        let err = run(
+            &plugins[1],
+            PluginEvent::Unuse,
+            &profile,
+            GitConfigScope::Local,
+            timeout,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("timed out"));
+        Ok(())
+    }
+}
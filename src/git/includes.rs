@@ -0,0 +1,339 @@
+// src/git/includes.rs
+// `[includeIf]` rules of the system and global config that set the identity gitp applies
+
+use anyhow::{bail, Context, Result};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use super::{git_output, regex_escape};
+use crate::utils::expand_home;
+use crate::utils::output::Paint;
+
+/// Identity keys gitp applies that an included file can override
+const IDENTITY_REGEX: &str = r"^user\.(name|email|signingkey)$";
+
+/// An `[includeIf "<condition>"] path = <file>` rule in the system or global config whose
+/// file sets user.name, user.email or user.signingkey.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IncludeRule {
+    /// `system` or `global`
+    pub scope: String,
+    /// Config file holding the rule
+    pub file: PathBuf,
+    /// The condition, e.g. `gitdir:~/work/`
+    pub condition: String,
+    /// The included file, resolved like git does
+    pub path: PathBuf,
+    /// Identity keys the included file sets, in file order
+    pub identity: Vec<(String, String)>,
+    /// Identity keys whose effective value in the current directory comes from the included
+    /// file, so nothing set after it (such as a local switch) takes their place
+    pub effective: Vec<(String, String)>,
+}
+
+impl IncludeRule {
+    /// The section header as written in the config file.
+    pub fn header(&self) -> String {
+        format!("[includeIf \"{}\"]", self.condition)
+    }
+
+    /// The value the included file sets for `key`.
+    pub fn value(&self, key: &str) -> Option<&str> {
+        self.identity
+            .iter()
+            .rev()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// The directory tree a `gitdir:` condition covers (`gitdir:~/work/`). `None` for other
+    /// conditions and for patterns git would match anywhere (no leading `/` or `~/`) or
+    /// with wildcards.
+    pub fn gitdir(&self) -> Option<PathBuf> {
+        let dir = self
+            .condition
+            .strip_prefix("gitdir:")
+            .or_else(|| self.condition.strip_prefix("gitdir/i:"))?;
+        if !dir.ends_with('/')
+            || dir.contains(['*', '?', '['])
+            || !(dir.starts_with('/') || dir.starts_with("~/"))
+        {
+            return None;
+        }
+        Some(expand_home(dir))
+    }
+}
+
+/// An entry of `git config --show-scope --show-origin`.
+#[derive(Debug, PartialEq)]
+struct ScopedEntry {
+    scope: String,
+    /// The file the value was read from, without git's `file:` prefix
+    origin: PathBuf,
+    key: String,
+    value: String,
+}
+
+/// The includeIf rules of the system and global config that set identity keys, in the order
+/// git reads them. Empty without git.
+pub fn identity_include_rules() -> Result<Vec<IncludeRule>> {
+    let regex = format!(r"^includeif\..*\.path$|{}", IDENTITY_REGEX);
+    let entries = scoped_entries(&[
+        "config",
+        "--show-scope",
+        "--show-origin",
+        "-z",
+        "--get-regexp",
+        &regex,
+    ])?;
+
+    let mut rules = Vec::new();
+    for entry in &entries {
+        if !matches!(entry.scope.as_str(), "system" | "global") {
+            continue;
+        }
+        let Some(condition) = entry
+            .key
+            .strip_prefix("includeif.")
+            .and_then(|k| k.strip_suffix(".path"))
+        else {
+            continue;
+        };
+        let path = resolve_include(&entry.origin, &entry.value);
+        let identity = included_identity(&path)?;
+        if identity.is_empty() {
+            continue;
+        }
+        let effective = identity
+            .iter()
+            .filter(|(key, _)| {
+                entries
+                    .iter()
+                    .rev()
+                    .find(|e| e.key == *key)
+                    .is_some_and(|last| last.origin == path)
+            })
+            .cloned()
+            .collect();
+        rules.push(IncludeRule {
+            scope: entry.scope.clone(),
+            file: entry.origin.clone(),
+            condition: condition.to_string(),
+            path,
+            identity,
+            effective,
+        });
+    }
+    Ok(rules)
+}
+
+/// Removes `rule`'s section from the file holding it.
+pub fn remove_include_rule(rule: &IncludeRule) -> Result<()> {
+    let section = format!("includeIf.{}", rule.condition);
+    let file = rule.file.to_string_lossy();
+    let args = ["config", "--file", &file, "--remove-section", &section];
+    let output = git_output(args)
+        .with_context(|| format!("Failed to execute command: git {}", args.join(" ")))?;
+    if !output.status.success() {
+        bail!(
+            "Failed to remove {} from {}: {}",
+            rule.header(),
+            rule.file.display(),
+            String::from_utf8_lossy(&output.stderr).trim().error()
+        );
+    }
+    Ok(())
+}
+
+/// `(condition, path)` of every `[includeIf]` path in the global config, in file order, with
+/// the path as written.
+pub fn global_include_paths() -> Result<Vec<(String, String)>> {
+    Ok(scoped_entries(&[
+        "config",
+        "--global",
+        "--show-scope",
+        "--show-origin",
+        "-z",
+        "--get-regexp",
+        r"^includeif\..*\.path$",
+    ])?
+    .into_iter()
+    .filter_map(|entry| {
+        let condition = entry
+            .key
+            .strip_prefix("includeif.")?
+            .strip_suffix(".path")?
+            .to_string();
+        Some((condition, entry.value))
+    })
+    .collect())
+}
+
+/// Adds `[includeIf "<condition>"] path = <path>` to the global config. A new section goes at
+/// the end of the file, after the `[user]` section it should override.
+pub fn add_global_include(condition: &str, path: &str) -> Result<()> {
+    let key = format!("includeIf.{}.path", condition);
+    run_config(&["config", "--global", "--add", &key, path])
+}
+
+/// Removes `path` from the global `[includeIf "<condition>"]` section, and the section once
+/// nothing is left in it.
+pub fn remove_global_include(condition: &str, path: &str) -> Result<()> {
+    let key = format!("includeIf.{}.path", condition);
+    run_config(&[
+        "config",
+        "--global",
+        "--fixed-value",
+        "--unset-all",
+        &key,
+        path,
+    ])?;
+    let section = format!("includeIf.{}", condition);
+    let remaining = scoped_entries(&[
+        "config",
+        "--global",
+        "--show-scope",
+        "--show-origin",
+        "-z",
+        "--get-regexp",
+        &format!(r"^includeif\.{}\.", regex_escape(condition)),
+    ])?;
+    if remaining.is_empty() {
+        // Fails when git already dropped the section with its last key
+        let _ = git_output(["config", "--global", "--remove-section", &section]);
+    }
+    Ok(())
+}
+
+fn run_config(args: &[&str]) -> Result<()> {
+    let output = git_output(args)
+        .with_context(|| format!("Failed to execute command: git {}", args.join(" ")))?;
+    if !output.status.success() {
+        bail!(
+            "Git command failed: {}\n{}",
+            format!("git {}", args.join(" ")).error(),
+            String::from_utf8_lossy(&output.stderr).trim().error()
+        );
+    }
+    Ok(())
+}
+
+/// The included file as git finds it: `~/` is expanded and relative paths are relative to the
+/// directory of the file holding the rule.
+fn resolve_include(file: &Path, path: &str) -> PathBuf {
+    let path = expand_home(path);
+    if path.is_absolute() {
+        return path;
+    }
+    file.parent().map_or(path.clone(), |dir| dir.join(&path))
+}
+
+fn included_identity(path: &Path) -> Result<Vec<(String, String)>> {
+    if !path.is_file() {
+        return Ok(Vec::new());
+    }
+    let file = path.to_string_lossy();
+    Ok(scoped_entries(&[
+        "config",
+        "--show-scope",
+        "--show-origin",
+        "-z",
+        "--file",
+        &file,
+        "--get-regexp",
+        IDENTITY_REGEX,
+    ])?
+    .into_iter()
+    .map(|e| (e.key, e.value))
+    .collect())
+}
+
+fn scoped_entries(args: &[&str]) -> Result<Vec<ScopedEntry>> {
+    let command_str = format!("git {}", args.join(" "));
+    let output = match git_output(args) {
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        result => result.with_context(|| format!("Failed to execute command: {}", command_str))?,
+    };
+    if output.status.success() {
+        Ok(parse_scoped_entries(&String::from_utf8_lossy(
+            &output.stdout,
+        )))
+    } else if output.status.code() == Some(1) && output.stderr.is_empty() {
+        Ok(Vec::new())
+    } else {
+        bail!(
+            "Failed to read git config: {}\n{}",
+            command_str.error(),
+            String::from_utf8_lossy(&output.stderr).trim().error()
+        );
+    }
+}
+
+/// Parses `git config --show-scope --show-origin -z` output: `scope NUL origin NUL key LF
+/// value NUL` per entry.
+fn parse_scoped_entries(output: &str) -> Vec<ScopedEntry> {
+    let mut fields = output.split('\0');
+    let mut entries = Vec::new();
+    while let (Some(scope), Some(origin), Some(key_value)) =
+        (fields.next(), fields.next(), fields.next())
+    {
+        let (key, value) = key_value.split_once('\n').unwrap_or((key_value, ""));
+        entries.push(ScopedEntry {
+            scope: scope.to_string(),
+            origin: PathBuf::from(origin.strip_prefix("file:").unwrap_or(origin)),
+            key: key.to_string(),
+            value: value.to_string(),
+        });
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_gitdir() {
+        let output = "global\0file:/home/me/.gitconfig\0includeif.gitdir:~/work/.path\n\
+                      .gitconfig-work\0global\0file:/home/me/.gitconfig-work\0user.email\n\
+                      me@work.com\0";
+        let entries = parse_scoped_entries(output);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].origin, PathBuf::from("/home/me/.gitconfig"));
+        assert_eq!(entries[1].key, "user.email");
+        assert_eq!(entries[1].value, "me@work.com");
+        assert_eq!(
+            resolve_include(&entries[0].origin, &entries[0].value),
+            PathBuf::from("/home/me/.gitconfig-work")
+        );
+
+        let rule = |condition: &str| IncludeRule {
+            scope: "global".to_string(),
+            file: PathBuf::from("/home/me/.gitconfig"),
+            condition: condition.to_string(),
+            path: PathBuf::from("/home/me/.gitconfig-work"),
+            identity: Vec::new(),
+            effective: Vec::new(),
+        };
+        assert_eq!(
+            rule("gitdir:/src/work/").gitdir(),
+            Some("/src/work/".into())
+        );
+        assert_eq!(
+            rule("gitdir/i:/src/Work/").gitdir(),
+            Some("/src/Work/".into())
+        );
+        for condition in [
+            "gitdir:work/",
+            "gitdir:/src/*/work/",
+            "gitdir:/src/work/.git",
+            "onbranch:main",
+        ] {
+            assert_eq!(rule(condition).gitdir(), None, "{}", condition);
+        }
+        assert_eq!(
+            rule("gitdir:~/work/").header(),
+            "[includeIf \"gitdir:~/work/\"]"
+        );
+    }
+}
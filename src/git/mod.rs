@@ -1,6 +1,18 @@
+pub mod includes;
+pub mod url;
+
 use anyhow::{bail, Context, Result};
-use colored::Colorize;
-use std::process::{Command, Stdio};
+use git2::{ConfigLevel, ErrorCode};
+use regex::Regex;
+use std::ffi::OsStr;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::OnceLock;
+
+use crate::utils::output::Paint;
+use crate::utils::timing;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum GitConfigScope {
@@ -8,41 +20,150 @@ pub enum GitConfigScope {
     Global,
 }
 
-impl GitConfigScope {
-    fn as_arg(&self) -> &'static str {
-        match self {
-            GitConfigScope::Local => "--local",
-            GitConfigScope::Global => "--global",
+/// Where to get git when it isn't installed, for error messages.
+pub const GIT_INSTALL_HINT: &str = "Install git (https://git-scm.com/downloads) or add it to PATH.";
+
+/// `git --version`, or `None` when git can't be run. Checked once per process.
+pub fn git_version() -> Option<&'static str> {
+    static VERSION: OnceLock<Option<String>> = OnceLock::new();
+    VERSION
+        .get_or_init(|| {
+            let output = Command::new("git")
+                .arg("--version")
+                .stdin(Stdio::null())
+                .stderr(Stdio::null())
+                .output()
+                .ok()
+                .filter(|output| output.status.success())?;
+            Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+        })
+        .as_deref()
+}
+
+/// Fails with instructions when git isn't installed, for commands that can't work without it.
+/// Other commands carry on: reading git config without git finds nothing.
+pub fn require_git(action: &str) -> Result<()> {
+    if git_version().is_none() {
+        bail!(
+            "{} needs git, which wasn't found on PATH. {}",
+            action,
+            GIT_INSTALL_HINT
+        );
+    }
+    Ok(())
+}
+
+/// Runs git with `args`, capturing its output.
+fn git_output<I, S>(args: I) -> std::io::Result<Output>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    timing::measure("git", || {
+        Command::new("git")
+            .args(args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output()
+    })
+    .map_err(|e| match e.kind() {
+        ErrorKind::NotFound => {
+            std::io::Error::new(e.kind(), format!("git was not found. {}", GIT_INSTALL_HINT))
         }
+        _ => e,
+    })
+}
+
+/// Opens the config file(s) `git config` reads and writes at `scope` and runs `f` on them,
+/// without running git. `None` for the local scope outside a repository.
+fn with_config<T>(
+    scope: GitConfigScope,
+    f: impl FnOnce(&mut git2::Config) -> Result<T, git2::Error>,
+) -> Result<Option<T>> {
+    timing::measure("git config", || {
+        let mut config = match scope {
+            GitConfigScope::Local => match open_repo()? {
+                Some(repo) => repo
+                    .config()
+                    .and_then(|config| config.open_level(ConfigLevel::Local))
+                    .context("Failed to open the repository's Git config")?,
+                None => return Ok(None),
+            },
+            GitConfigScope::Global => {
+                let path =
+                    global_config_path().context("Failed to find the global Git config file.")?;
+                let mut config = git2::Config::new()?;
+                // `git config --global` reads the XDG file too, beneath the one it writes to
+                if std::env::var_os("GIT_CONFIG_GLOBAL").is_none() {
+                    if let Some(xdg) = xdg_config_path().filter(|xdg| *xdg != path && xdg.exists())
+                    {
+                        config
+                            .add_file(&xdg, ConfigLevel::XDG, false)
+                            .with_context(|| format!("Failed to read {:?}", xdg))?;
+                    }
+                }
+                config
+                    .add_file(&path, ConfigLevel::Global, false)
+                    .with_context(|| format!("Failed to read {:?}", path))?;
+                config
+            }
+        };
+        Ok(Some(f(&mut config)?))
+    })
+}
+
+/// Like `with_config`, for changes, which fail for the local scope outside a repository.
+fn change_config(
+    scope: GitConfigScope,
+    f: impl FnOnce(&mut git2::Config) -> Result<(), git2::Error>,
+) -> Result<()> {
+    with_config(scope, f)?.context("Not inside a Git repository.")
+}
+
+/// The repository git would find from the working directory (or `GIT_DIR`), if any.
+fn open_repo() -> Result<Option<git2::Repository>> {
+    match git2::Repository::open_from_env() {
+        Ok(repo) => Ok(Some(repo)),
+        Err(e) if e.code() == ErrorCode::NotFound => Ok(None),
+        Err(e) => Err(e).context("Failed to open the Git repository"),
     }
 }
 
-/// Runs a git command with the given arguments.
-fn run_git_command(args: &[&str]) -> Result<()> {
-    let command_str = format!("git {}", args.join(" "));
-    // println!("Executing: {}", command_str.dimmed()); // Optional: for debugging
+/// Treats a key or value that isn't there as removed.
+fn removed(result: Result<(), git2::Error>) -> Result<(), git2::Error> {
+    match result {
+        Err(e) if e.code() == ErrorCode::NotFound => Ok(()),
+        result => result,
+    }
+}
 
-    let output = Command::new("git")
-        .args(args)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .output()
-        .with_context(|| format!("Failed to execute command: {}", command_str))?;
+/// `value` with the characters of POSIX extended regular expressions escaped.
+pub(crate) fn regex_escape(value: &str) -> String {
+    let mut escaped = String::new();
+    for c in value.chars() {
+        if r"\.^$|?*+()[]{}".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        bail!(
-            "Git command failed: {}\\n{}",
-            command_str.red(),
-            stderr.trim().red()
-        );
+/// A value pattern no value matches, so that setting a multi-valued key adds to it.
+const NO_VALUE_PATTERN: &str = "a^";
+
+/// The value of a config entry; a bare key (`[core] bare`) has an empty one.
+fn entry_value(entry: &git2::ConfigEntry) -> String {
+    if entry.has_value() {
+        String::from_utf8_lossy(entry.value_bytes()).into_owned()
+    } else {
+        String::new()
     }
-    Ok(())
 }
 
 /// Sets a Git configuration value.
 pub fn set_git_config(key: &str, value: &str, scope: GitConfigScope) -> Result<()> {
-    run_git_command(&["config", scope.as_arg(), key, value]).with_context(|| {
+    change_config(scope, |config| config.set_str(key, value)).with_context(|| {
         format!(
             "Failed to set Git config {} to '{}' ({:?})",
             key, value, scope
@@ -51,86 +172,650 @@ pub fn set_git_config(key: &str, value: &str, scope: GitConfigScope) -> Result<(
 }
 
 /// Unsets a Git configuration value.
-/// It's not an error if the key doesn't exist.
+/// It's not an error if the key doesn't exist. gitp itself unsets keys in an
+/// [`apply_git_config`] batch.
+#[cfg(test)]
 pub fn unset_git_config(key: &str, scope: GitConfigScope) -> Result<()> {
-    let args = &["config", scope.as_arg(), "--unset", key];
-    let command_str = format!("git {}", args.join(" "));
+    change_config(scope, |config| removed(config.remove(key)))
+        .with_context(|| format!("Failed to unset Git config key '{}' ({:?})", key, scope))
+}
 
-    let output = Command::new("git")
-        .args(args)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .output()
-        .with_context(|| {
-            format!(
-                "Failed to execute command to unset Git config: {}",
-                command_str
-            )
-        })?;
+/// Gets a Git configuration value.
+/// Returns Ok(None) if the key is not set, or outside a repository for the local scope.
+pub fn get_git_config(key: &str, scope: GitConfigScope) -> Result<Option<String>> {
+    let value = with_config(scope, |config| match config.get_entry(key) {
+        Ok(entry) => Ok(Some(entry_value(&entry))),
+        Err(e) if e.code() == ErrorCode::NotFound => Ok(None),
+        Err(e) => Err(e),
+    })
+    .with_context(|| format!("Failed to get Git config for key '{}' ({:?})", key, scope))?;
+    // A key without a value reads as unset (shouldn't happen for user.name/email)
+    Ok(value
+        .flatten()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty()))
+}
 
-    if output.status.success() {
-        // Key was found and successfully removed
-        Ok(())
-    } else if output.status.code() == Some(5) {
-        // Key was not found, which is fine for an unset operation.
-        Ok(())
+/// The file `git config --global` writes to: `GIT_CONFIG_GLOBAL` if set, else ~/.gitconfig,
+/// unless only the XDG file ($XDG_CONFIG_HOME/git/config) exists.
+pub fn global_config_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("GIT_CONFIG_GLOBAL").filter(|p| !p.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+    let home = dirs::home_dir()?.join(".gitconfig");
+    match xdg_config_path() {
+        Some(xdg) if !home.exists() && xdg.exists() => Some(xdg),
+        _ => Some(home),
+    }
+}
+
+/// $XDG_CONFIG_HOME/git/config, or ~/.config/git/config.
+fn xdg_config_path() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|p| !p.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|h| h.join(".config")))
+        .map(|dir| dir.join("git").join("config"))
+}
+
+/// A change to one Git configuration key, made together with others by [`apply_git_config`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum GitConfigChange {
+    Set(String, String),
+    /// Removes the key; it's not an error if it doesn't exist
+    Unset(String),
+    /// Adds a value to a multi-valued key, keeping the others
+    Add(String, String),
+    /// Removes every occurrence of exactly the value from a multi-valued key
+    UnsetValue(String, String),
+    /// Removes every value of a multi-valued key
+    UnsetAll(String),
+}
+
+/// Makes all of `changes` to the config file `scope` writes to, or none of them. Like git, the
+/// changes go into a copy of the file held as its lock file (`config.lock`), which then
+/// replaces it; a write that fails midway leaves the file as it was.
+pub fn apply_git_config(changes: &[GitConfigChange], scope: GitConfigScope) -> Result<()> {
+    let path = match scope {
+        GitConfigScope::Local => local_config_path()?.context("Not inside a Git repository.")?,
+        GitConfigScope::Global => {
+            global_config_path().context("Failed to find the global Git config file.")?
+        }
+    };
+    // git writes through a symlinked file (dotfile managers link ~/.gitconfig) to its target
+    let path = fs::canonicalize(&path).unwrap_or(path);
+    let mut lock_name = path.clone().into_os_string();
+    lock_name.push(".lock");
+    let lock = PathBuf::from(lock_name);
+
+    let mut file = match OpenOptions::new().write(true).create_new(true).open(&lock) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => bail!(
+            "{} is locked: another git process is changing it, or one crashed and left {} \
+             behind (remove it if no git process is running).",
+            path.display(),
+            lock.display()
+        ),
+        Err(e) => return Err(e).with_context(|| format!("Failed to create {:?}", lock)),
+    };
+    let result = (|| {
+        match fs::read(&path) {
+            Ok(content) => {
+                file.write_all(&content)
+                    .with_context(|| format!("Failed to write {:?}", lock))?;
+                let permissions = fs::metadata(&path)
+                    .with_context(|| format!("Failed to read {:?}", path))?
+                    .permissions();
+                fs::set_permissions(&lock, permissions)
+                    .with_context(|| format!("Failed to set the permissions of {:?}", lock))?;
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {:?}", path)),
+        }
+        drop(file);
+        timing::measure("git config", || {
+            let mut config = git2::Config::open(&lock)?;
+            for change in changes {
+                match change {
+                    GitConfigChange::Set(key, value) => config.set_str(key, value)?,
+                    GitConfigChange::Unset(key) => removed(config.remove(key))?,
+                    GitConfigChange::Add(key, value) => {
+                        config.set_multivar(key, NO_VALUE_PATTERN, value)?
+                    }
+                    GitConfigChange::UnsetValue(key, value) => removed(
+                        config.remove_multivar(key, &format!("^{}$", regex_escape(value))),
+                    )?,
+                    GitConfigChange::UnsetAll(key) => {
+                        removed(config.remove_multivar(key, ".*"))?
+                    }
+                }
+            }
+            Ok::<_, git2::Error>(())
+        })
+        .with_context(|| format!("Failed to change {:?}", path))?;
+        fs::rename(&lock, &path).with_context(|| format!("Failed to replace {:?}", path))
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&lock);
+    }
+    result
+}
+
+/// The current repository's config file, shared by all its work trees.
+fn local_config_path() -> Result<Option<PathBuf>> {
+    let Some(repo) = open_repo()? else {
+        return Ok(None);
+    };
+    // A linked work tree's git dir names the main one in `commondir`
+    let git_dir = repo.path();
+    let common_dir = match fs::read_to_string(git_dir.join("commondir")) {
+        Ok(common_dir) => git_dir.join(common_dir.trim()),
+        Err(_) => git_dir.to_path_buf(),
+    };
+    Ok(Some(common_dir.join("config")))
+}
+
+/// Returns the top-level directory of the current Git work tree, or `None` outside a repository
+/// (or in a bare one).
+pub fn get_repo_root() -> Result<Option<PathBuf>> {
+    // Collecting the components drops the trailing separator libgit2 keeps
+    Ok(open_repo()?.and_then(|repo| repo.workdir().map(|dir| dir.components().collect())))
+}
+
+/// Runs `f` with the process working directory set to `repo`, so local-scope Git config calls
+/// act on that repository. The previous working directory is restored afterwards.
+pub fn in_repo<T>(repo: &Path, f: impl FnOnce() -> Result<T>) -> Result<T> {
+    let previous = std::env::current_dir().context("Failed to get current directory.")?;
+    std::env::set_current_dir(repo)
+        .with_context(|| format!("Failed to enter repository {:?}", repo))?;
+    let result = f();
+    std::env::set_current_dir(&previous)
+        .with_context(|| format!("Failed to return to {:?}", previous))?;
+    result
+}
+
+/// Runs `git [-c <config>]... clone <extra_args> <url> <dir>` with the terminal attached, so
+/// git shows its progress and can ask for credentials.
+pub fn clone_repo(url: &str, dir: &Path, config: &[String], extra_args: &[String]) -> Result<()> {
+    let mut command = Command::new("git");
+    for entry in config {
+        command.arg("-c").arg(entry);
+    }
+    command.arg("clone").args(extra_args).arg(url).arg(dir);
+    let status = timing::measure("git", || command.status())
+        .context("Failed to execute command: git clone")?;
+    if !status.success() {
+        bail!("git clone {} failed.", url.error());
+    }
+    Ok(())
+}
+
+/// Adds a value to a (possibly multi-valued) Git configuration key, keeping existing values.
+pub fn add_git_config(key: &str, value: &str, scope: GitConfigScope) -> Result<()> {
+    change_config(scope, |config| {
+        config.set_multivar(key, NO_VALUE_PATTERN, value)
+    })
+    .with_context(|| {
+        format!(
+            "Failed to add '{}' to Git config {} ({:?})",
+            value, key, scope
+        )
+    })
+}
+
+/// Replaces every occurrence of exactly `old_value` in a multi-valued Git configuration key,
+/// keeping the position of each value.
+pub fn replace_git_config_value(
+    key: &str,
+    old_value: &str,
+    new_value: &str,
+    scope: GitConfigScope,
+) -> Result<()> {
+    change_config(scope, |config| {
+        config.set_multivar(key, &format!("^{}$", regex_escape(old_value)), new_value)
+    })
+    .with_context(|| {
+        format!(
+            "Failed to replace '{}' with '{}' in Git config {} ({:?})",
+            old_value, new_value, key, scope
+        )
+    })
+}
+
+/// Removes every occurrence of exactly `value` from a multi-valued Git configuration key.
+/// Other values of the key are left untouched. It's not an error if the value isn't present.
+pub fn unset_git_config_value(key: &str, value: &str, scope: GitConfigScope) -> Result<()> {
+    change_config(scope, |config| {
+        removed(config.remove_multivar(key, &format!("^{}$", regex_escape(value))))
+    })
+    .with_context(|| {
+        format!(
+            "Failed to remove '{}' from Git config key '{}' ({:?})",
+            value, key, scope
+        )
+    })
+}
+
+/// Gets all (key, value) pairs whose key matches `key_regex`, in config file order.
+/// Returns an empty list if nothing matches, or outside a repository for the local scope.
+pub fn get_git_config_regexp(
+    key_regex: &str,
+    scope: GitConfigScope,
+) -> Result<Vec<(String, String)>> {
+    let regex = Regex::new(key_regex)
+        .with_context(|| format!("Invalid Git config key pattern '{}'", key_regex))?;
+    let entries = with_config(scope, |config| {
+        let mut entries = Vec::new();
+        let mut iter = config.entries(None)?;
+        while let Some(entry) = iter.next() {
+            let entry = entry?;
+            let key = String::from_utf8_lossy(entry.name_bytes()).into_owned();
+            if regex.is_match(&key) {
+                entries.push((entry.level() as i32, key, entry_value(entry)));
+            }
+        }
+        Ok(entries)
+    })
+    .with_context(|| {
+        format!(
+            "Failed to get Git config matching '{}' ({:?})",
+            key_regex, scope
+        )
+    })?
+    .unwrap_or_default();
+    // libgit2 lists the files it writes to first; git lists the XDG file before ~/.gitconfig
+    let mut entries = entries;
+    entries.sort_by_key(|(level, _, _)| *level);
+    Ok(entries
+        .into_iter()
+        .map(|(_, key, value)| (key, value))
+        .collect())
+}
+
+/// Work trees at or below `root`, at most `max_depth` directories down. Hidden directories,
+/// symlinks and the insides of work trees (submodules, vendored repos) are not searched.
+pub fn find_repos(root: &Path, max_depth: usize) -> Vec<PathBuf> {
+    let mut repos = Vec::new();
+    let mut pending = vec![(root.to_path_buf(), 0)];
+    while let Some((dir, depth)) = pending.pop() {
+        if dir.join(".git").exists() {
+            repos.push(dir);
+            continue;
+        }
+        if depth >= max_depth {
+            continue;
+        }
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let hidden = entry.file_name().to_string_lossy().starts_with('.');
+            if !hidden && entry.file_type().is_ok_and(|t| t.is_dir()) {
+                pending.push((entry.path(), depth + 1));
+            }
+        }
+    }
+    repos.sort();
+    repos
+}
+
+/// A remote of the current repository.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Remote {
+    pub name: String,
+    /// `remote.<name>.url` as stored in the repository's config
+    pub url: String,
+    /// The URL git actually connects to, after `url.<base>.insteadOf` rules
+    pub effective_url: String,
+}
+
+/// The current repository's remotes, in config order.
+pub fn get_remotes() -> Result<Vec<Remote>> {
+    get_git_config_regexp(r"^remote\..*\.url$", GitConfigScope::Local)?
+        .into_iter()
+        .filter_map(|(key, url)| {
+            let name = key.strip_prefix("remote.")?.strip_suffix(".url")?;
+            Some((name.to_string(), url))
+        })
+        .map(|(name, url)| {
+            let effective_url = get_remote_effective_url(&name)?.unwrap_or_else(|| url.clone());
+            Ok(Remote {
+                name,
+                url,
+                effective_url,
+            })
+        })
+        .collect()
+}
+
+/// The current repository's `origin` remote, or its first one when there is no `origin`.
+pub fn get_primary_remote() -> Result<Option<Remote>> {
+    let mut remotes = get_remotes()?;
+    let index = remotes.iter().position(|r| r.name == "origin").unwrap_or(0);
+    Ok((index < remotes.len()).then(|| remotes.swap_remove(index)))
+}
+
+/// The URL git fetches `name` from, with the user's insteadOf rules applied (`git remote
+/// get-url`), or `None` if there is no such remote.
+pub fn get_remote_effective_url(name: &str) -> Result<Option<String>> {
+    let Some(repo) = open_repo()? else {
+        return Ok(None);
+    };
+    let url = match repo.find_remote(name) {
+        Ok(remote) => String::from_utf8_lossy(remote.url_bytes()).into_owned(),
+        Err(e) if matches!(e.code(), ErrorCode::NotFound | ErrorCode::InvalidSpec) => {
+            return Ok(None)
+        }
+        Err(e) => return Err(e).with_context(|| format!("Failed to look up remote '{}'", name)),
+    };
+    Ok((!url.is_empty()).then_some(url))
+}
+
+/// Author and committer of a commit, as recorded (before `.mailmap` is applied).
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommitIdentity {
+    pub author_name: String,
+    pub author_email: String,
+    pub committer_name: String,
+    pub committer_email: String,
+}
+
+/// Authors and committers of every commit reachable from the current repository's refs,
+/// newest first. Empty for a repository without commits.
+pub fn commit_identities() -> Result<Vec<CommitIdentity>> {
+    let output = git_output(["log", "--all", "--format=%an%x00%ae%x00%cn%x00%ce"])
+        .context("Failed to execute command: git log")?;
+    if !output.status.success() {
+        bail!(
+            "git log failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\0').map(str::to_string);
+            Some(CommitIdentity {
+                author_name: fields.next()?,
+                author_email: fields.next()?,
+                committer_name: fields.next()?,
+                committer_email: fields.next()?,
+            })
+        })
+        .collect())
+}
+
+/// Names and emails of the authors of the last `limit` commits on the current branch, newest
+/// first. Empty outside a repository and in one without commits.
+pub fn recent_authors(limit: usize) -> Result<Vec<(String, String)>> {
+    let count = format!("--max-count={}", limit);
+    let output = match git_output(["log", &count, "--format=%an%x00%ae"]) {
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        result => result.context("Failed to execute command: git log")?,
+    };
+    if !output.status.success() {
+        return Ok(Vec::new());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (name, email) = line.split_once('\0')?;
+            Some((name.to_string(), email.to_string()))
+        })
+        .collect())
+}
+
+/// Distinct author emails of the commits `git log <revs>` lists, newest first.
+pub fn author_emails(revs: &[String]) -> Result<Vec<String>> {
+    let mut args = vec!["log".to_string(), "--format=%ae".to_string()];
+    args.extend(revs.iter().cloned());
+    let output = git_output(&args).context("Failed to execute command: git log")?;
+    if !output.status.success() {
+        bail!(
+            "git log failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let mut emails: Vec<String> = Vec::new();
+    for email in String::from_utf8_lossy(&output.stdout).lines() {
+        if !email.is_empty() && !emails.iter().any(|e| e.eq_ignore_ascii_case(email)) {
+            emails.push(email.to_string());
+        }
+    }
+    Ok(emails)
+}
+
+/// Where git runs the hook `name` from in the current repository (`core.hooksPath` applies).
+pub fn hook_path(name: &str) -> Result<PathBuf> {
+    let arg = format!("hooks/{}", name);
+    let output = git_output(["rev-parse", "--git-path", &arg])
+        .context("Failed to execute command: git rev-parse --git-path")?;
+    if !output.status.success() {
+        bail!(
+            "git rev-parse failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let path = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim());
+    if path.is_absolute() {
+        Ok(path)
     } else {
-        // Another error occurred
-        let stderr = String::from_utf8_lossy(&output.stderr);
+        Ok(std::env::current_dir()
+            .context("Failed to get current directory.")?
+            .join(path))
+    }
+}
+
+/// The username and password git's credential helpers have for `protocol://host`, asked
+/// without prompting (`git credential fill`). `None` when no helper has them.
+pub fn credential_fill(
+    protocol: &str,
+    host: &str,
+    username: Option<&str>,
+) -> Result<Option<(String, String)>> {
+    fill(None, protocol, host, username)
+}
+
+/// Like [`credential_fill`], but asks only `helper` (e.g. `manager` or `osxkeychain`), leaving
+/// out every helper git's config files set up.
+pub fn helper_fill(
+    helper: &str,
+    protocol: &str,
+    host: &str,
+    username: Option<&str>,
+) -> Result<Option<(String, String)>> {
+    fill(Some(helper), protocol, host, username)
+}
+
+/// Has `helper` store `password` for `username` at `protocol://host`
+/// (`git credential approve`).
+pub fn helper_approve(
+    helper: &str,
+    protocol: &str,
+    host: &str,
+    username: &str,
+    password: &str,
+) -> Result<()> {
+    let request = credential_request(protocol, host, Some(username), Some(password));
+    let output = git_credential(Some(helper), "approve", &request)?;
+    if !output.status.success() {
         bail!(
-            "Failed to unset Git config key '{}' ({:?}): {}\n{}",
-            key,
-            scope,
-            command_str.red(),
-            stderr.trim().red()
+            "git credential approve failed for helper '{}': {}",
+            helper,
+            String::from_utf8_lossy(&output.stderr).trim()
         );
     }
+    Ok(())
 }
 
-/// Gets a Git configuration value.
-/// Returns Ok(None) if the key is not set.
-pub fn get_git_config(key: &str, scope: GitConfigScope) -> Result<Option<String>> {
-    let args = &["config", scope.as_arg(), "--get", key];
-    let command_str = format!("git {}", args.join(" "));
+fn fill(
+    helper: Option<&str>,
+    protocol: &str,
+    host: &str,
+    username: Option<&str>,
+) -> Result<Option<(String, String)>> {
+    let request = credential_request(protocol, host, username, None);
+    let output = git_credential(helper, "fill", &request)?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    let answer = String::from_utf8_lossy(&output.stdout);
+    let field = |name: &str| {
+        answer
+            .lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix('='))
+            .map(str::to_string)
+    };
+    Ok(field("username").zip(field("password")))
+}
+
+/// A credential description in git's helper protocol.
+fn credential_request(
+    protocol: &str,
+    host: &str,
+    username: Option<&str>,
+    password: Option<&str>,
+) -> String {
+    let mut request = format!("protocol={}\nhost={}\n", protocol, host);
+    if let Some(username) = username {
+        request.push_str(&format!("username={}\n", username));
+    }
+    if let Some(password) = password {
+        request.push_str(&format!("password={}\n", password));
+    }
+    request.push('\n');
+    request
+}
 
-    let output = Command::new("git")
-        .args(args)
+/// Runs `git credential <operation>` with `request` on stdin, never prompting. With `helper`,
+/// the empty `credential.helper` first clears the helpers git's config files set up, so only
+/// that one is asked.
+fn git_credential(helper: Option<&str>, operation: &str, request: &str) -> Result<Output> {
+    let mut command = Command::new("git");
+    command.args(["-c", "credential.interactive=false"]);
+    if let Some(helper) = helper {
+        command
+            .args(["-c", "credential.helper=", "-c"])
+            .arg(format!("credential.helper={}", helper));
+    }
+    let mut child = command
+        .args(["credential", operation])
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .env_remove("GIT_ASKPASS")
+        .env_remove("SSH_ASKPASS")
+        .stdin(Stdio::piped())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
-        .output()
-        .with_context(|| {
+        .spawn()
+        .with_context(|| format!("Failed to execute command: git credential {}", operation))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(request.as_bytes())
+            .with_context(|| format!("Failed to write to git credential {}", operation))?;
+    }
+    let output = child
+        .wait_with_output()
+        .with_context(|| format!("Failed to execute command: git credential {}", operation))?;
+    // git only warns about a helper it can't run, and goes on without it
+    if let Some(name) = helper.and_then(|helper| helper.split_whitespace().next()) {
+        let missing = format!("'credential-{}' is not a git command", name);
+        if String::from_utf8_lossy(&output.stderr).contains(&missing) {
+            bail!(
+                "Credential helper '{}' isn't installed (git found no git-credential-{}).",
+                name,
+                name
+            );
+        }
+    }
+    Ok(output)
+}
+
+/// Removes all values of a (possibly multi-valued) Git configuration key.
+/// It's not an error if the key doesn't exist.
+pub fn unset_all_git_config(key: &str, scope: GitConfigScope) -> Result<()> {
+    change_config(scope, |config| removed(config.remove_multivar(key, ".*")))
+        .with_context(|| format!("Failed to unset Git config key '{}' ({:?})", key, scope))
+}
+
+/// A configuration value together with the file it comes from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GitConfigEntry {
+    /// Where git read the value from, e.g. `file:/home/user/.gitconfig`
+    pub origin: String,
+    pub key: String,
+    pub value: String,
+}
+
+/// Gets all entries whose key matches `key_regex` from every config file git reads
+/// (system, global, then local), in the order git applies them. Empty without git.
+pub fn get_effective_git_config_regexp(key_regex: &str) -> Result<Vec<GitConfigEntry>> {
+    let args = &["config", "--show-origin", "-z", "--get-regexp", key_regex];
+    let command_str = format!("git {}", args.join(" "));
+
+    let output = match git_output(args) {
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        result => result.with_context(|| {
             format!(
                 "Failed to execute command to get Git config: {}",
                 command_str
             )
-        })?;
+        })?,
+    };
 
     if output.status.success() {
-        let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
-        if stdout.is_empty() {
-            Ok(None) // Key exists but has no value (shouldn't happen for user.name/email)
-        } else {
-            Ok(Some(stdout))
-        }
+        Ok(parse_origin_entries(&String::from_utf8_lossy(
+            &output.stdout,
+        )))
     } else {
-        // Exit code 1 usually means the key was not found, which is not an error for us.
-        // Other exit codes might indicate a real problem.
         let stderr = String::from_utf8_lossy(&output.stderr);
         if output.status.code() == Some(1) && stderr.is_empty() {
-            // Key not found
-            Ok(None)
+            Ok(Vec::new())
         } else {
             bail!(
-                "Failed to get Git config for key '{}' ({:?}): {}\\n{}",
-                key,
-                scope,
-                command_str.red(),
-                stderr.trim().red()
+                "Failed to get Git config matching '{}': {}\n{}",
+                key_regex,
+                command_str.error(),
+                stderr.trim().error()
             );
         }
     }
 }
 
+/// The value git uses for `key` in the current directory, following includes such as the
+/// `includeIf` rules of `gitp rules`: the last one it reads. `None` without git.
+pub fn get_effective_git_config(key: &str) -> Result<Option<String>> {
+    // git lists section and variable names in lower case; subsections keep theirs
+    let key = match (key.split_once('.'), key.rsplit_once('.')) {
+        (Some((section, _)), Some((rest, variable))) if rest.len() > section.len() => format!(
+            "{}{}.{}",
+            section.to_lowercase(),
+            &rest[section.len()..],
+            variable.to_lowercase()
+        ),
+        _ => key.to_lowercase(),
+    };
+    Ok(
+        get_effective_git_config_regexp(&format!("^{}$", regex_escape(&key)))?
+            .pop()
+            .map(|entry| entry.value),
+    )
+}
+
+/// Parses `git config --show-origin -z` output: `origin NUL key LF value NUL` per entry.
+fn parse_origin_entries(output: &str) -> Vec<GitConfigEntry> {
+    let mut fields = output.split('\0');
+    let mut entries = Vec::new();
+    while let (Some(origin), Some(key_value)) = (fields.next(), fields.next()) {
+        let (key, value) = key_value.split_once('\n').unwrap_or((key_value, ""));
+        entries.push(GitConfigEntry {
+            origin: origin.to_string(),
+            key: key.to_string(),
+            value: value.to_string(),
+        });
+    }
+    entries
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -148,6 +833,7 @@ mod tests {
     }
 
     #[test]
+    #[serial_test::serial]
     fn test_set_get_unset_local_config() -> Result<()> {
         cleanup_git_config(TEST_KEY_LOCAL, GitConfigScope::Local);
 
@@ -194,6 +880,227 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_find_repos() -> Result<()> {
+        let root = tempfile::tempdir()?;
+        for dir in [
+            "a/.git",
+            "a/vendor/b/.git",
+            "c/d/.git",
+            ".hidden/e/.git",
+            "f/g/h/.git",
+        ] {
+            std::fs::create_dir_all(root.path().join(dir))?;
+        }
+        let repos = find_repos(root.path(), 2);
+        assert_eq!(repos, vec![root.path().join("a"), root.path().join("c/d")]);
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_in_repo() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        git2::Repository::init(dir.path())?;
+        let before = std::env::current_dir()?;
+        let key = "gitp.test.inrepo";
+
+        in_repo(dir.path(), || {
+            set_git_config(key, "x", GitConfigScope::Local)
+        })?;
+        assert_eq!(std::env::current_dir()?, before);
+        assert_eq!(
+            in_repo(dir.path(), || get_git_config(key, GitConfigScope::Local))?.as_deref(),
+            Some("x")
+        );
+        assert_eq!(get_git_config(key, GitConfigScope::Local)?, None);
+
+        // The working directory is restored when `f` fails too
+        assert!(in_repo(dir.path(), || -> Result<()> { bail!("failed") }).is_err());
+        assert_eq!(std::env::current_dir()?, before);
+        assert!(in_repo(&dir.path().join("missing"), || Ok(())).is_err());
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_get_effective_git_config_follows_includes() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        git2::Repository::init(dir.path())?;
+        let included = dir.path().join("fragment.gitconfig");
+        std::fs::write(&included, "[gitp \"Test\"]\n\tIncluded = fragment\n")?;
+
+        in_repo(dir.path(), || {
+            set_git_config(
+                "include.path",
+                &included.to_string_lossy(),
+                GitConfigScope::Local,
+            )?;
+            assert_eq!(
+                get_effective_git_config("gitp.Test.included")?.as_deref(),
+                Some("fragment")
+            );
+            assert_eq!(get_effective_git_config("gitp.test.included")?, None);
+            // Values read after the include win
+            add_git_config("gitp.Test.included", "local", GitConfigScope::Local)?;
+            assert_eq!(
+                get_effective_git_config("GITP.Test.INCLUDED")?.as_deref(),
+                Some("local")
+            );
+            Ok(())
+        })
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_multi_valued_local_config() -> Result<()> {
+        let key = "gitp.test.multivalue";
+        cleanup_git_config(key, GitConfigScope::Local);
+        let _ = unset_git_config_value(key, "first", GitConfigScope::Local);
+        let _ = unset_git_config_value(key, "second", GitConfigScope::Local);
+
+        add_git_config(key, "first", GitConfigScope::Local)?;
+        add_git_config(key, "second", GitConfigScope::Local)?;
+        let values = get_git_config_regexp(r"^gitp\.test\.multivalue$", GitConfigScope::Local)?;
+        assert_eq!(
+            values,
+            vec![
+                (key.to_string(), "first".to_string()),
+                (key.to_string(), "second".to_string())
+            ]
+        );
+
+        unset_git_config_value(key, "first", GitConfigScope::Local)?;
+        let values = get_git_config_regexp(r"^gitp\.test\.multivalue$", GitConfigScope::Local)?;
+        assert_eq!(values, vec![(key.to_string(), "second".to_string())]);
+
+        // Values are matched literally, not as patterns
+        add_git_config(key, "!gitp (a|b) *", GitConfigScope::Local)?;
+        replace_git_config_value(key, "!gitp (a|b) *", "third", GitConfigScope::Local)?;
+        unset_git_config_value(key, "sec.nd", GitConfigScope::Local)?;
+        let values = get_git_config_regexp(r"^gitp\.test\.multivalue$", GitConfigScope::Local)?;
+        assert_eq!(
+            values,
+            vec![
+                (key.to_string(), "second".to_string()),
+                (key.to_string(), "third".to_string())
+            ]
+        );
+        unset_all_git_config(key, GitConfigScope::Local)?;
+
+        // Removing a value that isn't there is fine
+        unset_git_config_value(key, "first", GitConfigScope::Local)?;
+        unset_git_config_value(key, "second", GitConfigScope::Local)?;
+        assert!(
+            get_git_config_regexp(r"^gitp\.test\.multivalue$", GitConfigScope::Local)?.is_empty()
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_apply_git_config() -> Result<()> {
+        let (name, email) = ("gitp.test.applyname", "gitp.test.applyemail");
+        set_git_config(email, "old@example.com", GitConfigScope::Local)?;
+        apply_git_config(
+            &[
+                GitConfigChange::Set(name.to_string(), "Jane".to_string()),
+                GitConfigChange::Unset(email.to_string()),
+            ],
+            GitConfigScope::Local,
+        )?;
+        assert_eq!(
+            get_git_config(name, GitConfigScope::Local)?.as_deref(),
+            Some("Jane")
+        );
+        assert_eq!(get_git_config(email, GitConfigScope::Local)?, None);
+
+        // A held lock leaves every key as it was
+        let lock = local_config_path()?.unwrap().with_extension("lock");
+        fs::write(&lock, "")?;
+        let result = apply_git_config(
+            &[GitConfigChange::Set(name.to_string(), "John".to_string())],
+            GitConfigScope::Local,
+        );
+        fs::remove_file(&lock)?;
+        assert!(result.is_err());
+        assert_eq!(
+            get_git_config(name, GitConfigScope::Local)?.as_deref(),
+            Some("Jane")
+        );
+        unset_git_config(name, GitConfigScope::Local)?;
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_apply_git_config_multi_valued() -> Result<()> {
+        let (multi, other) = ("gitp.test.applymulti", "gitp.test.applyother");
+        let values = |key: &str| -> Result<Vec<String>> {
+            Ok(get_git_config_regexp(&format!("^{}$", regex_escape(key)), GitConfigScope::Local)?
+                .into_iter()
+                .map(|(_, value)| value)
+                .collect())
+        };
+        add_git_config(multi, "a", GitConfigScope::Local)?;
+        add_git_config(multi, "b.c", GitConfigScope::Local)?;
+        add_git_config(other, "x", GitConfigScope::Local)?;
+        add_git_config(other, "y", GitConfigScope::Local)?;
+        apply_git_config(
+            &[
+                GitConfigChange::UnsetValue(multi.to_string(), "b.c".to_string()),
+                GitConfigChange::Add(multi.to_string(), "d".to_string()),
+                GitConfigChange::UnsetValue(multi.to_string(), "missing".to_string()),
+                GitConfigChange::UnsetAll(other.to_string()),
+            ],
+            GitConfigScope::Local,
+        )?;
+        assert_eq!(values(multi)?, vec!["a", "d"]);
+        assert!(values(other)?.is_empty());
+        unset_all_git_config(multi, GitConfigScope::Local)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_helper_approve_fill() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let helper = format!("store --file {}", dir.path().join("credentials").display());
+        assert_eq!(helper_fill(&helper, "https", "example.com", None)?, None);
+
+        helper_approve(&helper, "https", "example.com", "jdoe", "tok123")?;
+        assert_eq!(
+            helper_fill(&helper, "https", "example.com", None)?,
+            Some(("jdoe".to_string(), "tok123".to_string()))
+        );
+        assert_eq!(
+            helper_fill(&helper, "https", "example.com", Some("other"))?,
+            None
+        );
+        assert!(helper_fill("gitp-missing", "https", "example.com", None).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_origin_entries() {
+        let output = "file:/etc/gitconfig\0credential.helper\nstore\0file:/home/u/.gitconfig\0credential.https://github.com/.helper\n\0";
+        assert_eq!(
+            parse_origin_entries(output),
+            vec![
+                GitConfigEntry {
+                    origin: "file:/etc/gitconfig".to_string(),
+                    key: "credential.helper".to_string(),
+                    value: "store".to_string(),
+                },
+                GitConfigEntry {
+                    origin: "file:/home/u/.gitconfig".to_string(),
+                    key: "credential.https://github.com/.helper".to_string(),
+                    value: String::new(),
+                },
+            ]
+        );
+        assert!(parse_origin_entries("").is_empty());
+    }
+
     #[test]
     fn test_get_non_existent_config() -> Result<()> {
         let non_existent_key = "gitp.test.nonexistentkey";
@@ -0,0 +1,299 @@
+// src/gpg/mod.rs
+
+use anyhow::{bail, Context, Result};
+use std::process::{Command, Stdio};
+
+use crate::config::Profile;
+
+/// Where the secret part of a signing key lives, from `gpg --list-secret-keys`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyLocation {
+    /// In the local keyring
+    Local,
+    /// On a smartcard (OpenPGP card, YubiKey, ...) with this serial number
+    Card(String),
+    /// Only a stub is left locally; the secret key is offline or on a card gpg hasn't seen
+    Stub,
+}
+
+/// The key gpg signs with for a configured key ID.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SigningKey {
+    pub fingerprint: String,
+    pub location: KeyLocation,
+}
+
+/// The smartcard currently inserted, from `gpg --card-status`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CardStatus {
+    pub serial: String,
+    pub vendor: Option<String>,
+    /// Fingerprints of the signature, encryption and authentication keys on the card
+    pub fingerprints: Vec<String>,
+}
+
+impl CardStatus {
+    pub fn holds(&self, fingerprint: &str) -> bool {
+        self.fingerprints
+            .iter()
+            .any(|fpr| fpr.eq_ignore_ascii_case(fingerprint))
+    }
+}
+
+/// The profile's GPG signing key ID, unless it signs with SSH or X.509 (`gpg.format`).
+pub fn gpg_signing_key(profile: &Profile) -> Option<&str> {
+    let format = profile
+        .custom_config
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case("gpg.format"))
+        .and_then(|(_, value)| value.values().last())
+        .map(|format| format.to_lowercase());
+    if format.is_some_and(|format| format != "openpgp") {
+        return None;
+    }
+    profile
+        .git_config
+        .user_signingkey
+        .as_deref()
+        .or(profile.gpg_key.as_deref())
+}
+
+/// Whether the profile makes git sign every commit or tag (`commit.gpgsign`/`tag.gpgsign`).
+pub fn signing_required(profile: &Profile) -> bool {
+    profile.custom_config.iter().any(|(key, value)| {
+        (key.eq_ignore_ascii_case("commit.gpgsign") || key.eq_ignore_ascii_case("tag.gpgsign"))
+            && value
+                .values()
+                .last()
+                .is_some_and(|v| matches!(v.to_lowercase().as_str(), "true" | "yes" | "on" | "1"))
+    })
+}
+
+/// Looks up the secret key gpg would sign with for `key_id`, or `None` if it has none.
+pub fn signing_key(key_id: &str) -> Result<Option<SigningKey>> {
+    let output = gpg(&["--list-secret-keys", "--with-colons", key_id])?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    Ok(parse_signing_key(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// The inserted smartcard, or `None` when there is no card (or no reader).
+pub fn card_status() -> Result<Option<CardStatus>> {
+    let output = gpg(&["--card-status", "--with-colons"])?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    Ok(parse_card_status(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Why the profile can't sign right now because its key lives on a smartcard, or `None` when
+/// it can (or doesn't sign with a card). gpg not being installed is an error.
+pub fn card_problem(profile: &Profile) -> Result<Option<String>> {
+    let Some(key_id) = gpg_signing_key(profile) else {
+        return Ok(None);
+    };
+    let Some(key) = signing_key(key_id)? else {
+        return Ok(Some(format!(
+            "gpg has no secret key for {}. If it lives on a smartcard, run 'gpg --card-status' \
+             with the card inserted to create the key stubs.",
+            key_id
+        )));
+    };
+    let expected = match &key.location {
+        KeyLocation::Local => return Ok(None),
+        KeyLocation::Card(serial) => format!("the smartcard {}", display_serial(serial)),
+        KeyLocation::Stub => "an offline key or smartcard".to_string(),
+    };
+    match card_status()? {
+        Some(card) if card.holds(&key.fingerprint) => Ok(None),
+        Some(card) => Ok(Some(format!(
+            "Signing key {} is on {}, but the inserted card ({}) doesn't hold it.",
+            key_id,
+            expected,
+            display_serial(&card.serial)
+        ))),
+        None => Ok(Some(format!(
+            "Signing key {} is on {}, which isn't inserted.",
+            key_id, expected
+        ))),
+    }
+}
+
+/// Makes a throwaway signature with `key_id`, as git would. On a card this asks for the PIN
+/// (and a touch, when the card is set up for it).
+pub fn test_signature(key_id: &str) -> Result<()> {
+    let mut child = Command::new("gpg")
+        .args(["--batch", "--yes", "--local-user", key_id, "--clearsign"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to execute 'gpg'. Is GnuPG installed?")?;
+    // Closing stdin right away ends the message
+    drop(child.stdin.take());
+    let output = child
+        .wait_with_output()
+        .context("Failed to wait for 'gpg --clearsign'")?;
+    if !output.status.success() {
+        bail!(
+            "gpg could not sign with {}: {}",
+            key_id,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// Card serial as gpg prints it (`0006 12345678`) when `serial` is a full OpenPGP card
+/// application ID.
+pub fn display_serial(serial: &str) -> String {
+    if serial.len() == 32 && serial.to_uppercase().starts_with("D27600012401") {
+        format!("{} {}", &serial[16..20], &serial[20..28])
+    } else {
+        serial.to_string()
+    }
+}
+
+fn gpg(args: &[&str]) -> Result<std::process::Output> {
+    Command::new("gpg")
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .context("Failed to execute 'gpg'. Is GnuPG installed?")
+}
+
+/// Picks the key gpg signs with from `--list-secret-keys --with-colons` output: the last
+/// signing-capable subkey, else the primary key. Field 15 of a `sec`/`ssb` record is `+` for a
+/// local secret key, `#` for a stub, and otherwise the serial of the card holding it.
+fn parse_signing_key(output: &str) -> Option<SigningKey> {
+    let mut primary: Option<SigningKey> = None;
+    let mut subkey: Option<SigningKey> = None;
+    let mut seen_primary = false;
+    // Which record the next `fpr` line belongs to, and whether it can sign
+    let mut pending: Option<(bool, bool, KeyLocation)> = None;
+    for line in output.lines() {
+        let fields: Vec<&str> = line.split(':').collect();
+        match fields.first().copied() {
+            Some(kind @ ("sec" | "ssb")) => {
+                if kind == "sec" {
+                    if seen_primary {
+                        // A second key matched; gpg uses the first
+                        break;
+                    }
+                    seen_primary = true;
+                }
+                // Expired and revoked keys can't sign
+                let usable = !matches!(fields.get(1).copied(), Some("e" | "r"));
+                let can_sign = usable && fields.get(11).is_some_and(|caps| caps.contains('s'));
+                let location = match fields.get(14).copied().unwrap_or_default() {
+                    "" | "+" => KeyLocation::Local,
+                    "#" => KeyLocation::Stub,
+                    serial => KeyLocation::Card(serial.to_string()),
+                };
+                pending = Some((kind == "sec", can_sign, location));
+            }
+            Some("fpr") => {
+                let Some((is_primary, can_sign, location)) = pending.take() else {
+                    continue;
+                };
+                let key = SigningKey {
+                    fingerprint: fields.get(9).copied().unwrap_or_default().to_string(),
+                    location,
+                };
+                match (is_primary, can_sign) {
+                    (true, true) => primary = Some(key),
+                    (false, true) => subkey = Some(key),
+                    (_, false) => {}
+                }
+            }
+            _ => {}
+        }
+    }
+    subkey.or(primary)
+}
+
+fn parse_card_status(output: &str) -> Option<CardStatus> {
+    let mut status = CardStatus::default();
+    for line in output.lines() {
+        let fields: Vec<&str> = line.split(':').collect();
+        match fields.as_slice() {
+            ["serial", serial, ..] => status.serial = serial.to_string(),
+            ["vendor", _, name, ..] => status.vendor = Some(name.to_string()),
+            ["fpr", fingerprints @ ..] => {
+                status.fingerprints = fingerprints
+                    .iter()
+                    .filter(|fpr| !fpr.is_empty())
+                    .map(|fpr| fpr.to_string())
+                    .collect()
+            }
+            _ => {}
+        }
+    }
+    (!status.serial.is_empty()).then_some(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CARD_KEY: &str = "\
+sec:u:255:22:1111222233334444:1700000000:::u:::cC:::#:::ed25519:::0:
+fpr:::::::::AAAA1111222233334444AAAA1111222233334444:
+grp:::::::::0000:
+uid:u::::1700000000::HASH::John Doe <john@company.com>::::::::::0:
+ssb:u:255:22:5555666677778888:1700000000::::::s:::D2760001240103040006123456780000:::ed25519::
+fpr:::::::::BBBB5555666677778888BBBB5555666677778888:
+ssb:u:255:18:9999000011112222:1700000000::::::e:::D2760001240103040006123456780000:::cv25519::
+fpr:::::::::CCCC9999000011112222CCCC9999000011112222:
+";
+
+    #[test]
+    fn test_parse_signing_key() {
+        assert_eq!(
+            parse_signing_key(CARD_KEY),
+            Some(SigningKey {
+                fingerprint: "BBBB5555666677778888BBBB5555666677778888".to_string(),
+                location: KeyLocation::Card("D2760001240103040006123456780000".to_string()),
+            })
+        );
+
+        let local = "\
+sec:u:255:22:1111222233334444:1700000000:::u:::scESC:::+:::ed25519:::0:
+fpr:::::::::AAAA1111222233334444AAAA1111222233334444:
+ssb:u:255:18:9999000011112222:1700000000::::::e:::+:::cv25519::
+fpr:::::::::CCCC9999000011112222CCCC9999000011112222:
+";
+        assert_eq!(
+            parse_signing_key(local),
+            Some(SigningKey {
+                fingerprint: "AAAA1111222233334444AAAA1111222233334444".to_string(),
+                location: KeyLocation::Local,
+            })
+        );
+        assert_eq!(parse_signing_key(""), None);
+    }
+
+    #[test]
+    fn test_parse_card_status() {
+        let output = "\
+Reader:1050:0407:X:0:
+version:0304:
+vendor:0006:Yubico:
+serial:12345678:
+fpr:BBBB5555666677778888BBBB5555666677778888:CCCC9999000011112222CCCC9999000011112222::
+";
+        let status = parse_card_status(output).unwrap();
+        assert_eq!(status.serial, "12345678");
+        assert_eq!(status.vendor.as_deref(), Some("Yubico"));
+        assert!(status.holds("bbbb5555666677778888bbbb5555666677778888"));
+        assert!(!status.holds("AAAA1111222233334444AAAA1111222233334444"));
+        assert_eq!(parse_card_status("Reader:::\n"), None);
+        assert_eq!(
+            display_serial("D2760001240103040006123456780000"),
+            "0006 12345678"
+        );
+    }
+}
@@ -0,0 +1,210 @@
+// src/http/mod.rs
+// HTTP client for provider APIs: proxy, extra CA certificates, retries and rate limits
+
+use anyhow::{bail, Context, Result};
+use rustls_pki_types::pem::PemObject;
+use rustls_pki_types::CertificateDer;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::config::Settings;
+use crate::git::{get_git_config, GitConfigScope};
+use crate::utils::expand_home;
+
+/// Longest wait for a rate limit to reset before the request fails instead.
+const MAX_RATE_LIMIT_WAIT_SECS: u64 = 10;
+/// Delay before the first retry of a failed request, doubled for each further retry.
+const RETRY_BASE_DELAY_MS: u64 = 500;
+
+/// Client shared by every command that talks to a provider.
+pub struct Client {
+    agent: ureq::Agent,
+    retries: u32,
+}
+
+impl Client {
+    pub fn new(settings: &Settings) -> Result<Self> {
+        let mut builder = ureq::AgentBuilder::new()
+            .timeout(Duration::from_secs(settings.http_timeout_secs))
+            .user_agent(&format!("gitp/{}", env!("CARGO_PKG_VERSION")))
+            .try_proxy_from_env(true);
+        if let Some(proxy) = setting_or_git(&settings.http_proxy, "http.proxy")? {
+            let proxy =
+                ureq::Proxy::new(&proxy).with_context(|| format!("Invalid proxy '{}'", proxy))?;
+            builder = builder.proxy(proxy);
+        }
+        if let Some(ca_file) = setting_or_git(&settings.http_ca_file, "http.sslCAInfo")? {
+            builder = builder.tls_config(Arc::new(tls_config(&expand_home(&ca_file))?));
+        }
+        Ok(Client {
+            agent: builder.build(),
+            retries: settings.http_retries,
+        })
+    }
+
+    /// GETs `url`, retrying network errors, 5xx answers and rate limits that reset soon.
+    /// Other error statuses fail at once, with a message saying what the status means.
+    pub fn get(&self, url: &str, headers: &[(&str, &str)]) -> Result<ureq::Response> {
+        let mut attempt = 0;
+        loop {
+            let mut request = self.agent.get(url);
+            for (name, value) in headers {
+                request = request.set(name, value);
+            }
+            let can_retry = attempt < self.retries;
+            let delay = match request.call() {
+                Ok(response) => return Ok(response),
+                Err(ureq::Error::Status(code, response)) => {
+                    let header = |name: &str| response.header(name).map(str::to_string);
+                    match rate_limit_wait(code, header, now_secs()) {
+                        Some(wait) if can_retry && wait <= MAX_RATE_LIMIT_WAIT_SECS => {
+                            Duration::from_secs(wait)
+                        }
+                        Some(wait) => bail!(
+                            "The provider's rate limit was reached at {}. Try again in {}.",
+                            url,
+                            describe_wait(wait)
+                        ),
+                        None if code == 401 => bail!(
+                            "The provider rejected the token (401 Unauthorized) at {}",
+                            url
+                        ),
+                        None if code >= 500 && can_retry => retry_delay(attempt),
+                        None => bail!(
+                            "Request to {} failed with HTTP {} {}",
+                            url,
+                            code,
+                            response.status_text()
+                        ),
+                    }
+                }
+                Err(ureq::Error::Transport(transport)) => {
+                    let transient = matches!(
+                        transport.kind(),
+                        ureq::ErrorKind::Dns
+                            | ureq::ErrorKind::ConnectionFailed
+                            | ureq::ErrorKind::Io
+                    );
+                    if !(transient && can_retry) {
+                        return Err(transport).with_context(|| format!("Failed to reach {}", url));
+                    }
+                    retry_delay(attempt)
+                }
+            };
+            std::thread::sleep(delay);
+            attempt += 1;
+        }
+    }
+}
+
+/// gitp's own setting, or else git's global value for `git_key`.
+fn setting_or_git(setting: &Option<String>, git_key: &str) -> Result<Option<String>> {
+    match setting {
+        Some(value) => Ok(Some(value.clone())),
+        None => Ok(get_git_config(git_key, GitConfigScope::Global)?.filter(|v| !v.is_empty())),
+    }
+}
+
+/// TLS settings trusting the certificates in `ca_file` as well as the built-in roots.
+fn tls_config(ca_file: &Path) -> Result<rustls::ClientConfig> {
+    let certs = CertificateDer::pem_file_iter(ca_file)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .with_context(|| format!("Failed to read CA certificates from {}", ca_file.display()))?;
+    let mut roots = rustls::RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+    let (added, _) = roots.add_parsable_certificates(certs);
+    if added == 0 {
+        bail!("{} holds no usable CA certificate.", ca_file.display());
+    }
+    // Same crypto provider and protocol versions as ureq's default configuration
+    let config = rustls::ClientConfig::builder_with_provider(
+        rustls::crypto::ring::default_provider().into(),
+    )
+    .with_protocol_versions(&[&rustls::version::TLS12, &rustls::version::TLS13])
+    .context("Failed to set up TLS.")?
+    .with_root_certificates(roots)
+    .with_no_client_auth();
+    Ok(config)
+}
+
+/// Seconds until a rate-limited request may be retried, or `None` when the answer isn't a rate
+/// limit. GitHub answers 403 or 429 with `x-ratelimit-remaining: 0` and the reset time (or
+/// Retry-After for its secondary limits); GitLab answers 429 with Retry-After.
+fn rate_limit_wait(status: u16, header: impl Fn(&str) -> Option<String>, now: u64) -> Option<u64> {
+    if status != 403 && status != 429 {
+        return None;
+    }
+    if let Some(seconds) = header("retry-after").and_then(|v| v.trim().parse().ok()) {
+        return Some(seconds);
+    }
+    let exhausted = ["x-ratelimit-remaining", "ratelimit-remaining"]
+        .iter()
+        .any(|name| header(name).is_some_and(|v| v.trim() == "0"));
+    let reset = ["x-ratelimit-reset", "ratelimit-reset"]
+        .iter()
+        .find_map(|name| header(name)?.trim().parse::<u64>().ok());
+    match (exhausted, reset) {
+        (true, Some(reset)) => Some(reset.saturating_sub(now)),
+        // Without a reset time, a minute is what providers usually mean
+        (true, None) => Some(60),
+        (false, _) if status == 429 => Some(60),
+        (false, _) => None,
+    }
+}
+
+fn describe_wait(seconds: u64) -> String {
+    match seconds {
+        0..=90 => format!("{} seconds", seconds),
+        _ => format!("{} minutes", seconds.div_ceil(60)),
+    }
+}
+
+fn retry_delay(attempt: u32) -> Duration {
+    Duration::from_millis(RETRY_BASE_DELAY_MS << attempt.min(5))
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GITHUB: &[(&str, &str)] = &[
+        ("x-ratelimit-remaining", "0"),
+        ("x-ratelimit-reset", "1700000600"),
+    ];
+
+    fn headers(pairs: &'static [(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        move |name| {
+            pairs
+                .iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+        }
+    }
+
+    #[test]
+    fn test_rate_limit_wait() {
+        let now = 1_700_000_000;
+        assert_eq!(rate_limit_wait(403, headers(GITHUB), now), Some(600));
+        assert_eq!(
+            rate_limit_wait(429, headers(&[("retry-after", "30")]), now),
+            Some(30)
+        );
+        assert_eq!(rate_limit_wait(429, headers(&[]), now), Some(60));
+        // A plain 403 is a permission problem, not a rate limit
+        assert_eq!(
+            rate_limit_wait(403, headers(&[("x-ratelimit-remaining", "41")]), now),
+            None
+        );
+        assert_eq!(rate_limit_wait(500, headers(GITHUB), now), None);
+        assert_eq!(describe_wait(600), "10 minutes");
+    }
+}
@@ -1,34 +1,75 @@
 use anyhow::Result;
-use clap::Parser;
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser};
 use colored::Colorize;
 
 mod cli;
 mod commands;
 mod config;
 mod credentials;
+mod extras;
 mod git;
+mod gpg;
+#[cfg(feature = "http")]
+mod http;
+mod providers;
 mod ssh;
 mod utils;
 
 use cli::{Cli, Commands};
+use utils::output::Paint;
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
+    if cli.timings {
+        utils::timing::enable();
+    }
 
     // Set up colored output based on environment
     colored::control::set_override(cli.color);
+    utils::progress::set_quiet(cli.quiet);
+    utils::time::set_utc(cli.utc);
+    utils::mask::set_reveal(cli.reveal);
+    // Plumbing that git and ssh run doesn't print for people, so it skips the config load
+    let (output_style, theme) = if cli.version || cli.command.as_ref().is_some_and(is_plumbing) {
+        Default::default()
+    } else {
+        config::Config::load_cached()
+            .map(|config| (config.settings.output, config.settings.theme))
+            .unwrap_or_default()
+    };
+    utils::output::init(cli.ascii, output_style, theme);
+
+    if cli.version {
+        commands::version::execute(cli.verbose);
+        return Ok(());
+    }
 
-    match run(cli) {
+    let result = run(cli);
+    utils::timing::report();
+    match result {
         Ok(_) => Ok(()),
         Err(e) => {
-            eprintln!("{} {}", "Error:".red().bold(), e);
+            eprintln!("{} {:#}", "Error:".error().bold(), e);
             std::process::exit(1);
         }
     }
 }
 
 fn run(cli: Cli) -> Result<()> {
-    match cli.command {
+    let quiet = cli.quiet;
+    let Some(command) = cli.command else {
+        Cli::command()
+            .error(ErrorKind::MissingSubcommand, "a subcommand is required")
+            .exit();
+    };
+    if let Some(action) = git_action(&command) {
+        git::require_git(action)?;
+    }
+    if let Some(action) = config_change(&command) {
+        config::storage::require_writable(action)?;
+    }
+    match command {
         Commands::New {
             name,
             user_name,
@@ -41,6 +82,9 @@ fn run(cli: Cli) -> Result<()> {
             https_token,
             https_store_in_keychain, // Destructuring updated
             ssh_key_host,
+            autocrlf,
+            eol,
+            defaults,
         } => {
             commands::new::execute(
                 name,
@@ -54,23 +98,61 @@ fn run(cli: Cli) -> Result<()> {
                 https_token,
                 https_store_in_keychain, // Function call updated
                 ssh_key_host,
+                autocrlf,
+                eol,
+                defaults,
             )?;
         }
-        Commands::List { verbose } => {
-            commands::list::execute(verbose)?;
+        Commands::List { verbose, format } => {
+            commands::list::execute(verbose, format)?;
+        }
+        Commands::Use {
+            name: Some(name),
+            session: true,
+            shell,
+            ..
+        } => {
+            commands::session::execute_use(name, shell)?;
         }
         Commands::Use {
             name,
             local,
             global,
+            auto,
+            no_validate,
+            ..
+        } => {
+            commands::use_profile::execute(name, local || auto, global, no_validate)?;
+        }
+        Commands::Current {
+            match_profiles,
+            format,
         } => {
-            commands::use_profile::execute(name, local, global)?;
+            commands::current::execute(match_profiles, format)?;
         }
-        Commands::Current => {
-            commands::current::execute()?;
+        Commands::Status => {
+            commands::status::execute()?;
+        }
+        Commands::Whoami {
+            profile_name,
+            refresh,
+        } => {
+            commands::whoami::execute(profile_name, refresh)?;
         }
-        Commands::Show { name } => {
-            commands::show::execute(name)?;
+        Commands::Resolve { remote_url } => {
+            commands::resolve::execute(remote_url)?;
+        }
+        Commands::Credentials { command } => {
+            commands::credentials::execute(command)?;
+        }
+        Commands::CredentialHelper { profile, operation } => {
+            commands::credential_helper::execute(Some(profile), operation)?;
+        }
+        Commands::SshMatch { profile } => {
+            std::process::exit(if commands::ssh_match::execute(profile) { 0 } else { 1 });
+        }
+        Commands::Show { names, format } => {
+            commands::show::execute(names, format)?;
         }
         Commands::Edit {
             name,
@@ -85,6 +167,16 @@ fn run(cli: Cli) -> Result<()> {
             https_store_in_keychain,  // Updated field
             https_remove_credentials, // Updated field
             ssh_key_host,
+            autocrlf,
+            eol,
+            set_config,
+            unset_config,
+            ssh_managed,
+            ssh_match_dir,
+            add_ssh_host,
+            remove_ssh_host,
+            notes,
+            no_validate,
         } => {
             commands::edit::execute(
                 name,
@@ -99,10 +191,20 @@ fn run(cli: Cli) -> Result<()> {
                 https_store_in_keychain,  // Pass updated field
                 https_remove_credentials, // Pass updated field
                 ssh_key_host,
+                autocrlf,
+                eol,
+                set_config,
+                unset_config,
+                ssh_managed,
+                ssh_match_dir,
+                add_ssh_host,
+                remove_ssh_host,
+                notes,
+                no_validate,
             )?;
         }
-        Commands::Remove { name, force } => {
-            commands::remove::execute(name, force)?;
+        Commands::Remove { names, force } => {
+            commands::remove::execute(names, force)?;
         }
         Commands::Rename { old_name, new_name } => {
             commands::rename::execute(old_name, new_name)?;
@@ -110,17 +212,180 @@ fn run(cli: Cli) -> Result<()> {
         Commands::SshKey { command } => {
             commands::ssh_key::execute(command)?;
         }
-        Commands::Export { name, output_path } => {
-            commands::export::execute(name, output_path)?;
+        Commands::Ssh { command } => {
+            commands::ssh::execute(command)?;
+        }
+        Commands::Export {
+            name,
+            output_path,
+            format,
+            encrypt_secrets,
+            effective,
+        } => {
+            commands::export::execute(name, output_path, format, encrypt_secrets, effective)?;
         }
         Commands::Import {
             input_path,
             profile_name,
             force,
+            from_credential_store,
+        } => match from_credential_store {
+            Some(path) => commands::import::execute_from_credential_store(path)?,
+            None => commands::import::execute(input_path, profile_name, force)?,
+        },
+        Commands::Remotes { command } => {
+            commands::remotes::execute(command)?;
+        }
+        Commands::Clone {
+            url,
+            dir,
+            profile,
+            git_args,
         } => {
-            commands::import::execute(input_path, profile_name, force)?;
+            commands::clone::execute(&url, dir, &profile, &git_args)?;
+        }
+        Commands::Url { command } => {
+            commands::url::execute(command)?;
+        }
+        Commands::Rules { command } => {
+            commands::rules::execute(command)?;
+        }
+        Commands::Paths => {
+            commands::paths::execute()?;
+        }
+        Commands::Provider { host } => {
+            commands::provider::execute(&host)?;
+        }
+        Commands::Changelog { profile, limit } => {
+            commands::changelog::execute(profile, limit)?;
+        }
+        Commands::Doctor { convert_includes } => {
+            commands::doctor::execute(convert_includes)?;
+        }
+        Commands::Gpg { command } => {
+            commands::gpg::execute(command)?;
+        }
+        Commands::Report { repo } => {
+            commands::report::execute(repo)?;
+        }
+        Commands::Check { command } => {
+            let status = commands::check::execute(command, quiet);
+            // Exits without returning to main, which reports otherwise
+            utils::timing::report();
+            std::process::exit(status);
+        }
+        Commands::Mailmap { command } => {
+            commands::mailmap::execute(command)?;
+        }
+        Commands::Sendemail { command } => {
+            commands::sendemail::execute(command)?;
+        }
+        Commands::DebugBundle { output_path } => {
+            commands::debug_bundle::execute(output_path)?;
+        }
+        Commands::Split => {
+            commands::split::execute()?;
+        }
+        Commands::Bootstrap { name, .. } => {
+            commands::bootstrap::execute(name)?;
+        }
+        Commands::Ci { command } => {
+            commands::ci::execute(command)?;
+        }
+        Commands::Guard { command } => {
+            commands::guard::execute(command)?;
+        }
+        Commands::ShellInit { shell } => {
+            commands::session::execute_init(shell)?;
+        }
+        Commands::ShellAliases { shell } => {
+            commands::shell_aliases::execute(shell)?;
+        }
+        Commands::Badge { format, path } => {
+            commands::badge::execute(format, path)?;
+        }
+        Commands::Storage { command } => {
+            commands::storage::execute(command)?;
         }
     }
 
     Ok(())
 }
+
+/// What a command that changes the gitp configuration does, for the error when it is
+/// read-only. `use` and `clone` aren't here: they still apply git config, only without
+/// recording the switch.
+fn config_change(command: &Commands) -> Option<&'static str> {
+    use cli::{
+        CredentialsCommands as Creds, RulesCommands, SendemailCommands, SshCommands,
+        SshKeyCommands,
+    };
+    match command {
+        Commands::New { .. } => Some("Creating a profile"),
+        Commands::Edit { .. } => Some("Editing a profile"),
+        Commands::Remove { .. } => Some("Removing a profile"),
+        Commands::Rename { .. } => Some("Renaming a profile"),
+        Commands::Whoami { refresh: true, .. } => Some("Refreshing account details"),
+        Commands::Import { .. } => Some("Importing profiles"),
+        Commands::Storage { .. } => Some("Migrating the storage"),
+        Commands::Doctor {
+            convert_includes: true,
+        } => Some("Converting includeIf rules"),
+        Commands::Rules { command } if !matches!(command, RulesCommands::List) => {
+            Some("Managing directory rules")
+        }
+        Commands::Credentials { command } => match command {
+            Creds::Show { .. } | Creds::Flush { .. } => None,
+            Creds::Get | Creds::Store | Creds::Erase => None,
+            Creds::Migrate { dry_run: true, .. } | Creds::Export { .. } => None,
+            _ => Some("Changing HTTPS credentials"),
+        },
+        Commands::SshKey { command } => match command {
+            SshKeyCommands::Show { .. }
+            | SshKeyCommands::ShowHost { .. }
+            | SshKeyCommands::Scan { .. } => None,
+            _ => Some("Changing a profile's SSH key"),
+        },
+        Commands::Ssh {
+            command: SshCommands::Control { .. },
+        } => Some("Changing SSH connection sharing"),
+        Commands::Sendemail {
+            command: SendemailCommands::Set { .. },
+        } => Some("Changing send-email settings"),
+        _ => None,
+    }
+}
+
+/// What a command that can't work without git does, for the error when git is missing. The
+/// other commands at most read git config, and carry on without git.
+/// Hidden commands git and ssh invoke: the credential helpers and the `Match exec` check.
+fn is_plumbing(command: &Commands) -> bool {
+    use cli::CredentialsCommands as Creds;
+    matches!(
+        command,
+        Commands::CredentialHelper { .. }
+            | Commands::SshMatch { .. }
+            | Commands::Credentials {
+                command: Creds::Get | Creds::Store | Creds::Erase,
+            }
+    )
+}
+
+fn git_action(command: &Commands) -> Option<&'static str> {
+    use cli::CredentialsCommands as Creds;
+    match command {
+        Commands::Doctor {
+            convert_includes: true,
+        } => Some("Converting includeIf rules"),
+        Commands::Resolve { .. } => Some("Resolving a remote"),
+        Commands::Clone { .. } => Some("Cloning a repository"),
+        Commands::Rules { .. } => Some("Managing directory rules"),
+        Commands::Report { .. } => Some("Reporting on a repository's authors"),
+        Commands::Mailmap { .. } => Some("Writing a .mailmap"),
+        Commands::Guard { .. } => Some("Installing git hooks"),
+        Commands::Credentials {
+            command: Creds::Import { .. } | Creds::Export { .. },
+        } => Some("Exchanging tokens with a credential helper"),
+        _ => None,
+    }
+}
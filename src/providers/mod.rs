@@ -0,0 +1,418 @@
+// src/providers/mod.rs
+// What gitp knows about Git hosting providers: SSH endpoints, APIs, token headers and private
+// commit emails, with overrides for self-hosted instances in providers.toml
+
+use anyhow::{Context, Result};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use crate::config::storage::get_config_dir;
+use crate::config::{normalize_host, same_host};
+
+const OVERRIDES_FILE_NAME: &str = "providers.toml";
+
+/// Login user for SSH when nothing else is known
+pub const DEFAULT_SSH_USER: &str = "git";
+
+/// The software a host runs, which decides its API and token conventions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    GitHub,
+    GitLab,
+    /// Gitea and Forgejo (Codeberg)
+    Gitea,
+    Bitbucket,
+    #[serde(rename = "azure")]
+    AzureDevOps,
+}
+
+impl ProviderKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProviderKind::GitHub => "GitHub",
+            ProviderKind::GitLab => "GitLab",
+            ProviderKind::Gitea => "Gitea",
+            ProviderKind::Bitbucket => "Bitbucket",
+            ProviderKind::AzureDevOps => "Azure DevOps",
+        }
+    }
+
+    /// Whether a token granted `granted` has what `needed` allows, by the provider's scope
+    /// hierarchy: GitHub's `repo` includes `public_repo` and `admin:x` includes `write:x` and
+    /// `read:x`, GitLab's `api` includes the repository scopes, Gitea's `all` includes
+    /// everything. Elsewhere only the same scope does.
+    pub fn scope_covers(&self, granted: &str, needed: &str) -> bool {
+        if granted == needed {
+            return true;
+        }
+        // Levels of `<level>:<resource>` (GitHub, Gitea) or `<level>_<resource>` (GitLab)
+        let level_covers = |separator: char| {
+            let rank = |level: &str| ["read", "write", "admin"].iter().position(|l| *l == level);
+            match (granted.split_once(separator), needed.split_once(separator)) {
+                (Some((have, resource)), Some((want, wanted))) if resource == wanted => rank(have)
+                    .zip(rank(want))
+                    .is_some_and(|(have, want)| have >= want),
+                _ => false,
+            }
+        };
+        match self {
+            ProviderKind::GitHub => {
+                level_covers(':')
+                    || match granted {
+                        "repo" => matches!(
+                            needed,
+                            "repo:status"
+                                | "repo_deployment"
+                                | "public_repo"
+                                | "repo:invite"
+                                | "security_events"
+                        ),
+                        "user" => matches!(needed, "read:user" | "user:email" | "user:follow"),
+                        "project" => needed == "read:project",
+                        _ => false,
+                    }
+            }
+            ProviderKind::GitLab => {
+                level_covers('_')
+                    || (granted == "api"
+                        && matches!(
+                            needed,
+                            "read_api"
+                                | "read_user"
+                                | "read_repository"
+                                | "write_repository"
+                                | "read_registry"
+                                | "write_registry"
+                        ))
+            }
+            ProviderKind::Gitea => granted == "all" || level_covers(':'),
+            ProviderKind::Bitbucket | ProviderKind::AzureDevOps => false,
+        }
+    }
+
+    /// The scopes of `needed` that none of `granted` covers.
+    pub fn missing_scopes<'n>(&self, granted: &[String], needed: &'n [String]) -> Vec<&'n str> {
+        needed
+            .iter()
+            .filter(|n| !granted.iter().any(|g| self.scope_covers(g, n)))
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+/// Facts about one provider host.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Provider {
+    pub kind: ProviderKind,
+    /// Name shown to the user, e.g. `Codeberg`
+    pub name: String,
+    /// Web and HTTPS host
+    pub host: String,
+    /// Host SSH connects to, when it differs from `host` (`ssh.dev.azure.com`)
+    pub ssh_host: String,
+    pub ssh_port: u16,
+    pub ssh_user: String,
+    /// REST API base URL, without a trailing slash
+    pub api_base: Option<String>,
+    /// URL to POST a public SSH key to; `{user}` stands for the account name
+    pub key_upload_url: Option<String>,
+    /// Form of the private commit email the provider gives accounts
+    pub noreply_email: Option<String>,
+}
+
+impl Provider {
+    /// Defaults for a self-hosted instance of `kind` at `host`.
+    fn self_hosted(kind: ProviderKind, host: &str) -> Self {
+        let api_base = match kind {
+            ProviderKind::GitHub => format!("https://{}/api/v3", host),
+            ProviderKind::GitLab => format!("https://{}/api/v4", host),
+            ProviderKind::Gitea => format!("https://{}/api/v1", host),
+            ProviderKind::Bitbucket => format!("https://{}/rest/api/1.0", host),
+            ProviderKind::AzureDevOps => format!("https://{}", host),
+        };
+        let key_upload_url = match kind {
+            ProviderKind::GitHub | ProviderKind::GitLab | ProviderKind::Gitea => {
+                Some(format!("{}/user/keys", api_base))
+            }
+            ProviderKind::Bitbucket => Some(format!("https://{}/rest/ssh/1.0/keys", host)),
+            ProviderKind::AzureDevOps => None,
+        };
+        Self {
+            kind,
+            name: kind.name().to_string(),
+            host: host.to_string(),
+            ssh_host: host.to_string(),
+            // Bitbucket Data Center serves SSH on its own port
+            ssh_port: if kind == ProviderKind::Bitbucket {
+                7999
+            } else {
+                22
+            },
+            ssh_user: DEFAULT_SSH_USER.to_string(),
+            api_base: Some(api_base),
+            key_upload_url,
+            noreply_email: None,
+        }
+    }
+
+    /// The header that authenticates an API request with `token`.
+    pub fn auth_header(&self, token: &str) -> (&'static str, String) {
+        match self.kind {
+            ProviderKind::GitHub | ProviderKind::Gitea => {
+                ("Authorization", format!("token {}", token))
+            }
+            ProviderKind::GitLab => ("PRIVATE-TOKEN", token.to_string()),
+            ProviderKind::Bitbucket => ("Authorization", format!("Bearer {}", token)),
+            // Personal access tokens go in as the password of Basic auth with no user
+            ProviderKind::AzureDevOps => (
+                "Authorization",
+                format!(
+                    "Basic {}",
+                    base64::engine::general_purpose::STANDARD.encode(format!(":{}", token))
+                ),
+            ),
+        }
+    }
+
+    /// Token scopes of which at least one is needed to push over HTTPS, if known.
+    pub fn repo_scopes(&self) -> Option<&'static [&'static str]> {
+        match self.kind {
+            ProviderKind::GitHub => Some(&["repo"]),
+            ProviderKind::GitLab => Some(&["write_repository", "api"]),
+            ProviderKind::Gitea => Some(&["write:repository"]),
+            ProviderKind::Bitbucket | ProviderKind::AzureDevOps => None,
+        }
+    }
+
+    /// Where to upload a public key for account `user`.
+    pub fn key_upload_url(&self, user: &str) -> Option<String> {
+        self.key_upload_url
+            .as_ref()
+            .map(|url| url.replace("{user}", user))
+    }
+
+    /// Whether `host` names this provider's web or SSH host.
+    fn serves(&self, host: &str) -> bool {
+        same_host(&self.host, host) || same_host(&self.ssh_host, host)
+    }
+}
+
+/// The hosted services gitp knows without configuration.
+fn builtin() -> Vec<Provider> {
+    let github = Provider {
+        api_base: Some("https://api.github.com".to_string()),
+        key_upload_url: Some("https://api.github.com/user/keys".to_string()),
+        noreply_email: Some("ID+USERNAME@users.noreply.github.com".to_string()),
+        ..Provider::self_hosted(ProviderKind::GitHub, "github.com")
+    };
+    let gitlab = Provider {
+        noreply_email: Some("ID-USERNAME@users.noreply.gitlab.com".to_string()),
+        ..Provider::self_hosted(ProviderKind::GitLab, "gitlab.com")
+    };
+    let codeberg = Provider {
+        name: "Codeberg".to_string(),
+        noreply_email: Some("USERNAME@noreply.codeberg.org".to_string()),
+        ..Provider::self_hosted(ProviderKind::Gitea, "codeberg.org")
+    };
+    let bitbucket = Provider {
+        ssh_port: 22,
+        api_base: Some("https://api.bitbucket.org/2.0".to_string()),
+        key_upload_url: Some("https://api.bitbucket.org/2.0/users/{user}/ssh-keys".to_string()),
+        ..Provider::self_hosted(ProviderKind::Bitbucket, "bitbucket.org")
+    };
+    let azure = Provider {
+        ssh_host: "ssh.dev.azure.com".to_string(),
+        ..Provider::self_hosted(ProviderKind::AzureDevOps, "dev.azure.com")
+    };
+    vec![github, gitlab, codeberg, bitbucket, azure]
+}
+
+/// The kind of a self-hosted instance, guessed from its host name (`github.acme.com`).
+fn guess_kind(host: &str) -> Option<ProviderKind> {
+    [
+        ("github", ProviderKind::GitHub),
+        ("gitlab", ProviderKind::GitLab),
+        ("gitea", ProviderKind::Gitea),
+        ("forgejo", ProviderKind::Gitea),
+        ("bitbucket", ProviderKind::Bitbucket),
+    ]
+    .into_iter()
+    .find(|(word, _)| host.contains(word))
+    .map(|(_, kind)| kind)
+}
+
+/// An entry of providers.toml. Fields left out keep the built-in value, or the default for
+/// the kind.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProviderOverride {
+    pub kind: Option<ProviderKind>,
+    pub name: Option<String>,
+    pub ssh_host: Option<String>,
+    pub ssh_port: Option<u16>,
+    pub ssh_user: Option<String>,
+    pub api_base: Option<String>,
+    pub key_upload_url: Option<String>,
+    pub noreply_email: Option<String>,
+}
+
+/// providers.toml: overrides keyed by host.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Providers {
+    #[serde(default)]
+    pub providers: BTreeMap<String, ProviderOverride>,
+}
+
+impl Providers {
+    /// The provider serving `host`: an override, a built-in service, or a self-hosted instance
+    /// recognized by its name. `None` when the kind can't be told.
+    pub fn find(&self, host: &str) -> Option<Provider> {
+        let host = normalize_host(host);
+        let builtin = builtin().into_iter().find(|p| p.serves(&host));
+        let overridden = self
+            .providers
+            .iter()
+            .find(|(known, _)| same_host(known, &host));
+
+        let Some((known, entry)) = overridden else {
+            return builtin.or_else(|| Some(Provider::self_hosted(guess_kind(&host)?, &host)));
+        };
+        let base = match (builtin, entry.kind) {
+            (Some(builtin), kind) if kind.is_none_or(|kind| kind == builtin.kind) => builtin,
+            (_, kind) => {
+                Provider::self_hosted(kind.or_else(|| guess_kind(&host))?, &normalize_host(known))
+            }
+        };
+        Some(Provider {
+            kind: base.kind,
+            name: entry.name.clone().unwrap_or(base.name),
+            host: base.host,
+            ssh_host: entry.ssh_host.clone().unwrap_or(base.ssh_host),
+            ssh_port: entry.ssh_port.unwrap_or(base.ssh_port),
+            ssh_user: entry.ssh_user.clone().unwrap_or(base.ssh_user),
+            api_base: entry
+                .api_base
+                .as_ref()
+                .map(|url| url.trim_end_matches('/').to_string())
+                .or(base.api_base),
+            key_upload_url: entry.key_upload_url.clone().or(base.key_upload_url),
+            noreply_email: entry.noreply_email.clone().or(base.noreply_email),
+        })
+    }
+
+    /// Reads providers.toml; empty when it doesn't exist.
+    pub fn load() -> Result<Self> {
+        let path = overrides_path()?;
+        match std::fs::read_to_string(&path) {
+            Ok(content) => toml::from_str(&content)
+                .with_context(|| format!("Failed to parse {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        }
+    }
+}
+
+/// Path of the overrides file in the gitp config directory.
+pub fn overrides_path() -> Result<PathBuf> {
+    Ok(get_config_dir()?.join(OVERRIDES_FILE_NAME))
+}
+
+/// The provider serving `host`, with providers.toml read once per run. A broken file is
+/// reported and ignored, so the built-in knowledge still applies.
+pub fn lookup(host: &str) -> Option<Provider> {
+    static PROVIDERS: OnceLock<Providers> = OnceLock::new();
+    PROVIDERS
+        .get_or_init(|| {
+            Providers::load().unwrap_or_else(|e| {
+                eprintln!("Warning: ignoring provider overrides: {:#}", e);
+                Providers::default()
+            })
+        })
+        .find(host)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find() {
+        let none = Providers::default();
+        let api = |p: Option<Provider>| p.and_then(|p| p.api_base);
+        assert_eq!(
+            api(none.find("GitHub.com")).as_deref(),
+            Some("https://api.github.com")
+        );
+        assert_eq!(
+            api(none.find("github.acme.com")).as_deref(),
+            Some("https://github.acme.com/api/v3")
+        );
+        assert_eq!(
+            api(none.find("gitlab.com")).as_deref(),
+            Some("https://gitlab.com/api/v4")
+        );
+        let github = none.find("github.com").unwrap();
+        assert_eq!(github.ssh_user, DEFAULT_SSH_USER);
+        assert!(github.noreply_email.is_some());
+        assert!(none.find("bitbucket.org").unwrap().noreply_email.is_none());
+        assert_eq!(none.find("ssh.dev.azure.com").unwrap().name, "Azure DevOps");
+        assert_eq!(
+            none.find("bitbucket.org")
+                .unwrap()
+                .key_upload_url("octocat"),
+            Some("https://api.bitbucket.org/2.0/users/octocat/ssh-keys".to_string())
+        );
+        assert_eq!(none.find("git.example.com"), None);
+
+        let overrides: Providers = toml::from_str(
+            r#"
+            [providers."git.example.com"]
+            kind = "gitlab"
+            ssh_port = 2222
+
+            [providers."github.com"]
+            ssh_host = "ssh.github.com"
+            ssh_port = 443
+            "#,
+        )
+        .unwrap();
+        let corp = overrides.find("git.example.com").unwrap();
+        assert_eq!(corp.kind, ProviderKind::GitLab);
+        assert_eq!(corp.ssh_port, 2222);
+        assert_eq!(
+            corp.api_base.as_deref(),
+            Some("https://git.example.com/api/v4")
+        );
+        assert_eq!(corp.auth_header("t"), ("PRIVATE-TOKEN", "t".to_string()));
+        let github = overrides.find("github.com").unwrap();
+        assert_eq!(
+            (github.ssh_host.as_str(), github.ssh_port),
+            ("ssh.github.com", 443)
+        );
+        assert!(github.noreply_email.is_some());
+        assert!(toml::from_str::<Providers>("[providers.x]\nport = 1\n").is_err());
+    }
+
+    #[test]
+    fn test_scope_covers() {
+        let github = ProviderKind::GitHub;
+        assert!(github.scope_covers("repo", "public_repo"));
+        assert!(github.scope_covers("admin:public_key", "write:public_key"));
+        assert!(github.scope_covers("write:org", "read:org"));
+        assert!(!github.scope_covers("read:public_key", "write:public_key"));
+        assert!(!github.scope_covers("public_repo", "repo"));
+        assert!(ProviderKind::GitLab.scope_covers("api", "write_repository"));
+        assert!(ProviderKind::GitLab.scope_covers("write_repository", "read_repository"));
+        assert!(!ProviderKind::GitLab.scope_covers("read_api", "api"));
+        assert!(ProviderKind::Gitea.scope_covers("all", "write:repository"));
+        assert!(!ProviderKind::Bitbucket.scope_covers("repository:write", "repository"));
+
+        let granted = ["repo".to_string(), "read:org".to_string()];
+        let needed = ["public_repo".to_string(), "write:org".to_string()];
+        assert_eq!(github.missing_scopes(&granted, &needed), ["write:org"]);
+    }
+}
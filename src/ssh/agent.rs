@@ -0,0 +1,192 @@
+// src/ssh/agent.rs
+
+use anyhow::{bail, Context, Result};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use super::keys::inspect_key;
+
+/// A key loaded in ssh-agent, as listed by `ssh-add -l`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AgentIdentity {
+    pub fingerprint: String,
+    pub comment: String,
+    pub key_type: String,
+}
+
+/// Keys loaded in ssh-agent, in the order the agent offers them, or `None` when no agent is
+/// reachable.
+pub fn agent_identities() -> Result<Option<Vec<AgentIdentity>>> {
+    let output = Command::new("ssh-add")
+        .arg("-l")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .context("Failed to execute 'ssh-add -l'. Is OpenSSH installed?")?;
+
+    // ssh-add exits with 1 when the agent has no keys and 2 when it can't reach an agent
+    match output.status.code() {
+        Some(0) => Ok(Some(parse_fingerprints(&String::from_utf8_lossy(
+            &output.stdout,
+        )))),
+        Some(1) => Ok(Some(Vec::new())),
+        Some(2) => Ok(None),
+        _ => bail!(
+            "'ssh-add -l' failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+    }
+}
+
+/// Fingerprint of the key at `path` (its `.pub` file when there is one, so encrypted keys
+/// don't need a passphrase), or `None` if ssh-keygen can't read it.
+pub fn key_fingerprint(path: &Path) -> Result<Option<String>> {
+    let public = path.with_file_name(format!(
+        "{}.pub",
+        path.file_name().unwrap_or_default().to_string_lossy()
+    ));
+    let path = if public.exists() { &public } else { path };
+    let output = Command::new("ssh-keygen")
+        .arg("-lf")
+        .arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .context("Failed to execute 'ssh-keygen -lf'. Is OpenSSH installed?")?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    Ok(parse_fingerprints(&String::from_utf8_lossy(&output.stdout))
+        .into_iter()
+        .next()
+        .map(|identity| identity.fingerprint))
+}
+
+/// What a key needs before git can use it where nobody can type a passphrase (CI, an IDE's
+/// background fetch).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AgentRequirement {
+    /// The key has no passphrase, or ssh-agent already holds it
+    Ready,
+    /// An agent runs but doesn't hold the key, so ssh asks for the passphrase
+    NotLoaded,
+    /// No agent is reachable, so ssh asks for the passphrase on every connection
+    NoAgent,
+}
+
+/// Whether the key at `path` can be used without a terminal. Keys without a passphrase, and
+/// keys gitp can't read, are `Ready`.
+pub fn agent_requirement(path: &Path) -> Result<AgentRequirement> {
+    if !inspect_key(path).is_ok_and(|info| info.encrypted == Some(true)) {
+        return Ok(AgentRequirement::Ready);
+    }
+    let identities = agent_identities()?;
+    let fingerprint = key_fingerprint(path)?;
+    Ok(requirement(identities.as_deref(), fingerprint.as_deref()))
+}
+
+/// What a passphrase-protected key with `fingerprint` needs, given the agent's keys (`None`
+/// without an agent).
+fn requirement(
+    identities: Option<&[AgentIdentity]>,
+    fingerprint: Option<&str>,
+) -> AgentRequirement {
+    match identities {
+        None => AgentRequirement::NoAgent,
+        Some(identities)
+            if fingerprint.is_some_and(|f| identities.iter().any(|i| i.fingerprint == f)) =>
+        {
+            AgentRequirement::Ready
+        }
+        Some(_) => AgentRequirement::NotLoaded,
+    }
+}
+
+/// Loads the key at `path` into ssh-agent; ssh-add asks for its passphrase on the terminal.
+/// With `use_keychain` (macOS), ssh-add also stores the passphrase in the keychain.
+pub fn add_to_agent(path: &Path, use_keychain: bool) -> Result<()> {
+    let mut command = Command::new("ssh-add");
+    if use_keychain {
+        command.arg("--apple-use-keychain");
+    }
+    let status = command
+        .arg(path)
+        .status()
+        .context("Failed to execute 'ssh-add'. Is OpenSSH installed?")?;
+    if !status.success() {
+        bail!("ssh-add failed ({}).", status);
+    }
+    Ok(())
+}
+
+/// Parses `<bits> <fingerprint> <comment> (<type>)` lines from ssh-add or ssh-keygen.
+fn parse_fingerprints(output: &str) -> Vec<AgentIdentity> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, ' ');
+            let _bits = parts.next()?;
+            let fingerprint = parts.next()?.to_string();
+            let rest = parts.next().unwrap_or_default();
+            let (comment, key_type) = match rest.rsplit_once(" (") {
+                Some((comment, key_type)) => (comment, key_type.trim_end_matches(')')),
+                None => (rest, ""),
+            };
+            Some(AgentIdentity {
+                fingerprint,
+                comment: comment.to_string(),
+                key_type: key_type.to_string(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_requirement() {
+        let loaded = [AgentIdentity {
+            fingerprint: "SHA256:abc".to_string(),
+            comment: "gitp:work@github.com".to_string(),
+            key_type: "ED25519".to_string(),
+        }];
+        assert_eq!(
+            requirement(Some(&loaded), Some("SHA256:abc")),
+            AgentRequirement::Ready
+        );
+        assert_eq!(
+            requirement(Some(&loaded), Some("SHA256:def")),
+            AgentRequirement::NotLoaded
+        );
+        assert_eq!(requirement(Some(&[]), None), AgentRequirement::NotLoaded);
+        assert_eq!(
+            requirement(None, Some("SHA256:abc")),
+            AgentRequirement::NoAgent
+        );
+    }
+
+    #[test]
+    fn test_parse_fingerprints() {
+        let output = "256 SHA256:abc john@work (ED25519)\n\
+                      3072 SHA256:def /home/john/.ssh/id rsa (RSA)\n";
+        assert_eq!(
+            parse_fingerprints(output),
+            vec![
+                AgentIdentity {
+                    fingerprint: "SHA256:abc".to_string(),
+                    comment: "john@work".to_string(),
+                    key_type: "ED25519".to_string(),
+                },
+                AgentIdentity {
+                    fingerprint: "SHA256:def".to_string(),
+                    comment: "/home/john/.ssh/id rsa".to_string(),
+                    key_type: "RSA".to_string(),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,106 @@
+// src/ssh/auth.rs
+// Which account an SSH key authenticates as, from the greeting Git hosts answer `ssh -T` with
+
+use anyhow::{bail, Context, Result};
+use std::process::{Command, Stdio};
+
+/// Connects to `host` the way git would (through `ssh_command` when git is configured with
+/// one) and returns the account the host greets. `None` when the host authenticated the key
+/// but its greeting doesn't name the account.
+pub fn authenticated_login(
+    ssh_command: Option<&str>,
+    user: Option<&str>,
+    host: &str,
+    port: Option<u16>,
+) -> Result<Option<String>> {
+    let mut args: Vec<String> = ["-T", "-o", "BatchMode=yes", "-o", "ConnectTimeout=10"]
+        .iter()
+        .map(|a| a.to_string())
+        .collect();
+    if let Some(port) = port {
+        args.extend(["-p".to_string(), port.to_string()]);
+    }
+    args.push(match user {
+        Some(user) => format!("{}@{}", user, host),
+        None => host.to_string(),
+    });
+
+    let mut command = match ssh_command {
+        // git runs core.sshCommand through the shell, with the arguments appended
+        Some(ssh_command) if cfg!(unix) => {
+            let mut command = Command::new("sh");
+            command
+                .arg("-c")
+                .arg(format!("{} \"$@\"", ssh_command))
+                .arg("ssh");
+            command
+        }
+        _ => Command::new("ssh"),
+    };
+    let output = command
+        .args(&args)
+        .stdin(Stdio::null())
+        .output()
+        .context("Failed to run ssh")?;
+    let text = format!(
+        "{}\n{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    if let Some(login) = parse_greeting(&text) {
+        return Ok(Some(login));
+    }
+    // Hosts end the session with an error status even after authenticating
+    if text.contains("Permission denied") || output.status.code() == Some(255) {
+        bail!(
+            "ssh could not authenticate to {}: {}",
+            host,
+            text.trim().lines().last().unwrap_or_default()
+        );
+    }
+    Ok(None)
+}
+
+/// The account named in a Git host's `ssh -T` greeting (GitHub, GitLab, Bitbucket, Gitea and
+/// Forgejo).
+fn parse_greeting(text: &str) -> Option<String> {
+    let between = |line: &str, start: &str, ends: &[char]| {
+        let rest = &line[line.find(start)? + start.len()..];
+        let login = &rest[..rest.find(ends).unwrap_or(rest.len())];
+        (!login.is_empty()).then(|| login.to_string())
+    };
+    text.lines().find_map(|line| {
+        let line = line.trim();
+        between(line, "Hi there, ", &['!'])
+            .or_else(|| between(line, "Hi ", &['!']))
+            .or_else(|| between(line, "Welcome to GitLab, @", &['!']))
+            .or_else(|| between(line, "logged in as ", &['.', ' ']))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_greeting() {
+        for (text, login) in [
+            (
+                "Hi octocat! You've successfully authenticated, but GitHub does not provide shell access.",
+                Some("octocat"),
+            ),
+            ("Welcome to GitLab, @jdoe!", Some("jdoe")),
+            (
+                "authenticated via ssh key.\n\nYou can use git to connect to Bitbucket. Shell access is disabled.\n\nlogged in as jdoe-acme.\n",
+                Some("jdoe-acme"),
+            ),
+            (
+                "Hi there, jdoe! You've successfully authenticated with the key named laptop, but Forgejo does not provide shell access.",
+                Some("jdoe"),
+            ),
+            ("git@github.com: Permission denied (publickey).", None),
+        ] {
+            assert_eq!(parse_greeting(text).as_deref(), login, "{}", text);
+        }
+    }
+}
@@ -0,0 +1,113 @@
+// src/ssh/effective.rs
+
+use anyhow::{bail, Context, Result};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// What OpenSSH will actually use for a host, after applying ~/.ssh/config.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EffectiveSshConfig {
+    pub hostname: String,
+    pub user: String,
+    pub port: u16,
+    pub identity_files: Vec<String>,
+    pub identities_only: bool,
+    /// Control socket, with its tokens expanded; `None` when connections aren't shared
+    pub control_path: Option<String>,
+}
+
+/// Asks `ssh -G` how it would connect to `host`.
+pub fn effective_ssh_config(
+    host: &str,
+    user: Option<&str>,
+    port: Option<u16>,
+) -> Result<EffectiveSshConfig> {
+    run_ssh_g(host, user, port, None)
+}
+
+/// Like [`effective_ssh_config`], with ssh run in `dir`, for `Match ... exec` entries that
+/// depend on the working directory.
+pub fn effective_ssh_config_in(host: &str, dir: &Path) -> Result<EffectiveSshConfig> {
+    run_ssh_g(host, None, None, Some(dir))
+}
+
+fn run_ssh_g(
+    host: &str,
+    user: Option<&str>,
+    port: Option<u16>,
+    dir: Option<&Path>,
+) -> Result<EffectiveSshConfig> {
+    let mut command = Command::new("ssh");
+    command.arg("-G");
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+    if let Some(user) = user {
+        command.args(["-l", user]);
+    }
+    if let Some(port) = port {
+        command.args(["-p", &port.to_string()]);
+    }
+    let output = command
+        .arg(host)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .context("Failed to execute 'ssh -G'. Is OpenSSH installed?")?;
+
+    if !output.status.success() {
+        bail!(
+            "'ssh -G {}' failed: {}",
+            host,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(parse_ssh_g_output(&String::from_utf8_lossy(&output.stdout)))
+}
+
+fn parse_ssh_g_output(output: &str) -> EffectiveSshConfig {
+    let mut config = EffectiveSshConfig {
+        port: 22,
+        ..Default::default()
+    };
+    for line in output.lines() {
+        let (key, value) = match line.split_once(' ') {
+            Some(pair) => pair,
+            None => continue,
+        };
+        match key {
+            "hostname" => config.hostname = value.to_string(),
+            "user" => config.user = value.to_string(),
+            "port" => config.port = value.parse().unwrap_or(22),
+            "identityfile" => config.identity_files.push(value.to_string()),
+            "identitiesonly" => config.identities_only = value == "yes",
+            "controlpath" if value != "none" => config.control_path = Some(value.to_string()),
+            _ => {}
+        }
+    }
+    config
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ssh_g_output() {
+        let output = "host work-gh\nuser git\nhostname github.com\nport 22\n\
+                      identitiesonly yes\nidentityfile ~/.ssh/id_work\nidentityfile ~/.ssh/id_rsa\n\
+                      controlpath /home/me/.ssh/gitp-work-0a1b2c\n";
+        assert_eq!(
+            parse_ssh_g_output(output),
+            EffectiveSshConfig {
+                hostname: "github.com".to_string(),
+                user: "git".to_string(),
+                port: 22,
+                identity_files: vec!["~/.ssh/id_work".to_string(), "~/.ssh/id_rsa".to_string()],
+                identities_only: true,
+                control_path: Some("/home/me/.ssh/gitp-work-0a1b2c".to_string()),
+            }
+        );
+    }
+}
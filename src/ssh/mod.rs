@@ -1 +1,17 @@
+pub mod agent;
+pub mod auth;
+pub mod effective;
+pub mod keys;
 pub mod ssh_config;
+
+use std::path::Path;
+
+use crate::utils::quote::shell_path;
+
+/// `core.sshCommand` (or `GIT_SSH_COMMAND`) that makes ssh use only `key`.
+pub fn ssh_command(key: &Path) -> String {
+    format!(
+        "ssh -i {} -o IdentitiesOnly=yes",
+        shell_path(&key.to_string_lossy())
+    )
+}
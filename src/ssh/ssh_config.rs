@@ -1,10 +1,15 @@
 // SSH Config Management Logic
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use std::fs::{OpenOptions};
 use std::io::{Write};
 use std::path::{Path, PathBuf};
 
+use crate::config::{is_host_pattern, normalize_host, SshBlockPosition, SshControl};
+use crate::ssh::keys::is_security_key;
+use crate::utils::quote::{ssh_config_path, ssh_config_value};
+use crate::utils::{check_format, expand_home, format_tag, glob_match, timing, FormatCheck, MANAGED_FORMAT_VERSION};
+
 pub(crate) const SSH_CONFIG_HEADER_START: &str = "# BEGIN MANAGED BY GITP";
 pub(crate) const SSH_CONFIG_HEADER_END: &str = "# END MANAGED BY GITP";
 
@@ -24,35 +29,276 @@ pub(crate) fn read_ssh_config(config_path: &Path) -> Result<String> {
         .with_context(|| format!("Failed to read SSH config file from {:?}", config_path))
 }
 
-/// Generates a standard SSH config entry string for a given host and identity file.
-pub(crate) fn generate_ssh_config_entry(
-    host: &str,
-    identity_file_path: &Path,
-    user: Option<&str>,
-) -> String {
-    let user = user.unwrap_or("git");
+/// Format of the gitp-managed block in `content`, or `None` when there is no block.
+pub(crate) fn managed_block_format(content: &str) -> Option<FormatCheck> {
+    let start = content.find(SSH_CONFIG_HEADER_START)? + SSH_CONFIG_HEADER_START.len();
+    Some(check_format(content[start..].lines().next().unwrap_or_default()))
+}
+
+/// A Host (or Match) entry of the managed block.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SshEntry {
+    /// Name remotes use: the alias, or the host itself
+    pub host: String,
+    /// Real host name
+    pub hostname: String,
+    pub identity_file: PathBuf,
+    /// Login user; `git` when unset
+    pub user: Option<String>,
+    /// Profile whose `ssh_match_dirs` the entry is limited to
+    pub match_profile: Option<String>,
+    /// Connection sharing, with the ControlPath resolved for the profile
+    pub control: Option<SshControl>,
+    /// AddKeysToAgent: whether ssh loads the key into the agent once its passphrase is typed
+    pub add_keys_to_agent: Option<String>,
+    /// UseKeychain yes: the passphrase comes from the macOS keychain (only set on macOS)
+    pub use_keychain: bool,
+}
+
+/// Command the `Match ... exec` entry of `profile` runs; it succeeds inside the profile's
+/// directories.
+pub(crate) fn match_exec_command(profile: &str) -> String {
+    if profile.chars().all(|c| c.is_ascii_alphanumeric() || "-_.@+".contains(c)) {
+        format!("gitp ssh-match --profile {}", profile)
+    } else {
+        format!("gitp ssh-match --profile '{}'", profile.replace('\'', r"'\''"))
+    }
+}
+
+/// The text `entry` takes in the managed block: a standard SSH config entry for its host and
+/// identity file. `host` is the name remotes use; it differs from `hostname` when it is an
+/// alias. With a `match_profile`, the entry is a `Match host ... exec` section that only
+/// applies inside the profile's directories. `control` is written as given (see
+/// [`SshControl::for_profile`]). `security_key_provider` is only written for FIDO2 keys.
+pub(crate) fn render_entry(entry: &SshEntry, security_key_provider: Option<&str>) -> String {
+    let (host, hostname) = (entry.host.as_str(), entry.hostname.as_str());
+    let user = entry.user.as_deref().unwrap_or("git");
+    let match_exec = entry.match_profile.as_deref().map(match_exec_command);
     // Ensure the path is absolute and correctly formatted for the SSH config
     // SSH config typically expects absolute paths, especially if `~` is not expanded by SSH itself in all contexts.
     // However, `IdentityFile` does expand `~`, so we can use it if the path starts with `~`.
     // For simplicity and robustness, we'll try to provide an absolute path if not already.
-    let identity_file_str = identity_file_path.to_string_lossy();
+    let identity_file_str = entry.identity_file.to_string_lossy();
 
-    format!(
-        "Host {host}\n    HostName {host}\n    User {user}\n    IdentityFile {identity_file_str}\n    IdentitiesOnly yes\n",
-        host = host,
+    // `*` also spans dots in ssh; the negation keeps a pattern's entry to the hosts gitp's own
+    // matching (and git's) assigns to the profile. ssh connects to the name it was given.
+    let (patterns, hostname) = if is_host_pattern(host) {
+        let domain = normalize_host(host).split_off(2);
+        (vec![format!("*.{}", domain), format!("!*.*.{}", domain)], None)
+    } else {
+        (vec![host.to_string()], Some(hostname))
+    };
+    let mut rendered = match match_exec {
+        // `Match host` sees the name after HostName substitution, hence no HostName here.
+        // Match arguments are split on quotes only, without backslash escapes.
+        Some(command) => format!("Match host {} exec \"{}\"\n", patterns.join(","), command),
+        None => {
+            let mut header = format!("Host {}\n", patterns.join(" "));
+            if let Some(hostname) = hostname {
+                header.push_str(&format!("    HostName {}\n", hostname));
+            }
+            header
+        }
+    };
+    rendered.push_str(&format!(
+        "    User {user}\n    IdentityFile {identity_file_str}\n    IdentitiesOnly yes\n",
         user = user,
-        identity_file_str = identity_file_str
-    )
+        identity_file_str = ssh_config_path(&identity_file_str)
+    ));
+    if let Some(control) = &entry.control {
+        if let Some(master) = control.master {
+            rendered.push_str(&format!("    ControlMaster {}\n", master.as_str()));
+        }
+        if let Some(path) = &control.path {
+            rendered.push_str(&format!("    ControlPath {}\n", ssh_config_value(path)));
+        }
+        if let Some(persist) = &control.persist {
+            rendered.push_str(&format!("    ControlPersist {}\n", persist));
+        }
+    }
+    if let Some(add_keys_to_agent) = &entry.add_keys_to_agent {
+        rendered.push_str(&format!("    AddKeysToAgent {}\n", add_keys_to_agent));
+    }
+    if entry.use_keychain {
+        rendered.push_str("    UseKeychain yes\n");
+    }
+    if let Some(provider) = security_key_provider {
+        if is_security_key(&expand_home(&identity_file_str)) {
+            rendered.push_str(&format!("    SecurityKeyProvider {}\n", ssh_config_value(provider)));
+        }
+    }
+    rendered
+}
+
+/// How an entry compares to what the managed block in `content` holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum EntryStatus {
+    /// The block has exactly this entry
+    InSync,
+    /// The block has an entry with the same header, but other options
+    Outdated,
+    /// The block has no entry for it, or there is no block
+    Missing,
+}
+
+/// Compares `rendered` (from [`render_entry`]) with the managed block in `content`.
+pub(crate) fn entry_status(content: &str, rendered: &str) -> EntryStatus {
+    let Some(start) = content.find(SSH_CONFIG_HEADER_START) else {
+        return EntryStatus::Missing;
+    };
+    let block = &content[start..];
+    let block = block.find(SSH_CONFIG_HEADER_END).map_or(block, |end| &block[..end]);
+    let header = rendered.lines().next().unwrap_or_default();
+    if block.contains(rendered) {
+        EntryStatus::InSync
+    } else if block.lines().any(|line| line == header) {
+        EntryStatus::Outdated
+    } else {
+        EntryStatus::Missing
+    }
 }
 
 use std::fs;
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
 
+/// `content` with its managed block replaced by `block` (which may be empty), put where
+/// `position` asks.
+fn place_block(content: &str, block: &str, position: SshBlockPosition) -> String {
+    let start = content.find(SSH_CONFIG_HEADER_START);
+    let end = content.rfind(SSH_CONFIG_HEADER_END);
+    let (before, after) = match (start, end) {
+        (Some(start), Some(end)) if start < end => {
+            let end = end + SSH_CONFIG_HEADER_END.len();
+            // Take the newline after the block along with it
+            let end = if content[end..].starts_with('\n') { end + 1 } else { end };
+            (&content[..start], &content[end..])
+        }
+        // No block, or a malformed one: leave the file as it is
+        _ => (content, ""),
+    };
+    let parts = match position {
+        SshBlockPosition::Keep => [before, block, after],
+        SshBlockPosition::Top => [block, before, after],
+        SshBlockPosition::Bottom => [before, after, block],
+    };
+    let mut placed = String::new();
+    for part in parts.into_iter().filter(|part| !part.is_empty()) {
+        if !placed.is_empty() && !placed.ends_with('\n') {
+            placed.push('\n');
+        }
+        placed.push_str(part);
+    }
+    placed
+}
+
+/// A section above the managed block that also applies to some of its hosts and sets options
+/// its entries set. ssh keeps the first value it finds (and tries IdentityFiles in order), so
+/// the section's values win over gitp's.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ShadowingSection {
+    /// 1-based line of the `Host`/`Match` line, 0 for options before the first one
+    pub line: usize,
+    /// The `Host`/`Match` line as written
+    pub header: String,
+    /// Options it sets that gitp's entries set too, lowercase
+    pub options: Vec<String>,
+}
+
+/// Options gitp writes in its entries.
+const ENTRY_OPTIONS: [&str; 5] = [
+    "hostname",
+    "user",
+    "identityfile",
+    "identitiesonly",
+    "securitykeyprovider",
+];
+
+/// Sections of `content` before the managed block that apply to one of `hosts` and set an
+/// option gitp's entries set. `Match` sections other than `Match all` can't be evaluated
+/// without connecting and are ignored.
+pub(crate) fn shadowing_sections(content: &str, hosts: &[&str]) -> Vec<ShadowingSection> {
+    let before_block = content
+        .find(SSH_CONFIG_HEADER_START)
+        .map_or("", |start| &content[..start]);
+    let mut sections = Vec::new();
+    // Options before the first Host or Match line apply to every host
+    let mut current = Some(ShadowingSection {
+        line: 0,
+        header: String::new(),
+        options: Vec::new(),
+    });
+    for (i, line) in before_block.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (keyword, args) = line
+            .split_once(|c: char| c.is_whitespace() || c == '=')
+            .unwrap_or((line, ""));
+        let keyword = keyword.to_lowercase();
+        let args = args.trim_start_matches(|c: char| c.is_whitespace() || c == '=');
+        match keyword.as_str() {
+            "host" | "match" => {
+                sections.extend(current.take().filter(|s| !s.options.is_empty()));
+                let applies = if keyword == "host" {
+                    hosts.iter().any(|host| host_pattern_matches(args, host))
+                } else {
+                    args.trim().eq_ignore_ascii_case("all")
+                };
+                current = applies.then(|| ShadowingSection {
+                    line: i + 1,
+                    header: line.to_string(),
+                    options: Vec::new(),
+                });
+            }
+            _ => {
+                if let Some(section) = current.as_mut() {
+                    if ENTRY_OPTIONS.contains(&keyword.as_str()) && !section.options.contains(&keyword) {
+                        section.options.push(keyword);
+                    }
+                }
+            }
+        }
+    }
+    sections.extend(current.filter(|s| !s.options.is_empty()));
+    sections
+}
+
+/// Whether an ssh `Host` pattern list matches `host`: some pattern matches and no negated
+/// (`!`) one does.
+fn host_pattern_matches(patterns: &str, host: &str) -> bool {
+    let host = host.to_lowercase();
+    let mut matched = false;
+    for pattern in patterns.split(|c: char| c.is_whitespace() || c == ',').filter(|p| !p.is_empty()) {
+        let pattern = pattern.to_lowercase();
+        match pattern.strip_prefix('!') {
+            Some(negated) if glob_match(negated, &host) => return false,
+            Some(_) => {}
+            None => matched |= glob_match(&pattern, &host),
+        }
+    }
+    matched
+}
+
 /// Updates the SSH config file with entries managed by gitp.
 /// It ensures that only entries from currently defined gitp profiles with SSH are present
 /// within a specially marked block in the SSH config file.
-pub fn update_ssh_config(managed_entries: &[(String, PathBuf, Option<String>)]) -> Result<()> {
+pub fn update_ssh_config(
+    managed_entries: &[SshEntry],
+    security_key_provider: Option<&str>,
+    position: SshBlockPosition,
+) -> Result<()> {
+    timing::measure("ssh config write", || {
+        write_managed_block(managed_entries, security_key_provider, position)
+    })
+}
+
+fn write_managed_block(
+    managed_entries: &[SshEntry],
+    security_key_provider: Option<&str>,
+    position: SshBlockPosition,
+) -> Result<()> {
     let config_path = get_ssh_config_path()?;
     let ssh_dir = config_path.parent().ok_or_else(|| anyhow::anyhow!("Invalid SSH config path, cannot get parent directory."))?;
 
@@ -65,44 +311,43 @@ pub fn update_ssh_config(managed_entries: &[(String, PathBuf, Option<String>)])
     }
 
     let original_config_content = read_ssh_config(&config_path)?;
-    let mut new_config_content = original_config_content.clone();
+
+    // Never rewrite a block this version doesn't understand
+    match managed_block_format(&original_config_content) {
+        Some(FormatCheck::Newer(version)) => bail!(
+            "The gitp-managed block in {:?} was written by a newer gitp (format {}, this version writes format {}). Upgrade gitp to change it.",
+            config_path, version, MANAGED_FORMAT_VERSION
+        ),
+        Some(FormatCheck::Unrecognized(tag)) => bail!(
+            "The gitp-managed block in {:?} has an unrecognized header tag '{}'. Fix or remove the block and retry.",
+            config_path, tag
+        ),
+        Some(FormatCheck::Outdated(version)) if !managed_entries.is_empty() => println!(
+            "Upgrading the gitp-managed SSH block from format {} to {}.",
+            version, MANAGED_FORMAT_VERSION
+        ),
+        _ => {}
+    }
 
     let mut new_gitp_block_content = String::new();
     if !managed_entries.is_empty() {
         new_gitp_block_content.push_str(SSH_CONFIG_HEADER_START);
+        new_gitp_block_content.push_str(&format_tag());
         new_gitp_block_content.push('\n');
-        for (host, key_path, user) in managed_entries {
-            new_gitp_block_content.push_str(&generate_ssh_config_entry(host, key_path, user.as_deref()));
+        for entry in managed_entries {
+            new_gitp_block_content.push_str(&render_entry(entry, security_key_provider));
+        }
+        if position == SshBlockPosition::Top {
+            // Options the user wrote at the top of the file would otherwise become part of
+            // gitp's last Host entry; `Match all` makes them global again
+            new_gitp_block_content.push_str("Match all\n");
         }
         new_gitp_block_content.push_str(SSH_CONFIG_HEADER_END);
         new_gitp_block_content.push('\n');
     }
 
-    let start_marker_idx = original_config_content.find(SSH_CONFIG_HEADER_START);
-    let end_marker_idx = original_config_content.rfind(SSH_CONFIG_HEADER_END);
+    let mut new_config_content = place_block(&original_config_content, &new_gitp_block_content, position);
 
-    match (start_marker_idx, end_marker_idx) {
-        (Some(start_idx), Some(end_idx)) if start_idx < end_idx => {
-            // Block found, replace it
-            let end_of_block = end_idx + SSH_CONFIG_HEADER_END.len();
-            // Include newline after block if it exists
-            let end_of_block_with_newline = original_config_content.get(end_of_block..)
-                .and_then(|s| s.chars().next().filter(|&c| c == '\n'))
-                .map_or(end_of_block, |_| end_of_block + 1);
-            
-            new_config_content.replace_range(start_idx..end_of_block_with_newline, &new_gitp_block_content);
-        }
-        _ => {
-            // Block not found or malformed, append if there's new content
-            if !new_gitp_block_content.is_empty() {
-                if !new_config_content.is_empty() && !new_config_content.ends_with('\n') {
-                    new_config_content.push('\n'); // Ensure a newline before appending new block
-                }
-                new_config_content.push_str(&new_gitp_block_content);
-            }
-        }
-    }
-    
     // Trim multiple blank lines and ensure a single trailing newline
     let mut temp_lines: Vec<String> = Vec::new();
     let mut last_line_was_empty = false;
@@ -164,3 +409,78 @@ pub fn update_ssh_config(managed_entries: &[(String, PathBuf, Option<String>)])
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLOCK: &str = "# BEGIN MANAGED BY GITP (format 2)\nHost github.com\n    IdentityFile ~/.ssh/id_work\n# END MANAGED BY GITP\n";
+
+    #[test]
+    fn test_place_block() {
+        let user = "Host *\n    IdentitiesOnly no\n";
+        let kept = place_block(user, BLOCK, SshBlockPosition::Keep);
+        assert_eq!(kept, format!("{}{}", user, BLOCK));
+        // An existing block stays put unless a position is asked for
+        assert_eq!(place_block(&kept, BLOCK, SshBlockPosition::Keep), kept);
+        assert_eq!(place_block(&kept, BLOCK, SshBlockPosition::Top), format!("{}{}", BLOCK, user));
+        let top = format!("{}{}", BLOCK, user);
+        assert_eq!(place_block(&top, BLOCK, SshBlockPosition::Bottom), kept);
+        assert_eq!(place_block(&top, "", SshBlockPosition::Top), user);
+    }
+
+    #[test]
+    fn test_shadowing_sections() {
+        let content = format!(
+            "User me\nHost *.example.com\n    User admin\nHost * !github.com\n    IdentityFile ~/.ssh/id_rsa\nHost *\n    IdentitiesOnly no\n    IdentityFile ~/.ssh/id_rsa\n    ForwardAgent yes\nMatch exec \"true\"\n    User other\n{}Host *\n    User late\n",
+            BLOCK
+        );
+        let sections = shadowing_sections(&content, &["github.com"]);
+        assert_eq!(
+            sections,
+            [
+                ShadowingSection { line: 0, header: String::new(), options: vec!["user".to_string()] },
+                ShadowingSection {
+                    line: 6,
+                    header: "Host *".to_string(),
+                    options: vec!["identitiesonly".to_string(), "identityfile".to_string()],
+                },
+            ]
+        );
+        assert!(shadowing_sections(BLOCK, &["github.com"]).is_empty());
+    }
+
+    #[test]
+    fn test_entry_status() {
+        let entry = "Host github.com\n    IdentityFile ~/.ssh/id_work\n";
+        assert_eq!(entry_status(BLOCK, entry), EntryStatus::InSync);
+        assert_eq!(
+            entry_status(BLOCK, "Host github.com\n    IdentityFile ~/.ssh/id_new\n"),
+            EntryStatus::Outdated
+        );
+        assert_eq!(
+            entry_status(BLOCK, "Host gitlab.com\n    IdentityFile ~/.ssh/id_work\n"),
+            EntryStatus::Missing
+        );
+        // Entries outside the managed block don't count
+        assert_eq!(entry_status(entry, entry), EntryStatus::Missing);
+    }
+
+    #[test]
+    fn test_render_entry() {
+        let entry = SshEntry {
+            host: "github.com-work".to_string(),
+            hostname: "github.com".to_string(),
+            identity_file: PathBuf::from("~/.ssh/id_work"),
+            user: None,
+            match_profile: None,
+            control: None,
+            add_keys_to_agent: Some("yes".to_string()),
+            use_keychain: true,
+        };
+        assert_eq!(
+            render_entry(&entry, None),
+            "Host github.com-work\n    HostName github.com\n    User git\n    IdentityFile ~/.ssh/id_work\n    IdentitiesOnly yes\n    AddKeysToAgent yes\n    UseKeychain yes\n"
+        );
+    }
+}
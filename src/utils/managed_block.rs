@@ -0,0 +1,105 @@
+// src/utils/managed_block.rs
+
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::Path;
+
+use super::{check_format, format_tag, FormatCheck, MANAGED_FORMAT_VERSION};
+
+pub const BLOCK_BEGIN: &str = "# BEGIN MANAGED BY GITP";
+pub const BLOCK_END: &str = "# END MANAGED BY GITP";
+
+/// Returns `content` with its gitp-managed block replaced by `body`. The block is appended when
+/// missing and removed when `body` is empty; everything outside it is kept as is.
+pub fn replace_block(content: &str, body: &str) -> Result<String> {
+    let block = if body.trim().is_empty() {
+        String::new()
+    } else {
+        format!(
+            "{}{}\n{}\n{}\n",
+            BLOCK_BEGIN,
+            format_tag(),
+            body.trim_end(),
+            BLOCK_END
+        )
+    };
+
+    let start = content.find(BLOCK_BEGIN);
+    let end = content.rfind(BLOCK_END);
+    match (start, end) {
+        (Some(start), Some(end)) if start < end => {
+            let tag = content[start + BLOCK_BEGIN.len()..]
+                .lines()
+                .next()
+                .unwrap_or_default();
+            match check_format(tag) {
+                FormatCheck::Newer(version) => bail!(
+                    "The block was written by a newer gitp (format {}, this version writes format {}). Upgrade gitp to change it.",
+                    version,
+                    MANAGED_FORMAT_VERSION
+                ),
+                FormatCheck::Unrecognized(tag) => bail!(
+                    "The block has an unrecognized header tag '{}'. Fix or remove it and retry.",
+                    tag
+                ),
+                FormatCheck::Current | FormatCheck::Outdated(_) => {}
+            }
+            let mut end = end + BLOCK_END.len();
+            if content[end..].starts_with('\n') {
+                end += 1;
+            }
+            Ok(format!("{}{}{}", &content[..start], block, &content[end..]))
+        }
+        _ if block.is_empty() => Ok(content.to_string()),
+        _ if content.is_empty() || content.ends_with('\n') => Ok(format!("{}{}", content, block)),
+        _ => Ok(format!("{}\n{}", content, block)),
+    }
+}
+
+/// Updates the gitp-managed block of the file at `path`, creating the file if needed. Returns
+/// whether the file changed.
+pub fn write_block(path: &Path, body: &str) -> Result<bool> {
+    let content = if path.exists() {
+        fs::read_to_string(path).with_context(|| format!("Failed to read {:?}", path))?
+    } else {
+        String::new()
+    };
+    let updated = replace_block(&content, body).with_context(|| format!("In {:?}", path))?;
+    if updated == content {
+        return Ok(false);
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory {:?}", parent))?;
+    }
+    fs::write(path, updated).with_context(|| format!("Failed to write {:?}", path))?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replace_block() {
+        let added = replace_block("color=true", "email=a@b.c\n").unwrap();
+        assert_eq!(
+            added,
+            format!(
+                "color=true\n{}{}\nemail=a@b.c\n{}\n",
+                BLOCK_BEGIN,
+                format_tag(),
+                BLOCK_END
+            )
+        );
+
+        let replaced = replace_block(&format!("{}after\n", added), "email=x@y.z").unwrap();
+        assert!(replaced.contains("email=x@y.z\n") && !replaced.contains("a@b.c"));
+        assert!(replaced.starts_with("color=true\n") && replaced.ends_with("after\n"));
+
+        assert_eq!(replace_block(&replaced, "").unwrap(), "color=true\nafter\n");
+
+        let newer = format!("{} (format 99)\nx\n{}\n", BLOCK_BEGIN, BLOCK_END);
+        assert!(replace_block(&newer, "y").is_err());
+    }
+}
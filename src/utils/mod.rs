@@ -0,0 +1,241 @@
+// src/utils/mod.rs
+
+pub mod managed_block;
+pub mod mask;
+pub mod net;
+pub mod output;
+pub mod progress;
+pub mod prompt;
+pub mod quote;
+pub mod terminal;
+pub mod time;
+pub mod timing;
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+
+/// Expands a leading `~/` to the home directory.
+pub fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// `path` with the home directory replaced by `~`, so a config synced between machines with
+/// different home directories keeps working. Other paths are returned unchanged.
+pub fn contract_home(path: &Path) -> PathBuf {
+    match dirs::home_dir() {
+        Some(home) => contract_home_in(path, &home),
+        None => path.to_path_buf(),
+    }
+}
+
+fn contract_home_in(path: &Path, home: &Path) -> PathBuf {
+    let rest = match path.strip_prefix(home) {
+        Ok(rest) if path.is_absolute() && !rest.as_os_str().is_empty() => rest,
+        _ => return path.to_path_buf(),
+    };
+    // `~/` with forward slashes is what expand_home reads on every platform
+    let parts: Option<Vec<&str>> = rest.components().map(|c| c.as_os_str().to_str()).collect();
+    match parts {
+        Some(parts) => PathBuf::from(format!("~/{}", parts.join("/"))),
+        None => path.to_path_buf(),
+    }
+}
+
+/// A path typed by the user as gitp stores it: absolute (relative paths are taken from the
+/// current directory), then with the home directory contracted to `~`.
+pub fn normalize_path(path: &str) -> PathBuf {
+    let expanded = expand_home(path.trim());
+    let absolute = std::path::absolute(&expanded).unwrap_or(expanded);
+    let cleaned: PathBuf = absolute
+        .components()
+        .filter(|c| !matches!(c, Component::CurDir))
+        .collect();
+    contract_home(&cleaned)
+}
+
+/// `value` without surrounding whitespace, or `None` when that leaves nothing. For optional
+/// flags and prompt answers where blank means "not set".
+pub fn non_empty(value: &str) -> Option<String> {
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+/// Replaces the file at `path` with `content`, readable only by the user. The file is written
+/// next to it first, so readers never see a partial file.
+pub fn write_private_file(path: &Path, content: &[u8]) -> Result<()> {
+    let tmp_path = path.with_extension("tmp");
+    // Left over from an interrupted write; created anew so it is private from the start
+    let _ = fs::remove_file(&tmp_path);
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+        .open(&tmp_path)
+        .and_then(|mut file| file.write_all(content))
+        .with_context(|| format!("Failed to write {:?}", tmp_path))?;
+    fs::rename(&tmp_path, path).with_context(|| format!("Failed to replace {:?}", path))
+}
+
+/// Format of the blocks and fragments gitp writes into files it doesn't own (the SSH config
+/// block, generated git config fragments). Bump it when their layout changes, so newer versions
+/// can upgrade old blocks and older versions leave newer ones alone.
+pub const MANAGED_FORMAT_VERSION: u32 = 2;
+
+/// How a managed block's format compares to the one this gitp writes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormatCheck {
+    Current,
+    /// Written by an older gitp; rewritten on the next update
+    Outdated(u32),
+    /// Written by a newer gitp; must not be rewritten by this one
+    Newer(u32),
+    /// Header gitp doesn't understand
+    Unrecognized(String),
+}
+
+/// Suffix for a managed block header, e.g. ` (format 2)`.
+pub fn format_tag() -> String {
+    format!(" (format {})", MANAGED_FORMAT_VERSION)
+}
+
+/// Checks what follows the BEGIN marker of a managed block header. Headers without a tag
+/// predate versioning and count as format 1.
+pub fn check_format(tag: &str) -> FormatCheck {
+    let tag = tag.trim();
+    if tag.is_empty() {
+        return FormatCheck::Outdated(1);
+    }
+    let version = tag
+        .strip_prefix("(format ")
+        .and_then(|rest| rest.strip_suffix(')'))
+        .and_then(|version| version.trim().parse::<u32>().ok());
+    match version {
+        Some(v) if v == MANAGED_FORMAT_VERSION => FormatCheck::Current,
+        Some(v) if v < MANAGED_FORMAT_VERSION => FormatCheck::Outdated(v),
+        Some(v) => FormatCheck::Newer(v),
+        None => FormatCheck::Unrecognized(tag.to_string()),
+    }
+}
+
+/// Whether `text` contains shell-style wildcards (`*` or `?`).
+pub fn is_glob(text: &str) -> bool {
+    text.contains(['*', '?'])
+}
+
+/// Matches `text` against a shell-style pattern where `*` matches any run of characters and
+/// `?` a single one.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and of the text it is currently matched up to
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("work-*", "work-acme"));
+        assert!(glob_match("work-*", "work-"));
+        assert!(!glob_match("work-*", "personal"));
+        assert!(glob_match("*-acme", "client-acme"));
+        assert!(glob_match("w?rk", "work"));
+        assert!(!glob_match("w?rk", "wrk"));
+        assert!(glob_match("*a*b*", "xxaxxbxx"));
+        assert!(!glob_match("*a*b", "xxaxxbxx"));
+        assert!(glob_match("*", ""));
+        assert!(!is_glob("work"));
+        assert!(is_glob("work-*"));
+    }
+
+    #[test]
+    fn test_contract_home() {
+        let home = Path::new("/home/me");
+        assert_eq!(
+            contract_home_in(Path::new("/home/me/.ssh/id_work"), home),
+            PathBuf::from("~/.ssh/id_work")
+        );
+        assert_eq!(
+            contract_home_in(Path::new("/home/me/OneDrive - Co/id"), home),
+            PathBuf::from("~/OneDrive - Co/id")
+        );
+        // Not below home, already contracted, or home itself
+        for path in [
+            "/home/meadow/id",
+            "/etc/ssh/id",
+            "~/.ssh/id",
+            "/home/me",
+            "id",
+        ] {
+            assert_eq!(contract_home_in(Path::new(path), home), PathBuf::from(path));
+        }
+    }
+
+    #[test]
+    fn test_non_empty() {
+        assert_eq!(non_empty("  work "), Some("work".to_string()));
+        assert_eq!(non_empty(" \t"), None);
+        assert_eq!(non_empty(""), None);
+    }
+
+    #[test]
+    fn test_check_format() {
+        assert_eq!(check_format(&format_tag()), FormatCheck::Current);
+        assert_eq!(check_format(""), FormatCheck::Outdated(1));
+        assert_eq!(check_format(" (format 1)"), FormatCheck::Outdated(1));
+        assert_eq!(check_format("(format 99)"), FormatCheck::Newer(99));
+        assert_eq!(
+            check_format("by hand"),
+            FormatCheck::Unrecognized("by hand".to_string())
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_private_file() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("secrets.toml");
+        // A leftover temporary file, readable by everyone
+        fs::write(path.with_extension("tmp"), "stale")?;
+        write_private_file(&path, b"token")?;
+        assert_eq!(fs::read_to_string(&path)?, "token");
+        assert_eq!(fs::metadata(&path)?.permissions().mode() & 0o777, 0o600);
+        assert!(!path.with_extension("tmp").exists());
+        Ok(())
+    }
+}
@@ -0,0 +1,34 @@
+// src/utils/net.rs
+// TCP reachability, to tell a missing VPN or network from a configuration error
+
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// How long a host gets to accept a connection before it counts as unreachable.
+pub const REACHABILITY_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Whether `host` accepts a TCP connection on `port` within `timeout`. A name that doesn't
+/// resolve is unreachable too: split-horizon DNS often only answers on the VPN.
+pub fn reachable(host: &str, port: u16, timeout: Duration) -> bool {
+    let Ok(addrs) = (host, port).to_socket_addrs() else {
+        return false;
+    };
+    addrs
+        .into_iter()
+        .any(|addr| TcpStream::connect_timeout(&addr, timeout).is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_reachable() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        assert!(reachable("127.0.0.1", port, REACHABILITY_TIMEOUT));
+        drop(listener);
+        assert!(!reachable("127.0.0.1", port, REACHABILITY_TIMEOUT));
+    }
+}
@@ -0,0 +1,282 @@
+// src/utils/output.rs
+
+use anyhow::{Context, Result};
+use colored::{ColoredString, Colorize};
+#[cfg(feature = "interactive")]
+use dialoguer::{console::Style, theme::ColorfulTheme};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+use crate::config::{ColorTheme, OutputStyle};
+
+static ASCII: AtomicBool = AtomicBool::new(false);
+static THEME: OnceLock<ColorTheme> = OnceLock::new();
+
+/// What a piece of output means, which decides its color in the current theme.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    /// Something done or in place: values set, checks passed
+    Success,
+    /// Names, commands and paths picked out of the text
+    Accent,
+    /// Warnings and values that need a look
+    Warning,
+    /// Errors and failed checks
+    Error,
+}
+
+/// Colors text by [`Role`] in the palette of the `theme` setting. Commands use these rather
+/// than naming colors, so every command follows the theme.
+pub trait Paint {
+    fn paint(self, role: Role) -> ColoredString;
+
+    fn success(self) -> ColoredString
+    where
+        Self: Sized,
+    {
+        self.paint(Role::Success)
+    }
+
+    fn accent(self) -> ColoredString
+    where
+        Self: Sized,
+    {
+        self.paint(Role::Accent)
+    }
+
+    fn warning(self) -> ColoredString
+    where
+        Self: Sized,
+    {
+        self.paint(Role::Warning)
+    }
+
+    fn error(self) -> ColoredString
+    where
+        Self: Sized,
+    {
+        self.paint(Role::Error)
+    }
+}
+
+impl Paint for &str {
+    fn paint(self, role: Role) -> ColoredString {
+        paint_in(current_theme(), ColoredString::from(self), role)
+    }
+}
+
+impl Paint for ColoredString {
+    fn paint(self, role: Role) -> ColoredString {
+        paint_in(current_theme(), self, role)
+    }
+}
+
+fn paint_in(theme: ColorTheme, text: ColoredString, role: Role) -> ColoredString {
+    match (theme, role) {
+        (ColorTheme::Default, Role::Success) => text.green(),
+        (ColorTheme::Default, Role::Accent) => text.cyan(),
+        (ColorTheme::Default, Role::Warning) => text.yellow(),
+        (ColorTheme::Default, Role::Error) => text.red(),
+        (ColorTheme::HighContrast, Role::Success) => text.bright_green().bold(),
+        (ColorTheme::HighContrast, Role::Accent) => text.bright_cyan().bold(),
+        (ColorTheme::HighContrast, Role::Warning) => text.bright_yellow().bold(),
+        (ColorTheme::HighContrast, Role::Error) => text.bright_red().bold(),
+        (ColorTheme::ColorblindSafe, Role::Success) => text.blue(),
+        (ColorTheme::ColorblindSafe, Role::Accent) => text.cyan(),
+        (ColorTheme::ColorblindSafe, Role::Warning) => text.yellow(),
+        (ColorTheme::ColorblindSafe, Role::Error) => text.magenta().bold(),
+        (ColorTheme::Mono, Role::Warning | Role::Error) => text.bold(),
+        (ColorTheme::Mono, Role::Success | Role::Accent) => text,
+    }
+}
+
+fn current_theme() -> ColorTheme {
+    THEME.get().copied().unwrap_or_default()
+}
+
+/// Markers gitp prints next to list entries and check results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Symbol {
+    /// The profile in use
+    Current,
+    /// Any other profile
+    Profile,
+    Ok,
+    Warn,
+    Fail,
+}
+
+impl Symbol {
+    /// The marker in the current output style. The ASCII forms don't rely on color to tell
+    /// them apart.
+    pub fn as_str(self) -> &'static str {
+        match (self, is_ascii()) {
+            (Symbol::Current, false) | (Symbol::Profile, false) => "●",
+            (Symbol::Current, true) => "*",
+            (Symbol::Profile, true) => "-",
+            (Symbol::Ok, false) => "✓",
+            (Symbol::Ok, true) => "[ok]",
+            (Symbol::Warn, false) => "!",
+            (Symbol::Warn, true) => "[warn]",
+            (Symbol::Fail, false) => "✗",
+            (Symbol::Fail, true) => "[FAIL]",
+        }
+    }
+}
+
+/// Sets the output style and color theme for the rest of the process: `--ascii` wins over the
+/// `output` setting, and `auto` follows the locale.
+pub fn init(ascii_flag: bool, setting: OutputStyle, theme: ColorTheme) {
+    let _ = THEME.set(theme);
+    let ascii = ascii_flag
+        || match setting {
+            OutputStyle::Auto => !locale_is_unicode(),
+            OutputStyle::Unicode => false,
+            OutputStyle::Ascii => true,
+        };
+    ASCII.store(ascii, Ordering::Relaxed);
+}
+
+pub fn is_ascii() -> bool {
+    ASCII.load(Ordering::Relaxed)
+}
+
+/// Spinner frames for indicatif, the last one shown when finished.
+#[cfg(feature = "interactive")]
+pub fn spinner_chars() -> &'static str {
+    if is_ascii() {
+        "|/-\\ "
+    } else {
+        "⠁⠂⠄⡀⢀⠠⠐⠈ "
+    }
+}
+
+/// The theme for interactive prompts in the colors of the `theme` setting, with ASCII
+/// prefixes in ASCII mode.
+#[cfg(feature = "interactive")]
+pub fn theme() -> ColorfulTheme {
+    let theme = current_theme();
+    let [success, accent, warning, error] =
+        [Role::Success, Role::Accent, Role::Warning, Role::Error]
+            .map(|role| console_style(theme, role));
+    let muted = match theme {
+        ColorTheme::Mono => Style::new().for_stderr().dim(),
+        _ => Style::new().for_stderr().black().bright(),
+    };
+    let unchecked = match theme {
+        ColorTheme::Default => Style::new().for_stderr().magenta(),
+        _ => accent.clone(),
+    };
+    let ascii = is_ascii();
+    let prefix = |style: &Style, unicode: &str, plain: &str| {
+        style.apply_to(if ascii { plain } else { unicode }.to_string())
+    };
+    ColorfulTheme {
+        defaults_style: accent.clone(),
+        prompt_prefix: prefix(&warning, "?", "?"),
+        prompt_suffix: prefix(&muted, "›", ":"),
+        success_prefix: prefix(&success, "✔", "+"),
+        success_suffix: prefix(&muted, "·", ":"),
+        error_prefix: prefix(&error, "✘", "x"),
+        error_style: error,
+        hint_style: muted,
+        values_style: success.clone(),
+        active_item_style: accent,
+        active_item_prefix: prefix(&success, "❯", ">"),
+        checked_item_prefix: prefix(&success, "✔", "[x]"),
+        unchecked_item_prefix: prefix(&unchecked, "⬚", "[ ]"),
+        picked_item_prefix: prefix(&success, "❯", ">"),
+        ..ColorfulTheme::default()
+    }
+}
+
+/// [`paint_in`]'s palette for dialoguer.
+#[cfg(feature = "interactive")]
+fn console_style(theme: ColorTheme, role: Role) -> Style {
+    let style = Style::new().for_stderr();
+    match (theme, role) {
+        (ColorTheme::Default, Role::Success) => style.green(),
+        (ColorTheme::Default, Role::Accent) => style.cyan(),
+        (ColorTheme::Default, Role::Warning) => style.yellow(),
+        (ColorTheme::Default, Role::Error) => style.red(),
+        (ColorTheme::HighContrast, Role::Success) => style.green().bright().bold(),
+        (ColorTheme::HighContrast, Role::Accent) => style.cyan().bright().bold(),
+        (ColorTheme::HighContrast, Role::Warning) => style.yellow().bright().bold(),
+        (ColorTheme::HighContrast, Role::Error) => style.red().bright().bold(),
+        (ColorTheme::ColorblindSafe, Role::Success) => style.blue(),
+        (ColorTheme::ColorblindSafe, Role::Accent) => style.cyan(),
+        (ColorTheme::ColorblindSafe, Role::Warning) => style.yellow(),
+        (ColorTheme::ColorblindSafe, Role::Error) => style.magenta().bold(),
+        (ColorTheme::Mono, Role::Warning | Role::Error) => style.bold(),
+        (ColorTheme::Mono, Role::Success | Role::Accent) => style,
+    }
+}
+
+#[cfg(not(feature = "interactive"))]
+pub fn theme() -> crate::utils::prompt::Theme {
+    crate::utils::prompt::Theme
+}
+
+/// Whether the locale (`LC_ALL`, then `LC_CTYPE`, then `LANG`) uses UTF-8. Without any locale
+/// variable, Unix terminals fall back to the C locale; Windows consoles render Unicode.
+fn locale_is_unicode() -> bool {
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty());
+    charset_is_unicode(locale.as_deref())
+}
+
+fn charset_is_unicode(locale: Option<&str>) -> bool {
+    match locale {
+        Some(locale) => {
+            let locale = locale.to_lowercase();
+            locale.contains("utf-8") || locale.contains("utf8")
+        }
+        None => cfg!(windows),
+    }
+}
+
+/// Prints `value` as pretty-printed JSON, for `--format json`.
+pub fn print_json<T: serde::Serialize>(value: &T) -> Result<()> {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(value).context("Failed to serialize output.")?
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paint_in() {
+        use colored::{Color, Styles};
+
+        let paint = |theme, role| paint_in(theme, "text".into(), role);
+        assert_eq!(
+            paint(ColorTheme::Default, Role::Success).fgcolor,
+            Some(Color::Green)
+        );
+        let error = paint(ColorTheme::ColorblindSafe, Role::Error);
+        assert_eq!(error.fgcolor, Some(Color::Magenta));
+        assert!(error.style.contains(Styles::Bold));
+        assert!(paint(ColorTheme::Mono, Role::Accent).is_plain());
+        let warning = paint(ColorTheme::Mono, Role::Warning);
+        assert_eq!(warning.fgcolor, None);
+        assert!(warning.style.contains(Styles::Bold));
+        // Styles given before the role stay
+        let bold = paint_in(ColorTheme::Mono, "text".bold(), Role::Success);
+        assert!(bold.style.contains(Styles::Bold));
+    }
+
+    #[test]
+    fn test_charset_is_unicode() {
+        assert!(charset_is_unicode(Some("en_US.UTF-8")));
+        assert!(charset_is_unicode(Some("C.utf8")));
+        assert!(!charset_is_unicode(Some("C")));
+        assert!(!charset_is_unicode(Some("de_DE.ISO-8859-1")));
+        assert_eq!(charset_is_unicode(None), cfg!(windows));
+    }
+}
@@ -0,0 +1,50 @@
+// src/utils/progress.rs
+
+#[cfg(feature = "interactive")]
+use indicatif::{ProgressBar, ProgressStyle};
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "interactive")]
+use std::time::Duration;
+
+#[cfg(feature = "interactive")]
+use crate::utils::output::spinner_chars;
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Turns spinners off for the rest of the process (`--quiet`, or output read by another
+/// program).
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+#[cfg(feature = "interactive")]
+fn enabled() -> bool {
+    !QUIET.load(Ordering::Relaxed) && atty::is(atty::Stream::Stderr)
+}
+
+/// Runs `f` while a spinner with `message` turns on stderr, so operations that can take
+/// seconds don't look like hangs. Nothing is drawn under `--quiet` or without a terminal.
+/// `f` must not prompt.
+#[cfg(feature = "interactive")]
+pub fn with_spinner<T>(message: &str, f: impl FnOnce() -> T) -> T {
+    let spinner = enabled().then(|| {
+        let spinner = ProgressBar::new_spinner();
+        if let Ok(style) = ProgressStyle::with_template("{spinner:.cyan} {msg}") {
+            spinner.set_style(style.tick_chars(spinner_chars()));
+        }
+        spinner.set_message(message.to_string());
+        spinner.enable_steady_tick(Duration::from_millis(100));
+        spinner
+    });
+    let result = f();
+    if let Some(spinner) = spinner {
+        spinner.finish_and_clear();
+    }
+    result
+}
+
+/// Builds without the `interactive` feature draw no spinners.
+#[cfg(not(feature = "interactive"))]
+pub fn with_spinner<T>(_message: &str, f: impl FnOnce() -> T) -> T {
+    f()
+}
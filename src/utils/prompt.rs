@@ -0,0 +1,314 @@
+// src/utils/prompt.rs
+// Interactive prompts: dialoguer with the `interactive` feature, stand-ins without it
+
+#[cfg(feature = "interactive")]
+pub use dialoguer::{Confirm, Input, Password, Select};
+
+#[cfg(not(feature = "interactive"))]
+pub use stand_in::{Confirm, Input, Password, Select, Theme};
+
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+
+use super::non_empty;
+use super::output::{theme, Paint};
+
+/// Asks for an optional value, prefilled with `current`. A blank answer means none.
+pub fn prompt_optional(prompt: &str, current: Option<&str>) -> Result<Option<String>> {
+    let theme = theme();
+    let mut input = Input::<String>::with_theme(&theme)
+        .with_prompt(prompt)
+        .allow_empty(true);
+    if let Some(current) = current.filter(|c| !c.is_empty()) {
+        input = input.default(current.to_string());
+    }
+    let answer = input
+        .interact_text()
+        .with_context(|| format!("Failed to read '{}'.", prompt))?;
+    Ok(non_empty(&answer))
+}
+
+/// Asks for a value that can't be blank, prefilled with `current`. A blank answer fails with
+/// `what` named in the error, e.g. "User name cannot be empty."
+pub fn prompt_required(prompt: &str, current: Option<&str>, what: &str) -> Result<String> {
+    let theme = theme();
+    let mut input = Input::<String>::with_theme(&theme).with_prompt(prompt);
+    if let Some(current) = current.filter(|c| !c.is_empty()) {
+        input = input.default(current.to_string());
+    }
+    let answer = input
+        .interact_text()
+        .with_context(|| format!("Failed to read '{}'.", prompt))?;
+    match non_empty(&answer) {
+        Some(value) => Ok(value),
+        None => bail!("{} cannot be empty.", what),
+    }
+}
+
+/// Values offered while typing a prompt answer, most likely first. Tab completes the first one
+/// starting with what was typed.
+#[derive(Debug, Default)]
+pub struct Suggestions(Vec<String>);
+
+impl Suggestions {
+    /// `candidates` without blanks and case-insensitive duplicates, in order.
+    pub fn new(candidates: impl IntoIterator<Item = String>) -> Self {
+        let mut values: Vec<String> = Vec::new();
+        for candidate in candidates {
+            let candidate = candidate.trim();
+            if !candidate.is_empty() && !values.iter().any(|v| v.eq_ignore_ascii_case(candidate)) {
+                values.push(candidate.to_string());
+            }
+        }
+        Suggestions(values)
+    }
+
+    /// The first suggestion starting with `input`, ignoring case.
+    #[cfg_attr(not(feature = "interactive"), allow(dead_code))]
+    pub fn complete(&self, input: &str) -> Option<String> {
+        let input = input.to_lowercase();
+        self.0
+            .iter()
+            .find(|v| v.to_lowercase().starts_with(&input) && v.len() > input.len())
+            .cloned()
+    }
+
+    /// A suggestion `value` is probably a typo of: one or two edits away, and `value` isn't a
+    /// suggestion itself.
+    pub fn near_miss(&self, value: &str) -> Option<&str> {
+        if value.chars().count() < 5 || self.0.iter().any(|v| v.eq_ignore_ascii_case(value)) {
+            return None;
+        }
+        self.0
+            .iter()
+            .map(|v| (edit_distance(&v.to_lowercase(), &value.to_lowercase()), v))
+            .filter(|(distance, _)| *distance <= 2)
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+#[cfg(feature = "interactive")]
+impl dialoguer::Completion for Suggestions {
+    fn get(&self, input: &str) -> Option<String> {
+        self.complete(input)
+    }
+}
+
+/// Like [`prompt_required`], with Tab completing from `suggestions`. An answer a couple of
+/// keystrokes away from a suggestion asks whether the suggestion was meant.
+pub fn prompt_suggested(
+    prompt: &str,
+    current: Option<&str>,
+    what: &str,
+    suggestions: &Suggestions,
+) -> Result<String> {
+    if !suggestions.0.is_empty() {
+        let mut shown = suggestions.0[..suggestions.0.len().min(3)].join(", ");
+        if suggestions.0.len() > 3 {
+            shown.push_str(&format!(", {} more", suggestions.0.len() - 3));
+        }
+        println!("  {}", format!("Tab completes: {}", shown).dimmed());
+    }
+    let theme = theme();
+    let mut input = Input::<String>::with_theme(&theme)
+        .with_prompt(prompt)
+        .completion_with(suggestions);
+    if let Some(current) = current.filter(|c| !c.is_empty()) {
+        input = input.default(current.to_string());
+    }
+    let answer = input
+        .interact_text()
+        .with_context(|| format!("Failed to read '{}'.", prompt))?;
+    let Some(answer) = non_empty(&answer) else {
+        bail!("{} cannot be empty.", what);
+    };
+    if let Some(suggestion) = suggestions.near_miss(&answer) {
+        if Confirm::with_theme(&theme)
+            .with_prompt(format!(
+                "'{}' is close to {}. Use {} instead?",
+                answer,
+                suggestion.accent(),
+                suggestion.accent()
+            ))
+            .default(true)
+            .interact()?
+        {
+            return Ok(suggestion.to_string());
+        }
+    }
+    Ok(answer)
+}
+
+/// Levenshtein distance between `a` and `b`, in characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Builds without the `interactive` feature keep dialoguer's builder API so callers compile
+/// unchanged, but every prompt fails. [`can_prompt`](super::terminal::can_prompt) reports no
+/// terminal in these builds, so commands ask for flags before getting this far.
+#[cfg(not(feature = "interactive"))]
+mod stand_in {
+    use std::io;
+    use std::marker::PhantomData;
+
+    pub struct Theme;
+
+    fn unavailable<T>() -> io::Result<T> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "this gitp build has no interactive prompts (built without the 'interactive' feature)",
+        ))
+    }
+
+    pub struct Confirm;
+
+    impl Confirm {
+        pub fn with_theme(_theme: &Theme) -> Self {
+            Confirm
+        }
+
+        pub fn with_prompt(self, _prompt: impl Into<String>) -> Self {
+            self
+        }
+
+        pub fn default(self, _value: bool) -> Self {
+            self
+        }
+
+        pub fn interact(self) -> io::Result<bool> {
+            unavailable()
+        }
+    }
+
+    pub struct Input<T>(PhantomData<T>);
+
+    impl<T> Input<T> {
+        pub fn with_theme(_theme: &Theme) -> Self {
+            Input(PhantomData)
+        }
+
+        pub fn with_prompt(self, _prompt: impl Into<String>) -> Self {
+            self
+        }
+
+        pub fn default(self, _value: T) -> Self {
+            self
+        }
+
+        pub fn allow_empty(self, _allow: bool) -> Self {
+            self
+        }
+
+        pub fn with_initial_text(self, _text: impl Into<String>) -> Self {
+            self
+        }
+
+        pub fn validate_with<V>(self, _validator: V) -> Self {
+            self
+        }
+
+        pub fn completion_with<C>(self, _completion: &C) -> Self {
+            self
+        }
+
+        pub fn interact_text(self) -> io::Result<T> {
+            unavailable()
+        }
+    }
+
+    pub struct Password;
+
+    impl Password {
+        pub fn with_theme(_theme: &Theme) -> Self {
+            Password
+        }
+
+        pub fn with_prompt(self, _prompt: impl Into<String>) -> Self {
+            self
+        }
+
+        pub fn with_confirmation(
+            self,
+            _prompt: impl Into<String>,
+            _mismatch_err: impl Into<String>,
+        ) -> Self {
+            self
+        }
+
+        pub fn interact(self) -> io::Result<String> {
+            unavailable()
+        }
+    }
+
+    pub struct Select;
+
+    impl Select {
+        pub fn with_theme(_theme: &Theme) -> Self {
+            Select
+        }
+
+        pub fn with_prompt(self, _prompt: impl Into<String>) -> Self {
+            self
+        }
+
+        pub fn items<T: ToString>(self, _items: &[T]) -> Self {
+            self
+        }
+
+        pub fn default(self, _index: usize) -> Self {
+            self
+        }
+
+        pub fn interact(self) -> io::Result<usize> {
+            unavailable()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suggestions() {
+        let suggestions = Suggestions::new(
+            [
+                "john@company.com",
+                "John@Company.com",
+                " ",
+                "john@personal.dev",
+            ]
+            .map(String::from),
+        );
+        assert_eq!(suggestions.0.len(), 2);
+        assert_eq!(
+            suggestions.complete("JOHN@P").as_deref(),
+            Some("john@personal.dev")
+        );
+        assert_eq!(
+            suggestions.complete("").as_deref(),
+            Some("john@company.com")
+        );
+        assert_eq!(suggestions.complete("john@company.com"), None);
+        assert_eq!(
+            suggestions.near_miss("jhon@company.com"),
+            Some("john@company.com")
+        );
+        assert_eq!(suggestions.near_miss("john@compnay.con"), None);
+        assert_eq!(suggestions.near_miss("JOHN@company.com"), None);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+}
@@ -0,0 +1,136 @@
+// src/utils/quote.rs
+// Quoting for the shells, git config files and ssh config gitp writes values into
+
+/// `value` in single quotes for sh, bash and zsh.
+pub fn posix_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// `value` in single quotes for fish.
+pub fn fish_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\\', r"\\").replace('\'', r"\'"))
+}
+
+/// `value` as one sh word, quoted only when it needs to be.
+pub fn shell_word(value: &str) -> String {
+    if !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_.@/+:,%=".contains(c))
+    {
+        value.to_string()
+    } else {
+        posix_quote(value)
+    }
+}
+
+/// A path as one sh word. A leading `~/` stays outside the quotes, so the shell still expands
+/// it.
+pub fn shell_path(path: &str) -> String {
+    match path.strip_prefix("~/") {
+        Some(rest) if !rest.is_empty() => format!("~/{}", shell_word(rest)),
+        _ => shell_word(path),
+    }
+}
+
+/// Quotes a value for a git config file when it would otherwise be misread.
+pub fn gitconfig_value(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t");
+    if escaped != value
+        || value.contains(['#', ';'])
+        || value.starts_with(char::is_whitespace)
+        || value.ends_with(char::is_whitespace)
+    {
+        format!("\"{}\"", escaped)
+    } else {
+        escaped
+    }
+}
+
+/// A subsection name for a git config section header (`[url "<name>"]`). Git can't represent
+/// newlines in one, so they become spaces.
+pub fn gitconfig_subsection(name: &str) -> String {
+    format!(
+        "\"{}\"",
+        name.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', " ")
+    )
+}
+
+/// An argument in ~/.ssh/config, in double quotes when it has whitespace, quotes or
+/// backslashes, which ssh would otherwise split on or unescape.
+pub fn ssh_config_value(value: &str) -> String {
+    if !value.is_empty() && !value.contains(|c: char| c.is_whitespace() || "\"'\\".contains(c)) {
+        return value.to_string();
+    }
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// A file path for ssh config keywords that expand `%` tokens (IdentityFile). Windows paths get
+/// forward slashes, which ssh on Windows reads the same way and which need no escaping.
+pub fn ssh_config_path(path: &str) -> String {
+    let path = if is_windows_path(path) {
+        path.replace('\\', "/")
+    } else {
+        path.to_string()
+    };
+    ssh_config_value(&path.replace('%', "%%"))
+}
+
+/// `C:\...`, `C:/...` or a UNC path (`\\server\share`).
+fn is_windows_path(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    path.starts_with(r"\\")
+        || (bytes.len() >= 3
+            && bytes[0].is_ascii_alphabetic()
+            && bytes[1] == b':'
+            && (bytes[2] == b'\\' || bytes[2] == b'/'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quoting() {
+        assert_eq!(shell_word("work"), "work");
+        assert_eq!(shell_word("client a"), "'client a'");
+        assert_eq!(shell_word("it's"), r"'it'\''s'");
+        assert_eq!(shell_path("~/.ssh/id_work"), "~/.ssh/id_work");
+        assert_eq!(
+            shell_path("~/OneDrive - Company/id"),
+            "~/'OneDrive - Company/id'"
+        );
+        assert_eq!(shell_path(r"C:\keys\id"), r"'C:\keys\id'");
+
+        assert_eq!(gitconfig_value("line\nbreak"), r#""line\nbreak""#);
+        assert_eq!(gitconfig_subsection(r#"git@a"b:"#), r#""git@a\"b:""#);
+
+        assert_eq!(ssh_config_path("~/.ssh/id_work"), "~/.ssh/id_work");
+        assert_eq!(
+            ssh_config_path("/home/me/OneDrive - Company/id 100%"),
+            "\"/home/me/OneDrive - Company/id 100%%\""
+        );
+        assert_eq!(
+            ssh_config_path(r"C:\Users\Me\OneDrive - Company\.ssh\id"),
+            "\"C:/Users/Me/OneDrive - Company/.ssh/id\""
+        );
+        assert_eq!(ssh_config_path(r"\\srv\keys\id"), "//srv/keys/id");
+        assert_eq!(ssh_config_value("plain"), "plain");
+        assert_eq!(ssh_config_value(r#"a"b"#), r#""a\"b""#);
+    }
+
+    #[test]
+    fn test_gitconfig_value_quoting() {
+        assert_eq!(gitconfig_value("plain value"), "plain value");
+        assert_eq!(gitconfig_value("a#b"), "\"a#b\"");
+        assert_eq!(gitconfig_value(" padded"), "\" padded\"");
+        assert_eq!(gitconfig_value(r#"say "hi""#), r#""say \"hi\"""#);
+        assert_eq!(gitconfig_value(r"C:\keys"), r#""C:\\keys""#);
+    }
+}
@@ -0,0 +1,47 @@
+// src/utils/terminal.rs
+
+use anyhow::{bail, Result};
+
+/// Whether interactive prompts can run. dialoguer draws prompts on stderr and reads keys from
+/// the controlling terminal, opening /dev/tty when stdin is redirected, so a piped stdin alone
+/// doesn't rule prompting out. Builds without the `interactive` feature never prompt.
+pub fn can_prompt() -> bool {
+    cfg!(feature = "interactive")
+        && atty::is(atty::Stream::Stderr)
+        && (atty::is(atty::Stream::Stdin) || controlling_terminal_available())
+}
+
+#[cfg(unix)]
+fn controlling_terminal_available() -> bool {
+    std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .is_ok()
+}
+
+#[cfg(not(unix))]
+fn controlling_terminal_available() -> bool {
+    false
+}
+
+/// Fails up front when `action` would need to prompt but no terminal is available, instead of
+/// erroring (or blocking) halfway through. `hint` says how to do it non-interactively.
+pub fn require_terminal(action: &str, hint: &str) -> Result<()> {
+    if !cfg!(feature = "interactive") {
+        bail!(
+            "{} needs prompts, but this gitp build has none (built without the 'interactive' \
+             feature). {}",
+            action,
+            hint
+        );
+    }
+    if !can_prompt() {
+        bail!(
+            "{} needs a terminal for its prompts, but none is available. {}",
+            action,
+            hint
+        );
+    }
+    Ok(())
+}
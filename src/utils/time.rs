@@ -0,0 +1,105 @@
+// src/utils/time.rs
+
+use chrono::{DateTime, Local, NaiveDate, SecondsFormat, Utc};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static UTC: AtomicBool = AtomicBool::new(false);
+
+/// Shows times in UTC instead of the local time zone for the rest of the process (`--utc`).
+pub fn set_utc(utc: bool) {
+    UTC.store(utc, Ordering::Relaxed);
+}
+
+/// `at` as ISO 8601 in the local time zone (or UTC with `--utc`), followed by how long ago it
+/// was: `2024-06-01T14:03:00+02:00 (3 days ago)`.
+pub fn format_time(at: &DateTime<Utc>) -> String {
+    format_time_at(at, &Utc::now(), UTC.load(Ordering::Relaxed))
+}
+
+fn format_time_at(at: &DateTime<Utc>, now: &DateTime<Utc>, utc: bool) -> String {
+    let iso = if utc {
+        at.to_rfc3339_opts(SecondsFormat::Secs, true)
+    } else {
+        at.with_timezone(&Local)
+            .to_rfc3339_opts(SecondsFormat::Secs, false)
+    };
+    format!("{} ({})", iso, humanize_seconds((*at - *now).num_seconds()))
+}
+
+/// `date` followed by how far it is from today: `2024-06-30 (in 12 days)`.
+pub fn format_date(date: NaiveDate) -> String {
+    let today = if UTC.load(Ordering::Relaxed) {
+        Utc::now().date_naive()
+    } else {
+        Local::now().date_naive()
+    };
+    format_date_at(date, today)
+}
+
+fn format_date_at(date: NaiveDate, today: NaiveDate) -> String {
+    let relative = match (date - today).num_days() {
+        0 => "today".to_string(),
+        1 => "tomorrow".to_string(),
+        -1 => "yesterday".to_string(),
+        days => humanize_seconds(days * 86_400),
+    };
+    format!("{} ({})", date, relative)
+}
+
+/// A signed offset from now in words, in the largest unit that fits: `3 days ago`,
+/// `in 2 hours`, `just now`.
+pub fn humanize_seconds(offset: i64) -> String {
+    let seconds = offset.unsigned_abs();
+    if seconds < 60 {
+        return "just now".to_string();
+    }
+    let (count, unit) = [
+        (365 * 86_400, "year"),
+        (30 * 86_400, "month"),
+        (7 * 86_400, "week"),
+        (86_400, "day"),
+        (3_600, "hour"),
+        (60, "minute"),
+    ]
+    .into_iter()
+    .find(|(size, _)| seconds >= *size)
+    .map(|(size, unit)| (seconds / size, unit))
+    .unwrap_or((seconds / 60, "minute"));
+    let amount = format!("{} {}{}", count, unit, if count == 1 { "" } else { "s" });
+    if offset < 0 {
+        format!("{} ago", amount)
+    } else {
+        format!("in {}", amount)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_time_and_date() {
+        let now = DateTime::parse_from_rfc3339("2024-06-04T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let at = DateTime::parse_from_rfc3339("2024-06-01T10:30:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(
+            format_time_at(&at, &now, true),
+            "2024-06-01T10:30:00Z (3 days ago)"
+        );
+        assert!(format_time_at(&at, &now, false).ends_with(" (3 days ago)"));
+
+        assert_eq!(humanize_seconds(30), "just now");
+        assert_eq!(humanize_seconds(-3_600), "1 hour ago");
+        assert_eq!(humanize_seconds(90 * 60), "in 1 hour");
+        assert_eq!(humanize_seconds(-400 * 86_400), "1 year ago");
+
+        let today = NaiveDate::from_ymd_opt(2024, 6, 18).unwrap();
+        let date = |d| NaiveDate::from_ymd_opt(2024, 6, d).unwrap();
+        assert_eq!(format_date_at(date(30), today), "2024-06-30 (in 1 week)");
+        assert_eq!(format_date_at(date(17), today), "2024-06-17 (yesterday)");
+        assert_eq!(format_date_at(date(18), today), "2024-06-18 (today)");
+    }
+}
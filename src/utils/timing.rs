@@ -0,0 +1,92 @@
+// src/utils/timing.rs
+// `--timings`: how long the slow parts of a command (config, git, keyring, SSH config) took
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static STARTED: OnceLock<Instant> = OnceLock::new();
+/// Calls and total time per category, in the order categories first ran
+static TOTALS: Mutex<Vec<(&'static str, u32, Duration)>> = Mutex::new(Vec::new());
+
+/// Starts recording timings for the rest of the process.
+pub fn enable() {
+    STARTED.get_or_init(Instant::now);
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Runs `f`, adding its duration to `category` when timings are on.
+pub fn measure<T>(category: &'static str, f: impl FnOnce() -> T) -> T {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return f();
+    }
+    let start = Instant::now();
+    let result = f();
+    record(category, start.elapsed());
+    result
+}
+
+fn record(category: &'static str, elapsed: Duration) {
+    let Ok(mut totals) = TOTALS.lock() else {
+        return;
+    };
+    match totals.iter_mut().find(|(name, _, _)| *name == category) {
+        Some((_, calls, total)) => {
+            *calls += 1;
+            *total += elapsed;
+        }
+        None => totals.push((category, 1, elapsed)),
+    }
+}
+
+/// Prints the recorded timings to stderr, so they don't mix with a command's output.
+pub fn report() {
+    let Some(started) = STARTED.get() else {
+        return;
+    };
+    let totals = TOTALS.lock().map(|t| t.clone()).unwrap_or_default();
+    eprint!("{}", format_report(&totals, started.elapsed()));
+}
+
+fn format_report(totals: &[(&str, u32, Duration)], elapsed: Duration) -> String {
+    let mut text = format!("Timings (total {}):\n", format_duration(elapsed));
+    if totals.is_empty() {
+        text.push_str("  nothing measured\n");
+    }
+    for (category, calls, total) in totals {
+        text.push_str(&format!(
+            "  {:<18} {:>4} {:<5} {:>10}\n",
+            category,
+            calls,
+            if *calls == 1 { "call" } else { "calls" },
+            format_duration(*total)
+        ));
+    }
+    text
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{:.1} ms", duration.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_report() {
+        let totals = [
+            ("config load", 1, Duration::from_micros(2_140)),
+            ("git", 12, Duration::from_millis(40)),
+        ];
+        assert_eq!(
+            format_report(&totals, Duration::from_millis(45)),
+            "Timings (total 45.0 ms):\n  config load           1 call      2.1 ms\n  git                  12 calls    40.0 ms\n"
+        );
+        assert_eq!(
+            format_report(&[], Duration::ZERO),
+            "Timings (total 0.0 ms):\n  nothing measured\n"
+        );
+    }
+}